@@ -0,0 +1,10 @@
+fn main() {
+    // The migrations are built into the binary, which has to be rebuilt when they change.
+    for dir in ["migrations", "migrations_postgres", "migrations_sqlite"] {
+        println!("cargo:rerun-if-changed={}", dir);
+    }
+
+    // The gRPC service is optional, so the protobuf definitions are only compiled when it is turned on.
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/content.proto").unwrap();
+}
@@ -0,0 +1 @@
+ALTER TABLE modules ADD COLUMN encrypted BOOLEAN DEFAULT FALSE NOT NULL;
@@ -0,0 +1,10 @@
+ALTER TABLE users ADD COLUMN email varchar(255) UNIQUE;
+
+CREATE TABLE IF NOT EXISTS data_requests (
+    uuid varchar(255) PRIMARY KEY,
+    email varchar(255) NOT NULL,
+    request_type varchar(255) NOT NULL,
+    actor varchar(255) NOT NULL,
+    records_affected BIGINT NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE modules ADD COLUMN compressed BOOLEAN DEFAULT FALSE NOT NULL;
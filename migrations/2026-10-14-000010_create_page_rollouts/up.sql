@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS page_rollouts (
+    uuid varchar(255) PRIMARY KEY,
+    page_uuid varchar(255) NOT NULL UNIQUE,
+    percentage INT NOT NULL,
+    content TEXT NOT NULL,
+    control_views BIGINT DEFAULT 0 NOT NULL,
+    canary_views BIGINT DEFAULT 0 NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (page_uuid) REFERENCES pages(uuid) ON DELETE CASCADE
+);
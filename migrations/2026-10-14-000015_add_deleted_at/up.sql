@@ -0,0 +1,2 @@
+ALTER TABLE pages ADD COLUMN deleted_at TIMESTAMP NULL;
+ALTER TABLE modules ADD COLUMN deleted_at TIMESTAMP NULL;
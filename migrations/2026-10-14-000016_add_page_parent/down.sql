@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE pages DROP FOREIGN KEY pages_parent_page;
+ALTER TABLE pages DROP COLUMN parent_page;
@@ -0,0 +1,2 @@
+ALTER TABLE pages ADD COLUMN parent_page varchar(255) NULL;
+ALTER TABLE pages ADD CONSTRAINT pages_parent_page FOREIGN KEY (parent_page) REFERENCES pages(uuid) ON DELETE SET NULL;
@@ -0,0 +1 @@
+ALTER TABLE modules ADD COLUMN order_index INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Everyone who could sign in before roles could do everything, so they stay admins.
+ALTER TABLE users ADD COLUMN role varchar(20) NOT NULL DEFAULT 'admin';
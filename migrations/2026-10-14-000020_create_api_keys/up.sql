@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS api_keys (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL,
+    key_hash varchar(255) NOT NULL UNIQUE,
+    user_uuid varchar(255) NOT NULL,
+    scopes TEXT NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid) ON DELETE CASCADE
+);
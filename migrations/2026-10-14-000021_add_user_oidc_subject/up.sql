@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN oidc_subject varchar(255) NULL UNIQUE;
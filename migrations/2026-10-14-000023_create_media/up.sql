@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS media (
+    uuid varchar(255) PRIMARY KEY,
+    filename varchar(255) NOT NULL,
+    mime_type varchar(255) NOT NULL,
+    size BIGINT NOT NULL,
+    checksum varchar(255) NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
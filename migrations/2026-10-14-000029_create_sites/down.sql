@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE media DROP FOREIGN KEY media_site_id;
+ALTER TABLE media DROP COLUMN site_id;
+ALTER TABLE modules DROP FOREIGN KEY modules_site_id;
+ALTER TABLE modules DROP COLUMN site_id;
+ALTER TABLE pages DROP FOREIGN KEY pages_site_id;
+DROP INDEX pages_site_url ON pages;
+ALTER TABLE pages DROP COLUMN site_id;
+DROP TABLE sites;
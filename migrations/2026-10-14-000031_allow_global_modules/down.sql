@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM modules WHERE page_uuid IS NULL;
+ALTER TABLE modules MODIFY page_uuid varchar(255) NOT NULL;
@@ -0,0 +1,2 @@
+-- Modules without a page are global, like a footer that every page shows.
+ALTER TABLE modules MODIFY page_uuid varchar(255) NULL;
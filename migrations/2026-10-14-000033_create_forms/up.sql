@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS forms (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL UNIQUE,
+    fields TEXT NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS form_submissions (
+    uuid varchar(255) PRIMARY KEY,
+    form_uuid varchar(255) NOT NULL,
+    data TEXT NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (form_uuid) REFERENCES forms(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX form_submissions_form ON form_submissions (form_uuid, time_created);
@@ -0,0 +1,2 @@
+-- who can see the page: `public`, `authenticated`, or `role:<name>` for users with at least that role.
+ALTER TABLE pages ADD COLUMN visibility varchar(40) NOT NULL DEFAULT 'public';
@@ -0,0 +1,4 @@
+-- a site's own quotas, in place of `APP_MAX_PAGES`, `APP_MAX_MODULES`, and `APP_MAX_MEDIA_BYTES` when set.
+ALTER TABLE sites ADD COLUMN max_pages BIGINT NULL;
+ALTER TABLE sites ADD COLUMN max_modules BIGINT NULL;
+ALTER TABLE sites ADD COLUMN max_media_bytes BIGINT NULL;
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE setting_changes DROP COLUMN site_id;
+DELETE FROM settings WHERE site_id <> 'default';
+ALTER TABLE settings DROP FOREIGN KEY settings_site_id;
+ALTER TABLE settings DROP PRIMARY KEY, ADD PRIMARY KEY (setting_key);
+ALTER TABLE settings DROP COLUMN site_id;
+DELETE FROM redirects WHERE site_id <> 'default';
+ALTER TABLE redirects DROP FOREIGN KEY redirects_site_id;
+DROP INDEX redirects_site_source ON redirects;
+CREATE UNIQUE INDEX source ON redirects (source);
+ALTER TABLE redirects DROP COLUMN site_id;
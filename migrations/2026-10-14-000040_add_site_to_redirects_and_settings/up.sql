@@ -0,0 +1,11 @@
+-- redirects and settings belong to a site like pages do, and everything there was before belongs to the default one.
+ALTER TABLE redirects ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL;
+ALTER TABLE redirects ADD CONSTRAINT redirects_site_id FOREIGN KEY (site_id) REFERENCES sites(uuid);
+ALTER TABLE redirects DROP INDEX source;
+CREATE UNIQUE INDEX redirects_site_source ON redirects (site_id, source);
+
+ALTER TABLE settings ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL;
+ALTER TABLE settings ADD CONSTRAINT settings_site_id FOREIGN KEY (site_id) REFERENCES sites(uuid);
+ALTER TABLE settings DROP PRIMARY KEY, ADD PRIMARY KEY (site_id, setting_key);
+
+ALTER TABLE setting_changes ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL;
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS signing_keys (
+    kid varchar(255) PRIMARY KEY,
+    secret varchar(255) NOT NULL,
+    active BOOLEAN DEFAULT TRUE NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    time_retired TIMESTAMP NULL
+);
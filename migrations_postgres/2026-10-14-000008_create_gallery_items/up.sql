@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS gallery_items (
+    uuid varchar(255) PRIMARY KEY,
+    module_uuid varchar(255) NOT NULL,
+    media_url varchar(2048) NOT NULL,
+    caption TEXT NULL,
+    position INT NOT NULL,
+    FOREIGN KEY (module_uuid) REFERENCES modules(uuid) ON DELETE CASCADE
+);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS search_documents (
+    page_uuid varchar(255) PRIMARY KEY,
+    page_url varchar(255) NOT NULL,
+    page_name varchar(500) NOT NULL,
+    page_title varchar(500) NOT NULL,
+    content TEXT NOT NULL,
+    time_indexed TIMESTAMP NOT NULL,
+    FOREIGN KEY (page_uuid) REFERENCES pages(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX search_documents_fulltext ON search_documents
+    USING GIN (to_tsvector('simple', page_title || ' ' || page_name || ' ' || content));
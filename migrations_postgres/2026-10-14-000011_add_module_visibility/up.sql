@@ -0,0 +1 @@
+ALTER TABLE modules ADD COLUMN visible_to TEXT NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE pages ADD COLUMN parent_page varchar(255) NULL REFERENCES pages(uuid) ON DELETE SET NULL;
+
+CREATE INDEX pages_parent_page ON pages (parent_page);
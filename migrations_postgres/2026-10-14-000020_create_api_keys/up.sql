@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS api_keys (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL,
+    key_hash varchar(255) NOT NULL UNIQUE,
+    user_uuid varchar(255) NOT NULL REFERENCES users(uuid) ON DELETE CASCADE,
+    scopes TEXT NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS webhooks (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL,
+    url TEXT NOT NULL,
+    secret varchar(255) NOT NULL,
+    events TEXT NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
@@ -0,0 +1,4 @@
+ALTER TABLE pages ADD COLUMN meta_description TEXT NULL;
+ALTER TABLE pages ADD COLUMN meta_keywords varchar(255) NULL;
+ALTER TABLE pages ADD COLUMN og_image varchar(1024) NULL;
+ALTER TABLE pages ADD COLUMN noindex BOOLEAN DEFAULT FALSE NOT NULL;
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS menus (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL UNIQUE,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS menu_items (
+    uuid varchar(255) PRIMARY KEY,
+    menu_uuid varchar(255) NOT NULL,
+    parent_item varchar(255) NULL,
+    title varchar(255) NOT NULL,
+    page_uuid varchar(255) NULL,
+    url varchar(1024) NULL,
+    position INT NOT NULL,
+    FOREIGN KEY (menu_uuid) REFERENCES menus(uuid) ON DELETE CASCADE,
+    FOREIGN KEY (parent_item) REFERENCES menu_items(uuid) ON DELETE CASCADE,
+    FOREIGN KEY (page_uuid) REFERENCES pages(uuid) ON DELETE CASCADE
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE page_categories;
+DROP TABLE page_tags;
+DROP TABLE categories;
+DROP TABLE tags;
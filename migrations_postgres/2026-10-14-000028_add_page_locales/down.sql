@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE pages DROP COLUMN translation_group;
+ALTER TABLE pages DROP COLUMN locale;
@@ -0,0 +1,2 @@
+ALTER TABLE pages ADD COLUMN locale varchar(35) NULL;
+ALTER TABLE pages ADD COLUMN translation_group varchar(255) NULL;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE media DROP COLUMN site_id;
+ALTER TABLE modules DROP COLUMN site_id;
+ALTER TABLE pages DROP COLUMN site_id;
+DROP TABLE sites;
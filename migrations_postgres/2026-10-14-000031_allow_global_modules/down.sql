@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM modules WHERE page_uuid IS NULL;
+ALTER TABLE modules ALTER COLUMN page_uuid SET NOT NULL;
@@ -0,0 +1,2 @@
+-- Modules without a page are global, like a footer that every page shows.
+ALTER TABLE modules ALTER COLUMN page_uuid DROP NOT NULL;
@@ -0,0 +1,12 @@
+ALTER TABLE users ADD COLUMN totp_secret varchar(255) NULL;
+ALTER TABLE users ADD COLUMN totp_enabled BOOLEAN DEFAULT FALSE NOT NULL;
+-- the last time step a code was used for, so that a code can't be used twice.
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT NULL;
+
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    uuid varchar(255) PRIMARY KEY,
+    user_uuid varchar(255) NOT NULL,
+    code_hash varchar(255) NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE modules DROP COLUMN version;
+ALTER TABLE pages DROP COLUMN version;
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE setting_changes DROP COLUMN site_id;
+DELETE FROM settings WHERE site_id <> 'default';
+ALTER TABLE settings DROP CONSTRAINT settings_pkey;
+ALTER TABLE settings ADD PRIMARY KEY (setting_key);
+ALTER TABLE settings DROP COLUMN site_id;
+DELETE FROM redirects WHERE site_id <> 'default';
+ALTER TABLE redirects DROP CONSTRAINT redirects_site_source;
+ALTER TABLE redirects ADD CONSTRAINT redirects_source_key UNIQUE (source);
+ALTER TABLE redirects DROP COLUMN site_id;
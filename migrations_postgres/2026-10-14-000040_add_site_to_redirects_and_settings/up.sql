@@ -0,0 +1,10 @@
+-- redirects and settings belong to a site like pages do, and everything there was before belongs to the default one.
+ALTER TABLE redirects ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL REFERENCES sites(uuid);
+ALTER TABLE redirects DROP CONSTRAINT redirects_source_key;
+ALTER TABLE redirects ADD CONSTRAINT redirects_site_source UNIQUE (site_id, source);
+
+ALTER TABLE settings ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL REFERENCES sites(uuid);
+ALTER TABLE settings DROP CONSTRAINT settings_pkey;
+ALTER TABLE settings ADD PRIMARY KEY (site_id, setting_key);
+
+ALTER TABLE setting_changes ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS settings (
+    setting_key varchar(255) PRIMARY KEY,
+    setting_value TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS setting_changes (
+    uuid varchar(255) PRIMARY KEY,
+    setting_key varchar(255) NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    actor varchar(255) NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE data_requests;
+DROP INDEX users_email;
+ALTER TABLE users DROP COLUMN email;
@@ -0,0 +1,12 @@
+-- SQLite can't add a UNIQUE column, so the index is created on its own.
+ALTER TABLE users ADD COLUMN email varchar(255);
+CREATE UNIQUE INDEX users_email ON users (email);
+
+CREATE TABLE IF NOT EXISTS data_requests (
+    uuid varchar(255) PRIMARY KEY,
+    email varchar(255) NOT NULL,
+    request_type varchar(255) NOT NULL,
+    actor varchar(255) NOT NULL,
+    records_affected BIGINT NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
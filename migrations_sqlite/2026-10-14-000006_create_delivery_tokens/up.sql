@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS delivery_tokens (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL,
+    token_hash varchar(255) NOT NULL UNIQUE,
+    content_types TEXT NULL,
+    locales TEXT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
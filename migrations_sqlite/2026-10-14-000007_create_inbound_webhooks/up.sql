@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS inbound_webhooks (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL,
+    secret varchar(255) NOT NULL,
+    target varchar(255) NOT NULL,
+    transform TEXT NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
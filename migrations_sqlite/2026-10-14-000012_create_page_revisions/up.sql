@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS page_revisions (
+    uuid varchar(255) PRIMARY KEY,
+    page_uuid varchar(255) NOT NULL,
+    content TEXT NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (page_uuid) REFERENCES pages(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX page_revisions_page_uuid ON page_revisions (page_uuid);
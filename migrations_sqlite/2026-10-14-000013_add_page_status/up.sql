@@ -0,0 +1 @@
+ALTER TABLE pages ADD COLUMN status varchar(20) NOT NULL DEFAULT 'published';
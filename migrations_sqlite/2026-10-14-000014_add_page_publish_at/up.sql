@@ -0,0 +1 @@
+ALTER TABLE pages ADD COLUMN publish_at TIMESTAMP NULL;
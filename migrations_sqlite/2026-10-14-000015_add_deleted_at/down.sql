@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE modules DROP COLUMN deleted_at;
+ALTER TABLE pages DROP COLUMN deleted_at;
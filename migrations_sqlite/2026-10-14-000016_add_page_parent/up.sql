@@ -0,0 +1,4 @@
+-- SQLite can't drop a column that is part of a foreign key, so the parent is only checked by the application here.
+ALTER TABLE pages ADD COLUMN parent_page varchar(255) NULL;
+
+CREATE INDEX pages_parent_page ON pages (parent_page);
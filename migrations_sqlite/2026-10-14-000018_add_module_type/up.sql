@@ -0,0 +1 @@
+ALTER TABLE modules ADD COLUMN module_type varchar(20) NOT NULL DEFAULT 'text';
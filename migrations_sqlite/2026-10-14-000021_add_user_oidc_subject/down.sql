@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX users_oidc_subject;
+ALTER TABLE users DROP COLUMN oidc_subject;
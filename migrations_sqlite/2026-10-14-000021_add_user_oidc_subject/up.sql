@@ -0,0 +1,3 @@
+-- SQLite can't add a UNIQUE column, so the index is created on its own.
+ALTER TABLE users ADD COLUMN oidc_subject varchar(255);
+CREATE UNIQUE INDEX users_oidc_subject ON users (oidc_subject);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE pages DROP COLUMN noindex;
+ALTER TABLE pages DROP COLUMN og_image;
+ALTER TABLE pages DROP COLUMN meta_keywords;
+ALTER TABLE pages DROP COLUMN meta_description;
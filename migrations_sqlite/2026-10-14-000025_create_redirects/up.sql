@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS redirects (
+    uuid varchar(255) PRIMARY KEY,
+    source varchar(255) NOT NULL UNIQUE,
+    target varchar(1024) NOT NULL,
+    status_code INTEGER DEFAULT 301 NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
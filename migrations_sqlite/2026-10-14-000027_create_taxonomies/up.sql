@@ -0,0 +1,29 @@
+CREATE TABLE IF NOT EXISTS tags (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL,
+    slug varchar(255) NOT NULL UNIQUE,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS categories (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL,
+    slug varchar(255) NOT NULL UNIQUE,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS page_tags (
+    page_uuid varchar(255) NOT NULL,
+    tag_uuid varchar(255) NOT NULL,
+    PRIMARY KEY (page_uuid, tag_uuid),
+    FOREIGN KEY (page_uuid) REFERENCES pages(uuid) ON DELETE CASCADE,
+    FOREIGN KEY (tag_uuid) REFERENCES tags(uuid) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS page_categories (
+    page_uuid varchar(255) NOT NULL,
+    category_uuid varchar(255) NOT NULL,
+    PRIMARY KEY (page_uuid, category_uuid),
+    FOREIGN KEY (page_uuid) REFERENCES pages(uuid) ON DELETE CASCADE,
+    FOREIGN KEY (category_uuid) REFERENCES categories(uuid) ON DELETE CASCADE
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE media DROP COLUMN site_id;
+ALTER TABLE modules DROP COLUMN site_id;
+DROP INDEX pages_site_url;
+ALTER TABLE pages DROP COLUMN site_id;
+DROP TABLE sites;
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS sites (
+    uuid varchar(255) PRIMARY KEY,
+    name varchar(255) NOT NULL,
+    hostname varchar(255) NULL UNIQUE,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+-- Everything there was before there were sites belongs to this one, which also answers hosts no other site has.
+INSERT OR IGNORE INTO sites (uuid, name) VALUES ('default', 'Default');
+
+-- SQLite can't drop a column that is part of a foreign key, so the sites are only checked by the application here.
+ALTER TABLE pages ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL;
+CREATE INDEX pages_site_url ON pages (site_id, page_url);
+
+ALTER TABLE modules ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL;
+
+ALTER TABLE media ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL;
@@ -0,0 +1,29 @@
+-- This file should undo anything in `up.sql`
+-- SQLite can't change a column, so the table is made again. Dropping it removes the gallery items of its modules
+-- along with it, so they are kept aside until the new table is in its place.
+CREATE TEMPORARY TABLE gallery_items_kept AS SELECT * FROM gallery_items;
+
+CREATE TABLE modules_global (
+    uuid varchar(255) PRIMARY KEY,
+    page_uuid VARCHAR(255) NOT NULL,
+    category_uuid VARCHAR(255),
+    title varchar(255) NOT NULL,
+    content TEXT NOT NULL,
+    encrypted BOOLEAN DEFAULT FALSE NOT NULL,
+    compressed BOOLEAN DEFAULT FALSE NOT NULL,
+    visible_to TEXT NULL,
+    deleted_at TIMESTAMP NULL,
+    order_index INTEGER NOT NULL DEFAULT 0,
+    module_type varchar(20) NOT NULL DEFAULT 'text',
+    site_id varchar(255) DEFAULT 'default' NOT NULL,
+    FOREIGN KEY (page_uuid) REFERENCES pages(uuid) ON DELETE CASCADE,
+    FOREIGN KEY (category_uuid) REFERENCES module_category(uuid) ON DELETE CASCADE
+);
+
+INSERT INTO modules_global SELECT uuid, page_uuid, category_uuid, title, content, encrypted, compressed, visible_to, deleted_at, order_index, module_type, site_id FROM modules WHERE page_uuid IS NOT NULL;
+DROP TABLE modules;
+ALTER TABLE modules_global RENAME TO modules;
+
+DELETE FROM gallery_items;
+INSERT INTO gallery_items SELECT * FROM gallery_items_kept WHERE module_uuid IN (SELECT uuid FROM modules);
+DROP TABLE gallery_items_kept;
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS comments (
+    uuid varchar(255) PRIMARY KEY,
+    page_uuid varchar(255) NOT NULL,
+    author_name varchar(255) NOT NULL,
+    author_email varchar(255) NULL,
+    body TEXT NOT NULL,
+    status varchar(255) NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    FOREIGN KEY (page_uuid) REFERENCES pages(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX comments_page_status ON comments (page_uuid, status);
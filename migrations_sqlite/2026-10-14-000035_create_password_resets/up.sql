@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS password_resets (
+    uuid varchar(255) PRIMARY KEY,
+    user_uuid varchar(255) NOT NULL,
+    token_hash varchar(255) NOT NULL UNIQUE,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    time_expires TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+-- counted up on every change, so that an update made from an older read of the row can be refused.
+ALTER TABLE pages ADD COLUMN version INTEGER DEFAULT 1 NOT NULL;
+ALTER TABLE modules ADD COLUMN version INTEGER DEFAULT 1 NOT NULL;
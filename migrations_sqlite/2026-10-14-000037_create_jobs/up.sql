@@ -0,0 +1,16 @@
+-- work that is done after the request that asked for it, like webhook deliveries and mails, kept here so that it is
+-- retried until it succeeds and isn't lost when the server restarts.
+CREATE TABLE IF NOT EXISTS jobs (
+    uuid varchar(255) PRIMARY KEY,
+    kind varchar(255) NOT NULL,
+    payload TEXT NOT NULL,
+    status varchar(255) NOT NULL,
+    attempts INTEGER DEFAULT 0 NOT NULL,
+    max_attempts INTEGER NOT NULL,
+    last_error TEXT,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    time_updated TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    -- when a pending job is due, and when a running one is given up on as lost, like by a server that crashed.
+    run_at TIMESTAMP NOT NULL
+);
+CREATE INDEX jobs_status_run_at ON jobs (status, run_at);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE sites DROP COLUMN max_media_bytes;
+ALTER TABLE sites DROP COLUMN max_modules;
+ALTER TABLE sites DROP COLUMN max_pages;
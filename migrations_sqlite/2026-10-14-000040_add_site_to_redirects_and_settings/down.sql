@@ -0,0 +1,23 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE setting_changes DROP COLUMN site_id;
+
+CREATE TABLE settings_shared (
+    setting_key varchar(255) PRIMARY KEY,
+    setting_value TEXT NOT NULL
+);
+INSERT INTO settings_shared (setting_key, setting_value)
+    SELECT setting_key, setting_value FROM settings WHERE site_id = 'default';
+DROP TABLE settings;
+ALTER TABLE settings_shared RENAME TO settings;
+
+CREATE TABLE redirects_shared (
+    uuid varchar(255) PRIMARY KEY,
+    source varchar(255) NOT NULL UNIQUE,
+    target varchar(1024) NOT NULL,
+    status_code INTEGER DEFAULT 301 NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+INSERT INTO redirects_shared (uuid, source, target, status_code, time_created)
+    SELECT uuid, source, target, status_code, time_created FROM redirects WHERE site_id = 'default';
+DROP TABLE redirects;
+ALTER TABLE redirects_shared RENAME TO redirects;
@@ -0,0 +1,27 @@
+-- redirects and settings belong to a site like pages do, and everything there was before belongs to the default one.
+-- SQLite can't change a table's unique columns or primary key, so both tables are made again.
+CREATE TABLE redirects_by_site (
+    uuid varchar(255) PRIMARY KEY,
+    source varchar(255) NOT NULL,
+    target varchar(1024) NOT NULL,
+    status_code INTEGER DEFAULT 301 NOT NULL,
+    time_created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    site_id varchar(255) DEFAULT 'default' NOT NULL,
+    UNIQUE (site_id, source)
+);
+INSERT INTO redirects_by_site (uuid, source, target, status_code, time_created)
+    SELECT uuid, source, target, status_code, time_created FROM redirects;
+DROP TABLE redirects;
+ALTER TABLE redirects_by_site RENAME TO redirects;
+
+CREATE TABLE settings_by_site (
+    setting_key varchar(255) NOT NULL,
+    setting_value TEXT NOT NULL,
+    site_id varchar(255) DEFAULT 'default' NOT NULL,
+    PRIMARY KEY (site_id, setting_key)
+);
+INSERT INTO settings_by_site (setting_key, setting_value) SELECT setting_key, setting_value FROM settings;
+DROP TABLE settings;
+ALTER TABLE settings_by_site RENAME TO settings;
+
+ALTER TABLE setting_changes ADD COLUMN site_id varchar(255) DEFAULT 'default' NOT NULL;
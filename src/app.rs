@@ -0,0 +1,202 @@
+use std::sync::Mutex;
+
+use actix_web::dev::Service;
+use actix_web::web;
+use handlebars::Handlebars;
+
+use crate::controllers;
+use crate::middleware::auth_middleware::RequireLogin;
+use crate::models::config_models::LocalConfig;
+use crate::models::{Databases, DbPool};
+use crate::routers::delivery_routers::DeliveryRouter;
+use crate::routers::live_routers::{EventRouter, SocketRouter};
+use crate::routers::oidc_routers::OidcRouter;
+use crate::routers::render_routers::RenderRouter;
+use crate::routers::search_routers::SearchRouter;
+use crate::routers::v1_routers::V1;
+use crate::routers::{ApiVersion, Router};
+use crate::services::cache_service::CacheBackend;
+use crate::services::feed_service::Feed;
+use crate::services::graphql_service::RadicalSchema;
+use crate::services::job_service::JobQueue;
+use crate::services::live_service::{EventStreams, LiveHub};
+use crate::services::mail_service::MailQueue;
+use crate::services::media_store_service::MediaStore;
+use crate::services::metrics_service::Metrics;
+use crate::services::migration_service::SchemaState;
+use crate::services::page_cache_service::PageCache;
+use crate::services::preview_service::PreviewHub;
+use crate::services::publish_service::PublishQueue;
+use crate::services::rate_limit_service::RateLimiter;
+use crate::services::redirect_service::RedirectTable;
+use crate::services::response_cache_service::ResponseCache;
+use crate::services::route_service::RouteTable;
+use crate::services::search_service::{SearchIndex, SearchIndexer};
+use crate::services::session_service::{self, SessionStore};
+use crate::services::settings_service::SettingsCache;
+use crate::services::site_service::SiteTable;
+use crate::services::sitemap_service::Sitemap;
+use crate::services::theme_service::Themes;
+use crate::services::warmup_service::Readiness;
+use crate::services::webhook_service::WebhookQueue;
+use crate::services::api_version_service;
+use crate::services::body_limit_service;
+use crate::Plugins;
+
+/// Everything the handlers are given, made once and shared by every worker of the server.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: DbPool,
+    pub conf: LocalConfig,
+    pub databases: web::Data<Databases>,
+    pub handlebars: web::Data<Mutex<Handlebars<'static>>>,
+    pub themes: web::Data<Themes>,
+    pub cache: web::Data<dyn CacheBackend>,
+    pub settings_cache: web::Data<SettingsCache>,
+    pub route_table: web::Data<RouteTable>,
+    pub redirect_table: web::Data<RedirectTable>,
+    pub site_table: web::Data<SiteTable>,
+    pub page_cache: web::Data<PageCache>,
+    pub response_cache: web::Data<ResponseCache>,
+    pub sitemap: web::Data<Sitemap>,
+    pub feed: web::Data<Feed>,
+    pub readiness: web::Data<Readiness>,
+    pub metrics: web::Data<Metrics>,
+    pub rate_limiter: web::Data<RateLimiter>,
+    pub session_store: web::Data<dyn SessionStore>,
+    pub media_store: web::Data<dyn MediaStore>,
+    pub search_index: web::Data<dyn SearchIndex>,
+    pub search_indexer: web::Data<SearchIndexer>,
+    pub publish_queue: web::Data<PublishQueue>,
+    pub job_queue: web::Data<JobQueue>,
+    pub webhook_queue: web::Data<WebhookQueue>,
+    pub mail_queue: web::Data<MailQueue>,
+    pub preview_hub: web::Data<PreviewHub>,
+    pub live_hub: web::Data<LiveHub>,
+    pub event_streams: web::Data<EventStreams>,
+    pub plugins: web::Data<Plugins>,
+    pub schema_state: web::Data<SchemaState>,
+    pub graphql_schema: web::Data<RadicalSchema>,
+}
+
+impl AppState {
+    /// Every route but the pages, along with everything their handlers are given. The middleware around all of them,
+    /// and the pages, are added by `app!`.
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        let conf = &self.conf;
+        // the routes of plugins come after the built-in ones, so they can't take their place.
+        let api = |path: &str| {
+            self.plugins
+                .routes(V1::routes(web::scope(path)))
+                .wrap(RequireLogin)
+                .wrap(session_service::middleware(conf, path))
+        };
+        let v1_mount = api_version_service::mount(V1::NAME);
+
+        cfg.service(api(&v1_mount));
+        // until they are turned off, the routes from before there were versions are v1's, marked as deprecated.
+        if conf.legacy_api {
+            cfg.service(api(api_version_service::LEGACY_PREFIX).wrap_fn(|req, srv| {
+                let res = srv.call(req);
+                async move {
+                    let mut res = res.await?;
+                    api_version_service::deprecate(&mut res, V1::NAME);
+                    Ok(res)
+                }
+            }));
+        }
+
+        cfg
+            // shares the session cookie of `/api/v1`, which the callback logs the user in with.
+            .service(OidcRouter::new().wrap(session_service::middleware(conf, &v1_mount)))
+            .service(DeliveryRouter::new())
+            .service(RenderRouter::new())
+            .service(SearchRouter::new())
+            .service(SocketRouter::new())
+            .service(EventRouter::new())
+            .route("/metrics", web::get().to(controllers::system_controllers::get_metrics))
+            .route("/healthz", web::get().to(controllers::system_controllers::get_health))
+            .route("/readyz", web::get().to(controllers::system_controllers::get_readiness))
+            .route("/graphql", web::post().to(controllers::graphql_controllers::graphql))
+            .route("/openapi.json", web::get().to(controllers::openapi_controllers::get_openapi))
+            .route("/docs", web::get().to(controllers::openapi_controllers::get_swagger_ui))
+            .route("/assets/{path:.*}", web::get().to(controllers::static_controllers::get_asset))
+            .route("/static/{path:.*}", web::get().to(controllers::static_controllers::get_static))
+            .route("/sitemap.xml", web::get().to(controllers::sitemap_controllers::get_sitemap))
+            .route("/feed.xml", web::get().to(controllers::feed_controllers::get_feed))
+            .data(self.pool.clone())
+            .data(conf.clone())
+            .app_data(body_limit_service::json(conf.json_max_size))
+            .app_data(body_limit_service::payload(conf))
+            .app_data(self.handlebars.clone())
+            .app_data(self.settings_cache.clone())
+            .app_data(self.route_table.clone())
+            .app_data(self.redirect_table.clone())
+            .app_data(self.site_table.clone())
+            .app_data(self.page_cache.clone())
+            .app_data(self.sitemap.clone())
+            .app_data(self.feed.clone())
+            .app_data(self.readiness.clone())
+            .app_data(self.metrics.clone())
+            .app_data(self.cache.clone())
+            .app_data(self.session_store.clone())
+            .app_data(self.media_store.clone())
+            .app_data(self.search_index.clone())
+            .app_data(self.databases.clone())
+            .app_data(self.publish_queue.clone())
+            .app_data(self.job_queue.clone())
+            .app_data(self.webhook_queue.clone())
+            .app_data(self.mail_queue.clone())
+            .app_data(self.themes.clone())
+            .app_data(self.preview_hub.clone())
+            .app_data(self.live_hub.clone())
+            .app_data(self.event_streams.clone())
+            .app_data(self.plugins.clone())
+            .app_data(self.search_indexer.clone())
+            .app_data(self.schema_state.clone())
+            .app_data(self.graphql_schema.clone());
+    }
+}
+
+/// The app each worker of the server runs from an `AppState`, which `test_utils` tests against too. It is a macro, as the
+/// type of an `App` with its middleware can't be written out.
+macro_rules! app {
+    ($state:expr) => {{
+        use actix_web::dev::Service as _;
+
+        let state: &$crate::app::AppState = $state;
+        let schema = state.schema_state.clone();
+
+        actix_web::App::new()
+            // everything a request does is scoped to the site of its host.
+            .wrap($crate::middleware::replica_middleware::Replicas)
+            .wrap($crate::middleware::site_middleware::Sites)
+            .wrap($crate::middleware::response_cache_middleware::ResponseCaching::new(state.response_cache.clone()))
+            .wrap_fn(move |req, srv| match schema.refuses(&req) {
+                true => futures::future::Either::Left(futures::future::ok(
+                    req.error_response($crate::services::errors_service::CustomHttpError::SchemaBehind),
+                )),
+                false => futures::future::Either::Right(srv.call(req)),
+            })
+            .wrap($crate::middleware::rate_limit_middleware::RateLimit::new(state.rate_limiter.clone()))
+            .wrap($crate::middleware::trace_middleware::RequestSpan)
+            .wrap($crate::middleware::request_id_middleware::RequestId)
+            .wrap($crate::middleware::security_headers_middleware::SecurityHeaders::new(&state.conf))
+            .wrap($crate::services::cors_service::policy(&state.conf))
+            .wrap($crate::middleware::metrics_middleware::RequestMetrics::new(state.metrics.clone()))
+            // outside of everything else, so that what is cached and measured is the plain response.
+            .wrap($crate::middleware::compression_middleware::Compression::new(&state.conf))
+            .wrap(actix_web::middleware::Compress::default())
+            .configure(|cfg| state.configure(cfg))
+            // paths without a page are checked for a redirect before they get the 404 page.
+            .default_service(
+                actix_web::web::resource("")
+                    .default_service(actix_web::web::get().to($crate::controllers::page_controllers::display_page))
+                    .wrap($crate::middleware::redirect_middleware::Redirects)
+                    .wrap($crate::middleware::error_page_middleware::ErrorPages)
+                    // only ever read, for the pages only some users can see (see `APP_PAGE_SESSIONS`).
+                    .wrap($crate::services::session_service::middleware(&state.conf, "/"))
+                    .wrap($crate::middleware::security_headers_middleware::SecurityHeaders::pages(&state.conf)),
+            )
+    }};
+}
@@ -0,0 +1,24 @@
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::services::backup_service::Backups;
+
+/// Writes a backup of the content now, the same as `APP_BACKUP_SCHEDULE` does, and prunes the old ones.
+pub fn run() {
+    let conf = LocalConfig::load_or_exit();
+
+    let backups = match Backups::new(&conf) {
+        Ok(backups) => backups,
+        Err(e) => {
+            println!("Invalid APP_BACKUP_STORE: {}", e);
+            return;
+        }
+    };
+
+    let connection = establish_connection(conf)
+        .expect("Could not connect to the database.");
+
+    match backups.backup(&connection) {
+        Ok(key) => println!("Backed up the content to {}.", key),
+        Err(e) => println!("Failed to back up: {}", e),
+    };
+}
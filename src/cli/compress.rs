@@ -0,0 +1,27 @@
+
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::models::module_models::Module;
+use crate::services::compression_service;
+
+/// Compresses the content of existing modules over `APP_COMPRESSION_THRESHOLD`.
+/// New and updated modules are compressed automatically, so this only has to be run once after turning compression on.
+pub fn run() {
+    let conf = LocalConfig::load_or_exit();
+
+    let threshold = match compression_service::threshold() {
+        Some(threshold) => threshold,
+        None => {
+            println!("APP_COMPRESSION_THRESHOLD is not set, so there is nothing to compress.");
+            return;
+        }
+    };
+
+    let connection = establish_connection(conf)
+        .expect("Could not connect to the database.");
+
+    match Module::compress_existing(threshold, &connection) {
+        Ok(compressed) => println!("Compressed {} modules.", compressed),
+        Err(e) => println!("Failed to compress modules: {}", e),
+    };
+}
@@ -0,0 +1,35 @@
+use super::init::create_admin;
+use super::{option, prompt_password};
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+
+/// Creates an admin, or makes an existing user one, without the server running. The username is the email unless
+/// `--username` says otherwise, and the password is asked for unless `--password` is given.
+pub fn run() {
+    let email = match option("email") {
+        Some(email) => email,
+        None => {
+            println!("Usage: radical create-admin --email <email> [--username <username>] [--password <password>]");
+            return;
+        }
+    };
+    let username = option("username").unwrap_or_else(|| email.clone());
+    let password = option("password").unwrap_or_else(|| loop {
+        let password = prompt_password("Password");
+
+        if password == prompt_password("Confirm password") {
+            break password;
+        }
+        println!("Passwords do not match.");
+    });
+
+    let conf = LocalConfig::load_or_exit();
+
+    let connection = establish_connection(conf)
+        .expect("Could not connect to the database.");
+
+    match create_admin(username.clone(), password, Some(email), &connection) {
+        Ok(_) => println!("{} is now an admin.", username),
+        Err(e) => println!("Failed to create the admin: {}", e),
+    };
+}
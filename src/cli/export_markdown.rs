@@ -0,0 +1,40 @@
+use std::path::Path;
+
+
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::services::markdown_export_service::{self, ExportFormat};
+use crate::services::theme_service;
+
+/// Exports every page as the source of a Hugo, Jekyll, or Zola site, so content can be mirrored or moved away.
+pub fn run(dir: Option<String>, format: Option<String>) {
+    let (dir, format) = match (dir, format.as_deref().map(ExportFormat::parse)) {
+        (Some(dir), Some(Some(format))) => (dir, format),
+        (Some(dir), None) => (dir, ExportFormat::Hugo),
+        _ => {
+            println!("Usage: radical export-markdown <directory> [hugo|jekyll|zola]");
+            return;
+        }
+    };
+
+    let conf = LocalConfig::load_or_exit();
+
+    let connection = establish_connection(conf.clone())
+        .expect("Could not connect to the database.");
+    let assets = theme_service::assets_dir(theme_service::configured(&conf, &connection).as_deref());
+
+    match markdown_export_service::export(format, Path::new(&dir), &assets, &connection) {
+        Ok(report) => {
+            for file in &report.written {
+                println!("Wrote {}", file.display());
+            }
+            println!(
+                "Exported {} pages and {} assets to {}.",
+                report.written.len(),
+                report.assets_copied,
+                dir
+            );
+        }
+        Err(e) => println!("Failed to export: {}", e.descriptor()),
+    };
+}
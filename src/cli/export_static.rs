@@ -0,0 +1,40 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use actix_web::web;
+use handlebars::Handlebars;
+
+use crate::helpers;
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::services::media_store_service;
+use crate::services::static_export_service::{self, DEFAULT_DIR};
+use crate::services::theme_service::{self, Themes};
+
+/// Renders the published site into a directory that any static host or CDN can serve, in place of the server.
+pub fn run(out: Option<String>) {
+    let conf = LocalConfig::load_or_exit();
+    let out = out.or_else(|| conf.static_export_dir.clone()).unwrap_or_else(|| String::from(DEFAULT_DIR));
+
+    let connection = establish_connection(conf.clone())
+        .expect("Could not connect to the database.");
+    let store = media_store_service::store(&conf).unwrap_or_else(|e| panic!("Invalid APP_MEDIA_STORE: {}", e));
+
+    let handlebars = web::Data::new(Mutex::new(Handlebars::new()));
+    let themes = Themes::new(theme_service::configured(&conf, &connection));
+    themes.reload(&handlebars).unwrap_or_else(|e| panic!("Invalid templates: {:?}", e));
+    helpers::default::register_helpers(handlebars.clone());
+
+    match static_export_service::export(Path::new(&out), &conf, &connection, &handlebars, &themes, &*store) {
+        Ok(export) => {
+            println!(
+                "Exported {} pages, {} assets, {} files, and {} uploads to {}.",
+                export.pages, export.assets, export.files, export.media, out
+            );
+            if !export.sitemap {
+                println!("Set APP_SITE_URL to export sitemap.xml and feed.xml too.");
+            }
+        }
+        Err(e) => println!("Failed to export: {}", e),
+    };
+}
@@ -0,0 +1,35 @@
+
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::services::{gc_service, media_store_service};
+
+/// Reports orphaned data, unreferenced media, and expired tokens. They are only removed when `--clean` is passed.
+pub fn run(clean: bool) {
+    let conf = LocalConfig::load_or_exit();
+    let store = media_store_service::store(&conf).expect("Invalid APP_MEDIA_STORE.");
+
+    let connection = establish_connection(conf)
+        .expect("Could not connect to the database.");
+
+    let report = match clean {
+        true => gc_service::clean(&connection, &*store),
+        false => gc_service::report(&connection),
+    };
+
+    match report {
+        Ok(report) => {
+            println!("Orphaned modules: {:?}", report.orphaned_modules);
+            println!("Orphaned categories: {:?}", report.orphaned_categories);
+            println!("Expired tokens: {:?}", report.expired_tokens);
+            println!("Unreferenced media: {:?}", report.unreferenced_media);
+            println!("Expired password resets: {:?}", report.expired_password_resets);
+
+            match report.cleaned {
+                true => println!("Cleaned up everything above."),
+                false if !report.is_empty() => println!("Run again with --clean to remove everything above."),
+                false => {}
+            }
+        }
+        Err(e) => println!("Failed to collect garbage: {}", e),
+    };
+}
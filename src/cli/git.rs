@@ -0,0 +1,35 @@
+use std::path::Path;
+
+
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::services::git_service;
+use crate::services::plugin_service::Plugins;
+
+/// Exports every page to `APP_GIT_REPO`, or imports the pages in it back into the database.
+pub fn run(action: Option<String>, plugins: &Plugins) {
+    let conf = LocalConfig::load_or_exit();
+
+    let repo = match conf.git_repo.clone() {
+        Some(repo) => repo,
+        None => {
+            println!("APP_GIT_REPO is not set.");
+            return;
+        }
+    };
+
+    let connection = establish_connection(conf.clone())
+        .expect("Could not connect to the database.");
+
+    match action.as_deref() {
+        Some("export") => match git_service::export_all(Path::new(&repo), conf.git_push, &connection) {
+            Ok(exported) => println!("Exported {} pages to {}.", exported, repo),
+            Err(e) => println!("Failed to export: {}", e),
+        },
+        Some("import") => match git_service::import_all(Path::new(&repo), plugins, &connection) {
+            Ok(imported) => println!("Imported {} pages from {}.", imported, repo),
+            Err(e) => println!("Failed to import, nothing was changed: {}", e),
+        },
+        _ => println!("Usage: radical git <export|import>"),
+    }
+}
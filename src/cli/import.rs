@@ -0,0 +1,42 @@
+use std::fs;
+
+
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::services::content_import_service::{self, ImportSource};
+use crate::services::plugin_service::Plugins;
+
+/// Imports a Contentful space export or a Strapi export. `--dry-run` only prints the mapping report.
+pub fn run(source: Option<String>, file: Option<String>, dry_run: bool, plugins: &Plugins) {
+    let (source, file) = match (source.as_deref().and_then(ImportSource::parse), file) {
+        (Some(source), Some(file)) => (source, file),
+        _ => {
+            println!("Usage: radical import <contentful|strapi> <file> [--dry-run]");
+            return;
+        }
+    };
+
+    let export = match fs::read_to_string(&file) {
+        Ok(export) => export,
+        Err(e) => {
+            println!("Failed to read {}: {}", file, e);
+            return;
+        }
+    };
+
+    let conf = LocalConfig::load_or_exit();
+
+    let connection = establish_connection(conf.clone())
+        .expect("Could not connect to the database.");
+
+    match content_import_service::import(source, &export, dry_run, &conf, plugins, &connection) {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            match dry_run {
+                true => println!("Dry run, nothing was imported. {} pages would be created.", report.pages.len()),
+                false => println!("Imported {} pages, skipped {} entries.", report.pages.len(), report.skipped.len()),
+            }
+        }
+        Err(e) => println!("Failed to import {}: {}", file, e.descriptor()),
+    };
+}
@@ -0,0 +1,36 @@
+use std::path::Path;
+
+
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::services::markdown_import_service;
+use crate::services::plugin_service::Plugins;
+
+/// Imports a directory of Markdown files, like the content directory of a Hugo, Jekyll, or Zola site.
+pub fn run(dir: Option<String>, plugins: &Plugins) {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => {
+            println!("Usage: radical import-markdown <directory>");
+            return;
+        }
+    };
+
+    let conf = LocalConfig::load_or_exit();
+
+    let connection = establish_connection(conf.clone())
+        .expect("Could not connect to the database.");
+
+    match markdown_import_service::import_dir(Path::new(&dir), &conf, plugins, &connection) {
+        Ok(report) => {
+            for url in &report.imported {
+                println!("Imported {}", url);
+            }
+            for (path, reason) in &report.skipped {
+                println!("Skipped {}: {}", path.display(), reason);
+            }
+            println!("Imported {} pages, skipped {} files.", report.imported.len(), report.skipped.len());
+        }
+        Err(e) => println!("Failed to read {}: {}", dir, e),
+    };
+}
@@ -0,0 +1,39 @@
+use std::fs;
+
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::services::plugin_service::Plugins;
+use crate::services::wordpress_import_service;
+
+/// Imports the posts and pages of a WordPress export, made with Tools > Export in the WordPress admin.
+pub fn run(file: Option<String>, plugins: &Plugins) {
+    let file = match file {
+        Some(file) => file,
+        None => {
+            println!("Usage: radical import-wordpress <export.xml>");
+            return;
+        }
+    };
+
+    let export = match fs::read_to_string(&file).map_err(|e| e.to_string()).and_then(|xml| wordpress_import_service::parse(&xml)) {
+        Ok(export) => export,
+        Err(e) => {
+            println!("Failed to read {}: {}", file, e);
+            return;
+        }
+    };
+
+    let conf = LocalConfig::load_or_exit();
+
+    let connection = establish_connection(conf.clone())
+        .expect("Could not connect to the database.");
+
+    let report = wordpress_import_service::import(&export, &conf, plugins, &connection);
+    for url in &report.imported {
+        println!("Imported {}", url);
+    }
+    for (item, reason) in &report.skipped {
+        println!("Skipped {}: {}", item, reason);
+    }
+    println!("Imported {} pages, skipped {} items.", report.imported.len(), report.skipped.len());
+}
@@ -0,0 +1,290 @@
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::{confirm, prompt, prompt_password};
+use crate::models::config_models::LocalConfig;
+use crate::models::user_models::{MutUser, User, UserRole};
+use crate::models::{establish_connection, format_connection_string, DbConnection, Model};
+use crate::services::auth_service::encrypt_password;
+use crate::services::migration_service;
+
+const CONFIG_PATH: &str = ".env";
+
+/// Interactively walks through the first time setup of Radical.
+/// Asks for the database details until a connection can be made, runs the migrations,
+/// creates the admin user, and finally writes everything to the `.env` file.
+pub fn run() {
+    println!("Welcome to Radical! Let's get you set up.");
+
+    if Path::new(CONFIG_PATH).exists()
+        && !confirm(&format!("{} already exists. Overwrite it?", CONFIG_PATH), false)
+    {
+        println!("Setup cancelled.");
+        return;
+    }
+
+    let (conf, connection) = loop {
+        let conf = ask_config();
+
+        println!("Connecting to the database...");
+        match establish_connection(conf.clone()) {
+            Ok(connection) => break (conf, connection),
+            Err(e) => println!("Could not connect to the database: {}. Please try again.", e),
+        }
+    };
+
+    match migration_service::run(&connection) {
+        Ok(_) => println!("Ran migrations."),
+        Err(e) => {
+            println!("Failed to run migrations: {}", e);
+            return;
+        }
+    };
+
+    let username = prompt("Admin username", Some("root"));
+    let password = loop {
+        let password = prompt_password("Admin password");
+
+        if password == prompt_password("Confirm admin password") {
+            break password;
+        }
+        println!("Passwords do not match.");
+    };
+
+    if let Err(e) = create_admin(username, password, None, &connection) {
+        println!("Failed to create the admin user: {}", e);
+        return;
+    }
+    println!("Created the admin user.");
+
+    match fs::write(CONFIG_PATH, format_config(&conf)) {
+        Ok(_) => println!("Wrote {}. You can now start Radical.", CONFIG_PATH),
+        Err(e) => println!("Failed to write {}: {}", CONFIG_PATH, e),
+    };
+}
+
+/// The database details, which are only a file when built for SQLite.
+#[derive(Default)]
+struct DatabaseAnswers {
+    url: Option<String>,
+    port: Option<u16>,
+    database: String,
+    username: String,
+    password: String,
+    sqlite_path: Option<String>,
+}
+
+#[cfg(feature = "sqlite")]
+fn ask_database() -> DatabaseAnswers {
+    DatabaseAnswers {
+        sqlite_path: Some(prompt("SQLite database file", Some("radical.db"))),
+        ..Default::default()
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn ask_database() -> DatabaseAnswers {
+    let (name, port) = match cfg!(feature = "postgres") {
+        true => ("Postgres", 5432),
+        false => ("MySQL", 3306),
+    };
+
+    DatabaseAnswers {
+        url: Some(prompt(&format!("{} host", name), Some("localhost"))),
+        port: Some(prompt(&format!("{} port", name), Some(&port.to_string())).parse().unwrap_or(port)),
+        database: prompt(&format!("{} database", name), Some("rustcms")),
+        username: prompt(&format!("{} username", name), Some("rustcms")),
+        password: prompt_password(&format!("{} password", name)),
+        sqlite_path: None,
+    }
+}
+
+fn ask_config() -> LocalConfig {
+    let database = ask_database();
+
+    let bind_address = prompt("Address to bind the server to", Some("0.0.0.0"));
+    let bind_port = prompt("Port to bind the server to", Some("8080"));
+    let max_req = prompt("Max requests per IP per minute", Some("100"));
+
+    let jwt_key = Uuid::new_v4().to_simple().to_string();
+
+    LocalConfig {
+        mysql_username: database.username,
+        mysql_password: database.password,
+        mysql_database: database.database,
+        mysql_url: database.url,
+        mysql_port: database.port,
+        sqlite_path: database.sqlite_path,
+        databases: None,
+        replica_url: None,
+        db_pool_max_size: 2,
+        db_pool_min_idle: None,
+        db_pool_timeout: 30,
+        db_pool_idle_timeout: 600,
+        db_pool_test_on_checkout: true,
+        auto_migrate: true,
+        bind_address,
+        bind_port: bind_port.parse().unwrap_or(8080),
+        socket_dir: None,
+        tls_cert: None,
+        tls_key: None,
+        security_headers: true,
+        frame_options: String::from("SAMEORIGIN"),
+        referrer_policy: String::from("strict-origin-when-cross-origin"),
+        hsts_max_age: 365 * 24 * 60 * 60,
+        csp: None,
+        csp_sites: None,
+        csp_themes: None,
+        sanitize: String::from("both"),
+        sanitize_types: None,
+        sql_name: None,
+        max_req: max_req.parse().unwrap_or(100),
+        cors_allowed_origins: None,
+        cors_allowed_methods: None,
+        cors_allowed_headers: None,
+        cors_allow_credentials: false,
+        cors_max_age: 3600,
+        rate_limit_anonymous_reads: None,
+        rate_limit_anonymous_writes: None,
+        rate_limit_key_reads: None,
+        rate_limit_key_writes: None,
+        jwt_key,
+        jwt_key_tokens: true,
+        metrics_token: None,
+        encryption_key: Some(Uuid::new_v4().to_simple().to_string()),
+        max_pages: None,
+        max_modules: None,
+        max_media_bytes: None,
+        compression_threshold: None,
+        revision_keep_last: None,
+        revision_keep_days: None,
+        revision_keep_published: false,
+        revision_prune_interval: 3600,
+        gc_interval: None,
+        schedule_interval: 60,
+        shutdown_timeout: 30,
+        backup_schedule: None,
+        backup_dir: None,
+        backup_store: None,
+        backup_keep: 7,
+        grpc_bind_port: None,
+        cache_backend: None,
+        redis_url: None,
+        session_store: None,
+        page_sessions: false,
+        page_cache_ttl: None,
+        response_cache_ttl: None,
+        response_compression: true,
+        response_compression_types: None,
+        response_compression_min_size: 1024,
+        warmup: true,
+        warmup_urls: None,
+        json_api: false,
+        delivery_max_age: 300,
+        publish_target: None,
+        publish_url: None,
+        publish_region: None,
+        publish_access_key: None,
+        publish_secret_key: None,
+        git_repo: None,
+        git_push: false,
+        oidc_issuer: None,
+        oidc_client_id: None,
+        oidc_client_secret: None,
+        oidc_redirect_url: None,
+        oidc_return_url: None,
+        oidc_create_users: false,
+        swagger_ui: false,
+        theme: None,
+        dev: false,
+        log_format: String::from("pretty"),
+        log_level: String::from("info"),
+        static_dir: None,
+        static_export_dir: None,
+        static_max_age: 3600,
+        media_dir: None,
+        media_max_size: 10 * 1024 * 1024,
+        media_presets: None,
+        json_max_size: 1024 * 1024,
+        body_max_size: 256 * 1024,
+        media_store: None,
+        s3_endpoint: None,
+        s3_bucket: None,
+        s3_region: None,
+        s3_access_key: None,
+        s3_secret_key: None,
+        search_backend: None,
+        meilisearch_url: None,
+        meilisearch_key: None,
+        meilisearch_index: None,
+        site_url: None,
+        feed_prefix: None,
+        feed_title: None,
+        default_locale: String::from("en"),
+        preview_link_ttl: 3600,
+        comments_per_hour: 5,
+        comment_max_links: 2,
+        form_submissions_per_hour: 10,
+        smtp_host: None,
+        smtp_port: None,
+        smtp_username: None,
+        smtp_password: None,
+        smtp_tls: String::from("starttls"),
+        mail_from: None,
+        mail_notify: None,
+        password_reset_url: None,
+        legacy_api: true,
+    }
+}
+
+/// Creates the admin user, or sets its password if the user already exists (like the default root user).
+/// Makes `username` an admin with `password`, creating the user if there isn't one by that name.
+pub fn create_admin(
+    username: String,
+    password: String,
+    email: Option<String>,
+    db: &DbConnection,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let admin = MutUser {
+        uuid: None,
+        username: username.clone(),
+        password: Some(encrypt_password(&password)?),
+        token: None,
+        email,
+        role: Some(String::from(UserRole::Admin.as_str())),
+    };
+
+    if User::read_one(username.clone(), db).is_ok() {
+        return Ok(User::update(username, &admin, db)?);
+    }
+
+    Ok(User::create(
+        &MutUser {
+            uuid: Some(Uuid::new_v4().to_string()),
+            ..admin
+        },
+        db,
+    )?)
+}
+
+fn format_config(conf: &LocalConfig) -> String {
+    let connection_string = format_connection_string(conf.clone());
+
+    let lines = vec![
+        format!("DATABASE_URL={}", connection_string),
+        format!("APP_MYSQL_USERNAME={}", conf.mysql_username),
+        format!("APP_MYSQL_PASSWORD={}", conf.mysql_password),
+        format!("APP_MYSQL_DATABASE={}", conf.mysql_database),
+        format!("APP_MYSQL_URL={}", conf.mysql_url.clone().unwrap_or_default()),
+        format!("APP_MYSQL_PORT={}", conf.mysql_port.map(|port| port.to_string()).unwrap_or_default()),
+        format!("APP_SQLITE_PATH={}", conf.sqlite_path.clone().unwrap_or_default()),
+        format!("APP_BIND_ADDRESS={}", conf.bind_address),
+        format!("APP_BIND_PORT={}", conf.bind_port),
+        format!("APP_MAX_REQ={}", conf.max_req),
+        format!("APP_JWT_KEY={}", conf.jwt_key),
+        format!("APP_ENCRYPTION_KEY={}", conf.encryption_key.clone().unwrap_or_default()),
+    ];
+
+    lines.join("\n") + "\n"
+}
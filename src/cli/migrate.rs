@@ -0,0 +1,36 @@
+
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::services::migration_service;
+
+/// `radical migrate status` lists every migration and whether it has been applied,
+/// `run` runs the pending ones, and `revert` reverts the last one that was run.
+pub fn run(command: Option<String>) {
+    let conf = LocalConfig::load_or_exit();
+
+    let connection = establish_connection(conf)
+        .expect("Could not connect to the database.");
+
+    match command.as_deref().unwrap_or("status") {
+        "status" => match migration_service::status(&connection) {
+            Ok(report) => {
+                for migration in &report.migrations {
+                    let state = if migration.applied { "applied" } else { "pending" };
+                    println!("[{}] {}", state, migration.name);
+                }
+                println!("{} pending.", report.pending);
+            }
+            Err(e) => println!("Could not read the migrations: {}", e),
+        },
+        "run" => match migration_service::run(&connection) {
+            Ok(ran) if ran.is_empty() => println!("Nothing to run."),
+            Ok(ran) => ran.iter().for_each(|m| println!("Ran {}", m.name)),
+            Err(e) => println!("Failed to run migrations: {}", e),
+        },
+        "revert" => match migration_service::revert(&connection) {
+            Ok(version) => println!("Reverted {}", version),
+            Err(e) => println!("Failed to revert: {}", e),
+        },
+        other => println!("Unknown command `{}`. Use `status`, `run`, or `revert`.", other),
+    }
+}
@@ -0,0 +1,69 @@
+// These are the subcommands that can be run instead of the server.
+pub mod backup;
+pub mod compress;
+pub mod create_admin;
+pub mod export_markdown;
+pub mod export_static;
+pub mod gc;
+pub mod git;
+pub mod import;
+pub mod import_markdown;
+pub mod import_wordpress;
+pub mod init;
+pub mod migrate;
+pub mod seed;
+
+use std::io::{self, Write};
+
+/// The value after `--name` in the arguments, like `a@b.c` of `--email a@b.c`.
+pub fn option(name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+
+    std::env::args().skip_while(|arg| *arg != flag).nth(1)
+}
+
+/// Asks the user for a value on stdin.
+/// If the user enters nothing and a default exists, the default is used.
+pub fn prompt(label: &str, default: Option<&str>) -> String {
+    loop {
+        match default {
+            Some(default) => print!("{} [{}]: ", label, default),
+            None => print!("{}: ", label),
+        }
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+        let input = input.trim();
+
+        match (input.is_empty(), default) {
+            (false, _) => return input.to_string(),
+            (true, Some(default)) => return default.to_string(),
+            (true, None) => println!("A value is required."),
+        }
+    }
+}
+
+/// Same as `prompt`, but the input is not echoed back to the terminal.
+pub fn prompt_password(label: &str) -> String {
+    loop {
+        let input = rpassword::prompt_password_stdout(&format!("{}: ", label)).unwrap();
+
+        if !input.is_empty() {
+            return input;
+        }
+        println!("A value is required.");
+    }
+}
+
+/// Asks a yes/no question. Anything starting with `y` is a yes.
+pub fn confirm(label: &str, default: bool) -> bool {
+    let default_str = if default { "Y/n" } else { "y/N" };
+    let answer = prompt(label, Some(default_str));
+
+    if answer == default_str {
+        return default;
+    }
+
+    answer.to_lowercase().starts_with('y')
+}
@@ -0,0 +1,61 @@
+use std::fs;
+
+use diesel::Connection;
+
+use crate::controllers::page_controllers::write_page_with_modules;
+use crate::models::config_models::LocalConfig;
+use crate::models::establish_connection;
+use crate::models::page_models::NewPageWithModules;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::plugin_service::Plugins;
+
+/// Creates the pages of a JSON file, each with its modules, like `[{ "page": { .. }, "modules": [..] }]`, the same as
+/// `POST /api/v1/pages/with-modules` does, content hooks included. Either every page is created or, when one can't be, none are.
+pub fn run(file: Option<String>, plugins: &Plugins) {
+    let file = match file {
+        Some(file) => file,
+        None => {
+            println!("Usage: radical seed <file.json>");
+            return;
+        }
+    };
+
+    let pages: Vec<NewPageWithModules> = match fs::read_to_string(&file).map(|seed| serde_json::from_str(&seed)) {
+        Ok(Ok(pages)) => pages,
+        Ok(Err(e)) => {
+            println!("{} isn't a list of pages with their modules: {}", file, e);
+            return;
+        }
+        Err(e) => {
+            println!("Failed to read {}: {}", file, e);
+            return;
+        }
+    };
+
+    let conf = LocalConfig::load_or_exit();
+
+    let connection = establish_connection(conf.clone())
+        .expect("Could not connect to the database.");
+
+    let seeded = connection.transaction::<_, CustomHttpError, _>(|| {
+        let mut modules = 0;
+        for (index, page) in pages.iter().enumerate() {
+            let (created, _, _) = write_page_with_modules(page.clone(), &conf, plugins, &connection)
+                .map_err(|error| CustomHttpError::Operation { index, error: Box::new(error) })?;
+            modules += created.modules.len();
+        }
+
+        Ok(modules)
+    });
+
+    match seeded {
+        Ok(modules) => println!("Seeded {} pages and {} modules.", pages.len(), modules),
+        Err(CustomHttpError::Operation { index, error }) => {
+            println!("Failed to seed page {}, so nothing was seeded: {}", index, error.descriptor());
+            if let CustomHttpError::Invalid(errors) = *error {
+                errors.iter().for_each(|error| println!("  {}: {}", error.field, error.message));
+            }
+        }
+        Err(e) => println!("Failed to seed {}: {}", file, e.descriptor()),
+    };
+}
@@ -0,0 +1,102 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::api_key_models::{ApiKey, MutApiKey, SCOPES};
+use crate::models::user_models::User;
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{generate_secret, Admin, RequireRole};
+use crate::services::delivery_service::hash_token;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{list, PageQuery};
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<String>,
+}
+
+/// The only time the key itself is ever sent back.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+/// Makes a key that acts as the admin making it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/api_keys",
+    tag = "api keys",
+    request_body = NewApiKey,
+    responses((status = 201, description = "The key, which is only ever sent this once", body = CreatedApiKey)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_api_key(
+    new: web::Json<NewApiKey>,
+    pool: web::Data<DbPool>,
+    admin: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    if new.scopes.is_empty() || new.scopes.iter().any(|s| !SCOPES.contains(&s.as_str())) {
+        return Err(CustomHttpError::BadRequest);
+    }
+
+    let key = generate_secret();
+    let (name, scopes, key_hash) = (new.name.clone(), new.scopes.join(","), hash_token(&key));
+
+    let api_key = with_connection(pool, move |db| {
+        // the claim's subject is the username, while keys point at the user's uuid.
+        let new_key = MutApiKey {
+            uuid: Uuid::new_v4().to_string(),
+            name,
+            key_hash,
+            user_uuid: User::read_one(admin.0.sub, db)?.uuid,
+            scopes,
+        };
+        ApiKey::create(&new_key, db)?;
+
+        Ok(ApiKey::read_one(new_key.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(CreatedApiKey { api_key, key }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/api_keys",
+    tag = "api keys",
+    params(PageQuery),
+    responses((status = 200, description = "Every one, or one page of them as `{ data, page, per_page, total, total_pages }` when `page` or `per_page` is set", body = [ApiKey])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_api_keys(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let keys = with_connection(pool, move |db| list(&query, db, ApiKey::read_all, ApiKey::read_paginated)).await?;
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+/// Revokes a key, which is refused from the next request on.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/api_keys/{id}",
+    tag = "api keys",
+    params(("id" = String, Path, description = "The key's uuid")),
+    responses((status = 200, description = "How many keys were revoked", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_api_key(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| Ok(ApiKey::delete(id.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -1,58 +1,101 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use uuid::Uuid;
 
 use crate::models::module_models::{ModuleCategory, MutCategory};
-use crate::models::{pool_handler, Model, MySQLPool};
-use crate::services::auth_service::Claims;
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Editor, RequireRole};
 use crate::services::errors_service::CustomHttpError;
+use crate::services::jsonapi_service::respond;
+use crate::services::validation_service::Validate;
+use crate::services::webhook_service::{WebhookEvent, WebhookQueue};
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/category",
+    tag = "categories",
+    request_body = MutCategory,
+    responses((status = 201, description = "The category that was made", body = MutCategory)),
+    security(("token" = []), ("session" = []))
+)]
 pub async fn create_category(
     new: web::Json<MutCategory>,
-    pool: web::Data<MySQLPool>,
-    _: Claims
+    pool: web::Data<DbPool>,
+    webhooks: web::Data<WebhookQueue>,
+    _: RequireRole<Editor>
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    new.validate()?;
 
     let mut uuid_new = new.clone();
     uuid_new.uuid = Some(Uuid::new_v4().to_string());
 
-    ModuleCategory::create(&uuid_new, &mysql_pool)?;
+    let created = uuid_new.clone();
+    with_connection(pool, move |db| Ok(ModuleCategory::create(&created, db)?)).await?;
+    webhooks.send(WebhookEvent::new("category.created", &uuid_new));
 
     Ok(HttpResponse::Created().json(uuid_new))
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/v1/category/{id}",
+    tag = "categories",
+    params(("id" = String, Path, description = "The category's uuid")),
+    request_body = MutCategory,
+    responses((status = 200, description = "The category as it was saved", body = MutCategory)),
+    security(("token" = []), ("session" = []))
+)]
 pub async fn update_category(
     updated_category: web::Json<MutCategory>,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
-    _: Claims
+    pool: web::Data<DbPool>,
+    webhooks: web::Data<WebhookQueue>,
+    _: RequireRole<Editor>
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    updated_category.validate()?;
 
-    ModuleCategory::update(id.clone(), &updated_category, &mysql_pool)?;
+    let updated = updated_category.clone();
+    let mut category = updated_category.clone();
+    category.uuid = Some(id.clone());
+    with_connection(pool, move |db| Ok(ModuleCategory::update(id.into_inner(), &updated, db)?)).await?;
+    webhooks.send(WebhookEvent::new("category.updated", category));
 
     Ok(HttpResponse::Ok().json(updated_category.0))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/category/{id}",
+    tag = "categories",
+    params(("id" = String, Path, description = "The category's uuid")),
+    responses((status = 200, description = "The category", body = ModuleCategory))
+)]
 pub async fn get_category(
+    req: HttpRequest,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
+    pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    let res = with_connection(pool, move |db| Ok(ModuleCategory::read_one(id.into_inner(), db)?)).await?;
 
-    let res = ModuleCategory::read_one(id.clone(), &mysql_pool)?;
-
-    Ok(HttpResponse::Ok().json(res))
+    Ok(respond(&req, HttpResponse::Ok(), res))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/category/{id}",
+    tag = "categories",
+    params(("id" = String, Path, description = "The category's uuid")),
+    responses((status = 200, description = "How many categories were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
 pub async fn delete_category(
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
-    _: Claims
+    pool: web::Data<DbPool>,
+    webhooks: web::Data<WebhookQueue>,
+    _: RequireRole<Editor>
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
-
-    let res = ModuleCategory::delete(id.clone(), &mysql_pool)?;
+    let category_id = id.clone();
+    let res = with_connection(pool, move |db| Ok(ModuleCategory::delete(category_id, db)?)).await?;
+    webhooks.send(WebhookEvent::deleted("category.deleted", &id));
 
     Ok(HttpResponse::Ok().json(res))
 }
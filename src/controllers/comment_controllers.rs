@@ -0,0 +1,194 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::models::comment_models::{
+    Comment, MutComment, MutCommentStatus, NewComment, PublicComment, APPROVED, PENDING, SPAM,
+};
+use crate::models::config_models::LocalConfig;
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Editor, RequireRole};
+use crate::services::cache_service::CacheBackend;
+use crate::services::comment_service::{check_rate, is_spam};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{Listing, PageQuery, Paginated};
+use crate::services::publish_service::{PublishJob, PublishQueue};
+use crate::services::validation_service::Validate;
+use crate::services::visibility_service::require_published;
+
+// Published pages show their approved comments, so a page is published again when one of its comments is moderated.
+
+#[derive(Debug, Deserialize, Clone, IntoParams)]
+pub struct CommentQuery {
+    /// Only the comments of the status, like `pending` for the ones waiting for a moderator.
+    pub status: Option<String>,
+}
+
+/// Anyone can comment on a published page, without logging in. Comments wait for a moderator before they are shown,
+/// and the ones that look like spam are kept aside for them. Each IP can only send `APP_COMMENTS_PER_HOUR` an hour.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/{id}/comments",
+    tag = "comments",
+    params(("id" = String, Path, description = "The page's uuid")),
+    request_body = NewComment,
+    responses(
+        (status = 202, description = "The comment, which is shown once it is approved", body = PublicComment),
+        (status = 429, description = "Too many comments were sent from the IP this hour")
+    )
+)]
+pub async fn create_comment(
+    req: HttpRequest,
+    id: web::Path<String>,
+    new: web::Json<NewComment>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    cache: web::Data<dyn CacheBackend>,
+) -> Result<HttpResponse, CustomHttpError> {
+    new.validate()?;
+
+    let client = req.peer_addr().map(|addr| addr.ip().to_string());
+    let new = new.into_inner();
+    let comment = MutComment {
+        uuid: Uuid::new_v4().to_string(),
+        page_uuid: id.into_inner(),
+        status: String::from(if is_spam(&new, &conf) { SPAM } else { PENDING }),
+        author_name: new.author_name,
+        author_email: new.author_email.filter(|email| !email.is_empty()),
+        body: new.body,
+    };
+    let comment = with_connection(pool, move |db| {
+        if let Some(client) = client {
+            check_rate(&client, &**cache, &conf)?;
+        }
+        require_published(&comment.page_uuid, db)?;
+        Comment::create(&comment, db)?;
+
+        Ok(Comment::read_one(comment.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Accepted().json(PublicComment::from(comment)))
+}
+
+/// The approved comments of a published page, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}/comments",
+    tag = "comments",
+    params(("id" = String, Path, description = "The page's uuid"), PageQuery),
+    responses((status = 200, description = "The approved comments, or one page of them when `page` or `per_page` is set", body = [PublicComment]))
+)]
+pub async fn get_page_comments(
+    id: web::Path<String>,
+    query: web::Query<PageQuery>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let comments = with_connection(pool, move |db| {
+        let page = id.into_inner();
+        require_published(&page, db)?;
+
+        let public = |comments: Vec<Comment>| comments.into_iter().map(PublicComment::from).collect::<Vec<_>>();
+        if !query.is_requested() {
+            return Ok(Listing::All(public(Comment::read_filtered(Some(&page), Some(APPROVED), db)?)));
+        }
+
+        let (offset, limit) = query.window()?;
+        let (data, total) = Comment::read_filtered_paginated(Some(&page), Some(APPROVED), offset, limit, db)?;
+
+        Ok(Listing::Page(Paginated::new(public(data), total, &query)))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(comments))
+}
+
+/// Every comment on the site's pages, with the emails they were left with, for moderators to work through.
+#[utoipa::path(
+    get,
+    path = "/api/v1/comments",
+    tag = "comments",
+    params(CommentQuery, PageQuery),
+    responses((status = 200, description = "The comments oldest first, or one page of them when `page` or `per_page` is set", body = [Comment])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_comments(
+    filter: web::Query<CommentQuery>,
+    query: web::Query<PageQuery>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let comments = with_connection(pool, move |db| {
+        let status = filter.into_inner().status;
+        if !query.is_requested() {
+            return Ok(Listing::All(Comment::read_filtered(None, status.as_deref(), db)?));
+        }
+
+        let (offset, limit) = query.window()?;
+        let (data, total) = Comment::read_filtered_paginated(None, status.as_deref(), offset, limit, db)?;
+
+        Ok(Listing::Page(Paginated::new(data, total, &query)))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(comments))
+}
+
+/// Approves a comment so that it is shown, or moves it back to `pending` or to `spam` to hide it again.
+#[utoipa::path(
+    put,
+    path = "/api/v1/comments/{id}/status",
+    tag = "comments",
+    params(("id" = String, Path, description = "The comment's uuid")),
+    request_body = MutCommentStatus,
+    responses((status = 200, description = "The comment as it was saved", body = Comment)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_comment_status(
+    id: web::Path<String>,
+    updated: web::Json<MutCommentStatus>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    updated.validate()?;
+
+    let comment = with_connection(pool, move |db| {
+        let comment = Comment::read_one(id.into_inner(), db)?;
+        Comment::set_status(comment.uuid.clone(), &updated.status, db)?;
+
+        Ok(Comment::read_one(comment.uuid, db)?)
+    })
+    .await?;
+
+    publish.send(PublishJob::Page(comment.page_uuid.clone()));
+
+    Ok(HttpResponse::Ok().json(comment))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/comments/{id}",
+    tag = "comments",
+    params(("id" = String, Path, description = "The comment's uuid")),
+    responses((status = 200, description = "How many comments were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_comment(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (page_uuid, res) = with_connection(pool, move |db| {
+        let comment = Comment::read_one(id.into_inner(), db)?;
+
+        Ok((comment.page_uuid, Comment::delete(comment.uuid, db)?))
+    })
+    .await?;
+
+    publish.send(PublishJob::Page(page_uuid));
+
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,141 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::models::content_type_models::{ContentType, ContentTypeDTO, MutContentType, NewContentType};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Admin, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{list, PageQuery};
+use crate::services::validation_service::Validate;
+
+fn to_mut(uuid: String, new: NewContentType) -> MutContentType {
+    MutContentType {
+        uuid,
+        name: new.name,
+        json_schema: new.schema.to_string(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/content-types",
+    tag = "content types",
+    request_body = NewContentType,
+    responses((status = 201, description = "The content type as it was saved", body = ContentTypeDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_content_type(
+    new: web::Json<NewContentType>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    new.validate()?;
+
+    let new_type = to_mut(Uuid::new_v4().to_string(), new.into_inner());
+    let content_type = with_connection(pool, move |db| {
+        ContentType::create(&new_type, db)?;
+
+        Ok(ContentType::read_one(new_type.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(ContentTypeDTO::from(content_type)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/content-types",
+    tag = "content types",
+    params(PageQuery),
+    responses((status = 200, description = "Every content type by name, or one page of them when `page` or `per_page` is set", body = [ContentTypeDTO]))
+)]
+pub async fn get_content_types(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let content_types = with_connection(pool, move |db| {
+        list(&query, db, ContentType::read_all, ContentType::read_paginated)?
+            .try_map(|types| Ok::<Vec<ContentTypeDTO>, CustomHttpError>(types.into_iter().map(|t| t.into()).collect()))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(content_types))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/content-types/{id}",
+    tag = "content types",
+    params(("id" = String, Path, description = "The content type's uuid")),
+    responses((status = 200, description = "The content type", body = ContentTypeDTO))
+)]
+pub async fn get_content_type(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let content_type = with_connection(pool, move |db| Ok(ContentType::read_one(id.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(ContentTypeDTO::from(content_type)))
+}
+
+/// Modules that are already of the type keep their content, and are checked against the new schema the next time they are written.
+/// A type can't be renamed while any module is of it.
+#[utoipa::path(
+    put,
+    path = "/api/v1/content-types/{id}",
+    tag = "content types",
+    params(("id" = String, Path, description = "The content type's uuid")),
+    request_body = NewContentType,
+    responses((status = 200, description = "The content type as it was saved", body = ContentTypeDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_content_type(
+    updated: web::Json<NewContentType>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    updated.validate()?;
+
+    let updated = to_mut(id.into_inner(), updated.into_inner());
+    let content_type = with_connection(pool, move |db| {
+        let old = ContentType::read_one(updated.uuid.clone(), db)?;
+        if old.name != updated.name && ContentType::is_used(&old.name, db)? {
+            return Err(CustomHttpError::Conflict);
+        }
+
+        ContentType::update(updated.uuid.clone(), &updated, db)?;
+
+        Ok(ContentType::read_one(updated.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ContentTypeDTO::from(content_type)))
+}
+
+/// Only content types that no module is of can be deleted, including the modules in the trash.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/content-types/{id}",
+    tag = "content types",
+    params(("id" = String, Path, description = "The content type's uuid")),
+    responses((status = 200, description = "How many content types were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_content_type(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| {
+        let content_type = ContentType::read_one(id.into_inner(), db)?;
+        if ContentType::is_used(&content_type.name, db)? {
+            return Err(CustomHttpError::Conflict);
+        }
+
+        Ok(ContentType::delete(content_type.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,198 @@
+use actix_web::dev::HttpResponseBuilder;
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::{Module, ModuleCategory};
+use crate::models::page_models::{is_live, Page, PageDTO, PageModuleDTO, PUBLIC};
+use crate::models::{with_connection, DbConnection, DbPool, Model};
+use crate::services::delivery_service::{cache_control, DeliveryAccess};
+use crate::services::encryption_service::{reveal_fields, reveal_module};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::jsonapi_service::{respond, respond_listing};
+use crate::services::pagination_service::{Listing, PageQuery, Paginated};
+use crate::services::sanitize_service::{on_render_fields, on_render_module};
+use crate::services::visibility_service::{require_published, require_published_module, require_visible, strip_fields, Role};
+
+// The delivery API is read only and public facing, so encrypted module content is never revealed through it.
+
+fn cached(conf: &LocalConfig) -> HttpResponseBuilder {
+    let mut builder = HttpResponse::Ok();
+    builder
+        .header("Cache-Control", cache_control(conf.delivery_max_age))
+        .header("Vary", "Authorization");
+
+    builder
+}
+
+/// The published pages in the token's locales, whole or one page of them like `list`.
+fn published_pages(access: &DeliveryAccess, query: &PageQuery, db: &DbConnection) -> Result<Listing<PageDTO>, CustomHttpError> {
+    let locales = access.0.locale_scope();
+    if !query.is_requested() {
+        return Ok(Listing::All(Page::read_published_in(&locales, db)?));
+    }
+
+    let (offset, limit) = query.window()?;
+    let (data, total) = Page::read_published_in_paginated(&locales, offset, limit, db)?;
+
+    Ok(Listing::Page(Paginated::new(data, total, query)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/content/v1/pages",
+    tag = "delivery",
+    params(PageQuery),
+    responses((status = 200, description = "Every one, or one page of them as `{ data, page, per_page, total, total_pages }` when `page` or `per_page` is set, of published pages only", body = [PageDTO])),
+    security(("delivery_token" = []))
+)]
+pub async fn get_pages(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    access: DeliveryAccess,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    access.require("pages")?;
+    let pages = with_connection(pool, move |db| published_pages(&access, &query, db)).await?;
+
+    Ok(respond_listing(&req, cached(&conf), pages))
+}
+
+#[utoipa::path(
+    get,
+    path = "/content/v1/pages/{id}",
+    tag = "delivery",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "The published page with its public modules", body = PageModuleDTO)),
+    security(("delivery_token" = []))
+)]
+pub async fn get_page(
+    req: HttpRequest,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    access: DeliveryAccess,
+) -> Result<HttpResponse, CustomHttpError> {
+    access.require("pages")?;
+    let page = with_connection(pool, move |db| {
+        let mut page = Page::read_one_join_on(id.into_inner(), db)?;
+        if !is_live(&page.status, page.publish_at) || page.visibility != PUBLIC {
+            return Err(CustomHttpError::NotFound);
+        }
+        access.require_locale(page.locale.as_deref())?;
+        page.fields = on_render_fields(reveal_fields(strip_fields(page.fields, Role::Public), false)?);
+
+        Ok(page)
+    })
+    .await?;
+
+    Ok(respond(&req, cached(&conf), page))
+}
+
+#[utoipa::path(
+    get,
+    path = "/content/v1/pages/url/{url}",
+    tag = "delivery",
+    params(("url" = String, Path, description = "The page's URL, without its leading `/`")),
+    responses((status = 200, description = "The published page with its public modules", body = PageModuleDTO)),
+    security(("delivery_token" = []))
+)]
+pub async fn get_page_by_url(
+    req: HttpRequest,
+    url: web::Path<String>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    access: DeliveryAccess,
+) -> Result<HttpResponse, CustomHttpError> {
+    access.require("pages")?;
+    let page = with_connection(pool, move |db| {
+        let (page, fields) = Page::read_one_join_on_url(format!("/{}", url), db)?;
+        // pages only some users can see are left out of the delivery API, like drafts.
+        if !is_live(&page.status, page.publish_at) || page.visibility != PUBLIC {
+            return Err(CustomHttpError::NotFound);
+        }
+        access.require_locale(page.locale.as_deref())?;
+
+        Ok(PageModuleDTO {
+            uuid: page.uuid,
+            page_name: page.page_name,
+            page_url: page.page_url,
+            page_title: page.page_title,
+            time_created: page.time_created,
+            status: page.status,
+            publish_at: page.publish_at,
+            parent_page: page.parent_page,
+            meta_description: page.meta_description,
+            meta_keywords: page.meta_keywords,
+            og_image: page.og_image,
+            noindex: page.noindex,
+            locale: page.locale,
+            translation_group: page.translation_group,
+            version: page.version,
+            visibility: page.visibility,
+            fields: on_render_fields(reveal_fields(strip_fields(fields, Role::Public), false)?),
+        })
+    })
+    .await?;
+
+    Ok(respond(&req, cached(&conf), page))
+}
+
+#[utoipa::path(
+    get,
+    path = "/content/v1/modules/{id}",
+    tag = "delivery",
+    params(("id" = String, Path, description = "The module's uuid")),
+    responses((status = 200, description = "The module, if it is public and on a published page", body = Module)),
+    security(("delivery_token" = []))
+)]
+pub async fn get_module(
+    req: HttpRequest,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    access: DeliveryAccess,
+) -> Result<HttpResponse, CustomHttpError> {
+    access.require("modules")?;
+    let module = with_connection(pool, move |db| {
+        let module = require_visible(Module::read_one(id.into_inner(), db)?, Role::Public)?;
+        require_published_module(&module, db)?;
+        // global modules are on pages of every locale.
+        if let Some(page_uuid) = &module.page_uuid {
+            access.require_page_locale(page_uuid, db)?;
+        }
+
+        Ok(on_render_module(reveal_module(module, false)?))
+    })
+    .await?;
+
+    Ok(respond(&req, cached(&conf), module))
+}
+
+#[utoipa::path(
+    get,
+    path = "/content/v1/categories/{id}",
+    tag = "delivery",
+    params(("id" = String, Path, description = "The category's uuid")),
+    responses((status = 200, description = "The category, if it is on a published page", body = ModuleCategory)),
+    security(("delivery_token" = []))
+)]
+pub async fn get_category(
+    req: HttpRequest,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    access: DeliveryAccess,
+) -> Result<HttpResponse, CustomHttpError> {
+    access.require("categories")?;
+    let category = with_connection(pool, move |db| {
+        let category = ModuleCategory::read_one(id.into_inner(), db)?;
+        require_published(&category.page_uuid, db)?;
+        access.require_page_locale(&category.page_uuid, db)?;
+
+        Ok(category)
+    })
+    .await?;
+
+    Ok(respond(&req, cached(&conf), category))
+}
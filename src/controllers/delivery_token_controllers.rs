@@ -0,0 +1,98 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::delivery_token_models::{DeliveryToken, MutDeliveryToken};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{generate_secret, Admin, RequireRole};
+use crate::services::delivery_service::hash_token;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{list, PageQuery};
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewDeliveryToken {
+    pub name: String,
+    pub content_types: Option<Vec<String>>,
+    pub locales: Option<Vec<String>>,
+}
+
+/// The only time the token itself is ever sent back.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedDeliveryToken {
+    #[serde(flatten)]
+    pub delivery_token: DeliveryToken,
+    pub token: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/delivery_tokens",
+    tag = "delivery tokens",
+    request_body = NewDeliveryToken,
+    responses((status = 201, description = "The token, which is only ever sent this once", body = CreatedDeliveryToken)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_delivery_token(
+    new: web::Json<NewDeliveryToken>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let token = generate_secret();
+    let new_token = MutDeliveryToken {
+        uuid: Uuid::new_v4().to_string(),
+        name: new.name.clone(),
+        token_hash: hash_token(&token),
+        content_types: new.content_types.as_ref().map(|c| c.join(",")),
+        locales: new.locales.as_ref().map(|l| l.join(",")),
+    };
+
+    let delivery_token = with_connection(pool, move |db| {
+        DeliveryToken::create(&new_token, db)?;
+
+        Ok(DeliveryToken::read_one(new_token.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(CreatedDeliveryToken { delivery_token, token }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/delivery_tokens",
+    tag = "delivery tokens",
+    params(PageQuery),
+    responses((status = 200, description = "Every token, or one page of them when `page` or `per_page` is set", body = [DeliveryToken])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_delivery_tokens(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let tokens = with_connection(pool, move |db| {
+        list(&query, db, DeliveryToken::read_all, DeliveryToken::read_paginated)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+/// Revokes a token. Responses cached against it stay cached until they expire.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/delivery_tokens/{id}",
+    tag = "delivery tokens",
+    params(("id" = String, Path, description = "The token's uuid")),
+    responses((status = 200, description = "How many tokens were revoked", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_delivery_token(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| Ok(DeliveryToken::delete(id.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(res))
+}
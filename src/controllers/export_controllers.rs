@@ -0,0 +1,152 @@
+use actix_web::http::header;
+use actix_web::{web, HttpResponse};
+use diesel::{Connection, OptionalExtension};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use super::module_controllers::{write_module, write_new_module_as, ModuleChange};
+use super::page_controllers::{write_new_page, write_page, PageChange};
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::{Module, MutModule};
+use crate::models::page_models::{MutPage, Page};
+use crate::models::{with_connection, DbConnection, DbPool};
+use crate::services::auth_service::{Admin, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::export_service::{self, parents_first, ContentDump, ImportReport};
+use crate::services::page_cache_service::PageCache;
+use crate::services::plugin_service::Plugins;
+use crate::services::publish_service::PublishQueue;
+use crate::services::route_service::RouteTable;
+use crate::services::webhook_service::WebhookQueue;
+
+/// `?dry_run=true` on an import checks and writes everything as a real one does, then rolls all of it back.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Dumps the site's content as JSON, to be imported into another server with `POST /api/v1/admin/import`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/export",
+    tag = "export",
+    responses((status = 200, description = "The site's pages, modules, menus, and media metadata", body = ContentDump)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn export_content(pool: web::Data<DbPool>, _: RequireRole<Admin>) -> Result<HttpResponse, CustomHttpError> {
+    let dump = with_connection(pool, export_service::export).await?;
+
+    Ok(HttpResponse::Ok()
+        .header(header::CONTENT_DISPOSITION, "attachment; filename=\"radical-export.json\"")
+        .json(dump))
+}
+
+/// Imports a dump from `GET /api/v1/admin/export` in one transaction, so that a dump that can't be imported whole leaves
+/// nothing behind. Everything keeps the uuid it was exported with, and overwrites what already has it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/import",
+    tag = "export",
+    params(ImportQuery),
+    request_body = ContentDump,
+    responses((status = 200, description = "How much was, or with a dry run would be, created and updated", body = ImportReport)),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn import_content(
+    dump: web::Json<ContentDump>,
+    query: web::Query<ImportQuery>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    pages: web::Data<PageCache>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    export_service::check_version(&dump)?;
+
+    let dry_run = query.dry_run;
+    let conf = conf.get_ref().clone();
+    let (report, page_changes, module_changes) = with_connection(pool, move |db| {
+        let dump = dump.into_inner();
+
+        match dry_run {
+            true => export_service::rolled_back(db, || write_dump(dump, dry_run, &conf, &plugins, db)),
+            false => db.transaction(|| write_dump(dump, dry_run, &conf, &plugins, db)),
+        }
+    })
+    .await?;
+
+    if !dry_run {
+        for change in page_changes {
+            change.announce(&publish, &routes, &webhooks);
+        }
+        for change in module_changes {
+            change.announce(&publish, &webhooks);
+        }
+        pages.clear();
+    }
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Writes the dump's pages, parents first, then their categories and modules, then the menus and media.
+/// What is in the trash under a uuid of the dump is taken out of it and overwritten.
+fn write_dump(
+    dump: ContentDump,
+    dry_run: bool,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(ImportReport, Vec<PageChange>, Vec<ModuleChange>), CustomHttpError> {
+    let mut report = ImportReport {
+        dry_run,
+        ..ImportReport::default()
+    };
+    let mut page_changes = Vec::new();
+    let mut module_changes = Vec::new();
+
+    for page in parents_first(dump.pages, |page| &page.uuid, |page| page.parent_page.as_deref()) {
+        let existing = Page::read_any(page.uuid.clone(), db).optional()?;
+        if existing.as_ref().is_some_and(|existing| existing.deleted_at.is_some()) {
+            Page::restore(page.uuid.clone(), db)?;
+        }
+
+        let written = export_service::mut_page(&page);
+        let (_, _, change) = match existing {
+            Some(_) => write_page(page.uuid.clone(), move |_| Ok(MutPage { uuid: None, ..written }), plugins, db)?,
+            None => write_new_page(written, conf, plugins, db)?,
+        };
+        // writing the page only sets its status, so a scheduled draft is given its `publish_at` here.
+        Page::set_status(page.uuid.clone(), &page.status, page.publish_at, db)?;
+
+        report.pages.count(existing.is_some());
+        page_changes.push(change);
+    }
+
+    export_service::write_categories(&dump.module_categories, &mut report, db)?;
+
+    for module in &dump.modules {
+        let existing = Module::read_any(module.uuid.clone(), db).optional()?;
+        if existing.as_ref().is_some_and(|existing| existing.deleted_at.is_some()) {
+            Module::restore(vec![module.uuid.clone()], db)?;
+        }
+
+        let written = export_service::mut_module(module);
+        let (_, _, change) = match existing {
+            Some(_) => write_module(module.uuid.clone(), move |_| Ok(MutModule { uuid: None, ..written }), plugins, db)?,
+            None => write_new_module_as(written, conf, plugins, db)?,
+        };
+
+        report.modules.count(existing.is_some());
+        module_changes.push(change);
+    }
+
+    export_service::write_menus(&dump.menus, dump.menu_items, &mut report, db)?;
+    export_service::write_media(&dump.media, &mut report, db)?;
+
+    Ok((report, page_changes, module_changes))
+}
@@ -0,0 +1,21 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::models::config_models::LocalConfig;
+use crate::models::{with_connection, DbPool};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::feed_service::{self, Feed};
+use crate::services::sitemap_service::site_url;
+
+/// Serves `/feed.xml`, an Atom feed of the newest pages under `APP_FEED_PREFIX`. A 404 when there is no prefix.
+pub async fn get_feed(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    feed: web::Data<Feed>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let prefix = feed_service::prefix(&conf).ok_or(CustomHttpError::NotFound)?;
+    let base = site_url(&req, &conf)?;
+    let xml = with_connection(pool, move |db| Ok(feed.render(&conf, &prefix, &base, db)?)).await?;
+
+    Ok(HttpResponse::Ok().content_type("application/atom+xml; charset=utf-8").body(xml))
+}
@@ -0,0 +1,290 @@
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::models::config_models::LocalConfig;
+use crate::models::form_models::{Form, FormDTO, FormSubmission, FormSubmissionDTO, MutForm, MutFormSubmission, NewForm};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Editor, RequireRole};
+use crate::services::body_limit_service::Body;
+use crate::services::cache_service::CacheBackend;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::form_service::{self, check_rate, check_submission, is_spam, parse_submission};
+use crate::services::mail_service::MailQueue;
+use crate::services::pagination_service::{list, Listing, PageQuery, Paginated};
+use crate::services::validation_service::Validate;
+
+fn to_mut(uuid: String, new: NewForm) -> MutForm {
+    MutForm {
+        uuid,
+        name: new.name,
+        fields: serde_json::to_string(&new.fields).unwrap_or_default(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/forms",
+    tag = "forms",
+    request_body = NewForm,
+    responses((status = 201, description = "The form as it was saved", body = FormDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_form(
+    new: web::Json<NewForm>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    new.validate()?;
+
+    let new_form = to_mut(Uuid::new_v4().to_string(), new.into_inner());
+    let form = with_connection(pool, move |db| {
+        Form::create(&new_form, db)?;
+
+        Ok(Form::read_one(new_form.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(FormDTO::from(form)))
+}
+
+/// Forms are public, so that a site can build them from their fields.
+#[utoipa::path(
+    get,
+    path = "/api/v1/forms",
+    tag = "forms",
+    params(PageQuery),
+    responses((status = 200, description = "Every form by name, or one page of them when `page` or `per_page` is set", body = [FormDTO]))
+)]
+pub async fn get_forms(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let forms = with_connection(pool, move |db| {
+        list(&query, db, Form::read_all, Form::read_paginated)?
+            .try_map(|forms| Ok::<Vec<FormDTO>, CustomHttpError>(forms.into_iter().map(|f| f.into()).collect()))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(forms))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/forms/{id}",
+    tag = "forms",
+    params(("id" = String, Path, description = "The form's uuid or name")),
+    responses((status = 200, description = "The form", body = FormDTO))
+)]
+pub async fn get_form(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let form = with_connection(pool, move |db| Ok(Form::read_one(id.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(FormDTO::from(form)))
+}
+
+/// Submissions that were already sent keep their values, including those of fields the form no longer has.
+#[utoipa::path(
+    put,
+    path = "/api/v1/forms/{id}",
+    tag = "forms",
+    params(("id" = String, Path, description = "The form's uuid or name")),
+    request_body = NewForm,
+    responses((status = 200, description = "The form as it was saved", body = FormDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_form(
+    updated: web::Json<NewForm>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    updated.validate()?;
+
+    let form = with_connection(pool, move |db| {
+        let form = Form::read_one(id.into_inner(), db)?;
+        Form::update(form.uuid.clone(), &to_mut(form.uuid.clone(), updated.into_inner()), db)?;
+
+        Ok(Form::read_one(form.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(FormDTO::from(form)))
+}
+
+/// Deletes the form along with its submissions.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/forms/{id}",
+    tag = "forms",
+    params(("id" = String, Path, description = "The form's uuid or name")),
+    responses((status = 200, description = "How many forms were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_form(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| {
+        let form = Form::read_one(id.into_inner(), db)?;
+
+        Ok(Form::delete(form.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Anyone can fill in a form, without logging in, from an HTML form or with JSON. Each IP can only send
+/// `APP_FORM_SUBMISSIONS_PER_HOUR` an hour, and submissions that fill in the hidden `_website` field are answered
+/// the same as any other, but thrown away. `APP_MAIL_NOTIFY` is sent each one that is kept.
+#[utoipa::path(
+    post,
+    path = "/api/v1/forms/{id}/submit",
+    tag = "forms",
+    params(("id" = String, Path, description = "The form's uuid or name")),
+    request_body(content = Object, description = "The values by field name, as JSON or `application/x-www-form-urlencoded`"),
+    responses(
+        (status = 202, description = "The submission was received"),
+        (status = 429, description = "Too many submissions were sent from the IP this hour")
+    )
+)]
+pub async fn submit_form(
+    req: HttpRequest,
+    id: web::Path<String>,
+    body: Body,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    cache: web::Data<dyn CacheBackend>,
+    mail: web::Data<MailQueue>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let content_type = req.headers().get(header::CONTENT_TYPE).and_then(|h| h.to_str().ok()).unwrap_or_default();
+    let data = parse_submission(content_type, &body)?;
+    let client = req.peer_addr().map(|addr| addr.ip().to_string());
+
+    let kept = with_connection(pool, move |db| {
+        if let Some(client) = client {
+            check_rate(&client, &**cache, &conf)?;
+        }
+
+        let form = FormDTO::from(Form::read_one(id.into_inner(), db)?);
+        let values = check_submission(&form.fields, &data)?;
+        if is_spam(&data) {
+            return Ok(None);
+        }
+
+        let submission = MutFormSubmission {
+            uuid: Uuid::new_v4().to_string(),
+            form_uuid: form.form.uuid,
+            data: serde_json::Value::Object(values.clone()).to_string(),
+        };
+        FormSubmission::create(&submission, db)?;
+
+        Ok(Some((form.form.name, submission.uuid, values)))
+    })
+    .await?;
+
+    if let Some((form, submission, values)) = kept {
+        mail.notify(
+            format!("New submission to {}", form),
+            "form-submission",
+            serde_json::json!({ "form": form, "submission": submission, "values": values }),
+        );
+    }
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/forms/{id}/submissions",
+    tag = "forms",
+    params(("id" = String, Path, description = "The form's uuid or name"), PageQuery),
+    responses((status = 200, description = "The form's submissions newest first, or one page of them when `page` or `per_page` is set", body = [FormSubmissionDTO])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_submissions(
+    id: web::Path<String>,
+    query: web::Query<PageQuery>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let submissions = with_connection(pool, move |db| {
+        let form = Form::read_one(id.into_inner(), db)?;
+        let dtos = |rows: Vec<FormSubmission>| rows.into_iter().map(FormSubmissionDTO::from).collect::<Vec<_>>();
+        if !query.is_requested() {
+            return Ok(Listing::All(dtos(FormSubmission::read_for_form(&form.uuid, db)?)));
+        }
+
+        let (offset, limit) = query.window()?;
+        let (data, total) = FormSubmission::read_for_form_paginated(&form.uuid, offset, limit, db)?;
+
+        Ok(Listing::Page(Paginated::new(dtos(data), total, &query)))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(submissions))
+}
+
+/// Every submission of the form as CSV, newest first, with a column for each of its fields.
+#[utoipa::path(
+    get,
+    path = "/api/v1/forms/{id}/submissions.csv",
+    tag = "forms",
+    params(("id" = String, Path, description = "The form's uuid or name")),
+    responses((status = 200, description = "The submissions", body = String, content_type = "text/csv")),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn export_submissions(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (name, csv) = with_connection(pool, move |db| {
+        let form = FormDTO::from(Form::read_one(id.into_inner(), db)?);
+        let submissions: Vec<FormSubmissionDTO> =
+            FormSubmission::read_for_form(&form.form.uuid, db)?.into_iter().map(|s| s.into()).collect();
+
+        Ok((form.form.name, form_service::to_csv(&form.fields, &submissions)))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}-submissions.csv\"", name))
+        .body(csv))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/forms/{id}/submissions/{submission_id}",
+    tag = "forms",
+    params(
+        ("id" = String, Path, description = "The form's uuid or name"),
+        ("submission_id" = String, Path, description = "The submission's uuid")
+    ),
+    responses((status = 200, description = "How many submissions were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_submission(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (id, submission_id) = path.into_inner();
+    let res = with_connection(pool, move |db| {
+        let form = Form::read_one(id, db)?;
+        let submission = FormSubmission::read_one(submission_id, db)?;
+        if submission.form_uuid != form.uuid {
+            return Err(CustomHttpError::NotFound);
+        }
+
+        Ok(FormSubmission::delete(submission.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,159 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::gallery_item_models::{GalleryItem, MutGalleryItem};
+use crate::models::module_models::Module;
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Claims, Editor, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::publish_service::{PublishJob, PublishQueue};
+use crate::services::visibility_service::{require_viewable_module, require_visible, Role};
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewGalleryItem {
+    pub media_url: String,
+    pub caption: Option<String>,
+}
+
+/// The items of a gallery, which are kept from clients its module is kept from, the same as by `GET /api/v1/modules/{id}`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/modules/{id}/gallery",
+    tag = "galleries",
+    params(("id" = String, Path, description = "The module's uuid")),
+    responses((status = 200, description = "The gallery's items in order", body = [GalleryItem]))
+)]
+pub async fn get_gallery(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let items = with_connection(pool, move |db| {
+        let module = require_visible(Module::read_one(id.into_inner(), db)?, Role::of(&claim))?;
+        require_viewable_module(&module, &claim, db)?;
+
+        Ok(GalleryItem::read_for_module(module.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(items))
+}
+
+/// Adds an item to the end of the gallery.
+#[utoipa::path(
+    post,
+    path = "/api/v1/modules/{id}/gallery",
+    tag = "galleries",
+    params(("id" = String, Path, description = "The module's uuid")),
+    request_body = NewGalleryItem,
+    responses((status = 201, description = "The item as it was saved", body = MutGalleryItem)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn add_gallery_item(
+    new: web::Json<NewGalleryItem>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (item, page_uuid) = with_connection(pool, move |db| {
+        let module = Module::read_one(id.into_inner(), db)?;
+
+        let item = MutGalleryItem {
+            uuid: Some(Uuid::new_v4().to_string()),
+            module_uuid: module.uuid.clone(),
+            media_url: new.media_url.clone(),
+            caption: new.caption.clone(),
+            position: GalleryItem::next_position(module.uuid, db)?,
+        };
+
+        GalleryItem::create(&item, db)?;
+
+        Ok((item, module.page_uuid))
+    })
+    .await?;
+
+    publish.send(PublishJob::for_module(page_uuid));
+
+    Ok(HttpResponse::Created().json(item))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/modules/{id}/gallery/{item_id}",
+    tag = "galleries",
+    params(
+        ("id" = String, Path, description = "The module's uuid"),
+        ("item_id" = String, Path, description = "The item's uuid")
+    ),
+    responses((status = 200, description = "How many items were removed", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn remove_gallery_item(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (id, item_id) = path.into_inner();
+
+    let (res, page_uuid) = with_connection(pool, move |db| {
+        let item = GalleryItem::read_one(item_id.clone(), db)?;
+        if item.module_uuid != id {
+            return Err(CustomHttpError::NotFound);
+        }
+
+        let res = GalleryItem::delete(item_id, db)?;
+
+        Ok((res, Module::read_one(id, db)?.page_uuid))
+    })
+    .await?;
+
+    publish.send(PublishJob::for_module(page_uuid));
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Takes the uuids of every item in the gallery, in their new order.
+#[utoipa::path(
+    put,
+    path = "/api/v1/modules/{id}/gallery",
+    tag = "galleries",
+    params(("id" = String, Path, description = "The module's uuid")),
+    request_body = [String],
+    responses((status = 200, description = "The gallery's items in their new order", body = [GalleryItem])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn reorder_gallery(
+    order: web::Json<Vec<String>>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (items, page_uuid) = with_connection(pool, move |db| {
+        let mut current: Vec<String> = GalleryItem::read_for_module(id.clone(), db)?
+            .into_iter()
+            .map(|i| i.uuid)
+            .collect();
+        let mut requested = order.clone();
+        current.sort();
+        requested.sort();
+
+        // a partial order would leave the positions of the missing items ambiguous.
+        if current != requested {
+            return Err(CustomHttpError::BadRequest);
+        }
+
+        GalleryItem::reorder(id.clone(), order.into_inner(), db)?;
+
+        Ok((GalleryItem::read_for_module(id.clone(), db)?, Module::read_one(id.clone(), db)?.page_uuid))
+    })
+    .await?;
+
+    publish.send(PublishJob::for_module(page_uuid));
+
+    Ok(HttpResponse::Ok().json(items))
+}
@@ -0,0 +1,19 @@
+use actix_web::{web, HttpResponse};
+
+use crate::models::DbPool;
+use crate::services::auth_service::Claims;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::graphql_service::{RadicalSchema, Viewer};
+
+/// Runs a query against the pages and their modules, sent as `{ "query": "..", "variables": {..} }`.
+/// Errors in the query come back in the response's `errors`, as GraphQL clients expect, rather than as a problem.
+pub async fn graphql(
+    request: web::Json<async_graphql::Request>,
+    schema: web::Data<RadicalSchema>,
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let request = request.into_inner().data(Viewer::new(&claim)).data(pool);
+
+    Ok(HttpResponse::Ok().json(schema.execute(request).await))
+}
@@ -0,0 +1,193 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::controllers::module_controllers::{write_module, write_new_module, ModuleChange};
+use crate::controllers::page_controllers::{write_new_page, write_page, PageChange};
+use crate::models::config_models::LocalConfig;
+use crate::models::inbound_webhook_models::{InboundWebhook, MutInboundWebhook};
+use crate::models::page_models::PUBLISHED;
+use crate::models::{with_connection, DbConnection, DbPool, Model};
+use crate::services::auth_service::{generate_secret, Admin, RequireRole};
+use crate::services::body_limit_service::Body;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::inbound_webhook_service::{self, Mapped, Transform, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use crate::services::pagination_service::{list, PageQuery};
+use crate::services::plugin_service::Plugins;
+use crate::services::publish_service::PublishQueue;
+use crate::services::route_service::RouteTable;
+use crate::services::webhook_service::WebhookQueue;
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewInboundWebhook {
+    pub name: String,
+    pub target: String,
+    pub transform: Transform,
+}
+
+/// The only time the secret is ever sent back.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedInboundWebhook {
+    #[serde(flatten)]
+    pub webhook: InboundWebhook,
+    pub secret: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/inbound_webhooks",
+    tag = "inbound webhooks",
+    request_body = NewInboundWebhook,
+    responses((status = 201, description = "The webhook, with the secret deliveries have to be signed with, which is only ever sent this once", body = CreatedInboundWebhook)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_inbound_webhook(
+    new: web::Json<NewInboundWebhook>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    inbound_webhook_service::validate(&new.target, &new.transform)?;
+
+    let new_webhook = MutInboundWebhook {
+        uuid: Uuid::new_v4().to_string(),
+        name: new.name.clone(),
+        secret: generate_secret(),
+        target: new.target.clone(),
+        transform: serde_json::to_string(&new.transform).map_err(|_| CustomHttpError::BadRequest)?,
+    };
+
+    let secret = new_webhook.secret.clone();
+    let webhook = with_connection(pool, move |db| {
+        InboundWebhook::create(&new_webhook, db)?;
+
+        Ok(InboundWebhook::read_one(new_webhook.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(CreatedInboundWebhook { webhook, secret }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/inbound_webhooks",
+    tag = "inbound webhooks",
+    params(PageQuery),
+    responses((status = 200, description = "Every inbound webhook, or one page of them when `page` or `per_page` is set", body = [InboundWebhook])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_inbound_webhooks(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let webhooks = with_connection(pool, move |db| {
+        list(&query, db, InboundWebhook::read_all, InboundWebhook::read_paginated)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/inbound_webhooks/{id}",
+    tag = "inbound webhooks",
+    params(("id" = String, Path, description = "The webhook's uuid")),
+    responses((status = 200, description = "How many webhooks were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_inbound_webhook(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| Ok(InboundWebhook::delete(id.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// What has to be told about what a delivery wrote.
+enum Change {
+    Page(PageChange),
+    Module(ModuleChange),
+}
+
+/// Writes the page or module the way the API does, returning its uuid.
+fn write(mapped: Mapped, conf: &LocalConfig, plugins: &Plugins, db: &DbConnection) -> Result<(String, Change), CustomHttpError> {
+    match mapped {
+        Mapped::Page(page, Some(id)) => {
+            let (_, _, change) = write_page(id.clone(), |_| Ok(page), plugins, db)?;
+            Ok((id, Change::Page(change)))
+        }
+        Mapped::Page(mut page, None) => {
+            let id = Uuid::new_v4().to_string();
+            page.uuid = Some(id.clone());
+            // the content comes from a system of its own, which has already decided it should be live.
+            page.status = Some(String::from(PUBLISHED));
+            let (_, _, change) = write_new_page(page, conf, plugins, db)?;
+            Ok((id, Change::Page(change)))
+        }
+        Mapped::Module(module, Some(id)) => {
+            let (_, _, change) = write_module(id.clone(), |_| Ok(module), plugins, db)?;
+            Ok((id, Change::Module(change)))
+        }
+        Mapped::Module(module, None) => {
+            let (created, _, change) = write_new_module(module, conf, plugins, db)?;
+            Ok((created.uuid.unwrap_or_default(), Change::Module(change)))
+        }
+    }
+}
+
+/// Called by the external system. Needs no login, as the signature proves the payload came from whoever holds the secret.
+#[utoipa::path(
+    post,
+    path = "/api/v1/inbound_webhooks/{id}/receive",
+    tag = "inbound webhooks",
+    params(
+        ("id" = String, Path, description = "The webhook's uuid"),
+        ("X-Radical-Timestamp" = i64, Header, description = "When the delivery was signed, in seconds since the epoch"),
+        ("X-Radical-Signature" = String, Header, description = "`sha256=` and the hex HMAC of `timestamp.body`, keyed with the webhook's secret")
+    ),
+    request_body(content = Object, description = "The external system's payload, which the webhook's transform maps onto a page or module"),
+    responses((status = 200, description = "The uuid of the page or module that was written", body = String))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn receive_inbound_webhook(
+    req: HttpRequest,
+    body: Body,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+) -> Result<HttpResponse, CustomHttpError> {
+    // unknown webhooks are reported the same as bad signatures so that ids can't be probed.
+    let webhook = with_connection(pool.clone(), move |db| {
+        InboundWebhook::read_one(id.into_inner(), db).map_err(|_| CustomHttpError::Unauthorized)
+    })
+    .await?;
+
+    let header = |name| req.headers().get(name).and_then(|s| s.to_str().ok());
+    let now = chrono::Utc::now().timestamp();
+    inbound_webhook_service::verify_signature(&webhook.secret, header(TIMESTAMP_HEADER), &body, header(SIGNATURE_HEADER), now)?;
+
+    let payload: serde_json::Value = serde_json::from_slice(&body).map_err(|_| CustomHttpError::BadRequest)?;
+
+    let conf = conf.get_ref().clone();
+    let (uuid, change) = with_connection(pool, move |db| {
+        let mapped = inbound_webhook_service::map(&webhook, &payload, db)?;
+
+        write(mapped, &conf, &plugins, db)
+    })
+    .await?;
+
+    match change {
+        Change::Page(change) => change.announce(&publish, &routes, &webhooks),
+        Change::Module(change) => change.announce(&publish, &webhooks),
+    }
+
+    Ok(HttpResponse::Ok().json(uuid))
+}
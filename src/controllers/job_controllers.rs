@@ -0,0 +1,53 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::models::job_models::{Job, STATUSES};
+use crate::models::{with_connection, DbPool};
+use crate::services::auth_service::{Admin, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{Listing, PageQuery, Paginated};
+
+#[derive(Debug, Deserialize, Clone, IntoParams)]
+pub struct JobQuery {
+    /// Only the jobs of the status, like `failed` for the ones that were given up on.
+    pub status: Option<String>,
+    /// Only the jobs of the kind, like `webhook.deliver`.
+    pub kind: Option<String>,
+}
+
+/// The background jobs that are waiting, running, or failed, next due first, for admins to see what is behind.
+/// Jobs that succeeded are gone, and what a job was given isn't shown.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs",
+    tag = "jobs",
+    params(JobQuery, PageQuery),
+    responses((status = 200, description = "The jobs, or one page of them when `page` or `per_page` is set", body = [Job])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_jobs(
+    filter: web::Query<JobQuery>,
+    query: web::Query<PageQuery>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let JobQuery { status, kind } = filter.into_inner();
+    if status.as_deref().is_some_and(|s| !STATUSES.contains(&s)) {
+        return Err(CustomHttpError::BadRequest);
+    }
+
+    let jobs = with_connection(pool, move |db| {
+        if !query.is_requested() {
+            return Ok(Listing::All(Job::read_filtered(status.as_deref(), kind.as_deref(), db)?));
+        }
+
+        let (offset, limit) = query.window()?;
+        let (data, total) = Job::read_filtered_paginated(status.as_deref(), kind.as_deref(), offset, limit, db)?;
+
+        Ok(Listing::Page(Paginated::new(data, total, &query)))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(jobs))
+}
@@ -0,0 +1,50 @@
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use serde::Deserialize;
+
+use crate::services::auth_service::{Editor, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::live_service::{EventStreams, LiveHub, LiveSocket};
+use crate::services::site_service;
+
+/// `?subscribe=page:<uuid>,module` subscribes the socket to those topics as it connects.
+#[derive(Deserialize)]
+pub struct LiveQuery {
+    pub subscribe: Option<String>,
+}
+
+/// Events carry drafts and hidden modules whole, so the socket is only opened for editors, who can read all of them anyway.
+/// Browsers log in with the session cookie.
+pub async fn live_socket(
+    req: HttpRequest,
+    stream: web::Payload,
+    query: web::Query<LiveQuery>,
+    hub: web::Data<LiveHub>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let topics = query
+        .subscribe
+        .iter()
+        .flat_map(|topics| topics.split(','))
+        .map(str::trim)
+        .filter(|topic| !topic.is_empty())
+        .map(String::from)
+        .collect();
+
+    let socket = LiveSocket {
+        site: site_service::current_or_default(),
+        hub: hub.clone(),
+        topics,
+    };
+
+    ws::start(socket, &req, stream).map_err(|_| CustomHttpError::BadRequest)
+}
+
+/// Server-sent events of every change to content, for clients that only listen. Like `/ws`, it is only for editors.
+pub async fn event_stream(streams: web::Data<EventStreams>, _: RequireRole<Editor>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .streaming(streams.open())
+}
@@ -0,0 +1,182 @@
+use std::fs;
+
+use actix_multipart::Multipart;
+use actix_web::error::BlockingError;
+use actix_web::http::{header, HeaderValue};
+use actix_web::{web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::models::config_models::LocalConfig;
+use crate::models::media_models::{Media, MutMedia};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Claims, Editor, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::job_service::{JobQueue, NewJob};
+use crate::services::media_service::{self, ResizeQuery};
+use crate::services::media_store_service::{staging_path, MediaStore};
+use crate::services::pagination_service::{list, PageQuery};
+use crate::services::quota_service;
+use crate::services::static_service;
+
+/// A file's uuid never points at anything else, so it can be cached for as long as browsers allow.
+const MEDIA_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Runs `f` on the blocking thread pool, as stores and resizing work synchronously.
+async fn blocking<T, F>(f: F) -> Result<T, CustomHttpError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, CustomHttpError> + Send + 'static,
+{
+    web::block(f).await.map_err(|e| match e {
+        BlockingError::Error(e) => e,
+        BlockingError::Canceled => CustomHttpError::Unknown,
+    })
+}
+
+/// Takes a `multipart/form-data` form with the file in any field, using the first one that is a file.
+#[utoipa::path(
+    post,
+    path = "/api/v1/media",
+    tag = "media",
+    request_body(content = String, content_type = "multipart/form-data", description = "The file, as a field with a filename"),
+    responses((status = 201, description = "What was saved about the file", body = Media)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn upload_media(
+    form: Multipart,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    store: web::Data<dyn MediaStore>,
+    jobs: web::Data<JobQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let uuid = Uuid::new_v4().to_string();
+    let path = staging_path(&conf)?;
+    let upload = media_service::receive(form, &path, conf.media_max_size).await?;
+
+    let (quota_conf, size) = (conf.get_ref().clone(), upload.size);
+    let within_quota = with_connection(pool.clone(), move |db| quota_service::check_media(&quota_conf, size, db)).await;
+    if within_quota.is_err() {
+        fs::remove_file(&path).ok();
+    }
+    within_quota?;
+
+    let (key, mime_type, put_store) = (uuid.clone(), upload.mime_type.clone(), store.clone());
+    blocking(move || {
+        let stored = put_store.put(&key, &path, &mime_type);
+        if stored.is_err() {
+            fs::remove_file(&path).ok();
+        }
+
+        Ok(stored?)
+    })
+    .await?;
+
+    let new = MutMedia {
+        uuid: uuid.clone(),
+        filename: upload.filename,
+        mime_type: upload.mime_type,
+        size: upload.size,
+        checksum: upload.checksum,
+    };
+    let id = uuid.clone();
+    let saved = with_connection(pool, move |db| {
+        Media::create(&new, db)?;
+
+        Ok(Media::read_one(id, db)?)
+    })
+    .await;
+
+    // a file without a row would never be found or deleted.
+    if saved.is_err() {
+        blocking(move || media_service::remove(&**store, &uuid)).await.ok();
+    }
+    let saved = saved?;
+    if media_service::has_presets(&conf, &saved) {
+        jobs.send(NewJob::new(media_service::JOB, serde_json::json!({ "media": saved.uuid })));
+    }
+
+    Ok(HttpResponse::Created().json(saved))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/media",
+    tag = "media",
+    params(PageQuery),
+    responses((status = 200, description = "Every upload, newest first, or one page of them when `page` or `per_page` is set", body = [Media]))
+)]
+pub async fn get_media_list(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let media = with_connection(pool, move |db| list(&query, db, Media::read_all, Media::read_paginated)).await?;
+
+    Ok(HttpResponse::Ok().json(media))
+}
+
+/// Serves the file with the type it was uploaded with, or an image resized with `w`, `h`, and `fit`, which for clients
+/// that aren't logged in is one of `APP_MEDIA_PRESETS`, or rounded up to one of a few sizes when there are none.
+/// Uploads are run in a sandbox when they are opened, so that an HTML or SVG file can't act as the site.
+#[utoipa::path(
+    get,
+    path = "/api/v1/media/{id}",
+    tag = "media",
+    params(("id" = String, Path, description = "The upload's uuid"), ResizeQuery),
+    responses((status = 200, description = "The file"))
+)]
+pub async fn get_media(
+    req: HttpRequest,
+    id: web::Path<String>,
+    query: web::Query<ResizeQuery>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    store: web::Data<dyn MediaStore>,
+    claim: Option<Claims>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let query = match (claim, query.is_requested()) {
+        (None, true) => media_service::for_visitor(&conf, &query)?,
+        _ => query.into_inner(),
+    };
+    let media = with_connection(pool, move |db| Ok(Media::read_one(id.into_inner(), db)?)).await?;
+    let content_type = media.mime_type.parse().ok();
+
+    // resizing, or reading the file from a store that isn't local, is kept off of the workers.
+    let path = blocking(move || match query.is_requested() {
+        true => media_service::variant(&**store, &conf, &media, &query).map(Some),
+        false => Ok(store.fetch(&media.uuid)?),
+    })
+    .await?;
+    let mut res = static_service::serve_as(&req, path, content_type, MEDIA_CACHE_CONTROL)?;
+
+    let headers = res.headers_mut();
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static("sandbox"));
+
+    Ok(res)
+}
+
+/// Removes the upload, its file, and its resized copies. Modules that link to it are left as they are.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/media/{id}",
+    tag = "media",
+    params(("id" = String, Path, description = "The upload's uuid")),
+    responses((status = 200, description = "How many uploads were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_media(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    store: web::Data<dyn MediaStore>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let uuid = id.clone();
+    let res = with_connection(pool, move |db| Ok(Media::delete(id.into_inner(), db)?)).await?;
+
+    if res > 0 {
+        blocking(move || media_service::remove(&**store, &uuid)).await?;
+    }
+
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,297 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::models::menu_models::{Menu, MenuItem, MutMenu, MutMenuItem, NewMenu, NewMenuItem};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Editor, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::menu_service::{check_item, tree};
+use crate::services::page_cache_service::PageCache;
+use crate::services::pagination_service::{list, PageQuery};
+use crate::services::validation_service::Validate;
+
+// Every page shows every menu, so cached pages are all dropped when one changes.
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/menus",
+    tag = "menus",
+    request_body = NewMenu,
+    responses((status = 201, description = "The menu as it was saved", body = Menu)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_menu(
+    new: web::Json<NewMenu>,
+    pool: web::Data<DbPool>,
+    pages: web::Data<PageCache>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    new.validate()?;
+
+    let new_menu = MutMenu {
+        uuid: Uuid::new_v4().to_string(),
+        name: new.into_inner().name,
+    };
+    let menu = with_connection(pool, move |db| {
+        Menu::create(&new_menu, db)?;
+
+        Ok(Menu::read_one(new_menu.uuid, db)?)
+    })
+    .await?;
+    pages.clear();
+
+    Ok(HttpResponse::Created().json(menu))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/menus",
+    tag = "menus",
+    params(PageQuery),
+    responses((status = 200, description = "Every menu by name, or one page of them when `page` or `per_page` is set", body = [Menu]))
+)]
+pub async fn get_menus(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let menus = with_connection(pool, move |db| list(&query, db, Menu::read_all, Menu::read_paginated)).await?;
+
+    Ok(HttpResponse::Ok().json(menus))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/menus/{id}",
+    tag = "menus",
+    params(("id" = String, Path, description = "The menu's uuid")),
+    responses((status = 200, description = "The menu with its items nested", body = MenuTree))
+)]
+pub async fn get_menu(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let menu = with_connection(pool, move |db| Ok(tree(Menu::read_one(id.into_inner(), db)?, db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(menu))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/menus/{id}",
+    tag = "menus",
+    params(("id" = String, Path, description = "The menu's uuid")),
+    request_body = NewMenu,
+    responses((status = 200, description = "The menu as it was saved", body = Menu)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_menu(
+    updated: web::Json<NewMenu>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    pages: web::Data<PageCache>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    updated.validate()?;
+
+    let updated = MutMenu {
+        uuid: id.into_inner(),
+        name: updated.into_inner().name,
+    };
+    let menu = with_connection(pool, move |db| {
+        Menu::update(updated.uuid.clone(), &updated, db)?;
+
+        Ok(Menu::read_one(updated.uuid, db)?)
+    })
+    .await?;
+    pages.clear();
+
+    Ok(HttpResponse::Ok().json(menu))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/menus/{id}",
+    tag = "menus",
+    params(("id" = String, Path, description = "The menu's uuid")),
+    responses((status = 200, description = "How many menus were deleted, along with their items", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_menu(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    pages: web::Data<PageCache>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| Ok(Menu::delete(id.into_inner(), db)?)).await?;
+    pages.clear();
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Adds an item to the end of the menu, or of the item it is nested under.
+#[utoipa::path(
+    post,
+    path = "/api/v1/menus/{id}/items",
+    tag = "menus",
+    params(("id" = String, Path, description = "The menu's uuid")),
+    request_body = NewMenuItem,
+    responses((status = 201, description = "The item as it was saved", body = MenuItem)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn add_menu_item(
+    new: web::Json<NewMenuItem>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    pages: web::Data<PageCache>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    new.validate()?;
+
+    let item = with_connection(pool, move |db| {
+        let menu = Menu::read_one(id.into_inner(), db)?;
+        check_item(&menu.uuid, None, &new, db)?;
+
+        let new = new.into_inner();
+        let item = MutMenuItem {
+            uuid: Uuid::new_v4().to_string(),
+            menu_uuid: menu.uuid.clone(),
+            parent_item: new.parent_item,
+            title: new.title,
+            page_uuid: new.page_uuid,
+            url: new.url,
+            position: MenuItem::next_position(menu.uuid, db)?,
+        };
+        MenuItem::create(&item, db)?;
+
+        Ok(MenuItem::read_one(item.uuid, db)?)
+    })
+    .await?;
+    pages.clear();
+
+    Ok(HttpResponse::Created().json(item))
+}
+
+/// Takes the uuids of every item in the menu, in their new order. Items nested under the same item keep that order among themselves.
+#[utoipa::path(
+    put,
+    path = "/api/v1/menus/{id}/items",
+    tag = "menus",
+    params(("id" = String, Path, description = "The menu's uuid")),
+    request_body = [String],
+    responses((status = 200, description = "The menu with its items in their new order", body = MenuTree)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn reorder_menu(
+    order: web::Json<Vec<String>>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    pages: web::Data<PageCache>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let menu = with_connection(pool, move |db| {
+        let menu = Menu::read_one(id.into_inner(), db)?;
+        let mut current: Vec<String> = MenuItem::read_for_menu(menu.uuid.clone(), db)?
+            .into_iter()
+            .map(|i| i.uuid)
+            .collect();
+        let mut requested = order.clone();
+        current.sort();
+        requested.sort();
+
+        // a partial order would leave the positions of the missing items ambiguous.
+        if current != requested {
+            return Err(CustomHttpError::BadRequest);
+        }
+
+        MenuItem::reorder(menu.uuid.clone(), order.into_inner(), db)?;
+
+        Ok(tree(menu, db)?)
+    })
+    .await?;
+    pages.clear();
+
+    Ok(HttpResponse::Ok().json(menu))
+}
+
+/// Changes what an item links to, its title, or what it is nested under. It keeps its position.
+#[utoipa::path(
+    put,
+    path = "/api/v1/menus/{id}/items/{item_id}",
+    tag = "menus",
+    params(
+        ("id" = String, Path, description = "The menu's uuid"),
+        ("item_id" = String, Path, description = "The item's uuid")
+    ),
+    request_body = NewMenuItem,
+    responses((status = 200, description = "The item as it was saved", body = MenuItem)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_menu_item(
+    updated: web::Json<NewMenuItem>,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    pages: web::Data<PageCache>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    updated.validate()?;
+    let (id, item_id) = path.into_inner();
+
+    let item = with_connection(pool, move |db| {
+        let item = MenuItem::read_one(item_id, db)?;
+        if item.menu_uuid != id {
+            return Err(CustomHttpError::NotFound);
+        }
+        check_item(&id, Some(&item.uuid), &updated, db)?;
+
+        let updated = updated.into_inner();
+        let changed = MutMenuItem {
+            uuid: item.uuid.clone(),
+            menu_uuid: item.menu_uuid,
+            parent_item: updated.parent_item,
+            title: updated.title,
+            page_uuid: updated.page_uuid,
+            url: updated.url,
+            position: item.position,
+        };
+        MenuItem::update(item.uuid.clone(), &changed, db)?;
+
+        Ok(MenuItem::read_one(item.uuid, db)?)
+    })
+    .await?;
+    pages.clear();
+
+    Ok(HttpResponse::Ok().json(item))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/menus/{id}/items/{item_id}",
+    tag = "menus",
+    params(
+        ("id" = String, Path, description = "The menu's uuid"),
+        ("item_id" = String, Path, description = "The item's uuid")
+    ),
+    responses((status = 200, description = "How many items were deleted, including the ones nested under it", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn remove_menu_item(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    pages: web::Data<PageCache>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (id, item_id) = path.into_inner();
+
+    let res = with_connection(pool, move |db| {
+        let item = MenuItem::read_one(item_id.clone(), db)?;
+        if item.menu_uuid != id {
+            return Err(CustomHttpError::NotFound);
+        }
+
+        Ok(MenuItem::delete(item_id, db)?)
+    })
+    .await?;
+    pages.clear();
+
+    Ok(HttpResponse::Ok().json(res))
+}
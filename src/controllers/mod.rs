@@ -1,4 +1,40 @@
 pub mod module_controllers;
 pub mod page_controllers;
+pub mod api_key_controllers;
 pub mod category_controllers;
-pub mod user_controllers;
\ No newline at end of file
+pub mod comment_controllers;
+pub mod content_type_controllers;
+pub mod delivery_controllers;
+pub mod delivery_token_controllers;
+pub mod export_controllers;
+pub mod form_controllers;
+pub mod feed_controllers;
+pub mod gallery_controllers;
+pub mod graphql_controllers;
+pub mod inbound_webhook_controllers;
+pub mod job_controllers;
+pub mod live_controllers;
+pub mod media_controllers;
+pub mod menu_controllers;
+pub mod oidc_controllers;
+pub mod openapi_controllers;
+pub mod preview_controllers;
+pub mod privacy_controllers;
+pub mod quota_controllers;
+pub mod redirect_controllers;
+pub mod render_controllers;
+pub mod revision_controllers;
+pub mod rollout_controllers;
+pub mod search_controllers;
+pub mod session_controllers;
+pub mod setting_controllers;
+pub mod signing_key_controllers;
+pub mod site_controllers;
+pub mod sitemap_controllers;
+pub mod static_controllers;
+pub mod system_controllers;
+pub mod taxonomy_controllers;
+pub mod theme_controllers;
+pub mod trash_controllers;
+pub mod user_controllers;
+pub mod webhook_controllers;
\ No newline at end of file
@@ -1,77 +1,510 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use uuid::Uuid;
 
-use crate::models::{Model, MySQLPool, pool_handler};
-use crate::models::module_models::{Module, ModuleCategory, MutModule};
+use crate::models::config_models::LocalConfig;
+use crate::models::{with_connection, DbConnection, DbPool, Model};
+use crate::models::module_models::{Module, ModuleCategory, ModuleType, MutModule, PatchModule};
 
-use crate::services::auth_service::Claims;
+use crate::services::auth_service::{Claims, Editor, RequireRole};
+use crate::services::content_type_service;
+use crate::services::bulk_service::{self, BulkOperation, BulkResult};
+use crate::services::duplicate_service::{self, DuplicateWarning, WithWarnings};
+use crate::services::encryption_service::{encrypt_content, reveal_module, reveal_modules, reveal_written, reveals_to};
 use crate::services::errors_service::CustomHttpError;
+use crate::services::jsonapi_service::{respond, respond_listing, respond_many};
+use crate::services::pagination_service::{list, PageQuery};
+use crate::services::plugin_service::Plugins;
+use crate::services::publish_service::{PublishJob, PublishQueue};
+use crate::services::quota_service;
+use crate::services::sanitize_service;
+use crate::services::trash_service::DeleteQuery;
+use crate::services::validation_service::Validate;
+use crate::services::version_service::{Precondition, Versioned};
+use crate::services::visibility_service::{require_viewable_module, require_visible, strip_hidden_pages, strip_modules, viewable, Role};
+use crate::services::webhook_service::{WebhookEvent, WebhookQueue};
 
+/// Webhooks are told about a module the way visitors see it, so the content of an encrypted one isn't sent.
+fn module_event(event: &'static str, mut module: Module) -> WebhookEvent {
+    if module.encrypted {
+        module.content = String::new();
+    }
+
+    WebhookEvent::new(event, module)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/modules",
+    tag = "modules",
+    request_body = MutModule,
+    responses((status = 201, description = "The module that was made", body = ModuleWithWarnings)),
+    security(("token" = []), ("session" = []))
+)]
 pub async fn create_module(
     new: web::Json<MutModule>,
-    pool: web::Data<MySQLPool>,
-    _: Claims
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    publish: web::Data<PublishQueue>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    editor: RequireRole<Editor>
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    new.validate()?;
 
-    let mut uuid_new = new.clone();
-    uuid_new.uuid = Some(Uuid::new_v4().to_string());
+    let reveals = reveals_to(Some(&editor.0));
+    let conf = conf.get_ref().clone();
+    let created = new.into_inner();
+    let (uuid_new, warnings, change) =
+        with_connection(pool, move |db| write_new_module(created, &conf, &plugins, db)).await?;
+    change.announce(&publish, &webhooks);
 
-    Module::create(&uuid_new, &mysql_pool)?;
+    Ok(HttpResponse::Created().json(WithWarnings::new(reveal_written(uuid_new, reveals), warnings)))
+}
 
-    Ok(HttpResponse::Created().json(uuid_new))
+/// What has to be told about a module once it is saved, which is kept until then, as a bulk change may still be rolled back.
+pub enum ModuleChange {
+    Created(Module),
+    Updated { old_page: Option<String>, module: Module },
+    Deleted(Module),
 }
 
-pub async fn get_modules(pool: web::Data<MySQLPool>) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
-    let modules = Module::read_all(&mysql_pool)?;
+impl ModuleChange {
+    pub fn announce(self, publish: &PublishQueue, webhooks: &WebhookQueue) {
+        match self {
+            Self::Created(module) => {
+                publish.send(PublishJob::for_module(module.page_uuid.clone()));
+                webhooks.send(module_event("module.created", module));
+            }
+            Self::Updated { old_page, module } => {
+                if old_page != module.page_uuid {
+                    publish.send(PublishJob::for_module(old_page));
+                }
+                publish.send(PublishJob::for_module(module.page_uuid.clone()));
+                webhooks.send(module_event("module.updated", module));
+            }
+            Self::Deleted(module) => {
+                webhooks.send(WebhookEvent::deleted("module.deleted", &module.uuid));
+                publish.send(PublishJob::for_module(module.page_uuid));
+            }
+        }
+    }
+}
 
-    Ok(HttpResponse::Created().json(modules))
+/// Writes a new module under a new uuid, checking its content is of its type. The module it returns has its content as
+/// it was saved, sanitized but not encrypted.
+pub fn write_new_module(
+    mut created: MutModule,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(MutModule, Vec<DuplicateWarning>, ModuleChange), CustomHttpError> {
+    created.uuid = Some(Uuid::new_v4().to_string());
+
+    write_new_module_as(created, conf, plugins, db)
 }
 
+/// Writes a new module under the uuid it was given, like an imported one keeps the uuid it was exported with.
+pub fn write_new_module_as(
+    mut created: MutModule,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(MutModule, Vec<DuplicateWarning>, ModuleChange), CustomHttpError> {
+    plugins.before_save_module(&mut created)?;
+
+    let module_type = created.module_type.as_deref().unwrap_or(ModuleType::Text.as_str());
+    content_type_service::check_content(module_type, &created.content, db)?;
+
+    quota_service::check_modules(conf, db)?;
+
+    let warnings = duplicate_service::check_module(&created, None, db)?;
+
+    // before it is encrypted, after which it can't be, and so that what is returned is what was saved.
+    created.content = sanitize_service::on_save(module_type, &created.content);
+    let mut stored = created.clone();
+    if stored.encrypted {
+        stored.content = encrypt_content(&stored.content)?;
+    }
+
+    Module::create(&stored, db)?;
+    let module = Module::read_one(created.uuid.clone().unwrap(), db)?;
+
+    Ok((created, warnings, ModuleChange::Created(module)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/modules",
+    tag = "modules",
+    params(PageQuery),
+    responses((status = 201, description = "Every one, or one page of them as `{ data, page, per_page, total, total_pages }` when `page` or `per_page` is set, leaving out the ones hidden from the client", body = [Module])),
+    security((), ("token" = []), ("session" = []))
+)]
+pub async fn get_modules(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let modules = with_connection(pool, move |db| {
+        let visibilities = viewable(&claim);
+        list(&query, db, Module::read_all, Module::read_paginated)?.try_map(|modules| {
+            let modules = strip_hidden_pages(strip_modules(modules, Role::of(&claim)), visibilities.as_deref(), db)?;
+
+            Ok(sanitize_service::on_read(&claim, reveal_modules(modules, reveals_to(claim.as_ref()))?))
+        })
+    })
+    .await?;
+
+    Ok(respond_listing(&req, HttpResponse::Created(), modules))
+}
+
+/// The modules that aren't on any page, like a footer or an announcement banner, which templates get under `globals`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/modules/global",
+    tag = "modules",
+    responses((status = 200, description = "Every global module in its order, leaving out the ones hidden from the client", body = [Module])),
+    security((), ("token" = []), ("session" = []))
+)]
+pub async fn get_global_modules(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let modules = with_connection(pool, move |db| {
+        let modules = reveal_modules(strip_modules(Module::read_global(db)?, Role::of(&claim)), reveals_to(claim.as_ref()))?;
+
+        Ok(sanitize_service::on_read(&claim, modules))
+    })
+    .await?;
+
+    Ok(respond_many(&req, HttpResponse::Ok(), modules))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/modules/{id}",
+    tag = "modules",
+    params(("id" = String, Path, description = "The module's uuid")),
+    responses((status = 201, description = "The module", body = Module)),
+    security((), ("token" = []), ("session" = []))
+)]
 pub async fn get_module(
+    req: HttpRequest,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    let module = with_connection(pool, move |db| {
+        let module = require_visible(Module::read_one(id.into_inner(), db)?, Role::of(&claim))?;
+        require_viewable_module(&module, &claim, db)?;
 
-    let module = Module::read_one(id.clone(), &mysql_pool)?;
+        Ok(sanitize_service::on_read_module(&claim, reveal_module(module, reveals_to(claim.as_ref()))?))
+    })
+    .await?;
 
-    Ok(HttpResponse::Created().json(module))
+    Ok(respond(&req, HttpResponse::Created(), module))
 }
 
+/// Has to be sent with the `version` of the module it was made from, as `If-Match` or in the body, and is refused with a 409
+/// when the module was changed since.
+#[utoipa::path(
+    put,
+    path = "/api/v1/modules/{id}",
+    tag = "modules",
+    params(
+        ("id" = String, Path, description = "The module's uuid"),
+        ("If-Match" = Option<String>, Header, description = "The `version` the update was made from, like `\"3\"`, or `*` for any")
+    ),
+    request_body(content = MutModule, description = "The module, along with the `version` it was made from unless `If-Match` is sent"),
+    responses(
+        (status = 201, description = "The module as it was saved", body = ModuleWithWarnings),
+        (status = 409, description = "The module was changed since the version"),
+        (status = 428, description = "Neither `If-Match` nor `version` was sent")
+    ),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_module(
-    updated_module: web::Json<MutModule>,
+    req: HttpRequest,
+    updated_module: web::Json<Versioned<MutModule>>,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
-    _: Claims
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    editor: RequireRole<Editor>
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    let Versioned { body: updated, version } = updated_module.into_inner();
+    updated.validate()?;
+    let precondition = Precondition::of(&req, version)?;
 
-    Module::update(id.clone(), &updated_module, &mysql_pool)?;
+    let reveals = reveals_to(Some(&editor.0));
+    save_module(id.into_inner(), reveals, pool, publish, webhooks, plugins, move |old_module| {
+        precondition.check(old_module.version)?;
 
-    Ok(HttpResponse::Created().json(updated_module.0))
+        Ok(updated)
+    })
+    .await
 }
 
-pub async fn delete_module(
+/// Changes only the fields that are sent, like the content, leaving the rest of the module as it is.
+/// Like a `PUT`, it needs the `version` it was made from.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/modules/{id}",
+    tag = "modules",
+    params(
+        ("id" = String, Path, description = "The module's uuid"),
+        ("If-Match" = Option<String>, Header, description = "The `version` the update was made from, like `\"3\"`, or `*` for any")
+    ),
+    request_body(content = PatchModule, description = "The fields to change, along with the `version` they were changed from unless `If-Match` is sent"),
+    responses(
+        (status = 201, description = "The module as it was saved", body = ModuleWithWarnings),
+        (status = 409, description = "The module was changed since the version"),
+        (status = 428, description = "Neither `If-Match` nor `version` was sent")
+    ),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn patch_module(
+    req: HttpRequest,
+    patch: web::Json<Versioned<PatchModule>>,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
-    _: Claims
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    editor: RequireRole<Editor>
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    let Versioned { body: patch, version } = patch.into_inner();
+    let precondition = Precondition::of(&req, version)?;
+
+    let reveals = reveals_to(Some(&editor.0));
+    save_module(id.into_inner(), reveals, pool, publish, webhooks, plugins, move |old_module| {
+        precondition.check(old_module.version)?;
+        // content that isn't sent is kept, and is encrypted again below if the module still is. It is only decrypted
+        // for that, and is blanked in what is sent back unless the editor could read it anyway.
+        let updated = patch.apply(&reveal_module(old_module.clone(), true)?);
+        updated.validate()?;
+
+        Ok(updated)
+    })
+    .await
+}
+
+/// Writes the change, and responds with the module as it was saved, with encrypted content only revealed if `reveals`.
+async fn save_module<F>(
+    id: String,
+    reveals: bool,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    change: F,
+) -> Result<HttpResponse, CustomHttpError>
+where
+    F: FnOnce(&Module) -> Result<MutModule, CustomHttpError> + Send + 'static,
+{
+    let (updated_module, warnings, change) =
+        with_connection(pool, move |db| write_module(id, change, &plugins, db)).await?;
+    change.announce(&publish, &webhooks);
+
+    Ok(HttpResponse::Created().json(WithWarnings::new(reveal_written(updated_module, reveals), warnings)))
+}
+
+/// Writes the update `change` makes to the module, checking its content is of its type.
+pub fn write_module<F>(
+    id: String,
+    change: F,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(MutModule, Vec<DuplicateWarning>, ModuleChange), CustomHttpError>
+where
+    F: FnOnce(&Module) -> Result<MutModule, CustomHttpError>,
+{
+    let old_module = Module::read_one(id.clone(), db)?;
+    let mut updated_module = change(&old_module)?;
+    plugins.before_save_module(&mut updated_module)?;
+    let mut encrypted_module = updated_module.clone();
+
+    let module_type = encrypted_module.module_type.as_deref().unwrap_or(&old_module.module_type);
+    content_type_service::check_content(module_type, &encrypted_module.content, db)?;
 
-    let res = Module::delete(id.clone(), &mysql_pool)?;
+    let warnings = duplicate_service::check_module(&encrypted_module, Some(&id), db)?;
+
+    // before it is encrypted, after which it can't be, and so that what is returned is what was saved.
+    updated_module.content = sanitize_service::on_save(module_type, &updated_module.content);
+    encrypted_module.content = updated_module.content.clone();
+    if encrypted_module.encrypted {
+        encrypted_module.content = encrypt_content(&encrypted_module.content)?;
+    }
+
+    // refused rather than made over a change that was saved since the module was read above.
+    if Module::update_at(id.clone(), &encrypted_module, old_module.version, db)? == 0 {
+        return Err(CustomHttpError::Stale);
+    }
+    let module = Module::read_one(id, db)?;
+
+    let change = ModuleChange::Updated {
+        old_page: old_module.page_uuid,
+        module,
+    };
+
+    Ok((updated_module, warnings, change))
+}
+
+/// Moves the module to the trash, or removes it for good with `?permanent=true`, which works on modules in the trash as well.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/modules/{id}",
+    tag = "modules",
+    params(("id" = String, Path, description = "The module's uuid"), DeleteQuery),
+    responses((status = 201, description = "How many modules were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_module(
+    id: web::Path<String>,
+    query: web::Query<DeleteQuery>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    webhooks: web::Data<WebhookQueue>,
+    _: RequireRole<Editor>
+) -> Result<HttpResponse, CustomHttpError> {
+    let (res, change) = with_connection(pool, move |db| remove_module(id.into_inner(), query.permanent, db)).await?;
+    change.announce(&publish, &webhooks);
 
     Ok(HttpResponse::Created().json(res))
 }
 
+fn remove_module(id: String, permanent: bool, db: &DbConnection) -> Result<(usize, ModuleChange), CustomHttpError> {
+    if permanent {
+        let module = Module::read_any(id.clone(), db)?;
+
+        return Ok((Module::purge(id, db)?, ModuleChange::Deleted(module)));
+    }
+
+    let module = Module::read_one(id.clone(), db)?;
+
+    Ok((Module::delete(id, db)?, ModuleChange::Deleted(module)))
+}
+
+/// Creates, updates, and deletes modules in one go, the same way as `POST /api/v1/pages/bulk`.
+/// Either every operation is saved or, when one fails, none are, and the error's `index` says which one it was.
+#[utoipa::path(
+    post,
+    path = "/api/v1/modules/bulk",
+    tag = "modules",
+    request_body = [ModuleOperation],
+    responses((status = 200, description = "What each operation did, in the order they were sent", body = [BulkResult])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn bulk_modules(
+    operations: web::Json<Vec<BulkOperation<MutModule>>>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    publish: web::Data<PublishQueue>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    _: RequireRole<Editor>
+) -> Result<HttpResponse, CustomHttpError> {
+    let operations = operations.into_inner();
+    bulk_service::validate(&operations)?;
+
+    let conf = conf.get_ref().clone();
+    let (results, changes): (Vec<BulkResult>, Vec<ModuleChange>) = with_connection(pool, move |db| {
+        let done = bulk_service::run(operations, db, |operation| match operation {
+            BulkOperation::Create { data } => {
+                let (created, warnings, change) = write_new_module(data, &conf, &plugins, db)?;
+
+                Ok((created.uuid.unwrap_or_default(), warnings, change))
+            }
+            BulkOperation::Update { id, data, version } => {
+                let precondition = version.map_or(Precondition::Any, Precondition::Version);
+                let (_, warnings, change) = write_module(
+                    id.clone(),
+                    move |old_module| {
+                        precondition.check(old_module.version)?;
+
+                        Ok(data)
+                    },
+                    &plugins,
+                    db,
+                )?;
+
+                Ok((id, warnings, change))
+            }
+            BulkOperation::Delete { id, permanent } => {
+                let (_, change) = remove_module(id.clone(), permanent, db)?;
+
+                Ok((id, Vec::new(), change))
+            }
+        })?;
+
+        Ok(done.into_iter().unzip())
+    })
+    .await?;
+
+    for change in changes {
+        change.announce(&publish, &webhooks);
+    }
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// Takes a module that was trashed on its own out of the trash.
+#[utoipa::path(
+    post,
+    path = "/api/v1/modules/{id}/restore",
+    tag = "modules",
+    params(("id" = String, Path, description = "The module's uuid")),
+    responses((status = 200, description = "The module, out of the trash", body = Module)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn restore_module(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    webhooks: web::Data<WebhookQueue>,
+    editor: RequireRole<Editor>
+) -> Result<HttpResponse, CustomHttpError> {
+    let module = with_connection(pool, move |db| {
+        if Module::restore(vec![id.clone()], db)? == 0 {
+            return Err(CustomHttpError::NotFound);
+        }
+
+        Ok(Module::read_one(id.into_inner(), db)?)
+    })
+    .await?;
+
+    publish.send(PublishJob::for_module(module.page_uuid.clone()));
+    webhooks.send(module_event("module.created", module.clone()));
+
+    Ok(HttpResponse::Ok().json(reveal_module(module, reveals_to(Some(&editor.0)))?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/modules/category/{id}",
+    tag = "modules",
+    params(("id" = String, Path, description = "The category's uuid")),
+    responses((status = 201, description = "The modules of the category", body = [Module])),
+    security((), ("token" = []), ("session" = []))
+)]
 pub async fn get_module_category(
+    req: HttpRequest,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    let modules = with_connection(pool, move |db| {
+        let modules = strip_modules(ModuleCategory::join(id.into_inner(), db)?, Role::of(&claim));
+        let modules = strip_hidden_pages(modules, viewable(&claim).as_deref(), db)?;
 
-    let modules = ModuleCategory::join(id.clone(), &mysql_pool)?;
+        Ok(sanitize_service::on_read(&claim, reveal_modules(modules, reveals_to(claim.as_ref()))?))
+    })
+    .await?;
 
-    Ok(HttpResponse::Created().json(modules))
+    Ok(respond_many(&req, HttpResponse::Created(), modules))
 }
\ No newline at end of file
@@ -0,0 +1,113 @@
+use actix_session::Session;
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::error::BlockingError;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use super::user_controllers::log_in_as;
+use crate::models::config_models::LocalConfig;
+use crate::models::{with_primary_connection, DbPool};
+use crate::services::cache_service::CacheBackend;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::oidc_service::{self, OidcConfig, OidcError, STATE_COOKIE, STATE_TTL};
+use crate::services::session_service::{SessionData, SessionStore};
+
+#[derive(Deserialize, IntoParams)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set by the issuer instead of `code` when the login was refused or cancelled.
+    pub error: Option<String>,
+}
+
+/// The issuer is talked to on the blocking thread pool, as `ureq` is synchronous.
+fn blocking_error(e: BlockingError<OidcError>) -> CustomHttpError {
+    match e {
+        BlockingError::Error(e) => e.into(),
+        BlockingError::Canceled => CustomHttpError::Unknown,
+    }
+}
+
+/// The state cookie, which is sent back from the issuer's site, so it can't be `Strict` like the session cookie.
+fn state_cookie(state: &str, max_age: i64) -> Cookie<'_> {
+    Cookie::build(STATE_COOKIE, state)
+        .path("/auth/oidc")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(max_age))
+        .finish()
+}
+
+/// Sends the browser to the issuer to log in. A 404 when OIDC isn't configured.
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/login",
+    tag = "oidc",
+    responses((status = 302, description = "Sent on to the issuer, with the state of the login in a cookie"))
+)]
+pub async fn oidc_login(
+    conf: web::Data<LocalConfig>,
+    cache: web::Data<dyn CacheBackend>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let config = OidcConfig::from_conf(&conf).ok_or(CustomHttpError::NotFound)?;
+
+    let (url, state) = web::block(move || oidc_service::start_login(&config, &**cache))
+        .await
+        .map_err(blocking_error)?;
+
+    Ok(HttpResponse::Found()
+        .header("Location", url)
+        .cookie(state_cookie(&state, STATE_TTL.as_secs() as i64))
+        .finish())
+}
+
+/// Where the issuer sends the browser back to. The user is logged in with a session, the same as `POST /api/v1/session`,
+/// and sent on to `APP_OIDC_RETURN_URL`.
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/callback",
+    tag = "oidc",
+    params(CallbackQuery),
+    responses((status = 302, description = "Logged in with a session, and sent on to `APP_OIDC_RETURN_URL`"))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn oidc_callback(
+    req: HttpRequest,
+    query: web::Query<CallbackQuery>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    cache: web::Data<dyn CacheBackend>,
+    store: web::Data<dyn SessionStore>,
+    session: Session,
+) -> Result<HttpResponse, CustomHttpError> {
+    let config = OidcConfig::from_conf(&conf).ok_or(CustomHttpError::NotFound)?;
+    let (code, state) = match (&query.error, &query.code, &query.state) {
+        (None, Some(code), Some(state)) => (code.clone(), state.clone()),
+        _ => return Err(CustomHttpError::Unauthorized),
+    };
+
+    let browser_state = req.cookie(STATE_COOKIE).map(|c| c.value().to_string());
+    let claims = web::block(move || oidc_service::finish_login(&config, &**cache, &code, &state, browser_state.as_deref()))
+        .await
+        .map_err(blocking_error)?;
+
+    let create = conf.oidc_create_users;
+    let cookie = with_primary_connection(pool, move |db| {
+        let user = oidc_service::find_user(&claims, create, db)?;
+
+        log_in_as(user.username, db)
+    })
+    .await?;
+
+    store.remove(&session)?;
+    session.renew();
+    store.save(&session, &SessionData::new(cookie.value().to_string()))?;
+
+    let return_url = conf.oidc_return_url.clone().unwrap_or_else(|| String::from("/"));
+
+    Ok(HttpResponse::Found()
+        .header("Location", return_url)
+        .cookie(state_cookie("", 0))
+        .finish())
+}
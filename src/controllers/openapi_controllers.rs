@@ -0,0 +1,20 @@
+use actix_web::{web, HttpResponse};
+use utoipa::OpenApi;
+
+use crate::models::config_models::LocalConfig;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::openapi_service::{ApiDoc, SWAGGER_UI};
+
+/// The OpenAPI spec of the API, for generating clients and for Swagger UI.
+pub async fn get_openapi() -> Result<HttpResponse, CustomHttpError> {
+    Ok(HttpResponse::Ok().json(ApiDoc::openapi()))
+}
+
+/// Swagger UI for `/openapi.json`, which is only there with `APP_SWAGGER_UI` on.
+pub async fn get_swagger_ui(conf: web::Data<LocalConfig>) -> Result<HttpResponse, CustomHttpError> {
+    if !conf.swagger_ui {
+        return Err(CustomHttpError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().content_type("text/html").body(SWAGGER_UI))
+}
@@ -1,130 +1,1115 @@
+use std::collections::HashMap;
 use std::sync::Mutex;
 
+use actix_web::cookie::Cookie;
+use actix_web::http::header;
 use actix_web::{web, HttpResponse};
+use diesel::{Connection, OptionalExtension};
 use handlebars::Handlebars;
+use time::Duration;
+use serde::Serialize;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::models::{pool_handler, Model, MySQLPool};
+use super::module_controllers::{write_new_module, ModuleChange};
+use crate::models::config_models::LocalConfig;
+use crate::models::{with_connection, DbConnection, DbPool, Model};
 
-use crate::models::module_models::{FieldsDTO};
-use crate::models::page_models::{PageModuleDisplayDTO,MutPage, Page, PageDTO};
+use crate::models::gallery_item_models::{GalleryItem, MutGalleryItem};
+use crate::models::module_models::{Module, ModuleCategory, MutCategory, MutModule};
+use crate::models::page_models::{
+    is_status, MutPage, MutPageParent, MutPageStatus, NewPageWithModules, Page, PatchPage, PageDTO, DRAFT, PUBLIC,
+};
+use crate::models::page_rollout_models::PageRollout;
+use crate::models::taxonomy_models::{PageCategory, Tag};
 
-use crate::services::auth_service::Claims;
+use crate::services::auth_service::{Claims, Editor, RequireRole};
+use crate::services::bulk_service::{self, BulkOperation, BulkResult};
+use crate::services::duplicate_service::{self, DuplicateWarning, WithWarnings};
+use crate::services::encryption_service::{reveal_fields, reveal_modules, reveal_written, reveals_to};
 use crate::services::errors_service::CustomHttpError;
+use crate::services::export_service;
+use crate::services::job_service::{JobQueue, NewJob};
+use crate::services::jsonapi_service::{respond, respond_listing};
+use crate::services::page_cache_service::PageCache;
+use crate::services::pagination_service::PageQuery;
+use crate::services::plugin_service::Plugins;
+use crate::services::publish_service::{PublishJob, PublishQueue};
+use crate::services::quota_service;
+use crate::services::preview_service;
+use crate::services::render_service::{render_page, render_page_with};
+use crate::services::rollout_service::{self, Variant};
+use crate::services::route_service::{self, RouteTable};
+use crate::services::schedule_service;
+use crate::services::translation_service::{self, Negotiation};
+use crate::services::trash_service::DeleteQuery;
+use crate::services::tree_service::{self, Moved};
+use crate::services::validation_service::{FieldError, Validate};
+use crate::services::version_service::{Precondition, Versioned};
+use crate::services::visibility_service::{can_view, list_viewable, require_viewable, strip_fields, viewable, Role};
+use crate::services::webhook_service::{WebhookEvent, WebhookQueue};
 
-fn parse_page(page: (Page, FieldsDTO)) -> Result<PageModuleDisplayDTO, CustomHttpError> {
-    let origin_page = page.0;
+/// Shows the page a preview link is for, whether or not it is published. Links that don't verify, or that are for
+/// another page, are ignored, so the page is shown the way it would be without one.
+async fn preview_page(
+    token: String,
+    path: String,
+    pool: web::Data<DbPool>,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+) -> Result<Option<HttpResponse>, CustomHttpError> {
+    let rendered = with_connection(pool, move |db| {
+        let page: Option<PageDTO> = match preview_service::verify_link(&token, db) {
+            Some(uuid) => Page::read_one(uuid, db).optional()?,
+            None => None,
+        };
 
-    // cast the origin page that is always standard into a new object that has the modules as a vec of children.
-    let mut res: PageModuleDisplayDTO = origin_page.into();
+        match page {
+            Some(page) if page.page_url == path => Ok(Some(render_page(path, db, &hb.lock().unwrap())?)),
+            _ => Ok(None),
+        }
+    })
+    .await?;
 
-    match page.1.categories {
-        Some(modules) => {
-            for module in modules {
-                res.array_fields.insert(module.title, module.modules);
+    // the page may not be public yet, so it is kept out of caches and search engines.
+    Ok(rendered.map(|html| {
+        HttpResponse::Ok()
+            .header("Cache-Control", "private, no-store")
+            .header("X-Robots-Tag", "noindex")
+            .content_type("text/html")
+            .body(html)
+    }))
+}
+
+pub async fn display_page(
+    req: web::HttpRequest,
+    pool: web::Data<DbPool>,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+    routes: web::Data<RouteTable>,
+    pages: web::Data<PageCache>,
+    conf: web::Data<LocalConfig>,
+    claim: Option<Claims>,
+) -> Result<HttpResponse, CustomHttpError> {
+    // nested pages are often linked to as `/docs/`, which is the same page as `/docs`.
+    let path = match req.path().trim_end_matches('/') {
+        "" => String::from("/"),
+        path => path.to_string(),
+    };
+    if let Some(token) = preview_service::link_token(&req) {
+        if let Some(res) = preview_page(token, path.clone(), pool.clone(), hb.clone()).await? {
+            return Ok(res);
+        }
+    }
+    let accepted = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|h| h.to_str().ok())
+        .map(translation_service::accepted)
+        .unwrap_or_default();
+
+    let (url, default_locale) = (path.clone(), conf.default_locale.clone());
+    let (route, rollout, negotiation) = with_connection(pool.clone(), move |db| {
+        let route = match routes.resolve(&url, db)? {
+            Some(route) => route,
+            None => {
+                let translation = translation_service::by_prefix(&url, &default_locale, db)?;
+                return Ok((None, None, translation.map_or(Negotiation::Fixed, Negotiation::Redirect)));
+            }
+        };
+
+        // a pattern serves many URLs, so there is no telling which URL of a translation's pattern to send a visitor to.
+        let negotiation = match route_service::is_pattern(&route.page_url) {
+            true => Negotiation::Fixed,
+            false => translation_service::negotiate(&url, &accepted, &default_locale, db)?,
+        };
+        let rollout = PageRollout::read_for_url(&route.page_url, db).optional()?;
+        Ok((Some(route), rollout, negotiation))
+    })
+    .await?;
+    let known = route.is_some();
+
+    let varies = match negotiation {
+        Negotiation::Redirect(target) => {
+            let location = match req.query_string() {
+                "" => target,
+                query => format!("{}?{}", target, query),
+            };
+            let mut res = HttpResponse::Found();
+            if known {
+                res.header(header::VARY, "Accept-Language");
             }
-        },
-        None => {}
+
+            return Ok(res.header(header::LOCATION, location).finish());
+        }
+        Negotiation::Varies => true,
+        Negotiation::Fixed => false,
     };
+    let route = match route {
+        Some(route) => route,
+        None => return Err(CustomHttpError::NotFound),
+    };
+
+    // only public pages are cached, so a page that is cached can be shown to anyone.
+    if let Some(cached) = pages.get(&path) {
+        let mut res = HttpResponse::Ok();
+        if varies {
+            res.header(header::VARY, "Accept-Language");
+        }
 
-    for module in page.1.modules {
-        res.fields.insert(module.title.clone(), module);
+        return Ok(res.content_type("text/html").body(cached));
     }
+    let (variant, bucket) = match &rollout {
+        Some(rollout) => {
+            let (variant, bucket) = rollout_service::choose(&req, rollout.percentage);
+            (Some(variant), bucket)
+        }
+        None => (None, None),
+    };
+    let canary_modules = match (&rollout, variant) {
+        (Some(rollout), Some(Variant::Canary)) => Some(rollout.modules()?),
+        _ => None,
+    };
+
+    let (render_hb, viewed) = (hb.clone(), rollout.clone().zip(variant));
+    let rendered = with_connection(pool, move |db| {
+        let visibility = Page::read_one_by_url(route.page_url.clone(), db)?.visibility;
+        require_viewable(&visibility, &claim)?;
+        let rendered = render_page_with(route, canary_modules.as_ref(), db, &render_hb.lock().unwrap())?;
+
+        if let Some((rollout, variant)) = viewed {
+            PageRollout::record_view(rollout.uuid, variant == Variant::Canary, db)?;
+        }
 
-    Ok(res)
+        Ok((rendered, visibility == PUBLIC))
+    })
+    .await;
+
+    let (rendered, public) = rendered?;
+
+    let mut res = HttpResponse::Ok();
+    if rollout.is_none() && public {
+        pages.set(&path, &rendered);
+    }
+    if !public {
+        // the page is only for some users, so shared caches can't keep it.
+        res.header("Cache-Control", "private");
+    }
+    if varies {
+        res.header(header::VARY, "Accept-Language");
+    }
+    if let Some(variant) = variant {
+        // the version depends on the visitor, so shared caches can't keep it.
+        res.header(rollout_service::VARIANT_HEADER, variant.as_str())
+            .header("Cache-Control", "private");
+    }
+    if let Some(bucket) = bucket {
+        res.cookie(
+            Cookie::build(rollout_service::BUCKET_COOKIE, bucket.to_string())
+                .path("/")
+                .max_age(Duration::days(30))
+                .finish(),
+        );
+    }
+
+    Ok(res.content_type("text/html").body(rendered))
 }
 
-pub async fn display_page(
-    req: web::HttpRequest,
-    pool: web::Data<MySQLPool>,
-    hb: web::Data<Mutex<Handlebars<'_>>>,
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages",
+    tag = "pages",
+    request_body = MutPage,
+    responses((status = 200, description = "The page that was made", body = PageWithWarnings)),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_page(
+    new: web::Json<MutPage>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    _: RequireRole<Editor>
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
-    let path = req.path();
-    let page_tuple = Page::read_one_join_on_url(path.to_string(), &mysql_pool);
+    new.validate()?;
 
-    if let Err(_) = page_tuple {
-        let s = hb.lock().unwrap().render("404", &String::from("")).unwrap();
-        return Ok(HttpResponse::Ok().content_type("text/html").body(s));
+    let conf = conf.get_ref().clone();
+    let mut created = new.into_inner();
+    created.uuid = Some(Uuid::new_v4().to_string());
+    let (created, warnings, change) =
+        with_connection(pool, move |db| write_new_page(created, &conf, &plugins, db)).await?;
+    change.announce(&publish, &routes, &webhooks);
+
+    Ok(HttpResponse::Ok().json(WithWarnings::new(created, warnings)))
+}
+
+/// What has to be told about a page once it is saved, which is kept until then, as a bulk change may still be rolled back.
+pub enum PageChange {
+    Created {
+        page: PageDTO,
+        translations: Vec<String>,
+    },
+    Updated {
+        old_url: String,
+        status_changed: bool,
+        moved: Vec<Moved>,
+        page: PageDTO,
+        translations: Vec<String>,
+    },
+    Deleted {
+        page: PageDTO,
+        translations: Vec<String>,
+    },
+}
+
+impl PageChange {
+    pub fn announce(self, publish: &PublishQueue, routes: &RouteTable, webhooks: &WebhookQueue) {
+        match self {
+            Self::Created { page, translations } => {
+                routes.invalidate();
+                publish.send(PublishJob::Page(page.uuid.clone()));
+                translation_service::publish_translations(translations, publish);
+                webhooks.send(WebhookEvent::new("page.created", page));
+            }
+            Self::Updated { old_url, status_changed, moved, page, translations } => {
+                // a page that moved leaves its old object behind otherwise.
+                if old_url != page.page_url {
+                    routes.invalidate();
+                    publish.send(PublishJob::Removed(old_url));
+                }
+                if status_changed {
+                    routes.invalidate();
+                }
+                tree_service::publish_moved(moved, publish);
+                publish.send(PublishJob::Page(page.uuid.clone()));
+                translation_service::publish_translations(translations, publish);
+                webhooks.send(WebhookEvent::new("page.updated", page));
+            }
+            Self::Deleted { page, translations } => {
+                routes.invalidate();
+                webhooks.send(WebhookEvent::deleted("page.deleted", &page.uuid));
+                publish.send(PublishJob::Removed(page.page_url));
+                translation_service::publish_translations(translations, publish);
+            }
+        }
     }
+}
 
-    let pagemodule = parse_page(page_tuple?)?;
+/// Writes a new page under the uuid it was given, which is whatever `created` has with its URL resolved under its parent.
+pub fn write_new_page(
+    mut created: MutPage,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(MutPage, Vec<DuplicateWarning>, PageChange), CustomHttpError> {
+    plugins.before_save_page(&mut created)?;
+    quota_service::check_pages(conf, db)?;
+    translation_service::check(None, &created.locale, &created.translation_group, db)?;
+
+    let from_title = created.page_url.is_empty();
+    if from_title {
+        created.page_url = tree_service::url_from_title(&created.page_title, created.parent_page.as_deref());
+    }
+    created.page_url = tree_service::resolve_url(&created.page_url, created.parent_page.as_deref(), db)?;
+    match from_title {
+        true => created.page_url = tree_service::free_url(&created.page_url, db)?,
+        false => tree_service::check_url_free(&created.page_url, None, db)?,
+    }
 
-    let s = hb
-        .lock()
-        .unwrap()
-        .render(&pagemodule.page_name, &pagemodule)
-        .unwrap();
+    let warnings = duplicate_service::check_page(&created, None, db)?;
 
-    Ok(HttpResponse::Ok().content_type("text/html").body(s))
+    Page::create(&created, db)?;
+    let page: PageDTO = Page::read_one(created.uuid.clone().unwrap(), db)?;
+    let translations = translation_service::translation_ids(&page.uuid, &[&page.translation_group], db)?;
+
+    Ok((created, warnings, PageChange::Created { page, translations }))
 }
 
-pub async fn create_page(
-    new: web::Json<MutPage>,
-    pool: web::Data<MySQLPool>,
-    _: Claims
+/// The page and modules that were made, as they would be by `POST /api/v1/pages` and `POST /api/v1/modules`.
+#[derive(Serialize, ToSchema)]
+pub struct PageWithModules {
+    #[schema(value_type = PageWithWarnings)]
+    pub page: WithWarnings<MutPage>,
+    #[schema(value_type = Vec<ModuleWithWarnings>)]
+    pub modules: Vec<WithWarnings<MutModule>>,
+}
+
+impl PageWithModules {
+    /// The modules as they are sent back, with encrypted content blanked unless `reveals`.
+    fn revealed(mut self, reveals: bool) -> Self {
+        self.modules = self
+            .modules
+            .into_iter()
+            .map(|module| WithWarnings::new(reveal_written(module.item, reveals), module.warnings))
+            .collect();
+
+        self
+    }
+}
+
+/// Creates a page along with its modules in one transaction, so that a module that can't be made leaves no page behind.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/with-modules",
+    tag = "pages",
+    request_body = NewPageWithModules,
+    responses((status = 201, description = "The page and modules that were made", body = PageWithModules)),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_page_with_modules(
+    new: web::Json<NewPageWithModules>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    editor: RequireRole<Editor>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    let conf = conf.get_ref().clone();
+    let (created, change, module_changes) =
+        with_connection(pool, move |db| write_page_with_modules(new.into_inner(), &conf, &plugins, db)).await?;
+
+    change.announce(&publish, &routes, &webhooks);
+    for change in module_changes {
+        change.announce(&publish, &webhooks);
+    }
+
+    Ok(HttpResponse::Created().json(created.revealed(reveals_to(Some(&editor.0)))))
+}
+
+/// Checks and writes a new page with its modules in one transaction, which is also how `radical seed` makes them.
+pub fn write_page_with_modules(
+    new: NewPageWithModules,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(PageWithModules, PageChange, Vec<ModuleChange>), CustomHttpError> {
+    let NewPageWithModules { mut page, mut modules } = new;
+    page.uuid = Some(Uuid::new_v4().to_string());
+    for module in &mut modules {
+        module.page_uuid = page.uuid.clone();
+    }
+
+    // every field that is wrong, of the page and of each module, like `modules.1.title`.
+    let mut errors = page.errors();
+    for (index, module) in modules.iter().enumerate() {
+        errors.extend(module.errors().into_iter().map(|error| FieldError {
+            field: format!("modules.{}.{}", index, error.field),
+            message: error.message,
+        }));
+    }
+    if !errors.is_empty() {
+        return Err(CustomHttpError::Invalid(errors));
+    }
+
+    db.transaction(|| {
+        let (page, warnings, change) = write_new_page(page, conf, plugins, db)?;
+
+        let mut module_results = Vec::new();
+        let mut module_changes = Vec::new();
+        for module in modules {
+            let (module, warnings, change) = write_new_module(module, conf, plugins, db)?;
+
+            module_results.push(WithWarnings::new(module, warnings));
+            module_changes.push(change);
+        }
+
+        let created = PageWithModules {
+            page: WithWarnings::new(page, warnings),
+            modules: module_results,
+        };
+
+        Ok((created, change, module_changes))
+    })
+}
+
+/// Copies a page as a draft at a URL of its own, like `/about-copy`, with its categories, modules, galleries, tags
+/// and page categories, in one transaction. The copy keeps the page's `page_name`, and with it the template it is rendered with.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/{id}/duplicate",
+    tag = "pages",
+    params(("id" = String, Path, description = "The uuid of the page to copy")),
+    responses((status = 201, description = "The copy and its modules", body = PageWithModules)),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn duplicate_page(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    editor: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let conf = conf.get_ref().clone();
+    let (copy, change, module_changes) =
+        with_connection(pool, move |db| write_duplicate(id.into_inner(), &conf, &plugins, db)).await?;
+
+    change.announce(&publish, &routes, &webhooks);
+    for change in module_changes {
+        change.announce(&publish, &webhooks);
+    }
+
+    Ok(HttpResponse::Created().json(copy.revealed(reveals_to(Some(&editor.0)))))
+}
+
+/// The first of `{url}-copy`, `{url}-copy-2`, and so on that no page is at.
+fn copy_url(url: &str, db: &DbConnection) -> Result<String, CustomHttpError> {
+    match url.trim_end_matches('/') {
+        "" => tree_service::free_url("/copy", db),
+        url => tree_service::free_url(&format!("{}-copy", url), db),
+    }
+}
+
+fn write_duplicate(
+    id: String,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(PageWithModules, PageChange, Vec<ModuleChange>), CustomHttpError> {
+    let original: PageDTO = Page::read_one(id.clone(), db)?;
+    // the copy is written like any new module, which encrypts again what was encrypted. It is only decrypted for that,
+    // and `duplicate_page` blanks it in what it sends back.
+    let modules = reveal_modules(Module::read_for_pages(vec![id.clone()], db)?, true)?;
+    let categories = ModuleCategory::read_for_pages(vec![id.clone()], db)?;
+    let mut galleries = GalleryItem::read_for_modules(modules.iter().map(|m| m.uuid.clone()).collect(), db)?;
+    let tags: Vec<String> = Tag::read_for_page(&id, db)?.into_iter().map(|t| t.uuid).collect();
+    let page_categories: Vec<String> = PageCategory::read_for_page(&id, db)?.into_iter().map(|c| c.uuid).collect();
+
+    db.transaction(|| {
+        let page = MutPage {
+            uuid: Some(Uuid::new_v4().to_string()),
+            page_url: copy_url(&original.page_url, db)?,
+            status: None,
+            translation_group: None,
+            ..export_service::mut_page(&original)
+        };
+        page.validate()?;
+        let (page, warnings, change) = write_new_page(page, conf, plugins, db)?;
+        let page_uuid = page.uuid.clone().unwrap();
+
+        let mut category_ids = HashMap::new();
+        for category in categories {
+            let copy = MutCategory {
+                uuid: Some(Uuid::new_v4().to_string()),
+                page_uuid: page_uuid.clone(),
+                title: category.title,
+            };
+            ModuleCategory::create(&copy, db)?;
+            category_ids.insert(category.uuid, copy.uuid.unwrap());
+        }
+
+        let mut module_results = Vec::new();
+        let mut module_changes = Vec::new();
+        for original in modules {
+            let module = MutModule {
+                uuid: None,
+                page_uuid: Some(page_uuid.clone()),
+                category_uuid: original.category_uuid.as_ref().and_then(|c| category_ids.get(c).cloned()),
+                ..export_service::mut_module(&original)
+            };
+            let (module, warnings, change) = write_new_module(module, conf, plugins, db)?;
+
+            let module_uuid = module.uuid.clone().unwrap();
+            for item in galleries.remove(&original.uuid).unwrap_or_default() {
+                GalleryItem::create(
+                    &MutGalleryItem {
+                        uuid: Some(Uuid::new_v4().to_string()),
+                        module_uuid: module_uuid.clone(),
+                        media_url: item.media_url,
+                        caption: item.caption,
+                        position: item.position,
+                    },
+                    db,
+                )?;
+            }
 
-    let mut uuid_new = new.clone();
-    uuid_new.uuid = Some(Uuid::new_v4().to_string());
+            module_results.push(WithWarnings::new(module, warnings));
+            module_changes.push(change);
+        }
 
-    Page::create(&uuid_new, &mysql_pool)?;
+        Tag::set_for_page(&page_uuid, &tags, db)?;
+        PageCategory::set_for_page(&page_uuid, &page_categories, db)?;
 
-    Ok(HttpResponse::Ok().json(uuid_new))
+        let copy = PageWithModules {
+            page: WithWarnings::new(page, warnings),
+            modules: module_results,
+        };
+
+        Ok((copy, change, module_changes))
+    })
 }
 
-pub async fn get_pages(pool: web::Data<MySQLPool>) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
-    let pages: Vec<PageDTO> = Page::read_all(&mysql_pool)?;
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages",
+    tag = "pages",
+    params(PageQuery),
+    responses((status = 200, description = "Every one, or one page of them as `{ data, page, per_page, total, total_pages }` when `page` or `per_page` is set", body = [PageDTO]))
+)]
+pub async fn get_pages(
+    req: web::HttpRequest,
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+    claim: Option<Claims>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let pages = with_connection(pool, move |db| list_viewable(&query, viewable(&claim).as_deref(), db)).await?;
 
-    Ok(HttpResponse::Ok().json(pages))
+    Ok(respond_listing(&req, HttpResponse::Ok(), pages))
 
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "The page", body = PageDTO))
+)]
 pub async fn get_page(
+    req: web::HttpRequest,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
-
-    let page: PageDTO = Page::read_one(id.clone(), &mysql_pool)?;
-    Ok(HttpResponse::Ok().json(page))
+    let page: PageDTO = with_connection(pool, move |db| Ok(Page::read_one(id.into_inner(), db)?)).await?;
+    require_viewable(&page.visibility, &claim)?;
+    Ok(respond(&req, HttpResponse::Ok(), page))
 
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}/modules",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "The page with its modules, leaving out the ones hidden from the client", body = PageModuleDTO)),
+    security((), ("token" = []), ("session" = []))
+)]
 pub async fn get_page_join_modules(
+    req: web::HttpRequest,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    let page_vec = with_connection(pool, move |db| {
+        let mut page_vec = Page::read_one_join_on(id.into_inner(), db)?;
+        require_viewable(&page_vec.visibility, &claim)?;
+        page_vec.fields = reveal_fields(strip_fields(page_vec.fields, Role::of(&claim)), reveals_to(claim.as_ref()))?;
 
-    let page_vec = Page::read_one_join_on(id.clone(), &mysql_pool)?;
+        Ok(page_vec)
+    })
+    .await?;
 
-    Ok(HttpResponse::Ok().json(page_vec))
+    Ok(respond(&req, HttpResponse::Ok(), page_vec))
 }
 
+/// Has to be sent with the `version` of the page it was made from, as `If-Match` or in the body, and is refused with a 409
+/// when the page was changed since.
+#[utoipa::path(
+    put,
+    path = "/api/v1/pages/{id}",
+    tag = "pages",
+    params(
+        ("id" = String, Path, description = "The page's uuid"),
+        ("If-Match" = Option<String>, Header, description = "The `version` the update was made from, like `\"3\"`, or `*` for any")
+    ),
+    request_body(content = MutPage, description = "The page, along with the `version` it was made from unless `If-Match` is sent"),
+    responses(
+        (status = 200, description = "The page as it was saved", body = PageWithWarnings),
+        (status = 409, description = "The page was changed since the version"),
+        (status = 428, description = "Neither `If-Match` nor `version` was sent")
+    ),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_page(
-    updated_page: web::Json<MutPage>,
+    req: web::HttpRequest,
+    updated_page: web::Json<Versioned<MutPage>>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    _: RequireRole<Editor>
+) -> Result<HttpResponse, CustomHttpError> {
+    let Versioned { body: updated, version } = updated_page.into_inner();
+    updated.validate()?;
+    let precondition = Precondition::of(&req, version)?;
+
+    save_page(id.into_inner(), pool, publish, routes, webhooks, plugins, move |old_page| {
+        precondition.check(old_page.version)?;
+
+        Ok(updated)
+    })
+    .await
+}
+
+/// Changes only the fields that are sent, like the title, leaving the rest of the page as it is.
+/// Like a `PUT`, it needs the `version` it was made from.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/pages/{id}",
+    tag = "pages",
+    params(
+        ("id" = String, Path, description = "The page's uuid"),
+        ("If-Match" = Option<String>, Header, description = "The `version` the update was made from, like `\"3\"`, or `*` for any")
+    ),
+    request_body(content = PatchPage, description = "The fields to change, along with the `version` they were changed from unless `If-Match` is sent"),
+    responses(
+        (status = 200, description = "The page as it was saved", body = PageWithWarnings),
+        (status = 409, description = "The page was changed since the version"),
+        (status = 428, description = "Neither `If-Match` nor `version` was sent")
+    ),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn patch_page(
+    req: web::HttpRequest,
+    patch: web::Json<Versioned<PatchPage>>,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
-    _: Claims
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    _: RequireRole<Editor>
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    let Versioned { body: patch, version } = patch.into_inner();
+    let precondition = Precondition::of(&req, version)?;
+
+    save_page(id.into_inner(), pool, publish, routes, webhooks, plugins, move |old_page| {
+        precondition.check(old_page.version)?;
+        let updated = patch.apply(old_page);
+        updated.validate()?;
 
-    Page::update(id.clone(), &updated_page, &mysql_pool)?;
+        Ok(updated)
+    })
+    .await
+}
 
-    Ok(HttpResponse::Ok().json(updated_page.0))
+async fn save_page<F>(
+    id: String,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    change: F,
+) -> Result<HttpResponse, CustomHttpError>
+where
+    F: FnOnce(&PageDTO) -> Result<MutPage, CustomHttpError> + Send + 'static,
+{
+    let (updated_page, warnings, change) =
+        with_connection(pool, move |db| write_page(id, change, &plugins, db)).await?;
+    change.announce(&publish, &routes, &webhooks);
 
+    Ok(HttpResponse::Ok().json(WithWarnings::new(updated_page, warnings)))
 }
 
-pub async fn delete_page(
+/// Writes the update `change` makes to the page, moving everything under it along with it when its URL changes.
+pub fn write_page<F>(
+    page_id: String,
+    change: F,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(MutPage, Vec<DuplicateWarning>, PageChange), CustomHttpError>
+where
+    F: FnOnce(&PageDTO) -> Result<MutPage, CustomHttpError>,
+{
+    let old_page: PageDTO = Page::read_one(page_id.clone(), db)?;
+    let mut updated = change(&old_page)?;
+    plugins.before_save_page(&mut updated)?;
+
+    let locale = updated.locale.clone().or_else(|| old_page.locale.clone());
+    let group = updated.translation_group.clone().or_else(|| old_page.translation_group.clone());
+    translation_service::check(Some(&page_id), &locale, &group, db)?;
+
+    if let Some(parent) = &updated.parent_page {
+        tree_service::check_parent(&page_id, parent, db)?;
+    }
+    if updated.page_url.is_empty() {
+        updated.page_url = old_page.page_url.clone();
+    }
+    let parent = updated.parent_page.clone().or_else(|| old_page.parent_page.clone());
+    updated.page_url = tree_service::resolve_url(&updated.page_url, parent.as_deref(), db)?;
+    tree_service::check_url_free(&updated.page_url, Some(&page_id), db)?;
+
+    let warnings = duplicate_service::check_page(&updated, Some(&page_id), db)?;
+
+    let moved = db.transaction::<_, CustomHttpError, _>(|| {
+        // refused rather than made over a change that was saved since the page was read above.
+        if Page::update_at(page_id.clone(), &updated, old_page.version, db)? == 0 {
+            return Err(CustomHttpError::Stale);
+        }
+
+        Ok(tree_service::move_descendants(page_id.clone(), &old_page.page_url, &updated.page_url, db)?)
+    })?;
+    let page: PageDTO = Page::read_one(page_id, db)?;
+    let groups = [&old_page.translation_group, &page.translation_group];
+    let translations = translation_service::translation_ids(&page.uuid, &groups, db)?;
+
+    let change = PageChange::Updated {
+        old_url: old_page.page_url,
+        status_changed: updated.status.is_some(),
+        moved,
+        page,
+        translations,
+    };
+
+    Ok((updated, warnings, change))
+}
+
+/// Publishes a draft, schedules it to go live on its own, or takes a published page back to being a draft.
+#[utoipa::path(
+    put,
+    path = "/api/v1/pages/{id}/status",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid")),
+    request_body = MutPageStatus,
+    responses((status = 200, description = "The page with its new status", body = PageDTO)),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn update_page_status(
+    new: web::Json<MutPageStatus>,
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    jobs: web::Data<JobQueue>,
+    _: RequireRole<Editor>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    if !is_status(&new.status) || (new.publish_at.is_some() && new.status != DRAFT) {
+        return Err(CustomHttpError::BadRequest);
+    }
+
+    let publish_at = new.publish_at;
+    let page_id = id.clone();
+    let (page, translations) = with_connection(pool, move |db| {
+        Page::set_status(page_id.clone(), &new.status, new.publish_at, db)?;
+        let page: PageDTO = Page::read_one(page_id, db)?;
+        let translations = translation_service::translation_ids(&page.uuid, &[&page.translation_group], db)?;
 
-    let res = Page::delete(id.clone(), &mysql_pool)?;
+        Ok((page, translations))
+    })
+    .await?;
+
+    routes.invalidate();
+    publish.send(PublishJob::Page(id.into_inner()));
+    translation_service::publish_translations(translations, &publish);
+    webhooks.send(WebhookEvent::new("page.updated", &page));
+    if let Some(at) = publish_at {
+        jobs.send(NewJob::new(schedule_service::JOB, serde_json::json!({ "page": page.uuid })).at(at));
+    }
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+/// Takes the uuids of every module on the page, in their new order.
+#[utoipa::path(
+    put,
+    path = "/api/v1/pages/{id}/modules/reorder",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid")),
+    request_body = [String],
+    responses((status = 200, description = "The page's modules in their new order", body = [Module])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn reorder_page_modules(
+    order: web::Json<Vec<String>>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    editor: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let page_id = id.clone();
+    let reveals = reveals_to(Some(&editor.0));
+    let modules = with_connection(pool, move |db| {
+        let page: PageDTO = Page::read_one(page_id, db)?;
+
+        let mut current: Vec<String> = Module::read_for_pages(vec![page.uuid.clone()], db)?
+            .into_iter()
+            .map(|m| m.uuid)
+            .collect();
+        let mut requested = order.clone();
+        current.sort();
+        requested.sort();
+
+        // a partial order would leave the places of the missing modules ambiguous.
+        if current != requested {
+            return Err(CustomHttpError::BadRequest);
+        }
+
+        Module::reorder(page.uuid.clone(), order.into_inner(), db)?;
+
+        Ok(reveal_modules(Module::read_for_pages(vec![page.uuid], db)?, reveals)?)
+    })
+    .await?;
+
+    publish.send(PublishJob::Page(id.into_inner()));
+
+    Ok(HttpResponse::Ok().json(modules))
+}
+
+/// Nests the page under another one, or makes it top level again, taking every page under it along.
+/// The page keeps the last part of its URL, so `/docs/install` moved under `/guides` is `/guides/install`.
+#[utoipa::path(
+    put,
+    path = "/api/v1/pages/{id}/parent",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid")),
+    request_body = MutPageParent,
+    responses((status = 200, description = "The page where it was moved to", body = PageDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_page_parent(
+    new: web::Json<MutPageParent>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let page_id = id.clone();
+    let (page, old_page, moved, translations) = with_connection(pool, move |db| {
+        let old_page: PageDTO = Page::read_one(page_id.clone(), db)?;
+
+        if let Some(parent) = &new.parent_page {
+            tree_service::check_parent(&page_id, parent, db)?;
+        }
+        let page_url = tree_service::rebase_url(&old_page.page_url, new.parent_page.as_deref(), db)?;
+
+        let moved = db.transaction::<_, CustomHttpError, _>(|| {
+            Page::set_parent(page_id.clone(), new.parent_page.clone(), db)?;
+            Page::set_url(page_id.clone(), &page_url, db)?;
+
+            Ok(tree_service::move_descendants(page_id.clone(), &old_page.page_url, &page_url, db)?)
+        })?;
+
+        let page: PageDTO = Page::read_one(page_id, db)?;
+        let translations = translation_service::translation_ids(&page.uuid, &[&page.translation_group], db)?;
+
+        Ok((page, old_page, moved, translations))
+    })
+    .await?;
+
+    if old_page.page_url != page.page_url {
+        routes.invalidate();
+        publish.send(PublishJob::Removed(old_page.page_url));
+    }
+    tree_service::publish_moved(moved, &publish);
+    publish.send(PublishJob::Page(id.into_inner()));
+    translation_service::publish_translations(translations, &publish);
+    webhooks.send(WebhookEvent::new("page.updated", &page));
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+/// The page and every page nested under it, as a tree.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}/tree",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "The page and every page nested under it", body = PageTree))
+)]
+pub async fn get_page_tree(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let mut tree = with_connection(pool, move |db| Ok(Page::read_tree(id.into_inner(), db)?)).await?;
+    require_viewable(&tree.page.visibility, &claim)?;
+    tree.retain(&|page| can_view(&page.visibility, &claim));
+
+    Ok(HttpResponse::Ok().json(tree))
+}
+
+/// The other pages in the page's translation group, drafts included. Empty when it isn't in one.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}/translations",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "The page's translations by locale", body = [PageDTO]))
+)]
+pub async fn get_page_translations(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    claim: Option<Claims>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let translations = with_connection(pool, move |db| {
+        let page: PageDTO = Page::read_one(id.into_inner(), db)?;
+        require_viewable(&page.visibility, &claim)?;
+        let translations: Vec<PageDTO> = match page.translation_group.as_deref().filter(|g| !g.is_empty()) {
+            Some(group) => Page::read_translations(group, db)?
+                .into_iter()
+                .filter(|p| p.uuid != page.uuid && can_view(&p.visibility, &claim))
+                .map(|p| p.into())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(translations)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(translations))
+}
+
+/// Moves the page to the trash, or removes it for good with `?permanent=true`, which works on pages in the trash as well.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/pages/{id}",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid"), DeleteQuery),
+    responses((status = 200, description = "How many pages were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_page(
+    id: web::Path<String>,
+    query: web::Query<DeleteQuery>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (res, change) = with_connection(pool, move |db| remove_page(id.into_inner(), query.permanent, db)).await?;
+    change.announce(&publish, &routes, &webhooks);
 
     Ok(HttpResponse::Ok().json(res))
 }
+
+fn remove_page(id: String, permanent: bool, db: &DbConnection) -> Result<(usize, PageChange), CustomHttpError> {
+    let page: PageDTO = match permanent {
+        true => Page::read_any(id.clone(), db)?.into(),
+        false => Page::read_one(id.clone(), db)?,
+    };
+    let translations = translation_service::translation_ids(&page.uuid, &[&page.translation_group], db)?;
+
+    let res = match permanent {
+        true => Page::purge(id, db)?,
+        false => Page::delete(id, db)?,
+    };
+
+    Ok((res, PageChange::Deleted { page, translations }))
+}
+
+/// Creates, updates, and deletes pages in one go, as `[{ "op": "create", "data": { .. } }, { "op": "delete", "id": ".." }]`.
+/// Either every operation is saved or, when one fails, none are, and the error's `index` says which one it was.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/bulk",
+    tag = "pages",
+    request_body = [PageOperation],
+    responses((status = 200, description = "What each operation did, in the order they were sent", body = [BulkResult])),
+    security(("token" = []), ("session" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn bulk_pages(
+    operations: web::Json<Vec<BulkOperation<MutPage>>>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    plugins: web::Data<Plugins>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let operations = operations.into_inner();
+    bulk_service::validate(&operations)?;
+
+    let conf = conf.get_ref().clone();
+    let (results, changes): (Vec<BulkResult>, Vec<PageChange>) = with_connection(pool, move |db| {
+        let done = bulk_service::run(operations, db, |operation| match operation {
+            BulkOperation::Create { mut data } => {
+                data.uuid = Some(Uuid::new_v4().to_string());
+                let (created, warnings, change) = write_new_page(data, &conf, &plugins, db)?;
+
+                Ok((created.uuid.unwrap_or_default(), warnings, change))
+            }
+            BulkOperation::Update { id, data, version } => {
+                let precondition = version.map_or(Precondition::Any, Precondition::Version);
+                let (_, warnings, change) = write_page(
+                    id.clone(),
+                    move |old_page| {
+                        precondition.check(old_page.version)?;
+
+                        Ok(data)
+                    },
+                    &plugins,
+                    db,
+                )?;
+
+                Ok((id, warnings, change))
+            }
+            BulkOperation::Delete { id, permanent } => {
+                let (_, change) = remove_page(id.clone(), permanent, db)?;
+
+                Ok((id, Vec::new(), change))
+            }
+        })?;
+
+        Ok(done.into_iter().unzip())
+    })
+    .await?;
+
+    for change in changes {
+        change.announce(&publish, &routes, &webhooks);
+    }
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// Takes the page out of the trash, along with the modules that went in with it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/{id}/restore",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "The page, out of the trash", body = PageDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn restore_page(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    webhooks: web::Data<WebhookQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let page_id = id.clone();
+    let (page, translations) = with_connection(pool, move |db| {
+        // a page that was put at the URL since can't be moved out of the way.
+        if let Some(trashed) = Page::read_any(page_id.clone(), db).optional()? {
+            tree_service::check_url_free(&trashed.page_url, Some(&page_id), db)?;
+        }
+        if Page::restore(page_id.clone(), db)? == 0 {
+            return Err(CustomHttpError::NotFound);
+        }
+        let page: PageDTO = Page::read_one(page_id, db)?;
+        let translations = translation_service::translation_ids(&page.uuid, &[&page.translation_group], db)?;
+
+        Ok((page, translations))
+    })
+    .await?;
+
+    routes.invalidate();
+    publish.send(PublishJob::Page(id.into_inner()));
+    translation_service::publish_translations(translations, &publish);
+    // to anyone listening, a page that is back from the trash is new again.
+    webhooks.send(WebhookEvent::new("page.created", &page));
+
+    Ok(HttpResponse::Ok().json(page))
+}
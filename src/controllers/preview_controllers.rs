@@ -0,0 +1,137 @@
+use std::sync::Mutex;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use chrono::NaiveDateTime;
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::config_models::LocalConfig;
+use crate::models::page_models::{Page, PageDTO};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{generate_secret, Editor, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::preview_service::{self, render_fragments, PreviewHub, PreviewSocket};
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewPreview {
+    pub page_uuid: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Preview {
+    pub token: String,
+    pub page_uuid: String,
+}
+
+/// A link that shows a page to whoever has it, drafts included, until it expires.
+#[derive(Serialize, ToSchema)]
+pub struct PreviewLink {
+    pub token: String,
+    /// The page's URL with the token in `?preview=`.
+    pub url: String,
+    pub expires_at: NaiveDateTime,
+}
+
+/// Signs a link to a page for sharing it before it is published. Links can't be revoked, other than by retiring the
+/// signing key they were signed with, so they only last `APP_PREVIEW_LINK_TTL` seconds.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/{id}/preview-token",
+    tag = "pages",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 201, description = "The link", body = PreviewLink)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_preview_link(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let ttl = conf.preview_link_ttl;
+    let (page, (token, expires_at)) = with_connection(pool, move |db| {
+        let page: PageDTO = Page::read_one(id.into_inner(), db)?;
+        let link = preview_service::sign_link(page.uuid.clone(), ttl, db)?;
+
+        Ok((page, link))
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(PreviewLink {
+        url: format!("{}?{}={}", page.page_url, preview_service::PREVIEW_PARAM, token),
+        token,
+        expires_at,
+    }))
+}
+
+/// Opens a preview of a page. The token is what the preview pane connects to `/ws/preview/{token}` with.
+#[utoipa::path(
+    post,
+    path = "/api/v1/preview",
+    tag = "previews",
+    request_body = NewPreview,
+    responses((status = 201, description = "The preview, with the token to connect to it with", body = Preview)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_preview(
+    new: web::Json<NewPreview>,
+    pool: web::Data<DbPool>,
+    hub: web::Data<PreviewHub>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    // makes sure the page exists before handing out a token for it.
+    let page_uuid = new.page_uuid.clone();
+    let page: PageDTO = with_connection(pool, move |db| Ok(Page::read_one(page_uuid, db)?)).await?;
+
+    let token = generate_secret();
+    hub.open(token.clone(), page.uuid.clone());
+
+    Ok(HttpResponse::Created().json(Preview {
+        token,
+        page_uuid: page.uuid,
+    }))
+}
+
+/// Closes a preview. Sockets already connected to it stop receiving updates.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/preview/{token}",
+    tag = "previews",
+    params(("token" = String, Path, description = "The preview's token")),
+    responses((status = 200, description = "The preview was closed")),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_preview(
+    token: web::Path<String>,
+    hub: web::Data<PreviewHub>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    match hub.close(&token) {
+        true => Ok(HttpResponse::Ok().finish()),
+        false => Err(CustomHttpError::NotFound),
+    }
+}
+
+/// The token is all the socket needs, so a preview pane can be opened in another tab or on another device.
+pub async fn preview_socket(
+    req: HttpRequest,
+    stream: web::Payload,
+    token: web::Path<String>,
+    pool: web::Data<DbPool>,
+    hub: web::Data<PreviewHub>,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let page_uuid = hub.page_for(&token).ok_or(CustomHttpError::NotFound)?;
+
+    let initial = with_connection(pool, move |db| render_fragments(page_uuid, db, &hb.lock().unwrap())).await?;
+
+    let socket = PreviewSocket {
+        token: token.clone(),
+        hub: hub.clone(),
+        initial,
+    };
+
+    ws::start(socket, &req, stream).map_err(|_| CustomHttpError::BadRequest)
+}
@@ -0,0 +1,73 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::data_request_models::DataRequest;
+use crate::models::{with_connection, with_primary_connection, DbPool};
+use crate::services::auth_service::{Admin, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::privacy_service;
+
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct ErasureConfirmation {
+    /// Must match the email being erased, so that an erasure can't happen by accident.
+    pub confirm_email: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/privacy/{email}",
+    tag = "privacy",
+    params(("email" = String, Path, description = "The email to export the data of")),
+    responses((status = 200, description = "Everything stored that is tied to the email", body = PersonalDataExport)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn export_personal_data(
+    email: web::Path<String>,
+    pool: web::Data<DbPool>,
+    claim: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let export = with_primary_connection(pool, move |db| privacy_service::export(&email, claim.0.sub, db)).await?;
+
+    Ok(HttpResponse::Ok().json(export))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/privacy/{email}/erase",
+    tag = "privacy",
+    params(("email" = String, Path, description = "The email to erase the data of")),
+    request_body = ErasureConfirmation,
+    responses((status = 200, description = "How many records were erased", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn erase_personal_data(
+    email: web::Path<String>,
+    confirmation: web::Json<ErasureConfirmation>,
+    pool: web::Data<DbPool>,
+    claim: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    if confirmation.confirm_email != *email {
+        return Err(CustomHttpError::BadRequest);
+    }
+
+    let res = with_connection(pool, move |db| privacy_service::erase(&email, claim.0.sub, db)).await?;
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/privacy/requests",
+    tag = "privacy",
+    responses((status = 200, description = "Every export and erasure, newest first", body = [DataRequest])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_data_requests(
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let requests = with_connection(pool, |db| Ok(DataRequest::read_all(db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(requests))
+}
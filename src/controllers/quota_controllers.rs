@@ -0,0 +1,25 @@
+use actix_web::{web, HttpResponse};
+
+use crate::models::config_models::LocalConfig;
+use crate::models::{with_connection, DbPool};
+use crate::services::auth_service::Claims;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::quota_service;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/usage",
+    tag = "usage",
+    responses((status = 200, description = "How much of each quota of the site is used", body = Usage)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_usage(
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    _: Claims,
+) -> Result<HttpResponse, CustomHttpError> {
+    let conf = conf.get_ref().clone();
+    let usage = with_connection(pool, move |db| Ok(quota_service::usage(&conf, db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(usage))
+}
@@ -0,0 +1,129 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::models::redirect_models::{MutRedirect, NewRedirect, Redirect, STATUS_CODES};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Editor, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{list, PageQuery};
+use crate::services::redirect_service::{normalize, RedirectTable};
+use crate::services::validation_service::Validate;
+
+fn to_mut(uuid: String, new: NewRedirect) -> MutRedirect {
+    MutRedirect {
+        uuid,
+        source: normalize(&new.source),
+        target: new.target,
+        status_code: new.status_code.unwrap_or(STATUS_CODES[0]),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/redirects",
+    tag = "redirects",
+    request_body = NewRedirect,
+    responses((status = 201, description = "The redirect as it was saved", body = Redirect)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_redirect(
+    new: web::Json<NewRedirect>,
+    pool: web::Data<DbPool>,
+    redirects: web::Data<RedirectTable>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    new.validate()?;
+
+    let new_redirect = to_mut(Uuid::new_v4().to_string(), new.into_inner());
+    let redirect = with_connection(pool, move |db| {
+        Redirect::create(&new_redirect, db)?;
+
+        Ok(Redirect::read_one(new_redirect.uuid, db)?)
+    })
+    .await?;
+    redirects.invalidate();
+
+    Ok(HttpResponse::Created().json(redirect))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/redirects",
+    tag = "redirects",
+    params(PageQuery),
+    responses((status = 200, description = "Every redirect by source, or one page of them when `page` or `per_page` is set", body = [Redirect]))
+)]
+pub async fn get_redirects(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let redirects = with_connection(pool, move |db| list(&query, db, Redirect::read_all, Redirect::read_paginated)).await?;
+
+    Ok(HttpResponse::Ok().json(redirects))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/redirects/{id}",
+    tag = "redirects",
+    params(("id" = String, Path, description = "The redirect's uuid")),
+    responses((status = 200, description = "The redirect", body = Redirect))
+)]
+pub async fn get_redirect(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let redirect = with_connection(pool, move |db| Ok(Redirect::read_one(id.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(redirect))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/redirects/{id}",
+    tag = "redirects",
+    params(("id" = String, Path, description = "The redirect's uuid")),
+    request_body = NewRedirect,
+    responses((status = 200, description = "The redirect as it was saved", body = Redirect)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_redirect(
+    updated: web::Json<NewRedirect>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    redirects: web::Data<RedirectTable>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    updated.validate()?;
+
+    let updated = to_mut(id.into_inner(), updated.into_inner());
+    let redirect = with_connection(pool, move |db| {
+        Redirect::update(updated.uuid.clone(), &updated, db)?;
+
+        Ok(Redirect::read_one(updated.uuid, db)?)
+    })
+    .await?;
+    redirects.invalidate();
+
+    Ok(HttpResponse::Ok().json(redirect))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/redirects/{id}",
+    tag = "redirects",
+    params(("id" = String, Path, description = "The redirect's uuid")),
+    responses((status = 200, description = "How many redirects were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_redirect(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    redirects: web::Data<RedirectTable>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| Ok(Redirect::delete(id.into_inner(), db)?)).await?;
+    redirects.invalidate();
+
+    Ok(HttpResponse::Ok().json(res))
+}
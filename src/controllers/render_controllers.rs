@@ -0,0 +1,60 @@
+use std::sync::Mutex;
+
+use actix_web::{web, HttpResponse};
+use handlebars::Handlebars;
+
+use crate::models::config_models::LocalConfig;
+use crate::models::{with_connection, DbPool};
+use crate::services::delivery_service::cache_control;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::render_service::{render_module, render_region};
+
+// Fragments are public like the pages they are part of, and are cached the same as the delivery API.
+
+fn fragment(conf: &LocalConfig, html: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .header("Cache-Control", cache_control(conf.delivery_max_age))
+        .body(html)
+}
+
+#[utoipa::path(
+    get,
+    path = "/render/modules/{id}",
+    tag = "render",
+    params(("id" = String, Path, description = "The module's uuid")),
+    responses((status = 200, description = "The module rendered with its template", body = String, content_type = "text/html"))
+)]
+pub async fn get_module_fragment(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let html = with_connection(pool, move |db| render_module(id.into_inner(), db, &hb.lock().unwrap())).await?;
+
+    Ok(fragment(&conf, html))
+}
+
+#[utoipa::path(
+    get,
+    path = "/render/pages/{id}/region/{name}",
+    tag = "render",
+    params(
+        ("id" = String, Path, description = "The page's uuid"),
+        ("name" = String, Path, description = "The region's name")
+    ),
+    responses((status = 200, description = "The modules of the region rendered with their templates", body = String, content_type = "text/html"))
+)]
+pub async fn get_region_fragment(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (page_id, name) = path.into_inner();
+
+    let html = with_connection(pool, move |db| render_region(page_id, name, db, &hb.lock().unwrap())).await?;
+
+    Ok(fragment(&conf, html))
+}
@@ -0,0 +1,128 @@
+use actix_web::{web, HttpResponse};
+
+use crate::models::page_models::{Page, PageDTO};
+use crate::models::page_revision_models::{PageRevision, PageRevisionDTO, PageSnapshot};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Claims, Editor, RequireRole};
+use crate::services::encryption_service::{reveal_modules, reveals_to};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::publish_service::{PublishJob, PublishQueue};
+use crate::services::revision_service;
+use crate::services::route_service::RouteTable;
+use crate::services::tree_service;
+
+/// Every revision of a page, newest first, with encrypted module content decrypted for admins.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}/revisions",
+    tag = "revisions",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "Every revision of the page, newest first", body = [PageRevisionDTO])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_revisions(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    claim: Claims,
+) -> Result<HttpResponse, CustomHttpError> {
+    let reveals = reveals_to(Some(&claim));
+    let revisions = with_connection(pool, move |db| {
+        let page: PageDTO = Page::read_one(id.into_inner(), db)?;
+
+        PageRevision::read_for_page(page.uuid, db)?
+            .into_iter()
+            .map(|revision| {
+                let snapshot = revision.snapshot()?;
+                let snapshot = PageSnapshot {
+                    modules: reveal_modules(snapshot.modules, reveals)?,
+                    ..snapshot
+                };
+
+                Ok(PageRevisionDTO { revision, snapshot })
+            })
+            .collect::<Result<Vec<_>, CustomHttpError>>()
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(revisions))
+}
+
+/// What changed from one revision of a page to another, by field and by module, with a text diff of each module's content.
+/// Either of them can be `current` for the page as it is now, and encrypted module content is decrypted for admins.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}/revisions/{from}/diff/{to}",
+    tag = "revisions",
+    params(
+        ("id" = String, Path, description = "The page's uuid"),
+        ("from" = String, Path, description = "A revision's uuid, or `current`"),
+        ("to" = String, Path, description = "A revision's uuid, or `current`")
+    ),
+    responses((status = 200, description = "What changed from one to the other", body = RevisionDiff)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_revision_diff(
+    path: web::Path<(String, String, String)>,
+    pool: web::Data<DbPool>,
+    claim: Claims,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (page_id, from, to) = path.into_inner();
+    let reveals = reveals_to(Some(&claim));
+
+    let diff = with_connection(pool, move |db| {
+        let page: PageDTO = Page::read_one(page_id, db)?;
+        let reveal = |snapshot: PageSnapshot| -> Result<PageSnapshot, CustomHttpError> {
+            Ok(PageSnapshot {
+                modules: reveal_modules(snapshot.modules, reveals)?,
+                ..snapshot
+            })
+        };
+        let a = reveal(revision_service::snapshot(&page.uuid, from.clone(), db)?)?;
+        let b = reveal(revision_service::snapshot(&page.uuid, to.clone(), db)?)?;
+
+        Ok(revision_service::diff((from, &a), (to, &b)))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(diff))
+}
+
+/// Puts a page and its modules back the way they were in one of its revisions.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/{id}/revisions/{revision_id}/restore",
+    tag = "revisions",
+    params(
+        ("id" = String, Path, description = "The page's uuid"),
+        ("revision_id" = String, Path, description = "The revision's uuid")
+    ),
+    responses((status = 200, description = "The page as it was restored", body = PageDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn restore_revision(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (page_id, revision_id) = path.into_inner();
+
+    let page_uuid = page_id.clone();
+    let (restored, page) = with_connection(pool, move |db| {
+        let restored = revision_service::restore(page_uuid.clone(), revision_id, db)?;
+        let page: PageDTO = Page::read_one(page_uuid, db)?;
+
+        Ok((restored, page))
+    })
+    .await?;
+
+    if restored.old_url != restored.page_url {
+        routes.invalidate();
+        publish.send(PublishJob::Removed(restored.old_url));
+    }
+    tree_service::publish_moved(restored.moved, &publish);
+    publish.send(PublishJob::Page(page_id));
+
+    Ok(HttpResponse::Ok().json(page))
+}
@@ -0,0 +1,150 @@
+use actix_web::{web, HttpResponse};
+use diesel::OptionalExtension;
+use uuid::Uuid;
+
+use crate::models::page_models::{Page, PageDTO};
+use crate::models::page_rollout_models::{MutPageRollout, PageRollout, PageRolloutDTO};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Claims, Editor, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::page_cache_service::PageCache;
+use crate::services::publish_service::{PublishJob, PublishQueue};
+use crate::services::rollout_service::{self, RolloutRequest};
+
+/// The rollout of a page, with how many times each version has been viewed.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}/rollout",
+    tag = "rollouts",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "The rollout", body = PageRolloutDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_rollout(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: Claims,
+) -> Result<HttpResponse, CustomHttpError> {
+    let rollout: PageRolloutDTO =
+        with_connection(pool, move |db| Ok(PageRollout::read_for_page(id.into_inner(), db)?.into())).await?;
+
+    Ok(HttpResponse::Ok().json(rollout))
+}
+
+/// Starts a rollout of new module content, or changes the one already running.
+/// Changing the content of a running rollout starts its view counts over.
+#[utoipa::path(
+    put,
+    path = "/api/v1/pages/{id}/rollout",
+    tag = "rollouts",
+    params(("id" = String, Path, description = "The page's uuid")),
+    request_body = RolloutRequest,
+    responses((status = 200, description = "The rollout as it was saved", body = PageRolloutDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn put_rollout(
+    new: web::Json<RolloutRequest>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    pages: web::Data<PageCache>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (rollout, page_url) = with_connection(pool, move |db| {
+        let page: PageDTO = Page::read_one(id.into_inner(), db)?;
+        rollout_service::validate(&new, &page.uuid, db)?;
+
+        let content = serde_json::to_string(&new.modules).map_err(|_| CustomHttpError::BadRequest)?;
+
+        match PageRollout::read_for_page(page.uuid.clone(), db).optional()? {
+            // keeping the view counts when only the percentage changes lets a rollout be widened step by step.
+            Some(existing) if existing.content == content => {
+                PageRollout::update(
+                    existing.uuid.clone(),
+                    &MutPageRollout {
+                        uuid: existing.uuid,
+                        page_uuid: existing.page_uuid,
+                        percentage: new.percentage,
+                        content,
+                    },
+                    db,
+                )?;
+            }
+            existing => {
+                if let Some(existing) = existing {
+                    PageRollout::delete(existing.uuid, db)?;
+                }
+
+                PageRollout::create(
+                    &MutPageRollout {
+                        uuid: Uuid::new_v4().to_string(),
+                        page_uuid: page.uuid.clone(),
+                        percentage: new.percentage,
+                        content,
+                    },
+                    db,
+                )?;
+            }
+        }
+
+        let rollout: PageRolloutDTO = PageRollout::read_for_page(page.uuid, db)?.into();
+
+        Ok((rollout, page.page_url))
+    })
+    .await?;
+
+    // pages under a rollout are never cached, as the version depends on the visitor.
+    pages.delete(&page_url);
+
+    Ok(HttpResponse::Ok().json(rollout))
+}
+
+/// Shows the canary to everyone by making it the content of its modules.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/{id}/rollout/promote",
+    tag = "rollouts",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "How many modules were given the canary's content", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn promote_rollout(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (res, page_uuid) = with_connection(pool, move |db| {
+        let rollout = PageRollout::read_for_page(id.into_inner(), db)?;
+
+        Ok((rollout_service::promote(&rollout, db)?, rollout.page_uuid))
+    })
+    .await?;
+
+    publish.send(PublishJob::Page(page_uuid));
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Ends the rollout without changing anything, so everyone sees the current content again.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/{id}/rollout/rollback",
+    tag = "rollouts",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "How many rollouts were ended", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn rollback_rollout(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| {
+        let rollout = PageRollout::read_for_page(id.into_inner(), db)?;
+
+        Ok(PageRollout::delete(rollout.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,69 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::services::auth_service::{Admin, Claims, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::PageQuery;
+use crate::services::search_service::{self, SearchIndex, SearchIndexer};
+
+#[derive(Debug, Deserialize, Clone, IntoParams)]
+pub struct SearchQuery {
+    /// What to search for.
+    pub q: String,
+}
+
+/// Full text search over the title, name, and module content of every page, for site search and admin UIs alike.
+/// It is answered by the index of `APP_SEARCH_BACKEND`, so with Meilisearch, misspelled searches find pages too.
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "search",
+    params(SearchQuery, PageQuery),
+    responses((status = 200, description = "One page of the pages that matched, as `{ data, page, per_page, total, total_pages }`", body = [SearchResult]))
+)]
+pub async fn search(
+    index: web::Data<dyn SearchIndex>,
+    search: web::Query<SearchQuery>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let results = search_service::search(search.into_inner().q, query.into_inner(), index).await?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// The progress of the current, or last, full rebuild of the search index.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/search/reindex",
+    tag = "search",
+    responses((status = 200, description = "How the rebuild is going", body = ReindexStatus)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_reindex_status(
+    indexer: web::Data<SearchIndexer>,
+    _: Claims,
+) -> Result<HttpResponse, CustomHttpError> {
+    Ok(HttpResponse::Ok().json(indexer.status()))
+}
+
+/// Starts a full rebuild of the search index. Responds with a 409 if one is already running.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/search/reindex",
+    tag = "search",
+    responses(
+        (status = 202, description = "The rebuild was started", body = ReindexStatus),
+        (status = 409, description = "A rebuild is already running", body = ReindexStatus)
+    ),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn reindex(
+    indexer: web::Data<SearchIndexer>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    match indexer.rebuild() {
+        true => Ok(HttpResponse::Accepted().json(indexer.status())),
+        false => Ok(HttpResponse::Conflict().json(indexer.status())),
+    }
+}
@@ -0,0 +1,101 @@
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::user_controllers::{log_in, LoginOutcome, CODE_REQUIRED};
+use crate::models::user_models::{LoginBody, User, UserRole};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::Claims;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::session_service::{self, SessionData, SessionStore};
+
+/// Sent to the admin panel, which has to send `csrf_token` back in `X-CSRF-Token` with every write.
+#[derive(Serialize, ToSchema)]
+pub struct SessionInfo {
+    pub user: String,
+    pub role: UserRole,
+    pub csrf_token: String,
+}
+
+/// Logs in like `/api/v1/user/login`, but keeps the token in the session instead of handing it to the client.
+#[utoipa::path(
+    post,
+    path = "/api/v1/session",
+    tag = "sessions",
+    request_body = LoginBody,
+    responses((status = 200, description = "Logged in, with the session cookie set", body = SessionInfo))
+)]
+pub async fn create_session(
+    body: web::Json<LoginBody>,
+    pool: web::Data<DbPool>,
+    store: web::Data<dyn SessionStore>,
+    session: Session,
+) -> Result<HttpResponse, CustomHttpError> {
+    let username = body.user.username.clone();
+    let (outcome, role) = with_connection(pool, move |db| {
+        let LoginBody { user, code } = body.into_inner();
+        let role = User::read_one(user.username.clone(), db)?.role();
+
+        Ok((log_in(user, code, db)?, role))
+    })
+    .await?;
+
+    let (mut res, cookie) = match outcome {
+        LoginOutcome::Forbidden => return Ok(HttpResponse::Forbidden().finish()),
+        LoginOutcome::Failed => return Ok(HttpResponse::Unauthorized().json("Failed to authenticate.")),
+        LoginOutcome::CodeRequired => return Ok(HttpResponse::Unauthorized().json(CODE_REQUIRED)),
+        LoginOutcome::FirstLogin(cookie) => (HttpResponse::Accepted(), cookie),
+        LoginOutcome::LoggedIn(cookie) => (HttpResponse::Ok(), cookie),
+    };
+
+    // a new session every login, so one set before logging in can't be carried over.
+    store.remove(&session)?;
+    session.renew();
+
+    let data = SessionData::new(cookie.value().to_string());
+    store.save(&session, &data)?;
+
+    Ok(res.json(SessionInfo {
+        user: username,
+        role,
+        csrf_token: data.csrf_token,
+    }))
+}
+
+/// The session's user and CSRF token, so an admin panel that was reloaded can pick up where it was.
+#[utoipa::path(
+    get,
+    path = "/api/v1/session",
+    tag = "sessions",
+    responses((status = 200, description = "The session's user and CSRF token", body = SessionInfo)),
+    security(("session" = []))
+)]
+pub async fn get_session(
+    claim: Claims,
+    store: web::Data<dyn SessionStore>,
+    session: Session,
+) -> Result<HttpResponse, CustomHttpError> {
+    let data = session_service::logged_in(&session, Some(&store)).ok_or(CustomHttpError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(SessionInfo {
+        user: claim.sub,
+        role: claim.role,
+        csrf_token: data.csrf_token,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/session",
+    tag = "sessions",
+    responses((status = 200, description = "Logged out"))
+)]
+pub async fn delete_session(
+    store: web::Data<dyn SessionStore>,
+    session: Session,
+) -> Result<HttpResponse, CustomHttpError> {
+    store.remove(&session)?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,141 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::setting_models::{Setting, SettingChange};
+use crate::models::{with_connection, DbPool};
+use crate::services::auth_service::{Admin, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::settings_service::SettingsCache;
+
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct SettingValue {
+    pub value: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings",
+    tag = "settings",
+    responses((status = 200, description = "Every setting of the site", body = [Setting])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_settings(
+    pool: web::Data<DbPool>,
+    cache: web::Data<SettingsCache>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let settings = with_connection(pool, move |db| Ok(cache.all(db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{id}",
+    tag = "settings",
+    params(("id" = String, Path, description = "The setting's key")),
+    responses((status = 200, description = "The setting", body = Setting)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_setting(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    cache: web::Data<SettingsCache>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let key = id.clone();
+    let setting = Setting {
+        setting_key: id.clone(),
+        setting_value: with_connection(pool, move |db| Ok(cache.get(&key, db)?)).await?.ok_or(CustomHttpError::NotFound)?,
+    };
+
+    Ok(HttpResponse::Ok().json(setting))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/settings/{id}",
+    tag = "settings",
+    params(("id" = String, Path, description = "The setting's key")),
+    request_body = SettingValue,
+    responses((status = 200, description = "The setting as it was saved", body = Setting)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn set_setting(
+    id: web::Path<String>,
+    new: web::Json<SettingValue>,
+    pool: web::Data<DbPool>,
+    cache: web::Data<SettingsCache>,
+    claim: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (key, value) = (id.clone(), new.value.clone());
+    with_connection(pool, move |db| Ok(Setting::set(key, value, claim.0.sub, db)?)).await?;
+    cache.invalidate();
+
+    let setting = Setting {
+        setting_key: id.clone(),
+        setting_value: new.value.clone(),
+    };
+
+    Ok(HttpResponse::Ok().json(setting))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/settings/{id}",
+    tag = "settings",
+    params(("id" = String, Path, description = "The setting's key")),
+    responses((status = 200, description = "How many settings were removed", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_setting(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    cache: web::Data<SettingsCache>,
+    claim: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| Ok(Setting::unset(id.into_inner(), claim.0.sub, db)?)).await?;
+    cache.invalidate();
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/{id}/history",
+    tag = "settings",
+    params(("id" = String, Path, description = "The setting's key")),
+    responses((status = 200, description = "Every change made to the setting", body = [SettingChange])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_setting_history(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let changes = with_connection(pool, move |db| Ok(SettingChange::read_for_key(id.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(changes))
+}
+
+/// Reverts the setting touched by a change back to the value it had before that change.
+#[utoipa::path(
+    post,
+    path = "/api/v1/settings/changes/{id}/revert",
+    tag = "settings",
+    params(("id" = String, Path, description = "The change's uuid")),
+    responses((status = 200, description = "How many settings were changed back", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn revert_setting_change(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    cache: web::Data<SettingsCache>,
+    claim: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| Ok(SettingChange::revert(id.into_inner(), claim.0.sub, db)?)).await?;
+    cache.invalidate();
+
+    Ok(HttpResponse::Ok().json(res))
+}
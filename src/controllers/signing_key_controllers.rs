@@ -0,0 +1,82 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::models::signing_key_models::{MutSigningKey, SigningKey, SigningKeyDTO};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{generate_secret, Admin, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{list, PageQuery};
+
+/// Adds a new signing key. New tokens will be signed with it, while tokens signed by older active keys stay valid.
+#[utoipa::path(
+    post,
+    path = "/api/v1/keys",
+    tag = "signing keys",
+    responses((status = 201, description = "The key, without its secret", body = SigningKeyDTO)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_signing_key(
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let new_key = MutSigningKey {
+        kid: Uuid::new_v4().to_string(),
+        secret: generate_secret(),
+        active: true,
+        time_retired: None,
+    };
+
+    let key: SigningKeyDTO = with_connection(pool, move |db| {
+        SigningKey::create(&new_key, db)?;
+
+        Ok(SigningKey::read_one(new_key.kid, db)?.into())
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(key))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys",
+    tag = "signing keys",
+    params(PageQuery),
+    responses((status = 200, description = "Every key, or one page of them when `page` or `per_page` is set", body = [SigningKeyDTO])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_signing_keys(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let keys = with_connection(pool, move |db| {
+        list(&query, db, SigningKey::read_all, SigningKey::read_paginated)?
+            .try_map(|keys| Ok::<Vec<SigningKeyDTO>, CustomHttpError>(keys.into_iter().map(|k| k.into()).collect()))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+/// Retires a key. Any token signed with it will stop working. Tokens signed with `APP_JWT_KEY` are retired with
+/// `APP_JWT_KEY_TOKENS` instead.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/keys/{id}",
+    tag = "signing keys",
+    params(("id" = String, Path, description = "The key's `kid`")),
+    responses((status = 200, description = "How many keys were retired", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn retire_signing_key(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| Ok(SigningKey::retire(id.into_inner(), db)?)).await?;
+    if res == 0 {
+        return Err(CustomHttpError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,158 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::models::site_models::{MutSite, NewSite, Site};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Admin, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{list, PageQuery};
+use crate::services::site_service::{self, hostname, SiteTable, DEFAULT_SITE};
+use crate::services::validation_service::Validate;
+
+fn to_mut(uuid: String, new: NewSite) -> MutSite {
+    MutSite {
+        uuid,
+        name: new.name,
+        hostname: new.hostname.as_deref().map(hostname),
+        max_pages: new.max_pages,
+        max_modules: new.max_modules,
+        max_media_bytes: new.max_media_bytes,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/sites",
+    tag = "sites",
+    request_body = NewSite,
+    responses((status = 201, description = "The site as it was saved", body = Site)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_site(
+    new: web::Json<NewSite>,
+    pool: web::Data<DbPool>,
+    sites: web::Data<SiteTable>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    new.validate()?;
+
+    let new_site = to_mut(Uuid::new_v4().to_string(), new.into_inner());
+    let site = with_connection(pool, move |db| {
+        Site::create(&new_site, db)?;
+
+        Ok(Site::read_one(new_site.uuid, db)?)
+    })
+    .await?;
+    sites.invalidate();
+
+    Ok(HttpResponse::Created().json(site))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/sites",
+    tag = "sites",
+    params(PageQuery),
+    responses((status = 200, description = "Every site by name, or one page of them when `page` or `per_page` is set", body = [Site]))
+)]
+pub async fn get_sites(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let sites = with_connection(pool, move |db| list(&query, db, Site::read_all, Site::read_paginated)).await?;
+
+    Ok(HttpResponse::Ok().json(sites))
+}
+
+/// The site the request was made to, which everything else on the API is scoped to.
+#[utoipa::path(
+    get,
+    path = "/api/v1/sites/current",
+    tag = "sites",
+    responses((status = 200, description = "The site of the request's `Host`", body = Site))
+)]
+pub async fn get_current_site(pool: web::Data<DbPool>) -> Result<HttpResponse, CustomHttpError> {
+    let current = site_service::current_or_default();
+    let site = with_connection(pool, move |db| Ok(Site::read_one(current, db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(site))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/sites/{id}",
+    tag = "sites",
+    params(("id" = String, Path, description = "The site's uuid")),
+    responses((status = 200, description = "The site", body = Site))
+)]
+pub async fn get_site(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let site = with_connection(pool, move |db| Ok(Site::read_one(id.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(site))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/sites/{id}",
+    tag = "sites",
+    params(("id" = String, Path, description = "The site's uuid")),
+    request_body = NewSite,
+    responses((status = 200, description = "The site as it was saved", body = Site)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_site(
+    updated: web::Json<NewSite>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    sites: web::Data<SiteTable>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    updated.validate()?;
+
+    let updated = to_mut(id.into_inner(), updated.into_inner());
+    let site = with_connection(pool, move |db| {
+        Site::update(updated.uuid.clone(), &updated, db)?;
+
+        Ok(Site::read_one(updated.uuid, db)?)
+    })
+    .await?;
+    sites.invalidate();
+
+    Ok(HttpResponse::Ok().json(site))
+}
+
+/// Only sites without any pages, modules, or media can be deleted, and the default site never can.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/sites/{id}",
+    tag = "sites",
+    params(("id" = String, Path, description = "The site's uuid")),
+    responses((status = 200, description = "How many sites were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_site(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    sites: web::Data<SiteTable>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let id = id.into_inner();
+    if id == DEFAULT_SITE {
+        return Err(CustomHttpError::Conflict);
+    }
+
+    let res = with_connection(pool, move |db| {
+        if Site::has_content(&id, db)? {
+            return Err(CustomHttpError::Conflict);
+        }
+
+        Ok(Site::delete(id, db)?)
+    })
+    .await?;
+    sites.invalidate();
+
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,19 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::models::config_models::LocalConfig;
+use crate::models::{with_connection, DbPool};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::sitemap_service::{site_url, Sitemap};
+
+/// Serves `/sitemap.xml`, listing every published page for search engines.
+pub async fn get_sitemap(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    sitemap: web::Data<Sitemap>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let base = site_url(&req, &conf)?;
+    let xml = with_connection(pool, move |db| Ok(sitemap.render(&base, db)?)).await?;
+
+    Ok(HttpResponse::Ok().content_type("application/xml; charset=utf-8").body(xml))
+}
@@ -0,0 +1,29 @@
+use std::path::Path;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::models::config_models::LocalConfig;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::static_service::{self, file_in, DEFAULT_STATIC_DIR};
+use crate::services::theme_service::Themes;
+
+/// Serves the active theme's static files at `/assets`.
+pub async fn get_asset(
+    req: HttpRequest,
+    path: web::Path<String>,
+    themes: web::Data<Themes>,
+    conf: web::Data<LocalConfig>,
+) -> Result<HttpResponse, CustomHttpError> {
+    static_service::serve(&req, themes.asset(&path), conf.static_max_age)
+}
+
+/// Serves the files the site keeps whatever the theme, like uploads and favicons, at `/static`.
+pub async fn get_static(
+    req: HttpRequest,
+    path: web::Path<String>,
+    conf: web::Data<LocalConfig>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let root = conf.static_dir.as_deref().unwrap_or(DEFAULT_STATIC_DIR);
+
+    static_service::serve(&req, file_in(Path::new(root), &path), conf.static_max_age)
+}
@@ -0,0 +1,288 @@
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::models::config_models::LocalConfig;
+use crate::models::{with_connection, with_primary_connection, Databases, DbPool};
+use crate::services::auth_service::{Admin, Editor, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::job_service::{JobQueue, NewJob};
+use crate::services::publish_service::{PublishJob, PublishQueue};
+use crate::services::cache_service::CacheBackend;
+use crate::services::metrics_service::Metrics;
+use crate::services::migration_service::{self, SchemaState};
+use crate::services::warmup_service::Readiness;
+use crate::services::media_store_service::MediaStore;
+use crate::services::{db_health_service, gc_service, static_export_service, stats_service};
+
+/// For liveness probes. Only says that the process is answering, without going to the database, so that an outage of the
+/// database doesn't get every instance restarted.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "system",
+    responses((status = 200, description = "`{ \"alive\": true }`", body = Object))
+)]
+pub async fn get_health() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "alive": true }))
+}
+
+/// For load balancers and orchestrators. Responds with a 503 until the server has warmed up, so no traffic is sent to it before then,
+/// and again once it has started stopping, or whenever the database doesn't answer a `SELECT 1`.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "system",
+    responses(
+        (status = 200, description = "`{ \"ready\": true, \"database\": true }`", body = Object),
+        (status = 503, description = "Not ready, with `ready` and `database` saying why", body = Object)
+    )
+)]
+pub async fn get_readiness(
+    readiness: web::Data<Readiness>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let database = with_primary_connection(pool, |db| Ok(db_health_service::select_one(db)?)).await.is_ok();
+    let ready = readiness.is_ready() && database;
+    let body = serde_json::json!({ "ready": ready, "database": database });
+
+    match ready {
+        true => Ok(HttpResponse::Ok().json(body)),
+        false => Ok(HttpResponse::ServiceUnavailable().json(body)),
+    }
+}
+
+/// Request, pool, and cache metrics for Prometheus to scrape. When `APP_METRICS_TOKEN` is set, the scraper has to send it
+/// as `Authorization: Bearer <token>`.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "system",
+    responses((status = 200, description = "The metrics in Prometheus' text format", body = String, content_type = "text/plain")),
+    security((), ("metrics_token" = []))
+)]
+pub async fn get_metrics(
+    req: HttpRequest,
+    metrics: web::Data<Metrics>,
+    databases: web::Data<Databases>,
+    conf: web::Data<LocalConfig>,
+) -> Result<HttpResponse, CustomHttpError> {
+    if let Some(token) = &conf.metrics_token {
+        let sent = req.headers().get(header::AUTHORIZATION).and_then(|h| h.to_str().ok());
+        if sent.and_then(|h| h.strip_prefix("Bearer ")) != Some(token.as_str()) {
+            return Err(CustomHttpError::Unauthorized);
+        }
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render(&databases)))
+}
+
+/// Pool statistics together with a `SELECT 1` probe. Responds with a 503 if the probe fails.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/system/db",
+    tag = "system",
+    responses(
+        (status = 200, description = "The content database's pool and probe", body = DbHealth),
+        (status = 503, description = "The probe failed", body = DbHealth)
+    ),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_db_health(
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let health = db_health_service::health(&pool);
+
+    match health.probe.healthy {
+        true => Ok(HttpResponse::Ok().json(health)),
+        false => Ok(HttpResponse::ServiceUnavailable().json(health)),
+    }
+}
+
+/// The same as `get_db_health`, for one of the databases configured through `APP_DATABASES`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/system/db/{name}",
+    tag = "system",
+    params(("name" = String, Path, description = "The database's name in `APP_DATABASES`, or `replica`")),
+    responses(
+        (status = 200, description = "The database's pool and probe", body = DbHealth),
+        (status = 503, description = "The probe failed", body = DbHealth)
+    ),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_named_db_health(
+    name: web::Path<String>,
+    databases: web::Data<Databases>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    if !databases.is_configured(&name) {
+        return Err(CustomHttpError::NotFound);
+    }
+
+    let health = db_health_service::health(databases.get(&name));
+
+    match health.probe.healthy {
+        true => Ok(HttpResponse::Ok().json(health)),
+        false => Ok(HttpResponse::ServiceUnavailable().json(health)),
+    }
+}
+
+/// Reports orphaned data, unreferenced media, and expired tokens without removing them.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/system/gc",
+    tag = "system",
+    responses((status = 200, description = "What a run would remove", body = GcReport)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_gc_report(
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let report = with_connection(pool, |db| Ok(gc_service::report(db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/system/gc",
+    tag = "system",
+    responses((status = 200, description = "What was removed", body = GcReport)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn run_gc(
+    pool: web::Data<DbPool>,
+    store: web::Data<dyn MediaStore>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let report = with_connection(pool, move |db| Ok(gc_service::clean(db, &**store)?)).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Row counts and storage per table, plus the largest pages. Useful for capacity planning.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/system/stats",
+    tag = "system",
+    responses((status = 200, description = "The stats", body = SystemStats)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_system_stats(
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let stats = with_connection(pool, |db| Ok(stats_service::stats(db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+/// Counts of the site's content, its latest edits, and the webhook deliveries still waiting, for the dashboard of an admin panel.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/stats",
+    tag = "system",
+    responses((status = 200, description = "The stats", body = DashboardStats)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_dashboard_stats(
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let stats = with_connection(pool, |db| Ok(stats_service::dashboard(db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+/// Queues every page and asset to be pushed to the publish target, like after first setting one up.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/system/publish",
+    tag = "system",
+    responses((status = 202, description = "Everything was queued to be published")),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn publish_everything(
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    publish.send(PublishJob::Everything);
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Queues the site to be exported to `APP_STATIC_EXPORT_DIR`, like `radical export-static` does.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/system/export-static",
+    tag = "system",
+    responses((status = 202, description = "The export was queued as a job")),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn export_static(jobs: web::Data<JobQueue>, _: RequireRole<Admin>) -> Result<HttpResponse, CustomHttpError> {
+    jobs.send(NewJob::new(static_export_service::JOB, serde_json::json!({})));
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Every migration and whether it has been applied.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/system/migrations",
+    tag = "system",
+    responses((status = 200, description = "Every migration, and how many are pending", body = MigrationReport)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_migrations(
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let report = with_primary_connection(pool, |db| migration_service::status(db).map_err(|_| CustomHttpError::Unknown)).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Runs the pending migrations, after which changes are accepted again.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/system/migrations",
+    tag = "system",
+    responses((status = 200, description = "The migrations that were run", body = [MigrationStatus])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn run_migrations(
+    pool: web::Data<DbPool>,
+    schema: web::Data<SchemaState>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let ran = with_connection(pool, move |db| {
+        let ran = migration_service::run(db).map_err(|_| CustomHttpError::Unknown)?;
+        schema.refresh(db).map_err(|_| CustomHttpError::Unknown)?;
+
+        Ok(ran)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ran))
+}
+
+/// Empties every cache, like after changing the database by hand.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/system/cache",
+    tag = "system",
+    responses((status = 200, description = "Every cache was emptied")),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn clear_cache(
+    cache: web::Data<dyn CacheBackend>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    cache.clear("").map_err(|_| CustomHttpError::Unknown)?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,460 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::models::page_models::{Page, PageDTO};
+use crate::models::taxonomy_models::{MutPageCategory, MutTag, NewTerm, PageCategory, Tag};
+use crate::models::{with_connection, DbConnection, DbPool, Model};
+use crate::services::auth_service::{Editor, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{list, Listing, PageQuery, Paginated};
+use crate::services::publish_service::{PublishJob, PublishQueue};
+use crate::services::validation_service::{FieldError, Validate};
+
+// Pages show their own tags and categories, so the pages with one are published again when it is renamed or deleted.
+
+/// The published pages among `ids`, whole or one page of them like `list`.
+fn pages_among(ids: Vec<String>, query: &PageQuery, db: &DbConnection) -> Result<Listing<PageDTO>, CustomHttpError> {
+    if !query.is_requested() {
+        return Ok(Listing::All(Page::read_published_among(ids, db)?));
+    }
+
+    let (offset, limit) = query.window()?;
+    let (data, total) = Page::read_published_among_paginated(ids, offset, limit, db)?;
+
+    Ok(Listing::Page(Paginated::new(data, total, query)))
+}
+
+/// A 422 naming every one of `keys` that isn't the uuid or slug of one of `found`.
+fn check_found(field: &str, keys: &[String], found: &[(&str, &str)]) -> Result<(), CustomHttpError> {
+    let errors: Vec<FieldError> = keys
+        .iter()
+        .filter(|k| !found.iter().any(|(uuid, slug)| k == uuid || k == slug))
+        .map(|k| FieldError {
+            field: String::from(field),
+            message: format!("has no {}", k),
+        })
+        .collect();
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(CustomHttpError::Invalid(errors)),
+    }
+}
+
+fn publish_all(publish: &PublishQueue, pages: Vec<String>) {
+    for page in pages {
+        publish.send(PublishJob::Page(page));
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/tags",
+    tag = "taxonomies",
+    request_body = NewTerm,
+    responses((status = 201, description = "The tag as it was saved", body = Tag)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_tag(
+    new: web::Json<NewTerm>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    new.validate()?;
+
+    let new_tag = MutTag {
+        uuid: Uuid::new_v4().to_string(),
+        slug: new.slug(),
+        name: new.into_inner().name,
+    };
+    let tag = with_connection(pool, move |db| {
+        Tag::create(&new_tag, db)?;
+
+        Ok(Tag::read_one(new_tag.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(tag))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tags",
+    tag = "taxonomies",
+    params(PageQuery),
+    responses((status = 200, description = "Every tag by slug, or one page of them when `page` or `per_page` is set", body = [Tag]))
+)]
+pub async fn get_tags(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let tags = with_connection(pool, move |db| list(&query, db, Tag::read_all, Tag::read_paginated)).await?;
+
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tags/{tag}",
+    tag = "taxonomies",
+    params(("tag" = String, Path, description = "The tag's slug or uuid")),
+    responses((status = 200, description = "The tag", body = Tag))
+)]
+pub async fn get_tag(
+    tag: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let tag = with_connection(pool, move |db| Ok(Tag::read_one(tag.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(tag))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/tags/{tag}",
+    tag = "taxonomies",
+    params(("tag" = String, Path, description = "The tag's slug or uuid")),
+    request_body = NewTerm,
+    responses((status = 200, description = "The tag as it was saved", body = Tag)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_tag(
+    updated: web::Json<NewTerm>,
+    tag: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    updated.validate()?;
+
+    let (tag, pages) = with_connection(pool, move |db| {
+        let tag = Tag::read_one(tag.into_inner(), db)?;
+        let changed = MutTag {
+            uuid: tag.uuid.clone(),
+            slug: updated.slug(),
+            name: updated.into_inner().name,
+        };
+        Tag::update(tag.uuid.clone(), &changed, db)?;
+
+        Ok((Tag::read_one(tag.uuid.clone(), db)?, Tag::page_ids(&tag.uuid, db)?))
+    })
+    .await?;
+    publish_all(&publish, pages);
+
+    Ok(HttpResponse::Ok().json(tag))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/tags/{tag}",
+    tag = "taxonomies",
+    params(("tag" = String, Path, description = "The tag's slug or uuid")),
+    responses((status = 200, description = "How many tags were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_tag(
+    tag: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (res, pages) = with_connection(pool, move |db| {
+        let tag = Tag::read_one(tag.into_inner(), db)?;
+        let pages = Tag::page_ids(&tag.uuid, db)?;
+
+        Ok((Tag::delete(tag.uuid, db)?, pages))
+    })
+    .await?;
+    publish_all(&publish, pages);
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tags/{tag}/pages",
+    tag = "taxonomies",
+    params(("tag" = String, Path, description = "The tag's slug or uuid"), PageQuery),
+    responses((status = 200, description = "The published pages with the tag, newest first, or one page of them when `page` or `per_page` is set", body = [PageDTO]))
+)]
+pub async fn get_tag_pages(
+    tag: web::Path<String>,
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let pages = with_connection(pool, move |db| {
+        let tag = Tag::read_one(tag.into_inner(), db)?;
+
+        pages_among(Tag::page_ids(&tag.uuid, db)?, &query, db)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(pages))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}/tags",
+    tag = "taxonomies",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "The page's tags by slug", body = [Tag]))
+)]
+pub async fn get_page_tags(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let tags = with_connection(pool, move |db| {
+        let page = Page::read_one(id.into_inner(), db)?;
+
+        Ok(Tag::read_for_page(&page.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+/// Takes the slugs or uuids of every tag the page should have, taking off the ones it had that aren't among them.
+#[utoipa::path(
+    put,
+    path = "/api/v1/pages/{id}/tags",
+    tag = "taxonomies",
+    params(("id" = String, Path, description = "The page's uuid")),
+    request_body = [String],
+    responses((status = 200, description = "The page's tags by slug", body = [Tag])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn set_page_tags(
+    keys: web::Json<Vec<String>>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let page_uuid = id.into_inner();
+    let page_id = page_uuid.clone();
+
+    let tags = with_connection(pool, move |db| {
+        let page = Page::read_one(page_id, db)?;
+        let found = Tag::read_many(&keys, db)?;
+        let names: Vec<(&str, &str)> = found.iter().map(|t| (t.uuid.as_str(), t.slug.as_str())).collect();
+        check_found("tags", &keys, &names)?;
+
+        let ids: Vec<String> = found.iter().map(|t| t.uuid.clone()).collect();
+        Tag::set_for_page(&page.uuid, &ids, db)?;
+
+        Ok(Tag::read_for_page(&page.uuid, db)?)
+    })
+    .await?;
+    publish.send(PublishJob::Page(page_uuid));
+
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/categories",
+    tag = "taxonomies",
+    request_body = NewTerm,
+    responses((status = 201, description = "The category as it was saved", body = PageCategory)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_page_category(
+    new: web::Json<NewTerm>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    new.validate()?;
+
+    let new_category = MutPageCategory {
+        uuid: Uuid::new_v4().to_string(),
+        slug: new.slug(),
+        name: new.into_inner().name,
+    };
+    let category = with_connection(pool, move |db| {
+        PageCategory::create(&new_category, db)?;
+
+        Ok(PageCategory::read_one(new_category.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(category))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/categories",
+    tag = "taxonomies",
+    params(PageQuery),
+    responses((status = 200, description = "Every category by slug, or one page of them when `page` or `per_page` is set", body = [PageCategory]))
+)]
+pub async fn get_page_categories(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let categories = with_connection(pool, move |db| {
+        list(&query, db, PageCategory::read_all, PageCategory::read_paginated)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/categories/{category}",
+    tag = "taxonomies",
+    params(("category" = String, Path, description = "The category's slug or uuid")),
+    responses((status = 200, description = "The category", body = PageCategory))
+)]
+pub async fn get_page_category(
+    category: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let category = with_connection(pool, move |db| Ok(PageCategory::read_one(category.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(category))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/categories/{category}",
+    tag = "taxonomies",
+    params(("category" = String, Path, description = "The category's slug or uuid")),
+    request_body = NewTerm,
+    responses((status = 200, description = "The category as it was saved", body = PageCategory)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_page_category(
+    updated: web::Json<NewTerm>,
+    category: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    updated.validate()?;
+
+    let (category, pages) = with_connection(pool, move |db| {
+        let category = PageCategory::read_one(category.into_inner(), db)?;
+        let changed = MutPageCategory {
+            uuid: category.uuid.clone(),
+            slug: updated.slug(),
+            name: updated.into_inner().name,
+        };
+        PageCategory::update(category.uuid.clone(), &changed, db)?;
+
+        Ok((
+            PageCategory::read_one(category.uuid.clone(), db)?,
+            PageCategory::page_ids(&category.uuid, db)?,
+        ))
+    })
+    .await?;
+    publish_all(&publish, pages);
+
+    Ok(HttpResponse::Ok().json(category))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/categories/{category}",
+    tag = "taxonomies",
+    params(("category" = String, Path, description = "The category's slug or uuid")),
+    responses((status = 200, description = "How many categories were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_page_category(
+    category: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let (res, pages) = with_connection(pool, move |db| {
+        let category = PageCategory::read_one(category.into_inner(), db)?;
+        let pages = PageCategory::page_ids(&category.uuid, db)?;
+
+        Ok((PageCategory::delete(category.uuid, db)?, pages))
+    })
+    .await?;
+    publish_all(&publish, pages);
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/categories/{category}/pages",
+    tag = "taxonomies",
+    params(("category" = String, Path, description = "The category's slug or uuid"), PageQuery),
+    responses((status = 200, description = "The published pages in the category, newest first, or one page of them when `page` or `per_page` is set", body = [PageDTO]))
+)]
+pub async fn get_page_category_pages(
+    category: web::Path<String>,
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let pages = with_connection(pool, move |db| {
+        let category = PageCategory::read_one(category.into_inner(), db)?;
+
+        pages_among(PageCategory::page_ids(&category.uuid, db)?, &query, db)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(pages))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{id}/categories",
+    tag = "taxonomies",
+    params(("id" = String, Path, description = "The page's uuid")),
+    responses((status = 200, description = "The page's categories by slug", body = [PageCategory]))
+)]
+pub async fn get_categories_of_page(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let categories = with_connection(pool, move |db| {
+        let page = Page::read_one(id.into_inner(), db)?;
+
+        Ok(PageCategory::read_for_page(&page.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+/// Takes the slugs or uuids of every category the page should be in, taking it out of the ones it was in that aren't among them.
+#[utoipa::path(
+    put,
+    path = "/api/v1/pages/{id}/categories",
+    tag = "taxonomies",
+    params(("id" = String, Path, description = "The page's uuid")),
+    request_body = [String],
+    responses((status = 200, description = "The page's categories by slug", body = [PageCategory])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn set_categories_of_page(
+    keys: web::Json<Vec<String>>,
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    publish: web::Data<PublishQueue>,
+    _: RequireRole<Editor>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let page_uuid = id.into_inner();
+    let page_id = page_uuid.clone();
+
+    let categories = with_connection(pool, move |db| {
+        let page = Page::read_one(page_id, db)?;
+        let found = PageCategory::read_many(&keys, db)?;
+        let names: Vec<(&str, &str)> = found.iter().map(|c| (c.uuid.as_str(), c.slug.as_str())).collect();
+        check_found("categories", &keys, &names)?;
+
+        let ids: Vec<String> = found.iter().map(|c| c.uuid.clone()).collect();
+        PageCategory::set_for_page(&page.uuid, &ids, db)?;
+
+        Ok(PageCategory::read_for_page(&page.uuid, db)?)
+    })
+    .await?;
+    publish.send(PublishJob::Page(page_uuid));
+
+    Ok(HttpResponse::Ok().json(categories))
+}
@@ -0,0 +1,91 @@
+use std::sync::Mutex;
+
+use actix_web::{web, HttpResponse};
+use diesel::OptionalExtension;
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::setting_models::Setting;
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{Admin, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::page_cache_service::PageCache;
+use crate::services::settings_service::SettingsCache;
+use crate::services::site_service::{self, DEFAULT_SITE};
+use crate::services::theme_service::{self, Themes, THEME_SETTING};
+
+#[derive(Serialize, ToSchema)]
+pub struct ThemeList {
+    /// `None` when the templates come from `./templates`.
+    pub active: Option<String>,
+    pub themes: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ActiveTheme {
+    pub name: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/themes",
+    tag = "themes",
+    responses((status = 200, description = "The installed themes and the active one", body = ThemeList)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_themes(themes: web::Data<Themes>, _: RequireRole<Admin>) -> Result<HttpResponse, CustomHttpError> {
+    Ok(HttpResponse::Ok().json(ThemeList {
+        active: themes.active(),
+        themes: theme_service::list(),
+    }))
+}
+
+/// Switches the theme pages are rendered with, or back to `./templates` with a `null` name, without a restart.
+/// The theme is saved as the `theme` setting, so it is kept the next time the server starts.
+#[utoipa::path(
+    put,
+    path = "/api/v1/themes/active",
+    tag = "themes",
+    request_body = ActiveTheme,
+    responses((status = 200, description = "The installed themes and the one now active", body = ThemeList)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn set_theme(
+    new: web::Json<ActiveTheme>,
+    pool: web::Data<DbPool>,
+    themes: web::Data<Themes>,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+    settings: web::Data<SettingsCache>,
+    pages: web::Data<PageCache>,
+    claim: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let name = new.into_inner().name;
+    theme_service::check(name.as_deref())?;
+
+    let (setting, actor) = (name.clone(), claim.0.sub);
+    // the theme is the same for every site, so it is kept with the default site's settings.
+    with_connection(pool, move |db| {
+        site_service::scoped(Some(String::from(DEFAULT_SITE)), || {
+            match setting {
+                Some(value) => Setting::set(THEME_SETTING.to_string(), value, actor, db)?,
+                None => match Setting::read_one(THEME_SETTING.to_string(), db).optional()? {
+                    Some(_) => Setting::unset(THEME_SETTING.to_string(), actor, db)?,
+                    None => 0,
+                },
+            };
+
+            Ok(())
+        })
+    })
+    .await?;
+    settings.invalidate();
+
+    themes.switch(name, &hb)?;
+    pages.clear();
+
+    Ok(HttpResponse::Ok().json(ThemeList {
+        active: themes.active(),
+        themes: theme_service::list(),
+    }))
+}
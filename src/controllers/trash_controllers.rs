@@ -0,0 +1,19 @@
+use actix_web::{web, HttpResponse};
+
+use crate::models::{with_connection, DbPool};
+use crate::services::auth_service::Claims;
+use crate::services::errors_service::CustomHttpError;
+use crate::services::trash_service;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/trash",
+    tag = "trash",
+    responses((status = 200, description = "Everything in the trash", body = Trash)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_trash(pool: web::Data<DbPool>, claim: Claims) -> Result<HttpResponse, CustomHttpError> {
+    let trash = with_connection(pool, move |db| trash_service::read(&claim, db)).await?;
+
+    Ok(HttpResponse::Ok().json(trash))
+}
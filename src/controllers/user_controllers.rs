@@ -4,50 +4,94 @@ use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
-use crate::models::user_models::{MutUser, User};
-use crate::models::{pool_handler, Model, MySQLPool};
-use crate::services::auth_service::{authenticate, encrypt, encrypt_password, Claims};
+use crate::models::config_models::LocalConfig;
+use crate::models::recovery_code_models::RecoveryCode;
+use crate::models::user_models::{
+    ForgotPassword, LoginBody, MutUser, MutUserRole, RecoveryCodes, ResetPassword, TwoFactorCode, TwoFactorSetup, User,
+    UserRole,
+};
+use crate::models::{with_connection, with_primary_connection, DbConnection, DbPool, Model};
+use crate::services::auth_service::{authenticate, encrypt, encrypt_password, Admin, Claims, RequireRole};
+use crate::services::cache_service::CacheBackend;
 use crate::services::errors_service::CustomHttpError;
-
+use crate::services::mail_service::MailQueue;
+use crate::services::{password_reset_service, two_factor_service};
+use crate::services::validation_service::FieldError;
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/user",
+    tag = "users",
+    request_body = MutUser,
+    responses((status = 201, description = "The user that was made", body = MutUser)),
+    security(("token" = []), ("session" = []))
+)]
 pub async fn create_user(
     new: web::Json<MutUser>,
-    pool: web::Data<MySQLPool>,
-    _: Claims,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    if new.role.as_deref().is_some_and(|r| UserRole::parse(r).is_none()) {
+        return Err(CustomHttpError::BadRequest);
+    }
 
     let mut salted_user = new.clone();
-    let encrypted_password = encrypt_password(&salted_user.password.unwrap())?;
-    salted_user.password = Some(encrypted_password);
-    salted_user.uuid = Some(Uuid::new_v4().to_string());
 
-    User::create(&salted_user, &mysql_pool)?;
+    with_connection(pool, move |db| {
+        let encrypted_password = encrypt_password(&salted_user.password.unwrap())?;
+        salted_user.password = Some(encrypted_password);
+        salted_user.uuid = Some(Uuid::new_v4().to_string());
+
+        Ok(User::create(&salted_user, db)?)
+    })
+    .await?;
 
     Ok(HttpResponse::Created().json(&new.clone()))
 }
 
+/// Users can read themselves, and admins anyone.
+#[utoipa::path(
+    get,
+    path = "/api/v1/user/{id}",
+    tag = "users",
+    params(("id" = String, Path, description = "The username")),
+    responses((status = 200, description = "The user, without their password or token", body = MutUser)),
+    security(("token" = []), ("session" = []))
+)]
 pub async fn get_user(
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
-    _: Claims,
+    pool: web::Data<DbPool>,
+    claim: Claims,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    if *id != claim.sub && claim.role < UserRole::Admin {
+        return Err(CustomHttpError::Forbidden);
+    }
 
-    let user: User = User::read_one(id.clone(), &mysql_pool)?;
+    let user: User = with_connection(pool, move |db| Ok(User::read_one(id.into_inner(), db)?)).await?;
 
     Ok(HttpResponse::Ok().json(&user))
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/v1/user/{id}",
+    tag = "users",
+    params(("id" = String, Path, description = "The username")),
+    request_body = MutUser,
+    responses((status = 200, description = "The user as it was saved", body = MutUser)),
+    security(("token" = []), ("session" = []))
+)]
 pub async fn update_user(
     id: web::Path<String>,
     new: web::Json<MutUser>,
-    pool: web::Data<MySQLPool>,
+    pool: web::Data<DbPool>,
     claim: Claims,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
-
     // TODO maybe make this only happen whenever the password changes?
     let mut salted_user = new.clone();
+    // users can't raise their own role, which only an admin can change, or take over another user's uuid.
+    salted_user.role = None;
+    salted_user.uuid = None;
 
     // if you're trying to change someone elses data, don't allow it.
     if id.clone() != claim.sub {
@@ -55,102 +99,210 @@ pub async fn update_user(
         return Ok(HttpResponse::Unauthorized().finish());
     }
 
-    let encrypted_password = encrypt_password(&salted_user.password.unwrap())?;
-    salted_user.password = Some(encrypted_password);
+    let token_enc = with_connection(pool, move |db| {
+        let encrypted_password = encrypt_password(&salted_user.password.unwrap())?;
+        salted_user.password = Some(encrypted_password);
 
-    let exp_time = chrono::Utc::now() + chrono::Duration::days(10);
+        let exp_time = chrono::Utc::now() + chrono::Duration::days(10);
 
-    // give them a new token just in case they update their username.
-    let claim = Claims {
-        exp: (exp_time).timestamp() as usize,
-        sub: salted_user.username.clone(),
-    };
+        // give them a new token just in case they update their username.
+        let claim = Claims {
+            exp: (exp_time).timestamp() as usize,
+            sub: salted_user.username.clone(),
+            role: UserRole::default(),
+        };
+
+        let token_enc = encrypt(claim, db)?;
+        salted_user.token = Some(token_enc.clone());
+        User::update(id.into_inner(), &salted_user, db)?;
+
+        Ok(token_enc)
+    })
+    .await?;
 
     let time: OffsetDateTime = OffsetDateTime::now_utc() + Duration::hour();
 
-    let token_enc = encrypt(claim)?;
     let cookie = Cookie::build("auth", &token_enc)
         .expires(time)
         .path("/")
         .finish();
 
-    let user = HttpResponse::Ok().cookie(cookie).json(&new.clone());
-    salted_user.token = Some(token_enc);
-    User::update(id.clone(), &salted_user, &mysql_pool)?;
-
-    Ok(user)
+    Ok(HttpResponse::Ok().cookie(cookie).json(&new.clone()))
 }
 
+/// Deletes a user. Admins can't delete themselves, so that there is always one left.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/user/{id}",
+    tag = "users",
+    params(("id" = String, Path, description = "The username")),
+    responses((status = 200, description = "How many users were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
 pub async fn delete_user(
     id: web::Path<String>,
-    pool: web::Data<MySQLPool>,
-    _: Claims,
+    pool: web::Data<DbPool>,
+    admin: RequireRole<Admin>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    if *id == admin.0.sub {
+        return Err(CustomHttpError::BadRequest);
+    }
 
-    let res = User::delete(id.clone(), &mysql_pool)?;
+    let res = with_connection(pool, move |db| Ok(User::delete(id.into_inner(), db)?)).await?;
 
     Ok(HttpResponse::Ok().json(res))
 }
 
+/// Gives a user another role. Admins can't take their own away, so that there is always one left.
+#[utoipa::path(
+    put,
+    path = "/api/v1/user/{id}/role",
+    tag = "users",
+    params(("id" = String, Path, description = "The username")),
+    request_body = MutUserRole,
+    responses((status = 200, description = "The new role", body = MutUserRole)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn update_user_role(
+    id: web::Path<String>,
+    new: web::Json<MutUserRole>,
+    pool: web::Data<DbPool>,
+    admin: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let role = UserRole::parse(&new.role).ok_or(CustomHttpError::BadRequest)?;
+    if *id == admin.0.sub && role != UserRole::Admin {
+        return Err(CustomHttpError::BadRequest);
+    }
+
+    with_connection(pool, move |db| match User::set_role(id.into_inner(), role, db)? {
+        0 => Err(CustomHttpError::NotFound),
+        res => Ok(res),
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(&new.into_inner()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/login",
+    tag = "users",
+    request_body = LoginBody,
+    responses(
+        (status = 200, description = "Logged in, with the token in the `auth` cookie"),
+        (status = 401, description = "The password or two-factor code is wrong, or the user has two-factor authentication and no `code` was sent")
+    )
+)]
 pub async fn login(
-    user: web::Json<MutUser>,
-    pool: web::Data<MySQLPool>,
+    body: web::Json<LoginBody>,
+    pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
+    let LoginBody { user, code } = body.into_inner();
+    let outcome = with_connection(pool, move |db| log_in(user, code, db)).await?;
+
+    match outcome {
+        LoginOutcome::Forbidden => Ok(HttpResponse::Forbidden().finish()),
+        LoginOutcome::FirstLogin(cookie) => Ok(HttpResponse::Accepted().cookie(cookie).finish()),
+        LoginOutcome::LoggedIn(cookie) => Ok(HttpResponse::Ok().cookie(cookie).finish()),
+        LoginOutcome::CodeRequired => Ok(HttpResponse::Unauthorized().json(CODE_REQUIRED)),
+        LoginOutcome::Failed => Ok(HttpResponse::Unauthorized().json("Failed to authenticate.")),
+    }
+}
+
+/// Sent back when the password was right but the user has two-factor authentication, so that clients know to ask for a code.
+pub const CODE_REQUIRED: &str = "A two-factor code is required.";
+
+/// Checks the user's password, and their two-factor `code` if they turned it on, and gives them a new token,
+/// which is shared by `login` and sessions.
+pub fn log_in(user: MutUser, code: Option<String>, db: &DbConnection) -> Result<LoginOutcome, CustomHttpError> {
     let arg = Argon2::default();
 
-    let read_user = User::read_one(user.username.clone(), &mysql_pool)?;
+    let read_user = User::read_one(user.username.clone(), db)?;
 
     let is_default = read_user.username == "root" && read_user.password == "";
 
     // if you're trying to login to a root user more than once with no password set, send back a forbidden.
     if read_user.token.is_some() && is_default {
-        return Ok(HttpResponse::Forbidden().finish());
+        return Ok(LoginOutcome::Forbidden);
     }
 
     // default password handler.
     if is_default {
         let mut new_user = user.clone();
-        let cookie = login_res(&mut new_user)?;
-
-        let cookie_response = HttpResponse::Accepted().cookie(cookie.clone()).finish();
+        let cookie = login_res(&mut new_user, db)?;
 
         new_user.token = Some(cookie.value().to_string());
 
-        User::update_with_token(&new_user, &mysql_pool)?;
+        User::update_with_token(&new_user, db)?;
 
-        return Ok(cookie_response);
+        return Ok(LoginOutcome::FirstLogin(cookie));
     }
-    let read_user_password = PasswordHash::new(&read_user.password).unwrap();
+    let read_user_password = match PasswordHash::new(&read_user.password) {
+        Ok(hash) => hash,
+        Err(_) => return Ok(LoginOutcome::Failed),
+    };
 
     match arg.verify_password(
         user.password.clone().unwrap().as_bytes(),
         &read_user_password,
     ) {
+        Ok(_) if read_user.totp_enabled && code.is_none() => Ok(LoginOutcome::CodeRequired),
+        Ok(_) if read_user.totp_enabled && !two_factor_service::verify(&read_user, code.as_deref().unwrap_or_default(), db)? => {
+            Ok(LoginOutcome::Failed)
+        }
         Ok(_) => {
             let mut new_user = user;
-            let cookie = login_res(&mut new_user)?;
-
-            let cookie_response = HttpResponse::Ok().cookie(cookie.clone()).finish();
+            let cookie = login_res(&mut new_user, db)?;
 
             new_user.token = Some(cookie.value().to_string());
 
-            User::update_with_token(&new_user, &mysql_pool)?;
+            User::update_with_token(&new_user, db)?;
 
-            Ok(cookie_response)
+            Ok(LoginOutcome::LoggedIn(cookie))
         }
-        _ => Ok(HttpResponse::Unauthorized().json("Failed to authenticate.")),
+        _ => Ok(LoginOutcome::Failed),
     }
 }
 
-fn login_res(user: &mut MutUser) -> Result<Cookie, CustomHttpError> {
+/// Logs a user in without their password, once something else has vouched for them, like an OIDC issuer.
+pub fn log_in_as(username: String, db: &DbConnection) -> Result<Cookie<'static>, CustomHttpError> {
+    let mut user = MutUser {
+        uuid: None,
+        username,
+        password: None,
+        token: None,
+        email: None,
+        role: None,
+    };
+    let cookie = login_res(&mut user, db)?;
+
+    user.token = Some(cookie.value().to_string());
+    User::update_with_token(&user, db)?;
+
+    Ok(cookie)
+}
+
+/// How a login went. Responses can't leave the blocking pool, so `login` builds its response from this.
+pub enum LoginOutcome {
+    Forbidden,
+    /// The default root user logged in without a password for the first time.
+    FirstLogin(Cookie<'static>),
+    LoggedIn(Cookie<'static>),
+    /// The password was right, but the user has two-factor authentication and sent no code.
+    CodeRequired,
+    Failed,
+}
+
+fn login_res(user: &mut MutUser, db: &DbConnection) -> Result<Cookie<'static>, CustomHttpError> {
     let claim = Claims {
         exp: (chrono::Utc::now() + chrono::Duration::days(10)).timestamp() as usize,
         sub: user.username.clone(),
+        role: UserRole::default(),
     };
     user.password = None;
-    let token_enc = encrypt(claim)?;
+    // a login can't be used to change the user's role.
+    user.role = None;
+    let token_enc = encrypt(claim, db)?;
 
     let time: OffsetDateTime = OffsetDateTime::now_utc() + Duration::hour();
     let cookie = Cookie::build("auth", token_enc)
@@ -161,6 +313,12 @@ fn login_res(user: &mut MutUser) -> Result<Cookie, CustomHttpError> {
     Ok(cookie)
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/user/logout",
+    tag = "users",
+    responses((status = 200, description = "Logged out"))
+)]
 pub async fn logout() -> Result<HttpResponse, CustomHttpError> {
     let cookie = Cookie::build("auth", "")
         .expires(OffsetDateTime::now_utc())
@@ -170,17 +328,232 @@ pub async fn logout() -> Result<HttpResponse, CustomHttpError> {
     Ok(HttpResponse::Ok().cookie(cookie).finish())
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/user",
+    tag = "users",
+    responses((status = 200, description = "The token is valid")),
+    security((), ("token" = []), ("session" = []))
+)]
 pub async fn check_login(
     req: HttpRequest,
-    pool: web::Data<MySQLPool>,
+    pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, CustomHttpError> {
-    let mysql_pool = pool_handler(pool)?;
-    let auth_header = req.headers().get("authorization");
+    let auth_header = req.headers().get("authorization").unwrap().clone();
 
-    let auth_res = authenticate(auth_header.unwrap(), &mysql_pool).await;
+    let auth_res = with_primary_connection(pool, move |db| authenticate(&auth_header, db)).await;
 
     match auth_res {
         Ok(_) => Ok(HttpResponse::Ok().finish()),
         Err(_) => Ok(HttpResponse::Unauthorized().finish()),
     }
 }
+
+fn wrong_code() -> CustomHttpError {
+    CustomHttpError::Invalid(vec![FieldError {
+        field: String::from("code"),
+        message: String::from("isn't a current code of the secret"),
+    }])
+}
+
+/// Starts turning on two-factor authentication with a new secret, which logins don't ask a code of until it is
+/// confirmed with `POST /api/v1/user/2fa/enable`. Turn it off first to change the secret of a user that has it on.
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/2fa/setup",
+    tag = "users",
+    responses(
+        (status = 200, description = "The secret, and the `otpauth://` URI to show as a QR code", body = TwoFactorSetup),
+        (status = 409, description = "The user already has two-factor authentication")
+    ),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn setup_two_factor(pool: web::Data<DbPool>, claim: Claims) -> Result<HttpResponse, CustomHttpError> {
+    let setup = with_connection(pool, move |db| {
+        let user = User::read_one(claim.sub, db)?;
+        if user.totp_enabled {
+            return Err(CustomHttpError::Conflict);
+        }
+
+        let secret = two_factor_service::generate_secret();
+        User::set_totp(user.username.clone(), Some(secret.clone()), false, db)?;
+
+        Ok(TwoFactorSetup {
+            otpauth_uri: two_factor_service::otpauth_uri(&user.username, &secret),
+            secret,
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(setup))
+}
+
+/// Turns two-factor authentication on once a code from the new secret shows the app was set up with it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/2fa/enable",
+    tag = "users",
+    request_body = TwoFactorCode,
+    responses(
+        (status = 200, description = "Two-factor authentication is on, with the recovery codes", body = RecoveryCodes),
+        (status = 409, description = "The user already has two-factor authentication")
+    ),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn enable_two_factor(
+    body: web::Json<TwoFactorCode>,
+    pool: web::Data<DbPool>,
+    claim: Claims,
+) -> Result<HttpResponse, CustomHttpError> {
+    let codes = with_connection(pool, move |db| {
+        let user = User::read_one(claim.sub, db)?;
+        if user.totp_enabled {
+            return Err(CustomHttpError::Conflict);
+        }
+        let secret = user.totp_secret.clone().ok_or(CustomHttpError::BadRequest)?;
+        let step = two_factor_service::check_code(&secret, &body.code, None).ok_or_else(wrong_code)?;
+
+        User::set_totp(user.username.clone(), Some(secret), true, db)?;
+        User::set_totp_last_step(user.username.clone(), step, db)?;
+
+        two_factor_service::replace_recovery_codes(&user, db)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodes { recovery_codes: codes }))
+}
+
+/// Turns two-factor authentication off, with a code or a recovery code to show it is the user doing so.
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/2fa/disable",
+    tag = "users",
+    request_body = TwoFactorCode,
+    responses((status = 200, description = "Two-factor authentication is off")),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn disable_two_factor(
+    body: web::Json<TwoFactorCode>,
+    pool: web::Data<DbPool>,
+    claim: Claims,
+) -> Result<HttpResponse, CustomHttpError> {
+    with_connection(pool, move |db| {
+        let user = User::read_one(claim.sub, db)?;
+        if !user.totp_enabled || !two_factor_service::verify(&user, &body.code, db)? {
+            return Err(wrong_code());
+        }
+
+        User::set_totp(user.username.clone(), None, false, db)?;
+        Ok(RecoveryCode::delete_for_user(&user.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// New recovery codes in place of the user's old ones, with a code or a recovery code to show it is the user asking.
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/2fa/recovery-codes",
+    tag = "users",
+    request_body = TwoFactorCode,
+    responses((status = 200, description = "The new recovery codes", body = RecoveryCodes)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn replace_recovery_codes(
+    body: web::Json<TwoFactorCode>,
+    pool: web::Data<DbPool>,
+    claim: Claims,
+) -> Result<HttpResponse, CustomHttpError> {
+    let codes = with_connection(pool, move |db| {
+        let user = User::read_one(claim.sub, db)?;
+        if !user.totp_enabled || !two_factor_service::verify(&user, &body.code, db)? {
+            return Err(wrong_code());
+        }
+
+        two_factor_service::replace_recovery_codes(&user, db)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodes { recovery_codes: codes }))
+}
+
+/// Turns another user's two-factor authentication off, for when they've lost both their app and their recovery codes.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/user/{id}/2fa",
+    tag = "users",
+    params(("id" = String, Path, description = "The username")),
+    responses((status = 200, description = "Two-factor authentication is off")),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn reset_two_factor(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    with_connection(pool, move |db| {
+        let user = User::read_one(id.into_inner(), db)?;
+
+        User::set_totp(user.username.clone(), None, false, db)?;
+        Ok(RecoveryCode::delete_for_user(&user.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Mails a token that sets a new password to every user with the email, if mail is set up. The answer is the same
+/// whether anyone has the email or not. Each IP can ask for 5 an hour.
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/forgot",
+    tag = "users",
+    request_body = ForgotPassword,
+    responses(
+        (status = 202, description = "A mail is on its way, if there is a user with the email"),
+        (status = 429, description = "Too many resets were asked for from the IP this hour")
+    )
+)]
+pub async fn forgot_password(
+    req: HttpRequest,
+    body: web::Json<ForgotPassword>,
+    pool: web::Data<DbPool>,
+    conf: web::Data<LocalConfig>,
+    cache: web::Data<dyn CacheBackend>,
+    mail: web::Data<MailQueue>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let client = req.peer_addr().map(|addr| addr.ip().to_string());
+
+    let mails = with_connection(pool, move |db| {
+        if let Some(client) = client {
+            password_reset_service::check_rate(&client, &**cache)?;
+        }
+
+        password_reset_service::request(body.email.trim(), &conf, db)
+    })
+    .await?;
+
+    for m in mails {
+        mail.send(m);
+    }
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Sets a new password with the token from the mail, which logs the user out everywhere. Two-factor authentication stays on.
+#[utoipa::path(
+    post,
+    path = "/api/v1/user/reset",
+    tag = "users",
+    request_body = ResetPassword,
+    responses((status = 200, description = "The password was changed"))
+)]
+pub async fn reset_password(
+    body: web::Json<ResetPassword>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, CustomHttpError> {
+    with_connection(pool, move |db| password_reset_service::reset(&body.token, &body.password, db)).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
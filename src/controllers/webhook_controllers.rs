@@ -0,0 +1,99 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::webhook_models::{MutWebhook, Webhook, EVENTS};
+use crate::models::{with_connection, DbPool, Model};
+use crate::services::auth_service::{generate_secret, Admin, RequireRole};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::pagination_service::{list, PageQuery};
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewWebhook {
+    pub name: String,
+    pub url: String,
+    pub events: Vec<String>,
+}
+
+/// The only time the secret is ever sent back.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    request_body = NewWebhook,
+    responses((status = 201, description = "The webhook, with the secret its deliveries are signed with, which is only ever sent this once", body = CreatedWebhook)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn create_webhook(
+    new: web::Json<NewWebhook>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let url = url::Url::parse(&new.url).map_err(|_| CustomHttpError::BadRequest)?;
+    let events_known = new.events.iter().all(|e| e == "*" || EVENTS.contains(&e.as_str()));
+    if !matches!(url.scheme(), "http" | "https") || new.events.is_empty() || !events_known {
+        return Err(CustomHttpError::BadRequest);
+    }
+
+    let new_webhook = MutWebhook {
+        uuid: Uuid::new_v4().to_string(),
+        name: new.name.clone(),
+        url: url.to_string(),
+        secret: generate_secret(),
+        events: new.events.join(","),
+    };
+
+    let secret = new_webhook.secret.clone();
+    let webhook = with_connection(pool, move |db| {
+        Webhook::create(&new_webhook, db)?;
+
+        Ok(Webhook::read_one(new_webhook.uuid, db)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(CreatedWebhook { webhook, secret }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    params(PageQuery),
+    responses((status = 200, description = "Every webhook, or one page of them when `page` or `per_page` is set", body = [Webhook])),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn get_webhooks(
+    pool: web::Data<DbPool>,
+    query: web::Query<PageQuery>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let webhooks = with_connection(pool, move |db| list(&query, db, Webhook::read_all, Webhook::read_paginated)).await?;
+
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = String, Path, description = "The webhook's uuid")),
+    responses((status = 200, description = "How many webhooks were deleted", body = usize)),
+    security(("token" = []), ("session" = []))
+)]
+pub async fn delete_webhook(
+    id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    _: RequireRole<Admin>,
+) -> Result<HttpResponse, CustomHttpError> {
+    let res = with_connection(pool, move |db| Ok(Webhook::delete(id.into_inner(), db)?)).await?;
+
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,172 @@
+use std::net::SocketAddr;
+
+use tonic::{transport::Server, Request, Response, Status};
+
+use crate::models::module_models::{FieldsDTO, Module};
+use crate::models::page_models::{Page, PageDTO};
+use crate::models::{DbConnection, DbPool, Model};
+use crate::services::encryption_service::{reveal_fields, reveal_module};
+use crate::services::sanitize_service::{on_render_fields, on_render_module};
+use crate::services::visibility_service::{require_published, require_published_module, require_visible, strip_fields, Role};
+use crate::services::errors_service::CustomHttpError;
+
+pub mod proto {
+    tonic::include_proto!("radical.content");
+}
+
+use proto::content_server::{Content, ContentServer};
+
+impl From<Module> for proto::Module {
+    fn from(module: Module) -> Self {
+        Self {
+            uuid: module.uuid,
+            page_uuid: module.page_uuid.unwrap_or_default(),
+            category_uuid: module.category_uuid.unwrap_or_default(),
+            title: module.title,
+            content: module.content,
+        }
+    }
+}
+
+impl From<PageDTO> for proto::Page {
+    fn from(page: PageDTO) -> Self {
+        Self {
+            uuid: page.uuid,
+            page_name: page.page_name,
+            page_url: page.page_url,
+            page_title: page.page_title,
+            time_created: page.time_created.timestamp(),
+            modules: Vec::new(),
+            categories: Vec::new(),
+        }
+    }
+}
+
+fn with_fields(mut page: proto::Page, fields: FieldsDTO) -> Result<proto::Page, CustomHttpError> {
+    // like the rendered pages, gRPC is for public consumption so encrypted content is never sent.
+    let fields = on_render_fields(reveal_fields(strip_fields(fields, Role::Public), false)?);
+
+    page.modules = fields.modules.into_iter().map(|m| m.into()).collect();
+    page.categories = fields
+        .categories
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| proto::Category {
+            uuid: c.uuid,
+            title: c.title,
+            modules: c.modules.into_iter().map(|m| m.into()).collect(),
+        })
+        .collect();
+
+    Ok(page)
+}
+
+impl From<CustomHttpError> for Status {
+    fn from(e: CustomHttpError) -> Self {
+        match e {
+            CustomHttpError::NotFound => Status::not_found(e.descriptor()),
+            CustomHttpError::BadRequest => Status::invalid_argument(e.descriptor()),
+            CustomHttpError::PoolExhausted => Status::unavailable(e.descriptor()),
+            _ => Status::internal(e.descriptor()),
+        }
+    }
+}
+
+pub struct ContentService {
+    pool: DbPool,
+}
+
+impl ContentService {
+    /// Diesel is synchronous, so every query is moved off of the async runtime.
+    async fn query<T, F>(&self, f: F) -> Result<T, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(&DbConnection) -> Result<T, CustomHttpError> + Send + 'static,
+    {
+        let pool = self.pool.clone();
+
+        tokio1::task::spawn_blocking(move || {
+            let conn = pool.get().or(Err(CustomHttpError::PoolExhausted))?;
+            f(&conn)
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(|e| e.into())
+    }
+}
+
+#[tonic::async_trait]
+impl Content for ContentService {
+    async fn get_page(&self, request: Request<proto::GetPageRequest>) -> Result<Response<proto::Page>, Status> {
+        let uuid = request.into_inner().uuid;
+
+        let page = self
+            .query(move |db| {
+                require_published(&uuid, db)?;
+                let joined = Page::read_one_join_on(uuid.clone(), db)?;
+                with_fields(Page::read_one(uuid, db)?.into(), joined.fields)
+            })
+            .await?;
+
+        Ok(Response::new(page))
+    }
+
+    async fn get_page_by_url(
+        &self,
+        request: Request<proto::GetPageByUrlRequest>,
+    ) -> Result<Response<proto::Page>, Status> {
+        let page_url = request.into_inner().page_url;
+
+        let page = self
+            .query(move |db| {
+                let (page, fields) = Page::read_one_join_on_url(page_url, db)?;
+                require_published(&page.uuid, db)?;
+                with_fields(PageDTO::from(page).into(), fields)
+            })
+            .await?;
+
+        Ok(Response::new(page))
+    }
+
+    async fn list_pages(
+        &self,
+        _: Request<proto::ListPagesRequest>,
+    ) -> Result<Response<proto::ListPagesResponse>, Status> {
+        let pages = self
+            .query(|db| Ok(Page::read_published(db)?.into_iter().map(|p| p.into()).collect()))
+            .await?;
+
+        Ok(Response::new(proto::ListPagesResponse { pages }))
+    }
+
+    async fn get_module(
+        &self,
+        request: Request<proto::GetModuleRequest>,
+    ) -> Result<Response<proto::Module>, Status> {
+        let uuid = request.into_inner().uuid;
+
+        let module = self
+            .query(move |db| {
+                let module = require_visible(Module::read_one(uuid, db)?, Role::Public)?;
+                require_published_module(&module, db)?;
+
+                Ok(on_render_module(reveal_module(module, false)?).into())
+            })
+            .await?;
+
+        Ok(Response::new(module))
+    }
+}
+
+/// Runs the gRPC server on its own tokio runtime. This blocks forever, so it should be given its own thread.
+pub fn serve(pool: DbPool, addr: SocketAddr) {
+    let runtime = tokio1::runtime::Runtime::new().expect("Failed to start the gRPC runtime.");
+
+    let server = Server::builder()
+        .add_service(ContentServer::new(ContentService { pool }))
+        .serve(addr);
+
+    if let Err(e) = runtime.block_on(server) {
+        tracing::error!("gRPC server error: {:?}", e);
+    }
+}
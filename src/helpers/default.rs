@@ -5,6 +5,8 @@ use handlebars::{
 };
 use std::sync::Mutex;
 
+use crate::models::module_models::{Module, ModuleValue};
+
 fn get(
     h: &Helper,
     _: &Handlebars,
@@ -42,6 +44,46 @@ fn get(
     Ok(())
 }
 
+/// Like `get`, but gives the module's content read as its type, like the `url` and `alt` of an image module,
+/// for use with `#with` or `#if`. Content that doesn't suit the type gives nothing.
+#[derive(Clone, Copy)]
+pub struct ValueHelper;
+
+impl HelperDef for ValueHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let module_title = h
+            .param(0)
+            .ok_or(RenderError::new(
+                "No module title provided to helper function.",
+            ))?
+            .render();
+
+        let module = ctx
+            .data()
+            .get("fields")
+            .and_then(|fields| fields.get(&module_title))
+            .and_then(|module| serde_json::from_value::<Module>(module.clone()).ok());
+
+        let value = match module.and_then(|m| m.value()) {
+            Some(ModuleValue::Text(s)) | Some(ModuleValue::RichText(s)) => to_json(s),
+            Some(ModuleValue::Image(image)) => to_json(image),
+            Some(ModuleValue::Boolean(b)) => to_json(b),
+            Some(ModuleValue::Json(json)) => json,
+            None => serde_json::Value::Null,
+        };
+
+        Ok(Some(value.into()))
+    }
+}
+
+pub static VALUE_HELPER: ValueHelper = ValueHelper;
+
 /// For this helper, we need to return ScopedJson.
 /// The #each operator does not accept a string as an argument, and normal helpers are meant to write strings.
 /// With such, we use the handlebars HelperDef object that allows us to return ScopedJson.
@@ -94,4 +136,8 @@ pub fn register_helpers(handlebars: Data<Mutex<Handlebars<'_>>>) {
         .lock()
         .unwrap()
         .register_helper("getarray", Box::new(ARRAY_HELPER));
+    handlebars
+        .lock()
+        .unwrap()
+        .register_helper("getvalue", Box::new(VALUE_HELPER));
 }
@@ -0,0 +1,459 @@
+use actix_web::{web, HttpServer};
+use handlebars::Handlebars;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[macro_use]
+mod app;
+mod cli;
+mod controllers;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod helpers;
+mod middleware;
+mod services;
+mod models;
+mod routers;
+mod schema;
+mod watch;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+
+use app::AppState;
+use models::config_models::LocalConfig;
+use services::backup_service::Backups;
+use services::cron_service::CronSchedule;
+use services::live_service::{EventStreams, LiveHub};
+use services::mail_service::{self, MailQueue};
+use services::metrics_service::Metrics;
+use services::migration_service::{self, SchemaState};
+use services::page_cache_service::PageCache;
+use services::preview_service::PreviewHub;
+use services::publish_service::PublishQueue;
+use services::rate_limit_service::{Limits, RateLimiter};
+use services::redirect_service::RedirectTable;
+use services::response_cache_service::ResponseCache;
+use services::route_service::RouteTable;
+use services::search_service::{self, SearchIndexer};
+use services::cache_service;
+use services::feed_service::Feed;
+use services::graphql_service;
+use services::job_service::{self, JobHandlers, JobQueue};
+use services::media_store_service;
+use services::session_service;
+use services::settings_service::SettingsCache;
+use services::shutdown_service::{self, Shutdown};
+use services::site_service::SiteTable;
+use services::sitemap_service::Sitemap;
+use services::theme_service::{self, Themes};
+use services::trace_service;
+use services::warmup_service::Readiness;
+use services::webhook_service::WebhookQueue;
+
+#[macro_use]
+extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
+
+/// What plugins are written against, as the rest of the crate isn't public.
+pub mod plugins {
+    pub use crate::models::module_models::MutModule;
+    pub use crate::models::page_models::MutPage;
+    pub use crate::models::{with_connection, DbConnection, DbPool};
+    pub use crate::services::auth_service::{Admin, Claims, Editor, RequireRole};
+    pub use crate::services::errors_service::CustomHttpError;
+    pub use crate::services::plugin_service::{Content, ContentHook, Plugins, RouteProvider};
+    pub use crate::services::webhook_service::WebhookEvent;
+}
+
+pub use plugins::Plugins;
+
+/// Runs the subcommand the server was started with, or the server, with `plugins` installed in it. The `radical` binary
+/// runs it without any, while a crate that depends on this one can run it from its own `main` with its own.
+pub async fn run(plugins: Plugins) -> std::io::Result<()> {
+    // subcommands log to the console, while the server logs the way its config says once it is loaded.
+    if !matches!(std::env::args().nth(1).as_deref(), None | Some("serve")) {
+        trace_service::init_console();
+    }
+
+    // subcommands run instead of the server.
+    match std::env::args().nth(1).as_deref() {
+        Some("init") => {
+            cli::init::run();
+            return Ok(());
+        }
+        Some("create-admin") => {
+            cli::create_admin::run();
+            return Ok(());
+        }
+        Some("seed") => {
+            cli::seed::run(std::env::args().nth(2), &plugins);
+            return Ok(());
+        }
+        Some("compress") => {
+            cli::compress::run();
+            return Ok(());
+        }
+        Some("backup") => {
+            cli::backup::run();
+            return Ok(());
+        }
+        Some("gc") => {
+            cli::gc::run(std::env::args().any(|arg| arg == "--clean"));
+            return Ok(());
+        }
+        Some("export-markdown") => {
+            cli::export_markdown::run(std::env::args().nth(2), std::env::args().nth(3));
+            return Ok(());
+        }
+        Some("export-static") => {
+            cli::export_static::run(cli::option("out"));
+            return Ok(());
+        }
+        Some("git") => {
+            cli::git::run(std::env::args().nth(2), &plugins);
+            return Ok(());
+        }
+        Some("import") => {
+            let args: Vec<String> = std::env::args().filter(|arg| arg != "--dry-run").collect();
+            let dry_run = std::env::args().any(|arg| arg == "--dry-run");
+            cli::import::run(args.get(2).cloned(), args.get(3).cloned(), dry_run, &plugins);
+            return Ok(());
+        }
+        Some("migrate") => {
+            cli::migrate::run(std::env::args().nth(2));
+            return Ok(());
+        }
+        Some("import-markdown") => {
+            cli::import_markdown::run(std::env::args().nth(2), &plugins);
+            return Ok(());
+        }
+        Some("import-wordpress") => {
+            cli::import_wordpress::run(std::env::args().nth(2), &plugins);
+            return Ok(());
+        }
+        // `serve`, like no subcommand at all, starts the server.
+        _ => {}
+    }
+
+    let conf = LocalConfig::load_or_exit();
+    trace_service::init(&conf);
+
+    let pool = models::establish_database_connection(conf.clone()).unwrap_or_else(|e| {
+        tracing::error!("Could not connect to the database: {}", e);
+        std::process::exit(1);
+    });
+    let databases = web::Data::new(models::Databases::new(pool.clone(), &conf).unwrap_or_else(|e| panic!("Invalid APP_DATABASES or APP_REPLICA_URL: {}", e)));
+
+    let migration_connection = models::establish_connection(conf.clone()).unwrap_or_else(|e| {
+        tracing::error!("Could not connect to the database: {}", e);
+        std::process::exit(1);
+    });
+    if conf.auto_migrate {
+        match migration_service::run(&migration_connection) {
+            Ok(ran) if ran.is_empty() => {}
+            Ok(ran) => tracing::info!("Ran {} migrations.", ran.len()),
+            Err(e) => tracing::error!("Migrations not ran: {}", e),
+        };
+    }
+
+    // Writes are refused for as long as the schema is behind.
+    let schema_state = web::Data::new(SchemaState::new());
+    match schema_state.refresh(&migration_connection) {
+        Ok(0) => {}
+        Ok(pending) => tracing::warn!("{} migrations are pending. Changes will be refused until they are run.", pending),
+        Err(e) => tracing::error!("Could not check for pending migrations: {}", e),
+    };
+
+    let handlebars = Handlebars::new();
+
+    // web::Data is Arc, so we can safely clone it and send it between our watcher and the server.
+    let handlebars_ref = web::Data::new(Mutex::new(handlebars));
+    let hb = handlebars_ref.clone();
+
+    // The theme is read once here, and afterwards only changes through `/api/v1/themes/active`.
+    let themes = web::Data::new(Themes::new(pool.get().ok().and_then(|db| theme_service::configured(&conf, &db))));
+    themes.reload(&hb).unwrap_or_else(|e| panic!("Invalid templates: {:?}", e));
+
+    // Registers all default handlebars functions.
+    helpers::default::register_helpers(handlebars_ref.clone());
+
+    let cache = cache_service::backend(&conf).unwrap_or_else(|e| panic!("Invalid APP_CACHE_BACKEND: {}", e));
+    let page_cache = web::Data::new(PageCache::new(cache.clone(), &conf));
+    let response_cache = web::Data::new(ResponseCache::new(cache.clone(), &conf));
+
+    // Registers the fs watcher that updates the templates in memory every time a template is changed.
+    // This is what enables hot reload, which is only wanted while templates are being worked on.
+    if conf.dev {
+        let (watch_pages, watch_themes) = (page_cache.clone(), themes.clone());
+        std::thread::spawn(|| watch::watch(hb, watch_pages, watch_themes));
+    }
+
+    // Prunes revisions that fall outside of the retention policy on an interval.
+    let (prune_pool, prune_conf) = (pool.clone(), conf.clone());
+    std::thread::spawn(move || services::retention_service::run(prune_pool, prune_conf));
+
+    // Cleans up orphaned data and expired tokens on an interval.
+    let (gc_pool, gc_conf) = (pool.clone(), conf.clone());
+    std::thread::spawn(move || services::gc_service::run(gc_pool, gc_conf));
+
+    // The workers that are waited on when the server stops, so that what they were doing isn't cut off.
+    let shutdown = web::Data::new(Shutdown::new());
+    let mut publish_workers = Vec::new();
+
+    let mut publish_queue = PublishQueue::new();
+
+    // Pushes rendered pages to the publish target as they change, if one is configured.
+    if conf.publish_target.is_some() {
+        let publish_jobs = publish_queue.subscribe();
+        let (publish_pool, publish_conf, publish_hb, publish_themes) =
+            (pool.clone(), conf.clone(), handlebars_ref.clone(), themes.clone());
+        publish_workers.push(("publish", std::thread::spawn(move || {
+            services::publish_service::run(publish_pool, publish_conf, publish_hb, publish_themes, publish_jobs)
+        })));
+    }
+
+    // Commits content to a git repository as it changes, if one is configured.
+    if let Some(repo) = conf.git_repo.clone() {
+        let git_jobs = publish_queue.subscribe();
+        let (git_pool, git_push) = (pool.clone(), conf.git_push);
+        let git_worker = std::thread::spawn(move || services::git_service::run(git_pool, repo, git_push, git_jobs));
+        publish_workers.push(("git", git_worker));
+    }
+
+    // Pushes pages that are being previewed to their preview panes as they are saved.
+    let preview_hub = web::Data::new(PreviewHub::new());
+    let preview_jobs = publish_queue.subscribe();
+    let (preview_pool, preview_hb, preview_hub_ref) = (pool.clone(), handlebars_ref.clone(), preview_hub.clone());
+    std::thread::spawn(move || services::preview_service::run(preview_pool, preview_hb, preview_hub_ref, preview_jobs));
+
+    // Keeps the search index up to date as content changes, and rebuilds it when asked to.
+    let (search_indexer, search_jobs) = SearchIndexer::new();
+    let search_indexer = web::Data::new(search_indexer);
+    publish_queue.attach(search_indexer.sender());
+    let (search_pool, search_indexer_ref) = (pool.clone(), search_indexer.clone());
+    let search_index =
+        search_service::index(&conf, pool.clone()).unwrap_or_else(|e| panic!("Invalid APP_SEARCH_BACKEND: {}", e));
+    let search_index_ref = search_index.clone();
+    std::thread::spawn(move || search_service::run(search_pool, search_indexer_ref, search_index_ref, search_jobs));
+    let search_index: web::Data<dyn search_service::SearchIndex> = web::Data::from(search_index);
+
+    // Drops cached pages as they change, and the cached responses they may have been sent in.
+    let page_cache_jobs = publish_queue.subscribe();
+    let (page_cache_pool, page_cache_ref, responses_ref) = (pool.clone(), page_cache.clone(), response_cache.clone());
+    std::thread::spawn(move || {
+        services::page_cache_service::run(page_cache_pool, page_cache_ref, responses_ref, page_cache_jobs)
+    });
+
+    // Drops the sitemap and the feed as soon as pages change, so they are read again the next time they are asked for.
+    let sitemap = web::Data::new(Sitemap::new(cache.clone()));
+    let feed = web::Data::new(Feed::new(cache.clone()));
+    let (sitemap_ref, feed_ref) = (sitemap.clone(), feed.clone());
+    publish_queue.hook(move |_| {
+        sitemap_ref.invalidate();
+        feed_ref.invalidate();
+    });
+
+    let publish_queue = web::Data::new(publish_queue);
+
+    // Keeps the work that is done after requests, like webhook deliveries and mails, in the `jobs` table, and runs it
+    // until it succeeds. The handlers are given to the worker once everything they need has been made.
+    let (job_queue, job_receiver) = JobQueue::new();
+    let job_queue = web::Data::new(job_queue);
+
+    // Tells the webhooks about changes to content, retrying the ones that fail.
+    let (mut webhook_queue, webhook_events) = WebhookQueue::new();
+    // and the live sockets, event streams, and plugins listening for them.
+    let live_hub = web::Data::new(LiveHub::new());
+    let event_streams = web::Data::new(EventStreams::new());
+    let (live_hub_ref, event_streams_ref) = (live_hub.clone(), event_streams.clone());
+    let after_save = plugins.clone();
+    webhook_queue.hook(move |event| {
+        live_hub_ref.broadcast(event);
+        event_streams_ref.broadcast(event);
+        after_save.after_save(event);
+    });
+    let webhook_queue = web::Data::new(webhook_queue);
+
+    // Sends notifications, like new form submissions and failing webhooks, when SMTP is set up.
+    let mail_queue = web::Data::new(MailQueue::new(&conf, job_queue.clone()));
+    let mailer = mail_service::mailer(&conf).unwrap_or_else(|e| panic!("Invalid SMTP config: {}", e));
+
+    let (webhook_pool, webhook_jobs) = (pool.clone(), job_queue.clone());
+    let webhook_worker =
+        std::thread::spawn(move || services::webhook_service::run(webhook_pool, webhook_events, webhook_jobs));
+
+    // Shared between all workers so that an invalidation from one is seen by all of them.
+    let settings_cache = web::Data::new(SettingsCache::new(cache.clone()));
+    let route_table = web::Data::new(RouteTable::new(cache.clone()));
+    let redirect_table = web::Data::new(RedirectTable::new(cache.clone()));
+    let site_table = web::Data::new(SiteTable::new(cache.clone()));
+    let cache_ref: web::Data<dyn cache_service::CacheBackend> = web::Data::from(cache.clone());
+    let session_store = session_service::store(&conf).unwrap_or_else(|e| panic!("Invalid APP_SESSION_STORE: {}", e));
+    let session_store: web::Data<dyn session_service::SessionStore> = web::Data::from(session_store);
+    let media_store = media_store_service::store(&conf).unwrap_or_else(|e| panic!("Invalid APP_MEDIA_STORE: {}", e));
+    let media_store: web::Data<dyn media_store_service::MediaStore> = web::Data::from(media_store);
+    let graphql_schema = web::Data::new(graphql_service::schema());
+    let rate_limiter = web::Data::new(RateLimiter::new(cache.clone(), Limits::from_conf(&conf), Duration::from_secs(60)));
+    let metrics = web::Data::new(Metrics::new());
+
+    // Publishes scheduled drafts as their time comes.
+    let (schedule_pool, schedule_conf) = (pool.clone(), conf.clone());
+    let (schedule_publish, schedule_routes, schedule_shutdown) = (publish_queue.clone(), route_table.clone(), shutdown.clone());
+    let schedule_worker = std::thread::spawn(move || {
+        services::schedule_service::run(schedule_pool, schedule_conf, schedule_publish, schedule_routes, schedule_shutdown)
+    });
+
+    // Runs the jobs as they come due: webhook deliveries, scheduled drafts, the sizes of `APP_MEDIA_PRESETS`, static
+    // exports, and mails.
+    let mut job_handlers = JobHandlers::new()
+        .on(services::webhook_service::JOB, services::webhook_service::handler(pool.clone(), mail_queue.clone().into_inner()))
+        .on(
+            services::schedule_service::JOB,
+            services::schedule_service::handler(pool.clone(), publish_queue.clone(), route_table.clone()),
+        )
+        .on(services::media_service::JOB, services::media_service::handler(pool.clone(), conf.clone(), media_store.clone()))
+        .on(
+            services::static_export_service::JOB,
+            services::static_export_service::handler(
+                pool.clone(),
+                conf.clone(),
+                handlebars_ref.clone(),
+                themes.clone(),
+                media_store.clone(),
+            ),
+        );
+    if let Some((mailer, from)) = mailer {
+        job_handlers = job_handlers.on(mail_service::JOB, mail_service::handler(mailer, from, handlebars_ref.clone()));
+    }
+    let job_pool = pool.clone();
+    let job_worker = std::thread::spawn(move || job_service::run(job_pool, job_receiver, job_handlers));
+
+    // Backs up the content on `APP_BACKUP_SCHEDULE`, if it is set.
+    let backup_worker = conf.backup_schedule.as_deref().and_then(CronSchedule::parse).map(|schedule| {
+        let backups = Backups::new(&conf).unwrap_or_else(|e| panic!("Invalid APP_BACKUP_STORE: {}", e));
+        let (backup_pool, backup_shutdown) = (pool.clone(), shutdown.clone());
+        std::thread::spawn(move || services::backup_service::run(backup_pool, backups, schedule, backup_shutdown))
+    });
+
+    // Fills the caches before `/readyz` reports ready, so a fresh deploy doesn't take its first traffic cold.
+    let readiness = web::Data::new(Readiness::new());
+    let (warmup_pool, warmup_conf, warmup_hb) = (pool.clone(), conf.clone(), handlebars_ref.clone());
+    let (warmup_routes, warmup_settings, warmup_pages, warmup_readiness) =
+        (route_table.clone(), settings_cache.clone(), page_cache.clone(), readiness.clone());
+    std::thread::spawn(move || {
+        services::warmup_service::run(
+            warmup_pool,
+            warmup_conf,
+            warmup_routes,
+            warmup_settings,
+            warmup_pages,
+            warmup_hb,
+            warmup_readiness,
+        )
+    });
+
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_port) = conf.grpc_bind_port {
+        let grpc_addr = format!("{}:{}", conf.bind_address, grpc_port).parse().unwrap();
+        let grpc_pool = pool.clone();
+        std::thread::spawn(move || grpc::serve(grpc_pool, grpc_addr));
+    }
+
+    let server_url = &format!(
+        "{}:{}",
+        &conf.bind_address,
+        &conf.bind_port
+    );
+
+    let tls = match services::tls_service::server_config(&conf) {
+        Ok(tls) => tls,
+        Err(e) => {
+            tracing::error!("Can't read the TLS certificate: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let plugins = web::Data::new(plugins);
+
+    let shutdown_timeout = conf.shutdown_timeout;
+    let (stopping_readiness, stopping_publish, stopping_webhooks, stopping_jobs) =
+        (readiness.clone(), publish_queue.clone(), webhook_queue.clone(), job_queue.clone());
+
+    let state = AppState {
+        pool: pool.clone(),
+        conf: conf.clone(),
+        databases,
+        handlebars: handlebars_ref,
+        themes,
+        cache: cache_ref,
+        settings_cache,
+        route_table,
+        redirect_table,
+        site_table,
+        page_cache,
+        response_cache,
+        sitemap,
+        feed,
+        readiness,
+        metrics,
+        rate_limiter,
+        session_store,
+        media_store,
+        search_index,
+        search_indexer,
+        publish_queue,
+        job_queue,
+        webhook_queue,
+        mail_queue,
+        preview_hub,
+        live_hub,
+        event_streams,
+        plugins,
+        schema_state,
+        graphql_schema,
+    };
+    let http_server = HttpServer::new(move || app!(&state));
+    let http_server = match tls {
+        Some(tls) => http_server.bind_rustls(server_url, tls)?,
+        None => http_server.bind(server_url)?,
+    }
+    .workers(2)
+    .shutdown_timeout(shutdown_timeout)
+    .disable_signals()
+    .run();
+
+    // On SIGTERM or SIGINT the server stops taking connections, and the requests in flight get `APP_SHUTDOWN_TIMEOUT` to finish.
+    let stopping_server = http_server.clone();
+    actix_web::rt::spawn(async move {
+        shutdown_service::signal().await;
+        tracing::info!("Stopping...");
+        stopping_readiness.mark_stopping();
+        stopping_server.stop(true).await;
+    });
+
+    tracing::info!("🚀 Server is running 🚀");
+
+    http_server.await?;
+
+    // Then the background work gets as long again to finish: the scheduler first, as it sends publish jobs, then the
+    // webhooks, which turn the events they have into jobs, then the job that is running, with the jobs that are waiting,
+    // retries included, left in the table for the next start, and last the publish jobs that are queued.
+    let deadline = Instant::now() + Duration::from_secs(shutdown_timeout);
+    shutdown.begin();
+    shutdown_service::join("schedule", schedule_worker, deadline);
+    if let Some(backup_worker) = backup_worker {
+        shutdown_service::join("backup", backup_worker, deadline);
+    }
+    stopping_webhooks.close();
+    shutdown_service::join("webhook", webhook_worker, deadline);
+    stopping_jobs.close();
+    shutdown_service::join("job", job_worker, deadline);
+    stopping_publish.close();
+    for (name, worker) in publish_workers {
+        shutdown_service::join(name, worker, deadline);
+    }
+    tracing::info!("Stopped.");
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_session::UserSession;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{HeaderValue, Method};
+use actix_web::{web, Error};
+use futures::future::{ok, LocalBoxFuture, Ready};
+
+use crate::models::{with_primary_connection, DbPool};
+use crate::models::user_models::UserRole;
+use crate::services::api_version_service::api_path;
+use crate::services::auth_service::{authenticate, trace_user, Claims, CryptoError};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::session_service::{self, SessionStore, CSRF_HEADER};
+
+/// Writes to these are how a token is gotten and given up, or a forgotten password replaced, so they can't need one.
+/// Paths here are within the API's version (see `api_version_service::api_path`), like `/user/login`.
+const OPEN: &[&str] = &["/user/login", "/user/logout", "/user/forgot", "/user/reset", "/session"];
+
+/// Inbound webhooks are called by other services, which sign their requests instead. See `inbound_webhook_service`.
+fn is_webhook_delivery(path: &str) -> bool {
+    path.starts_with("/inbound_webhooks/") && path.ends_with("/receive")
+}
+
+/// Visitors comment on pages without an account, which is held back by `comment_service` instead.
+fn is_comment_submission(path: &str) -> bool {
+    path.starts_with("/pages/") && path.ends_with("/comments") && path.split('/').count() == 4
+}
+
+/// Forms are filled in by visitors too, and held back by `form_service` the same way.
+fn is_form_submission(path: &str) -> bool {
+    path.starts_with("/forms/") && path.ends_with("/submit") && path.split('/').count() == 4
+}
+
+/// Every write needs a valid token, even when its handler forgets to ask for `Claims`. Reads are left to their handlers,
+/// as a lot of them are public and only hide things from visitors who aren't logged in.
+pub fn requires_login(req: &ServiceRequest) -> bool {
+    let read = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let path = api_path(req.path()).unwrap_or_default();
+
+    !read
+        && !OPEN.contains(&path)
+        && !is_webhook_delivery(path)
+        && !is_comment_submission(path)
+        && !is_form_submission(path)
+}
+
+/// Viewers can only read, apart from updating their own user, like its password.
+fn may_write(req: &ServiceRequest, claims: &Claims) -> bool {
+    claims.role >= UserRole::Editor || (req.method() == Method::PUT && api_path(req.path()) == Some(&format!("/user/{}", claims.sub)))
+}
+
+/// The token of the request's session, for requests without an `Authorization` header.
+/// The cookie is sent by the browser whoever asked for the request, so a write also has to send the session's CSRF token.
+fn session_token(req: &ServiceRequest) -> Result<HeaderValue, CustomHttpError> {
+    let data = session_service::logged_in(&req.get_session(), req.app_data::<web::Data<dyn SessionStore>>())
+        .ok_or_else(|| CustomHttpError::from(CryptoError::NoAuthHeader))?;
+
+    match req.headers().get(CSRF_HEADER).and_then(|h| h.to_str().ok()) {
+        Some(csrf_token) if csrf_token == data.csrf_token => data.auth_header(),
+        _ => Err(CustomHttpError::Forbidden),
+    }
+}
+
+/// Refuses writes without a valid token in the `Authorization` header, like `Claims` does, before they reach a handler.
+/// Browsers logged in with a session send its cookie and CSRF token instead. Writes by viewers are refused as well, while the handlers ask for the role they need with `RequireRole`.
+pub struct RequireLogin;
+
+impl<S, B> Transform<S> for RequireLogin
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireLoginMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequireLoginMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct RequireLoginMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for RequireLoginMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if !requires_login(&req) {
+            return Box::pin(self.service.borrow_mut().call(req));
+        }
+
+        let service = self.service.clone();
+        let pool = req.app_data::<web::Data<DbPool>>().unwrap().to_owned();
+        let auth_header = match req.headers().get("Authorization").cloned() {
+            Some(auth_header) => Ok(auth_header),
+            None => session_token(&req),
+        };
+
+        Box::pin(async move {
+            let claims = match auth_header {
+                Ok(auth_header) => with_primary_connection(pool, move |db| authenticate(&auth_header, db)).await,
+                Err(e) => Err(e),
+            };
+
+            // refused as a response rather than an error, so the layers outside can still add their headers to it.
+            match claims {
+                Ok(claims) if may_write(&req, &claims) => {
+                    trace_user(&claims);
+                    let res = service.borrow_mut().call(req);
+                    res.await
+                }
+                Ok(_) => Ok(req.error_response(CustomHttpError::Forbidden)),
+                Err(e) => Ok(req.error_response(e)),
+            }
+        })
+    }
+}
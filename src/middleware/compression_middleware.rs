@@ -0,0 +1,149 @@
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{BodyEncoding, BodySize, MessageBody, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, ContentEncoding, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::Error;
+use futures::future::{ok, LocalBoxFuture, Ready};
+
+use crate::models::config_models::LocalConfig;
+
+/// What is compressed when `APP_RESPONSE_COMPRESSION_TYPES` isn't set: the API, rendered pages, and the text assets
+/// they load. Images other than SVGs, and media in general, are already compressed.
+const DEFAULT_TYPES: &[&str] = &[
+    "application/json",
+    "application/problem+json",
+    "application/vnd.api+json",
+    "application/javascript",
+    "application/xml",
+    "image/svg+xml",
+    "text/*",
+];
+
+/// Which responses `actix_web::middleware::Compress`, just outside of this, is allowed to compress, by their content
+/// type and size, out of `APP_RESPONSE_COMPRESSION_*`. Bodies the size of which isn't known until they are sent are
+/// compressed by type alone, except for event streams, which would be held back until enough of them was written.
+pub struct Compression {
+    policy: Rc<Policy>,
+}
+
+struct Policy {
+    enabled: bool,
+    types: Vec<String>,
+    min_size: u64,
+}
+
+impl Compression {
+    pub fn new(conf: &LocalConfig) -> Self {
+        let types = match conf.response_compression_types.as_deref() {
+            Some(types) => types.split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_lowercase).collect(),
+            None => DEFAULT_TYPES.iter().map(|t| t.to_string()).collect(),
+        };
+
+        Self {
+            policy: Rc::new(Policy {
+                enabled: conf.response_compression,
+                types,
+                min_size: conf.response_compression_min_size as u64,
+            }),
+        }
+    }
+}
+
+impl Policy {
+    /// Whether the content type is one of the types, or under one of them like `text/html` under `text/*`.
+    fn allows_type(&self, content_type: &str) -> bool {
+        let essence = content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+        if essence == "text/event-stream" {
+            return false;
+        }
+
+        self.types.iter().any(|t| match t.strip_suffix("/*") {
+            Some(top) => essence.split('/').next() == Some(top),
+            None => *t == essence,
+        })
+    }
+
+    fn allows_size(&self, size: BodySize) -> bool {
+        match size {
+            BodySize::None | BodySize::Empty => false,
+            BodySize::Sized(size) => size >= self.min_size,
+            BodySize::Stream => true,
+        }
+    }
+
+    fn allows<B: MessageBody>(&self, res: &ServiceResponse<B>) -> bool {
+        let content_type = res.headers().get(header::CONTENT_TYPE).and_then(|h| h.to_str().ok());
+
+        self.enabled
+            // a part of a file can't be compressed on its own.
+            && res.status() != StatusCode::PARTIAL_CONTENT
+            && !res.headers().contains_key(header::CONTENT_ENCODING)
+            && content_type.is_some_and(|t| self.allows_type(t))
+            && self.allows_size(res.response().body().size())
+    }
+}
+
+impl<S, B> Transform<S> for Compression
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CompressionMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CompressionMiddleware {
+            service,
+            policy: self.policy.clone(),
+        })
+    }
+}
+
+pub struct CompressionMiddleware<S> {
+    service: S,
+    policy: Rc<Policy>,
+}
+
+impl<S, B> Service for CompressionMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let policy = self.policy.clone();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+
+            match policy.allows(&res) {
+                // caches in front of the server keep the compressed and the plain response apart.
+                true => {
+                    res.headers_mut().append(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+                }
+                false => {
+                    res.response_mut().encoding(ContentEncoding::Identity);
+                }
+            }
+
+            Ok(res)
+        })
+    }
+}
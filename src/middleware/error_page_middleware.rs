@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, Error, HttpResponse, ResponseError};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use handlebars::Handlebars;
+
+use crate::models::{with_connection, DbPool};
+use crate::services::errors_service::CustomHttpError;
+use crate::services::render_service::render_error_page;
+
+/// Shows visitors the error pages, like the one at `/_404`, instead of the JSON errors the API answers with.
+/// It only wraps the pages, and only takes the place of a page that wasn't found or failed, so the status stays the same.
+/// When there is nothing to show, or showing it fails too, the error is sent as it was.
+pub struct ErrorPages;
+
+impl<S> Transform<S> for ErrorPages
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ErrorPagesMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ErrorPagesMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct ErrorPagesMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+/// The status of the error the response is for, if it is one that has an error page.
+fn error_status(res: &HttpResponse) -> Option<StatusCode> {
+    let status = res.error()?.as_error::<CustomHttpError>()?.status_code();
+
+    match status == StatusCode::NOT_FOUND || status.is_server_error() {
+        true => Some(status),
+        false => None,
+    }
+}
+
+impl<S> Service for ErrorPagesMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let state = (
+            req.app_data::<web::Data<DbPool>>().cloned(),
+            req.app_data::<web::Data<Mutex<Handlebars<'static>>>>().cloned(),
+        );
+        let (pool, hb) = match state {
+            (Some(pool), Some(hb)) if matches!(*req.method(), Method::GET | Method::HEAD) => (pool, hb),
+            _ => return Box::pin(self.service.borrow_mut().call(req)),
+        };
+
+        let res = self.service.borrow_mut().call(req);
+
+        Box::pin(async move {
+            let res = res.await?;
+            let status = match error_status(res.response()) {
+                Some(status) => status,
+                None => return Ok(res),
+            };
+
+            let not_found = status == StatusCode::NOT_FOUND;
+            let page = with_connection(pool, move |db| render_error_page(not_found, db, &hb.lock().unwrap())).await;
+
+            match page {
+                Ok(Some(html)) => Ok(res.into_response(HttpResponse::build(status).content_type("text/html").body(html))),
+                Ok(None) => Ok(res),
+                Err(e) => {
+                    tracing::error!("Failed to render the error page for a {}: {}", status.as_u16(), e);
+                    Ok(res)
+                }
+            }
+        })
+    }
+}
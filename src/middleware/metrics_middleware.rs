@@ -0,0 +1,94 @@
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::{web, Error};
+use futures::future::{ok, LocalBoxFuture, Ready};
+
+use crate::services::metrics_service::{Metrics, PAGE_ROUTE};
+
+/// Methods anyone can make up are counted together, for the same reason as pages.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::OPTIONS => "OPTIONS",
+        _ => "other",
+    }
+}
+
+/// Records every request in `Metrics`, by the pattern of the route it matched. It sits outside of everything else,
+/// so requests answered from the response cache or refused by the rate limits are counted too.
+pub struct RequestMetrics {
+    metrics: web::Data<Metrics>,
+}
+
+impl RequestMetrics {
+    pub fn new(metrics: web::Data<Metrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> Transform<S> for RequestMetrics
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestMetricsMiddleware {
+            service,
+            metrics: self.metrics.clone(),
+        })
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: S,
+    metrics: web::Data<Metrics>,
+}
+
+impl<S> Service for RequestMetricsMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let route = req.match_pattern().unwrap_or_else(|| String::from(PAGE_ROUTE));
+        let method = method_label(req.method());
+        let metrics = self.metrics.clone();
+        let start = Instant::now();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await;
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            metrics.record(&route, method, status.as_u16(), start.elapsed());
+
+            res
+        })
+    }
+}
@@ -0,0 +1,12 @@
+pub mod auth_middleware;
+pub mod compression_middleware;
+pub mod error_page_middleware;
+pub mod metrics_middleware;
+pub mod rate_limit_middleware;
+pub mod redirect_middleware;
+pub mod replica_middleware;
+pub mod request_id_middleware;
+pub mod response_cache_middleware;
+pub mod security_headers_middleware;
+pub mod site_middleware;
+pub mod trace_middleware;
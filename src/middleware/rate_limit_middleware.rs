@@ -0,0 +1,87 @@
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error, HttpResponse};
+use futures::future::{ok, Either, LocalBoxFuture, Ready};
+use futures::{FutureExt, TryFutureExt};
+
+use crate::services::rate_limit_service::RateLimiter;
+
+/// Answers clients over their limit with a 429 and a `Retry-After`, before anything else is done for them.
+/// It sits outside of every route, so it only ever sees plain bodies. Every other response gets the `x-ratelimit-*` headers,
+/// and the credential of the request is remembered when a route checked it.
+pub struct RateLimit {
+    limiter: web::Data<RateLimiter>,
+}
+
+impl RateLimit {
+    pub fn new(limiter: web::Data<RateLimiter>) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Transform<S> for RateLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service,
+            limiter: self.limiter.clone(),
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: S,
+    limiter: web::Data<RateLimiter>,
+}
+
+impl<S> Service for RateLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = Either<Ready<Result<Self::Response, Self::Error>>, LocalBoxFuture<'static, Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        match self.limiter.check(&req) {
+            Some(limit) if limit.exceeded => {
+                let mut res = HttpResponse::TooManyRequests().finish();
+                limit.apply(res.headers_mut());
+                Either::Left(ok(req.into_response(res)))
+            }
+            limit => {
+                let limiter = self.limiter.clone();
+
+                Either::Right(
+                    self.service
+                        .call(req)
+                        .map_ok(move |mut res| {
+                            limiter.remember(res.request());
+                            if let Some(limit) = limit {
+                                limit.apply(res.headers_mut());
+                            }
+                            res
+                        })
+                        .boxed_local(),
+                )
+            }
+        }
+    }
+}
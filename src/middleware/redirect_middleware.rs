@@ -0,0 +1,96 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{web, Error, HttpResponse};
+use futures::future::{ok, LocalBoxFuture, Ready};
+
+use crate::models::{with_connection, DbPool};
+use crate::services::redirect_service::{normalize, RedirectTable};
+use crate::services::route_service::RouteTable;
+
+/// Sends requests for paths that no page has, but a redirect does, on to the redirect's target instead of the 404 page.
+/// It only wraps the pages, so the API and the assets are never redirected, and a page always wins over a redirect,
+/// so one left behind for a URL that was taken again stops being followed.
+pub struct Redirects;
+
+impl<S> Transform<S> for Redirects
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RedirectsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RedirectsMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct RedirectsMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S> Service for RedirectsMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let tables = (
+            req.app_data::<web::Data<DbPool>>().cloned(),
+            req.app_data::<web::Data<RouteTable>>().cloned(),
+            req.app_data::<web::Data<RedirectTable>>().cloned(),
+        );
+        let (pool, routes, redirects) = match tables {
+            (Some(pool), Some(routes), Some(redirects)) if matches!(*req.method(), Method::GET | Method::HEAD) => {
+                (pool, routes, redirects)
+            }
+            _ => return Box::pin(self.service.borrow_mut().call(req)),
+        };
+
+        let service = self.service.clone();
+        let (path, query) = (req.path().to_string(), req.query_string().to_string());
+
+        Box::pin(async move {
+            let target = with_connection(pool, move |db| {
+                if routes.contains(&normalize(&path), db)? {
+                    return Ok(None);
+                }
+
+                Ok(redirects.find(&path, &query, db)?)
+            })
+            .await;
+
+            match target {
+                Ok(Some(target)) => {
+                    let status = StatusCode::from_u16(target.status_code as u16).unwrap_or(StatusCode::MOVED_PERMANENTLY);
+                    let res = HttpResponse::build(status).header(header::LOCATION, target.location).finish();
+                    Ok(req.into_response(res))
+                }
+                Ok(None) => {
+                    let res = service.borrow_mut().call(req);
+                    res.await
+                }
+                Err(e) => Ok(req.error_response(e)),
+            }
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::{web, Error};
+use futures::future::{ok, LocalBoxFuture, Ready};
+
+use crate::models::{self, Databases};
+
+/// Sends the reads of `GET` and `HEAD` requests to the read replica when `APP_REPLICA_URL` is set (see `models::read_from`).
+/// Everything else, and every request when there is no replica, uses the content database.
+pub struct Replicas;
+
+impl<S> Transform<S> for Replicas
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ReplicasMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ReplicasMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct ReplicasMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S> Service for ReplicasMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let replica = match *req.method() {
+            Method::GET | Method::HEAD => {
+                req.app_data::<web::Data<Databases>>().and_then(|databases| databases.replica().cloned())
+            }
+            _ => None,
+        };
+        let replica = match replica {
+            Some(replica) => replica,
+            None => return Box::pin(self.service.borrow_mut().call(req)),
+        };
+        let service = self.service.clone();
+
+        // like `Sites`, the request is only handed on inside the scope, as some of what it does happens as soon as it is.
+        Box::pin(models::read_from(replica, async move {
+            let res = service.borrow_mut().call(req);
+            res.await
+        }))
+    }
+}
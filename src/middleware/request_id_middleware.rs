@@ -0,0 +1,100 @@
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, HeaderName, HeaderValue};
+use actix_web::{Error, HttpMessage, HttpResponse};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use uuid::Uuid;
+
+use crate::services::errors_service::CustomHttpError;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The ID of the request, for the layers inside, like `RequestSpan`.
+pub struct CurrentRequestId(pub String);
+
+/// An ID sent by a proxy in front of the server is kept, so that its logs line up with these, as long as it looks like one.
+fn incoming_id(req: &ServiceRequest) -> Option<String> {
+    let id = req.headers().get(REQUEST_ID_HEADER)?.to_str().ok()?;
+    let valid = !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    match valid {
+        true => Some(id.to_string()),
+        false => None,
+    }
+}
+
+/// Gives the error response the request's ID, keeping the headers the layers inside already added, like the rate limits.
+fn with_request_id(res: &HttpResponse, request_id: &str) -> Option<HttpResponse> {
+    let err = res.error()?.as_error::<CustomHttpError>()?;
+    let mut with_id = err.error_response_for(request_id);
+
+    for (name, value) in res.headers() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            with_id.headers_mut().insert(name.clone(), value.clone());
+        }
+    }
+
+    Some(with_id)
+}
+
+/// Gives every request an ID, sent back in `x-request-id` and in the body of every `CustomHttpError`, which its span carries too.
+/// It sits outside of every route, so it only ever sees plain bodies.
+pub struct RequestId;
+
+impl<S> Transform<S> for RequestId
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestIdMiddleware { service })
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service for RequestIdMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let request_id = incoming_id(&req).unwrap_or_else(|| Uuid::new_v4().to_string());
+        req.extensions_mut().insert(CurrentRequestId(request_id.clone()));
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await?;
+            let mut res = match with_request_id(res.response(), &request_id) {
+                Some(with_id) => res.into_response(with_id),
+                None => res,
+            };
+
+            // the ID is only ever made of characters a header can hold.
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+
+            Ok(res)
+        })
+    }
+}
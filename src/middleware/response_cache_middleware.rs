@@ -0,0 +1,91 @@
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::{web, Error};
+use futures::future::{ok, Either, LocalBoxFuture, Ready};
+use futures::{FutureExt, TryFutureExt};
+
+use crate::services::response_cache_service::ResponseCache;
+
+/// Answers reads from the `ResponseCache` and keeps what is sent for them, and empties it whenever a write succeeds.
+/// It sits inside the rate limits, so cached reads are counted all the same.
+pub struct ResponseCaching {
+    cache: web::Data<ResponseCache>,
+}
+
+impl ResponseCaching {
+    pub fn new(cache: web::Data<ResponseCache>) -> Self {
+        Self { cache }
+    }
+}
+
+impl<S> Transform<S> for ResponseCaching
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ResponseCachingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ResponseCachingMiddleware {
+            service,
+            cache: self.cache.clone(),
+        })
+    }
+}
+
+pub struct ResponseCachingMiddleware<S> {
+    service: S,
+    cache: web::Data<ResponseCache>,
+}
+
+impl<S> Service for ResponseCachingMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = Either<Ready<Result<Self::Response, Self::Error>>, LocalBoxFuture<'static, Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if !self.cache.is_enabled() {
+            return Either::Right(self.service.call(req).boxed_local());
+        }
+
+        let key = self.cache.key(&req);
+        if let Some(cached) = key.as_deref().and_then(|key| self.cache.get(key, &req)) {
+            return Either::Left(ok(req.into_response(cached)));
+        }
+
+        // GraphQL queries are posted, but only ever read.
+        let writes = !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) && req.path() != "/graphql";
+        let cache = self.cache.clone();
+
+        Either::Right(
+            self.service
+                .call(req)
+                .map_ok(move |res| {
+                    if writes && res.status().is_success() {
+                        cache.clear();
+                    }
+                    if let Some(key) = key {
+                        cache.set(&key, res.response());
+                    }
+                    res
+                })
+                .boxed_local(),
+        )
+    }
+}
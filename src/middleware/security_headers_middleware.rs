@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{MessageBody, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, HeaderName, HeaderValue};
+use actix_web::{web, Error};
+use futures::future::{ok, LocalBoxFuture, Ready};
+
+use crate::models::config_models::LocalConfig;
+use crate::services::site_service;
+use crate::services::theme_service::Themes;
+
+/// The policies of `APP_CSP_SITES` or `APP_CSP_THEMES`, by site or theme.
+fn policies(value: Option<&str>) -> HashMap<String, HeaderValue> {
+    value
+        .into_iter()
+        .flat_map(|v| v.split(','))
+        .filter_map(|entry| entry.split_once('='))
+        .filter_map(|(name, policy)| Some((name.trim().to_string(), HeaderValue::from_str(policy.trim()).ok()?)))
+        .collect()
+}
+
+/// The `Content-Security-Policy` of rendered pages, which differs by site, then by theme, from `APP_CSP`.
+struct Csp {
+    default: Option<HeaderValue>,
+    sites: HashMap<String, HeaderValue>,
+    themes: HashMap<String, HeaderValue>,
+}
+
+impl Csp {
+    fn for_page(&self, site: Option<String>, theme: Option<String>) -> Option<HeaderValue> {
+        let site = site.unwrap_or_else(|| String::from(site_service::DEFAULT_SITE));
+
+        self.sites
+            .get(&site)
+            .or_else(|| theme.and_then(|theme| self.themes.get(&theme)))
+            .or(self.default.as_ref())
+            .cloned()
+    }
+}
+
+struct Headers {
+    fixed: Vec<(HeaderName, HeaderValue)>,
+    /// Only sent over HTTPS, including when a proxy in front of the server terminates it.
+    hsts: Option<HeaderValue>,
+    csp: Option<Csp>,
+}
+
+/// Sets the security headers of `APP_SECURITY_HEADERS` on responses that don't set their own. `new` sets the ones every
+/// response gets, and `pages` the `Content-Security-Policy` of rendered pages, as the rest is JSON, which it means
+/// nothing to, or pages like `/docs` that load what they need from elsewhere.
+pub struct SecurityHeaders {
+    headers: Rc<Headers>,
+}
+
+impl SecurityHeaders {
+    pub fn new(conf: &LocalConfig) -> Self {
+        let mut fixed = Vec::new();
+        let mut hsts = None;
+        if conf.security_headers {
+            fixed.push((header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")));
+            let configured = [
+                (header::X_FRAME_OPTIONS, &conf.frame_options),
+                (header::REFERRER_POLICY, &conf.referrer_policy),
+            ];
+            // an empty value leaves the header out, like a frame option that would keep previews from being embedded.
+            for (name, value) in configured.iter().filter(|(_, value)| !value.is_empty()) {
+                if let Ok(value) = HeaderValue::from_str(value) {
+                    fixed.push((name.clone(), value));
+                }
+            }
+            if conf.hsts_max_age > 0 {
+                hsts = HeaderValue::from_str(&format!("max-age={}", conf.hsts_max_age)).ok();
+            }
+        }
+
+        Self::from_headers(Headers { fixed, hsts, csp: None })
+    }
+
+    pub fn pages(conf: &LocalConfig) -> Self {
+        let csp = Csp {
+            default: conf.csp.as_deref().and_then(|csp| HeaderValue::from_str(csp).ok()),
+            sites: policies(conf.csp_sites.as_deref()),
+            themes: policies(conf.csp_themes.as_deref()),
+        };
+
+        Self::from_headers(Headers {
+            fixed: Vec::new(),
+            hsts: None,
+            csp: Some(csp).filter(|_| conf.security_headers),
+        })
+    }
+
+    fn from_headers(headers: Headers) -> Self {
+        Self { headers: Rc::new(headers) }
+    }
+}
+
+impl<S, B> Transform<S> for SecurityHeaders
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SecurityHeadersMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(SecurityHeadersMiddleware {
+            service,
+            headers: self.headers.clone(),
+        })
+    }
+}
+
+pub struct SecurityHeadersMiddleware<S> {
+    service: S,
+    headers: Rc<Headers>,
+}
+
+impl<S, B> Service for SecurityHeadersMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let headers = self.headers.clone();
+        let https = req.connection_info().scheme() == "https";
+        let theme = req.app_data::<web::Data<Themes>>().and_then(|themes| themes.active());
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+            let csp = headers.csp.as_ref().and_then(|csp| csp.for_page(site_service::current(), theme));
+
+            let set = headers
+                .fixed
+                .iter()
+                .cloned()
+                .chain(headers.hsts.clone().filter(|_| https).map(|hsts| (header::STRICT_TRANSPORT_SECURITY, hsts)))
+                .chain(csp.map(|csp| (header::CONTENT_SECURITY_POLICY, csp)));
+            for (name, value) in set {
+                if !res.headers().contains_key(&name) {
+                    res.headers_mut().insert(name, value);
+                }
+            }
+
+            Ok(res)
+        })
+    }
+}
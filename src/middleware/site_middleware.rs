@@ -0,0 +1,83 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error};
+use futures::future::{ok, LocalBoxFuture, Ready};
+
+use crate::models::{with_connection, DbPool};
+use crate::services::site_service::{self, SiteTable, DEFAULT_SITE};
+use crate::services::warmup_service::PROBES;
+
+/// Scopes everything a request does to the site its `Host` is for (see `site_service::current`), so that it only ever sees
+/// that site's pages, modules, and media, on the API and the rendered pages alike.
+pub struct Sites;
+
+impl<S> Transform<S> for Sites
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SitesMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(SitesMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct SitesMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S> Service for SitesMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let host = req.connection_info().host().to_string();
+        let tables = match PROBES.contains(&req.path()) {
+            // probes are about the server, so they don't wait on the database to find their site.
+            true => (None, None),
+            false => (req.app_data::<web::Data<DbPool>>().cloned(), req.app_data::<web::Data<SiteTable>>().cloned()),
+        };
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let site = match tables {
+                (Some(pool), Some(sites)) => match sites.resolve_cached(&host) {
+                    Some(site) => site,
+                    None => match with_connection(pool, move |db| Ok(sites.resolve(&host, db)?)).await {
+                        Ok(site) => site,
+                        Err(e) => return Ok(req.error_response(e)),
+                    },
+                },
+                _ => String::from(DEFAULT_SITE),
+            };
+
+            // the request is only handed on inside the scope, as some of what it does happens as soon as it is.
+            site_service::serve(site, async move {
+                let res = service.borrow_mut().call(req);
+                res.await
+            })
+            .await
+        })
+    }
+}
@@ -0,0 +1,84 @@
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::dev::{Body, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpMessage};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use tracing::field::Empty;
+use tracing::Instrument;
+
+use super::request_id_middleware::CurrentRequestId;
+use crate::services::metrics_service::PAGE_ROUTE;
+
+/// Runs every request in a `request` span with its route, its request ID, and the user who made it once they are known,
+/// so that everything logged while it is handled, including by its queries, can be told apart from the other requests.
+/// The request is logged when it has been answered. It sits inside `RequestId`, which the ID comes from.
+pub struct RequestSpan;
+
+impl<S> Transform<S> for RequestSpan
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestSpanMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestSpanMiddleware { service })
+    }
+}
+
+pub struct RequestSpanMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service for RequestSpanMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let request_id = req.extensions().get::<CurrentRequestId>().map(|id| id.0.clone()).unwrap_or_default();
+        let span = tracing::info_span!(
+            "request",
+            method = %req.method(),
+            path = %req.path(),
+            route = %req.match_pattern().unwrap_or_else(|| String::from(PAGE_ROUTE)),
+            request_id = %request_id,
+            peer = %req.connection_info().realip_remote_addr().unwrap_or_default(),
+            user = Empty,
+        );
+        let start = Instant::now();
+        let fut = {
+            let _entered = span.enter();
+            self.service.call(req)
+        };
+
+        Box::pin(
+            async move {
+                let res = fut.await;
+                let status = match &res {
+                    Ok(res) => res.status(),
+                    Err(e) => e.as_response_error().status_code(),
+                };
+                tracing::info!(status = status.as_u16(), ms = start.elapsed().as_secs_f64() * 1000.0, "answered");
+
+                res
+            }
+            .instrument(span),
+        )
+    }
+}
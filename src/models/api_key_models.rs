@@ -0,0 +1,120 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::user_models::{User, UserRole};
+use super::{DbConnection, Model};
+use crate::schema::{api_keys, users};
+
+/// What a key may do. Each one is the same as a role, so `write` is what an editor can do.
+pub const SCOPES: &[&str] = &["read", "write", "admin"];
+
+/// A long lived key for the management API, sent as `Authorization: ApiKey <key>`, acting as the user who made it.
+/// Only a hash of the key is stored, the key itself is shown once when it is made.
+/// `scopes` is comma separated.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "api_keys"]
+pub struct ApiKey {
+    pub uuid: String,
+    pub name: String,
+    #[serde(skip)]
+    pub key_hash: String,
+    pub user_uuid: String,
+    pub scopes: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "api_keys"]
+pub struct MutApiKey {
+    pub uuid: String,
+    pub name: String,
+    pub key_hash: String,
+    pub user_uuid: String,
+    pub scopes: String,
+}
+
+impl Model<ApiKey, MutApiKey, String> for ApiKey {
+    fn create(new: &MutApiKey, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(api_keys::table)
+            .values(new)
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<ApiKey, diesel::result::Error> {
+        use api_keys::dsl::uuid;
+
+        api_keys::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<ApiKey>, diesel::result::Error> {
+        use api_keys::dsl::time_created;
+
+        api_keys::table.order(time_created.desc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<ApiKey>, i64), diesel::result::Error> {
+        use api_keys::dsl::{time_created, uuid};
+
+        let total = api_keys::table.count().get_result(db)?;
+        let rows = api_keys::table.order((time_created.desc(), uuid.asc())).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutApiKey,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use api_keys::dsl::uuid;
+
+        diesel::update(api_keys::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use api_keys::dsl::uuid;
+
+        diesel::delete(api_keys::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl ApiKey {
+    /// The key with the hash, along with the user it acts as.
+    pub fn read_by_hash(hash: String, db: &DbConnection) -> Result<(ApiKey, User), diesel::result::Error> {
+        use api_keys::dsl::key_hash;
+
+        api_keys::table
+            .inner_join(users::table)
+            .filter(key_hash.eq(hash))
+            .first::<(Self, User)>(db)
+    }
+
+    pub fn delete_for_user(user: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use api_keys::dsl::user_uuid;
+
+        diesel::delete(api_keys::table.filter(user_uuid.eq(user))).execute(db)
+    }
+
+    /// The most the key's scopes allow, which is never more than its user's role allows.
+    pub fn role(&self, user_role: UserRole) -> UserRole {
+        let scopes: Vec<&str> = self.scopes.split(',').map(str::trim).collect();
+        let role = if scopes.contains(&"admin") {
+            UserRole::Admin
+        } else if scopes.contains(&"write") {
+            UserRole::Editor
+        } else {
+            UserRole::Viewer
+        };
+
+        role.min(user_role)
+    }
+}
@@ -0,0 +1,184 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::page_models::on_site;
+use super::{DbConnection, Model};
+use crate::schema::{comments, pages};
+
+/// Comments wait for a moderator, and are only shown once they are approved.
+pub const PENDING: &str = "pending";
+pub const APPROVED: &str = "approved";
+/// Kept for moderators to look over, like one that was caught by the spam checks, but never shown.
+pub const SPAM: &str = "spam";
+
+pub fn is_comment_status(status: &str) -> bool {
+    [PENDING, APPROVED, SPAM].contains(&status)
+}
+
+type Backend = <DbConnection as Connection>::Backend;
+
+/// A visitor's comment on a page.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "comments"]
+pub struct Comment {
+    pub uuid: String,
+    pub page_uuid: String,
+    pub author_name: String,
+    /// Only ever shown to moderators.
+    pub author_email: Option<String>,
+    pub body: String,
+    /// One of `pending`, `approved`, or `spam`.
+    pub status: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "comments"]
+pub struct MutComment {
+    pub uuid: String,
+    pub page_uuid: String,
+    pub author_name: String,
+    pub author_email: Option<String>,
+    pub body: String,
+    pub status: String,
+}
+
+/// An approved comment the way visitors see it, in `GET /api/v1/pages/{id}/comments` and as the `comments` of templates.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PublicComment {
+    pub uuid: String,
+    pub author_name: String,
+    pub body: String,
+    pub time_created: NaiveDateTime,
+}
+
+impl From<Comment> for PublicComment {
+    fn from(comment: Comment) -> Self {
+        Self {
+            uuid: comment.uuid,
+            author_name: comment.author_name,
+            body: comment.body,
+            time_created: comment.time_created,
+        }
+    }
+}
+
+/// The body of `POST /api/v1/pages/{id}/comments`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewComment {
+    pub author_name: String,
+    #[serde(default)]
+    pub author_email: Option<String>,
+    pub body: String,
+    /// Left empty by people, as forms hide it, and filled in by bots that fill in every field, which marks the comment as spam.
+    #[serde(default)]
+    pub website: Option<String>,
+}
+
+/// The body of `PUT /api/v1/comments/{id}/status`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MutCommentStatus {
+    pub status: String,
+}
+
+impl Model<Comment, MutComment, String> for Comment {
+    fn create(new: &MutComment, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(comments::table).values(new).execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<Comment, diesel::result::Error> {
+        Self::filtered(None, None).filter(comments::uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<Comment>, diesel::result::Error> {
+        Self::read_filtered(None, None, db)
+    }
+
+    fn read_paginated(offset: i64, limit: i64, db: &DbConnection) -> Result<(Vec<Comment>, i64), diesel::result::Error> {
+        Self::read_filtered_paginated(None, None, offset, limit, db)
+    }
+
+    fn update(id: String, new: &MutComment, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use comments::dsl::uuid;
+
+        diesel::update(comments::table.filter(uuid.eq(id))).set(new).execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use comments::dsl::uuid;
+
+        diesel::delete(comments::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl Comment {
+    /// The current site's comments, of one page or every one and of one status or every one.
+    fn filtered<'a>(page: Option<&'a str>, status: Option<&'a str>) -> comments::BoxedQuery<'a, Backend> {
+        let mut query = comments::table
+            .filter(comments::page_uuid.eq_any(pages::table.filter(on_site()).select(pages::uuid)))
+            .into_boxed();
+        if let Some(page) = page {
+            query = query.filter(comments::page_uuid.eq(page));
+        }
+        if let Some(status) = status {
+            query = query.filter(comments::status.eq(status));
+        }
+
+        query
+    }
+
+    /// Oldest first, as a conversation is read and a moderator works through what is waiting.
+    pub fn read_filtered(
+        page: Option<&str>,
+        status: Option<&str>,
+        db: &DbConnection,
+    ) -> Result<Vec<Comment>, diesel::result::Error> {
+        use comments::dsl::{time_created, uuid};
+
+        Self::filtered(page, status).order((time_created.asc(), uuid.asc())).load::<Self>(db)
+    }
+
+    pub fn read_filtered_paginated(
+        page: Option<&str>,
+        status: Option<&str>,
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Comment>, i64), diesel::result::Error> {
+        use comments::dsl::{time_created, uuid};
+
+        let total = Self::filtered(page, status).count().get_result(db)?;
+        let rows = Self::filtered(page, status)
+            .order((time_created.asc(), uuid.asc()))
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    pub fn set_status(id: String, new_status: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use comments::dsl::{status, uuid};
+
+        diesel::update(comments::table.filter(uuid.eq(id))).set(status.eq(new_status)).execute(db)
+    }
+
+    /// Every comment left with the email, on any site.
+    pub fn read_by_email(email: &str, db: &DbConnection) -> Result<Vec<Comment>, diesel::result::Error> {
+        use comments::dsl::{author_email, time_created};
+
+        comments::table.filter(author_email.eq(email)).order(time_created.asc()).load::<Self>(db)
+    }
+
+    /// Takes the name and email off the comments left with the email, keeping what they said.
+    pub fn anonymize_by_email(email: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use comments::dsl::{author_email, author_name};
+
+        diesel::update(comments::table.filter(author_email.eq(email)))
+            .set((author_name.eq("Anonymous"), author_email.eq(None::<String>)))
+            .execute(db)
+    }
+}
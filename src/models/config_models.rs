@@ -1,16 +1,670 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use actix_web::http::HeaderValue;
+use chrono::Utc;
+use dotenv::dotenv;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::services::cron_service::CronSchedule;
+use crate::services::media_service;
+use crate::services::sanitize_service::{self, When};
+
+/// Every config key is read from an environment variable named after the field in uppercase, prefixed with `APP_`.
+/// For example, `mysql_username` is read from `APP_MYSQL_USERNAME`.
+pub const ENV_PREFIX: &str = "APP_";
+
+/// The config file, which is read from `radical.toml` when it exists and this isn't set.
+const CONFIG_FILE_VAR: &str = "APP_CONFIG_FILE";
+const DEFAULT_CONFIG_FILE: &str = "radical.toml";
+
+/// The config `load` resolved last. Some of it is read in places the config isn't passed to, like the secrets tokens and
+/// modules are encrypted with, so it is kept here too.
+static LOADED: RwLock<Option<Arc<LocalConfig>>> = RwLock::new(None);
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("{0} is missing")]
+    Missing(String),
+    /// A variable that doesn't parse, like a port that isn't a number.
+    #[error("{0}")]
+    Invalid(String),
+    #[error("{path} can't be read: {message}")]
+    File { path: String, message: String },
+}
+
+impl From<envy::Error> for ConfigError {
+    fn from(e: envy::Error) -> Self {
+        match e {
+            envy::Error::MissingValue(field) => Self::Missing(env_var(field)),
+            // envy names the variable without its prefix.
+            envy::Error::Custom(message) => Self::Invalid(message.replace("provided by ", &format!("provided by {}", ENV_PREFIX))),
+        }
+    }
+}
+
+/// The variable a config key is read from, like `APP_MYSQL_URL` for `mysql_url`.
+fn env_var(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.to_uppercase())
+}
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct LocalConfig {
+    /// `APP_MYSQL_USERNAME`
+    #[serde(default)]
     pub mysql_username: String,
+    /// `APP_MYSQL_PASSWORD`
+    #[serde(default)]
     pub mysql_password: String,
+    /// `APP_MYSQL_DATABASE`
+    #[serde(default)]
     pub mysql_database: String,
+    /// `APP_MYSQL_URL`
     pub mysql_url: Option<String>,
+    /// `APP_MYSQL_PORT`
     pub mysql_port: Option<u16>,
+    /// `APP_SQLITE_PATH`
+    pub sqlite_path: Option<String>,
+    /// `APP_DATABASES`
+    pub databases: Option<String>,
+    /// `APP_REPLICA_URL`
+    pub replica_url: Option<String>,
+    /// `APP_DB_POOL_MAX_SIZE`
+    #[serde(default = "default_db_pool_max_size")]
+    pub db_pool_max_size: u32,
+    /// `APP_DB_POOL_MIN_IDLE`
+    pub db_pool_min_idle: Option<u32>,
+    /// `APP_DB_POOL_TIMEOUT`, in seconds.
+    #[serde(default = "default_db_pool_timeout")]
+    pub db_pool_timeout: u64,
+    /// `APP_DB_POOL_IDLE_TIMEOUT`, in seconds.
+    #[serde(default = "default_db_pool_idle_timeout")]
+    pub db_pool_idle_timeout: u64,
+    /// `APP_DB_POOL_TEST_ON_CHECKOUT`
+    #[serde(default = "default_db_pool_test_on_checkout")]
+    pub db_pool_test_on_checkout: bool,
+    /// `APP_AUTO_MIGRATE`
+    #[serde(default = "default_auto_migrate")]
+    pub auto_migrate: bool,
+    /// `APP_BIND_ADDRESS`
+    #[serde(default = "default_bind_address")]
     pub bind_address: String,
+    /// `APP_BIND_PORT`
+    #[serde(default = "default_bind_port")]
     pub bind_port: u16,
+    /// `APP_SOCKET_DIR`
     pub socket_dir: Option<String>,
+    /// `APP_TLS_CERT`
+    pub tls_cert: Option<String>,
+    /// `APP_TLS_KEY`
+    pub tls_key: Option<String>,
+    /// `APP_SECURITY_HEADERS`
+    #[serde(default = "default_security_headers")]
+    pub security_headers: bool,
+    /// `APP_FRAME_OPTIONS`
+    #[serde(default = "default_frame_options")]
+    pub frame_options: String,
+    /// `APP_REFERRER_POLICY`
+    #[serde(default = "default_referrer_policy")]
+    pub referrer_policy: String,
+    /// `APP_HSTS_MAX_AGE`
+    #[serde(default = "default_hsts_max_age")]
+    pub hsts_max_age: u64,
+    /// `APP_CSP`
+    pub csp: Option<String>,
+    /// `APP_CSP_SITES`
+    pub csp_sites: Option<String>,
+    /// `APP_CSP_THEMES`
+    pub csp_themes: Option<String>,
+    /// `APP_SANITIZE`
+    #[serde(default = "default_sanitize")]
+    pub sanitize: String,
+    /// `APP_SANITIZE_TYPES`
+    pub sanitize_types: Option<String>,
+    /// `APP_SQL_NAME`
     pub sql_name: Option<String>,
+    /// `APP_MAX_REQ`
+    #[serde(default = "default_max_req")]
     pub max_req: u16,
-    pub jwt_key: String
+    /// `APP_CORS_ALLOWED_ORIGINS`
+    pub cors_allowed_origins: Option<String>,
+    /// `APP_CORS_ALLOWED_METHODS`
+    pub cors_allowed_methods: Option<String>,
+    /// `APP_CORS_ALLOWED_HEADERS`
+    pub cors_allowed_headers: Option<String>,
+    /// `APP_CORS_ALLOW_CREDENTIALS`
+    #[serde(default)]
+    pub cors_allow_credentials: bool,
+    /// `APP_CORS_MAX_AGE`
+    #[serde(default = "default_cors_max_age")]
+    pub cors_max_age: usize,
+    /// `APP_RATE_LIMIT_ANONYMOUS_READS`
+    pub rate_limit_anonymous_reads: Option<u64>,
+    /// `APP_RATE_LIMIT_ANONYMOUS_WRITES`
+    pub rate_limit_anonymous_writes: Option<u64>,
+    /// `APP_RATE_LIMIT_KEY_READS`
+    pub rate_limit_key_reads: Option<u64>,
+    /// `APP_RATE_LIMIT_KEY_WRITES`
+    pub rate_limit_key_writes: Option<u64>,
+    /// `APP_JWT_KEY`
+    pub jwt_key: String,
+    /// `APP_JWT_KEY_TOKENS`
+    #[serde(default = "default_jwt_key_tokens")]
+    pub jwt_key_tokens: bool,
+    /// `APP_ENCRYPTION_KEY`
+    pub encryption_key: Option<String>,
+    /// `APP_METRICS_TOKEN`
+    pub metrics_token: Option<String>,
+    /// `APP_MAX_PAGES`
+    pub max_pages: Option<i64>,
+    /// `APP_MAX_MODULES`
+    pub max_modules: Option<i64>,
+    /// `APP_MAX_MEDIA_BYTES`
+    pub max_media_bytes: Option<i64>,
+    /// `APP_COMPRESSION_THRESHOLD`
+    pub compression_threshold: Option<usize>,
+    /// `APP_REVISION_KEEP_LAST`
+    pub revision_keep_last: Option<i64>,
+    /// `APP_REVISION_KEEP_DAYS`
+    pub revision_keep_days: Option<i64>,
+    /// `APP_REVISION_KEEP_PUBLISHED`
+    #[serde(default)]
+    pub revision_keep_published: bool,
+    /// `APP_REVISION_PRUNE_INTERVAL`
+    #[serde(default = "default_revision_prune_interval")]
+    pub revision_prune_interval: u64,
+    /// `APP_GC_INTERVAL`
+    pub gc_interval: Option<u64>,
+    /// `APP_SCHEDULE_INTERVAL`
+    #[serde(default = "default_schedule_interval")]
+    pub schedule_interval: u64,
+    /// `APP_SHUTDOWN_TIMEOUT`
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+    /// `APP_BACKUP_SCHEDULE`
+    pub backup_schedule: Option<String>,
+    /// `APP_BACKUP_DIR`
+    pub backup_dir: Option<String>,
+    /// `APP_BACKUP_STORE`
+    pub backup_store: Option<String>,
+    /// `APP_BACKUP_KEEP`
+    #[serde(default = "default_backup_keep")]
+    pub backup_keep: usize,
+    /// `APP_GRPC_BIND_PORT`
+    pub grpc_bind_port: Option<u16>,
+    /// `APP_CACHE_BACKEND`
+    pub cache_backend: Option<String>,
+    /// `APP_REDIS_URL`
+    pub redis_url: Option<String>,
+    /// `APP_SESSION_STORE`
+    pub session_store: Option<String>,
+    /// `APP_PAGE_SESSIONS`, whether the session cookie is sent to rendered pages too, so that the pages only some users
+    /// can see are shown in the browsers they logged in with.
+    #[serde(default)]
+    pub page_sessions: bool,
+    /// `APP_PAGE_CACHE_TTL`
+    pub page_cache_ttl: Option<u64>,
+    /// `APP_RESPONSE_CACHE_TTL`
+    pub response_cache_ttl: Option<u64>,
+    /// `APP_RESPONSE_COMPRESSION`
+    #[serde(default = "default_response_compression")]
+    pub response_compression: bool,
+    /// `APP_RESPONSE_COMPRESSION_TYPES`
+    pub response_compression_types: Option<String>,
+    /// `APP_RESPONSE_COMPRESSION_MIN_SIZE`
+    #[serde(default = "default_response_compression_min_size")]
+    pub response_compression_min_size: usize,
+    /// `APP_WARMUP`
+    #[serde(default = "default_warmup")]
+    pub warmup: bool,
+    /// `APP_WARMUP_URLS`
+    pub warmup_urls: Option<String>,
+    /// `APP_JSON_API`
+    #[serde(default)]
+    pub json_api: bool,
+    /// `APP_DELIVERY_MAX_AGE`
+    #[serde(default = "default_delivery_max_age")]
+    pub delivery_max_age: u64,
+    /// `APP_PUBLISH_TARGET`
+    pub publish_target: Option<String>,
+    /// `APP_PUBLISH_URL`
+    pub publish_url: Option<String>,
+    /// `APP_PUBLISH_REGION`
+    pub publish_region: Option<String>,
+    /// `APP_PUBLISH_ACCESS_KEY`
+    pub publish_access_key: Option<String>,
+    /// `APP_PUBLISH_SECRET_KEY`
+    pub publish_secret_key: Option<String>,
+    /// `APP_GIT_REPO`
+    pub git_repo: Option<String>,
+    /// `APP_GIT_PUSH`
+    #[serde(default)]
+    pub git_push: bool,
+    /// `APP_OIDC_ISSUER`
+    pub oidc_issuer: Option<String>,
+    /// `APP_OIDC_CLIENT_ID`
+    pub oidc_client_id: Option<String>,
+    /// `APP_OIDC_CLIENT_SECRET`
+    pub oidc_client_secret: Option<String>,
+    /// `APP_OIDC_REDIRECT_URL`
+    pub oidc_redirect_url: Option<String>,
+    /// `APP_OIDC_RETURN_URL`
+    pub oidc_return_url: Option<String>,
+    /// `APP_OIDC_CREATE_USERS`
+    #[serde(default)]
+    pub oidc_create_users: bool,
+    /// `APP_SWAGGER_UI`
+    #[serde(default)]
+    pub swagger_ui: bool,
+    /// `APP_THEME`
+    pub theme: Option<String>,
+    /// `APP_DEV`
+    #[serde(default)]
+    pub dev: bool,
+    /// `APP_LOG_FORMAT`
+    #[serde(default = "default_log_format")]
+    pub log_format: String,
+    /// `APP_LOG_LEVEL`
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// `APP_STATIC_DIR`
+    pub static_dir: Option<String>,
+    /// `APP_STATIC_EXPORT_DIR`
+    pub static_export_dir: Option<String>,
+    /// `APP_STATIC_MAX_AGE`
+    #[serde(default = "default_static_max_age")]
+    pub static_max_age: u64,
+    /// `APP_MEDIA_DIR`
+    pub media_dir: Option<String>,
+    /// `APP_MEDIA_MAX_SIZE`
+    #[serde(default = "default_media_max_size")]
+    pub media_max_size: u64,
+    /// `APP_MEDIA_PRESETS`
+    pub media_presets: Option<String>,
+    /// `APP_JSON_MAX_SIZE`
+    #[serde(default = "default_json_max_size")]
+    pub json_max_size: u64,
+    /// `APP_BODY_MAX_SIZE`
+    #[serde(default = "default_body_max_size")]
+    pub body_max_size: u64,
+    /// `APP_MEDIA_STORE`
+    pub media_store: Option<String>,
+    /// `APP_S3_ENDPOINT`
+    pub s3_endpoint: Option<String>,
+    /// `APP_S3_BUCKET`
+    pub s3_bucket: Option<String>,
+    /// `APP_S3_REGION`
+    pub s3_region: Option<String>,
+    /// `APP_S3_ACCESS_KEY`
+    pub s3_access_key: Option<String>,
+    /// `APP_S3_SECRET_KEY`
+    pub s3_secret_key: Option<String>,
+    /// `APP_SEARCH_BACKEND`
+    pub search_backend: Option<String>,
+    /// `APP_MEILISEARCH_URL`
+    pub meilisearch_url: Option<String>,
+    /// `APP_MEILISEARCH_KEY`
+    pub meilisearch_key: Option<String>,
+    /// `APP_MEILISEARCH_INDEX`
+    pub meilisearch_index: Option<String>,
+    /// `APP_SITE_URL`
+    pub site_url: Option<String>,
+    /// `APP_FEED_PREFIX`
+    pub feed_prefix: Option<String>,
+    /// `APP_FEED_TITLE`
+    pub feed_title: Option<String>,
+    /// `APP_DEFAULT_LOCALE`
+    #[serde(default = "default_locale")]
+    pub default_locale: String,
+    /// `APP_PREVIEW_LINK_TTL`
+    #[serde(default = "default_preview_link_ttl")]
+    pub preview_link_ttl: u64,
+    /// `APP_COMMENTS_PER_HOUR`
+    #[serde(default = "default_comments_per_hour")]
+    pub comments_per_hour: u64,
+    /// `APP_COMMENT_MAX_LINKS`
+    #[serde(default = "default_comment_max_links")]
+    pub comment_max_links: usize,
+    /// `APP_FORM_SUBMISSIONS_PER_HOUR`
+    #[serde(default = "default_form_submissions_per_hour")]
+    pub form_submissions_per_hour: u64,
+    /// `APP_SMTP_HOST`
+    pub smtp_host: Option<String>,
+    /// `APP_SMTP_PORT`
+    pub smtp_port: Option<u16>,
+    /// `APP_SMTP_USERNAME`
+    pub smtp_username: Option<String>,
+    /// `APP_SMTP_PASSWORD`
+    pub smtp_password: Option<String>,
+    /// `APP_SMTP_TLS`
+    #[serde(default = "default_smtp_tls")]
+    pub smtp_tls: String,
+    /// `APP_MAIL_FROM`
+    pub mail_from: Option<String>,
+    /// `APP_MAIL_NOTIFY`
+    pub mail_notify: Option<String>,
+    /// `APP_PASSWORD_RESET_URL`
+    pub password_reset_url: Option<String>,
+    /// `APP_LEGACY_API`, whether the REST API is still served at `/v1` as well as at `/api/v1`.
+    #[serde(default = "default_legacy_api")]
+    pub legacy_api: bool,
+}
+
+fn default_auto_migrate() -> bool {
+    true
+}
+
+fn default_legacy_api() -> bool {
+    true
+}
+
+fn default_db_pool_max_size() -> u32 {
+    2
+}
+
+fn default_db_pool_timeout() -> u64 {
+    30
+}
+
+fn default_db_pool_idle_timeout() -> u64 {
+    600
+}
+
+fn default_db_pool_test_on_checkout() -> bool {
+    true
+}
+
+fn default_warmup() -> bool {
+    true
+}
+
+fn default_security_headers() -> bool {
+    true
+}
+
+fn default_jwt_key_tokens() -> bool {
+    true
+}
+
+fn default_frame_options() -> String {
+    String::from("SAMEORIGIN")
+}
+
+fn default_referrer_policy() -> String {
+    String::from("strict-origin-when-cross-origin")
+}
+
+fn default_hsts_max_age() -> u64 {
+    365 * 24 * 60 * 60
+}
+
+fn default_response_compression() -> bool {
+    true
+}
+
+fn default_response_compression_min_size() -> usize {
+    1024
+}
+
+fn default_locale() -> String {
+    String::from("en")
+}
+
+fn default_preview_link_ttl() -> u64 {
+    3600
+}
+
+fn default_comments_per_hour() -> u64 {
+    5
+}
+
+fn default_comment_max_links() -> usize {
+    2
+}
+
+fn default_form_submissions_per_hour() -> u64 {
+    10
+}
+
+fn default_sanitize() -> String {
+    String::from("both")
+}
+
+fn default_smtp_tls() -> String {
+    String::from("starttls")
+}
+
+fn default_bind_address() -> String {
+    String::from("0.0.0.0")
+}
+
+fn default_bind_port() -> u16 {
+    8080
+}
+
+fn default_max_req() -> u16 {
+    100
+}
+
+fn default_cors_max_age() -> usize {
+    3600
+}
+
+fn default_backup_keep() -> usize {
+    7
+}
+
+fn default_revision_prune_interval() -> u64 {
+    3600
+}
+
+fn default_schedule_interval() -> u64 {
+    60
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+
+fn default_log_format() -> String {
+    String::from("pretty")
+}
+
+fn default_log_level() -> String {
+    String::from("info")
+}
+
+fn default_delivery_max_age() -> u64 {
+    300
+}
+
+fn default_static_max_age() -> u64 {
+    3600
+}
+
+fn default_media_max_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_json_max_size() -> u64 {
+    1024 * 1024
+}
+
+fn default_body_max_size() -> u64 {
+    256 * 1024
+}
+
+impl LocalConfig {
+    /// Resolves the config from the environment, over the keys of the config file.
+    /// A `.env` file is read if one exists, but anything already set in the environment takes precedence over it,
+    /// and either takes precedence over the config file, so both files are entirely optional.
+    /// The config is checked once it is resolved, so that what is missing is known before anything connects, and then
+    /// kept for `loaded`, and for module content to be sanitized by (see `sanitize_service`).
+    pub fn load() -> Result<Self, ConfigError> {
+        dotenv().ok();
+
+        let mut vars = read_file()?;
+        vars.extend(std::env::vars());
+
+        let conf = envy::prefixed(ENV_PREFIX).from_iter::<_, LocalConfig>(vars)?;
+        conf.check()?;
+        sanitize_service::configure(&conf);
+        *LOADED.write().unwrap() = Some(Arc::new(conf.clone()));
+
+        Ok(conf)
+    }
+
+    /// The config that was loaded last, for what is read where the config isn't passed. `None` until one is loaded.
+    pub fn loaded() -> Option<Arc<LocalConfig>> {
+        LOADED.read().unwrap().clone()
+    }
+
+    /// Like `load`, but prints what is wrong with the config and exits, for the server and the CLI.
+    pub fn load_or_exit() -> Self {
+        Self::load().unwrap_or_else(|e| {
+            eprintln!("Invalid config: {}. Every config key can be set through its `APP_` environment variable.", e);
+            std::process::exit(1);
+        })
+    }
+
+    /// Whether there is everything the database connection is made from, which isn't known from the types alone.
+    fn check(&self) -> Result<(), ConfigError> {
+        if self.jwt_key.is_empty() {
+            return Err(ConfigError::Missing(env_var("jwt_key")));
+        }
+        if !matches!(self.log_format.as_str(), "pretty" | "json") {
+            return Err(ConfigError::Invalid(format!("{} should be `pretty` or `json`", env_var("log_format"))));
+        }
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.log_level) {
+            return Err(ConfigError::Invalid(format!("{} isn't a valid filter: {}", env_var("log_level"), e)));
+        }
+        if self.db_pool_max_size == 0 {
+            return Err(ConfigError::Invalid(format!("{} should be at least 1", env_var("db_pool_max_size"))));
+        }
+        if self.db_pool_min_idle.is_some_and(|min| min > self.db_pool_max_size) {
+            return Err(ConfigError::Invalid(format!(
+                "{} can't be more than {}",
+                env_var("db_pool_min_idle"),
+                env_var("db_pool_max_size")
+            )));
+        }
+        if self.db_pool_timeout == 0 {
+            return Err(ConfigError::Invalid(format!("{} should be at least 1", env_var("db_pool_timeout"))));
+        }
+        let headers = [
+            ("frame_options", Some(self.frame_options.as_str())),
+            ("referrer_policy", Some(self.referrer_policy.as_str())),
+            ("csp", self.csp.as_deref()),
+        ];
+        for (key, value) in headers {
+            if value.is_some_and(|v| HeaderValue::from_str(v).is_err()) {
+                return Err(ConfigError::Invalid(format!("{} can't be sent as a header", env_var(key))));
+            }
+        }
+        for (key, value) in [("csp_sites", &self.csp_sites), ("csp_themes", &self.csp_themes)] {
+            let valid = value.iter().flat_map(|v| v.split(',')).filter(|e| !e.trim().is_empty()).all(|entry| {
+                entry.split_once('=').is_some_and(|(name, policy)| !name.trim().is_empty() && HeaderValue::from_str(policy.trim()).is_ok())
+            });
+            if !valid {
+                return Err(ConfigError::Invalid(format!("{} should be comma separated `name=policy` pairs", env_var(key))));
+            }
+        }
+        // one without the other would quietly serve plain HTTP.
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(_), None) => return Err(ConfigError::Missing(env_var("tls_key"))),
+            (None, Some(_)) => return Err(ConfigError::Missing(env_var("tls_cert"))),
+            _ => (),
+        }
+        if let Some(schedule) = &self.backup_schedule {
+            let next = CronSchedule::parse(schedule).and_then(|s| s.next_after(Utc::now().naive_utc()));
+            if next.is_none() {
+                return Err(ConfigError::Invalid(format!("{} should be a crontab schedule that comes around, like `0 3 * * *`", env_var("backup_schedule"))));
+            }
+        }
+        if let Err(e) = media_service::presets(self.media_presets.as_deref()) {
+            return Err(ConfigError::Invalid(format!("{} {}", env_var("media_presets"), e)));
+        }
+        if When::parse(&self.sanitize).is_none() {
+            return Err(ConfigError::Invalid(format!("{} should be `save`, `render`, `both`, or `off`", env_var("sanitize"))));
+        }
+        if let Err(e) = sanitize_service::allowlists(self.sanitize_types.as_deref()) {
+            return Err(ConfigError::Invalid(format!("{} {}", env_var("sanitize_types"), e)));
+        }
+        if !matches!(self.backup_store.as_deref(), None | Some("local") | Some("s3")) {
+            return Err(ConfigError::Invalid(format!("{} should be `local` or `s3`", env_var("backup_store"))));
+        }
+        match self.search_backend.as_deref() {
+            None | Some("fulltext") => (),
+            Some("meilisearch") if self.meilisearch_url.as_deref().is_none_or(str::is_empty) => {
+                return Err(ConfigError::Missing(env_var("meilisearch_url")));
+            }
+            Some("meilisearch") => (),
+            Some(_) => {
+                let message = format!("{} should be `fulltext` or `meilisearch`", env_var("search_backend"));
+                return Err(ConfigError::Invalid(message));
+            }
+        }
+        if !matches!(self.smtp_tls.as_str(), "starttls" | "tls" | "none") {
+            return Err(ConfigError::Invalid(format!("{} should be `starttls`, `tls`, or `none`", env_var("smtp_tls"))));
+        }
+        // mail is sent from someone, and without a host there is nowhere to send it.
+        if self.smtp_host.is_some() && self.mail_from.as_deref().is_none_or(str::is_empty) {
+            return Err(ConfigError::Missing(env_var("mail_from")));
+        }
+
+        if cfg!(feature = "sqlite") {
+            return Ok(());
+        }
+        if self.mysql_url.is_none() && std::env::var("MYSQL_UNIX_PORT").is_err() {
+            return Err(ConfigError::Missing(format!("{} (or MYSQL_UNIX_PORT)", env_var("mysql_url"))));
+        }
+        if self.mysql_url.is_some() && self.mysql_port.is_none() {
+            return Err(ConfigError::Missing(env_var("mysql_port")));
+        }
+        for (key, value) in [
+            ("mysql_username", &self.mysql_username),
+            ("mysql_database", &self.mysql_database),
+        ] {
+            if value.is_empty() {
+                return Err(ConfigError::Missing(env_var(key)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The keys of the config file as the variables they stand in for, like `mysql_url = ".."` for `APP_MYSQL_URL`.
+/// Files ending in `.json` are read as JSON, and the rest as TOML. Only `radical.toml` may be missing.
+fn read_file() -> Result<HashMap<String, String>, ConfigError> {
+    let path = match std::env::var(CONFIG_FILE_VAR) {
+        Ok(path) => path,
+        Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => String::from(DEFAULT_CONFIG_FILE),
+        Err(_) => return Ok(HashMap::new()),
+    };
+    let file_error = |message: String| ConfigError::File {
+        path: path.clone(),
+        message,
+    };
+
+    let raw = std::fs::read_to_string(&path).map_err(|e| file_error(e.to_string()))?;
+    let keys: HashMap<String, serde_json::Value> = match path.ends_with(".json") {
+        true => serde_json::from_str(&raw).map_err(|e| file_error(e.to_string()))?,
+        false => toml::from_str(&raw).map_err(|e| file_error(e.to_string()))?,
+    };
+
+    keys.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                _ => return Err(file_error(format!("`{}` has to be a string, a number, or a boolean", key))),
+            };
+
+            Ok((env_var(&key), value))
+        })
+        .collect()
 }
@@ -0,0 +1,119 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::{content_types, modules};
+
+/// A module type an admin defined, like `product`, whose content is JSON checked against `json_schema` when it is written.
+/// Content types are shared by every site, as the templates that read them are.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "content_types"]
+pub struct ContentType {
+    pub uuid: String,
+    /// What modules of the type have as their `module_type`.
+    pub name: String,
+    #[serde(skip)]
+    pub json_schema: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "content_types"]
+pub struct MutContentType {
+    pub uuid: String,
+    pub name: String,
+    pub json_schema: String,
+}
+
+/// Used in the JSON response of content types, with the schema parsed.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ContentTypeDTO {
+    #[serde(flatten)]
+    pub content_type: ContentType,
+    #[schema(value_type = Object)]
+    pub schema: serde_json::Value,
+}
+
+impl From<ContentType> for ContentTypeDTO {
+    fn from(content_type: ContentType) -> Self {
+        let schema = serde_json::from_str(&content_type.json_schema).unwrap_or_default();
+
+        Self { content_type, schema }
+    }
+}
+
+/// The body of `POST /api/v1/content-types` and `PUT /api/v1/content-types/{id}`.
+/// `schema` is a JSON Schema, like `{ "type": "object", "properties": { "price": { "type": "number" } }, "required": ["price"] }`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewContentType {
+    pub name: String,
+    #[schema(value_type = Object)]
+    pub schema: serde_json::Value,
+}
+
+impl Model<ContentType, MutContentType, String> for ContentType {
+    fn create(new: &MutContentType, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(content_types::table).values(new).execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<ContentType, diesel::result::Error> {
+        use content_types::dsl::uuid;
+
+        content_types::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<ContentType>, diesel::result::Error> {
+        use content_types::dsl::name;
+
+        content_types::table.order(name.asc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<ContentType>, i64), diesel::result::Error> {
+        use content_types::dsl::name;
+
+        let total = content_types::table.count().get_result(db)?;
+        let rows = content_types::table.order(name.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutContentType,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use content_types::dsl::uuid;
+
+        diesel::update(content_types::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use content_types::dsl::uuid;
+
+        diesel::delete(content_types::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl ContentType {
+    pub fn read_by_name(type_name: &str, db: &DbConnection) -> Result<ContentType, diesel::result::Error> {
+        use content_types::dsl::name;
+
+        content_types::table.filter(name.eq(type_name)).first::<Self>(db)
+    }
+
+    /// Whether any module on any site is of the type, including the ones in the trash.
+    pub fn is_used(type_name: &str, db: &DbConnection) -> Result<bool, diesel::result::Error> {
+        let count: i64 = modules::table.filter(modules::module_type.eq(type_name)).count().get_result(db)?;
+
+        Ok(count > 0)
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::schema::data_requests;
+use super::DbConnection;
+
+/// The audit trail of every personal data export or erasure.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "data_requests"]
+pub struct DataRequest {
+    pub uuid: String,
+    /// The SHA-256 hash of the email, so that the trail doesn't keep what was erased, but can still be searched for an email.
+    pub email: String,
+    /// Either `export` or `erasure`.
+    pub request_type: String,
+    pub actor: String,
+    pub records_affected: i64,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "data_requests"]
+pub struct MutDataRequest {
+    pub uuid: String,
+    pub email: String,
+    pub request_type: String,
+    pub actor: String,
+    pub records_affected: i64,
+}
+
+impl DataRequest {
+    pub fn create(new: &MutDataRequest, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(data_requests::table).values(new).execute(db)
+    }
+
+    pub fn read_all(db: &DbConnection) -> Result<Vec<DataRequest>, diesel::result::Error> {
+        use data_requests::dsl::time_created;
+
+        data_requests::table.order(time_created.desc()).load::<Self>(db)
+    }
+
+    /// Swaps the email for its hash on requests recorded before emails were hashed.
+    pub fn replace_email(plain: &str, hashed: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use data_requests::dsl::email;
+
+        diesel::update(data_requests::table.filter(email.eq(plain))).set(email.eq(hashed)).execute(db)
+    }
+}
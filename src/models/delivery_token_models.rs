@@ -0,0 +1,123 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::delivery_tokens;
+
+/// A read only token for the content delivery API.
+/// Only a hash of the token is stored, the token itself is shown once when it is created.
+/// `content_types` and `locales` are comma separated, and `None` means the token is not restricted by them.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "delivery_tokens"]
+pub struct DeliveryToken {
+    pub uuid: String,
+    pub name: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub content_types: Option<String>,
+    pub locales: Option<String>,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "delivery_tokens"]
+pub struct MutDeliveryToken {
+    pub uuid: String,
+    pub name: String,
+    pub token_hash: String,
+    pub content_types: Option<String>,
+    pub locales: Option<String>,
+}
+
+impl Model<DeliveryToken, MutDeliveryToken, String> for DeliveryToken {
+    fn create(new: &MutDeliveryToken, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(delivery_tokens::table)
+            .values(new)
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<DeliveryToken, diesel::result::Error> {
+        use delivery_tokens::dsl::uuid;
+
+        delivery_tokens::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<DeliveryToken>, diesel::result::Error> {
+        use delivery_tokens::dsl::time_created;
+
+        delivery_tokens::table.order(time_created.desc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<DeliveryToken>, i64), diesel::result::Error> {
+        use delivery_tokens::dsl::{time_created, uuid};
+
+        let total = delivery_tokens::table.count().get_result(db)?;
+        let rows = delivery_tokens::table.order((time_created.desc(), uuid.asc())).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutDeliveryToken,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use delivery_tokens::dsl::uuid;
+
+        diesel::update(delivery_tokens::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use delivery_tokens::dsl::uuid;
+
+        diesel::delete(delivery_tokens::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl DeliveryToken {
+    pub fn read_by_hash(hash: String, db: &DbConnection) -> Result<DeliveryToken, diesel::result::Error> {
+        use delivery_tokens::dsl::token_hash;
+
+        delivery_tokens::table.filter(token_hash.eq(hash)).first::<Self>(db)
+    }
+
+    /// Whether the token may read the given content type, such as `pages` or `modules`.
+    pub fn allows_content_type(&self, content_type: &str) -> bool {
+        allows(&self.content_types, content_type)
+    }
+
+    pub fn allows_locale(&self, locale: &str) -> bool {
+        allows(&self.locales, locale)
+    }
+
+    /// Whether the token may read a page in `locale`. Pages without a locale are only for tokens without locales.
+    pub fn allows_page_locale(&self, locale: Option<&str>) -> bool {
+        match locale {
+            Some(locale) => self.allows_locale(locale),
+            None => self.locales.is_none(),
+        }
+    }
+
+    /// The locales the token is limited to, or `None` for every one.
+    pub fn locale_scope(&self) -> Option<Vec<String>> {
+        let locales = self.locales.as_ref()?;
+
+        Some(locales.split(',').map(|l| l.trim().to_string()).collect())
+    }
+}
+
+fn allows(scope: &Option<String>, value: &str) -> bool {
+    match scope {
+        Some(scope) => scope.split(',').any(|s| s.trim() == value),
+        None => true,
+    }
+}
@@ -0,0 +1,258 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::{form_submissions, forms};
+
+/// What a form's fields can be.
+pub const FIELD_TYPES: &[&str] = &["text", "textarea", "email", "number", "checkbox", "select"];
+
+/// A form visitors fill in, like `contact`, whose submissions are kept for editors. Forms are shared by every site, like menus.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "forms"]
+pub struct Form {
+    pub uuid: String,
+    pub name: String,
+    #[serde(skip)]
+    pub fields: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "forms"]
+pub struct MutForm {
+    pub uuid: String,
+    pub name: String,
+    pub fields: String,
+}
+
+fn default_field_type() -> String {
+    String::from("text")
+}
+
+/// One field of a form, which submissions are checked against.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FormField {
+    /// What the field is sent as, like the `name` of an `<input>`.
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// One of `text`, `textarea`, `email`, `number`, `checkbox`, or `select`, and `text` if it isn't set.
+    #[serde(rename = "type", default = "default_field_type")]
+    pub field_type: String,
+    #[serde(default)]
+    pub required: bool,
+    /// The most characters a `text` or `textarea` field takes, 5000 if it isn't set.
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    /// What a `select` field can be.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+}
+
+/// Used in the JSON response of forms, with the fields parsed.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct FormDTO {
+    #[serde(flatten)]
+    pub form: Form,
+    pub fields: Vec<FormField>,
+}
+
+impl From<Form> for FormDTO {
+    fn from(form: Form) -> Self {
+        let fields = serde_json::from_str(&form.fields).unwrap_or_default();
+
+        Self { form, fields }
+    }
+}
+
+/// The body of `POST /api/v1/forms` and `PUT /api/v1/forms/{id}`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewForm {
+    pub name: String,
+    pub fields: Vec<FormField>,
+}
+
+/// What a visitor sent with a form, after it was checked against its fields.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "form_submissions"]
+pub struct FormSubmission {
+    pub uuid: String,
+    pub form_uuid: String,
+    #[serde(skip)]
+    pub data: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "form_submissions"]
+pub struct MutFormSubmission {
+    pub uuid: String,
+    pub form_uuid: String,
+    pub data: String,
+}
+
+/// Used in the JSON response of submissions, with the values by field name.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct FormSubmissionDTO {
+    #[serde(flatten)]
+    pub submission: FormSubmission,
+    #[schema(value_type = Object)]
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+impl From<FormSubmission> for FormSubmissionDTO {
+    fn from(submission: FormSubmission) -> Self {
+        let data = serde_json::from_str(&submission.data).unwrap_or_default();
+
+        Self { submission, data }
+    }
+}
+
+impl FormSubmissionDTO {
+    /// Whether any of the values is `value`, the way comments are matched by their email.
+    pub fn has_value(&self, value: &str) -> bool {
+        self.data.values().any(|v| v.as_str() == Some(value))
+    }
+}
+
+impl Model<Form, MutForm, String> for Form {
+    fn create(new: &MutForm, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(forms::table).values(new).execute(db)
+    }
+
+    /// By its uuid or its name, so that a form can be sent to at `/api/v1/forms/contact/submit`.
+    fn read_one(id: String, db: &DbConnection) -> Result<Form, diesel::result::Error> {
+        use forms::dsl::{name, uuid};
+
+        forms::table.filter(uuid.eq(&id).or(name.eq(&id))).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<Form>, diesel::result::Error> {
+        use forms::dsl::name;
+
+        forms::table.order(name.asc()).load::<Self>(db)
+    }
+
+    fn read_paginated(offset: i64, limit: i64, db: &DbConnection) -> Result<(Vec<Form>, i64), diesel::result::Error> {
+        use forms::dsl::name;
+
+        let total = forms::table.count().get_result(db)?;
+        let rows = forms::table.order(name.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(id: String, new: &MutForm, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use forms::dsl::uuid;
+
+        diesel::update(forms::table.filter(uuid.eq(id))).set(new).execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use forms::dsl::uuid;
+
+        diesel::delete(forms::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl Model<FormSubmission, MutFormSubmission, String> for FormSubmission {
+    fn create(new: &MutFormSubmission, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(form_submissions::table).values(new).execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<FormSubmission, diesel::result::Error> {
+        use form_submissions::dsl::uuid;
+
+        form_submissions::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<FormSubmission>, diesel::result::Error> {
+        use form_submissions::dsl::{time_created, uuid};
+
+        form_submissions::table.order((time_created.desc(), uuid.asc())).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<FormSubmission>, i64), diesel::result::Error> {
+        use form_submissions::dsl::{time_created, uuid};
+
+        let total = form_submissions::table.count().get_result(db)?;
+        let rows = form_submissions::table
+            .order((time_created.desc(), uuid.asc()))
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(id: String, new: &MutFormSubmission, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use form_submissions::dsl::uuid;
+
+        diesel::update(form_submissions::table.filter(uuid.eq(id))).set(new).execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use form_submissions::dsl::uuid;
+
+        diesel::delete(form_submissions::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl FormSubmission {
+    /// The submissions with the email as one of their values, like in the `email` field of a contact form, oldest first.
+    pub fn read_by_email(email: &str, db: &DbConnection) -> Result<Vec<FormSubmission>, diesel::result::Error> {
+        use form_submissions::dsl::{data, time_created};
+
+        // `LIKE` only narrows them down, as it can't tell a value from part of one.
+        let candidates = form_submissions::table
+            .filter(data.like(format!("%{}%", email)))
+            .order(time_created.asc())
+            .load::<Self>(db)?;
+
+        Ok(candidates.into_iter().filter(|s| FormSubmissionDTO::from(s.clone()).has_value(email)).collect())
+    }
+
+    pub fn delete_many(ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use form_submissions::dsl::uuid;
+
+        diesel::delete(form_submissions::table.filter(uuid.eq_any(ids))).execute(db)
+    }
+
+    /// The form's submissions, newest first.
+    pub fn read_for_form(form: &str, db: &DbConnection) -> Result<Vec<FormSubmission>, diesel::result::Error> {
+        use form_submissions::dsl::{form_uuid, time_created, uuid};
+
+        form_submissions::table
+            .filter(form_uuid.eq(form))
+            .order((time_created.desc(), uuid.asc()))
+            .load::<Self>(db)
+    }
+
+    pub fn read_for_form_paginated(
+        form: &str,
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<FormSubmission>, i64), diesel::result::Error> {
+        use form_submissions::dsl::{form_uuid, time_created, uuid};
+
+        let total = form_submissions::table.filter(form_uuid.eq(form)).count().get_result(db)?;
+        let rows = form_submissions::table
+            .filter(form_uuid.eq(form))
+            .order((time_created.desc(), uuid.asc()))
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::gallery_items;
+
+/// A piece of media in a gallery module. Any module with items is a gallery, and its items are shown in `position` order.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "gallery_items"]
+pub struct GalleryItem {
+    pub uuid: String,
+    pub module_uuid: String,
+    pub media_url: String,
+    pub caption: Option<String>,
+    pub position: i32,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize, ToSchema)]
+#[table_name = "gallery_items"]
+pub struct MutGalleryItem {
+    pub uuid: Option<String>,
+    pub module_uuid: String,
+    pub media_url: String,
+    pub caption: Option<String>,
+    pub position: i32,
+}
+
+impl Model<GalleryItem, MutGalleryItem, String> for GalleryItem {
+    fn create(new: &MutGalleryItem, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(gallery_items::table).values(new).execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<GalleryItem, diesel::result::Error> {
+        use gallery_items::dsl::uuid;
+
+        gallery_items::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<GalleryItem>, diesel::result::Error> {
+        gallery_items::table.load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<GalleryItem>, i64), diesel::result::Error> {
+        use gallery_items::dsl::uuid;
+
+        let total = gallery_items::table.count().get_result(db)?;
+        let rows = gallery_items::table.order(uuid.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutGalleryItem,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use gallery_items::dsl::uuid;
+
+        diesel::update(gallery_items::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use gallery_items::dsl::uuid;
+
+        diesel::delete(gallery_items::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl GalleryItem {
+    pub fn read_for_module(id: String, db: &DbConnection) -> Result<Vec<GalleryItem>, diesel::result::Error> {
+        use gallery_items::dsl::{module_uuid, position};
+
+        gallery_items::table
+            .filter(module_uuid.eq(id))
+            .order(position.asc())
+            .load::<Self>(db)
+    }
+
+    /// Every gallery among the given modules, keyed by the module's uuid.
+    pub fn read_for_modules(
+        ids: Vec<String>,
+        db: &DbConnection,
+    ) -> Result<HashMap<String, Vec<GalleryItem>>, diesel::result::Error> {
+        use gallery_items::dsl::{module_uuid, position};
+
+        let items = gallery_items::table
+            .filter(module_uuid.eq_any(ids))
+            .order(position.asc())
+            .load::<Self>(db)?;
+
+        let mut galleries: HashMap<String, Vec<GalleryItem>> = HashMap::new();
+        for item in items {
+            galleries.entry(item.module_uuid.clone()).or_default().push(item);
+        }
+
+        Ok(galleries)
+    }
+
+    pub fn delete_for_module(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use gallery_items::dsl::module_uuid;
+
+        diesel::delete(gallery_items::table.filter(module_uuid.eq(id))).execute(db)
+    }
+
+    /// The position an item added to the end of the gallery gets.
+    pub fn next_position(id: String, db: &DbConnection) -> Result<i32, diesel::result::Error> {
+        use gallery_items::dsl::{module_uuid, position};
+
+        let last: Option<i32> = gallery_items::table
+            .filter(module_uuid.eq(id))
+            .select(diesel::dsl::max(position))
+            .first(db)?;
+
+        Ok(last.map(|p| p + 1).unwrap_or(0))
+    }
+
+    /// Puts the gallery's items in the order of `ids`.
+    pub fn reorder(id: String, ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use gallery_items::dsl::{module_uuid, position, uuid};
+
+        db.transaction(|| {
+            for (index, item) in ids.iter().enumerate() {
+                diesel::update(gallery_items::table.filter(uuid.eq(item)).filter(module_uuid.eq(&id)))
+                    .set(position.eq(index as i32))
+                    .execute(db)?;
+            }
+
+            Ok(ids.len())
+        })
+    }
+}
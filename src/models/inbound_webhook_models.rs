@@ -0,0 +1,84 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::inbound_webhooks;
+
+/// An endpoint external systems can push content to.
+/// `target` is what gets created or updated, either `page` or `module`, and `transform` is the JSON mapping
+/// from the incoming payload onto it. The secret signs the payloads, so it is only shown when the webhook is created.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "inbound_webhooks"]
+pub struct InboundWebhook {
+    pub uuid: String,
+    pub name: String,
+    #[serde(skip)]
+    pub secret: String,
+    pub target: String,
+    pub transform: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "inbound_webhooks"]
+pub struct MutInboundWebhook {
+    pub uuid: String,
+    pub name: String,
+    pub secret: String,
+    pub target: String,
+    pub transform: String,
+}
+
+impl Model<InboundWebhook, MutInboundWebhook, String> for InboundWebhook {
+    fn create(new: &MutInboundWebhook, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(inbound_webhooks::table)
+            .values(new)
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<InboundWebhook, diesel::result::Error> {
+        use inbound_webhooks::dsl::uuid;
+
+        inbound_webhooks::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<InboundWebhook>, diesel::result::Error> {
+        use inbound_webhooks::dsl::time_created;
+
+        inbound_webhooks::table.order(time_created.desc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<InboundWebhook>, i64), diesel::result::Error> {
+        use inbound_webhooks::dsl::{time_created, uuid};
+
+        let total = inbound_webhooks::table.count().get_result(db)?;
+        let rows = inbound_webhooks::table.order((time_created.desc(), uuid.asc())).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutInboundWebhook,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use inbound_webhooks::dsl::uuid;
+
+        diesel::update(inbound_webhooks::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use inbound_webhooks::dsl::uuid;
+
+        diesel::delete(inbound_webhooks::table.filter(uuid.eq(id))).execute(db)
+    }
+}
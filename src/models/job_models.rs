@@ -0,0 +1,166 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::DbConnection;
+use crate::schema::jobs;
+
+/// Waiting for its `run_at`, either for the first time or for a retry.
+pub const PENDING: &str = "pending";
+/// Claimed by a worker until its `run_at`, after which it is taken to be lost and run again.
+pub const RUNNING: &str = "running";
+/// Given up on after `max_attempts`, and kept so that it can be looked into.
+pub const FAILED: &str = "failed";
+pub const STATUSES: &[&str] = &[PENDING, RUNNING, FAILED];
+
+type Backend = <DbConnection as Connection>::Backend;
+
+/// Work that is done after the request that asked for it, like a webhook delivery or a mail, by `job_service::run`.
+/// Jobs that succeed are deleted, so the table only holds what is waiting, running, or failed.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "jobs"]
+pub struct Job {
+    pub uuid: String,
+    /// What the job does, like `webhook.deliver`, which picks the handler that runs it.
+    pub kind: String,
+    /// Left out of responses, as it can hold what a mail says or where a webhook goes.
+    #[serde(skip)]
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub last_error: Option<String>,
+    pub time_created: NaiveDateTime,
+    pub time_updated: NaiveDateTime,
+    pub run_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "jobs"]
+pub struct MutJob {
+    pub uuid: String,
+    pub kind: String,
+    pub payload: String,
+    pub status: String,
+    pub max_attempts: i32,
+    pub run_at: NaiveDateTime,
+}
+
+impl Job {
+    /// The payload as what its handler expects.
+    pub fn data<T: DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_str(&self.payload).map_err(|e| format!("invalid payload: {}", e))
+    }
+
+    pub fn create(new: &MutJob, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(jobs::table).values(new).execute(db)
+    }
+
+    /// Claims up to `limit` jobs that are due, marking them as running until `lease` from now and counting the attempt.
+    /// A job is only claimed if no one else claimed it in the meantime, so several servers can share the table.
+    pub fn claim_due(limit: i64, lease: chrono::Duration, db: &DbConnection) -> Result<Vec<Job>, diesel::result::Error> {
+        use jobs::dsl::{attempts, run_at, status, time_updated, uuid};
+
+        let now = Utc::now().naive_utc();
+        let due = jobs::table
+            .filter(status.eq_any(&[PENDING, RUNNING]))
+            .filter(run_at.le(now))
+            .order((run_at.asc(), uuid.asc()))
+            .limit(limit)
+            .load::<Self>(db)?;
+
+        let mut claimed = Vec::new();
+        for job in due {
+            let updated = diesel::update(
+                jobs::table.filter(uuid.eq(&job.uuid)).filter(status.eq(&job.status)).filter(run_at.eq(job.run_at)),
+            )
+            .set((status.eq(RUNNING), run_at.eq(now + lease), attempts.eq(attempts + 1), time_updated.eq(now)))
+            .execute(db)?;
+
+            if updated == 1 {
+                claimed.push(Job {
+                    status: String::from(RUNNING),
+                    attempts: job.attempts + 1,
+                    run_at: now + lease,
+                    time_updated: now,
+                    ..job
+                });
+            }
+        }
+
+        Ok(claimed)
+    }
+
+    /// Done with a job that succeeded.
+    pub fn complete(id: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use jobs::dsl::uuid;
+
+        diesel::delete(jobs::table.filter(uuid.eq(id))).execute(db)
+    }
+
+    /// Puts a job that failed back to wait until `at`.
+    pub fn retry(id: &str, error: &str, at: NaiveDateTime, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use jobs::dsl::{last_error, run_at, status, time_updated, uuid};
+
+        diesel::update(jobs::table.filter(uuid.eq(id)))
+            .set((status.eq(PENDING), last_error.eq(error), run_at.eq(at), time_updated.eq(Utc::now().naive_utc())))
+            .execute(db)
+    }
+
+    pub fn fail(id: &str, error: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use jobs::dsl::{last_error, status, time_updated, uuid};
+
+        diesel::update(jobs::table.filter(uuid.eq(id)))
+            .set((status.eq(FAILED), last_error.eq(error), time_updated.eq(Utc::now().naive_utc())))
+            .execute(db)
+    }
+
+    /// The jobs of one status or every one, and of one kind or every one.
+    fn filtered<'a>(status: Option<&'a str>, kind: Option<&'a str>) -> jobs::BoxedQuery<'a, Backend> {
+        let mut query = jobs::table.into_boxed();
+        if let Some(status) = status {
+            query = query.filter(jobs::status.eq(status));
+        }
+        if let Some(kind) = kind {
+            query = query.filter(jobs::kind.eq(kind));
+        }
+
+        query
+    }
+
+    /// Next due first, which is also the order they are run in.
+    pub fn read_filtered(status: Option<&str>, kind: Option<&str>, db: &DbConnection) -> Result<Vec<Job>, diesel::result::Error> {
+        use jobs::dsl::{run_at, uuid};
+
+        Self::filtered(status, kind).order((run_at.asc(), uuid.asc())).load::<Self>(db)
+    }
+
+    pub fn read_filtered_paginated(
+        status: Option<&str>,
+        kind: Option<&str>,
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Job>, i64), diesel::result::Error> {
+        use jobs::dsl::{run_at, uuid};
+
+        let total = Self::filtered(status, kind).count().get_result(db)?;
+        let rows = Self::filtered(status, kind)
+            .order((run_at.asc(), uuid.asc()))
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    /// How many jobs of the kind are waiting to be run or retried, or are running.
+    pub fn count_waiting(job_kind: &str, db: &DbConnection) -> Result<i64, diesel::result::Error> {
+        use jobs::dsl::{kind, status};
+
+        jobs::table.filter(kind.eq(job_kind)).filter(status.eq_any(&[PENDING, RUNNING])).count().get_result(db)
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::site_models::default_site;
+use super::{DbConnection, Model};
+use crate::schema::{gallery_items, media, menu_items, page_revisions, pages, settings};
+use crate::services::site_service;
+
+/// An uploaded file, like an image for a module. The file itself is kept in `APP_MEDIA_DIR` under its uuid,
+/// and `checksum` is the hex SHA-256 of it.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "media"]
+pub struct Media {
+    pub uuid: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub checksum: String,
+    pub time_created: NaiveDateTime,
+    /// The site the file was uploaded to, which is the only one it is served on.
+    #[serde(default = "default_site")]
+    pub site_id: String,
+}
+
+type OnSite = diesel::dsl::Or<diesel::dsl::Eq<media::site_id, String>, diesel::dsl::AsExprOf<bool, Bool>>;
+
+/// Scopes a query to the current site's media, or to every site's outside of a request (see `site_service::current`).
+fn on_site() -> OnSite {
+    let site = site_service::current();
+
+    media::site_id.eq(site.clone().unwrap_or_default()).or(site.is_none().into_sql::<Bool>())
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "media"]
+pub struct MutMedia {
+    pub uuid: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub checksum: String,
+}
+
+impl Model<Media, MutMedia, String> for Media {
+    fn create(new: &MutMedia, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(media::table)
+            .values((new, media::site_id.eq(site_service::current_or_default())))
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<Media, diesel::result::Error> {
+        use media::dsl::uuid;
+
+        media::table.filter(on_site()).filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<Media>, diesel::result::Error> {
+        use media::dsl::time_created;
+
+        media::table.filter(on_site()).order(time_created.desc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Media>, i64), diesel::result::Error> {
+        use media::dsl::{time_created, uuid};
+
+        let total = media::table.filter(on_site()).count().get_result(db)?;
+        let rows = media::table.filter(on_site()).order((time_created.desc(), uuid.asc())).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutMedia,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use media::dsl::uuid;
+
+        diesel::update(media::table.filter(on_site()).filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use media::dsl::uuid;
+
+        diesel::delete(media::table.filter(on_site()).filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl Media {
+    /// The bytes all of the site's uploads take up. They are added up here rather than with `SUM`, whose type isn't the
+    /// same on every backend.
+    pub fn total_size(db: &DbConnection) -> Result<i64, diesel::result::Error> {
+        use media::dsl::size;
+
+        Ok(media::table.filter(on_site()).select(size).load::<i64>(db)?.into_iter().sum())
+    }
+
+    pub fn read_created_before(time: NaiveDateTime, db: &DbConnection) -> Result<Vec<Media>, diesel::result::Error> {
+        use media::dsl::{time_created, uuid};
+
+        media::table.filter(on_site()).filter(time_created.lt(time)).order(uuid.asc()).load::<Self>(db)
+    }
+
+    /// Everything but modules that can link to an upload, on every site: gallery items, the images of pages, revisions,
+    /// menu items, and settings.
+    pub fn read_links(db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+        let mut links = gallery_items::table.select(gallery_items::media_url).load::<String>(db)?;
+        links.extend(pages::table.select(pages::og_image).load::<Option<String>>(db)?.into_iter().flatten());
+        links.extend(page_revisions::table.select(page_revisions::content).load::<String>(db)?);
+        links.extend(menu_items::table.select(menu_items::url).load::<Option<String>>(db)?.into_iter().flatten());
+        links.extend(settings::table.select(settings::setting_value).load::<String>(db)?);
+
+        Ok(links)
+    }
+
+    pub fn delete_many(ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use media::dsl::uuid;
+
+        diesel::delete(media::table.filter(on_site()).filter(uuid.eq_any(ids))).execute(db)
+    }
+}
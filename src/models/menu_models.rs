@@ -0,0 +1,276 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::{menu_items, menus, pages};
+
+/// A navigation menu, like `main` or `footer`, which templates find under `menus` by its name.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "menus"]
+pub struct Menu {
+    pub uuid: String,
+    pub name: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "menus"]
+pub struct MutMenu {
+    pub uuid: String,
+    pub name: String,
+}
+
+/// The body of `POST /api/v1/menus` and `PUT /api/v1/menus/{id}`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewMenu {
+    pub name: String,
+}
+
+/// A link in a menu, to a page by its uuid, so that it follows the page when its URL changes, or to any other URL.
+/// Items under `parent_item` are nested under it, and the items next to each other are shown in `position` order.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "menu_items"]
+pub struct MenuItem {
+    pub uuid: String,
+    pub menu_uuid: String,
+    pub parent_item: Option<String>,
+    pub title: String,
+    pub page_uuid: Option<String>,
+    pub url: Option<String>,
+    pub position: i32,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "menu_items"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct MutMenuItem {
+    pub uuid: String,
+    pub menu_uuid: String,
+    pub parent_item: Option<String>,
+    pub title: String,
+    pub page_uuid: Option<String>,
+    pub url: Option<String>,
+    pub position: i32,
+}
+
+/// The body of `POST /api/v1/menus/{id}/items` and `PUT /api/v1/menus/{id}/items/{item_id}`. An item links to either a page or a URL.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewMenuItem {
+    pub title: String,
+    pub page_uuid: Option<String>,
+    /// A path on the site, like `/contact`, or an `http://` or `https://` URL.
+    pub url: Option<String>,
+    /// The item this one is nested under, which has to be in the same menu.
+    pub parent_item: Option<String>,
+}
+
+/// A menu item and everything nested under it, as editors see it.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MenuItemTree {
+    #[serde(flatten)]
+    pub item: MenuItem,
+    pub children: Vec<MenuItemTree>,
+}
+
+/// A menu with its items nested as they are shown.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MenuTree {
+    #[serde(flatten)]
+    pub menu: Menu,
+    pub items: Vec<MenuItemTree>,
+}
+
+/// A menu item as templates get it, with the URL it links to worked out.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NavItem {
+    pub title: String,
+    pub url: String,
+    /// Whether it links to the page being rendered.
+    pub active: bool,
+    pub children: Vec<NavItem>,
+}
+
+impl Model<Menu, MutMenu, String> for Menu {
+    fn create(new: &MutMenu, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(menus::table).values(new).execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<Menu, diesel::result::Error> {
+        use menus::dsl::uuid;
+
+        menus::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<Menu>, diesel::result::Error> {
+        use menus::dsl::name;
+
+        menus::table.order(name.asc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Menu>, i64), diesel::result::Error> {
+        use menus::dsl::name;
+
+        let total = menus::table.count().get_result(db)?;
+        let rows = menus::table.order(name.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutMenu,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use menus::dsl::uuid;
+
+        diesel::update(menus::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    /// Its items go along with it.
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use menus::dsl::uuid;
+
+        db.transaction(|| {
+            diesel::delete(menu_items::table.filter(menu_items::menu_uuid.eq(&id))).execute(db)?;
+            diesel::delete(menus::table.filter(uuid.eq(&id))).execute(db)
+        })
+    }
+}
+
+impl Model<MenuItem, MutMenuItem, String> for MenuItem {
+    fn create(new: &MutMenuItem, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(menu_items::table).values(new).execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<MenuItem, diesel::result::Error> {
+        use menu_items::dsl::uuid;
+
+        menu_items::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<MenuItem>, diesel::result::Error> {
+        use menu_items::dsl::{position, uuid};
+
+        menu_items::table.order((position.asc(), uuid.asc())).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<MenuItem>, i64), diesel::result::Error> {
+        use menu_items::dsl::uuid;
+
+        let total = menu_items::table.count().get_result(db)?;
+        let rows = menu_items::table.order(uuid.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutMenuItem,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use menu_items::dsl::uuid;
+
+        diesel::update(menu_items::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    /// Everything nested under it goes along with it.
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use menu_items::dsl::{parent_item, uuid};
+
+        // deepest first, so the foreign key doesn't delete any of them along with their parent, where they wouldn't be counted.
+        db.transaction(|| {
+            let mut levels = vec![vec![id]];
+            let mut seen = levels[0].clone();
+            while let Some(level) = levels.last().filter(|l| !l.is_empty()) {
+                let children: Vec<String> = menu_items::table
+                    .filter(parent_item.eq_any(level))
+                    .filter(uuid.ne_all(&seen))
+                    .select(uuid)
+                    .load(db)?;
+                seen.extend(children.iter().cloned());
+                levels.push(children);
+            }
+
+            let mut deleted = 0;
+            for level in levels.iter().rev() {
+                deleted += diesel::delete(menu_items::table.filter(uuid.eq_any(level))).execute(db)?;
+            }
+
+            Ok(deleted)
+        })
+    }
+}
+
+impl MenuItem {
+    /// The menu's items, in `position` order.
+    pub fn read_for_menu(id: String, db: &DbConnection) -> Result<Vec<MenuItem>, diesel::result::Error> {
+        use menu_items::dsl::{menu_uuid, position, uuid};
+
+        menu_items::table
+            .filter(menu_uuid.eq(id))
+            .order((position.asc(), uuid.asc()))
+            .load::<Self>(db)
+    }
+
+    /// The position an item added to the end of the menu gets.
+    pub fn next_position(id: String, db: &DbConnection) -> Result<i32, diesel::result::Error> {
+        use menu_items::dsl::{menu_uuid, position};
+
+        let last: Option<i32> = menu_items::table
+            .filter(menu_uuid.eq(id))
+            .select(diesel::dsl::max(position))
+            .first(db)?;
+
+        Ok(last.map(|p| p + 1).unwrap_or(0))
+    }
+
+    /// Puts the menu's items in the order of `ids`.
+    pub fn reorder(id: String, ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use menu_items::dsl::{menu_uuid, position, uuid};
+
+        db.transaction(|| {
+            for (index, item) in ids.iter().enumerate() {
+                diesel::update(menu_items::table.filter(uuid.eq(item)).filter(menu_uuid.eq(&id)))
+                    .set(position.eq(index as i32))
+                    .execute(db)?;
+            }
+
+            Ok(ids.len())
+        })
+    }
+
+    /// Whether any menu links to the page, so that every page showing that menu changes along with it.
+    pub fn links_to_page(id: &str, db: &DbConnection) -> Result<bool, diesel::result::Error> {
+        use menu_items::dsl::page_uuid;
+
+        let count: i64 = menu_items::table.filter(page_uuid.eq(id)).count().get_result(db)?;
+        Ok(count > 0)
+    }
+
+    /// The same as `links_to_page`, for the page that was at `url`, including one in the trash, or an item linking to it directly.
+    pub fn links_to_url(url: &str, db: &DbConnection) -> Result<bool, diesel::result::Error> {
+        let ids: Vec<String> = pages::table.filter(pages::page_url.eq(url)).select(pages::uuid).load(db)?;
+        let count: i64 = menu_items::table
+            .filter(menu_items::url.eq(url).or(menu_items::page_uuid.eq_any(ids)))
+            .count()
+            .get_result(db)?;
+
+        Ok(count > 0)
+    }
+}
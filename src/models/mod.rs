@@ -1,17 +1,80 @@
+pub mod api_key_models;
+pub mod comment_models;
 pub mod config_models;
+pub mod content_type_models;
+pub mod data_request_models;
+pub mod delivery_token_models;
+pub mod form_models;
+pub mod gallery_item_models;
+pub mod inbound_webhook_models;
+pub mod job_models;
+pub mod media_models;
+pub mod menu_models;
 pub mod module_models;
 pub mod page_models;
+pub mod page_revision_models;
+pub mod page_rollout_models;
+pub mod password_reset_models;
+pub mod recovery_code_models;
+pub mod redirect_models;
+pub mod search_document_models;
+pub mod setting_models;
+pub mod signing_key_models;
+pub mod site_models;
+pub mod taxonomy_models;
 pub mod user_models;
+pub mod webhook_models;
 
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix_web::error::BlockingError;
 use actix_web::web;
-use diesel::{MysqlConnection, query_builder::AsChangeset, r2d2::{ConnectionManager, Pool, PoolError, PooledConnection}};
+use diesel::{query_builder::AsChangeset, r2d2::{ConnectionManager, Pool, PoolError, PooledConnection}, Connection, ConnectionResult};
+
+#[cfg(feature = "sqlite")]
+use diesel::connection::SimpleConnection;
 
+use crate::services::db_health_service::PoolEventHandler;
 use crate::services::errors_service::CustomHttpError;
+use crate::services::site_service;
 
 use self::config_models::LocalConfig;
 
-pub type MySQLPool = Pool<ConnectionManager<MysqlConnection>>;
-pub type MySQLPooledConnection = PooledConnection<ConnectionManager<MysqlConnection>>;
+#[cfg(not(any(feature = "mysql", feature = "postgres", feature = "sqlite")))]
+compile_error!("One of the `mysql`, `postgres`, or `sqlite` features has to be enabled.");
+#[cfg(any(
+    all(feature = "mysql", feature = "postgres"),
+    all(feature = "mysql", feature = "sqlite"),
+    all(feature = "postgres", feature = "sqlite")
+))]
+compile_error!("Only one of the `mysql`, `postgres`, or `sqlite` features can be enabled. Use `--no-default-features` to pick one other than `mysql`.");
+
+/// The connection every model and service works with, picked by the `mysql`, `postgres`, or `sqlite` feature.
+#[cfg(feature = "mysql")]
+pub type DbConnection = diesel::MysqlConnection;
+#[cfg(feature = "postgres")]
+pub type DbConnection = diesel::PgConnection;
+#[cfg(feature = "sqlite")]
+pub type DbConnection = diesel::SqliteConnection;
+
+/// The scheme of the connection strings built from the `APP_MYSQL_*` keys.
+#[cfg(feature = "mysql")]
+const URL_SCHEME: &str = "mysql";
+#[cfg(feature = "postgres")]
+const URL_SCHEME: &str = "postgres";
+
+/// Where the database file is kept when `APP_SQLITE_PATH` isn't set.
+#[cfg(feature = "sqlite")]
+const DEFAULT_SQLITE_PATH: &str = "radical.db";
+
+/// SQLite leaves foreign keys, and so every `ON DELETE CASCADE`, off unless each connection turns them on.
+/// The busy timeout lets the pool's connections wait on each other's writes instead of failing straight away.
+#[cfg(feature = "sqlite")]
+pub(crate) const SQLITE_PRAGMAS: &str = "PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;";
+
+pub type DbPool = Pool<ConnectionManager<DbConnection>>;
+pub type DbPooledConnection = PooledConnection<ConnectionManager<DbConnection>>;
 
 /// CRUD implementation.
 /// TQueryable: The queryable struct.
@@ -19,15 +82,21 @@ pub type MySQLPooledConnection = PooledConnection<ConnectionManager<MysqlConnect
 /// TPrimary: The primary key type.
 /// TDto: The DTO object that will be sent back to the user.
 pub trait Model<TQueryable, TMutable: AsChangeset, TPrimary, TDto = TQueryable> {
-    fn create(new: &TMutable, db: &MysqlConnection) -> Result<usize, diesel::result::Error>;
-    fn read_one(id: TPrimary, db: &MysqlConnection) -> Result<TDto, diesel::result::Error>;
-    fn read_all(db: &MysqlConnection) -> Result<Vec<TDto>, diesel::result::Error>;
+    fn create(new: &TMutable, db: &DbConnection) -> Result<usize, diesel::result::Error>;
+    fn read_one(id: TPrimary, db: &DbConnection) -> Result<TDto, diesel::result::Error>;
+    fn read_all(db: &DbConnection) -> Result<Vec<TDto>, diesel::result::Error>;
+    /// Up to `limit` rows from `offset` on, in the same order every time, along with how many rows there are in total.
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<TDto>, i64), diesel::result::Error>;
     fn update(
         id: TPrimary,
         new: &TMutable,
-        db: &MysqlConnection,
+        db: &DbConnection,
     ) -> Result<usize, diesel::result::Error>;
-    fn delete(id: TPrimary, db: &MysqlConnection) -> Result<usize, diesel::result::Error>;
+    fn delete(id: TPrimary, db: &DbConnection) -> Result<usize, diesel::result::Error>;
 }
 
 pub trait DTO<TColumns> {
@@ -40,15 +109,22 @@ pub trait DTO<TColumns> {
 pub trait Joinable<TLeft, TRight, TPrimary> {
     fn read_one_join_on(
         id: TPrimary,
-        db: &MysqlConnection,
+        db: &DbConnection,
     ) -> Result<(TLeft, Vec<TRight>), diesel::result::Error>;
 }
 
+#[cfg(feature = "sqlite")]
+pub fn format_connection_string(conf: LocalConfig) -> String {
+    conf.sqlite_path.unwrap_or_else(|| String::from(DEFAULT_SQLITE_PATH))
+}
+
+#[cfg(not(feature = "sqlite"))]
 pub fn format_connection_string(conf: LocalConfig) -> String {
     match conf.mysql_url {
         Some(mysql_url) => {
             format!(
-                "mysql://{}:{}@{}:{}/{}",
+                "{}://{}:{}@{}:{}/{}",
+                URL_SCHEME,
                 conf.mysql_username,
                 conf.mysql_password,
                 mysql_url,
@@ -58,7 +134,8 @@ pub fn format_connection_string(conf: LocalConfig) -> String {
         }
         None if std::env::var("MYSQL_UNIX_PORT").is_ok() => {
             format!(
-                "mysql://{}:{}@/{}",
+                "{}://{}:{}@/{}",
+                URL_SCHEME,
                 conf.mysql_username,
                 conf.mysql_password,
                 conf.mysql_database
@@ -70,22 +147,192 @@ pub fn format_connection_string(conf: LocalConfig) -> String {
     }
 }
 
-pub fn establish_database_connection(conf: LocalConfig) -> Option<MySQLPool> {
-    let db_url = format_connection_string(conf);
+/// A single connection outside of the pool, like for migrations and the CLI.
+pub fn establish_connection(conf: LocalConfig) -> ConnectionResult<DbConnection> {
+    let connection = DbConnection::establish(&format_connection_string(conf))?;
+
+    #[cfg(feature = "sqlite")]
+    connection
+        .batch_execute(SQLITE_PRAGMAS)
+        .map_err(|e| diesel::ConnectionError::BadConnection(e.to_string()))?;
+
+    Ok(connection)
+}
+
+pub fn establish_database_connection(conf: LocalConfig) -> Result<DbPool, PoolError> {
+    let db_url = format_connection_string(conf.clone());
 
-    Some(init_pool(&db_url).expect("Failed to create pool."))
+    init_pool(&db_url, &conf)
 }
 
-pub fn init_connection(db_url: &str) -> ConnectionManager<diesel::MysqlConnection> {
-    ConnectionManager::<MysqlConnection>::new(db_url)
+pub fn init_connection(db_url: &str) -> ConnectionManager<DbConnection> {
+    ConnectionManager::<DbConnection>::new(db_url)
 }
 
 // https://dev.to/werner/practical-rust-web-development-connection-pool-46f4
-pub fn init_pool(db_url: &str) -> Result<MySQLPool, PoolError> {
+/// Every database's pool is sized and timed out the same, by the `APP_DB_POOL_*` keys.
+pub fn init_pool(db_url: &str, conf: &LocalConfig) -> Result<DbPool, PoolError> {
     let manager = init_connection(db_url);
-    Pool::builder().max_size(2).build(manager)
+    let builder = Pool::builder()
+        .max_size(conf.db_pool_max_size)
+        .min_idle(conf.db_pool_min_idle)
+        .connection_timeout(Duration::from_secs(conf.db_pool_timeout))
+        // 0 keeps idle connections open for as long as the pool is.
+        .idle_timeout(Some(Duration::from_secs(conf.db_pool_idle_timeout)).filter(|t| !t.is_zero()))
+        .test_on_check_out(conf.db_pool_test_on_checkout)
+        .event_handler(Box::new(PoolEventHandler));
+
+    #[cfg(feature = "sqlite")]
+    let builder = builder.connection_customizer(Box::new(SqlitePragmas));
+
+    builder.build(manager)
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+struct SqlitePragmas;
+
+#[cfg(feature = "sqlite")]
+impl diesel::r2d2::CustomizeConnection<DbConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, connection: &mut DbConnection) -> Result<(), diesel::r2d2::Error> {
+        connection.batch_execute(SQLITE_PRAGMAS).map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// The database content lives in, configured through the `APP_MYSQL_*` keys. Migrations only run against it.
+pub const CONTENT_DATABASE: &str = "content";
+/// A read replica of the content database, configured through `APP_REPLICA_URL`, that reads are sent to.
+pub const REPLICA_DATABASE: &str = "replica";
+
+tokio::task_local! {
+    /// The pool `with_connection` takes its connections from instead of the one it is given, set by the `Replicas`
+    /// middleware around requests that only read.
+    static READ_POOL: DbPool;
+}
+
+/// Every configured database, each with its own pool, by name.
+/// Extra databases are configured through `APP_DATABASES`. Radical itself only keeps content, so nothing it ships
+/// writes to them yet; they are pooled, probed, and reported so that a workload like analytics can be moved onto one
+/// through `get` without touching the config again.
+#[derive(Clone)]
+pub struct Databases {
+    pools: HashMap<String, DbPool>,
+}
+
+impl Databases {
+    /// `APP_DATABASES` is a comma separated list of `name=mysql://..` pairs, `name=postgres://..` when built for Postgres,
+    /// or `name=path/to/file.db` when built for SQLite.
+    pub fn new(content: DbPool, conf: &LocalConfig) -> Result<Self, String> {
+        let mut pools = HashMap::new();
+        pools.insert(String::from(CONTENT_DATABASE), content);
+
+        for entry in conf.databases.iter().flat_map(|d| d.split(',')).filter(|e| !e.trim().is_empty()) {
+            let (name, url) = match entry.split_once('=') {
+                Some((name, url)) => (name.trim(), url.trim()),
+                None => return Err(format!("`{}` should be `name=url`", entry)),
+            };
+
+            if pools.contains_key(name) {
+                return Err(format!("The {} database is configured more than once", name));
+            }
+
+            let pool = init_pool(url, conf).map_err(|e| format!("Could not connect to the {} database: {}", name, e))?;
+            pools.insert(name.to_string(), pool);
+        }
+
+        if let Some(url) = conf.replica_url.as_deref().map(str::trim).filter(|url| !url.is_empty()) {
+            if pools.contains_key(REPLICA_DATABASE) {
+                return Err(format!("The {} database is configured more than once", REPLICA_DATABASE));
+            }
+
+            let pool = init_pool(url, conf).map_err(|e| format!("Could not connect to the {} database: {}", REPLICA_DATABASE, e))?;
+            pools.insert(String::from(REPLICA_DATABASE), pool);
+        }
+
+        Ok(Self { pools })
+    }
+
+    pub fn content(&self) -> &DbPool {
+        &self.pools[CONTENT_DATABASE]
+    }
+
+    /// Falls back to the content database for names that aren't configured,
+    /// so that everything still works on a deployment with a single database.
+    pub fn get(&self, name: &str) -> &DbPool {
+        self.pools.get(name).unwrap_or_else(|| self.content())
+    }
+
+    /// The read replica, if `APP_REPLICA_URL` is set.
+    pub fn replica(&self) -> Option<&DbPool> {
+        self.pools.get(REPLICA_DATABASE)
+    }
+
+    pub fn is_configured(&self, name: &str) -> bool {
+        self.pools.contains_key(name)
+    }
+
+    /// Every pool by name, the content database's included.
+    pub fn all(&self) -> impl Iterator<Item = (&str, &DbPool)> {
+        self.pools.iter().map(|(name, pool)| (name.as_str(), pool))
+    }
+}
+
+/// A connection from the pool. Not getting one within `APP_DB_POOL_TIMEOUT` is a 503, as every connection is busy
+/// or the database can't be reached, which the client can try again after.
+pub fn pool_handler(pool: web::Data<DbPool>) -> Result<DbPooledConnection, CustomHttpError> {
+    pool.get().map_err(|e| {
+        tracing::warn!("No database connection was free: {}", e);
+        CustomHttpError::PoolExhausted
+    })
+}
+
+/// Runs a request's future with its reads sent to `replica`. Only requests that don't write should be, as a replica
+/// refuses writes.
+pub async fn read_from<F: std::future::Future>(replica: DbPool, f: F) -> F::Output {
+    READ_POOL.scope(replica, f).await
+}
+
+/// Diesel is synchronous, so controllers run their database work through this, on actix's blocking thread pool.
+/// That way a slow query only holds up its own request, rather than every request on the same worker.
+/// The work stays scoped to the request's site on the thread it is handed to, and runs in a `db` span inside the request's.
+/// Requests that only read take their connection from the read replica when there is one (see `read_from`).
+pub async fn with_connection<T, F>(pool: web::Data<DbPool>, f: F) -> Result<T, CustomHttpError>
+where
+    T: Send + 'static,
+    F: FnOnce(&DbConnection) -> Result<T, CustomHttpError> + Send + 'static,
+{
+    let pool = READ_POOL.try_with(|replica| web::Data::new(replica.clone())).unwrap_or(pool);
+
+    run_blocking(pool, f).await
 }
 
-pub fn pool_handler(pool: web::Data<MySQLPool>) -> Result<MySQLPooledConnection, CustomHttpError> {
-    pool.get().or(Err(CustomHttpError::BadRequest))
+/// Like `with_connection`, but always on the content database, for the few reads that have to see the latest writes,
+/// like checking a token that was only just given out, and for writes that are made on a `GET`.
+pub async fn with_primary_connection<T, F>(pool: web::Data<DbPool>, f: F) -> Result<T, CustomHttpError>
+where
+    T: Send + 'static,
+    F: FnOnce(&DbConnection) -> Result<T, CustomHttpError> + Send + 'static,
+{
+    run_blocking(pool, f).await
+}
+
+async fn run_blocking<T, F>(pool: web::Data<DbPool>, f: F) -> Result<T, CustomHttpError>
+where
+    T: Send + 'static,
+    F: FnOnce(&DbConnection) -> Result<T, CustomHttpError> + Send + 'static,
+{
+    let site = site_service::current();
+    // a child of the request's span, entered on the thread the work is handed to.
+    let span = tracing::info_span!("db");
+
+    web::block(move || {
+        let _entered = span.enter();
+        let connection = pool_handler(pool)?;
+        site_service::scoped(site, || f(&connection))
+    })
+    .await
+    .map_err(|e| match e {
+        BlockingError::Error(e) => e,
+        BlockingError::Canceled => CustomHttpError::Unknown,
+    })
 }
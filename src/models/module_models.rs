@@ -1,50 +1,224 @@
+use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
+use diesel::sql_types::Bool;
 use diesel::{Insertable, Queryable, RunQueryDsl};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::collections::HashMap;
 
-use super::page_models::Page;
-use super::{Model};
+use super::gallery_item_models::GalleryItem;
+use super::page_models::{self, Page};
+use super::page_revision_models::PageRevision;
+use super::site_models::default_site;
+use super::{DbConnection, Model};
+use crate::services::{compression_service, sanitize_service, site_service};
 use crate::schema::module_category;
 use crate::schema::modules;
+use crate::schema::pages;
 
-#[derive(Debug, Identifiable, Associations, Serialize, Deserialize, Queryable, PartialEq, Clone, Eq, Hash)]
+#[derive(Debug, Identifiable, Associations, Serialize, Deserialize, Queryable, PartialEq, Clone, Eq, Hash, ToSchema)]
 #[belongs_to(Page, foreign_key = "page_uuid")]
 #[belongs_to(ModuleCategory, foreign_key = "category_uuid")]
 #[primary_key(uuid)]
 #[table_name = "modules"]
 pub struct Module {
     pub uuid: String,
-    pub page_uuid: String,
+    /// `None` for a global module, like a footer, which every page can show.
+    pub page_uuid: Option<String>,
     pub category_uuid: Option<String>,
     pub title: String,
     pub content: String,
+    /// Encrypted modules have their content encrypted at rest, and only decrypted for authorized reads.
+    pub encrypted: bool,
+    /// Whether the content is stored compressed. This is handled by the model, so it is never sent to the user.
+    #[serde(skip)]
+    pub compressed: bool,
+    /// The roles that can see this module, separated by commas, like `admin`. Everyone can when this is empty.
+    #[serde(default)]
+    pub visible_to: Option<String>,
+    /// When the module was moved to the trash, on its own or along with its page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<NaiveDateTime>,
+    /// Where the module comes in its page. Modules are always read in this order.
+    #[serde(default)]
+    pub order_index: i32,
+    /// One of the `ModuleType`s, or the name of a `ContentType`, which says how `content` is read. See `Module::value`.
+    #[serde(default = "text")]
+    pub module_type: String,
+    /// The site of the module's page.
+    #[serde(default = "default_site")]
+    pub site_id: String,
+    /// Counted up on every change, so that an update made from an older read of the module is refused (see `version_service`).
+    #[serde(default)]
+    pub version: i32,
+}
+
+fn text() -> String {
+    String::from(ModuleType::Text.as_str())
+}
+
+type OnSite = diesel::dsl::Or<diesel::dsl::Eq<modules::site_id, String>, diesel::dsl::AsExprOf<bool, Bool>>;
+
+/// Scopes a query to the current site's modules, or to every site's outside of a request (see `site_service::current`).
+fn on_site() -> OnSite {
+    let site = site_service::current();
+
+    modules::site_id.eq(site.clone().unwrap_or_default()).or(site.is_none().into_sql::<Bool>())
+}
+
+/// What a module holds. Text and rich text are stored as they are, and everything else as JSON.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleType {
+    Text,
+    /// HTML, which templates output without escaping it.
+    RichText,
+    Image,
+    Boolean,
+    Json,
+}
+
+impl ModuleType {
+    pub fn parse(module_type: &str) -> Option<Self> {
+        match module_type {
+            "text" => Some(Self::Text),
+            "rich_text" => Some(Self::RichText),
+            "image" => Some(Self::Image),
+            "boolean" => Some(Self::Boolean),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::RichText => "rich_text",
+            Self::Image => "image",
+            Self::Boolean => "boolean",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// The content of an image module, like `{ "url": "/assets/hero.png", "alt": "A hero" }`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImageRef {
+    pub url: String,
+    #[serde(default)]
+    pub alt: Option<String>,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+}
+
+/// The content of a module, read as its type.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ModuleValue {
+    Text(String),
+    RichText(String),
+    Image(ImageRef),
+    Boolean(bool),
+    Json(serde_json::Value),
 }
 
-#[derive(Insertable, AsChangeset, Deserialize, Serialize, Clone)]
+impl ModuleValue {
+    /// Reads `content` as a `module_type`, or `None` when it isn't one, like an image without a `url`.
+    /// Modules of a content type are read as JSON, which their schema was checked against when they were written.
+    pub fn parse(module_type: &str, content: &str) -> Option<Self> {
+        match ModuleType::parse(module_type).unwrap_or(ModuleType::Json) {
+            ModuleType::Text => Some(Self::Text(content.to_string())),
+            ModuleType::RichText => Some(Self::RichText(content.to_string())),
+            ModuleType::Image => serde_json::from_str(content).ok().map(Self::Image),
+            ModuleType::Boolean => serde_json::from_str(content).ok().map(Self::Boolean),
+            ModuleType::Json => serde_json::from_str(content).ok().map(Self::Json),
+        }
+    }
+}
+
+#[derive(Insertable, AsChangeset, Deserialize, Serialize, Clone, ToSchema)]
 #[table_name = "modules"]
 pub struct MutModule {
     pub uuid: Option<String>,
     pub title: String,
-    pub page_uuid: String,
+    /// Left out for the modules of a page that is created along with them, and for global modules.
+    #[serde(default)]
+    pub page_uuid: Option<String>,
     pub category_uuid: Option<String>,
     pub content: String,
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default, skip_serializing)]
+    pub compressed: bool,
+    #[serde(default)]
+    pub visible_to: Option<String>,
+    /// New modules go after the last one on their page unless this says otherwise, and updates leave it alone when it isn't set.
+    #[serde(default)]
+    pub order_index: Option<i32>,
+    /// New modules are text unless this says otherwise, and updates leave the type alone when it isn't set.
+    #[serde(default)]
+    pub module_type: Option<String>,
+}
+
+/// The body of a partial update, like `{ "content": "Hello" }`. Anything that isn't set is left as it is.
+#[derive(Deserialize, Serialize, Clone, Default, ToSchema)]
+pub struct PatchModule {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub page_uuid: Option<String>,
+    #[serde(default)]
+    pub category_uuid: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub encrypted: Option<bool>,
+    #[serde(default)]
+    pub visible_to: Option<String>,
+    #[serde(default)]
+    pub order_index: Option<i32>,
+    #[serde(default)]
+    pub module_type: Option<String>,
+}
+
+impl PatchModule {
+    /// The update this makes to `module`, whose content has to be decrypted already.
+    pub fn apply(self, module: &Module) -> MutModule {
+        MutModule {
+            uuid: None,
+            title: self.title.unwrap_or_else(|| module.title.clone()),
+            page_uuid: self.page_uuid.or_else(|| module.page_uuid.clone()),
+            category_uuid: self.category_uuid,
+            content: self.content.unwrap_or_else(|| module.content.clone()),
+            encrypted: self.encrypted.unwrap_or(module.encrypted),
+            compressed: false,
+            visible_to: self.visible_to,
+            order_index: self.order_index,
+            module_type: self.module_type,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct CategoryDTO {
     pub uuid: String,
     pub title: String,
     pub modules: Vec<Module>
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
 pub struct FieldsDTO {
     pub modules: Vec<Module>,
-    pub categories: Option<Vec<CategoryDTO>>
+    pub categories: Option<Vec<CategoryDTO>>,
+    /// The items of every gallery module on the page, keyed by the module's uuid.
+    #[serde(default)]
+    pub galleries: HashMap<String, Vec<GalleryItem>>,
 }
 
 #[derive(
-    Debug, Identifiable, Associations, Serialize, Deserialize, Queryable, PartialEq, Clone, Eq, Hash,
+    Debug, Identifiable, Associations, Serialize, Deserialize, Queryable, PartialEq, Clone, Eq, Hash, ToSchema,
 )]
 #[primary_key(uuid)]
 #[belongs_to(Page, foreign_key = "page_uuid")]
@@ -56,7 +230,7 @@ pub struct ModuleCategory {
 }
 
 #[derive(
-    Debug, Serialize, Deserialize, AsChangeset, Insertable, PartialEq, Clone, Eq, Hash,
+    Debug, Serialize, Deserialize, AsChangeset, Insertable, PartialEq, Clone, Eq, Hash, ToSchema,
 )]
 #[table_name = "module_category"]
 pub struct MutCategory {
@@ -66,22 +240,97 @@ pub struct MutCategory {
 }
 
 impl ModuleCategory {
-    pub fn join(_id: String, db: &MysqlConnection) -> Result<Vec<Module>, diesel::result::Error> {
+    /// The categories of `page` with their modules, grouped in two queries however many categories there are.
+    /// Categories without any modules are kept, with none.
+    pub fn read_for_page(page: &Page, db: &DbConnection) -> Result<Vec<CategoryDTO>, diesel::result::Error> {
+        let categories = ModuleCategory::belonging_to(page)
+            .order(module_category::uuid.asc())
+            .load::<ModuleCategory>(db)?;
+
+        let modules = Module::inflate_all(Module::belonging_to(&categories)
+            .filter(modules::deleted_at.is_null())
+            .order((modules::order_index.asc(), modules::uuid.asc()))
+            .load::<Module>(db)?)?
+            .grouped_by(&categories);
+
+        Ok(categories
+            .into_iter()
+            .zip(modules)
+            .map(|(category, modules)| CategoryDTO {
+                uuid: category.uuid,
+                title: category.title,
+                modules,
+            })
+            .collect())
+    }
+
+    pub fn join(_id: String, db: &DbConnection) -> Result<Vec<Module>, diesel::result::Error> {
         use module_category::dsl::uuid;
         let categories = module_category::table.filter(uuid.eq(_id)).first::<Self>(db)?;
 
-        Module::belonging_to(&categories).load::<Module>(db)
+        Module::inflate_all(Module::belonging_to(&categories)
+            .filter(modules::deleted_at.is_null())
+            .order((modules::order_index.asc(), modules::uuid.asc()))
+            .load::<Module>(db)?)
+    }
+}
+
+impl ModuleCategory {
+    /// Categories whose page no longer exists. Categories are on the site of their page, so once it is gone they are only
+    /// known to be on the current site by the modules in them.
+    pub fn read_orphaned(db: &DbConnection) -> Result<Vec<ModuleCategory>, diesel::result::Error> {
+        let site = site_service::current();
+
+        module_category::table
+            .left_join(pages::table)
+            .filter(pages::uuid.nullable().is_null())
+            .filter(
+                module_category::uuid
+                    .nullable()
+                    .eq_any(modules::table.filter(on_site()).select(modules::category_uuid))
+                    .or(site.is_none().into_sql::<Bool>()),
+            )
+            .select(module_category::all_columns)
+            .load::<Self>(db)
+    }
+
+    /// The categories of the given pages.
+    pub fn read_for_pages(ids: Vec<String>, db: &DbConnection) -> Result<Vec<ModuleCategory>, diesel::result::Error> {
+        use module_category::dsl::{page_uuid, uuid};
+
+        module_category::table.filter(page_uuid.eq_any(ids)).order(uuid.asc()).load::<Self>(db)
+    }
+
+    /// Only deletes the categories on the current site, by their page or, like `read_orphaned`, by their modules.
+    pub fn delete_many(ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use module_category::dsl::{page_uuid, uuid};
+
+        let site = site_service::current();
+        let on_site = page_uuid
+            .eq_any(pages::table.filter(page_models::on_site()).select(pages::uuid))
+            .or(uuid.nullable().eq_any(modules::table.filter(on_site()).select(modules::category_uuid)))
+            .or(site.is_none().into_sql::<Bool>());
+
+        diesel::delete(module_category::table.filter(uuid.eq_any(ids)).filter(on_site)).execute(db)
     }
 }
 
 impl Model<Self, MutCategory, String, ModuleCategory> for ModuleCategory {
-    fn create(new: &MutCategory, db: &MysqlConnection) -> Result<usize, diesel::result::Error> {
-        Ok(diesel::insert_or_ignore_into(module_category::table)
+    fn create(new: &MutCategory, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        #[cfg(not(feature = "postgres"))]
+        let res = diesel::insert_or_ignore_into(module_category::table)
             .values(new)
-            .execute(db)?)
+            .execute(db)?;
+        #[cfg(feature = "postgres")]
+        let res = diesel::insert_into(module_category::table)
+            .values(new)
+            .on_conflict_do_nothing()
+            .execute(db)?;
+
+        Ok(res)
     }
 
-    fn read_one(_id: String, db: &MysqlConnection) -> Result<ModuleCategory, diesel::result::Error> {
+    fn read_one(_id: String, db: &DbConnection) -> Result<ModuleCategory, diesel::result::Error> {
         use module_category::dsl::uuid;
 
         let module = module_category::table.filter(uuid.eq(_id)).first::<ModuleCategory>(db)?;
@@ -89,14 +338,29 @@ impl Model<Self, MutCategory, String, ModuleCategory> for ModuleCategory {
         Ok(module)
     }
 
-    fn read_all(_db: &MysqlConnection) -> Result<Vec<ModuleCategory>, diesel::result::Error> {
+    fn read_all(_db: &DbConnection) -> Result<Vec<ModuleCategory>, diesel::result::Error> {
         unimplemented!()
     }
 
+    /// The categories of the current site's pages, which they belong to in place of a site of their own.
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<ModuleCategory>, i64), diesel::result::Error> {
+        use module_category::dsl::{page_uuid, uuid};
+
+        let on_site = || page_uuid.eq_any(pages::table.filter(page_models::on_site()).select(pages::uuid));
+        let total = module_category::table.filter(on_site()).count().get_result(db)?;
+        let rows = module_category::table.filter(on_site()).order(uuid.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
     fn update(
         _id: String,
         new: &MutCategory,
-        db: &MysqlConnection,
+        db: &DbConnection,
     ) -> Result<usize, diesel::result::Error> {
         use module_category::dsl::uuid;
 
@@ -105,7 +369,7 @@ impl Model<Self, MutCategory, String, ModuleCategory> for ModuleCategory {
             .execute(db)?)
     }
 
-    fn delete(_id: String, db: &MysqlConnection) -> Result<usize, diesel::result::Error> {
+    fn delete(_id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
         use module_category::dsl::uuid;
 
         Ok(diesel::delete(module_category::table.filter(uuid.eq(_id))).execute(db)?)
@@ -115,43 +379,378 @@ impl Model<Self, MutCategory, String, ModuleCategory> for ModuleCategory {
 impl Model<Self, MutModule, String, Module> for Module {
     fn create(
         new_module: &MutModule,
-        db: &MysqlConnection,
+        db: &DbConnection,
     ) -> Result<usize, diesel::result::Error> {
+        let mut new_module = new_module.deflate(new_module.module_type.as_deref().unwrap_or(ModuleType::Text.as_str()))?;
+        if new_module.order_index.is_none() {
+            new_module.order_index = Some(Module::next_order_index(new_module.page_uuid.as_deref(), db)?);
+        }
+        if new_module.module_type.is_none() {
+            new_module.module_type = Some(text());
+        }
+        // a module is on the site of its page, wherever it is created from, and a global module on the current site.
+        let site = pages::table
+            .filter(pages::uuid.nullable().eq(&new_module.page_uuid))
+            .select(pages::site_id)
+            .first::<String>(db)
+            .optional()?
+            .unwrap_or_else(site_service::current_or_default);
+
         Ok(diesel::insert_into(modules::table)
-            .values(new_module)
+            .values((&new_module, modules::site_id.eq(site)))
             .execute(db)?)
     }
 
-    fn read_one(mod_id: String, db: &MysqlConnection) -> Result<Module, diesel::result::Error> {
-        use modules::dsl::uuid;
+    fn read_one(mod_id: String, db: &DbConnection) -> Result<Module, diesel::result::Error> {
+        use modules::dsl::{deleted_at, uuid};
 
-        let module = modules::table.filter(uuid.eq(mod_id)).first::<Self>(db)?;
+        let module = modules::table.filter(on_site()).filter(uuid.eq(mod_id)).filter(deleted_at.is_null()).first::<Self>(db)?;
 
-        Ok(module.into())
+        module.inflate()
     }
 
-    fn read_all(db: &MysqlConnection) -> Result<Vec<Module>, diesel::result::Error> {
-        use modules::dsl::category_uuid;
-        Ok(modules::table
+    fn read_all(db: &DbConnection) -> Result<Vec<Module>, diesel::result::Error> {
+        use modules::dsl::{category_uuid, deleted_at};
+        Module::inflate_all(modules::table
+            .filter(on_site())
             .filter(category_uuid.is_null())
-            .load::<Module>(db)?.into_iter().map(|m| { m.into() }).collect())
+            .filter(deleted_at.is_null())
+            .load::<Module>(db)?)
     }
 
-    fn delete(mod_id: String, db: &MysqlConnection) -> Result<usize, diesel::result::Error> {
-        use modules::dsl::uuid;
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Module>, i64), diesel::result::Error> {
+        use modules::dsl::{category_uuid, deleted_at, uuid};
 
-        Ok(diesel::delete(modules::table.filter(uuid.eq(mod_id))).execute(db)?)
+        let total = modules::table.filter(on_site()).filter(category_uuid.is_null()).filter(deleted_at.is_null()).count().get_result(db)?;
+        let rows = modules::table
+            .filter(on_site())
+            .filter(category_uuid.is_null())
+            .filter(deleted_at.is_null())
+            .order(uuid.asc())
+            .offset(offset)
+            .limit(limit)
+            .load::<Module>(db)?;
+
+        Ok((Module::inflate_all(rows)?, total))
+    }
+
+    /// Moves the module to the trash.
+    fn delete(mod_id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use modules::dsl::{deleted_at, uuid};
+
+        diesel::update(modules::table.filter(on_site()).filter(uuid.eq(mod_id)).filter(deleted_at.is_null()))
+            .set(deleted_at.eq(Utc::now().naive_utc()))
+            .execute(db)
     }
 
+    /// Records a revision of the module's page as it was before the update.
     fn update(
         mod_id: String,
         new_module: &MutModule,
-        db: &MysqlConnection,
+        db: &DbConnection,
     ) -> Result<usize, diesel::result::Error> {
+        use modules::dsl::{page_uuid, uuid};
+
+        db.transaction(|| {
+            let page = modules::table.filter(on_site()).filter(uuid.eq(&mod_id)).select(page_uuid).first::<Option<String>>(db).optional()?;
+            if let Some(page) = page.flatten() {
+                PageRevision::record(&page, db)?;
+            }
+
+            Module::overwrite(mod_id, new_module, db)
+        })
+    }
+}
+
+impl Module {
+    /// Updates the module without recording a revision, like when one is being restored.
+    pub fn overwrite(mod_id: String, new_module: &MutModule, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use modules::dsl::{uuid, version};
+
+        let stored = new_module.deflate(&Module::saved_type(&mod_id, new_module, db)?)?;
+        diesel::update(modules::table.filter(on_site()).filter(uuid.eq(mod_id)))
+            .set((&stored, version.eq(version + 1)))
+            .execute(db)
+    }
+
+    /// `update`, but only if the module is still at `at`, the version it was read at. Nothing is updated when it was changed
+    /// since, and 0 is returned.
+    pub fn update_at(mod_id: String, new_module: &MutModule, at: i32, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use modules::dsl::{page_uuid, uuid, version};
+
+        let res = db.transaction(|| {
+            let page = modules::table.filter(on_site()).filter(uuid.eq(&mod_id)).select(page_uuid).first::<Option<String>>(db).optional()?;
+            if let Some(page) = page.flatten() {
+                PageRevision::record(&page, db)?;
+            }
+
+            let stored = new_module.deflate(&Module::saved_type(&mod_id, new_module, db)?)?;
+            match diesel::update(modules::table.filter(on_site()).filter(uuid.eq(mod_id)).filter(version.eq(at)))
+                .set((&stored, version.eq(version + 1)))
+                .execute(db)?
+            {
+                // so that the revision isn't kept either.
+                0 => Err(diesel::result::Error::RollbackTransaction),
+                updated => Ok(updated),
+            }
+        });
+
+        match res {
+            Err(diesel::result::Error::RollbackTransaction) => Ok(0),
+            res => res,
+        }
+    }
+
+    /// The `order_index` a module added to the end of the page gets, or to the end of the global modules without one.
+    pub fn next_order_index(page_id: Option<&str>, db: &DbConnection) -> Result<i32, diesel::result::Error> {
+        use modules::dsl::{order_index, page_uuid};
+
+        let query = modules::table.filter(on_site()).select(diesel::dsl::max(order_index)).into_boxed();
+        let last: Option<i32> = match page_id {
+            Some(page_id) => query.filter(page_uuid.eq(page_id)).first(db)?,
+            None => query.filter(page_uuid.is_null()).first(db)?,
+        };
+
+        Ok(last.map(|i| i + 1).unwrap_or(0))
+    }
+
+    /// Puts the page's modules in the order of `ids`, recording a revision of the page as it was before.
+    pub fn reorder(page_id: String, ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use modules::dsl::{order_index, page_uuid, uuid, version};
+
+        db.transaction(|| {
+            PageRevision::record(&page_id, db)?;
+
+            for (index, module) in ids.iter().enumerate() {
+                diesel::update(modules::table.filter(on_site()).filter(uuid.eq(module)).filter(page_uuid.eq(&page_id)))
+                    .set((order_index.eq(index as i32), version.eq(version + 1)))
+                    .execute(db)?;
+            }
+
+            Ok(ids.len())
+        })
+    }
+
+    /// Reads the module whether it is in the trash or not.
+    pub fn read_any(mod_id: String, db: &DbConnection) -> Result<Module, diesel::result::Error> {
         use modules::dsl::uuid;
 
-        Ok(diesel::update(modules::table.filter(uuid.eq(mod_id)))
-            .set(new_module)
-            .execute(db)?)
+        modules::table.filter(on_site()).filter(uuid.eq(mod_id)).first::<Self>(db)?.inflate()
+    }
+
+    /// Removes the module for good, along with its gallery items.
+    pub fn purge(mod_id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use modules::dsl::uuid;
+
+        diesel::delete(modules::table.filter(on_site()).filter(uuid.eq(mod_id))).execute(db)
+    }
+
+    /// Takes modules out of the trash. The ones whose page is in the trash stay there, as they come back with the page.
+    pub fn restore(ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use modules::dsl::{deleted_at, page_uuid, uuid};
+
+        let kept_pages = pages::table.filter(pages::deleted_at.is_null()).select(pages::uuid.nullable());
+
+        diesel::update(
+            modules::table
+                .filter(on_site())
+                .filter(uuid.eq_any(ids))
+                .filter(deleted_at.is_not_null())
+                .filter(page_uuid.is_null().or(page_uuid.eq_any(kept_pages))),
+        )
+        .set(deleted_at.eq(None::<NaiveDateTime>))
+        .execute(db)
+    }
+
+    /// Modules that were trashed on their own, the most recently trashed first.
+    /// The ones that went in with their page are only restored with it, so they are listed under the page instead.
+    pub fn read_trashed(db: &DbConnection) -> Result<Vec<Module>, diesel::result::Error> {
+        use modules::dsl::{deleted_at, page_uuid};
+
+        let kept_pages = pages::table.filter(pages::deleted_at.is_null()).select(pages::uuid.nullable());
+
+        Module::inflate_all(modules::table
+            .filter(on_site())
+            .filter(deleted_at.is_not_null())
+            .filter(page_uuid.is_null().or(page_uuid.eq_any(kept_pages)))
+            .order(deleted_at.desc())
+            .load::<Module>(db)?)
+    }
+
+    pub fn count(db: &DbConnection) -> Result<i64, diesel::result::Error> {
+        use modules::dsl::deleted_at;
+
+        modules::table.filter(on_site()).filter(deleted_at.is_null()).count().get_result(db)
+    }
+
+    /// Module titles are what templates look modules up by, so they identify a module within its page.
+    pub fn read_one_by_title(
+        page_id: String,
+        module_title: String,
+        db: &DbConnection,
+    ) -> Result<Module, diesel::result::Error> {
+        use modules::dsl::{deleted_at, page_uuid, title};
+
+        modules::table
+            .filter(on_site())
+            .filter(page_uuid.eq(page_id))
+            .filter(title.eq(module_title))
+            .filter(deleted_at.is_null())
+            .first::<Self>(db)?
+            .inflate()
     }
-}
\ No newline at end of file
+
+    /// The content read as the module's type. Encrypted content has to be revealed first, as it is never valid otherwise.
+    pub fn value(&self) -> Option<ModuleValue> {
+        ModuleValue::parse(&self.module_type, &self.content)
+    }
+
+    /// The type an update saves the module as, which is the one it has unless the update changes it.
+    fn saved_type(mod_id: &str, new_module: &MutModule, db: &DbConnection) -> Result<String, diesel::result::Error> {
+        use modules::dsl::{module_type, uuid};
+
+        match &new_module.module_type {
+            Some(saved) => Ok(saved.clone()),
+            None => Ok(modules::table
+                .filter(on_site())
+                .filter(uuid.eq(mod_id))
+                .select(module_type)
+                .first::<String>(db)
+                .optional()?
+                .unwrap_or_else(text)),
+        }
+    }
+
+    /// Decompresses the content if it was stored compressed. Everything that loads modules should pass them through here.
+    pub fn inflate(mut self) -> Result<Self, diesel::result::Error> {
+        if self.compressed {
+            self.content = compression_service::decompress(&self.content)
+                .map_err(diesel::result::Error::DeserializationError)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Modules whose page no longer exists, or that point at a category that no longer exists. Global modules never had a page.
+    pub fn read_orphaned(db: &DbConnection) -> Result<Vec<Module>, diesel::result::Error> {
+        let mut orphaned = modules::table
+            .left_join(pages::table)
+            .filter(on_site())
+            .filter(modules::page_uuid.is_not_null())
+            .filter(pages::uuid.nullable().is_null())
+            .select(modules::all_columns)
+            .load::<Module>(db)?;
+
+        orphaned.extend(
+            modules::table
+                .left_join(module_category::table)
+                .filter(on_site())
+                .filter(modules::category_uuid.is_not_null())
+                .filter(module_category::uuid.nullable().is_null())
+                .select(modules::all_columns)
+                .load::<Module>(db)?,
+        );
+
+        orphaned.sort_by(|a, b| a.uuid.cmp(&b.uuid));
+        orphaned.dedup_by(|a, b| a.uuid == b.uuid);
+
+        Ok(orphaned)
+    }
+
+    pub fn delete_many(ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use modules::dsl::uuid;
+
+        diesel::delete(modules::table.filter(on_site()).filter(uuid.eq_any(ids))).execute(db)
+    }
+
+    /// Every module of every site, trashed or not, which is what is checked for links before an upload is collected.
+    pub fn read_every(db: &DbConnection) -> Result<Vec<Module>, diesel::result::Error> {
+        Module::inflate_all(modules::table.load::<Module>(db)?)
+    }
+
+    /// Unlike `read_all`, this includes modules that are in a category.
+    pub fn read_all_including_categories(db: &DbConnection) -> Result<Vec<Module>, diesel::result::Error> {
+        use modules::dsl::deleted_at;
+
+        Module::inflate_all(modules::table.filter(on_site()).filter(deleted_at.is_null()).load::<Module>(db)?)
+    }
+
+    /// The modules that aren't on any page, like a footer or a banner, in their order.
+    pub fn read_global(db: &DbConnection) -> Result<Vec<Module>, diesel::result::Error> {
+        use modules::dsl::{deleted_at, order_index, page_uuid, uuid};
+
+        Module::inflate_all(modules::table
+            .filter(on_site())
+            .filter(page_uuid.is_null())
+            .filter(deleted_at.is_null())
+            .order((order_index.asc(), uuid.asc()))
+            .load::<Module>(db)?)
+    }
+
+    /// Every module of the given pages, including ones that are in a category.
+    pub fn read_for_pages(ids: Vec<String>, db: &DbConnection) -> Result<Vec<Module>, diesel::result::Error> {
+        use modules::dsl::{deleted_at, order_index, page_uuid, uuid};
+
+        Module::inflate_all(modules::table
+            .filter(on_site())
+            .filter(page_uuid.eq_any(ids))
+            .filter(deleted_at.is_null())
+            .order((page_uuid.asc(), order_index.asc(), uuid.asc()))
+            .load::<Module>(db)?)
+    }
+
+    pub fn inflate_all(modules: Vec<Self>) -> Result<Vec<Self>, diesel::result::Error> {
+        modules.into_iter().map(|m| m.inflate()).collect()
+    }
+
+    /// Compresses the content of every existing module at least `threshold` bytes long.
+    /// Used to bring rows written before compression was turned on in line.
+    pub fn compress_existing(threshold: usize, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use modules::dsl::{compressed, content, encrypted, uuid};
+
+        let uncompressed = modules::table
+            .filter(on_site())
+            .filter(compressed.eq(false))
+            .filter(encrypted.eq(false))
+            .load::<Module>(db)?;
+
+        let mut updated = 0;
+        for module in uncompressed.into_iter().filter(|m| m.content.len() >= threshold) {
+            let compressed_content = compression_service::compress(&module.content)
+                .map_err(diesel::result::Error::SerializationError)?;
+
+            updated += diesel::update(modules::table.filter(on_site()).filter(uuid.eq(module.uuid)))
+                .set((content.eq(compressed_content), compressed.eq(true)))
+                .execute(db)?;
+        }
+
+        Ok(updated)
+    }
+}
+
+impl MutModule {
+    /// Sanitizes the content as `module_type` (see `sanitize_service`), then compresses it if it is over the threshold.
+    /// Encrypted content is left alone, as it is sanitized before it is encrypted and won't compress.
+    fn deflate(&self, module_type: &str) -> Result<Self, diesel::result::Error> {
+        let mut stored = self.clone();
+        stored.compressed = false;
+        if !stored.encrypted {
+            stored.content = sanitize_service::on_save(module_type, &stored.content);
+        }
+
+        match compression_service::threshold() {
+            Some(threshold) if !stored.encrypted && stored.content.len() >= threshold => {
+                stored.content = compression_service::compress(&stored.content)
+                    .map_err(diesel::result::Error::SerializationError)?;
+                stored.compressed = true;
+            }
+            _ => {}
+        }
+
+        Ok(stored)
+    }
+}
@@ -1,16 +1,78 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
+use diesel::sql_types::Bool;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::collections::HashMap;
 
-use super::module_models::Module;
-use super::Model;
-use crate::models::module_models::CategoryDTO;
+use super::comment_models::PublicComment;
+use super::gallery_item_models::GalleryItem;
+use super::menu_models::NavItem;
+use super::module_models::{Module, MutModule};
+use super::page_revision_models::PageRevision;
+use super::site_models::default_site;
+use super::taxonomy_models::{PageCategory, Tag};
+use super::{DbConnection, Model};
 use crate::models::module_models::FieldsDTO;
 use crate::models::module_models::ModuleCategory;
-use crate::schema::module_category;
 use crate::schema::modules;
 use crate::schema::pages;
+use crate::services::site_service;
+
+/// Drafts are only seen through the management API, and published pages by everyone.
+pub const DRAFT: &str = "draft";
+pub const PUBLISHED: &str = "published";
+/// Who a page is seen by when nothing else is said.
+pub const PUBLIC: &str = "public";
+
+pub fn is_status(status: &str) -> bool {
+    status == DRAFT || status == PUBLISHED
+}
+
+/// Whether a page is public, which a draft is as soon as its `publish_at` has passed,
+/// even before the scheduler has gotten around to publishing it.
+pub fn is_live(status: &str, publish_at: Option<NaiveDateTime>) -> bool {
+    status == PUBLISHED || publish_at.is_some_and(|at| at <= Utc::now().naive_utc())
+}
+
+type Live = diesel::dsl::Or<diesel::dsl::Eq<pages::status, &'static str>, diesel::dsl::LtEq<pages::publish_at, NaiveDateTime>>;
+
+/// `is_live` as a filter.
+fn live() -> Live {
+    pages::status.eq(PUBLISHED).or(pages::publish_at.le(Utc::now().naive_utc()))
+}
+
+type Unrestricted = diesel::dsl::Eq<pages::visibility, &'static str>;
+
+/// Pages everyone can see, which are the only ones listed to the public, published to static targets, or searchable.
+fn unrestricted() -> Unrestricted {
+    pages::visibility.eq(PUBLIC)
+}
+
+type InLocales = diesel::dsl::Or<diesel::dsl::EqAny<pages::locale, Vec<String>>, diesel::dsl::AsExprOf<bool, Bool>>;
+
+/// Pages in one of `locales`, or every page when it is `None`. Pages without a locale are only in `None`.
+fn in_locales(locales: &Option<Vec<String>>) -> InLocales {
+    pages::locale.eq_any(locales.clone().unwrap_or_default()).or(locales.is_none().into_sql::<Bool>())
+}
+
+pub(crate) type OnSite = diesel::dsl::Or<diesel::dsl::Eq<pages::site_id, String>, diesel::dsl::AsExprOf<bool, Bool>>;
+
+/// Scopes a query to the current site's pages, or to every site's outside of a request (see `site_service::current`).
+pub(crate) fn on_site() -> OnSite {
+    let site = site_service::current();
+
+    pages::site_id.eq(site.clone().unwrap_or_default()).or(site.is_none().into_sql::<Bool>())
+}
+
+fn published() -> String {
+    String::from(PUBLISHED)
+}
+
+/// Pages written out before there were visibilities were all public.
+fn public() -> String {
+    String::from(PUBLIC)
+}
 
 #[derive(Identifiable, Debug, Serialize, Deserialize, Queryable, PartialEq, Clone)]
 #[primary_key(uuid)]
@@ -22,15 +84,197 @@ pub struct Page {
     pub page_url: String,
     pub page_title: String,
     pub time_created: NaiveDateTime,
+    /// Pages written out before there were drafts were all live.
+    #[serde(default = "published")]
+    pub status: String,
+    #[serde(default)]
+    pub publish_at: Option<NaiveDateTime>,
+    /// When the page was moved to the trash. Pages in the trash are left out of every read but the trash's own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<NaiveDateTime>,
+    /// The page this one is nested under. A child's `page_url` always starts with its parent's.
+    #[serde(default)]
+    pub parent_page: Option<String>,
+    /// What search engines and link previews show of the page, given to templates as they are.
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub meta_keywords: Option<String>,
+    /// The image link previews show, like `/api/v1/media/<uuid>`.
+    #[serde(default)]
+    pub og_image: Option<String>,
+    /// Asks search engines not to index the page, which also leaves it out of the sitemap.
+    #[serde(default)]
+    pub noindex: bool,
+    /// The language the page is in, like `fr` or `pt-BR`. Pages without one are in `APP_DEFAULT_LOCALE`.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Pages with the same translation group are translations of each other, one for each locale.
+    #[serde(default)]
+    pub translation_group: Option<String>,
+    /// The site the page is on. Its modules are always on the same one.
+    #[serde(default = "default_site")]
+    pub site_id: String,
+    /// Counted up on every change, so that an update made from an older read of the page is refused (see `version_service`).
+    #[serde(default)]
+    pub version: i32,
+    /// Who can see the page, like `authenticated` (see `visibility_service::Visibility`).
+    #[serde(default = "public")]
+    pub visibility: String,
 }
 
-#[derive(Insertable, AsChangeset, Deserialize, Serialize, Clone)]
+#[derive(Insertable, AsChangeset, Deserialize, Serialize, Clone, ToSchema)]
 #[table_name = "pages"]
 pub struct MutPage {
     pub uuid: Option<String>,
     pub page_name: String,
+    /// Left out of a new page, it is made from the title, so `About us` is at `/about-us`, or `/about-us-2` if that is taken,
+    /// and at `about-us` under its parent. Updates leave it alone when it isn't set.
+    #[serde(default)]
     pub page_url: String,
     pub page_title: String,
+    /// New pages are drafts unless this says otherwise, and updates leave it alone when it isn't set.
+    #[serde(default)]
+    pub status: Option<String>,
+    /// The page to nest this one under, which makes a `page_url` without a leading `/` relative to the parent's.
+    /// Updates leave the parent alone when this isn't set.
+    #[serde(default)]
+    pub parent_page: Option<String>,
+    /// Updates leave these alone when they aren't set, and an empty string clears them.
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub meta_keywords: Option<String>,
+    #[serde(default)]
+    pub og_image: Option<String>,
+    /// New pages are indexed unless this says otherwise, and updates leave it alone when it isn't set.
+    #[serde(default)]
+    pub noindex: Option<bool>,
+    /// Updates leave these alone when they aren't set, and an empty string clears them.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Any key the page's translations share, like the uuid of the page they were translated from.
+    #[serde(default)]
+    pub translation_group: Option<String>,
+    /// `public`, `authenticated`, or `role:<name>`. New pages are public unless this says otherwise, and updates leave it
+    /// alone when it isn't set.
+    #[serde(default)]
+    pub visibility: Option<String>,
+}
+
+/// A new page and the modules to make on it, which are all saved or none are.
+#[derive(Deserialize, Clone, ToSchema)]
+pub struct NewPageWithModules {
+    pub page: MutPage,
+    /// Made in this order, on the new page, so their `page_uuid` is left out.
+    #[serde(default)]
+    pub modules: Vec<MutModule>,
+}
+
+/// The body of a partial update, like `{ "page_title": "About us" }`. Anything that isn't set is left as it is.
+#[derive(Deserialize, Serialize, Clone, Default, ToSchema)]
+pub struct PatchPage {
+    #[serde(default)]
+    pub page_name: Option<String>,
+    #[serde(default)]
+    pub page_url: Option<String>,
+    #[serde(default)]
+    pub page_title: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub parent_page: Option<String>,
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub meta_keywords: Option<String>,
+    #[serde(default)]
+    pub og_image: Option<String>,
+    #[serde(default)]
+    pub noindex: Option<bool>,
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub translation_group: Option<String>,
+    #[serde(default)]
+    pub visibility: Option<String>,
+}
+
+impl PatchPage {
+    /// The update this makes to `page`. A page moved under another parent without a new `page_url`
+    /// keeps the last segment of its URL, which ends up under the parent's.
+    pub fn apply(self, page: &PageDTO) -> MutPage {
+        let page_url = match (self.page_url, &self.parent_page) {
+            (Some(page_url), _) => page_url,
+            (None, Some(_)) => page.page_url.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string(),
+            (None, None) => page.page_url.clone(),
+        };
+
+        MutPage {
+            uuid: None,
+            page_name: self.page_name.unwrap_or_else(|| page.page_name.clone()),
+            page_url,
+            page_title: self.page_title.unwrap_or_else(|| page.page_title.clone()),
+            status: self.status,
+            parent_page: self.parent_page,
+            meta_description: self.meta_description,
+            meta_keywords: self.meta_keywords,
+            og_image: self.og_image,
+            noindex: self.noindex,
+            locale: self.locale,
+            translation_group: self.translation_group,
+            visibility: self.visibility,
+        }
+    }
+}
+
+/// The body of a move, like `{ "parent_page": "<uuid>" }`, or `{ "parent_page": null }` to make the page top level again.
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct MutPageParent {
+    pub parent_page: Option<String>,
+}
+
+/// A page and everything nested under it.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct PageTree {
+    #[serde(flatten)]
+    pub page: PageDTO,
+    pub children: Vec<PageTree>,
+}
+
+impl PageTree {
+    /// Every page under this one, however deep, not including this one.
+    pub fn descendants(&self) -> Vec<&PageDTO> {
+        self.children
+            .iter()
+            .flat_map(|c| std::iter::once(&c.page).chain(c.descendants()))
+            .collect()
+    }
+
+    /// Leaves out the pages under this one that `keep` says no to, along with everything under them.
+    pub fn retain(&mut self, keep: &impl Fn(&PageDTO) -> bool) {
+        self.children.retain(|c| keep(&c.page));
+        for child in &mut self.children {
+            child.retain(keep);
+        }
+    }
+}
+
+/// The body of a status change, like `{ "status": "published" }`,
+/// or `{ "status": "draft", "publish_at": "2026-11-01T09:00:00" }` to schedule a draft.
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct MutPageStatus {
+    pub status: String,
+    #[serde(default)]
+    pub publish_at: Option<NaiveDateTime>,
+}
+
+/// Another language a page is in, as templates get it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Translation {
+    /// `x-default` for the translation without a locale, which picks one for visitors by their `Accept-Language`.
+    pub locale: String,
+    pub page_url: String,
 }
 
 /// Used in the displaying of pages.
@@ -41,10 +285,32 @@ pub struct PageModuleDisplayDTO {
     pub page_url: String,
     pub page_title: String,
     pub time_created: NaiveDateTime,
+    /// For the page's `<head>`, like `<meta name="description" content="{{meta_description}}">`.
+    pub meta_description: Option<String>,
+    pub meta_keywords: Option<String>,
+    pub og_image: Option<String>,
+    pub noindex: bool,
+    /// For `<html lang="{{locale}}">`, when the page has one.
+    pub locale: Option<String>,
+    /// The page's published translations, for `<link rel="alternate" hreflang="..">`.
+    pub translations: Vec<Translation>,
     /// the key of the hashmap is the `title` of the module, and the rest is the module.
     /// For the usefulness of this, see the `get` function on the default helpers.
     pub fields: HashMap<String, Module>,
     pub array_fields: HashMap<String, Vec<Module>>,
+    /// Gallery items keyed by the `title` of their module, like `fields`.
+    pub galleries: HashMap<String, Vec<GalleryItem>>,
+    /// The global modules by `title`, which every page has, like `{{globals.footer.content}}`.
+    pub globals: HashMap<String, Module>,
+    /// Every menu by name, like `{{#each menus.main}}`.
+    pub menus: HashMap<String, Vec<NavItem>>,
+    /// The page's own tags and categories, by slug.
+    pub tags: Vec<Tag>,
+    pub categories: Vec<PageCategory>,
+    /// What the request's path matched of a pattern `page_url`, like `{{params.slug}}` for `/blog/:slug`.
+    pub params: HashMap<String, String>,
+    /// The page's approved comments, oldest first, like `{{#each comments}}{{author_name}}: {{body}}{{/each}}`.
+    pub comments: Vec<PublicComment>,
 }
 
 impl From<Page> for PageModuleDisplayDTO {
@@ -55,20 +321,56 @@ impl From<Page> for PageModuleDisplayDTO {
             page_url: origin_page.page_url.to_string(),
             page_title: origin_page.page_title.to_string(),
             time_created: origin_page.time_created,
+            meta_description: origin_page.meta_description,
+            meta_keywords: origin_page.meta_keywords,
+            og_image: origin_page.og_image,
+            noindex: origin_page.noindex,
+            locale: origin_page.locale.filter(|l| !l.is_empty()),
+            translations: Vec::new(),
             fields: HashMap::new(),
             array_fields: HashMap::new(),
+            galleries: HashMap::new(),
+            globals: HashMap::new(),
+            menus: HashMap::new(),
+            tags: Vec::new(),
+            categories: Vec::new(),
+            params: HashMap::new(),
+            comments: Vec::new(),
         }
     }
 }
 
 /// Used in the JSON response of pages.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct PageModuleDTO {
     pub uuid: String,
     pub page_name: String,
     pub page_url: String,
     pub page_title: String,
     pub time_created: NaiveDateTime,
+    /// Pages written out before there were drafts were all live.
+    #[serde(default = "published")]
+    pub status: String,
+    #[serde(default)]
+    pub publish_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub parent_page: Option<String>,
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub meta_keywords: Option<String>,
+    #[serde(default)]
+    pub og_image: Option<String>,
+    #[serde(default)]
+    pub noindex: bool,
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub translation_group: Option<String>,
+    #[serde(default)]
+    pub version: i32,
+    #[serde(default = "public")]
+    pub visibility: String,
     pub fields: FieldsDTO
 }
 
@@ -80,18 +382,56 @@ impl From<Page> for PageModuleDTO {
             page_url: origin_page.page_url.to_string(),
             page_title: origin_page.page_title.to_string(),
             time_created: origin_page.time_created,
+            status: origin_page.status,
+            publish_at: origin_page.publish_at,
+            parent_page: origin_page.parent_page,
+            meta_description: origin_page.meta_description,
+            meta_keywords: origin_page.meta_keywords,
+            og_image: origin_page.og_image,
+            noindex: origin_page.noindex,
+            locale: origin_page.locale,
+            translation_group: origin_page.translation_group,
+            version: origin_page.version,
+            visibility: origin_page.visibility,
             fields: FieldsDTO::default(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct PageDTO {
     pub uuid: String,
     pub page_name: String,
     pub page_url: String,
     pub page_title: String,
     pub time_created: NaiveDateTime,
+    /// Pages written out before there were drafts were all live.
+    #[serde(default = "published")]
+    pub status: String,
+    #[serde(default)]
+    pub publish_at: Option<NaiveDateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub parent_page: Option<String>,
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub meta_keywords: Option<String>,
+    #[serde(default)]
+    pub og_image: Option<String>,
+    #[serde(default)]
+    pub noindex: bool,
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub translation_group: Option<String>,
+    /// What an update sends back as `If-Match` or `version`.
+    #[serde(default)]
+    pub version: i32,
+    /// Who can see the page: `public`, `authenticated`, or `role:<name>`.
+    #[serde(default = "public")]
+    pub visibility: String,
 }
 
 impl From<Page> for PageDTO {
@@ -102,84 +442,611 @@ impl From<Page> for PageDTO {
             page_url: origin_page.page_url.to_string(),
             page_title: origin_page.page_title.to_string(), 
             time_created: origin_page.time_created,
+            status: origin_page.status,
+            publish_at: origin_page.publish_at,
+            deleted_at: origin_page.deleted_at,
+            parent_page: origin_page.parent_page,
+            meta_description: origin_page.meta_description,
+            meta_keywords: origin_page.meta_keywords,
+            og_image: origin_page.og_image,
+            noindex: origin_page.noindex,
+            locale: origin_page.locale,
+            translation_group: origin_page.translation_group,
+            version: origin_page.version,
+            visibility: origin_page.visibility,
         }
     }
 }
 
 impl Model<Page, MutPage, String, PageDTO> for Page {
-    fn create(new_page: &MutPage, db: &MysqlConnection) -> Result<usize, diesel::result::Error> {
-        Ok(diesel::insert_or_ignore_into(pages::table)
-            .values(new_page)
-            .execute(db)?)
+    fn create(new_page: &MutPage, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        let new_page = &MutPage {
+            status: Some(new_page.status.clone().unwrap_or_else(|| String::from(DRAFT))),
+            noindex: Some(new_page.noindex.unwrap_or(false)),
+            visibility: Some(new_page.visibility.clone().unwrap_or_else(public)),
+            ..new_page.clone()
+        };
+
+        diesel::insert_into(pages::table)
+            .values((new_page, pages::site_id.eq(site_service::current_or_default())))
+            .execute(db)
     }
 
-    fn read_one(_id: String, db: &MysqlConnection) -> Result<PageDTO, diesel::result::Error> {
-        use pages::dsl::uuid;
+    fn read_one(_id: String, db: &DbConnection) -> Result<PageDTO, diesel::result::Error> {
+        use pages::dsl::{deleted_at, uuid};
 
-        let res = pages::table.filter(uuid.eq(_id)).first::<Self>(db)?.into();
+        let res = pages::table.filter(on_site()).filter(uuid.eq(_id)).filter(deleted_at.is_null()).first::<Self>(db)?.into();
 
         Ok(res)
     }
 
-    fn read_all(db: &MysqlConnection) -> Result<Vec<PageDTO>, diesel::result::Error> {
-        let res = pages::table.load::<Self>(db)?.into_iter().map(|x| x.into()).collect();
+    fn read_all(db: &DbConnection) -> Result<Vec<PageDTO>, diesel::result::Error> {
+        use pages::dsl::deleted_at;
+
+        let res = pages::table.filter(on_site()).filter(deleted_at.is_null()).load::<Self>(db)?.into_iter().map(|x| x.into()).collect();
 
         Ok(res)
     }
 
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<PageDTO>, i64), diesel::result::Error> {
+        let rows = Page::read_batch(offset, limit, db)?.into_iter().map(|x| x.into()).collect();
+
+        Ok((rows, Page::count(db)?))
+    }
+
+    /// Records a revision of the page as it was before the update.
     fn update(
         _id: String,
         new_page: &MutPage,
-        db: &MysqlConnection,
+        db: &DbConnection,
     ) -> Result<usize, diesel::result::Error> {
-        use pages::dsl::uuid;
+        db.transaction(|| {
+            PageRevision::record(&_id, db)?;
 
-        Ok(diesel::update(pages::table.filter(uuid.eq(_id)))
-            .set(new_page)
-            .execute(db)?)
+            Page::overwrite(_id, new_page, db)
+        })
     }
 
-    fn delete(_id: String, db: &MysqlConnection) -> Result<usize, diesel::result::Error> {
-        use pages::dsl::uuid;
+    /// Moves the page to the trash, along with every module of it that isn't in there already.
+    /// Those modules are given the page's `deleted_at`, which is how `restore` tells them apart from ones that were trashed on their own.
+    fn delete(_id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use pages::dsl::{deleted_at, uuid};
 
-        Ok(diesel::delete(pages::table.filter(uuid.eq(_id))).execute(db)?)
+        let now = Utc::now().naive_utc();
+
+        db.transaction(|| {
+            let res = diesel::update(pages::table.filter(on_site()).filter(uuid.eq(&_id)).filter(deleted_at.is_null()))
+                .set(deleted_at.eq(now))
+                .execute(db)?;
+
+            if res > 0 {
+                diesel::update(modules::table.filter(modules::page_uuid.eq(&_id)).filter(modules::deleted_at.is_null()))
+                    .set(modules::deleted_at.eq(now))
+                    .execute(db)?;
+            }
+
+            Ok(res)
+        })
     }
 }
 
 impl Page {
+    /// Updates the page without recording a revision, like when one is being restored.
+    pub fn overwrite(id: String, new_page: &MutPage, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use pages::dsl::{uuid, version};
+
+        diesel::update(pages::table.filter(on_site()).filter(uuid.eq(id)))
+            .set((new_page, version.eq(version + 1)))
+            .execute(db)
+    }
+
+    /// `update`, but only if the page is still at `at`, the version it was read at. Nothing is updated when it was changed
+    /// since, and 0 is returned, so that the change can be refused instead of silently undoing the other one.
+    pub fn update_at(id: String, new_page: &MutPage, at: i32, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use pages::dsl::{uuid, version};
+
+        let res = db.transaction(|| {
+            PageRevision::record(&id, db)?;
+
+            match diesel::update(pages::table.filter(on_site()).filter(uuid.eq(id)).filter(version.eq(at)))
+                .set((new_page, version.eq(version + 1)))
+                .execute(db)?
+            {
+                // so that the revision isn't kept either.
+                0 => Err(diesel::result::Error::RollbackTransaction),
+                updated => Ok(updated),
+            }
+        });
+
+        match res {
+            Err(diesel::result::Error::RollbackTransaction) => Ok(0),
+            res => res,
+        }
+    }
+
+    /// Reads the page whether it is in the trash or not.
+    pub fn read_any(id: String, db: &DbConnection) -> Result<Page, diesel::result::Error> {
+        use pages::dsl::uuid;
+
+        pages::table.filter(on_site()).filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    /// Removes the page for good, along with everything of it that cascades. Pages nested under it become top level.
+    pub fn purge(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use pages::dsl::{parent_page, uuid};
+
+        db.transaction(|| {
+            // SQLite has no foreign key on the parent to do this.
+            diesel::update(pages::table.filter(on_site()).filter(parent_page.eq(&id)))
+                .set(parent_page.eq(None::<String>))
+                .execute(db)?;
+
+            diesel::delete(pages::table.filter(on_site()).filter(uuid.eq(&id))).execute(db)
+        })
+    }
+
+    /// Moves the page under another one, or to the top level with `None`.
+    pub fn set_parent(id: String, new_parent: Option<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use pages::dsl::{parent_page, uuid, version};
+
+        diesel::update(pages::table.filter(on_site()).filter(uuid.eq(id)))
+            .set((parent_page.eq(new_parent), version.eq(version + 1)))
+            .execute(db)
+    }
+
+    /// Sets the URL of a page on its own, like when its parent has moved.
+    pub fn set_url(id: String, new_url: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use pages::dsl::{page_url, uuid, version};
+
+        diesel::update(pages::table.filter(on_site()).filter(uuid.eq(id)))
+            .set((page_url.eq(new_url), version.eq(version + 1)))
+            .execute(db)
+    }
+
+    /// The page and every page nested under it, however deep, with each page's children ordered by URL.
+    /// Pages in the trash are left out, along with everything under them.
+    pub fn read_tree(id: String, db: &DbConnection) -> Result<PageTree, diesel::result::Error> {
+        use pages::dsl::{deleted_at, page_url, parent_page};
+
+        let root: PageDTO = Page::read_one(id, db)?;
+
+        let mut children: HashMap<String, Vec<Page>> = HashMap::new();
+        for page in pages::table
+            .filter(on_site())
+            .filter(parent_page.is_not_null())
+            .filter(deleted_at.is_null())
+            .order(page_url.asc())
+            .load::<Self>(db)?
+        {
+            if let Some(parent) = page.parent_page.clone() {
+                children.entry(parent).or_default().push(page);
+            }
+        }
+
+        Ok(Page::grow(root, &mut children))
+    }
+
+    /// Each page's children are taken out of `children` as they are used, so a cycle can't make this recurse forever.
+    fn grow(page: PageDTO, children: &mut HashMap<String, Vec<Page>>) -> PageTree {
+        let nested = children.remove(&page.uuid).unwrap_or_default();
+
+        PageTree {
+            children: nested.into_iter().map(|c| Page::grow(c.into(), children)).collect(),
+            page,
+        }
+    }
+
+    /// Takes the page out of the trash, along with the modules that went in with it.
+    /// Returns 0 when the page isn't in the trash.
+    pub fn restore(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use pages::dsl::{deleted_at, uuid};
+
+        db.transaction(|| {
+            let trashed = pages::table
+                .filter(on_site())
+                .filter(uuid.eq(&id))
+                .select(deleted_at)
+                .first::<Option<NaiveDateTime>>(db)
+                .optional()?
+                .flatten();
+            let trashed = match trashed {
+                Some(at) => at,
+                None => return Ok(0),
+            };
+
+            diesel::update(modules::table.filter(modules::page_uuid.eq(&id)).filter(modules::deleted_at.eq(trashed)))
+                .set(modules::deleted_at.eq(None::<NaiveDateTime>))
+                .execute(db)?;
+
+            diesel::update(pages::table.filter(on_site()).filter(uuid.eq(&id)))
+                .set(deleted_at.eq(None::<NaiveDateTime>))
+                .execute(db)
+        })
+    }
+
+    /// Pages in the trash, the most recently trashed first.
+    pub fn read_trashed(db: &DbConnection) -> Result<Vec<PageDTO>, diesel::result::Error> {
+        use pages::dsl::deleted_at;
+
+        let res = pages::table
+            .filter(on_site())
+            .filter(deleted_at.is_not_null())
+            .order(deleted_at.desc())
+            .load::<Self>(db)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        Ok(res)
+    }
+
+    pub fn count(db: &DbConnection) -> Result<i64, diesel::result::Error> {
+        use pages::dsl::deleted_at;
+
+        pages::table.filter(on_site()).filter(deleted_at.is_null()).count().get_result(db)
+    }
+
+    /// Pages ordered by uuid, so that going through every page `limit` at a time neither skips nor repeats any.
+    pub fn read_batch(offset: i64, limit: i64, db: &DbConnection) -> Result<Vec<Page>, diesel::result::Error> {
+        use pages::dsl::{deleted_at, uuid};
+
+        pages::table.filter(on_site()).filter(deleted_at.is_null()).order(uuid.asc()).offset(offset).limit(limit).load::<Self>(db)
+    }
+
+    pub fn read_many(ids: Vec<String>, db: &DbConnection) -> Result<Vec<Page>, diesel::result::Error> {
+        use pages::dsl::{deleted_at, uuid};
+
+        pages::table.filter(on_site()).filter(uuid.eq_any(ids)).filter(deleted_at.is_null()).load::<Self>(db)
+    }
+
+    /// The pages with one of `visibilities`, like the ones a user who isn't an admin can see.
+    pub fn read_visible(visibilities: &[String], db: &DbConnection) -> Result<Vec<PageDTO>, diesel::result::Error> {
+        use pages::dsl::{deleted_at, visibility};
+
+        let res = pages::table
+            .filter(on_site())
+            .filter(visibility.eq_any(visibilities))
+            .filter(deleted_at.is_null())
+            .load::<Self>(db)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        Ok(res)
+    }
+
+    /// The same as `read_visible`, one page of them at a time.
+    pub fn read_visible_paginated(
+        visibilities: &[String],
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<PageDTO>, i64), diesel::result::Error> {
+        use pages::dsl::{deleted_at, uuid, visibility};
+
+        let total = pages::table
+            .filter(on_site())
+            .filter(visibility.eq_any(visibilities))
+            .filter(deleted_at.is_null())
+            .count()
+            .get_result(db)?;
+        let rows = pages::table
+            .filter(on_site())
+            .filter(visibility.eq_any(visibilities))
+            .filter(deleted_at.is_null())
+            .order(uuid.asc())
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        Ok((rows, total))
+    }
+
+    pub fn read_locale(id: &str, db: &DbConnection) -> Result<Option<String>, diesel::result::Error> {
+        use pages::dsl::{locale, uuid};
+
+        pages::table.filter(on_site()).filter(uuid.eq(id)).select(locale).first::<Option<String>>(db)
+    }
+
+    /// The uuids of the pages without one of `visibilities`, which are hidden from whoever can only see those.
+    pub fn read_hidden(visibilities: &[String], db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+        use pages::dsl::{uuid, visibility};
+
+        pages::table.filter(on_site()).filter(visibility.ne_all(visibilities)).select(uuid).load::<String>(db)
+    }
+
+    pub fn read_visibility(id: &str, db: &DbConnection) -> Result<String, diesel::result::Error> {
+        use pages::dsl::{uuid, visibility};
+
+        pages::table.filter(on_site()).filter(uuid.eq(id)).select(visibility).first::<String>(db)
+    }
+
+    /// The URLs of published pages, which are the only ones served, to everyone or to who their visibility lets see them.
+    pub fn read_urls(db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+        use pages::dsl::{deleted_at, page_url};
+
+        pages::table.filter(on_site()).filter(live()).filter(deleted_at.is_null()).select(page_url).load::<String>(db)
+    }
+
+    pub fn read_published(db: &DbConnection) -> Result<Vec<PageDTO>, diesel::result::Error> {
+        use pages::dsl::deleted_at;
+
+        let res = pages::table
+            .filter(on_site())
+            .filter(live())
+            .filter(unrestricted())
+            .filter(deleted_at.is_null())
+            .load::<Self>(db)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        Ok(res)
+    }
+
+    /// The same as `read_paginated`, for published pages only.
+    pub fn read_published_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<PageDTO>, i64), diesel::result::Error> {
+        use pages::dsl::{deleted_at, uuid};
+
+        let total = pages::table
+            .filter(on_site())
+            .filter(live())
+            .filter(unrestricted())
+            .filter(deleted_at.is_null())
+            .count()
+            .get_result(db)?;
+        let rows = pages::table
+            .filter(on_site())
+            .filter(live())
+            .filter(unrestricted())
+            .filter(deleted_at.is_null())
+            .order(uuid.asc())
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        Ok((rows, total))
+    }
+
+    /// The same as `read_published`, for the pages in one of `locales` (see `in_locales`).
+    pub fn read_published_in(locales: &Option<Vec<String>>, db: &DbConnection) -> Result<Vec<PageDTO>, diesel::result::Error> {
+        use pages::dsl::deleted_at;
+
+        let res = pages::table
+            .filter(on_site())
+            .filter(live())
+            .filter(unrestricted())
+            .filter(in_locales(locales))
+            .filter(deleted_at.is_null())
+            .load::<Self>(db)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        Ok(res)
+    }
+
+    /// The same as `read_published_paginated`, for the pages in one of `locales`.
+    pub fn read_published_in_paginated(
+        locales: &Option<Vec<String>>,
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<PageDTO>, i64), diesel::result::Error> {
+        use pages::dsl::{deleted_at, uuid};
+
+        let total = pages::table
+            .filter(on_site())
+            .filter(live())
+            .filter(unrestricted())
+            .filter(in_locales(locales))
+            .filter(deleted_at.is_null())
+            .count()
+            .get_result(db)?;
+        let rows = pages::table
+            .filter(on_site())
+            .filter(live())
+            .filter(unrestricted())
+            .filter(in_locales(locales))
+            .filter(deleted_at.is_null())
+            .order(uuid.asc())
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        Ok((rows, total))
+    }
+
+    /// The published pages among `ids`, newest first, like the pages with a tag.
+    pub fn read_published_among(ids: Vec<String>, db: &DbConnection) -> Result<Vec<PageDTO>, diesel::result::Error> {
+        use pages::dsl::{deleted_at, time_created, uuid};
+
+        let res = pages::table
+            .filter(on_site())
+            .filter(uuid.eq_any(ids))
+            .filter(live())
+            .filter(unrestricted())
+            .filter(deleted_at.is_null())
+            .order((time_created.desc(), uuid.asc()))
+            .load::<Self>(db)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        Ok(res)
+    }
+
+    /// The same as `read_published_among`, one page of them at a time.
+    pub fn read_published_among_paginated(
+        ids: Vec<String>,
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<PageDTO>, i64), diesel::result::Error> {
+        use pages::dsl::{deleted_at, time_created, uuid};
+
+        let total = pages::table
+            .filter(on_site())
+            .filter(uuid.eq_any(&ids))
+            .filter(live())
+            .filter(unrestricted())
+            .filter(deleted_at.is_null())
+            .count()
+            .get_result(db)?;
+        let rows = pages::table
+            .filter(on_site())
+            .filter(uuid.eq_any(ids))
+            .filter(live())
+            .filter(unrestricted())
+            .filter(deleted_at.is_null())
+            .order((time_created.desc(), uuid.asc()))
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+
+        Ok((rows, total))
+    }
+
+    /// Whether the page exists, is published for everyone to see, and isn't in the trash.
+    pub fn is_published(id: &str, db: &DbConnection) -> Result<bool, diesel::result::Error> {
+        use pages::dsl::{deleted_at, uuid};
+
+        let count: i64 = pages::table
+            .filter(on_site())
+            .filter(uuid.eq(id))
+            .filter(live())
+            .filter(unrestricted())
+            .filter(deleted_at.is_null())
+            .count()
+            .get_result(db)?;
+
+        Ok(count > 0)
+    }
+
+    /// Dates an imported page to when it was first published where it came from.
+    pub fn set_time_created(id: String, created: NaiveDateTime, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use pages::dsl::{time_created, uuid};
+
+        diesel::update(pages::table.filter(on_site()).filter(uuid.eq(id)))
+            .set(time_created.eq(created))
+            .execute(db)
+    }
+
+    /// Sets the status, along with when a draft goes live. Published pages have no `publish_at`.
+    pub fn set_status(
+        id: String,
+        new_status: &str,
+        new_publish_at: Option<NaiveDateTime>,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use pages::dsl::{publish_at, status, uuid, version};
+
+        let new_publish_at = new_publish_at.filter(|_| new_status == DRAFT);
+
+        diesel::update(pages::table.filter(on_site()).filter(uuid.eq(id)))
+            .set((status.eq(new_status), publish_at.eq(new_publish_at), version.eq(version + 1)))
+            .execute(db)
+    }
+
+    /// Publishes every draft whose `publish_at` has passed, returning their uuids.
+    pub fn publish_due(db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+        use pages::dsl::{deleted_at, publish_at, status, uuid};
+
+        let due = pages::table
+            .filter(on_site())
+            .filter(status.eq(DRAFT))
+            .filter(deleted_at.is_null())
+            .filter(publish_at.le(Utc::now().naive_utc()))
+            .select(uuid)
+            .load::<String>(db)?;
+
+        for id in &due {
+            Page::set_status(id.clone(), PUBLISHED, None, db)?;
+        }
+
+        Ok(due)
+    }
+
+    /// Every page in the translation group that isn't in the trash, whether it is published or not.
+    pub fn read_translations(group: &str, db: &DbConnection) -> Result<Vec<Page>, diesel::result::Error> {
+        use pages::dsl::{deleted_at, locale, translation_group, uuid};
+
+        pages::table
+            .filter(on_site())
+            .filter(translation_group.eq(group))
+            .filter(deleted_at.is_null())
+            .order((locale.asc(), uuid.asc()))
+            .load::<Self>(db)
+    }
+
+    pub fn read_one_by_url(url: String, db: &DbConnection) -> Result<Page, diesel::result::Error> {
+        use pages::dsl::{deleted_at, page_url};
+
+        pages::table.filter(on_site()).filter(page_url.eq(url)).filter(deleted_at.is_null()).first::<Self>(db)
+    }
+
+    /// Whether a page other than `except` is at `url`, leaving out the ones in the trash.
+    pub fn url_taken(url: &str, except: Option<&str>, db: &DbConnection) -> Result<bool, diesel::result::Error> {
+        use pages::dsl::{deleted_at, page_url, uuid};
+
+        let count: i64 = pages::table
+            .filter(on_site())
+            .filter(page_url.eq(url))
+            .filter(deleted_at.is_null())
+            .filter(uuid.ne(except.unwrap_or_default()))
+            .count()
+            .get_result(db)?;
+
+        Ok(count > 0)
+    }
+
     pub fn read_one_join_on(
         _id: String,
-        db: &MysqlConnection,
+        db: &DbConnection,
     ) -> Result<PageModuleDTO, diesel::result::Error> {
-        use pages::dsl::uuid;
+        use pages::dsl::{deleted_at, uuid};
         use modules::dsl::category_uuid;
 
-        let filtered_page = pages::table.filter(uuid.eq(_id)).first::<Page>(db)?;
-
-        let modules_no_category = Module::belonging_to(&filtered_page).filter(category_uuid.is_null()).load::<Module>(db)?;
+        let filtered_page = pages::table.filter(on_site()).filter(uuid.eq(_id)).filter(deleted_at.is_null()).first::<Page>(db)?;
 
-        let categories =  ModuleCategory::belonging_to(&filtered_page).load::<ModuleCategory>(db)?;
+        let modules_no_category = Module::inflate_all(Module::belonging_to(&filtered_page)
+            .filter(category_uuid.is_null())
+            .filter(modules::deleted_at.is_null())
+            .order((modules::order_index.asc(), modules::uuid.asc()))
+            .load::<Module>(db)?)?;
 
-        let module_array: Vec<(Vec<Module>, ModuleCategory)> = Module::belonging_to(&categories)
-            .load::<Module>(db)?
-            .grouped_by(&categories)
-            .iter()
-            .map(|a| a.clone())
-            .zip(categories)
-            .collect::<Vec<_>>();
+        let category_dtos = ModuleCategory::read_for_page(&filtered_page, db)?;
 
-        let category_dtos: Vec<CategoryDTO> = module_array
+        let module_ids = modules_no_category
             .iter()
-            .map(|a| CategoryDTO {
-                title: a.1.title.clone(),
-                modules: a.0.clone(),
-                uuid: a.1.uuid.clone(),
-            })
-            .collect::<Vec<_>>();
+            .chain(category_dtos.iter().flat_map(|c| c.modules.iter()))
+            .map(|m| m.uuid.clone())
+            .collect();
 
         let module_dto = FieldsDTO {
             modules: modules_no_category.into_iter().map(|m| m.into()).collect(),
             categories: Some(category_dtos),
+            galleries: GalleryItem::read_for_modules(module_ids, db)?,
         };
 
         let mut page_dto: PageModuleDTO = filtered_page.into();
@@ -192,38 +1059,31 @@ impl Page {
     /// This is used for displaying a page, rather than getting a page's modules/array modules.
     pub fn read_one_join_on_url(
         id: String,
-        db: &MysqlConnection,
+        db: &DbConnection,
     ) -> Result<(Self, FieldsDTO), diesel::result::Error> {
-        use crate::schema::pages::dsl::page_url;
-
-        let filtered_page = pages::table.filter(page_url.eq(id)).first::<Page>(db)?;
+        use crate::schema::pages::dsl::{deleted_at, page_url};
 
-        let modules = Module::belonging_to(&filtered_page).load::<Module>(db)?;
+        let filtered_page = pages::table.filter(on_site()).filter(page_url.eq(id)).filter(deleted_at.is_null()).first::<Page>(db)?;
 
-        let categories: Vec<ModuleCategory> = Module::belonging_to(&filtered_page)
-            .inner_join(module_category::table)
-            .select(module_category::all_columns)
-            .load::<ModuleCategory>(db)?;
+        let modules = Module::inflate_all(Module::belonging_to(&filtered_page)
+            .filter(modules::deleted_at.is_null())
+            .order((modules::order_index.asc(), modules::uuid.asc()))
+            .load::<Module>(db)?)?;
 
-        let module_array: Vec<(Vec<Module>, ModuleCategory)> = Module::belonging_to(&categories)
-            .load::<Module>(db)?
-            .grouped_by(&categories)
-            .into_iter()
-            .zip(categories)
-            .collect::<Vec<_>>();
+        // templates are only given the categories that have something to show.
+        let mut category_dtos = ModuleCategory::read_for_page(&filtered_page, db)?;
+        category_dtos.retain(|c| !c.modules.is_empty());
 
-        let category_dtos: Vec<CategoryDTO> = module_array
+        let module_ids = modules
             .iter()
-            .map(|a| CategoryDTO {
-                uuid: a.1.uuid.clone(),
-                title: a.1.title.clone(),
-                modules: a.0.clone().into_iter().map(|m| m.into()).collect(),
-            })
-            .collect::<Vec<_>>();
+            .chain(category_dtos.iter().flat_map(|c| c.modules.iter()))
+            .map(|m| m.uuid.clone())
+            .collect();
 
         let module_dto = FieldsDTO {
             modules: modules.into_iter().map(|m| m.into()).collect(),
             categories: Some(category_dtos),
+            galleries: GalleryItem::read_for_modules(module_ids, db)?,
         };
 
         Ok((filtered_page, module_dto))
@@ -0,0 +1,185 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::module_models::Module;
+use super::page_models::{is_live, Page};
+use super::{DbConnection, Model};
+use crate::schema::{page_revisions, pages};
+
+/// A page and its modules as they were before a change, so that the change can be undone.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "page_revisions"]
+pub struct PageRevision {
+    pub uuid: String,
+    pub page_uuid: String,
+    /// The `PageSnapshot` as JSON.
+    #[serde(skip)]
+    pub content: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "page_revisions"]
+pub struct MutPageRevision {
+    pub uuid: String,
+    pub page_uuid: String,
+    pub content: String,
+}
+
+/// Everything a revision puts back. Encrypted module content stays encrypted in here, the same as in `modules`.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct PageSnapshot {
+    pub page_name: String,
+    pub page_url: String,
+    pub page_title: String,
+    /// Revisions from before pages had SEO fields don't have them, which restores the page without any.
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub meta_keywords: Option<String>,
+    #[serde(default)]
+    pub og_image: Option<String>,
+    #[serde(default)]
+    pub noindex: bool,
+    /// Whether the page was live when the revision was taken, which revisions from before this was kept never are.
+    #[serde(default)]
+    pub published: bool,
+    pub modules: Vec<Module>,
+}
+
+/// Used in the JSON response of revisions, with the snapshot parsed.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct PageRevisionDTO {
+    #[serde(flatten)]
+    pub revision: PageRevision,
+    pub snapshot: PageSnapshot,
+}
+
+impl Model<PageRevision, MutPageRevision, String> for PageRevision {
+    fn create(new: &MutPageRevision, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(page_revisions::table)
+            .values(new)
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<PageRevision, diesel::result::Error> {
+        use page_revisions::dsl::uuid;
+
+        page_revisions::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<PageRevision>, diesel::result::Error> {
+        use page_revisions::dsl::time_created;
+
+        page_revisions::table.order(time_created.desc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<PageRevision>, i64), diesel::result::Error> {
+        use page_revisions::dsl::{time_created, uuid};
+
+        let total = page_revisions::table.count().get_result(db)?;
+        let rows = page_revisions::table
+            .order((time_created.desc(), uuid.asc()))
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutPageRevision,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use page_revisions::dsl::uuid;
+
+        diesel::update(page_revisions::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use page_revisions::dsl::uuid;
+
+        diesel::delete(page_revisions::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl PageSnapshot {
+    /// The page as it is right now, along with every one of its modules, or `None` if it doesn't exist.
+    pub fn read(page_id: &str, db: &DbConnection) -> Result<Option<PageSnapshot>, diesel::result::Error> {
+        let page = match pages::table.filter(pages::uuid.eq(page_id)).first::<Page>(db).optional()? {
+            Some(page) => page,
+            None => return Ok(None),
+        };
+
+        Ok(Some(PageSnapshot {
+            modules: Module::read_for_pages(vec![page.uuid.clone()], db)?,
+            page_name: page.page_name,
+            page_url: page.page_url,
+            page_title: page.page_title,
+            meta_description: page.meta_description,
+            meta_keywords: page.meta_keywords,
+            og_image: page.og_image,
+            noindex: page.noindex,
+            published: is_live(&page.status, page.publish_at),
+        }))
+    }
+}
+
+impl PageRevision {
+    /// Saves the page as it is right now, along with every one of its modules.
+    /// A page that doesn't exist has nothing to save, and nothing is recorded for it.
+    pub fn record(page_id: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        let snapshot = match PageSnapshot::read(page_id, db)? {
+            Some(snapshot) => snapshot,
+            None => return Ok(0),
+        };
+        let content = serde_json::to_string(&snapshot).map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+
+        Self::create(
+            &MutPageRevision {
+                uuid: Uuid::new_v4().to_string(),
+                page_uuid: page_id.to_string(),
+                content,
+            },
+            db,
+        )
+    }
+
+    /// Every revision of a page, newest first.
+    pub fn read_for_page(page_id: String, db: &DbConnection) -> Result<Vec<PageRevision>, diesel::result::Error> {
+        use page_revisions::dsl::{page_uuid, time_created};
+
+        page_revisions::table
+            .filter(page_uuid.eq(page_id))
+            .order(time_created.desc())
+            .load::<Self>(db)
+    }
+
+    /// Every page that has any revisions, including ones that have since been trashed.
+    pub fn read_page_ids(db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+        use page_revisions::dsl::page_uuid;
+
+        page_revisions::table.select(page_uuid).distinct().load::<String>(db)
+    }
+
+    pub fn delete_many(ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use page_revisions::dsl::uuid;
+
+        diesel::delete(page_revisions::table.filter(uuid.eq_any(ids))).execute(db)
+    }
+
+    pub fn snapshot(&self) -> Result<PageSnapshot, diesel::result::Error> {
+        serde_json::from_str(&self.content).map_err(|e| diesel::result::Error::DeserializationError(Box::new(e)))
+    }
+}
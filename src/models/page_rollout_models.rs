@@ -0,0 +1,132 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::{page_rollouts, pages};
+
+/// A new version of a page's modules that is only shown to `percentage` of visitors until it is promoted or rolled back.
+/// A page has at most one rollout at a time.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "page_rollouts"]
+pub struct PageRollout {
+    pub uuid: String,
+    pub page_uuid: String,
+    pub percentage: i32,
+    /// A JSON object of module uuids to the content they have in the canary.
+    #[serde(skip)]
+    pub content: String,
+    pub control_views: i64,
+    pub canary_views: i64,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "page_rollouts"]
+pub struct MutPageRollout {
+    pub uuid: String,
+    pub page_uuid: String,
+    pub percentage: i32,
+    pub content: String,
+}
+
+/// Used in the JSON response of rollouts, with the canary content parsed.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct PageRolloutDTO {
+    #[serde(flatten)]
+    pub rollout: PageRollout,
+    pub modules: HashMap<String, String>,
+}
+
+impl Model<PageRollout, MutPageRollout, String> for PageRollout {
+    fn create(new: &MutPageRollout, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(page_rollouts::table)
+            .values(new)
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<PageRollout, diesel::result::Error> {
+        use page_rollouts::dsl::uuid;
+
+        page_rollouts::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<PageRollout>, diesel::result::Error> {
+        page_rollouts::table.load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<PageRollout>, i64), diesel::result::Error> {
+        use page_rollouts::dsl::uuid;
+
+        let total = page_rollouts::table.count().get_result(db)?;
+        let rows = page_rollouts::table.order(uuid.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutPageRollout,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use page_rollouts::dsl::uuid;
+
+        diesel::update(page_rollouts::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use page_rollouts::dsl::uuid;
+
+        diesel::delete(page_rollouts::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl PageRollout {
+    pub fn read_for_page(page_id: String, db: &DbConnection) -> Result<PageRollout, diesel::result::Error> {
+        use page_rollouts::dsl::page_uuid;
+
+        page_rollouts::table.filter(page_uuid.eq(page_id)).first::<Self>(db)
+    }
+
+    pub fn read_for_url(url: &str, db: &DbConnection) -> Result<PageRollout, diesel::result::Error> {
+        page_rollouts::table
+            .inner_join(pages::table)
+            .filter(pages::page_url.eq(url))
+            .select(page_rollouts::all_columns)
+            .first::<Self>(db)
+    }
+
+    /// Counts a view of either the canary or the current version.
+    pub fn record_view(id: String, canary: bool, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use page_rollouts::dsl::{canary_views, control_views, uuid};
+
+        let rollout = page_rollouts::table.filter(uuid.eq(id));
+
+        match canary {
+            true => diesel::update(rollout).set(canary_views.eq(canary_views + 1)).execute(db),
+            false => diesel::update(rollout).set(control_views.eq(control_views + 1)).execute(db),
+        }
+    }
+
+    pub fn modules(&self) -> Result<HashMap<String, String>, diesel::result::Error> {
+        serde_json::from_str(&self.content).map_err(|e| diesel::result::Error::DeserializationError(Box::new(e)))
+    }
+}
+
+impl From<PageRollout> for PageRolloutDTO {
+    fn from(rollout: PageRollout) -> Self {
+        Self {
+            modules: rollout.modules().unwrap_or_default(),
+            rollout,
+        }
+    }
+}
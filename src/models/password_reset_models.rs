@@ -0,0 +1,64 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::password_resets;
+use super::DbConnection;
+
+/// A token mailed to a user who forgot their password, which sets a new one once before it expires.
+/// Only a hash of the token is stored.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize)]
+#[primary_key(uuid)]
+#[table_name = "password_resets"]
+pub struct PasswordReset {
+    pub uuid: String,
+    pub user_uuid: String,
+    pub token_hash: String,
+    pub time_created: NaiveDateTime,
+    pub time_expires: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "password_resets"]
+pub struct MutPasswordReset {
+    pub uuid: String,
+    pub user_uuid: String,
+    pub token_hash: String,
+    pub time_expires: NaiveDateTime,
+}
+
+impl PasswordReset {
+    pub fn create(new: &MutPasswordReset, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(password_resets::table).values(new).execute(db)
+    }
+
+    /// The reset with the token's hash, unless it has expired.
+    pub fn read_valid(hash: &str, db: &DbConnection) -> Result<PasswordReset, diesel::result::Error> {
+        use password_resets::dsl::{time_expires, token_hash};
+
+        password_resets::table
+            .filter(token_hash.eq(hash))
+            .filter(time_expires.gt(chrono::Utc::now().naive_utc()))
+            .first::<Self>(db)
+    }
+
+    /// Drops every reset of the user, so that none of the tokens they were mailed work once one was used.
+    pub fn delete_for_user(user: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use password_resets::dsl::user_uuid;
+
+        diesel::delete(password_resets::table.filter(user_uuid.eq(user))).execute(db)
+    }
+
+    /// The uuids of the resets that have expired.
+    pub fn read_expired(db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+        use password_resets::dsl::{time_expires, uuid};
+
+        password_resets::table.filter(time_expires.le(chrono::Utc::now().naive_utc())).select(uuid).load::<String>(db)
+    }
+
+    pub fn delete_expired(db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use password_resets::dsl::time_expires;
+
+        diesel::delete(password_resets::table.filter(time_expires.le(chrono::Utc::now().naive_utc()))).execute(db)
+    }
+}
@@ -0,0 +1,50 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::recovery_codes;
+use super::DbConnection;
+
+/// A code that logs a user with two-factor authentication in once, for when they don't have their authenticator app.
+/// Only a hash of the code is stored, the codes themselves are shown once when two-factor authentication is turned on.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize)]
+#[primary_key(uuid)]
+#[table_name = "recovery_codes"]
+pub struct RecoveryCode {
+    pub uuid: String,
+    pub user_uuid: String,
+    pub code_hash: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "recovery_codes"]
+pub struct MutRecoveryCode {
+    pub uuid: String,
+    pub user_uuid: String,
+    pub code_hash: String,
+}
+
+impl RecoveryCode {
+    /// Replaces the user's codes, so that the ones they had before no longer work.
+    pub fn replace_for_user(user: &str, new: &[MutRecoveryCode], db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        Self::delete_for_user(user, db)?;
+
+        diesel::insert_into(recovery_codes::table).values(new).execute(db)
+    }
+
+    /// Uses up the user's code with the hash, and returns whether they had it.
+    pub fn consume(user: &str, hash: &str, db: &DbConnection) -> Result<bool, diesel::result::Error> {
+        use recovery_codes::dsl::{code_hash, user_uuid};
+
+        let deleted = diesel::delete(recovery_codes::table.filter(user_uuid.eq(user)).filter(code_hash.eq(hash))).execute(db)?;
+
+        Ok(deleted > 0)
+    }
+
+    pub fn delete_for_user(user: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use recovery_codes::dsl::user_uuid;
+
+        diesel::delete(recovery_codes::table.filter(user_uuid.eq(user))).execute(db)
+    }
+}
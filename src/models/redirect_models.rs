@@ -0,0 +1,108 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::site_models::default_site;
+use super::{DbConnection, Model};
+use crate::schema::redirects;
+use crate::services::site_service;
+
+/// The status codes a redirect can be sent with, permanent first.
+pub const STATUS_CODES: &[i32] = &[301, 302];
+
+/// Sends requests for `source`, a path that no page has, on to `target`, which is a path on the site or a URL.
+/// Kept for the old URLs of pages that were moved, so links to them keep working.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "redirects"]
+pub struct Redirect {
+    pub uuid: String,
+    pub source: String,
+    pub target: String,
+    /// 301 for a permanent move, or 302 for a temporary one.
+    pub status_code: i32,
+    pub time_created: NaiveDateTime,
+    /// The site the redirect was created on, which is the only one it sends requests on from.
+    #[serde(default = "default_site")]
+    pub site_id: String,
+}
+
+type OnSite = diesel::dsl::Or<diesel::dsl::Eq<redirects::site_id, String>, diesel::dsl::AsExprOf<bool, Bool>>;
+
+/// Scopes a query to the current site's redirects, or to every site's outside of a request (see `site_service::current`).
+fn on_site() -> OnSite {
+    let site = site_service::current();
+
+    redirects::site_id.eq(site.clone().unwrap_or_default()).or(site.is_none().into_sql::<Bool>())
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "redirects"]
+pub struct MutRedirect {
+    pub uuid: String,
+    pub source: String,
+    pub target: String,
+    pub status_code: i32,
+}
+
+/// The body of `POST /api/v1/redirects` and `PUT /api/v1/redirects/{id}`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewRedirect {
+    pub source: String,
+    pub target: String,
+    /// 301 when it isn't set.
+    pub status_code: Option<i32>,
+}
+
+impl Model<Redirect, MutRedirect, String> for Redirect {
+    fn create(new: &MutRedirect, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(redirects::table)
+            .values((new, redirects::site_id.eq(site_service::current_or_default())))
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<Redirect, diesel::result::Error> {
+        use redirects::dsl::uuid;
+
+        redirects::table.filter(on_site()).filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<Redirect>, diesel::result::Error> {
+        use redirects::dsl::source;
+
+        redirects::table.filter(on_site()).order(source.asc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Redirect>, i64), diesel::result::Error> {
+        use redirects::dsl::source;
+
+        let total = redirects::table.filter(on_site()).count().get_result(db)?;
+        let rows = redirects::table.filter(on_site()).order(source.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutRedirect,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use redirects::dsl::uuid;
+
+        diesel::update(redirects::table.filter(on_site()).filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use redirects::dsl::uuid;
+
+        diesel::delete(redirects::table.filter(on_site()).filter(uuid.eq(id))).execute(db)
+    }
+}
@@ -0,0 +1,201 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text};
+use serde::{Deserialize, Serialize};
+
+use super::{DbConnection, Model};
+use crate::schema::search_documents;
+use crate::services::site_service;
+
+/// Matches the documents that contain the search terms, which are bound as the first parameter.
+#[cfg(feature = "mysql")]
+const MATCHES: &str = "MATCH (page_title, page_name, content) AGAINST (? IN NATURAL LANGUAGE MODE)";
+#[cfg(feature = "postgres")]
+const MATCHES: &str =
+    "to_tsvector('simple', page_title || ' ' || page_name || ' ' || content) @@ plainto_tsquery('simple', $1)";
+/// SQLite has no FULLTEXT index, so this is a scan for the terms as they were typed.
+#[cfg(feature = "sqlite")]
+const MATCHES: &str =
+    "(page_title LIKE ?1 ESCAPE '\\' OR page_name LIKE ?1 ESCAPE '\\' OR content LIKE ?1 ESCAPE '\\')";
+
+/// Matches the documents of the pages of a site, which is bound as the second parameter.
+#[cfg(feature = "mysql")]
+const ON_SITE: &str = "page_uuid IN (SELECT uuid FROM pages WHERE site_id = ?)";
+#[cfg(feature = "postgres")]
+const ON_SITE: &str = "page_uuid IN (SELECT uuid FROM pages WHERE site_id = $2)";
+#[cfg(feature = "sqlite")]
+const ON_SITE: &str = "page_uuid IN (SELECT uuid FROM pages WHERE site_id = ?2)";
+
+/// MySQL already sorts natural language matches by relevance.
+#[cfg(feature = "mysql")]
+const RANKED: &str = "LIMIT ? OFFSET ?";
+#[cfg(feature = "postgres")]
+const RANKED: &str = "ORDER BY ts_rank(to_tsvector('simple', page_title || ' ' || page_name || ' ' || content), \
+    plainto_tsquery('simple', $1)) DESC LIMIT $3 OFFSET $4";
+#[cfg(feature = "sqlite")]
+const RANKED: &str = "ORDER BY page_title LIMIT ?3 OFFSET ?4";
+
+/// A page as it is stored in the internal search index, with the content of all of its modules in one column
+/// so that a single FULLTEXT index covers it.
+#[derive(Queryable, QueryableByName, Identifiable, Debug, Clone, Serialize, Deserialize)]
+#[primary_key(page_uuid)]
+#[table_name = "search_documents"]
+pub struct SearchDocument {
+    pub page_uuid: String,
+    pub page_url: String,
+    pub page_name: String,
+    pub page_title: String,
+    pub content: String,
+    pub time_indexed: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "search_documents"]
+pub struct MutSearchDocument {
+    pub page_uuid: String,
+    pub page_url: String,
+    pub page_name: String,
+    pub page_title: String,
+    pub content: String,
+    pub time_indexed: NaiveDateTime,
+}
+
+#[derive(QueryableByName)]
+struct Total {
+    #[sql_type = "BigInt"]
+    total: i64,
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn terms(q: &str) -> String {
+    q.to_string()
+}
+
+#[cfg(feature = "sqlite")]
+fn terms(q: &str) -> String {
+    let escaped = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+
+    format!("%{}%", escaped)
+}
+
+impl Model<SearchDocument, MutSearchDocument, String> for SearchDocument {
+    fn create(new: &MutSearchDocument, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(search_documents::table)
+            .values(new)
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<SearchDocument, diesel::result::Error> {
+        use search_documents::dsl::page_uuid;
+
+        search_documents::table.filter(page_uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<SearchDocument>, diesel::result::Error> {
+        search_documents::table.load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<SearchDocument>, i64), diesel::result::Error> {
+        use search_documents::dsl::page_uuid;
+
+        let total = search_documents::table.count().get_result(db)?;
+        let rows = search_documents::table.order(page_uuid.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutSearchDocument,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use search_documents::dsl::page_uuid;
+
+        diesel::update(search_documents::table.filter(page_uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use search_documents::dsl::page_uuid;
+
+        diesel::delete(search_documents::table.filter(page_uuid.eq(id))).execute(db)
+    }
+}
+
+impl SearchDocument {
+    pub fn count(db: &DbConnection) -> Result<i64, diesel::result::Error> {
+        search_documents::table.count().get_result(db)
+    }
+
+    /// Creates or replaces the documents of every page given.
+    #[cfg(not(feature = "postgres"))]
+    pub fn upsert_many(new: &[MutSearchDocument], db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::replace_into(search_documents::table)
+            .values(new)
+            .execute(db)
+    }
+
+    #[cfg(feature = "postgres")]
+    pub fn upsert_many(new: &[MutSearchDocument], db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use diesel::pg::upsert::excluded;
+        use search_documents::dsl::{content, page_name, page_title, page_url, page_uuid, time_indexed};
+
+        diesel::insert_into(search_documents::table)
+            .values(new)
+            .on_conflict(page_uuid)
+            .do_update()
+            .set((
+                page_url.eq(excluded(page_url)),
+                page_name.eq(excluded(page_name)),
+                page_title.eq(excluded(page_title)),
+                content.eq(excluded(content)),
+                time_indexed.eq(excluded(time_indexed)),
+            ))
+            .execute(db)
+    }
+
+    pub fn delete_by_url(url: &str, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use search_documents::dsl::page_url;
+
+        diesel::delete(search_documents::table.filter(page_url.eq(url))).execute(db)
+    }
+
+    /// The documents of the current site matching `q`, most relevant first, along with how many match in total.
+    pub fn search(
+        q: &str,
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<SearchDocument>, i64), diesel::result::Error> {
+        let site = site_service::current_or_default();
+
+        let total =
+            diesel::sql_query(format!("SELECT COUNT(*) AS total FROM search_documents WHERE {} AND {}", MATCHES, ON_SITE))
+                .bind::<Text, _>(terms(q))
+                .bind::<Text, _>(&site)
+                .get_result::<Total>(db)?
+                .total;
+
+        let documents =
+            diesel::sql_query(format!("SELECT * FROM search_documents WHERE {} AND {} {}", MATCHES, ON_SITE, RANKED))
+                .bind::<Text, _>(terms(q))
+                .bind::<Text, _>(&site)
+                .bind::<BigInt, _>(limit)
+                .bind::<BigInt, _>(offset)
+                .load::<SearchDocument>(db)?;
+
+        Ok((documents, total))
+    }
+
+    /// Removes every document last indexed before `time`.
+    pub fn delete_indexed_before(time: NaiveDateTime, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use search_documents::dsl::time_indexed;
+
+        diesel::delete(search_documents::table.filter(time_indexed.lt(time))).execute(db)
+    }
+}
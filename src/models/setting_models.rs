@@ -0,0 +1,252 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{DbConnection, Model};
+use crate::schema::{setting_changes, settings};
+use crate::services::site_service;
+
+/// What is read of a setting, which leaves out the site it is of.
+const SETTING_COLUMNS: (settings::setting_key, settings::setting_value) = (settings::setting_key, settings::setting_value);
+const CHANGE_COLUMNS: (
+    setting_changes::uuid,
+    setting_changes::setting_key,
+    setting_changes::old_value,
+    setting_changes::new_value,
+    setting_changes::actor,
+    setting_changes::time_created,
+) = (
+    setting_changes::uuid,
+    setting_changes::setting_key,
+    setting_changes::old_value,
+    setting_changes::new_value,
+    setting_changes::actor,
+    setting_changes::time_created,
+);
+
+type OnSite = diesel::dsl::Or<diesel::dsl::Eq<settings::site_id, String>, diesel::dsl::AsExprOf<bool, Bool>>;
+type ChangeOnSite = diesel::dsl::Or<diesel::dsl::Eq<setting_changes::site_id, String>, diesel::dsl::AsExprOf<bool, Bool>>;
+
+/// Scopes a query to the current site's settings, or to every site's outside of a request (see `site_service::current`).
+fn on_site() -> OnSite {
+    let site = site_service::current();
+
+    settings::site_id.eq(site.clone().unwrap_or_default()).or(site.is_none().into_sql::<Bool>())
+}
+
+fn change_on_site() -> ChangeOnSite {
+    let site = site_service::current();
+
+    setting_changes::site_id.eq(site.clone().unwrap_or_default()).or(site.is_none().into_sql::<Bool>())
+}
+
+/// A key/value setting of a site.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(setting_key)]
+#[table_name = "settings"]
+pub struct Setting {
+    pub setting_key: String,
+    pub setting_value: String,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "settings"]
+pub struct MutSetting {
+    pub setting_key: String,
+    pub setting_value: String,
+}
+
+/// A record of a setting being changed.
+/// `old_value` is `None` when the setting was created, and `new_value` is `None` when it was removed.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "setting_changes"]
+pub struct SettingChange {
+    pub uuid: String,
+    pub setting_key: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub actor: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "setting_changes"]
+pub struct MutSettingChange {
+    pub uuid: String,
+    pub setting_key: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub actor: String,
+}
+
+impl Model<Setting, MutSetting, String> for Setting {
+    fn create(new: &MutSetting, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(settings::table)
+            .values((new, settings::site_id.eq(site_service::current_or_default())))
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<Setting, diesel::result::Error> {
+        use settings::dsl::setting_key;
+
+        settings::table.filter(on_site()).filter(setting_key.eq(id)).select(SETTING_COLUMNS).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<Setting>, diesel::result::Error> {
+        settings::table.filter(on_site()).select(SETTING_COLUMNS).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Setting>, i64), diesel::result::Error> {
+        use settings::dsl::setting_key;
+
+        let total = settings::table.filter(on_site()).count().get_result(db)?;
+        let rows = settings::table
+            .filter(on_site())
+            .select(SETTING_COLUMNS)
+            .order(setting_key.asc())
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutSetting,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use settings::dsl::setting_key;
+
+        diesel::update(settings::table.filter(on_site()).filter(setting_key.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use settings::dsl::setting_key;
+
+        diesel::delete(settings::table.filter(on_site()).filter(setting_key.eq(id))).execute(db)
+    }
+}
+
+impl Setting {
+    /// Creates or updates a setting, recording the change made by `actor`.
+    /// These should be used instead of the `Model` functions so that no change goes unrecorded.
+    pub fn set(
+        key: String,
+        value: String,
+        actor: String,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        db.transaction(|| {
+            let old_value = Self::read_one(key.clone(), db).optional()?.map(|s| s.setting_value);
+
+            let new = MutSetting {
+                setting_key: key.clone(),
+                setting_value: value.clone(),
+            };
+
+            let site = settings::site_id.eq(site_service::current_or_default());
+
+            #[cfg(not(feature = "postgres"))]
+            let res = diesel::replace_into(settings::table).values((&new, site)).execute(db)?;
+            #[cfg(feature = "postgres")]
+            let res = diesel::insert_into(settings::table)
+                .values((&new, site))
+                .on_conflict((settings::site_id, settings::setting_key))
+                .do_update()
+                .set(&new)
+                .execute(db)?;
+
+            SettingChange::record(key, old_value, Some(value), actor, db)?;
+
+            Ok(res)
+        })
+    }
+
+    /// Removes a setting, recording the change made by `actor`.
+    pub fn unset(key: String, actor: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        db.transaction(|| {
+            let old_value = Self::read_one(key.clone(), db)?.setting_value;
+
+            let res = Self::delete(key.clone(), db)?;
+
+            SettingChange::record(key, Some(old_value), None, actor, db)?;
+
+            Ok(res)
+        })
+    }
+}
+
+impl SettingChange {
+    fn record(
+        key: String,
+        old_value: Option<String>,
+        new_value: Option<String>,
+        actor: String,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        let change = MutSettingChange {
+            uuid: Uuid::new_v4().to_string(),
+            setting_key: key,
+            old_value,
+            new_value,
+            actor,
+        };
+
+        diesel::insert_into(setting_changes::table)
+            .values((&change, setting_changes::site_id.eq(site_service::current_or_default())))
+            .execute(db)
+    }
+
+    /// Every setting that has ever been changed, including ones that have since been removed, as `(site, key)`.
+    pub fn read_keys(db: &DbConnection) -> Result<Vec<(String, String)>, diesel::result::Error> {
+        use setting_changes::dsl::{setting_key, site_id};
+
+        setting_changes::table.filter(change_on_site()).select((site_id, setting_key)).distinct().load(db)
+    }
+
+    pub fn delete_many(ids: Vec<String>, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use setting_changes::dsl::uuid;
+
+        diesel::delete(setting_changes::table.filter(change_on_site()).filter(uuid.eq_any(ids))).execute(db)
+    }
+
+    pub fn read_one(id: String, db: &DbConnection) -> Result<SettingChange, diesel::result::Error> {
+        use setting_changes::dsl::uuid;
+
+        setting_changes::table.filter(change_on_site()).filter(uuid.eq(id)).select(CHANGE_COLUMNS).first::<Self>(db)
+    }
+
+    /// Every change made to a setting, newest first.
+    pub fn read_for_key(key: String, db: &DbConnection) -> Result<Vec<SettingChange>, diesel::result::Error> {
+        use setting_changes::dsl::{setting_key, time_created};
+
+        setting_changes::table
+            .filter(change_on_site())
+            .filter(setting_key.eq(key))
+            .select(CHANGE_COLUMNS)
+            .order(time_created.desc())
+            .load::<Self>(db)
+    }
+
+    /// Puts the setting back to the value it had before this change.
+    /// The revert is itself recorded as a new change.
+    pub fn revert(id: String, actor: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        let change = Self::read_one(id, db)?;
+
+        match change.old_value {
+            Some(old_value) => Setting::set(change.setting_key, old_value, actor, db),
+            None => Setting::unset(change.setting_key, actor, db),
+        }
+    }
+}
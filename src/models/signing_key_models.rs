@@ -0,0 +1,134 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::signing_keys;
+
+/// A key used to sign JWTs. The `kid` is sent in the JWT header so the matching key can be found on decode.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize)]
+#[primary_key(kid)]
+#[table_name = "signing_keys"]
+pub struct SigningKey {
+    pub kid: String,
+    pub secret: String,
+    pub active: bool,
+    pub time_created: NaiveDateTime,
+    pub time_retired: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "signing_keys"]
+pub struct MutSigningKey {
+    pub kid: String,
+    pub secret: String,
+    pub active: bool,
+    pub time_retired: Option<NaiveDateTime>,
+}
+
+/// Used in the JSON response of signing keys. The secret never leaves the server.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SigningKeyDTO {
+    pub kid: String,
+    pub active: bool,
+    pub time_created: NaiveDateTime,
+    pub time_retired: Option<NaiveDateTime>,
+}
+
+impl From<SigningKey> for SigningKeyDTO {
+    fn from(key: SigningKey) -> Self {
+        Self {
+            kid: key.kid,
+            active: key.active,
+            time_created: key.time_created,
+            time_retired: key.time_retired,
+        }
+    }
+}
+
+impl Model<SigningKey, MutSigningKey, String> for SigningKey {
+    fn create(new: &MutSigningKey, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(signing_keys::table)
+            .values(new)
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<SigningKey, diesel::result::Error> {
+        use signing_keys::dsl::kid;
+
+        signing_keys::table.filter(kid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<SigningKey>, diesel::result::Error> {
+        use signing_keys::dsl::time_created;
+
+        signing_keys::table.order(time_created.desc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<SigningKey>, i64), diesel::result::Error> {
+        use signing_keys::dsl::{kid, time_created};
+
+        let total = signing_keys::table.count().get_result(db)?;
+        let rows = signing_keys::table.order((time_created.desc(), kid.asc())).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutSigningKey,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use signing_keys::dsl::kid;
+
+        diesel::update(signing_keys::table.filter(kid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use signing_keys::dsl::kid;
+
+        diesel::delete(signing_keys::table.filter(kid.eq(id))).execute(db)
+    }
+}
+
+impl SigningKey {
+    /// The newest active key is the one new tokens get signed with.
+    pub fn read_newest_active(db: &DbConnection) -> Result<Option<SigningKey>, diesel::result::Error> {
+        use signing_keys::dsl::{active, time_created};
+
+        signing_keys::table
+            .filter(active.eq(true))
+            .order(time_created.desc())
+            .first::<Self>(db)
+            .optional()
+    }
+
+    /// Any active key can still be used to verify a token, which is what allows rotating without logging everyone out.
+    pub fn read_active(id: String, db: &DbConnection) -> Result<SigningKey, diesel::result::Error> {
+        use signing_keys::dsl::{active, kid};
+
+        signing_keys::table
+            .filter(kid.eq(id))
+            .filter(active.eq(true))
+            .first::<Self>(db)
+    }
+
+    /// Retired keys stay in the table but can no longer sign or verify tokens.
+    pub fn retire(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use signing_keys::dsl::{active, kid, time_retired};
+
+        diesel::update(signing_keys::table.filter(kid.eq(id)))
+            .set((
+                active.eq(false),
+                time_retired.eq(Some(chrono::Utc::now().naive_utc())),
+            ))
+            .execute(db)
+    }
+}
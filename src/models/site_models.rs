@@ -0,0 +1,132 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::{media, modules, pages, redirects, setting_changes, settings, sites};
+use crate::services::site_service::DEFAULT_SITE;
+
+/// One of the sites a deployment serves, picked for each request by its `Host`. Pages, modules, media, redirects, and
+/// settings belong to one site each,
+/// and every query for them is scoped to the site of the request. The `default` site answers every host that no other site has.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "sites"]
+pub struct Site {
+    pub uuid: String,
+    pub name: String,
+    /// Like `blog.example.com`, without the port.
+    pub hostname: Option<String>,
+    pub time_created: NaiveDateTime,
+    /// The site's own quotas, which are `APP_MAX_PAGES`, `APP_MAX_MODULES`, and `APP_MAX_MEDIA_BYTES` when unset.
+    pub max_pages: Option<i64>,
+    pub max_modules: Option<i64>,
+    pub max_media_bytes: Option<i64>,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "sites"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct MutSite {
+    pub uuid: String,
+    pub name: String,
+    pub hostname: Option<String>,
+    pub max_pages: Option<i64>,
+    pub max_modules: Option<i64>,
+    pub max_media_bytes: Option<i64>,
+}
+
+/// The body of `POST /api/v1/sites` and `PUT /api/v1/sites/{id}`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewSite {
+    pub name: String,
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub max_pages: Option<i64>,
+    #[serde(default)]
+    pub max_modules: Option<i64>,
+    #[serde(default)]
+    pub max_media_bytes: Option<i64>,
+}
+
+/// What rows written before there were sites, like in exports, belong to.
+pub fn default_site() -> String {
+    String::from(DEFAULT_SITE)
+}
+
+impl Model<Site, MutSite, String> for Site {
+    fn create(new: &MutSite, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(sites::table).values(new).execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<Site, diesel::result::Error> {
+        use sites::dsl::uuid;
+
+        sites::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<Site>, diesel::result::Error> {
+        use sites::dsl::{name, uuid};
+
+        sites::table.order((name.asc(), uuid.asc())).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Site>, i64), diesel::result::Error> {
+        use sites::dsl::{name, uuid};
+
+        let total = sites::table.count().get_result(db)?;
+        let rows = sites::table.order((name.asc(), uuid.asc())).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutSite,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use sites::dsl::uuid;
+
+        diesel::update(sites::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    /// The site's redirects and settings, along with the history of its settings, go with it.
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use sites::dsl::uuid;
+
+        db.transaction(|| {
+            diesel::delete(redirects::table.filter(redirects::site_id.eq(&id))).execute(db)?;
+            diesel::delete(settings::table.filter(settings::site_id.eq(&id))).execute(db)?;
+            diesel::delete(setting_changes::table.filter(setting_changes::site_id.eq(&id))).execute(db)?;
+
+            diesel::delete(sites::table.filter(uuid.eq(id))).execute(db)
+        })
+    }
+}
+
+impl Site {
+    /// Every site that has a hostname, as `(hostname, uuid)`.
+    pub fn read_hostnames(db: &DbConnection) -> Result<Vec<(String, String)>, diesel::result::Error> {
+        use sites::dsl::{hostname, uuid};
+
+        let rows: Vec<(Option<String>, String)> = sites::table.filter(hostname.is_not_null()).select((hostname, uuid)).load(db)?;
+
+        Ok(rows.into_iter().filter_map(|(host, id)| Some((host?, id))).collect())
+    }
+
+    /// Whether any page, module, or media belongs to the site, including the ones in the trash.
+    pub fn has_content(id: &str, db: &DbConnection) -> Result<bool, diesel::result::Error> {
+        let pages: i64 = pages::table.filter(pages::site_id.eq(id)).count().get_result(db)?;
+        let modules: i64 = modules::table.filter(modules::site_id.eq(id)).count().get_result(db)?;
+        let media: i64 = media::table.filter(media::site_id.eq(id)).count().get_result(db)?;
+
+        Ok(pages + modules + media > 0)
+    }
+}
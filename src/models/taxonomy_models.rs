@@ -0,0 +1,259 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::{categories, page_categories, page_tags, tags};
+use crate::services::markdown_import_service::slugify;
+
+/// A tag pages are filed under, like `rust`. Pages have any number of them, and `GET /api/v1/tags/{tag}/pages` lists them by it.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "tags"]
+pub struct Tag {
+    pub uuid: String,
+    pub name: String,
+    /// What URLs and templates use for it, like `/api/v1/tags/rust/pages`.
+    pub slug: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "tags"]
+pub struct MutTag {
+    pub uuid: String,
+    pub name: String,
+    pub slug: String,
+}
+
+/// A category pages are filed under, like tags are. Not to be confused with the categories of modules, which group the modules of one page.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "categories"]
+pub struct PageCategory {
+    pub uuid: String,
+    pub name: String,
+    /// What URLs and templates use for it, like `/api/v1/categories/news/pages`.
+    pub slug: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "categories"]
+pub struct MutPageCategory {
+    pub uuid: String,
+    pub name: String,
+    pub slug: String,
+}
+
+/// The body of `POST` and `PUT` on `/api/v1/tags` and `/api/v1/categories`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewTerm {
+    pub name: String,
+    /// Made from the name when it isn't set, like `Rust Tips` becoming `rust-tips`.
+    pub slug: Option<String>,
+}
+
+impl NewTerm {
+    /// The slug it is saved with.
+    pub fn slug(&self) -> String {
+        self.slug.clone().unwrap_or_else(|| slugify(&self.name))
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "page_tags"]
+struct PageTag<'a> {
+    page_uuid: &'a str,
+    tag_uuid: &'a str,
+}
+
+#[derive(Insertable)]
+#[table_name = "page_categories"]
+struct PageInCategory<'a> {
+    page_uuid: &'a str,
+    category_uuid: &'a str,
+}
+
+impl Model<Tag, MutTag, String> for Tag {
+    fn create(new: &MutTag, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(tags::table).values(new).execute(db)
+    }
+
+    /// Takes the tag's uuid or its slug.
+    fn read_one(id: String, db: &DbConnection) -> Result<Tag, diesel::result::Error> {
+        use tags::dsl::{slug, uuid};
+
+        tags::table.filter(uuid.eq(&id).or(slug.eq(&id))).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<Tag>, diesel::result::Error> {
+        use tags::dsl::slug;
+
+        tags::table.order(slug.asc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Tag>, i64), diesel::result::Error> {
+        use tags::dsl::slug;
+
+        let total = tags::table.count().get_result(db)?;
+        let rows = tags::table.order(slug.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutTag,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use tags::dsl::uuid;
+
+        diesel::update(tags::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    /// Takes the tag off every page that has it.
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use tags::dsl::uuid;
+
+        db.transaction(|| {
+            diesel::delete(page_tags::table.filter(page_tags::tag_uuid.eq(&id))).execute(db)?;
+            diesel::delete(tags::table.filter(uuid.eq(&id))).execute(db)
+        })
+    }
+}
+
+impl Tag {
+    /// The tags whose uuid or slug is one of `keys`.
+    pub fn read_many(keys: &[String], db: &DbConnection) -> Result<Vec<Tag>, diesel::result::Error> {
+        use tags::dsl::{slug, uuid};
+
+        tags::table.filter(uuid.eq_any(keys).or(slug.eq_any(keys))).order(slug.asc()).load::<Self>(db)
+    }
+
+    /// The page's tags, by slug.
+    pub fn read_for_page(id: &str, db: &DbConnection) -> Result<Vec<Tag>, diesel::result::Error> {
+        tags::table
+            .inner_join(page_tags::table)
+            .filter(page_tags::page_uuid.eq(id))
+            .select(tags::all_columns)
+            .order(tags::slug.asc())
+            .load::<Self>(db)
+    }
+
+    /// Gives the page exactly these tags, taking off the ones it had that aren't among them.
+    pub fn set_for_page(id: &str, tag_ids: &[String], db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        db.transaction(|| {
+            diesel::delete(page_tags::table.filter(page_tags::page_uuid.eq(id))).execute(db)?;
+
+            let rows: Vec<PageTag> = tag_ids.iter().map(|t| PageTag { page_uuid: id, tag_uuid: t }).collect();
+            diesel::insert_into(page_tags::table).values(&rows).execute(db)
+        })
+    }
+
+    /// The uuids of every page with the tag, in the trash or not.
+    pub fn page_ids(id: &str, db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+        page_tags::table.filter(page_tags::tag_uuid.eq(id)).select(page_tags::page_uuid).load(db)
+    }
+}
+
+impl Model<PageCategory, MutPageCategory, String> for PageCategory {
+    fn create(new: &MutPageCategory, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(categories::table).values(new).execute(db)
+    }
+
+    /// Takes the category's uuid or its slug.
+    fn read_one(id: String, db: &DbConnection) -> Result<PageCategory, diesel::result::Error> {
+        use categories::dsl::{slug, uuid};
+
+        categories::table.filter(uuid.eq(&id).or(slug.eq(&id))).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<PageCategory>, diesel::result::Error> {
+        use categories::dsl::slug;
+
+        categories::table.order(slug.asc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<PageCategory>, i64), diesel::result::Error> {
+        use categories::dsl::slug;
+
+        let total = categories::table.count().get_result(db)?;
+        let rows = categories::table.order(slug.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutPageCategory,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use categories::dsl::uuid;
+
+        diesel::update(categories::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    /// Takes the category off every page in it.
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use categories::dsl::uuid;
+
+        db.transaction(|| {
+            diesel::delete(page_categories::table.filter(page_categories::category_uuid.eq(&id))).execute(db)?;
+            diesel::delete(categories::table.filter(uuid.eq(&id))).execute(db)
+        })
+    }
+}
+
+impl PageCategory {
+    /// The categories whose uuid or slug is one of `keys`.
+    pub fn read_many(keys: &[String], db: &DbConnection) -> Result<Vec<PageCategory>, diesel::result::Error> {
+        use categories::dsl::{slug, uuid};
+
+        categories::table.filter(uuid.eq_any(keys).or(slug.eq_any(keys))).order(slug.asc()).load::<Self>(db)
+    }
+
+    /// The page's categories, by slug.
+    pub fn read_for_page(id: &str, db: &DbConnection) -> Result<Vec<PageCategory>, diesel::result::Error> {
+        categories::table
+            .inner_join(page_categories::table)
+            .filter(page_categories::page_uuid.eq(id))
+            .select(categories::all_columns)
+            .order(categories::slug.asc())
+            .load::<Self>(db)
+    }
+
+    /// Puts the page in exactly these categories, taking it out of the ones it was in that aren't among them.
+    pub fn set_for_page(id: &str, category_ids: &[String], db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        db.transaction(|| {
+            diesel::delete(page_categories::table.filter(page_categories::page_uuid.eq(id))).execute(db)?;
+
+            let rows: Vec<PageInCategory> = category_ids
+                .iter()
+                .map(|c| PageInCategory { page_uuid: id, category_uuid: c })
+                .collect();
+            diesel::insert_into(page_categories::table).values(&rows).execute(db)
+        })
+    }
+
+    /// The uuids of every page in the category, in the trash or not.
+    pub fn page_ids(id: &str, db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+        page_categories::table
+            .filter(page_categories::category_uuid.eq(id))
+            .select(page_categories::page_uuid)
+            .load(db)
+    }
+}
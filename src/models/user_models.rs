@@ -1,6 +1,7 @@
-use super::Model;
+use super::{DbConnection, Model};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::schema::users;
 
@@ -10,38 +11,151 @@ use crate::schema::users;
 pub struct User {
     pub uuid: String,
     pub username: String,
+    /// The argon2 hash of the password, which is never sent, like the token it would let someone take over the user with.
+    #[serde(skip)]
     pub password: String,
+    #[serde(skip)]
     pub token: Option<String>,
+    pub email: Option<String>,
+    /// One of the `UserRole`s.
+    pub role: String,
+    /// The `sub` of the user at the OIDC issuer, once they have logged in through it.
+    #[serde(skip)]
+    pub oidc_subject: Option<String>,
+    /// The base32 secret their authenticator app makes codes from, set by `POST /api/v1/user/2fa/setup`.
+    #[serde(skip)]
+    pub totp_secret: Option<String>,
+    /// Whether logging in takes a code too, which is once the secret was confirmed with one.
+    pub totp_enabled: bool,
+    #[serde(skip)]
+    pub totp_last_step: Option<i64>,
 }
 
-#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+/// What a user can do. Viewers can only read, editors can change content too, and admins can change everything,
+/// including settings, keys, tokens, and other users.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[default]
+    Viewer,
+    Editor,
+    Admin,
+}
+
+impl UserRole {
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "viewer" => Some(Self::Viewer),
+            "editor" => Some(Self::Editor),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Viewer => "viewer",
+            Self::Editor => "editor",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize, ToSchema)]
 #[table_name = "users"]
 pub struct MutUser {
     pub uuid: Option<String>,
     pub username: String,
     pub password: Option<String>,
     pub token: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// New users are viewers unless this says otherwise, and updates leave the role alone when it isn't set.
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+/// The body of a login, which also takes a two-factor `code` for users that turned it on.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LoginBody {
+    #[serde(flatten)]
+    pub user: MutUser,
+    /// A code from the user's authenticator app, or one of their recovery codes.
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+/// A code from the user's authenticator app, or one of their recovery codes where it says so.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorCode {
+    pub code: String,
+}
+
+/// What an authenticator app needs to make codes for the user.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// The codes that log the user in without their authenticator app, once each. They aren't shown again.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// The body of `POST /api/v1/user/forgot`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+/// The body of `POST /api/v1/user/reset`, with the token from the mail.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResetPassword {
+    pub token: String,
+    pub password: String,
+}
+
+/// The body of a role change, like `{ "role": "editor" }`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MutUserRole {
+    pub role: String,
 }
 
 impl Model<User, MutUser, String> for User {
-    fn create(new: &MutUser, db: &diesel::MysqlConnection) -> Result<usize, diesel::result::Error> {
+    fn create(new: &MutUser, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        let new = &MutUser {
+            role: Some(new.role.clone().unwrap_or_else(|| String::from(UserRole::Viewer.as_str()))),
+            ..new.clone()
+        };
+
         diesel::insert_into(users::table).values(new).execute(db)
     }
 
-    fn read_one(id: String, db: &diesel::MysqlConnection) -> Result<User, diesel::result::Error> {
+    fn read_one(id: String, db: &DbConnection) -> Result<User, diesel::result::Error> {
         use users::dsl::username;
 
         Ok(users::table.filter(username.eq(id)).first::<User>(db)?)
     }
 
-    fn read_all(_: &diesel::MysqlConnection) -> Result<Vec<User>, diesel::result::Error> {
+    fn read_all(_: &DbConnection) -> Result<Vec<User>, diesel::result::Error> {
         unimplemented!()
     }
 
+    fn read_paginated(offset: i64, limit: i64, db: &DbConnection) -> Result<(Vec<User>, i64), diesel::result::Error> {
+        use users::dsl::username;
+
+        let total = users::table.count().get_result(db)?;
+        let rows = users::table.order(username.asc()).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
     fn update(
         id: String,
         new: &MutUser,
-        db: &diesel::MysqlConnection,
+        db: &DbConnection,
     ) -> Result<usize, diesel::result::Error> {
         use users::dsl::username;
         let update = diesel::update(users::table.filter(username.eq(id)))
@@ -51,15 +165,84 @@ impl Model<User, MutUser, String> for User {
         Ok(update)
     }
 
-    fn delete(_: String, _: &diesel::MysqlConnection) -> Result<usize, diesel::result::Error> {
-        todo!()
+    /// Their API keys, recovery codes, and password resets go with them, as those reference the user.
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use users::dsl::username;
+
+        diesel::delete(users::table.filter(username.eq(id))).execute(db)
     }
 }
 
 impl User {
+    /// A role that isn't one of the `UserRole`s, which can only be set by hand, is treated as the least one.
+    pub fn role(&self) -> UserRole {
+        UserRole::parse(&self.role).unwrap_or_default()
+    }
+
+    pub fn set_role(id: String, new_role: UserRole, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use users::dsl::{role, username};
+
+        diesel::update(users::table.filter(username.eq(id)))
+            .set(role.eq(new_role.as_str()))
+            .execute(db)
+    }
+
+    pub fn read_by_uuid(id: String, db: &DbConnection) -> Result<User, diesel::result::Error> {
+        use users::dsl::uuid;
+
+        users::table.filter(uuid.eq(id)).first::<User>(db)
+    }
+
+    pub fn read_by_oidc_subject(subject: String, db: &DbConnection) -> Result<User, diesel::result::Error> {
+        use users::dsl::oidc_subject;
+
+        users::table.filter(oidc_subject.eq(subject)).first::<User>(db)
+    }
+
+    /// Links the user to their identity at the OIDC issuer, so that later logins find them by it.
+    pub fn set_oidc_subject(id: String, subject: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use users::dsl::{oidc_subject, username};
+
+        diesel::update(users::table.filter(username.eq(id)))
+            .set(oidc_subject.eq(subject))
+            .execute(db)
+    }
+
+    /// Sets a new password hash, and logs the user out of where they were logged in with the old one.
+    pub fn set_password(id: String, hash: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use users::dsl::{password, token, username};
+
+        diesel::update(users::table.filter(username.eq(id)))
+            .set((password.eq(hash), token.eq(None::<String>)))
+            .execute(db)
+    }
+
+    /// Sets the secret two-factor codes are checked against, and whether they are asked for when logging in.
+    pub fn set_totp(
+        id: String,
+        secret: Option<String>,
+        enabled: bool,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use users::dsl::{totp_enabled, totp_last_step, totp_secret, username};
+
+        diesel::update(users::table.filter(username.eq(id)))
+            .set((totp_secret.eq(secret), totp_enabled.eq(enabled), totp_last_step.eq(None::<i64>)))
+            .execute(db)
+    }
+
+    /// Remembers the time step of the code that was just used, so that it can't be used again.
+    pub fn set_totp_last_step(id: String, step: i64, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use users::dsl::{totp_last_step, username};
+
+        diesel::update(users::table.filter(username.eq(id)))
+            .set(totp_last_step.eq(step))
+            .execute(db)
+    }
+
     pub fn update_with_token(
         new: &MutUser,
-        db: &diesel::MysqlConnection,
+        db: &DbConnection,
     ) -> Result<usize, diesel::result::Error> {
         use users::dsl::username;
 
@@ -69,4 +252,59 @@ impl User {
 
         Ok(res)
     }
+
+    /// Every user that is currently holding a login token.
+    pub fn read_with_token(db: &DbConnection) -> Result<Vec<User>, diesel::result::Error> {
+        use users::dsl::token;
+
+        users::table.filter(token.is_not_null()).load::<User>(db)
+    }
+
+    pub fn clear_tokens(
+        usernames: Vec<String>,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use users::dsl::{token, username};
+
+        diesel::update(users::table.filter(username.eq_any(usernames)))
+            .set(token.eq(None::<String>))
+            .execute(db)
+    }
+
+    pub fn read_by_email(
+        user_email: String,
+        db: &DbConnection,
+    ) -> Result<Vec<User>, diesel::result::Error> {
+        use users::dsl::email;
+
+        users::table.filter(email.eq(user_email)).load::<User>(db)
+    }
+
+    /// Strips everything that identifies the user tied to the email, while keeping the row so that anything referencing it stays intact.
+    /// `unusable_password` replaces their password hash, and should be the hash of something nobody knows, so that nobody can log in as them.
+    pub fn anonymize_by_email(
+        user_email: String,
+        unusable_password: String,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        let mut anonymized = 0;
+
+        for user in Self::read_by_email(user_email, db)? {
+            use users::dsl::{email, oidc_subject, password, token, totp_enabled, totp_secret, username, uuid};
+
+            anonymized += diesel::update(users::table.filter(uuid.eq(user.uuid.clone())))
+                .set((
+                    username.eq(format!("deleted-{}", user.uuid)),
+                    password.eq(unusable_password.clone()),
+                    token.eq(None::<String>),
+                    email.eq(None::<String>),
+                    totp_secret.eq(None::<String>),
+                    totp_enabled.eq(false),
+                    oidc_subject.eq(None::<String>),
+                ))
+                .execute(db)?;
+        }
+
+        Ok(anonymized)
+    }
 }
@@ -0,0 +1,103 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{DbConnection, Model};
+use crate::schema::webhooks;
+
+/// Everything a webhook can be told about. `*` subscribes to all of them.
+pub const EVENTS: &[&str] = &[
+    "page.created",
+    "page.updated",
+    "page.deleted",
+    "module.created",
+    "module.updated",
+    "module.deleted",
+    "category.created",
+    "category.updated",
+    "category.deleted",
+];
+
+/// A URL that is sent a signed `POST` after every change to content it subscribes to.
+/// `events` is comma separated. The secret signs the payloads, so it is only shown when the webhook is created.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[primary_key(uuid)]
+#[table_name = "webhooks"]
+pub struct Webhook {
+    pub uuid: String,
+    pub name: String,
+    pub url: String,
+    #[serde(skip)]
+    pub secret: String,
+    pub events: String,
+    pub time_created: NaiveDateTime,
+}
+
+#[derive(Debug, AsChangeset, Insertable, Clone, Serialize, Deserialize)]
+#[table_name = "webhooks"]
+pub struct MutWebhook {
+    pub uuid: String,
+    pub name: String,
+    pub url: String,
+    pub secret: String,
+    pub events: String,
+}
+
+impl Model<Webhook, MutWebhook, String> for Webhook {
+    fn create(new: &MutWebhook, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(webhooks::table)
+            .values(new)
+            .execute(db)
+    }
+
+    fn read_one(id: String, db: &DbConnection) -> Result<Webhook, diesel::result::Error> {
+        use webhooks::dsl::uuid;
+
+        webhooks::table.filter(uuid.eq(id)).first::<Self>(db)
+    }
+
+    fn read_all(db: &DbConnection) -> Result<Vec<Webhook>, diesel::result::Error> {
+        use webhooks::dsl::time_created;
+
+        webhooks::table.order(time_created.desc()).load::<Self>(db)
+    }
+
+    fn read_paginated(
+        offset: i64,
+        limit: i64,
+        db: &DbConnection,
+    ) -> Result<(Vec<Webhook>, i64), diesel::result::Error> {
+        use webhooks::dsl::{time_created, uuid};
+
+        let total = webhooks::table.count().get_result(db)?;
+        let rows = webhooks::table.order((time_created.desc(), uuid.asc())).offset(offset).limit(limit).load::<Self>(db)?;
+
+        Ok((rows, total))
+    }
+
+    fn update(
+        id: String,
+        new: &MutWebhook,
+        db: &DbConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use webhooks::dsl::uuid;
+
+        diesel::update(webhooks::table.filter(uuid.eq(id)))
+            .set(new)
+            .execute(db)
+    }
+
+    fn delete(id: String, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        use webhooks::dsl::uuid;
+
+        diesel::delete(webhooks::table.filter(uuid.eq(id))).execute(db)
+    }
+}
+
+impl Webhook {
+    /// Whether the webhook is told about `event`.
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.split(',').map(str::trim).any(|e| e == "*" || e == event)
+    }
+}
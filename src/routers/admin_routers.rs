@@ -0,0 +1,38 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::export_controllers::*;
+use crate::controllers::job_controllers::*;
+use crate::controllers::search_controllers::*;
+use crate::controllers::system_controllers::*;
+use crate::services::body_limit_service;
+use crate::services::export_service::MAX_DUMP_SIZE;
+
+pub struct AdminRouter;
+
+impl Router for AdminRouter {
+    fn new() -> Scope {
+        web::scope("/admin")
+            .route("/stats", web::get().to(get_dashboard_stats))
+            .route("/jobs", web::get().to(get_jobs))
+            .route("/system/db", web::get().to(get_db_health))
+            .route("/system/db/{name}", web::get().to(get_named_db_health))
+            .route("/system/cache", web::delete().to(clear_cache))
+            .route("/system/gc", web::get().to(get_gc_report))
+            .route("/system/gc", web::post().to(run_gc))
+            .route("/system/stats", web::get().to(get_system_stats))
+            .route("/system/publish", web::post().to(publish_everything))
+            .route("/system/export-static", web::post().to(export_static))
+            .route("/system/migrations", web::get().to(get_migrations))
+            .route("/system/migrations", web::post().to(run_migrations))
+            .route("/search/reindex", web::get().to(get_reindex_status))
+            .route("/search/reindex", web::post().to(reindex))
+            .route("/export", web::get().to(export_content))
+            // a dump holds the whole site, so it is let through far past the usual limit on bodies.
+            .service(
+                web::resource("/import")
+                    .app_data(body_limit_service::json(MAX_DUMP_SIZE as u64))
+                    .route(web::post().to(import_content)),
+            )
+    }
+}
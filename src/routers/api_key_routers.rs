@@ -0,0 +1,15 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::api_key_controllers::*;
+
+pub struct ApiKeyRouter;
+
+impl Router for ApiKeyRouter {
+    fn new() -> Scope {
+        web::scope("/api_keys")
+            .route("", web::post().to(create_api_key))
+            .route("", web::get().to(get_api_keys))
+            .route("/{id}", web::delete().to(delete_api_key))
+    }
+}
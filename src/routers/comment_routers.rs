@@ -0,0 +1,16 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::comment_controllers::*;
+
+/// Moderating comments. Visitors send and read them under `/pages/{id}/comments`.
+pub struct CommentRouter;
+
+impl Router for CommentRouter {
+    fn new() -> Scope {
+        web::scope("/comments")
+            .route("", web::get().to(get_comments))
+            .route("/{id}/status", web::put().to(update_comment_status))
+            .route("/{id}", web::delete().to(delete_comment))
+    }
+}
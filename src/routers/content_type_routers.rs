@@ -0,0 +1,17 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::content_type_controllers::*;
+
+pub struct ContentTypeRouter;
+
+impl Router for ContentTypeRouter {
+    fn new() -> Scope {
+        web::scope("/content-types")
+            .route("", web::post().to(create_content_type))
+            .route("", web::get().to(get_content_types))
+            .route("/{id}", web::get().to(get_content_type))
+            .route("/{id}", web::put().to(update_content_type))
+            .route("/{id}", web::delete().to(delete_content_type))
+    }
+}
@@ -0,0 +1,18 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::delivery_controllers::*;
+
+/// The read only content delivery API, kept apart from the `/api/v1` management API.
+pub struct DeliveryRouter;
+
+impl Router for DeliveryRouter {
+    fn new() -> Scope {
+        web::scope("/content/v1")
+            .route("/pages", web::get().to(get_pages))
+            .route("/pages/url/{url:.*}", web::get().to(get_page_by_url))
+            .route("/pages/{id}", web::get().to(get_page))
+            .route("/modules/{id}", web::get().to(get_module))
+            .route("/categories/{id}", web::get().to(get_category))
+    }
+}
@@ -0,0 +1,15 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::delivery_token_controllers::*;
+
+pub struct DeliveryTokenRouter;
+
+impl Router for DeliveryTokenRouter {
+    fn new() -> Scope {
+        web::scope("/delivery_tokens")
+            .route("", web::post().to(create_delivery_token))
+            .route("", web::get().to(get_delivery_tokens))
+            .route("/{id}", web::delete().to(delete_delivery_token))
+    }
+}
@@ -0,0 +1,21 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::form_controllers::*;
+
+pub struct FormRouter;
+
+impl Router for FormRouter {
+    fn new() -> Scope {
+        web::scope("/forms")
+            .route("", web::post().to(create_form))
+            .route("", web::get().to(get_forms))
+            .route("/{id}", web::get().to(get_form))
+            .route("/{id}", web::put().to(update_form))
+            .route("/{id}", web::delete().to(delete_form))
+            .route("/{id}/submit", web::post().to(submit_form))
+            .route("/{id}/submissions", web::get().to(get_submissions))
+            .route("/{id}/submissions.csv", web::get().to(export_submissions))
+            .route("/{id}/submissions/{submission_id}", web::delete().to(delete_submission))
+    }
+}
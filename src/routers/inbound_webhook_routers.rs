@@ -0,0 +1,16 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::inbound_webhook_controllers::*;
+
+pub struct InboundWebhookRouter;
+
+impl Router for InboundWebhookRouter {
+    fn new() -> Scope {
+        web::scope("/inbound_webhooks")
+            .route("", web::post().to(create_inbound_webhook))
+            .route("", web::get().to(get_inbound_webhooks))
+            .route("/{id}", web::delete().to(delete_inbound_webhook))
+            .route("/{id}/receive", web::post().to(receive_inbound_webhook))
+    }
+}
@@ -0,0 +1,25 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::live_controllers::*;
+use crate::controllers::preview_controllers::preview_socket;
+
+/// The WebSockets, which are kept outside of `/api/v1` with the other endpoints that aren't JSON.
+pub struct SocketRouter;
+
+impl Router for SocketRouter {
+    fn new() -> Scope {
+        web::scope("/ws")
+            .route("", web::get().to(live_socket))
+            .route("/preview/{token}", web::get().to(preview_socket))
+    }
+}
+
+/// Server-sent events, for clients simpler than the WebSocket's.
+pub struct EventRouter;
+
+impl Router for EventRouter {
+    fn new() -> Scope {
+        web::scope("/events").route("", web::get().to(event_stream))
+    }
+}
@@ -0,0 +1,16 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::media_controllers::*;
+
+pub struct MediaRouter;
+
+impl Router for MediaRouter {
+    fn new() -> Scope {
+        web::scope("/media")
+            .route("", web::post().to(upload_media))
+            .route("", web::get().to(get_media_list))
+            .route("/{id}", web::get().to(get_media))
+            .route("/{id}", web::delete().to(delete_media))
+    }
+}
@@ -0,0 +1,21 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::menu_controllers::*;
+
+pub struct MenuRouter;
+
+impl Router for MenuRouter {
+    fn new() -> Scope {
+        web::scope("/menus")
+            .route("", web::post().to(create_menu))
+            .route("", web::get().to(get_menus))
+            .route("/{id}", web::get().to(get_menu))
+            .route("/{id}", web::put().to(update_menu))
+            .route("/{id}", web::delete().to(delete_menu))
+            .route("/{id}/items", web::post().to(add_menu_item))
+            .route("/{id}/items", web::put().to(reorder_menu))
+            .route("/{id}/items/{item_id}", web::put().to(update_menu_item))
+            .route("/{id}/items/{item_id}", web::delete().to(remove_menu_item))
+    }
+}
@@ -1,10 +1,48 @@
 use actix_web::Scope;
 
+pub mod admin_routers;
+pub mod api_key_routers;
 pub mod module_routers;
 pub mod page_routers;
 pub mod category_routers;
+pub mod comment_routers;
+pub mod content_type_routers;
+pub mod delivery_routers;
+pub mod delivery_token_routers;
+pub mod form_routers;
+pub mod inbound_webhook_routers;
+pub mod live_routers;
+pub mod media_routers;
+pub mod menu_routers;
+pub mod oidc_routers;
+pub mod preview_routers;
+pub mod privacy_routers;
+pub mod quota_routers;
+pub mod redirect_routers;
+pub mod render_routers;
+pub mod search_routers;
+pub mod session_routers;
+pub mod setting_routers;
+pub mod signing_key_routers;
+pub mod site_routers;
+pub mod taxonomy_routers;
+pub mod theme_routers;
+pub mod trash_routers;
 pub mod user_routers;
+pub mod v1_routers;
+pub mod webhook_routers;
 
 pub trait Router {
     fn new() -> Scope;
+}
+
+/// One version of the REST API, mounted at `/api/<NAME>` (see `api_version_service`). A version routes to handlers and DTOs
+/// of its own wherever it differs from the one before, so that a `v2` can change what it needs to while `v1` is served
+/// next to it, as it was, for the clients built on it.
+pub trait ApiVersion {
+    /// The version as it is in the path, like `v1`.
+    const NAME: &'static str;
+
+    /// Adds every route of the version to `scope`.
+    fn routes(scope: Scope) -> Scope;
 }
\ No newline at end of file
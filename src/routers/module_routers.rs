@@ -1,5 +1,6 @@
 use actix_web::{web, Scope};
 
+use crate::controllers::gallery_controllers::*;
 use crate::controllers::module_controllers::*;
 
 pub struct ModuleRouter;
@@ -9,9 +10,17 @@ impl ModuleRouter {
         web::scope("/modules")
             .route("", web::post().to(create_module))
             .route("", web::get().to(get_modules))
+            .route("/bulk", web::post().to(bulk_modules))
+            .route("/global", web::get().to(get_global_modules))
             .route("/{id}", web::get().to(get_module))
             .route("/{id}", web::put().to(update_module))
+            .route("/{id}", web::patch().to(patch_module))
             .route("/{id}", web::delete().to(delete_module))
+            .route("/{id}/restore", web::post().to(restore_module))
             .route("/category/{id}", web::get().to(get_module_category))
+            .route("/{id}/gallery", web::get().to(get_gallery))
+            .route("/{id}/gallery", web::post().to(add_gallery_item))
+            .route("/{id}/gallery", web::put().to(reorder_gallery))
+            .route("/{id}/gallery/{item_id}", web::delete().to(remove_gallery_item))
     }
 }
@@ -0,0 +1,14 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::oidc_controllers::*;
+
+pub struct OidcRouter;
+
+impl Router for OidcRouter {
+    fn new() -> Scope {
+        web::scope("/auth/oidc")
+            .route("/login", web::get().to(oidc_login))
+            .route("/callback", web::get().to(oidc_callback))
+    }
+}
@@ -1,6 +1,11 @@
 use actix_web::{web, Scope};
 
+use crate::controllers::comment_controllers::{create_comment, get_page_comments};
 use crate::controllers::page_controllers::*;
+use crate::controllers::preview_controllers::create_preview_link;
+use crate::controllers::revision_controllers::*;
+use crate::controllers::rollout_controllers::*;
+use crate::controllers::taxonomy_controllers::*;
 
 pub struct PageRouter;
 
@@ -9,9 +14,33 @@ impl PageRouter {
         web::scope("/pages")
             .route("", web::post().to(create_page))
             .route("", web::get().to(get_pages))
+            .route("/bulk", web::post().to(bulk_pages))
+            .route("/with-modules", web::post().to(create_page_with_modules))
             .route("/{id}", web::get().to(get_page))
             .route("/{id}/modules", web::get().to(get_page_join_modules))
+            .route("/{id}/modules/reorder", web::put().to(reorder_page_modules))
             .route("/{id}", web::put().to(update_page))
+            .route("/{id}", web::patch().to(patch_page))
+            .route("/{id}/status", web::put().to(update_page_status))
+            .route("/{id}/parent", web::put().to(update_page_parent))
+            .route("/{id}/tree", web::get().to(get_page_tree))
+            .route("/{id}/translations", web::get().to(get_page_translations))
+            .route("/{id}/preview-token", web::post().to(create_preview_link))
+            .route("/{id}/tags", web::get().to(get_page_tags))
+            .route("/{id}/tags", web::put().to(set_page_tags))
+            .route("/{id}/categories", web::get().to(get_categories_of_page))
+            .route("/{id}/categories", web::put().to(set_categories_of_page))
             .route("/{id}", web::delete().to(delete_page))
+            .route("/{id}/restore", web::post().to(restore_page))
+            .route("/{id}/duplicate", web::post().to(duplicate_page))
+            .route("/{id}/comments", web::post().to(create_comment))
+            .route("/{id}/comments", web::get().to(get_page_comments))
+            .route("/{id}/revisions", web::get().to(get_revisions))
+            .route("/{id}/revisions/{revision_id}/restore", web::post().to(restore_revision))
+            .route("/{id}/revisions/{from}/diff/{to}", web::get().to(get_revision_diff))
+            .route("/{id}/rollout", web::get().to(get_rollout))
+            .route("/{id}/rollout", web::put().to(put_rollout))
+            .route("/{id}/rollout/promote", web::post().to(promote_rollout))
+            .route("/{id}/rollout/rollback", web::post().to(rollback_rollout))
     }
 }
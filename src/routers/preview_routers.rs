@@ -0,0 +1,14 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::preview_controllers::*;
+
+pub struct PreviewRouter;
+
+impl Router for PreviewRouter {
+    fn new() -> Scope {
+        web::scope("/preview")
+            .route("", web::post().to(create_preview))
+            .route("/{token}", web::delete().to(delete_preview))
+    }
+}
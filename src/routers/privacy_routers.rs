@@ -0,0 +1,15 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::privacy_controllers::*;
+
+pub struct PrivacyRouter;
+
+impl Router for PrivacyRouter {
+    fn new() -> Scope {
+        web::scope("/privacy")
+            .route("/requests", web::get().to(get_data_requests))
+            .route("/{email}", web::get().to(export_personal_data))
+            .route("/{email}/erase", web::post().to(erase_personal_data))
+    }
+}
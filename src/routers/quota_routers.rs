@@ -0,0 +1,13 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::quota_controllers::*;
+
+pub struct QuotaRouter;
+
+impl Router for QuotaRouter {
+    fn new() -> Scope {
+        web::scope("/usage")
+            .route("", web::get().to(get_usage))
+    }
+}
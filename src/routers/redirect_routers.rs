@@ -0,0 +1,17 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::redirect_controllers::*;
+
+pub struct RedirectRouter;
+
+impl Router for RedirectRouter {
+    fn new() -> Scope {
+        web::scope("/redirects")
+            .route("", web::post().to(create_redirect))
+            .route("", web::get().to(get_redirects))
+            .route("/{id}", web::get().to(get_redirect))
+            .route("/{id}", web::put().to(update_redirect))
+            .route("/{id}", web::delete().to(delete_redirect))
+    }
+}
@@ -0,0 +1,15 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::render_controllers::*;
+
+/// Server rendered HTML fragments, for frontends that swap parts of a page in place (like htmx or Turbo).
+pub struct RenderRouter;
+
+impl Router for RenderRouter {
+    fn new() -> Scope {
+        web::scope("/render")
+            .route("/modules/{id}", web::get().to(get_module_fragment))
+            .route("/pages/{id}/region/{name}", web::get().to(get_region_fragment))
+    }
+}
@@ -0,0 +1,13 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::search_controllers::*;
+
+/// Public site search. Rebuilding the index is under the admin routes.
+pub struct SearchRouter;
+
+impl Router for SearchRouter {
+    fn new() -> Scope {
+        web::scope("/search").route("", web::get().to(search))
+    }
+}
@@ -0,0 +1,15 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::session_controllers::*;
+
+pub struct SessionRouter;
+
+impl Router for SessionRouter {
+    fn new() -> Scope {
+        web::scope("/session")
+            .route("", web::post().to(create_session))
+            .route("", web::get().to(get_session))
+            .route("", web::delete().to(delete_session))
+    }
+}
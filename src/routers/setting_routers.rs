@@ -0,0 +1,18 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::setting_controllers::*;
+
+pub struct SettingRouter;
+
+impl Router for SettingRouter {
+    fn new() -> Scope {
+        web::scope("/settings")
+            .route("", web::get().to(get_settings))
+            .route("/changes/{id}/revert", web::post().to(revert_setting_change))
+            .route("/{id}", web::get().to(get_setting))
+            .route("/{id}", web::put().to(set_setting))
+            .route("/{id}", web::delete().to(delete_setting))
+            .route("/{id}/history", web::get().to(get_setting_history))
+    }
+}
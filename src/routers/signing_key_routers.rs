@@ -0,0 +1,15 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::signing_key_controllers::*;
+
+pub struct SigningKeyRouter;
+
+impl Router for SigningKeyRouter {
+    fn new() -> Scope {
+        web::scope("/keys")
+            .route("", web::post().to(create_signing_key))
+            .route("", web::get().to(get_signing_keys))
+            .route("/{id}", web::delete().to(retire_signing_key))
+    }
+}
@@ -0,0 +1,18 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::site_controllers::*;
+
+pub struct SiteRouter;
+
+impl Router for SiteRouter {
+    fn new() -> Scope {
+        web::scope("/sites")
+            .route("", web::post().to(create_site))
+            .route("", web::get().to(get_sites))
+            .route("/current", web::get().to(get_current_site))
+            .route("/{id}", web::get().to(get_site))
+            .route("/{id}", web::put().to(update_site))
+            .route("/{id}", web::delete().to(delete_site))
+    }
+}
@@ -0,0 +1,33 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::taxonomy_controllers::*;
+
+pub struct TagRouter;
+
+impl Router for TagRouter {
+    fn new() -> Scope {
+        web::scope("/tags")
+            .route("", web::post().to(create_tag))
+            .route("", web::get().to(get_tags))
+            .route("/{tag}", web::get().to(get_tag))
+            .route("/{tag}", web::put().to(update_tag))
+            .route("/{tag}", web::delete().to(delete_tag))
+            .route("/{tag}/pages", web::get().to(get_tag_pages))
+    }
+}
+
+/// The categories of pages, rather than the `/category` of modules.
+pub struct PageCategoryRouter;
+
+impl Router for PageCategoryRouter {
+    fn new() -> Scope {
+        web::scope("/categories")
+            .route("", web::post().to(create_page_category))
+            .route("", web::get().to(get_page_categories))
+            .route("/{category}", web::get().to(get_page_category))
+            .route("/{category}", web::put().to(update_page_category))
+            .route("/{category}", web::delete().to(delete_page_category))
+            .route("/{category}/pages", web::get().to(get_page_category_pages))
+    }
+}
@@ -0,0 +1,14 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::theme_controllers::*;
+
+pub struct ThemeRouter;
+
+impl Router for ThemeRouter {
+    fn new() -> Scope {
+        web::scope("/themes")
+            .route("", web::get().to(get_themes))
+            .route("/active", web::put().to(set_theme))
+    }
+}
@@ -0,0 +1,13 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::trash_controllers::*;
+
+pub struct TrashRouter;
+
+impl Router for TrashRouter {
+    fn new() -> Scope {
+        web::scope("/trash")
+            .route("", web::get().to(get_trash))
+    }
+}
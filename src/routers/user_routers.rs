@@ -12,7 +12,15 @@ impl Router for UserRouter {
             .route("", web::get().to(check_login))
             .route("/login", web::post().to(login))
             .route("/logout", web::delete().to(logout))
+            .route("/forgot", web::post().to(forgot_password))
+            .route("/reset", web::post().to(reset_password))
+            .route("/2fa/setup", web::post().to(setup_two_factor))
+            .route("/2fa/enable", web::post().to(enable_two_factor))
+            .route("/2fa/disable", web::post().to(disable_two_factor))
+            .route("/2fa/recovery-codes", web::post().to(replace_recovery_codes))
             .route("/{id}", web::put().to(update_user))
+            .route("/{id}/role", web::put().to(update_user_role))
+            .route("/{id}/2fa", web::delete().to(reset_two_factor))
             .route("/{id}", web::get().to(get_user))
             .route("/{id}", web::delete().to(delete_user))
             
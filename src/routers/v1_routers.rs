@@ -0,0 +1,66 @@
+use actix_web::Scope;
+use super::{ApiVersion, Router};
+
+use super::admin_routers::AdminRouter;
+use super::api_key_routers::ApiKeyRouter;
+use super::category_routers::CategoryRouter;
+use super::comment_routers::CommentRouter;
+use super::content_type_routers::ContentTypeRouter;
+use super::delivery_token_routers::DeliveryTokenRouter;
+use super::form_routers::FormRouter;
+use super::inbound_webhook_routers::InboundWebhookRouter;
+use super::media_routers::MediaRouter;
+use super::menu_routers::MenuRouter;
+use super::module_routers::ModuleRouter;
+use super::page_routers::PageRouter;
+use super::preview_routers::PreviewRouter;
+use super::privacy_routers::PrivacyRouter;
+use super::quota_routers::QuotaRouter;
+use super::redirect_routers::RedirectRouter;
+use super::session_routers::SessionRouter;
+use super::setting_routers::SettingRouter;
+use super::signing_key_routers::SigningKeyRouter;
+use super::site_routers::SiteRouter;
+use super::taxonomy_routers::{PageCategoryRouter, TagRouter};
+use super::theme_routers::ThemeRouter;
+use super::trash_routers::TrashRouter;
+use super::user_routers::UserRouter;
+use super::webhook_routers::WebhookRouter;
+
+/// The first version of the REST API, at `/api/v1`, whose handlers are the ones in `controllers` and whose DTOs are the
+/// ones in `models`.
+pub struct V1;
+
+impl ApiVersion for V1 {
+    const NAME: &'static str = "v1";
+
+    fn routes(scope: Scope) -> Scope {
+        scope
+            .service(UserRouter::new())
+            .service(SessionRouter::new())
+            .service(PageRouter::new())
+            .service(ModuleRouter::new())
+            .service(CategoryRouter::new())
+            .service(ContentTypeRouter::new())
+            .service(SigningKeyRouter::new())
+            .service(DeliveryTokenRouter::new())
+            .service(ApiKeyRouter::new())
+            .service(InboundWebhookRouter::new())
+            .service(MediaRouter::new())
+            .service(MenuRouter::new())
+            .service(RedirectRouter::new())
+            .service(SiteRouter::new())
+            .service(TagRouter::new())
+            .service(PageCategoryRouter::new())
+            .service(CommentRouter::new())
+            .service(FormRouter::new())
+            .service(WebhookRouter::new())
+            .service(ThemeRouter::new())
+            .service(SettingRouter::new())
+            .service(PreviewRouter::new())
+            .service(PrivacyRouter::new())
+            .service(QuotaRouter::new())
+            .service(TrashRouter::new())
+            .service(AdminRouter::new())
+    }
+}
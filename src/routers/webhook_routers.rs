@@ -0,0 +1,15 @@
+use actix_web::{web, Scope};
+use super::Router;
+
+use crate::controllers::webhook_controllers::*;
+
+pub struct WebhookRouter;
+
+impl Router for WebhookRouter {
+    fn new() -> Scope {
+        web::scope("/webhooks")
+            .route("", web::post().to(create_webhook))
+            .route("", web::get().to(get_webhooks))
+            .route("/{id}", web::delete().to(delete_webhook))
+    }
+}
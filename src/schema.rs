@@ -1,10 +1,167 @@
 table! {
-    modules (uuid) {
+    api_keys (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        key_hash -> Varchar,
+        user_uuid -> Varchar,
+        scopes -> Text,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    categories (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        slug -> Varchar,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    comments (uuid) {
         uuid -> Varchar,
         page_uuid -> Varchar,
+        author_name -> Varchar,
+        author_email -> Nullable<Varchar>,
+        body -> Text,
+        status -> Varchar,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    content_types (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        json_schema -> Text,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    data_requests (uuid) {
+        uuid -> Varchar,
+        email -> Varchar,
+        request_type -> Varchar,
+        actor -> Varchar,
+        records_affected -> Bigint,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    delivery_tokens (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        token_hash -> Varchar,
+        content_types -> Nullable<Text>,
+        locales -> Nullable<Text>,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    form_submissions (uuid) {
+        uuid -> Varchar,
+        form_uuid -> Varchar,
+        data -> Text,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    forms (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        fields -> Text,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    gallery_items (uuid) {
+        uuid -> Varchar,
+        module_uuid -> Varchar,
+        media_url -> Varchar,
+        caption -> Nullable<Text>,
+        position -> Integer,
+    }
+}
+
+table! {
+    inbound_webhooks (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        secret -> Varchar,
+        target -> Varchar,
+        transform -> Text,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    jobs (uuid) {
+        uuid -> Varchar,
+        kind -> Varchar,
+        payload -> Text,
+        status -> Varchar,
+        attempts -> Integer,
+        max_attempts -> Integer,
+        last_error -> Nullable<Text>,
+        time_created -> Timestamp,
+        time_updated -> Timestamp,
+        run_at -> Timestamp,
+    }
+}
+
+table! {
+    media (uuid) {
+        uuid -> Varchar,
+        filename -> Varchar,
+        mime_type -> Varchar,
+        size -> Bigint,
+        checksum -> Varchar,
+        time_created -> Timestamp,
+        site_id -> Varchar,
+    }
+}
+
+table! {
+    menu_items (uuid) {
+        uuid -> Varchar,
+        menu_uuid -> Varchar,
+        parent_item -> Nullable<Varchar>,
+        title -> Varchar,
+        page_uuid -> Nullable<Varchar>,
+        url -> Nullable<Varchar>,
+        position -> Integer,
+    }
+}
+
+table! {
+    menus (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    modules (uuid) {
+        uuid -> Varchar,
+        page_uuid -> Nullable<Varchar>,
         category_uuid -> Nullable<Varchar>,
         title -> Varchar,
         content -> Text,
+        encrypted -> Bool,
+        compressed -> Bool,
+        visible_to -> Nullable<Text>,
+        deleted_at -> Nullable<Timestamp>,
+        order_index -> Integer,
+        module_type -> Varchar,
+        site_id -> Varchar,
+        version -> Integer,
     }
 }
 
@@ -16,6 +173,41 @@ table! {
     }
 }
 
+table! {
+    page_categories (page_uuid, category_uuid) {
+        page_uuid -> Varchar,
+        category_uuid -> Varchar,
+    }
+}
+
+table! {
+    page_revisions (uuid) {
+        uuid -> Varchar,
+        page_uuid -> Varchar,
+        content -> Text,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    page_rollouts (uuid) {
+        uuid -> Varchar,
+        page_uuid -> Varchar,
+        percentage -> Integer,
+        content -> Text,
+        control_views -> Bigint,
+        canary_views -> Bigint,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    page_tags (page_uuid, tag_uuid) {
+        page_uuid -> Varchar,
+        tag_uuid -> Varchar,
+    }
+}
+
 table! {
     pages (uuid) {
         uuid -> Varchar,
@@ -23,6 +215,111 @@ table! {
         page_url -> Varchar,
         page_title -> Varchar,
         time_created -> Timestamp,
+        status -> Varchar,
+        publish_at -> Nullable<Timestamp>,
+        deleted_at -> Nullable<Timestamp>,
+        parent_page -> Nullable<Varchar>,
+        meta_description -> Nullable<Text>,
+        meta_keywords -> Nullable<Varchar>,
+        og_image -> Nullable<Varchar>,
+        noindex -> Bool,
+        locale -> Nullable<Varchar>,
+        translation_group -> Nullable<Varchar>,
+        site_id -> Varchar,
+        version -> Integer,
+        visibility -> Varchar,
+    }
+}
+
+table! {
+    password_resets (uuid) {
+        uuid -> Varchar,
+        user_uuid -> Varchar,
+        token_hash -> Varchar,
+        time_created -> Timestamp,
+        time_expires -> Timestamp,
+    }
+}
+
+table! {
+    recovery_codes (uuid) {
+        uuid -> Varchar,
+        user_uuid -> Varchar,
+        code_hash -> Varchar,
+        time_created -> Timestamp,
+    }
+}
+
+table! {
+    redirects (uuid) {
+        uuid -> Varchar,
+        source -> Varchar,
+        target -> Varchar,
+        status_code -> Integer,
+        time_created -> Timestamp,
+        site_id -> Varchar,
+    }
+}
+
+table! {
+    search_documents (page_uuid) {
+        page_uuid -> Varchar,
+        page_url -> Varchar,
+        page_name -> Varchar,
+        page_title -> Varchar,
+        content -> Text,
+        time_indexed -> Timestamp,
+    }
+}
+
+table! {
+    setting_changes (uuid) {
+        uuid -> Varchar,
+        setting_key -> Varchar,
+        old_value -> Nullable<Text>,
+        new_value -> Nullable<Text>,
+        actor -> Varchar,
+        time_created -> Timestamp,
+        site_id -> Varchar,
+    }
+}
+
+table! {
+    settings (site_id, setting_key) {
+        setting_key -> Varchar,
+        setting_value -> Text,
+        site_id -> Varchar,
+    }
+}
+
+table! {
+    signing_keys (kid) {
+        kid -> Varchar,
+        secret -> Varchar,
+        active -> Bool,
+        time_created -> Timestamp,
+        time_retired -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    sites (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        hostname -> Nullable<Varchar>,
+        time_created -> Timestamp,
+        max_pages -> Nullable<Bigint>,
+        max_modules -> Nullable<Bigint>,
+        max_media_bytes -> Nullable<Bigint>,
+    }
+}
+
+table! {
+    tags (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        slug -> Varchar,
+        time_created -> Timestamp,
     }
 }
 
@@ -32,16 +329,72 @@ table! {
         username -> Varchar,
         password -> Varchar,
         token -> Nullable<Varchar>,
+        email -> Nullable<Varchar>,
+        role -> Varchar,
+        oidc_subject -> Nullable<Varchar>,
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled -> Bool,
+        totp_last_step -> Nullable<BigInt>,
+    }
+}
+
+table! {
+    webhooks (uuid) {
+        uuid -> Varchar,
+        name -> Varchar,
+        url -> Text,
+        secret -> Varchar,
+        events -> Text,
+        time_created -> Timestamp,
     }
 }
 
+joinable!(api_keys -> users (user_uuid));
+joinable!(comments -> pages (page_uuid));
+joinable!(form_submissions -> forms (form_uuid));
 joinable!(module_category -> pages (page_uuid));
 joinable!(modules -> module_category (category_uuid));
 joinable!(modules -> pages (page_uuid));
+joinable!(page_categories -> categories (category_uuid));
+joinable!(page_categories -> pages (page_uuid));
+joinable!(page_revisions -> pages (page_uuid));
+joinable!(page_rollouts -> pages (page_uuid));
+joinable!(page_tags -> pages (page_uuid));
+joinable!(page_tags -> tags (tag_uuid));
+joinable!(password_resets -> users (user_uuid));
+joinable!(recovery_codes -> users (user_uuid));
 
 allow_tables_to_appear_in_same_query!(
+    api_keys,
+    categories,
+    comments,
+    content_types,
+    data_requests,
+    delivery_tokens,
+    form_submissions,
+    forms,
+    gallery_items,
+    inbound_webhooks,
+    jobs,
+    media,
+    menu_items,
+    menus,
     modules,
     module_category,
+    page_categories,
+    page_revisions,
+    page_rollouts,
+    page_tags,
     pages,
+    password_resets,
+    recovery_codes,
+    redirects,
+    search_documents,
+    setting_changes,
+    settings,
+    signing_keys,
+    sites,
+    tags,
     users,
+    webhooks,
 );
@@ -0,0 +1,32 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::{header, HeaderName, HeaderValue};
+
+/// Where every version of the REST API is mounted, each under its name, like `/api/v1`.
+pub const API_PREFIX: &str = "/api";
+/// Where v1 was before the API had versions. It is only served while `APP_LEGACY_API` is on.
+pub const LEGACY_PREFIX: &str = "/v1";
+
+/// Where the version named `version` is mounted.
+pub fn mount(version: &str) -> String {
+    format!("{}/{}", API_PREFIX, version)
+}
+
+/// The path of a request to the REST API within its version, like `/user/login` for `/api/v1/user/login` and for the legacy
+/// `/v1/user/login`, so that what is checked of a route is the same on every mount. `None` for a path outside of the API.
+pub fn api_path(path: &str) -> Option<&str> {
+    if let Some(rest) = path.strip_prefix(API_PREFIX).and_then(|rest| rest.strip_prefix('/')) {
+        return Some(rest.find('/').map_or("", |i| &rest[i..]));
+    }
+
+    path.strip_prefix(LEGACY_PREFIX).filter(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Marks a response of the legacy routes as deprecated, linking to the same route under `version`'s mount.
+pub fn deprecate<B>(res: &mut ServiceResponse<B>, version: &str) {
+    let successor = format!("<{}{}>; rel=\"successor-version\"", mount(version), api_path(res.request().path()).unwrap_or_default());
+
+    res.headers_mut().insert(HeaderName::from_static("deprecation"), HeaderValue::from_static("true"));
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        res.headers_mut().append(header::LINK, link);
+    }
+}
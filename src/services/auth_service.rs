@@ -1,14 +1,24 @@
-use actix_web::{dev::Payload, http::HeaderValue, web, FromRequest, HttpRequest};
+use std::marker::PhantomData;
+
+use actix_session::UserSession;
+use actix_web::{dev::Payload, http::HeaderValue, web, FromRequest, HttpMessage, HttpRequest};
 use argon2::{Argon2, PasswordHasher, password_hash::SaltString};
-use diesel::MysqlConnection;
-use futures::{future::LocalBoxFuture, Future};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use rand_core::OsRng;
+use futures::future::LocalBoxFuture;
+use jsonwebtoken::{decode, decode_header, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand_core::{OsRng, RngCore};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use super::delivery_service::hash_token;
 use super::errors_service::CustomHttpError;
-use crate::models::{pool_handler, user_models, Model, MySQLPool};
+use super::rate_limit_service;
+use super::session_service::{self, SessionStore, SESSION_COOKIE};
+use crate::models::api_key_models::ApiKey;
+use crate::models::config_models::LocalConfig;
+use crate::models::signing_key_models::SigningKey;
+use crate::models::user_models::UserRole;
+use crate::models::{user_models, with_primary_connection, DbConnection, DbPool, Model};
 
 #[derive(Error, Debug)]
 pub enum CryptoError {
@@ -23,7 +33,13 @@ pub enum CryptoError {
     #[error("No auth header present.")]
     NoAuthHeader,
     #[error("Password operation failed.")]
-    OperationFail
+    OperationFail,
+    #[error("The token was signed with an unknown or retired key.")]
+    UnknownKey,
+    #[error("No encryption key is configured.")]
+    NoEncryptionKey,
+    #[error("No JWT key is configured.")]
+    NoJwtKey
 }
 
 impl From<jsonwebtoken::errors::Error> for CryptoError {
@@ -42,38 +58,90 @@ impl From<argon2::password_hash::Error> for CryptoError {
     }
 }
 
-pub fn encrypt(claim: Claims) -> Result<String, CryptoError> {
-    let encoded_token = encode(
-        &Header::default(),
-        &claim,
-        &EncodingKey::from_secret(std::env::var("APP_JWT_KEY").unwrap().as_bytes()),
-    )?;
+/// Signs the claim with the newest active signing key, putting its `kid` in the header.
+/// If no signing keys have been added yet, `APP_JWT_KEY` is used with no `kid`.
+pub fn encrypt(claim: Claims, db: &DbConnection) -> Result<String, CryptoError> {
+    sign(&claim, db)
+}
+
+/// Verifies the token with the key named by its `kid`, which must still be active.
+/// Tokens without a `kid` are verified with `APP_JWT_KEY`, unless `APP_JWT_KEY_TOKENS` is off and a signing key is active.
+pub fn decrypt(jwt: &String, db: &DbConnection) -> Result<Claims, CryptoError> {
+    verify(jwt, db)
+}
+
+/// `APP_JWT_KEY`, from the config that was loaded.
+fn jwt_key() -> Result<String, CryptoError> {
+    LocalConfig::loaded().map(|conf| conf.jwt_key.clone()).filter(|key| !key.is_empty()).ok_or(CryptoError::NoJwtKey)
+}
+
+/// The key tokens without a `kid` are verified with. Once a signing key is active, `APP_JWT_KEY_TOKENS` can retire
+/// them the way retiring a key does the tokens it signed.
+fn legacy_key(db: &DbConnection) -> Result<String, CryptoError> {
+    let accepted = LocalConfig::loaded().is_none_or(|conf| conf.jwt_key_tokens);
+    if !accepted && SigningKey::read_newest_active(db).or(Err(CryptoError::Unknown))?.is_some() {
+        return Err(CryptoError::UnknownKey);
+    }
+
+    jwt_key()
+}
+
+/// `encrypt` for claims other than a login's, like those of a preview link.
+pub fn sign<C: Serialize>(claim: &C, db: &DbConnection) -> Result<String, CryptoError> {
+    let (header, secret) = match SigningKey::read_newest_active(db).or(Err(CryptoError::Unknown))? {
+        Some(key) => (
+            Header {
+                kid: Some(key.kid),
+                ..Header::default()
+            },
+            key.secret,
+        ),
+        None => (Header::default(), jwt_key()?),
+    };
+
+    let encoded_token = encode(&header, claim, &EncodingKey::from_secret(secret.as_bytes()))?;
 
     Ok(encoded_token)
 }
 
-pub fn decrypt(jwt: &String) -> Result<Claims, CryptoError> {
-    let decoded_token = decode::<Claims>(
+/// `decrypt` for claims other than a login's. A token only verifies as the claims it was signed with,
+/// so a preview link can't be used to log in.
+pub fn verify<C: DeserializeOwned>(jwt: &str, db: &DbConnection) -> Result<C, CryptoError> {
+    let secret = match decode_header(jwt)?.kid {
+        Some(kid) => SigningKey::read_active(kid, db).or(Err(CryptoError::UnknownKey))?.secret,
+        None => legacy_key(db)?,
+    };
+
+    let decoded_token = decode::<C>(
         jwt,
-        &DecodingKey::from_secret(std::env::var("APP_JWT_KEY").unwrap().as_bytes()),
+        &DecodingKey::from_secret(secret.as_bytes()),
         &Validation::default(),
     )?;
 
     Ok(decoded_token.claims)
 }
 
+/// Generates a random secret for a new signing key.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 64];
+    OsRng.fill_bytes(&mut bytes);
+
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Returns the user the token belongs to when it is the one they are logged in with.
 pub fn compare(
     token: &Claims,
     enc_token: &String,
-    pool: &MysqlConnection,
-) -> Result<(), CryptoError> {
+    pool: &DbConnection,
+) -> Result<user_models::User, CryptoError> {
     if let Ok(user) = user_models::User::read_one(token.sub.clone(), &pool) {
         if user.token.is_none() {
             return Err(CryptoError::NotLoggedIn);
         }
         // verify against the encrypted version of the token.
         if user.token == Some(enc_token.clone()) {
-            return Ok(());
+            return Ok(user);
         } else {
             return Err(CryptoError::FailedComparison);
         };
@@ -95,6 +163,9 @@ pub fn encrypt_password(password: &String) -> Result<String, CryptoError> {
 pub struct Claims {
     pub exp: usize,
     pub sub: String,
+    /// Read from the user on every request rather than kept in the token, so that a new role applies straight away.
+    #[serde(skip)]
+    pub role: UserRole,
 }
 
 impl FromRequest for Claims {
@@ -103,30 +174,52 @@ impl FromRequest for Claims {
     type Config = ();
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let pool = req.app_data::<web::Data<MySQLPool>>().unwrap().to_owned();
-        // TODO this needs to not be blocking. not terribly important as only one or two users will be performing authenticated actions.
-        let mysql_pool = pool_handler(pool).unwrap();
-        let auth_header = req.headers().get("Authorization");
+        let pool = req.app_data::<web::Data<DbPool>>().unwrap().to_owned();
+        // reads don't need the CSRF token, so a session is enough. Writes have already been checked by `RequireLogin`.
+        let auth_header = req.headers().get("Authorization").cloned().or_else(|| {
+            let store = req.app_data::<web::Data<dyn SessionStore>>();
+            session_service::logged_in(&req.get_session(), store).and_then(|data| data.auth_header().ok())
+        });
+
+        // what the rate limits tell the client apart by, the header or else the session cookie.
+        let credential = req
+            .headers()
+            .get("Authorization")
+            .and_then(|auth| auth.to_str().ok())
+            .map(str::to_string)
+            .or_else(|| req.cookie(SESSION_COOKIE).map(|cookie| cookie.value().to_string()));
+        let req = req.clone();
 
         match auth_header {
-            Some(auth) => {
-                let fut = authenticate(auth, &mysql_pool);
-                Box::pin(fut)
-            }
+            Some(auth) => Box::pin(async move {
+                let claims = with_primary_connection(pool, move |db| authenticate(&auth, db)).await?;
+                trace_user(&claims);
+                if let Some(credential) = credential {
+                    rate_limit_service::verified(&req, credential);
+                }
+
+                Ok(claims)
+            }),
             _ => Box::pin(async { Err(CryptoError::NoAuthHeader.into()) }),
         }
     }
 }
 
-pub fn authenticate(
-    auth_header: &HeaderValue,
-    db: &MysqlConnection,
-) -> impl Future<Output = Result<Claims, CustomHttpError>> {
+/// Puts the user on the span of the request they made (see `RequestSpan`), once they are known.
+pub fn trace_user(claims: &Claims) {
+    tracing::Span::current().record("user", &claims.sub.as_str());
+}
+
+pub fn authenticate(auth_header: &HeaderValue, db: &DbConnection) -> Result<Claims, CustomHttpError> {
     let encrypted_token = std::str::from_utf8(auth_header.as_bytes())
         .unwrap()
         .to_string();
 
-    let decrypted_token = decrypt(&encrypted_token);
+    if let Some(key) = encrypted_token.strip_prefix("ApiKey ") {
+        return authenticate_api_key(key.trim(), db);
+    }
+
+    let decrypted_token = decrypt(&encrypted_token, db);
 
     // done this way to pass up the error.
     let mut logged_in = Err(CryptoError::NotLoggedIn);
@@ -134,10 +227,63 @@ pub fn authenticate(
         logged_in = compare(&decrypted_token, &encrypted_token, db);
     }
 
-    async move {
-        match logged_in {
-            Ok(_) => Ok(decrypted_token?),
-            Err(e) => Err(e.into()),
-        }
+    match logged_in {
+        Ok(user) => Ok(Claims {
+            role: user.role(),
+            ..decrypted_token?
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Acts as the user who made the key, with no more than the key's scopes allow.
+/// Keys don't expire, they last until they or their user are deleted.
+fn authenticate_api_key(key: &str, db: &DbConnection) -> Result<Claims, CustomHttpError> {
+    let (api_key, user) = ApiKey::read_by_hash(hash_token(key), db).or(Err(CryptoError::NotLoggedIn))?;
+
+    Ok(Claims {
+        exp: usize::MAX,
+        role: api_key.role(user.role()),
+        sub: user.username,
+    })
+}
+
+/// The least role a `RequireRole` lets through.
+pub trait MinimumRole {
+    const ROLE: UserRole;
+}
+
+/// Users who can change content.
+pub struct Editor;
+/// Users who can change everything else too.
+pub struct Admin;
+
+impl MinimumRole for Editor {
+    const ROLE: UserRole = UserRole::Editor;
+}
+
+impl MinimumRole for Admin {
+    const ROLE: UserRole = UserRole::Admin;
+}
+
+/// Like `Claims`, but responds with a 403 when the user's role is below `R`, like `RequireRole<Editor>`.
+pub struct RequireRole<R: MinimumRole>(pub Claims, PhantomData<R>);
+
+impl<R: MinimumRole + 'static> FromRequest for RequireRole<R> {
+    type Error = CustomHttpError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let claims = Claims::from_request(req, payload);
+
+        Box::pin(async move {
+            let claims = claims.await?;
+
+            match claims.role >= R::ROLE {
+                true => Ok(RequireRole(claims, PhantomData)),
+                false => Err(CustomHttpError::Forbidden),
+            }
+        })
     }
 }
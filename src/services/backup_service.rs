@@ -0,0 +1,127 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use actix_web::web;
+use chrono::Utc;
+use thiserror::Error;
+
+use super::cron_service::CronSchedule;
+use super::export_service;
+use super::media_store_service::{self, LocalStore, MediaStore, MediaStoreError, S3Store};
+use super::shutdown_service::Shutdown;
+use crate::models::config_models::LocalConfig;
+use crate::models::{DbConnection, DbPool};
+
+/// Where backups are written, and kept unless `APP_BACKUP_STORE` says otherwise.
+const DEFAULT_BACKUP_DIR: &str = "./backups";
+/// What backups are named, followed by when they were made, so that they sort oldest first and nothing else is pruned.
+const FILE_PREFIX: &str = "radical-";
+const FILE_SUFFIX: &str = ".json";
+/// Backups in a bucket are kept under this, away from the uploads that may share it.
+const S3_PREFIX: &str = "backups/";
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("The content could not be read: {0}")]
+    Content(String),
+    #[error("The backup could not be written: {0}")]
+    Write(#[from] io::Error),
+    #[error(transparent)]
+    Store(#[from] MediaStoreError),
+}
+
+/// Writes backups of the content, which are dumps like `GET /api/v1/admin/export` makes of every site, and prunes the old ones.
+/// They go to `APP_BACKUP_DIR`, or to the S3 bucket of `APP_S3_BUCKET` with `APP_BACKUP_STORE=s3`, where the newest
+/// `APP_BACKUP_KEEP` are kept. A backup is restored by importing it with `POST /api/v1/admin/import`.
+pub struct Backups {
+    dir: PathBuf,
+    store: Arc<dyn MediaStore>,
+    prefix: &'static str,
+    keep: usize,
+}
+
+impl Backups {
+    pub fn new(conf: &LocalConfig) -> Result<Self, MediaStoreError> {
+        let dir = PathBuf::from(conf.backup_dir.as_deref().unwrap_or(DEFAULT_BACKUP_DIR));
+        // a bucket keeps its copies in the directory, like it does of uploads in `APP_MEDIA_DIR`.
+        let (store, prefix): (Arc<dyn MediaStore>, _) = match conf.backup_store.as_deref() {
+            None | Some("local") => (Arc::new(LocalStore::new(dir.clone())), ""),
+            Some("s3") => (Arc::new(S3Store::from_conf(conf, dir.clone())?), S3_PREFIX),
+            Some(_) => return Err(MediaStoreError::Config),
+        };
+
+        Ok(Self {
+            dir,
+            store,
+            prefix,
+            keep: conf.backup_keep,
+        })
+    }
+
+    /// Writes a backup named after now, like `radical-20261014T030000Z.json`, then prunes. Returns the backup's key in the store.
+    pub fn backup(&self, db: &DbConnection) -> Result<String, BackupError> {
+        let dump = export_service::export(db).map_err(|e| BackupError::Content(e.descriptor()))?;
+        let key = format!("{}{}{}{}", self.prefix, FILE_PREFIX, Utc::now().format("%Y%m%dT%H%M%SZ"), FILE_SUFFIX);
+
+        let partial = media_store_service::staging_in(&self.dir)?;
+        let written = File::create(&partial)
+            .and_then(|file| serde_json::to_writer(BufWriter::new(file), &dump).map_err(io::Error::from));
+        if let Err(e) = written {
+            fs::remove_file(&partial).ok();
+            return Err(e.into());
+        }
+        self.store.put(&key, &partial, "application/json")?;
+
+        self.prune()?;
+
+        Ok(key)
+    }
+
+    /// Deletes every backup but the newest `APP_BACKUP_KEEP`, or none when that is 0. Returns how many were deleted.
+    pub fn prune(&self) -> Result<usize, MediaStoreError> {
+        if self.keep == 0 {
+            return Ok(0);
+        }
+
+        let name = |key: &str| key.strip_prefix(self.prefix).unwrap_or(key).to_string();
+        let mut backups: Vec<String> = self
+            .store
+            .list(self.prefix)?
+            .into_iter()
+            .filter(|key| name(key).starts_with(FILE_PREFIX) && key.ends_with(FILE_SUFFIX))
+            .collect();
+        backups.sort();
+
+        let old = backups.len().saturating_sub(self.keep);
+        for key in &backups[..old] {
+            self.store.delete(key)?;
+        }
+
+        Ok(old)
+    }
+}
+
+/// Writes a backup every time `schedule` comes around, until the shutdown begins. A backup that is being written then is finished.
+pub fn run(pool: DbPool, backups: Backups, schedule: CronSchedule, shutdown: web::Data<Shutdown>) {
+    loop {
+        let now = Utc::now().naive_utc();
+        let next = match schedule.next_after(now) {
+            Some(next) => next,
+            None => return,
+        };
+
+        if !shutdown.sleep((next - now).to_std().unwrap_or_default()) {
+            return;
+        }
+
+        match pool.get() {
+            Ok(conn) => match backups.backup(&conn) {
+                Ok(key) => tracing::info!("Backed up the content to {}.", key),
+                Err(e) => tracing::error!("backup error: {}", e),
+            },
+            Err(e) => tracing::error!("backup error: {:?}", e),
+        }
+    }
+}
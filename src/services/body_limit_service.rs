@@ -0,0 +1,52 @@
+use actix_web::dev::Payload;
+use actix_web::error::{JsonPayloadError, PayloadError};
+use actix_web::{web, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+
+use crate::models::config_models::LocalConfig;
+use crate::services::errors_service::CustomHttpError;
+
+/// JSON bodies of at most `limit` bytes, with one that is longer refused as `CustomHttpError::TooLarge` rather than
+/// with actix-web's plain text. Every route takes `APP_JSON_MAX_SIZE`, but the ones that set their own, like imports.
+pub fn json(limit: u64) -> web::JsonConfig {
+    web::JsonConfig::default().limit(limit as usize).error_handler(move |e, _| match e {
+        JsonPayloadError::Overflow | JsonPayloadError::Payload(PayloadError::Overflow) => {
+            CustomHttpError::TooLarge { limit }.into()
+        }
+        e => e.into(),
+    })
+}
+
+/// What `web::Bytes` reads bodies up to, `APP_BODY_MAX_SIZE`, which `Body` refuses longer ones with.
+pub fn payload(conf: &LocalConfig) -> web::PayloadConfig {
+    web::PayloadConfig::new(conf.body_max_size as usize)
+}
+
+/// The raw body of a request that isn't only JSON, like a form submission, of at most `APP_BODY_MAX_SIZE` bytes.
+pub struct Body(pub web::Bytes);
+
+impl std::ops::Deref for Body {
+    type Target = web::Bytes;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromRequest for Body {
+    type Error = CustomHttpError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let limit = req.app_data::<web::Data<LocalConfig>>().map(|conf| conf.body_max_size).unwrap_or_default();
+        let bytes = web::Bytes::from_request(req, payload);
+
+        Box::pin(async move {
+            bytes.await.map(Body).map_err(|e| match e.as_error::<PayloadError>() {
+                Some(PayloadError::Overflow) => CustomHttpError::TooLarge { limit },
+                _ => CustomHttpError::BadRequest,
+            })
+        })
+    }
+}
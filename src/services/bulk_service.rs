@@ -0,0 +1,120 @@
+use diesel::Connection;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::duplicate_service::DuplicateWarning;
+use super::errors_service::CustomHttpError;
+use super::validation_service::{FieldError, Validate};
+use crate::models::module_models::MutModule;
+use crate::models::page_models::MutPage;
+use crate::models::DbConnection;
+
+/// The most operations one request can make, so that a bulk request doesn't hold its transaction open for long.
+pub const MAX_OPERATIONS: usize = 500;
+
+/// One change of a bulk request, like `{ "op": "update", "id": "..", "data": { .. } }`.
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+#[aliases(PageOperation = BulkOperation<MutPage>, ModuleOperation = BulkOperation<MutModule>)]
+pub enum BulkOperation<T> {
+    // the aliases don't reach into variants, so `data` is described as an object rather than as a schema named `T`.
+    Create {
+        /// The page or module, the same as the body of its `POST`.
+        #[schema(value_type = Object)]
+        data: T,
+    },
+    /// Refused like a `PUT` when `version` is sent and it was changed since, but not when it is left out.
+    Update {
+        id: String,
+        /// The page or module, the same as the body of its `PUT`.
+        #[schema(value_type = Object)]
+        data: T,
+        #[serde(default)]
+        version: Option<i32>,
+    },
+    /// Moves it to the trash, or removes it for good when `permanent` is set, like `?permanent=true` does.
+    Delete {
+        id: String,
+        #[serde(default)]
+        permanent: bool,
+    },
+}
+
+impl<T> BulkOperation<T> {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Create { .. } => "create",
+            Self::Update { .. } => "update",
+            Self::Delete { .. } => "delete",
+        }
+    }
+}
+
+/// What one operation of a bulk request did.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct BulkResult {
+    /// Where the operation was in the request.
+    pub index: usize,
+    pub op: String,
+    /// The uuid of what was changed, which is new for a `create`.
+    pub id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<DuplicateWarning>,
+}
+
+/// Checks every operation before any of them is made, so that one 422 lists the problems of all of them.
+/// The fields of an operation are named by where it is in the request, like `3.page_url`.
+pub fn validate<T: Validate>(operations: &[BulkOperation<T>]) -> Result<(), CustomHttpError> {
+    if operations.len() > MAX_OPERATIONS {
+        return Err(CustomHttpError::Invalid(vec![FieldError {
+            field: String::new(),
+            message: format!("can't have more than {} operations", MAX_OPERATIONS),
+        }]));
+    }
+
+    let errors: Vec<FieldError> = operations
+        .iter()
+        .enumerate()
+        .flat_map(|(index, operation)| {
+            let errors = match operation {
+                BulkOperation::Create { data } | BulkOperation::Update { data, .. } => data.errors(),
+                BulkOperation::Delete { .. } => Vec::new(),
+            };
+
+            errors.into_iter().map(move |error| FieldError {
+                field: format!("{}.{}", index, error.field),
+                message: error.message,
+            })
+        })
+        .collect();
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(CustomHttpError::Invalid(errors)),
+    }
+}
+
+/// Makes the operations in order inside one transaction, so that either all of them are saved or none are.
+/// `apply` makes one, returning the uuid of what it changed, any warnings, and whatever has to be done once they're saved.
+/// The first one that fails rolls back the rest, and its error says which one it was.
+pub fn run<T, C>(
+    operations: Vec<BulkOperation<T>>,
+    db: &DbConnection,
+    mut apply: impl FnMut(BulkOperation<T>) -> Result<(String, Vec<DuplicateWarning>, C), CustomHttpError>,
+) -> Result<Vec<(BulkResult, C)>, CustomHttpError> {
+    db.transaction(|| {
+        operations
+            .into_iter()
+            .enumerate()
+            .map(|(index, operation)| {
+                let op = operation.name().to_string();
+                let (id, warnings, done) = apply(operation).map_err(|error| CustomHttpError::Operation {
+                    index,
+                    error: Box::new(error),
+                })?;
+
+                Ok((BulkResult { index, op, id, warnings }, done))
+            })
+            .collect()
+    })
+}
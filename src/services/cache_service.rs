@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use redis::Commands;
+use thiserror::Error;
+
+use crate::models::config_models::LocalConfig;
+
+/// Every key a backend stores is prefixed with this, so a shared Redis can be used by more than one thing.
+const KEY_PREFIX: &str = "radical:";
+
+#[derive(Error, Debug)]
+pub enum CacheError {
+    #[error("The cache backend is not configured correctly.")]
+    Config,
+    #[error("The cache backend could not be reached: {0}")]
+    Unavailable(String),
+}
+
+impl From<redis::RedisError> for CacheError {
+    fn from(e: redis::RedisError) -> Self {
+        CacheError::Unavailable(e.to_string())
+    }
+}
+
+/// Somewhere cached values can be kept. A `ttl` of `None` keeps the value until it is deleted or cleared.
+/// Everything that caches goes through this, so that a deployment can keep its caches in memory or share them through Redis.
+pub trait CacheBackend: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<String>, CacheError>;
+    fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), CacheError>;
+    fn delete(&self, key: &str) -> Result<(), CacheError>;
+    /// Removes every key that starts with `prefix`, or everything if it is empty.
+    fn clear(&self, prefix: &str) -> Result<(), CacheError>;
+    /// Adds one to a counter, creating it with `ttl` if it doesn't exist, and returns the new count.
+    fn increment(&self, key: &str, ttl: Duration) -> Result<u64, CacheError>;
+}
+
+/// Every this many values that are set, the expired ones that were never read again are dropped.
+const PRUNE_EVERY: usize = 1024;
+
+/// Kept in the memory of this process, so it isn't shared between instances. Expired values are dropped as they are read,
+/// and every so often as others are set, so that values only ever set once, like cached responses, don't pile up.
+#[derive(Default)]
+pub struct MemoryCache {
+    values: RwLock<HashMap<String, (String, Option<Instant>)>>,
+    sets: AtomicUsize,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn is_live(expires: &Option<Instant>) -> bool {
+        expires.is_none_or(|expires| expires > Instant::now())
+    }
+}
+
+impl CacheBackend for MemoryCache {
+    fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        if let Some((value, expires)) = self.values.read().unwrap().get(key) {
+            if Self::is_live(expires) {
+                return Ok(Some(value.clone()));
+            }
+        }
+
+        self.values.write().unwrap().remove(key);
+
+        Ok(None)
+    }
+
+    fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), CacheError> {
+        let expires = ttl.map(|ttl| Instant::now() + ttl);
+        let mut values = self.values.write().unwrap();
+        if self.sets.fetch_add(1, Ordering::Relaxed) % PRUNE_EVERY == PRUNE_EVERY - 1 {
+            values.retain(|_, (_, expires)| Self::is_live(expires));
+        }
+        values.insert(key.to_string(), (value.to_string(), expires));
+
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), CacheError> {
+        self.values.write().unwrap().remove(key);
+
+        Ok(())
+    }
+
+    fn clear(&self, prefix: &str) -> Result<(), CacheError> {
+        self.values.write().unwrap().retain(|key, _| !key.starts_with(prefix));
+
+        Ok(())
+    }
+
+    fn increment(&self, key: &str, ttl: Duration) -> Result<u64, CacheError> {
+        let mut values = self.values.write().unwrap();
+
+        let count = match values.get(key) {
+            Some((count, expires)) if Self::is_live(expires) => {
+                let count = count.parse::<u64>().unwrap_or(0) + 1;
+                let expires = *expires;
+                values.insert(key.to_string(), (count.to_string(), expires));
+                count
+            }
+            _ => {
+                values.insert(key.to_string(), (String::from("1"), Some(Instant::now() + ttl)));
+                1
+            }
+        };
+
+        Ok(count)
+    }
+}
+
+/// Shared by every instance pointed at the same Redis.
+/// One connection is kept open and reopened whenever a command fails, so a Redis restart doesn't need a restart here too.
+pub struct RedisCache {
+    client: redis::Client,
+    connection: Mutex<Option<redis::Connection>>,
+}
+
+impl RedisCache {
+    pub fn new(url: &str) -> Result<Self, CacheError> {
+        let client = redis::Client::open(url).map_err(|_| CacheError::Config)?;
+
+        Ok(Self {
+            client,
+            connection: Mutex::new(None),
+        })
+    }
+
+    fn key(key: &str) -> String {
+        format!("{}{}", KEY_PREFIX, key)
+    }
+
+    fn with_connection<T>(&self, f: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>) -> Result<T, CacheError> {
+        let mut connection = self.connection.lock().unwrap();
+
+        if connection.is_none() {
+            *connection = Some(self.client.get_connection()?);
+        }
+
+        let res = f(connection.as_mut().unwrap());
+        if res.is_err() {
+            *connection = None;
+        }
+
+        Ok(res?)
+    }
+}
+
+impl CacheBackend for RedisCache {
+    fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        self.with_connection(|c| c.get(Self::key(key)))
+    }
+
+    fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), CacheError> {
+        self.with_connection(|c| match ttl {
+            // Redis rejects an expiry of 0.
+            Some(ttl) => c.set_ex(Self::key(key), value, ttl.as_secs().max(1) as usize),
+            None => c.set(Self::key(key), value),
+        })
+    }
+
+    fn delete(&self, key: &str) -> Result<(), CacheError> {
+        self.with_connection(|c| c.del(Self::key(key)))
+    }
+
+    fn clear(&self, prefix: &str) -> Result<(), CacheError> {
+        self.with_connection(|c| {
+            let keys: Vec<String> = c.scan_match::<_, String>(format!("{}*", Self::key(prefix)))?.collect();
+
+            match keys.is_empty() {
+                true => Ok(()),
+                false => c.del(keys),
+            }
+        })
+    }
+
+    fn increment(&self, key: &str, ttl: Duration) -> Result<u64, CacheError> {
+        self.with_connection(|c| {
+            let count: u64 = c.incr(Self::key(key), 1)?;
+            if count == 1 {
+                c.expire::<_, ()>(Self::key(key), ttl.as_secs().max(1) as usize)?;
+            }
+
+            Ok(count)
+        })
+    }
+}
+
+/// The backend chosen by `APP_CACHE_BACKEND`, which is memory unless `redis` is asked for.
+pub fn backend(conf: &LocalConfig) -> Result<Arc<dyn CacheBackend>, CacheError> {
+    match conf.cache_backend.as_deref() {
+        None | Some("memory") => Ok(Arc::new(MemoryCache::new())),
+        Some("redis") => {
+            let url = conf.redis_url.as_deref().ok_or(CacheError::Config)?;
+            Ok(Arc::new(RedisCache::new(url)?))
+        }
+        Some(_) => Err(CacheError::Config),
+    }
+}
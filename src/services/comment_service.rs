@@ -0,0 +1,19 @@
+use super::cache_service::CacheBackend;
+use super::errors_service::CustomHttpError;
+use super::rate_limit_service::check_hourly;
+use crate::models::comment_models::NewComment;
+use crate::models::config_models::LocalConfig;
+
+/// Whether a new comment looks like spam, which is when the hidden `website` field was filled in,
+/// or when it links to more places than `APP_COMMENT_MAX_LINKS`. Spam is kept for moderators, but never shown.
+pub fn is_spam(new: &NewComment, conf: &LocalConfig) -> bool {
+    let honeypot = new.website.as_deref().is_some_and(|w| !w.trim().is_empty());
+    let links = new.body.matches("http://").count() + new.body.matches("https://").count();
+
+    honeypot || links > conf.comment_max_links
+}
+
+/// Counts a comment from `client`, like its IP, against `APP_COMMENTS_PER_HOUR`, with a 429 once it is over.
+pub fn check_rate(client: &str, cache: &dyn CacheBackend, conf: &LocalConfig) -> Result<(), CustomHttpError> {
+    check_hourly("comments", client, conf.comments_per_hour, cache)
+}
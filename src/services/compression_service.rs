@@ -0,0 +1,25 @@
+use std::error::Error;
+
+use crate::models::config_models::LocalConfig;
+
+/// zstd's default level, which is a good tradeoff for content that is written rarely and read often.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Content at least this many bytes long gets compressed. Compression is off if `APP_COMPRESSION_THRESHOLD` is unset.
+pub fn threshold() -> Option<usize> {
+    LocalConfig::loaded().and_then(|conf| conf.compression_threshold)
+}
+
+/// Compresses with zstd and base64 encodes the result so that it fits in the same text column as plain content.
+pub fn compress(content: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let compressed = zstd::encode_all(content.as_bytes(), COMPRESSION_LEVEL)?;
+
+    Ok(base64::encode(compressed))
+}
+
+pub fn decompress(content: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let compressed = base64::decode(content)?;
+    let decompressed = zstd::decode_all(compressed.as_slice())?;
+
+    Ok(String::from_utf8(decompressed)?)
+}
@@ -0,0 +1,324 @@
+use std::collections::{BTreeMap, HashMap};
+
+use diesel::Connection;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::errors_service::CustomHttpError;
+use super::markdown_import_service::slugify;
+use super::plugin_service::Plugins;
+use crate::controllers::module_controllers::write_new_module;
+use crate::controllers::page_controllers::write_new_page;
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::MutModule;
+use crate::models::page_models::{MutPage, Page, PUBLISHED};
+use crate::models::DbConnection;
+
+/// The CMSs content can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportSource {
+    /// A space export from `contentful space export`.
+    Contentful,
+    /// The `entities.jsonl` of an unencrypted, uncompressed `strapi export`.
+    Strapi,
+}
+
+impl ImportSource {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "contentful" => Some(Self::Contentful),
+            "strapi" => Some(Self::Strapi),
+            _ => None,
+        }
+    }
+}
+
+/// An entry from either CMS, before it becomes a page.
+struct ImportedEntry {
+    id: String,
+    content_type: String,
+    slug: Option<String>,
+    title: Option<String>,
+    /// Each field becomes a module of the same name.
+    fields: Vec<(String, Value)>,
+}
+
+/// What was, or with a dry run would be, imported.
+#[derive(Debug, Serialize, Default)]
+pub struct MappingReport {
+    pub dry_run: bool,
+    /// Each content type, which template its pages use, and the fields that became modules.
+    pub content_types: BTreeMap<String, Vec<String>>,
+    /// The URLs of the pages created.
+    pub pages: Vec<String>,
+    /// Asset ids and the URLs that fields linking to them were given.
+    pub assets: BTreeMap<String, String>,
+    /// Entries that were left out, along with why.
+    pub skipped: Vec<(String, String)>,
+}
+
+const TITLE_FIELDS: &[&str] = &["title", "name", "heading"];
+const SLUG_FIELDS: &[&str] = &["slug", "url", "path"];
+
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn entry(id: String, content_type: String, fields: Vec<(String, Value)>) -> ImportedEntry {
+    let find = |names: &[&str]| {
+        fields
+            .iter()
+            .find(|(name, _)| names.contains(&name.as_str()))
+            .and_then(|(_, value)| as_text(value))
+    };
+
+    ImportedEntry {
+        slug: find(SLUG_FIELDS),
+        title: find(TITLE_FIELDS),
+        id,
+        content_type,
+        fields,
+    }
+}
+
+/// Contentful stores every field under its locale, so only the default locale is imported.
+fn parse_contentful(export: &Value, report: &mut MappingReport) -> Vec<ImportedEntry> {
+    let locale = export["locales"]
+        .as_array()
+        .and_then(|locales| locales.iter().find(|l| l["default"].as_bool() == Some(true)))
+        .and_then(|l| l["code"].as_str())
+        .unwrap_or("en-US")
+        .to_string();
+
+    for asset in export["assets"].as_array().into_iter().flatten() {
+        let id = asset["sys"]["id"].as_str().unwrap_or_default().to_string();
+        // asset urls are protocol relative.
+        if let Some(url) = asset["fields"]["file"][&locale]["url"].as_str() {
+            let url = match url.starts_with("//") {
+                true => format!("https:{}", url),
+                false => url.to_string(),
+            };
+            report.assets.insert(id, url);
+        }
+    }
+
+    export["entries"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|e| {
+            let fields = e["fields"]
+                .as_object()
+                .map(|fields| {
+                    fields
+                        .iter()
+                        .map(|(name, localized)| (name.clone(), localized[&locale].clone()))
+                        .filter(|(_, value)| !value.is_null())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            entry(
+                e["sys"]["id"].as_str().unwrap_or_default().to_string(),
+                e["sys"]["contentType"]["sys"]["id"].as_str().unwrap_or("entry").to_string(),
+                fields,
+            )
+        })
+        .collect()
+}
+
+/// Strapi exports one entity per line, with uploads as `plugin::upload.file` entities of their own.
+fn parse_strapi(export: &str, report: &mut MappingReport) -> Vec<ImportedEntry> {
+    let mut entries = Vec::new();
+
+    for (line_number, line) in export.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let entity: Value = match serde_json::from_str(line) {
+            Ok(entity) => entity,
+            Err(e) => {
+                report.skipped.push((format!("line {}", line_number + 1), e.to_string()));
+                continue;
+            }
+        };
+
+        let kind = entity["type"].as_str().unwrap_or_default();
+        let id = format!("{}:{}", kind, entity["id"]);
+
+        if kind == "plugin::upload.file" {
+            if let Some(url) = entity["data"]["url"].as_str() {
+                report.assets.insert(entity["id"].to_string(), url.to_string());
+            }
+            continue;
+        }
+
+        // only content from the content type builder is imported, not users, roles, and other internals.
+        let content_type = match kind.strip_prefix("api::") {
+            Some(api) => api.rsplit('.').next().unwrap_or(api).to_string(),
+            None => {
+                report.skipped.push((id, String::from("Not a content type")));
+                continue;
+            }
+        };
+
+        let fields = entity["data"]
+            .as_object()
+            .map(|fields| {
+                fields
+                    .iter()
+                    .filter(|(name, value)| {
+                        !value.is_null()
+                            && !["id", "createdAt", "updatedAt", "publishedAt", "createdBy", "updatedBy"]
+                                .contains(&name.as_str())
+                    })
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        entries.push(entry(id, content_type, fields));
+    }
+
+    entries
+}
+
+/// Links to assets are replaced by the asset's URL, and anything else that isn't text is kept as JSON.
+fn module_content(value: &Value, assets: &BTreeMap<String, String>) -> String {
+    let linked_asset = match value {
+        // contentful links look like `{ "sys": { "type": "Link", "linkType": "Asset", "id": ".." } }`.
+        Value::Object(o) if o.get("sys").and_then(|s| s["linkType"].as_str()) == Some("Asset") => {
+            o["sys"]["id"].as_str().map(String::from)
+        }
+        // strapi links to uploads by their id.
+        Value::Object(o) if o.contains_key("mime") => o.get("id").map(|id| id.to_string()),
+        _ => None,
+    };
+
+    if let Some(url) = linked_asset.and_then(|id| assets.get(&id)) {
+        return url.clone();
+    }
+
+    match value {
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn create_page(
+    page: MutPage,
+    modules: Vec<(String, String)>,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(), CustomHttpError> {
+    db.transaction(|| {
+        let page_uuid = page.uuid.clone();
+        write_new_page(page, conf, plugins, db)?;
+
+        for (title, content) in modules {
+            let module = MutModule {
+                uuid: None,
+                page_uuid: page_uuid.clone(),
+                category_uuid: None,
+                title,
+                content,
+                encrypted: false,
+                compressed: false,
+                visible_to: None,
+                order_index: None,
+                module_type: None,
+            };
+            write_new_module(module, conf, plugins, db)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Imports a Contentful or Strapi export. Every entry becomes a page at `/<content type>/<slug or id>`
+/// rendered with the template named after its content type, and every field becomes a module.
+/// Pages are written as the API writes them, content hooks included.
+/// With `dry_run` nothing is written, and the report shows what would have been imported.
+pub fn import(
+    source: ImportSource,
+    export: &str,
+    dry_run: bool,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<MappingReport, CustomHttpError> {
+    let mut report = MappingReport {
+        dry_run,
+        ..MappingReport::default()
+    };
+
+    let entries = match source {
+        ImportSource::Contentful => {
+            let export: Value = serde_json::from_str(export).map_err(|_| CustomHttpError::BadRequest)?;
+            parse_contentful(&export, &mut report)
+        }
+        ImportSource::Strapi => parse_strapi(export, &mut report),
+    };
+
+    let mut urls: HashMap<String, String> = HashMap::new();
+
+    for entry in entries {
+        let url = format!(
+            "/{}/{}",
+            slugify(&entry.content_type),
+            slugify(entry.slug.as_deref().unwrap_or(&entry.id))
+        );
+
+        if let Some(other) = urls.get(&url) {
+            report.skipped.push((entry.id, format!("Has the same URL as {}", other)));
+            continue;
+        }
+        if Page::read_one_by_url(url.clone(), db).is_ok() {
+            report.skipped.push((entry.id, format!("A page already exists at {}", url)));
+            continue;
+        }
+
+        let fields = report.content_types.entry(entry.content_type.clone()).or_default();
+        for (name, _) in &entry.fields {
+            if !fields.contains(name) {
+                fields.push(name.clone());
+            }
+        }
+
+        let page = MutPage {
+            uuid: Some(Uuid::new_v4().to_string()),
+            page_name: entry.content_type.clone(),
+            page_url: url.clone(),
+            page_title: entry.title.clone().unwrap_or_else(|| entry.id.clone()),
+            status: Some(String::from(PUBLISHED)),
+            parent_page: None,
+            meta_description: None,
+            meta_keywords: None,
+            og_image: None,
+            noindex: None,
+            locale: None,
+            translation_group: None,
+            visibility: None,
+        };
+        let modules = entry
+            .fields
+            .iter()
+            .map(|(name, value)| (name.clone(), module_content(value, &report.assets)))
+            .collect();
+
+        if !dry_run {
+            if let Err(e) = create_page(page, modules, conf, plugins, db) {
+                report.skipped.push((entry.id, e.descriptor()));
+                continue;
+            }
+        }
+
+        urls.insert(url.clone(), entry.id);
+        report.pages.push(url);
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,233 @@
+use diesel::OptionalExtension;
+use serde_json::{Map, Value};
+
+use super::errors_service::CustomHttpError;
+use super::validation_service::FieldError;
+use crate::models::content_type_models::ContentType;
+use crate::models::module_models::{ModuleType, ModuleValue};
+use crate::models::DbConnection;
+
+/// The JSON Schema keywords content types can use. Anything else in a schema is refused rather than ignored,
+/// so that a schema never looks like it checks something it doesn't.
+const KEYWORDS: &[&str] = &[
+    "type",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "enum",
+    "minLength",
+    "maxLength",
+    "minimum",
+    "maximum",
+    "minItems",
+    "maxItems",
+];
+/// Keywords that only describe a value, which are kept but never checked.
+const ANNOTATIONS: &[&str] = &["$schema", "title", "description", "default", "format", "examples"];
+const TYPES: &[&str] = &["object", "array", "string", "number", "integer", "boolean", "null"];
+
+fn error(field: &str, message: impl Into<String>) -> FieldError {
+    FieldError {
+        field: field.to_string(),
+        message: message.into(),
+    }
+}
+
+fn is_count(value: &Value) -> bool {
+    value.as_u64().is_some()
+}
+
+/// Everything wrong with `schema` as a schema of the subset of JSON Schema that is supported, with `field` being where it is.
+pub fn schema_errors(field: &str, schema: &Value) -> Vec<FieldError> {
+    let schema = match schema {
+        Value::Object(schema) => schema,
+        _ => return vec![error(field, "has to be an object")],
+    };
+    let mut errors = Vec::new();
+
+    for (keyword, value) in schema {
+        let at = format!("{}.{}", field, keyword);
+        match keyword.as_str() {
+            "type" => {
+                let types = match value {
+                    Value::String(t) => vec![t.as_str()],
+                    Value::Array(types) => types.iter().map(|t| t.as_str().unwrap_or_default()).collect(),
+                    _ => vec![""],
+                };
+                if types.is_empty() || !types.iter().all(|t| TYPES.contains(t)) {
+                    errors.push(error(&at, format!("has to be one of {}, or a list of them", TYPES.join(", "))));
+                }
+            }
+            "properties" => match value {
+                Value::Object(properties) => {
+                    for (name, property) in properties {
+                        errors.extend(schema_errors(&format!("{}.{}", at, name), property));
+                    }
+                }
+                _ => errors.push(error(&at, "has to be an object of schemas")),
+            },
+            "required" => {
+                if !value.as_array().is_some_and(|names| names.iter().all(Value::is_string)) {
+                    errors.push(error(&at, "has to be a list of property names"));
+                }
+            }
+            "additionalProperties" => match value {
+                Value::Bool(_) => {}
+                _ => errors.extend(schema_errors(&at, value)),
+            },
+            "items" => errors.extend(schema_errors(&at, value)),
+            "enum" => {
+                if value.as_array().is_none_or(Vec::is_empty) {
+                    errors.push(error(&at, "has to be a list of values"));
+                }
+            }
+            "minLength" | "maxLength" | "minItems" | "maxItems" => {
+                if !is_count(value) {
+                    errors.push(error(&at, "has to be a whole number of at least 0"));
+                }
+            }
+            "minimum" | "maximum" => {
+                if !value.is_number() {
+                    errors.push(error(&at, "has to be a number"));
+                }
+            }
+            _ if ANNOTATIONS.contains(&keyword.as_str()) => {}
+            _ => errors.push(error(&at, format!("isn't supported, only {} are", KEYWORDS.join(", ")))),
+        }
+    }
+
+    errors
+}
+
+fn is_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+fn check_object(field: &str, schema: &Map<String, Value>, object: &Map<String, Value>, errors: &mut Vec<FieldError>) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+
+    for name in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+        if !object.contains_key(name) {
+            errors.push(error(&format!("{}.{}", field, name), "is required"));
+        }
+    }
+
+    for (name, value) in object {
+        let at = format!("{}.{}", field, name);
+        match (properties.and_then(|p| p.get(name)), schema.get("additionalProperties")) {
+            (Some(property), _) => check(&at, property, value, errors),
+            (None, Some(Value::Bool(false))) => errors.push(error(&at, "isn't a property of the type")),
+            (None, Some(additional @ Value::Object(_))) => check(&at, additional, value, errors),
+            (None, _) => {}
+        }
+    }
+}
+
+fn check(field: &str, schema: &Value, value: &Value, errors: &mut Vec<FieldError>) {
+    let schema = match schema {
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    if let Some(kind) = schema.get("type") {
+        let types: Vec<&str> = match kind {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            kind => kind.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|kind| is_type(value, kind)) {
+            // what else is wrong with a value of the wrong type would only repeat it.
+            errors.push(error(field, format!("has to be of type {}", types.join(" or "))));
+            return;
+        }
+    }
+
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        if !values.contains(value) {
+            let values: Vec<String> = values.iter().map(Value::to_string).collect();
+            errors.push(error(field, format!("has to be one of {}", values.join(", "))));
+        }
+    }
+
+    let count = |keyword: &str| schema.get(keyword).and_then(Value::as_u64).map(|n| n as usize);
+    match value {
+        Value::String(s) => {
+            let length = s.chars().count();
+            if let Some(min) = count("minLength").filter(|min| length < *min) {
+                errors.push(error(field, format!("can't be shorter than {} characters", min)));
+            }
+            if let Some(max) = count("maxLength").filter(|max| length > *max) {
+                errors.push(error(field, format!("can't be longer than {} characters", max)));
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64).filter(|min| n < *min) {
+                errors.push(error(field, format!("can't be less than {}", min)));
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64).filter(|max| n > *max) {
+                errors.push(error(field, format!("can't be more than {}", max)));
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = count("minItems").filter(|min| items.len() < *min) {
+                errors.push(error(field, format!("can't have fewer than {} items", min)));
+            }
+            if let Some(max) = count("maxItems").filter(|max| items.len() > *max) {
+                errors.push(error(field, format!("can't have more than {} items", max)));
+            }
+            if let Some(item) = schema.get("items") {
+                for (i, value) in items.iter().enumerate() {
+                    check(&format!("{}.{}", field, i), item, value, errors);
+                }
+            }
+        }
+        Value::Object(object) => check_object(field, schema, object, errors),
+        _ => {}
+    }
+}
+
+/// Everything about `content` that `schema` doesn't allow, with fields like `content.price`.
+/// The schema has to have been checked with `schema_errors` first.
+pub fn content_errors(schema: &Value, content: &Value) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    check("content", schema, content, &mut errors);
+
+    errors
+}
+
+/// Checks a module's content is of its type. The built in types are checked as they always were, with a 400 if it isn't,
+/// and content types are checked against their schema, with a 422 listing everything that is wrong.
+pub fn check_content(module_type: &str, content: &str, db: &DbConnection) -> Result<(), CustomHttpError> {
+    if ModuleType::parse(module_type).is_some() {
+        return match ModuleValue::parse(module_type, content) {
+            Some(_) => Ok(()),
+            None => Err(CustomHttpError::BadRequest),
+        };
+    }
+
+    let content_type = match ContentType::read_by_name(module_type, db).optional()? {
+        Some(content_type) => content_type,
+        None => return Err(CustomHttpError::Invalid(vec![error("module_type", "isn't a built in type or a content type")])),
+    };
+    let content: Value = match serde_json::from_str(content) {
+        Ok(content) => content,
+        Err(_) => return Err(CustomHttpError::Invalid(vec![error("content", "has to be JSON")])),
+    };
+    let schema: Value = serde_json::from_str(&content_type.json_schema).unwrap_or_default();
+
+    let errors = content_errors(&schema, &content);
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(CustomHttpError::Invalid(errors)),
+    }
+}
@@ -0,0 +1,38 @@
+use actix_cors::Cors;
+
+use crate::models::config_models::LocalConfig;
+
+fn list(value: &str) -> Vec<&str> {
+    value.split(',').map(str::trim).filter(|v| !v.is_empty()).collect()
+}
+
+/// The CORS policy from `APP_CORS_*`. Without `APP_CORS_ALLOWED_ORIGINS` every origin is allowed, with credentials,
+/// as it always was. Methods and headers that aren't listed are all allowed.
+pub fn policy(conf: &LocalConfig) -> Cors {
+    let origins = match conf.cors_allowed_origins.as_deref() {
+        Some(origins) => origins,
+        None => return Cors::permissive(),
+    };
+
+    let mut cors = Cors::default().expose_any_header().max_age(conf.cors_max_age);
+
+    cors = match list(origins).as_slice() {
+        ["*"] => cors.allow_any_origin(),
+        origins => origins.iter().fold(cors, |cors, origin| cors.allowed_origin(origin)),
+    };
+
+    cors = match conf.cors_allowed_methods.as_deref() {
+        Some(methods) => cors.allowed_methods(list(methods)),
+        None => cors.allow_any_method(),
+    };
+
+    cors = match conf.cors_allowed_headers.as_deref() {
+        Some(headers) => cors.allowed_headers(list(headers)),
+        None => cors.allow_any_header(),
+    };
+
+    match conf.cors_allow_credentials {
+        true => cors.supports_credentials(),
+        false => cors,
+    }
+}
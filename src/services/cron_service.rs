@@ -0,0 +1,119 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+
+/// How far ahead `next_after` looks, which is long enough for every schedule that ever comes around, even February 29th.
+const LOOKAHEAD_DAYS: i64 = 366 * 8;
+
+/// When something runs, in the five fields of a crontab: the minute, hour, day of the month, month, and day of the week,
+/// which is 0 or 7 for Sunday. Each field is `*`, a number, a range like `1-5`, a step like `*/15` or `0-30/10`, or a list
+/// of those, like `0 3 * * 1-5` for 3:00 every weekday. `@hourly`, `@daily`, `@weekly`, and `@monthly` stand for the usual.
+/// Times are UTC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Like cron, a day is due when it matches either the day of the month or of the week, if both are given.
+    either_day: bool,
+}
+
+/// The bits of the values `field` has, which all have to be between `min` and `max`.
+fn field(field: &str, min: u32, max: u32) -> Option<u64> {
+    let mut bits = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)?),
+            None => (part, 1),
+        };
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((from, to)) => (from.parse().ok()?, to.parse().ok()?),
+            // `5/10` runs from 5 on, as it does in cron.
+            None if part.contains('/') => (range.parse().ok()?, max),
+            None => (range.parse().ok()?, range.parse().ok()?),
+        };
+        if from < min || to > max || from > to {
+            return None;
+        }
+
+        for value in (from..=to).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Some(bits)
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+impl CronSchedule {
+    pub fn parse(schedule: &str) -> Option<Self> {
+        let schedule = match schedule.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            schedule => schedule,
+        };
+        let fields: Vec<&str> = schedule.split_whitespace().collect();
+        if fields.len() != 5 {
+            return None;
+        }
+
+        let mut weekdays = field(fields[4], 0, 7)?;
+        if has(weekdays, 7) {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Some(Self {
+            minutes: field(fields[0], 0, 59)?,
+            hours: field(fields[1], 0, 23)?,
+            days: field(fields[2], 1, 31)?,
+            months: field(fields[3], 1, 12)?,
+            weekdays,
+            either_day: !fields[2].starts_with('*') && !fields[4].starts_with('*'),
+        })
+    }
+
+    fn is_due_on(&self, date: NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+
+        match self.either_day {
+            true => day || weekday,
+            false => day && weekday,
+        }
+    }
+
+    /// The first minute after `time` the schedule is due, or `None` if it never is, like on February 30th.
+    pub fn next_after(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut next = time.date().and_hms_opt(time.hour(), time.minute(), 0)? + Duration::minutes(1);
+        let until = next + Duration::days(LOOKAHEAD_DAYS);
+
+        while next < until {
+            let date = next.date();
+
+            if !has(self.months, date.month()) {
+                let (year, month) = match date.month() {
+                    12 => (date.year() + 1, 1),
+                    month => (date.year(), month + 1),
+                };
+                next = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.is_due_on(date) {
+                next = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !has(self.hours, next.hour()) {
+                next = date.and_hms_opt(next.hour(), 0, 0)? + Duration::hours(1);
+            } else if !has(self.minutes, next.minute()) {
+                next += Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use diesel::RunQueryDsl;
+use diesel::r2d2::{event::{CheckoutEvent, TimeoutEvent}, HandleEvent};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::models::{DbConnection, DbPool};
+
+static CHECKOUTS: AtomicU64 = AtomicU64::new(0);
+static CHECKOUT_FAILURES: AtomicU64 = AtomicU64::new(0);
+static TOTAL_WAIT_MICROS: AtomicU64 = AtomicU64::new(0);
+static MAX_WAIT_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Registered on the pool to keep track of how long checkouts take, and how often they time out.
+#[derive(Debug)]
+pub struct PoolEventHandler;
+
+impl HandleEvent for PoolEventHandler {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        let wait = event.duration().as_micros() as u64;
+
+        CHECKOUTS.fetch_add(1, Ordering::Relaxed);
+        TOTAL_WAIT_MICROS.fetch_add(wait, Ordering::Relaxed);
+        MAX_WAIT_MICROS.fetch_max(wait, Ordering::Relaxed);
+    }
+
+    fn handle_timeout(&self, _: TimeoutEvent) {
+        CHECKOUT_FAILURES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct PoolStats {
+    pub max_size: u32,
+    pub connections: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    pub checkouts: u64,
+    pub checkout_failures: u64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: f64,
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct DbProbe {
+    pub healthy: bool,
+    /// How long `SELECT 1` took, including the checkout.
+    pub latency_ms: f64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct DbHealth {
+    pub pool: PoolStats,
+    pub probe: DbProbe,
+}
+
+pub fn pool_stats(pool: &DbPool) -> PoolStats {
+    let state = pool.state();
+    let checkouts = CHECKOUTS.load(Ordering::Relaxed);
+    let total_wait = TOTAL_WAIT_MICROS.load(Ordering::Relaxed);
+
+    PoolStats {
+        max_size: pool.max_size(),
+        connections: state.connections,
+        idle: state.idle_connections,
+        in_use: state.connections - state.idle_connections,
+        checkouts,
+        checkout_failures: CHECKOUT_FAILURES.load(Ordering::Relaxed),
+        avg_wait_ms: match checkouts {
+            0 => 0.0,
+            _ => total_wait as f64 / checkouts as f64 / 1000.0,
+        },
+        max_wait_ms: MAX_WAIT_MICROS.load(Ordering::Relaxed) as f64 / 1000.0,
+    }
+}
+
+pub fn select_one(db: &DbConnection) -> Result<usize, diesel::result::Error> {
+    diesel::sql_query("SELECT 1").execute(db)
+}
+
+/// Checks out a connection and runs `SELECT 1` on it, timing the whole thing.
+pub fn probe(pool: &DbPool) -> DbProbe {
+    let start = Instant::now();
+
+    let res = pool
+        .get()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| select_one(&conn).map_err(|e| e.to_string()));
+
+    DbProbe {
+        healthy: res.is_ok(),
+        latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+        error: res.err(),
+    }
+}
+
+pub fn health(pool: &DbPool) -> DbHealth {
+    DbHealth {
+        probe: probe(pool),
+        pool: pool_stats(pool),
+    }
+}
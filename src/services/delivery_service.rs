@@ -0,0 +1,110 @@
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::errors_service::CustomHttpError;
+use super::rate_limit_service;
+use crate::models::delivery_token_models::DeliveryToken;
+use crate::models::page_models::Page;
+use crate::models::{with_connection, DbConnection, DbPool};
+
+/// Only the hash of a delivery token is ever stored.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    access_token: Option<String>,
+    locale: Option<String>,
+}
+
+/// The delivery token the request was made with.
+/// The token can be sent as `Authorization: Bearer <token>` or as the `access_token` query parameter,
+/// and if the request asks for a `locale` the token has to be scoped to it. Content in other locales than the token's is
+/// reported as missing whether it is asked for or not.
+pub struct DeliveryAccess(pub DeliveryToken);
+
+impl DeliveryAccess {
+    /// Fails the request if the token is scoped to other content types.
+    pub fn require(&self, content_type: &str) -> Result<(), CustomHttpError> {
+        match self.0.allows_content_type(content_type) {
+            true => Ok(()),
+            false => Err(CustomHttpError::Unauthorized),
+        }
+    }
+
+    /// Reports a page in a locale the token isn't scoped to as missing.
+    pub fn require_locale(&self, locale: Option<&str>) -> Result<(), CustomHttpError> {
+        match self.0.allows_page_locale(locale) {
+            true => Ok(()),
+            false => Err(CustomHttpError::NotFound),
+        }
+    }
+
+    /// `require_locale` for what is on the page, like its modules and categories.
+    pub fn require_page_locale(&self, page_uuid: &str, db: &DbConnection) -> Result<(), CustomHttpError> {
+        if self.0.locales.is_none() {
+            return Ok(());
+        }
+
+        self.require_locale(Page::read_locale(page_uuid, db)?.as_deref())
+    }
+}
+
+fn token_from_request(req: &HttpRequest, query: &TokenQuery) -> Option<String> {
+    let header = req
+        .headers()
+        .get("Authorization")
+        .and_then(|auth| auth.to_str().ok())
+        .map(|auth| auth.trim_start_matches("Bearer ").to_string());
+
+    header.or_else(|| query.access_token.clone())
+}
+
+async fn access(pool: web::Data<DbPool>, query: TokenQuery, token: String) -> Result<DeliveryAccess, CustomHttpError> {
+    let delivery_token = with_connection(pool, move |db| {
+        DeliveryToken::read_by_hash(hash_token(&token), db).map_err(|_| CustomHttpError::Unauthorized)
+    })
+    .await?;
+
+    if let Some(locale) = &query.locale {
+        if !delivery_token.allows_locale(locale) {
+            return Err(CustomHttpError::Unauthorized);
+        }
+    }
+
+    Ok(DeliveryAccess(delivery_token))
+}
+
+impl FromRequest for DeliveryAccess {
+    type Error = CustomHttpError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let query = web::Query::<TokenQuery>::from_query(req.query_string()).map(|q| q.into_inner());
+        let pool = req.app_data::<web::Data<DbPool>>().unwrap().to_owned();
+        let token = query.as_ref().ok().and_then(|query| token_from_request(req, query));
+        // the token as it was sent, which is what the rate limits tell the client apart by.
+        let sent = req.headers().get("Authorization").and_then(|auth| auth.to_str().ok()).map(str::to_string);
+        let req = req.clone();
+
+        Box::pin(async move {
+            let query = query.map_err(|_| CustomHttpError::BadRequest)?;
+            let token = token.ok_or(CustomHttpError::Unauthorized)?;
+
+            let access = access(pool, query, token.clone()).await?;
+            rate_limit_service::verified(&req, sent.unwrap_or(token));
+
+            Ok(access)
+        })
+    }
+}
+
+/// Delivery responses only change when content does, so they can be cached by browsers and CDNs alike.
+/// They still vary by token, as tokens can be scoped to different content.
+pub fn cache_control(max_age: u64) -> String {
+    format!("public, max-age={}, stale-while-revalidate={}", max_age, max_age * 2)
+}
@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::models::module_models::{Module, MutModule};
+use crate::models::page_models::{MutPage, Page};
+use crate::models::{DbConnection, Model};
+
+/// How many edits apart two normalized urls or titles can be while still being considered near duplicates.
+const MAX_EDIT_DISTANCE: usize = 2;
+/// Strings shorter than this are only flagged when they are exactly the same, since everything short is a few edits apart.
+const MIN_FUZZY_LENGTH: usize = 6;
+/// How similar two module contents have to be to get flagged, from 0 to 1.
+const MIN_CONTENT_SIMILARITY: f64 = 0.9;
+/// Content is compared by overlapping runs of this many words.
+const SHINGLE_SIZE: usize = 3;
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct DuplicateWarning {
+    /// What was found to be duplicated. One of `page_url`, `page_title`, or `module_content`.
+    pub field: String,
+    /// The uuid of the existing page or module it duplicates.
+    pub duplicate_of: String,
+    pub similarity: f64,
+    pub message: String,
+}
+
+/// The saved item with any duplicate warnings added alongside its fields.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+#[aliases(PageWithWarnings = WithWarnings<MutPage>, ModuleWithWarnings = WithWarnings<MutModule>)]
+pub struct WithWarnings<T: Serialize> {
+    #[serde(flatten)]
+    pub item: T,
+    pub warnings: Vec<DuplicateWarning>,
+}
+
+impl<T: Serialize> WithWarnings<T> {
+    pub fn new(item: T, warnings: Vec<DuplicateWarning>) -> Self {
+        Self { item, warnings }
+    }
+}
+
+fn normalize(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Returns how similar the two strings are if they are near duplicates.
+fn near_duplicate(a: &str, b: &str) -> Option<f64> {
+    let (a, b) = (normalize(a), normalize(b));
+
+    if a == b {
+        return Some(1.0);
+    }
+
+    if a.len().min(b.len()) < MIN_FUZZY_LENGTH {
+        return None;
+    }
+
+    let distance = edit_distance(&a, &b);
+    match distance <= MAX_EDIT_DISTANCE {
+        true => Some(1.0 - distance as f64 / a.len().max(b.len()) as f64),
+        false => None,
+    }
+}
+
+fn shingles(content: &str) -> HashSet<Vec<String>> {
+    let words: Vec<String> = content.split_whitespace().map(normalize).collect();
+
+    if words.len() < SHINGLE_SIZE {
+        return std::iter::once(words).collect();
+    }
+
+    words.windows(SHINGLE_SIZE).map(|w| w.to_vec()).collect()
+}
+
+/// Jaccard similarity of the word shingles of both contents.
+fn content_similarity(a: &HashSet<Vec<String>>, b: &HashSet<Vec<String>>) -> f64 {
+    let union = a.union(b).count();
+
+    match union {
+        0 => 0.0,
+        _ => a.intersection(b).count() as f64 / union as f64,
+    }
+}
+
+/// Looks for existing pages with a url or title close to the one being saved.
+/// `exclude` is the uuid of the page being updated, so that it isn't flagged as a duplicate of itself.
+pub fn check_page(
+    page: &MutPage,
+    exclude: Option<&str>,
+    db: &DbConnection,
+) -> Result<Vec<DuplicateWarning>, diesel::result::Error> {
+    let mut warnings = Vec::new();
+
+    for existing in Page::read_all(db)? {
+        if Some(existing.uuid.as_str()) == exclude {
+            continue;
+        }
+
+        if let Some(similarity) = near_duplicate(&page.page_url, &existing.page_url) {
+            warnings.push(DuplicateWarning {
+                field: String::from("page_url"),
+                duplicate_of: existing.uuid.clone(),
+                similarity,
+                message: format!("The url is very close to that of `{}` ({}).", existing.page_name, existing.page_url),
+            });
+        }
+
+        if let Some(similarity) = near_duplicate(&page.page_title, &existing.page_title) {
+            warnings.push(DuplicateWarning {
+                field: String::from("page_title"),
+                duplicate_of: existing.uuid.clone(),
+                similarity,
+                message: format!("The title is very close to that of `{}` ({}).", existing.page_name, existing.page_title),
+            });
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Looks for existing modules with content very similar to the one being saved.
+/// Encrypted modules are never compared, as that would leak their content.
+pub fn check_module(
+    module: &MutModule,
+    exclude: Option<&str>,
+    db: &DbConnection,
+) -> Result<Vec<DuplicateWarning>, diesel::result::Error> {
+    if module.encrypted || module.content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let new_shingles = shingles(&module.content);
+
+    let warnings = Module::read_all_including_categories(db)?
+        .into_iter()
+        .filter(|existing| !existing.encrypted && Some(existing.uuid.as_str()) != exclude)
+        .filter_map(|existing| {
+            let similarity = content_similarity(&new_shingles, &shingles(&existing.content));
+
+            match similarity >= MIN_CONTENT_SIMILARITY {
+                true => Some(DuplicateWarning {
+                    field: String::from("module_content"),
+                    message: format!("The content is very similar to that of module `{}`.", existing.title),
+                    duplicate_of: existing.uuid,
+                    similarity,
+                }),
+                false => None,
+            }
+        })
+        .collect();
+
+    Ok(warnings)
+}
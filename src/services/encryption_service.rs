@@ -0,0 +1,90 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use sha2::{Digest, Sha256};
+
+use super::auth_service::{Claims, CryptoError};
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::{FieldsDTO, Module, MutModule};
+use crate::models::user_models::UserRole;
+
+const NONCE_SIZE: usize = 12;
+
+/// The content key is derived from `APP_ENCRYPTION_KEY`, so any length of secret can be used.
+fn cipher() -> Result<Aes256Gcm, CryptoError> {
+    let secret = LocalConfig::loaded().and_then(|conf| conf.encryption_key.clone()).ok_or(CryptoError::NoEncryptionKey)?;
+    let key = Sha256::digest(secret.as_bytes());
+
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+/// Encrypts content with AES-GCM. The nonce is prepended to the ciphertext and the whole thing is base64 encoded
+/// so that it can be stored in the same column as plain content.
+pub fn encrypt_content(content: &str) -> Result<String, CryptoError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher()?
+        .encrypt(&nonce, content.as_bytes())
+        .or(Err(CryptoError::OperationFail))?;
+
+    Ok(base64::encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+pub fn decrypt_content(content: &str) -> Result<String, CryptoError> {
+    let bytes = base64::decode(content).or(Err(CryptoError::OperationFail))?;
+
+    if bytes.len() < NONCE_SIZE {
+        return Err(CryptoError::OperationFail);
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_SIZE);
+
+    let plain = cipher()?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .or(Err(CryptoError::OperationFail))?;
+
+    String::from_utf8(plain).or(Err(CryptoError::OperationFail))
+}
+
+/// Whether encrypted content is decrypted for the claim, which it is only for admins. Editors can change encrypted content,
+/// and paths that write it decrypt it to encrypt it again, but what they send back is blanked for them like any read.
+pub fn reveals_to(claim: Option<&Claims>) -> bool {
+    claim.is_some_and(|claim| claim.role >= UserRole::Admin)
+}
+
+/// Decrypts an encrypted module for authorized readers, and blanks its content for everyone else.
+pub fn reveal_module(mut module: Module, authorized: bool) -> Result<Module, CryptoError> {
+    if module.encrypted {
+        module.content = match authorized {
+            true => decrypt_content(&module.content)?,
+            false => String::new(),
+        };
+    }
+
+    Ok(module)
+}
+
+/// `reveal_module` for a module as it was written, whose content isn't encrypted anymore.
+pub fn reveal_written(mut module: MutModule, authorized: bool) -> MutModule {
+    if module.encrypted && !authorized {
+        module.content = String::new();
+    }
+
+    module
+}
+
+pub fn reveal_modules(modules: Vec<Module>, authorized: bool) -> Result<Vec<Module>, CryptoError> {
+    modules
+        .into_iter()
+        .map(|m| reveal_module(m, authorized))
+        .collect()
+}
+
+pub fn reveal_fields(mut fields: FieldsDTO, authorized: bool) -> Result<FieldsDTO, CryptoError> {
+    fields.modules = reveal_modules(fields.modules, authorized)?;
+
+    if let Some(categories) = fields.categories.as_mut() {
+        for category in categories.iter_mut() {
+            category.modules = reveal_modules(std::mem::take(&mut category.modules), authorized)?;
+        }
+    }
+
+    Ok(fields)
+}
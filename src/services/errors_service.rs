@@ -1,8 +1,13 @@
 use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
+use diesel::result::DatabaseErrorKind;
 use serde::Serialize;
+use utoipa::ToSchema;
 use thiserror::Error;
 
 use super::auth_service::CryptoError;
+use super::jsonapi_service;
+use super::media_store_service::MediaStoreError;
+use super::validation_service::FieldError;
 
 #[derive(Error, Debug)]
 pub enum CustomHttpError {
@@ -14,25 +19,100 @@ pub enum CustomHttpError {
     Unknown,
     #[error("User is not authorized.")]
     Unauthorized,
+    #[error("User is not allowed to do this.")]
+    Forbidden,
+    #[error("Resource already exists.")]
+    Conflict,
+    /// The row was changed since the client read the version its update was made from.
+    #[error("Resource was changed.")]
+    Stale,
+    /// An update of a page or module was sent without the version it was made from.
+    #[error("Precondition required.")]
+    PreconditionRequired,
+    #[error("Invalid fields.")]
+    Invalid(Vec<FieldError>),
+    #[error("Quota exceeded.")]
+    QuotaExceeded { resource: String, limit: i64 },
+    #[error("Payload too large.")]
+    TooLarge { limit: u64 },
+    #[error("Service unavailable.")]
+    SchemaBehind,
+    /// No database connection could be checked out of the pool in time.
+    #[error("Service unavailable.")]
+    PoolExhausted,
+    /// More of something than a client may send in a while, like comments, apart from the limits on every request.
+    #[error("Too many requests.")]
+    TooManyRequests { limit: u64 },
+    /// The operation of a bulk request at `index` failed, so none of them were saved.
+    #[error("{error}")]
+    Operation { index: usize, error: Box<CustomHttpError> },
 }
 
 /// Provides an interface for getting a description of the request.
 impl CustomHttpError {
+    /// The last part of the problem's `type`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::BadRequest => "bad-request",
+            Self::NotFound => "not-found",
+            Self::Unknown => "unknown",
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden => "forbidden",
+            Self::Conflict => "conflict",
+            Self::Stale => "stale",
+            Self::PreconditionRequired => "precondition-required",
+            Self::Invalid(_) => "invalid",
+            Self::QuotaExceeded { .. } => "quota-exceeded",
+            Self::TooLarge { .. } => "too-large",
+            Self::SchemaBehind => "schema-behind",
+            Self::PoolExhausted => "pool-exhausted",
+            Self::TooManyRequests { .. } => "too-many-requests",
+            Self::Operation { error, .. } => error.kind(),
+        }
+    }
+
     pub fn descriptor(&self) -> String {
         match self {
             Self::BadRequest => String::from("Server was unable to handle data"),
             Self::Unknown => String::from("Internal server error"),
             Self::NotFound => String::from("Resource was not found"),
-            Self::Unauthorized => String::from("Not authorized")
+            Self::Unauthorized => String::from("Not authorized"),
+            Self::Forbidden => String::from("The user's role doesn't allow this"),
+            Self::Conflict => String::from("Something with the same unique value already exists"),
+            Self::Stale => String::from("It was changed since it was read, so it has to be read again and the change made to that"),
+            Self::PreconditionRequired => String::from("The version it was read at has to be sent, as `If-Match` or `version`"),
+            Self::Invalid(errors) => format!("{} of the fields are invalid", errors.len()),
+            Self::QuotaExceeded { resource, limit } => {
+                format!("The limit of {} {} has been reached", limit, resource)
+            }
+            Self::TooLarge { limit } => format!("The body can't be larger than {} bytes", limit),
+            Self::SchemaBehind => String::from("The database schema is behind, so changes can't be saved until the pending migrations are run"),
+            Self::PoolExhausted => String::from("No database connection was free in time, so the request can be tried again shortly"),
+            Self::TooManyRequests { limit } => format!("No more than {} can be sent an hour", limit),
+            Self::Operation { index, error } => format!("Operation {} failed, so none were saved: {}", index, error.descriptor()),
         }
     }
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    code: u16,
-    error: String,
-    message: String,
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// An RFC 7807 problem details object.
+#[derive(Serialize, ToSchema)]
+pub struct Problem {
+    /// One of the `/errors/...` types listed in the README, which each variant has its own of.
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: String,
+    status: u16,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// Each field that was wrong, for `/errors/invalid`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+    /// Which operation of a bulk request failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<usize>,
 }
 
 /// Full implementation of ResponseError trait so that it can be sent back as an error through actix-web.
@@ -42,28 +122,79 @@ impl ResponseError for CustomHttpError {
             Self::BadRequest => StatusCode::BAD_REQUEST,
             Self::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
             Self::NotFound => StatusCode::NOT_FOUND,
-            Self::Unauthorized => StatusCode::UNAUTHORIZED
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::Conflict => StatusCode::CONFLICT,
+            Self::Stale => StatusCode::CONFLICT,
+            Self::PreconditionRequired => StatusCode::PRECONDITION_REQUIRED,
+            Self::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::QuotaExceeded { .. } => StatusCode::FORBIDDEN,
+            Self::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::SchemaBehind => StatusCode::SERVICE_UNAVAILABLE,
+            Self::PoolExhausted => StatusCode::SERVICE_UNAVAILABLE,
+            Self::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Operation { ref error, .. } => error.status_code(),
         }
     }
 
     fn error_response(&self) -> HttpResponse {
+        self.response(None)
+    }
+}
+
+impl CustomHttpError {
+    /// Like `error_response`, with the ID of the request that failed so that it can be found in the server's logs.
+    pub fn error_response_for(&self, request_id: &str) -> HttpResponse {
+        self.response(Some(request_id))
+    }
+
+    fn field_errors(&self) -> Vec<FieldError> {
+        match self {
+            Self::Invalid(errors) => errors.clone(),
+            Self::Operation { error, .. } => error.field_errors(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn response(&self, request_id: Option<&str>) -> HttpResponse {
         let status_code = self.status_code();
-        let error_response = ErrorResponse {
-            code: status_code.as_u16(),
-            message: self.descriptor(),
-            error: self.to_string(),
+
+        if jsonapi_service::enabled() {
+            return HttpResponse::build(status_code)
+                .content_type(jsonapi_service::CONTENT_TYPE)
+                .json(jsonapi_service::error_document(status_code.as_u16(), self.to_string(), self.descriptor(), request_id));
+        }
+
+        let problem = Problem {
+            problem_type: format!("/errors/{}", self.kind()),
+            title: self.to_string(),
+            status: status_code.as_u16(),
+            detail: self.descriptor(),
+            request_id: request_id.map(str::to_string),
+            errors: self.field_errors(),
+            index: match self {
+                Self::Operation { index, .. } => Some(*index),
+                _ => None,
+            },
         };
 
-        HttpResponse::build(status_code).json(error_response)
+        HttpResponse::build(status_code)
+            .content_type(PROBLEM_CONTENT_TYPE)
+            .body(serde_json::to_string(&problem).unwrap_or_default())
     }
 }
 
-/// Any time an SQL query fails, it gets mapped to here.
+/// Any time an SQL query fails, it gets mapped to here. Failures that aren't the client's fault are logged,
+/// as the response only says that something went wrong.
 impl From<diesel::result::Error> for CustomHttpError {
     fn from(e: diesel::result::Error) -> Self {
         match e {
             diesel::result::Error::NotFound => CustomHttpError::NotFound,
-            _ => CustomHttpError::Unknown,
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => CustomHttpError::Conflict,
+            e => {
+                tracing::error!("Database error: {}", e);
+                CustomHttpError::Unknown
+            }
         }
     }
 }
@@ -76,9 +207,18 @@ impl From<jsonwebtoken::errors::Error> for CustomHttpError {
     }
 }
 
+/// The store failing is never the client's fault, so why it failed is printed rather than sent.
+impl From<MediaStoreError> for CustomHttpError {
+    fn from(e: MediaStoreError) -> Self {
+        tracing::error!("Media store error: {}", e);
+        CustomHttpError::Unknown
+    }
+}
+
 impl From<CryptoError> for CustomHttpError {
     fn from(e: CryptoError) -> Self {
         match e {
+            CryptoError::NoEncryptionKey | CryptoError::NoJwtKey => Self::Unknown,
             _ => Self::Unauthorized
         }
     }
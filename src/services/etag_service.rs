@@ -0,0 +1,39 @@
+use actix_web::dev::HttpResponseBuilder;
+use actix_web::http::{header, HeaderMap, Method, StatusCode};
+use actix_web::{HttpRequest, HttpResponse};
+use sha2::{Digest, Sha256};
+
+/// The ETag of a response body, which is a hash of its content, so a response only gets a new one when it changes.
+pub fn etag(body: &[u8]) -> String {
+    let hash = format!("{:x}", Sha256::digest(body));
+
+    format!("\"{}\"", &hash[..32])
+}
+
+/// Whether the client already has the response tagged `etag`, going by the `If-None-Match` of its request's `headers`.
+/// Weak tags, as proxies that compress responses turn them into, are compared the same as strong ones.
+pub fn is_fresh(headers: &HeaderMap, etag: &str) -> bool {
+    let if_none_match = match headers.get(header::IF_NONE_MATCH).and_then(|h| h.to_str().ok()) {
+        Some(if_none_match) => if_none_match,
+        None => return false,
+    };
+
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Sends `body` with its ETag, or a 304 without it when the request's `If-None-Match` already has it.
+/// Only reads are answered with a 304, while the rest are sent with the ETag of what they respond with.
+pub fn send(req: &HttpRequest, mut builder: HttpResponseBuilder, content_type: &str, body: Vec<u8>) -> HttpResponse {
+    let etag = etag(&body);
+    builder.header(header::ETAG, etag.as_str());
+
+    let reads = req.method() == Method::GET || req.method() == Method::HEAD;
+    if reads && is_fresh(req.headers(), &etag) {
+        return builder.status(StatusCode::NOT_MODIFIED).finish();
+    }
+
+    builder.content_type(content_type).body(body)
+}
@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::connection::TransactionManager;
+use diesel::Connection;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::encryption_service::decrypt_content;
+use super::errors_service::CustomHttpError;
+use super::validation_service::FieldError;
+use crate::models::media_models::{Media, MutMedia};
+use crate::models::menu_models::{Menu, MenuItem, MutMenu, MutMenuItem};
+use crate::models::module_models::{Module, ModuleCategory, MutCategory, MutModule};
+use crate::models::page_models::{MutPage, Page, PageDTO};
+use crate::models::{DbConnection, Model};
+
+/// Bumped whenever a dump's shape changes in a way older servers can't read, which `import` refuses.
+pub const DUMP_VERSION: u32 = 1;
+
+/// How large a dump `POST /api/v1/import` takes, which is far larger than other bodies, as it holds the whole site.
+pub const MAX_DUMP_SIZE: usize = 64 * 1024 * 1024;
+
+/// Everything of a site's content, as `GET /api/v1/export` writes it out and `POST /api/v1/import` reads it back in.
+/// Encrypted modules are written out decrypted, so a dump can be imported into a server with another key,
+/// and has to be kept as safe as the database. Media is only its metadata, and the files have to be copied separately.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ContentDump {
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    pub pages: Vec<PageDTO>,
+    #[serde(default)]
+    pub module_categories: Vec<ModuleCategory>,
+    #[serde(default)]
+    pub modules: Vec<Module>,
+    #[serde(default)]
+    pub menus: Vec<Menu>,
+    #[serde(default)]
+    pub menu_items: Vec<MenuItem>,
+    #[serde(default)]
+    pub media: Vec<Media>,
+}
+
+/// How many of one kind of thing an import made, and how many it overwrote as they already existed.
+#[derive(Debug, Serialize, Default, Clone, Copy, ToSchema)]
+pub struct ImportCounts {
+    pub created: usize,
+    pub updated: usize,
+}
+
+impl ImportCounts {
+    pub fn count(&mut self, existed: bool) {
+        match existed {
+            true => self.updated += 1,
+            false => self.created += 1,
+        }
+    }
+}
+
+/// What was, or with a dry run would be, imported.
+#[derive(Debug, Serialize, Default, ToSchema)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub pages: ImportCounts,
+    pub module_categories: ImportCounts,
+    pub modules: ImportCounts,
+    pub menus: ImportCounts,
+    pub menu_items: ImportCounts,
+    pub media: ImportCounts,
+}
+
+/// Dumps the current site's pages, with their categories and modules, every menu, and the site's media.
+/// Pages and modules in the trash are left out.
+pub fn export(db: &DbConnection) -> Result<ContentDump, CustomHttpError> {
+    let pages = Page::read_all(db)?;
+    let ids = pages.iter().map(|page| page.uuid.clone()).collect();
+
+    let modules = Module::read_all_including_categories(db)?
+        .into_iter()
+        .map(|mut module| {
+            if module.encrypted {
+                module.content = decrypt_content(&module.content)?;
+            }
+            Ok(module)
+        })
+        .collect::<Result<Vec<Module>, CustomHttpError>>()?;
+
+    Ok(ContentDump {
+        version: DUMP_VERSION,
+        exported_at: Utc::now().naive_utc(),
+        module_categories: ModuleCategory::read_for_pages(ids, db)?,
+        pages,
+        modules,
+        menus: Menu::read_all(db)?,
+        menu_items: MenuItem::read_all(db)?,
+        media: Media::read_all(db)?,
+    })
+}
+
+/// Refuses dumps of a version this server doesn't know how to read.
+pub fn check_version(dump: &ContentDump) -> Result<(), CustomHttpError> {
+    match dump.version == DUMP_VERSION {
+        true => Ok(()),
+        false => Err(CustomHttpError::Invalid(vec![FieldError {
+            field: String::from("version"),
+            message: format!("has to be {}, which is the version this server exports", DUMP_VERSION),
+        }])),
+    }
+}
+
+/// Orders `items` so that each one comes after the one it is nested under, as they can't be written before it exists.
+/// Items nested under one that isn't among them keep their place, as their parent may already exist.
+pub fn parents_first<T>(items: Vec<T>, id: impl Fn(&T) -> &str, parent: impl Fn(&T) -> Option<&str>) -> Vec<T> {
+    let ids: HashSet<String> = items.iter().map(|item| id(item).to_string()).collect();
+    let mut placed: HashSet<String> = HashSet::new();
+    let mut ordered = Vec::with_capacity(items.len());
+    let mut left = items;
+
+    loop {
+        let (ready, waiting): (Vec<T>, Vec<T>) = left.into_iter().partition(|item| {
+            parent(item).is_none_or(|parent| !ids.contains(parent) || placed.contains(parent))
+        });
+        if ready.is_empty() {
+            // whatever is left nests in a loop, and is written out as it is so that writing it fails.
+            ordered.extend(waiting);
+            return ordered;
+        }
+
+        placed.extend(ready.iter().map(|item| id(item).to_string()));
+        ordered.extend(ready);
+        left = waiting;
+    }
+}
+
+/// Runs `write` in a transaction that is always rolled back, so that a dry run goes through every check of a real
+/// import, including the database's own, without keeping any of it.
+pub fn rolled_back<T>(
+    db: &DbConnection,
+    write: impl FnOnce() -> Result<T, CustomHttpError>,
+) -> Result<T, CustomHttpError> {
+    let transactions = db.transaction_manager();
+    transactions.begin_transaction(db)?;
+    let res = write();
+    transactions.rollback_transaction(db)?;
+
+    res
+}
+
+/// Writes the categories over the ones with the same uuid, or as new ones. Their pages have to be written first.
+pub fn write_categories(
+    categories: &[ModuleCategory],
+    report: &mut ImportReport,
+    db: &DbConnection,
+) -> Result<(), CustomHttpError> {
+    for category in categories {
+        let existed = ModuleCategory::read_one(category.uuid.clone(), db).is_ok();
+        match existed {
+            true => ModuleCategory::update(category.uuid.clone(), &mut_category(category), db)?,
+            false => ModuleCategory::create(&mut_category(category), db)?,
+        };
+        report.module_categories.count(existed);
+    }
+
+    Ok(())
+}
+
+/// Writes the menus and then their items, each over the one with the same uuid or as a new one.
+pub fn write_menus(
+    menus: &[Menu],
+    items: Vec<MenuItem>,
+    report: &mut ImportReport,
+    db: &DbConnection,
+) -> Result<(), CustomHttpError> {
+    for menu in menus {
+        let existed = Menu::read_one(menu.uuid.clone(), db).is_ok();
+        match existed {
+            true => Menu::update(menu.uuid.clone(), &mut_menu(menu), db)?,
+            false => Menu::create(&mut_menu(menu), db)?,
+        };
+        report.menus.count(existed);
+    }
+
+    for item in parents_first(items, |item| &item.uuid, |item| item.parent_item.as_deref()) {
+        let existed = MenuItem::read_one(item.uuid.clone(), db).is_ok();
+        match existed {
+            true => MenuItem::update(item.uuid.clone(), &mut_menu_item(&item), db)?,
+            false => MenuItem::create(&mut_menu_item(&item), db)?,
+        };
+        report.menu_items.count(existed);
+    }
+
+    Ok(())
+}
+
+/// Writes the media's metadata, each over the one with the same uuid or as a new one, on the current site.
+pub fn write_media(media: &[Media], report: &mut ImportReport, db: &DbConnection) -> Result<(), CustomHttpError> {
+    for file in media {
+        let existed = Media::read_one(file.uuid.clone(), db).is_ok();
+        match existed {
+            true => Media::update(file.uuid.clone(), &mut_media(file), db)?,
+            false => Media::create(&mut_media(file), db)?,
+        };
+        report.media.count(existed);
+    }
+
+    Ok(())
+}
+
+/// The page as it is written, under the uuid it was exported with.
+pub fn mut_page(page: &PageDTO) -> MutPage {
+    MutPage {
+        uuid: Some(page.uuid.clone()),
+        page_name: page.page_name.clone(),
+        page_url: page.page_url.clone(),
+        page_title: page.page_title.clone(),
+        status: Some(page.status.clone()),
+        parent_page: page.parent_page.clone(),
+        meta_description: page.meta_description.clone(),
+        meta_keywords: page.meta_keywords.clone(),
+        og_image: page.og_image.clone(),
+        noindex: Some(page.noindex),
+        locale: page.locale.clone(),
+        translation_group: page.translation_group.clone(),
+        visibility: Some(page.visibility.clone()),
+    }
+}
+
+fn mut_category(category: &ModuleCategory) -> MutCategory {
+    MutCategory {
+        uuid: Some(category.uuid.clone()),
+        page_uuid: category.page_uuid.clone(),
+        title: category.title.clone(),
+    }
+}
+
+/// The module as it is written, with its content as it was exported, which is encrypted again when it is written.
+pub fn mut_module(module: &Module) -> MutModule {
+    MutModule {
+        uuid: Some(module.uuid.clone()),
+        title: module.title.clone(),
+        page_uuid: module.page_uuid.clone(),
+        category_uuid: module.category_uuid.clone(),
+        content: module.content.clone(),
+        encrypted: module.encrypted,
+        compressed: false,
+        visible_to: module.visible_to.clone(),
+        order_index: Some(module.order_index),
+        module_type: Some(module.module_type.clone()),
+    }
+}
+
+fn mut_menu(menu: &Menu) -> MutMenu {
+    MutMenu {
+        uuid: menu.uuid.clone(),
+        name: menu.name.clone(),
+    }
+}
+
+fn mut_menu_item(item: &MenuItem) -> MutMenuItem {
+    MutMenuItem {
+        uuid: item.uuid.clone(),
+        menu_uuid: item.menu_uuid.clone(),
+        parent_item: item.parent_item.clone(),
+        title: item.title.clone(),
+        page_uuid: item.page_uuid.clone(),
+        url: item.url.clone(),
+        position: item.position,
+    }
+}
+
+fn mut_media(media: &Media) -> MutMedia {
+    MutMedia {
+        uuid: media.uuid.clone(),
+        filename: media.filename.clone(),
+        mime_type: media.mime_type.clone(),
+        size: media.size,
+        checksum: media.checksum.clone(),
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::cache_service::CacheBackend;
+use super::route_service::is_pattern;
+use super::site_service;
+use super::sitemap_service::escape;
+use crate::models::config_models::LocalConfig;
+use crate::models::page_models::Page;
+use crate::models::DbConnection;
+
+const CACHE_KEY: &str = "feed";
+/// Pages can also be published by other processes, like the import subcommands, which can't invalidate a memory cache.
+const CACHE_TTL: Duration = Duration::from_secs(600);
+/// How many of the newest pages the feed holds.
+const FEED_SIZE: usize = 20;
+
+/// The collection the feed is made of, like `/blog` for the pages under `/blog/`.
+pub fn prefix(conf: &LocalConfig) -> Option<String> {
+    let prefix = conf.feed_prefix.as_deref()?.trim_end_matches('*').trim_end_matches('/');
+
+    Some(format!("/{}", prefix.trim_start_matches('/')))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    title: String,
+    created: NaiveDateTime,
+}
+
+/// What the feed lists, kept without the site's URL like the sitemap.
+#[derive(Debug, Serialize, Deserialize)]
+struct Collection {
+    title: String,
+    entries: Vec<Entry>,
+}
+
+/// The newest published pages under `APP_FEED_PREFIX`, for each site. It is dropped as soon as a page changes (see `run`).
+pub struct Feed {
+    cache: Arc<dyn CacheBackend>,
+}
+
+impl Feed {
+    pub fn new(cache: Arc<dyn CacheBackend>) -> Self {
+        Self { cache }
+    }
+
+    fn load(&self, conf: &LocalConfig, prefix: &str, db: &DbConnection) -> Result<Collection, diesel::result::Error> {
+        let cached = self.cache.get(&site_service::cache_key(CACHE_KEY)).ok().flatten();
+        if let Some(collection) = cached.and_then(|c| serde_json::from_str(&c).ok()) {
+            return Ok(collection);
+        }
+
+        let under = format!("{}/", prefix.trim_end_matches('/'));
+        let mut entries: Vec<Entry> = Page::read_published(db)?
+            .into_iter()
+            .filter(|p| p.page_url.starts_with(&under) && !is_pattern(&p.page_url))
+            .map(|p| Entry {
+                url: p.page_url,
+                title: p.page_title,
+                created: p.time_created,
+            })
+            .collect();
+        entries.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| a.url.cmp(&b.url)));
+        entries.truncate(FEED_SIZE);
+
+        // the page at the prefix, like a blog's index, names the feed unless `APP_FEED_TITLE` does.
+        let title = match &conf.feed_title {
+            Some(title) => title.clone(),
+            None => Page::read_one_by_url(prefix.to_string(), db)
+                .optional()?
+                .map(|p| p.page_title)
+                .unwrap_or_else(|| prefix.to_string()),
+        };
+        let collection = Collection { title, entries };
+
+        if let Ok(serialized) = serde_json::to_string(&collection) {
+            self.cache.set(&site_service::cache_key(CACHE_KEY), &serialized, Some(CACHE_TTL)).ok();
+        }
+
+        Ok(collection)
+    }
+
+    /// An Atom feed for the site at `base`, with an entry for each page by its title, URL, and when it was created.
+    pub fn render(&self, conf: &LocalConfig, prefix: &str, base: &Url, db: &DbConnection) -> Result<String, diesel::result::Error> {
+        let collection = self.load(conf, prefix, db)?;
+        let link = |url: &str| base.join(url).map(|u| escape(u.as_str())).unwrap_or_default();
+        let time = |time: &NaiveDateTime| time.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let updated = collection.entries.first().map(|e| e.created).unwrap_or_else(|| Utc::now().naive_utc());
+
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push_str("\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str(&format!("  <title>{}</title>\n", escape(&collection.title)));
+        xml.push_str(&format!("  <id>{}</id>\n", link(prefix)));
+        xml.push_str(&format!("  <link rel=\"alternate\" href=\"{}\"/>\n", link(prefix)));
+        xml.push_str(&format!("  <link rel=\"self\" href=\"{}\"/>\n", link("/feed.xml")));
+        xml.push_str(&format!("  <updated>{}</updated>\n", time(&updated)));
+        // Atom needs an author, and pages don't have one of their own.
+        xml.push_str(&format!("  <author><name>{}</name></author>\n", escape(&collection.title)));
+
+        for entry in &collection.entries {
+            xml.push_str(&format!(
+                "  <entry>\n    <title>{}</title>\n    <link href=\"{}\"/>\n    <id>{}</id>\n    <updated>{}</updated>\n  </entry>\n",
+                escape(&entry.title),
+                link(&entry.url),
+                link(&entry.url),
+                time(&entry.created)
+            ));
+        }
+
+        xml.push_str("</feed>\n");
+        Ok(xml)
+    }
+
+    pub fn invalidate(&self) {
+        self.cache.clear(CACHE_KEY).ok();
+    }
+}
@@ -0,0 +1,167 @@
+use serde_json::{Map, Number, Value};
+
+use super::cache_service::CacheBackend;
+use super::errors_service::CustomHttpError;
+use super::rate_limit_service::check_hourly;
+use super::validation_service::FieldError;
+use crate::models::config_models::LocalConfig;
+use crate::models::form_models::{FormField, FormSubmissionDTO};
+
+/// Left empty by people, as forms hide it, and filled in by bots that fill in every field.
+pub const HONEYPOT: &str = "_website";
+/// The most characters a `text` or `textarea` field takes when it doesn't set `max_length`.
+const MAX_VALUE_LENGTH: usize = 5000;
+
+fn error(field: &str, message: impl Into<String>) -> FieldError {
+    FieldError {
+        field: field.to_string(),
+        message: message.into(),
+    }
+}
+
+/// The body of a submission, which is sent from an HTML form as `application/x-www-form-urlencoded`, or as a JSON object.
+pub fn parse_submission(content_type: &str, body: &[u8]) -> Result<Map<String, Value>, CustomHttpError> {
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        return Ok(url::form_urlencoded::parse(body)
+            .map(|(key, value)| (key.into_owned(), Value::String(value.into_owned())))
+            .collect());
+    }
+
+    serde_json::from_slice(body).map_err(|_| CustomHttpError::BadRequest)
+}
+
+/// Whether the submission was sent by a bot, which is when it filled in the `HONEYPOT`.
+pub fn is_spam(data: &Map<String, Value>) -> bool {
+    match data.get(HONEYPOT) {
+        Some(Value::String(s)) => !s.trim().is_empty(),
+        Some(Value::Null) | None => false,
+        Some(_) => true,
+    }
+}
+
+/// A value of `field`, or `None` when it wasn't filled in.
+fn field_value(field: &FormField, value: &Value) -> Result<Option<Value>, &'static str> {
+    let text = match value {
+        Value::Null => return Ok(None),
+        Value::String(s) if s.trim().is_empty() && field.field_type != "checkbox" => return Ok(None),
+        Value::String(s) => Some(s.as_str()),
+        _ => None,
+    };
+
+    match field.field_type.as_str() {
+        "number" => match (value, text.map(str::trim)) {
+            (Value::Number(_), _) => Ok(Some(value.clone())),
+            // whole numbers are kept whole, so that `42` isn't exported as `42.0`.
+            (_, Some(s)) => match s.parse::<i64>().map(Number::from).ok().or_else(|| s.parse().ok().and_then(Number::from_f64)) {
+                Some(n) => Ok(Some(Value::Number(n))),
+                None => Err("has to be a number"),
+            },
+            _ => Err("has to be a number"),
+        },
+        "checkbox" => match (value, text) {
+            (Value::Bool(_), _) => Ok(Some(value.clone())),
+            // browsers send `on` for a checked box, and nothing for one that isn't.
+            (_, Some("on" | "true" | "1")) => Ok(Some(Value::Bool(true))),
+            (_, Some("" | "off" | "false" | "0")) => Ok(Some(Value::Bool(false))),
+            _ => Err("has to be true or false"),
+        },
+        _ => match text {
+            Some(_) => Ok(Some(value.clone())),
+            None => Err("has to be text"),
+        },
+    }
+}
+
+/// Checks a submission against the form's fields, and returns only the values of its fields, converted to their type,
+/// or a 422 listing every field that is wrong. Keys starting with `_`, like the `HONEYPOT`, are left out rather than refused.
+pub fn check_submission(fields: &[FormField], data: &Map<String, Value>) -> Result<Map<String, Value>, CustomHttpError> {
+    let mut errors = Vec::new();
+    let mut values = Map::new();
+
+    for key in data.keys() {
+        if !key.starts_with('_') && !fields.iter().any(|f| &f.name == key) {
+            errors.push(error(key, "isn't a field of the form"));
+        }
+    }
+
+    for field in fields {
+        let value = match data.get(&field.name).map(|v| field_value(field, v)).unwrap_or(Ok(None)) {
+            Ok(value) => value,
+            Err(message) => {
+                errors.push(error(&field.name, message));
+                continue;
+            }
+        };
+        let value = match value {
+            Some(Value::Bool(false)) | None if field.required => {
+                errors.push(error(&field.name, "is required"));
+                continue;
+            }
+            Some(value) => value,
+            None => continue,
+        };
+
+        if let Value::String(s) = &value {
+            let length = s.chars().count();
+            match field.field_type.as_str() {
+                "email" if !s.contains('@') => errors.push(error(&field.name, "has to be an email address")),
+                "email" if length > 255 => errors.push(error(&field.name, "can't be longer than 255 characters")),
+                "select" if !field.options.contains(s) => {
+                    errors.push(error(&field.name, format!("has to be one of {}", field.options.join(", "))))
+                }
+                "text" | "textarea" if length > field.max_length.unwrap_or(MAX_VALUE_LENGTH) => errors.push(error(
+                    &field.name,
+                    format!("can't be longer than {} characters", field.max_length.unwrap_or(MAX_VALUE_LENGTH)),
+                )),
+                _ => {}
+            }
+        }
+
+        values.insert(field.name.clone(), value);
+    }
+
+    match errors.is_empty() {
+        true => Ok(values),
+        false => Err(CustomHttpError::Invalid(errors)),
+    }
+}
+
+/// Counts a submission from `client`, like its IP, against `APP_FORM_SUBMISSIONS_PER_HOUR`, with a 429 once it is over.
+pub fn check_rate(client: &str, cache: &dyn CacheBackend, conf: &LocalConfig) -> Result<(), CustomHttpError> {
+    check_hourly("form_submissions", client, conf.form_submissions_per_hour, cache)
+}
+
+/// A CSV cell, quoted when it has to be. Cells that a spreadsheet would run as a formula are started with a `'`,
+/// as submissions are written by anyone.
+fn cell(value: &str) -> String {
+    let value = match value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        true => format!("'{}", value),
+        false => value.to_string(),
+    };
+
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value,
+    }
+}
+
+/// The submissions as CSV, with a column for when each was sent and one for each of the form's fields, in order.
+/// Values of fields the form no longer has are left out.
+pub fn to_csv(fields: &[FormField], submissions: &[FormSubmissionDTO]) -> String {
+    let mut header = vec![String::from("uuid"), String::from("time_created")];
+    header.extend(fields.iter().map(|f| cell(&f.name)));
+    let mut csv = header.join(",") + "\r\n";
+
+    for submission in submissions {
+        let mut row = vec![cell(&submission.submission.uuid), submission.submission.time_created.to_string()];
+        row.extend(fields.iter().map(|f| match submission.data.get(&f.name) {
+            Some(Value::String(s)) => cell(s),
+            Some(Value::Null) | None => String::new(),
+            // numbers and checkboxes are never formulas, and `-1` is kept as it is.
+            Some(value) => value.to_string(),
+        }));
+        csv += &(row.join(",") + "\r\n");
+    }
+
+    csv
+}
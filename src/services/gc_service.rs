@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use diesel::Connection;
+use jsonwebtoken::dangerous_insecure_decode;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::auth_service::Claims;
+use super::encryption_service::reveal_modules;
+use super::media_service;
+use super::media_store_service::{self, MediaStore};
+use crate::models::config_models::LocalConfig;
+use crate::models::media_models::Media;
+use crate::models::module_models::{Module, ModuleCategory};
+use crate::models::password_reset_models::PasswordReset;
+use crate::models::user_models::User;
+use crate::models::{DbConnection, DbPool};
+
+/// Uploads are only collected once they are this old, so that one isn't taken before the content linking to it is saved.
+const MEDIA_GRACE_HOURS: i64 = 24;
+
+/// Everything that is no longer reachable and can be cleaned up.
+#[derive(Debug, Serialize, Clone, Default, ToSchema)]
+pub struct GcReport {
+    /// uuids of modules without a valid page or category.
+    pub orphaned_modules: Vec<String>,
+    /// uuids of categories without a valid page.
+    pub orphaned_categories: Vec<String>,
+    /// usernames of users holding a token that has expired or can't be read.
+    pub expired_tokens: Vec<String>,
+    /// uuids of uploads older than a day that nothing links to.
+    pub unreferenced_media: Vec<String>,
+    /// uuids of password resets that have expired.
+    pub expired_password_resets: Vec<String>,
+    /// Whether the items above have been removed, or only reported.
+    pub cleaned: bool,
+}
+
+impl GcReport {
+    pub fn is_empty(&self) -> bool {
+        self.orphaned_modules.is_empty()
+            && self.orphaned_categories.is_empty()
+            && self.expired_tokens.is_empty()
+            && self.unreferenced_media.is_empty()
+            && self.expired_password_resets.is_empty()
+    }
+}
+
+fn token_expired(token: &str) -> bool {
+    let now = chrono::Utc::now().timestamp() as usize;
+
+    match dangerous_insecure_decode::<Claims>(token) {
+        Ok(data) => data.claims.exp < now,
+        Err(_) => true,
+    }
+}
+
+/// The site's uploads that no module, gallery item, page, revision, menu item, or setting of any site links to by uuid.
+/// None are when encrypted modules can't be read, as they might link to any of them.
+fn unreferenced_media(db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+    let before = chrono::Utc::now().naive_utc() - chrono::Duration::hours(MEDIA_GRACE_HOURS);
+    let candidates = Media::read_created_before(before, db)?;
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let modules = match reveal_modules(Module::read_every(db)?, true) {
+        Ok(modules) => modules,
+        Err(e) => {
+            tracing::warn!("Not collecting media, as encrypted modules can't be read: {}", e);
+            return Ok(Vec::new());
+        }
+    };
+    let mut links = Media::read_links(db)?;
+    links.extend(modules.into_iter().map(|m| m.content));
+
+    Ok(candidates
+        .into_iter()
+        .map(|m| m.uuid)
+        .filter(|uuid| !links.iter().any(|link| link.contains(uuid.as_str())))
+        .collect())
+}
+
+/// Finds everything that can be cleaned up, without touching it. Sessions and preview links aren't stored anywhere they
+/// would have to be cleaned up from, as the ones in Redis expire on their own and the rest are signed.
+pub fn report(db: &DbConnection) -> Result<GcReport, diesel::result::Error> {
+    Ok(GcReport {
+        orphaned_modules: Module::read_orphaned(db)?.into_iter().map(|m| m.uuid).collect(),
+        orphaned_categories: ModuleCategory::read_orphaned(db)?.into_iter().map(|c| c.uuid).collect(),
+        expired_tokens: User::read_with_token(db)?
+            .into_iter()
+            .filter(|u| u.token.as_deref().map(token_expired).unwrap_or(false))
+            .map(|u| u.username)
+            .collect(),
+        unreferenced_media: unreferenced_media(db)?,
+        expired_password_resets: PasswordReset::read_expired(db)?,
+        cleaned: false,
+    })
+}
+
+/// Finds and removes everything that can be cleaned up, in one transaction. The files of uploads are removed from `store`
+/// once it has been committed.
+pub fn clean(db: &DbConnection, store: &dyn MediaStore) -> Result<GcReport, diesel::result::Error> {
+    let report = db.transaction(|| {
+        let mut report = report(db)?;
+
+        // categories go first, as once their modules are gone there is nothing left to tell which site they are on.
+        if !report.orphaned_categories.is_empty() {
+            ModuleCategory::delete_many(report.orphaned_categories.clone(), db)?;
+        }
+        if !report.orphaned_modules.is_empty() {
+            Module::delete_many(report.orphaned_modules.clone(), db)?;
+        }
+        if !report.expired_tokens.is_empty() {
+            User::clear_tokens(report.expired_tokens.clone(), db)?;
+        }
+        if !report.unreferenced_media.is_empty() {
+            Media::delete_many(report.unreferenced_media.clone(), db)?;
+        }
+        if !report.expired_password_resets.is_empty() {
+            PasswordReset::delete_expired(db)?;
+        }
+
+        report.cleaned = true;
+
+        Ok::<_, diesel::result::Error>(report)
+    })?;
+
+    for uuid in &report.unreferenced_media {
+        if let Err(e) = media_service::remove(store, uuid) {
+            tracing::error!("Failed to remove the files of media {}: {:?}", uuid, e);
+        }
+    }
+
+    Ok(report)
+}
+
+/// The scheduled cleanup job. Does nothing unless `APP_GC_INTERVAL` is set.
+pub fn run(pool: DbPool, conf: LocalConfig) {
+    let interval = match conf.gc_interval {
+        Some(interval) => interval,
+        None => return,
+    };
+    let store = match media_store_service::store(&conf) {
+        Ok(store) => store,
+        Err(e) => return tracing::error!("gc error: {}", e),
+    };
+
+    loop {
+        match pool.get() {
+            Ok(conn) => match clean(&conn, &*store) {
+                Ok(report) if report.is_empty() => {}
+                Ok(report) => tracing::info!(
+                    "Cleaned {} orphaned modules, {} orphaned categories, {} expired tokens, {} unreferenced uploads, and {} expired password resets.",
+                    report.orphaned_modules.len(),
+                    report.orphaned_categories.len(),
+                    report.expired_tokens.len(),
+                    report.unreferenced_media.len(),
+                    report.expired_password_resets.len()
+                ),
+                Err(e) => tracing::error!("gc error: {:?}", e),
+            },
+            Err(e) => tracing::error!("gc error: {:?}", e),
+        }
+
+        std::thread::sleep(Duration::from_secs(interval));
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::Receiver;
+
+use diesel::{Connection, OptionalExtension};
+use thiserror::Error;
+
+use super::errors_service::CustomHttpError;
+use super::plugin_service::Plugins;
+use super::publish_service::PublishJob;
+use super::site_service::{self, DEFAULT_SITE};
+use crate::models::gallery_item_models::{GalleryItem, MutGalleryItem};
+use crate::models::module_models::{Module, ModuleCategory, MutCategory, MutModule};
+use crate::models::page_models::{MutPage, Page, PageDTO, PageModuleDTO};
+use crate::models::{DbConnection, DbPool, Model};
+
+/// Pages are stored under this directory of the repository, so that the rest of it can be used for anything else.
+const PAGES_DIR: &str = "pages";
+
+#[derive(Error, Debug)]
+pub enum GitError {
+    #[error("git failed: {0}")]
+    Command(String),
+    #[error("Could not read or write the repository: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("A page file is invalid: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Database error: {0}")]
+    Database(#[from] diesel::result::Error),
+    #[error("A content hook refused {0}: {1}")]
+    Refused(String, String),
+}
+
+impl GitError {
+    fn refused(what: &str, error: CustomHttpError) -> Self {
+        Self::Refused(what.to_string(), error.descriptor())
+    }
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String, GitError> {
+    // commits are made by Radical itself, so they shouldn't depend on the git config of whoever runs the server.
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["-c", "user.name=Radical", "-c", "user.email=radical@localhost"])
+        .args(args)
+        .output()?;
+
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+        false => Err(GitError::Command(String::from_utf8_lossy(&output.stderr).trim().to_string())),
+    }
+}
+
+/// Creates the repository if there isn't one yet.
+pub fn init(repo: &Path) -> Result<(), GitError> {
+    if !repo.join(".git").exists() {
+        fs::create_dir_all(repo)?;
+        git(repo, &["init"])?;
+    }
+
+    Ok(())
+}
+
+/// The file a page is stored in, named after its URL so that the repository reads like the site.
+pub fn file_for(url: &str) -> PathBuf {
+    match url.trim_matches('/') {
+        "" => Path::new(PAGES_DIR).join("index.json"),
+        path => Path::new(PAGES_DIR).join(format!("{}.json", path)),
+    }
+}
+
+/// Pages are stored along with all of their modules and categories.
+/// Encrypted modules are stored as they are in the database, so their content never reaches the repository in the clear.
+fn write_page(repo: &Path, page: &PageModuleDTO) -> Result<(), GitError> {
+    let path = repo.join(file_for(&page.page_url));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, serde_json::to_string_pretty(page)? + "\n")?;
+
+    Ok(())
+}
+
+/// Commits everything under the pages directory. Returns whether there was anything to commit.
+fn commit(repo: &Path, message: &str, push: bool) -> Result<bool, GitError> {
+    git(repo, &["add", "-A", PAGES_DIR])?;
+
+    if git(repo, &["status", "--porcelain", PAGES_DIR])?.trim().is_empty() {
+        return Ok(false);
+    }
+
+    git(repo, &["commit", "-m", message])?;
+
+    if push {
+        git(repo, &["push", "origin", "HEAD"])?;
+    }
+
+    Ok(true)
+}
+
+/// Writes every page to the repository and commits them together.
+pub fn export_all(repo: &Path, push: bool, db: &DbConnection) -> Result<usize, GitError> {
+    init(repo)?;
+
+    let pages: Vec<PageDTO> = Page::read_all(db)?;
+    for page in &pages {
+        write_page(repo, &Page::read_one_join_on(page.uuid.clone(), db)?)?;
+    }
+
+    commit(repo, "Export all pages", push)?;
+
+    Ok(pages.len())
+}
+
+fn page_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            page_files(&path, files)?;
+        } else if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn upsert_module(
+    module: &Module,
+    category_uuid: Option<String>,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(), GitError> {
+    let mut new = MutModule {
+        uuid: Some(module.uuid.clone()),
+        page_uuid: module.page_uuid.clone(),
+        category_uuid,
+        title: module.title.clone(),
+        content: module.content.clone(),
+        encrypted: module.encrypted,
+        compressed: false,
+        visible_to: module.visible_to.clone(),
+        order_index: None,
+        module_type: Some(module.module_type.clone()),
+    };
+    plugins.before_save_module(&mut new).map_err(|e| GitError::refused(&module.uuid, e))?;
+
+    match Module::read_one(module.uuid.clone(), db).optional()? {
+        Some(_) => Module::update(module.uuid.clone(), &new, db)?,
+        None => Module::create(&new, db)?,
+    };
+
+    Ok(())
+}
+
+fn import_page(page: PageModuleDTO, plugins: &Plugins, db: &DbConnection) -> Result<(), GitError> {
+    let mut new_page = MutPage {
+        uuid: Some(page.uuid.clone()),
+        page_name: page.page_name.clone(),
+        page_url: page.page_url.clone(),
+        page_title: page.page_title.clone(),
+        status: Some(page.status.clone()),
+        parent_page: None,
+        meta_description: page.meta_description.clone(),
+        meta_keywords: page.meta_keywords.clone(),
+        og_image: page.og_image.clone(),
+        noindex: Some(page.noindex),
+        locale: page.locale.clone(),
+        translation_group: page.translation_group.clone(),
+        visibility: Some(page.visibility.clone()),
+    };
+    plugins.before_save_page(&mut new_page).map_err(|e| GitError::refused(&page.page_url, e))?;
+
+    // modules that were removed from the file are removed from the page.
+    let mut stale: HashSet<String> = match Page::read_one(page.uuid.clone(), db).optional()? {
+        Some(_) => {
+            Page::update(page.uuid.clone(), &new_page, db)?;
+
+            let existing = Page::read_one_join_on(page.uuid.clone(), db)?.fields;
+            existing
+                .modules
+                .into_iter()
+                .chain(existing.categories.into_iter().flatten().flat_map(|c| c.modules))
+                .map(|m| m.uuid)
+                .collect()
+        }
+        None => {
+            Page::create(&new_page, db)?;
+            HashSet::new()
+        }
+    };
+
+    for module in &page.fields.modules {
+        stale.remove(&module.uuid);
+        upsert_module(module, None, plugins, db)?;
+    }
+
+    for category in page.fields.categories.iter().flatten() {
+        let new_category = MutCategory {
+            uuid: Some(category.uuid.clone()),
+            page_uuid: page.uuid.clone(),
+            title: category.title.clone(),
+        };
+        match ModuleCategory::read_one(category.uuid.clone(), db).optional()? {
+            Some(_) => ModuleCategory::update(category.uuid.clone(), &new_category, db)?,
+            None => ModuleCategory::create(&new_category, db)?,
+        };
+
+        for module in &category.modules {
+            stale.remove(&module.uuid);
+            upsert_module(module, Some(category.uuid.clone()), plugins, db)?;
+        }
+    }
+
+    Module::delete_many(stale.into_iter().collect(), db)?;
+
+    for (module_uuid, items) in &page.fields.galleries {
+        GalleryItem::delete_for_module(module_uuid.clone(), db)?;
+
+        for item in items {
+            GalleryItem::create(
+                &MutGalleryItem {
+                    uuid: Some(item.uuid.clone()),
+                    module_uuid: module_uuid.clone(),
+                    media_url: item.media_url.clone(),
+                    caption: item.caption.clone(),
+                    position: item.position,
+                },
+                db,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads every page in the repository back into the database, creating or updating them by uuid.
+/// Pages that aren't in the repository are left alone. Everything is imported in one transaction,
+/// so a single invalid file, or one a content hook refuses, leaves the database untouched.
+pub fn import_all(repo: &Path, plugins: &Plugins, db: &DbConnection) -> Result<usize, GitError> {
+    let mut files = Vec::new();
+    page_files(&repo.join(PAGES_DIR), &mut files)?;
+
+    db.transaction(|| {
+        for file in &files {
+            let page: PageModuleDTO = serde_json::from_str(&fs::read_to_string(file)?)?;
+            import_page(page, plugins, db)?;
+        }
+
+        Ok(files.len())
+    })
+}
+
+fn handle(job: PublishJob, repo: &Path, push: bool, db: &DbConnection) -> Result<(), GitError> {
+    match job {
+        PublishJob::Page(uuid) => {
+            // the page may have been deleted by the time the job gets here, which its own job takes care of.
+            let page = match Page::read_one_join_on(uuid, db).optional()? {
+                Some(page) => page,
+                None => return Ok(()),
+            };
+
+            write_page(repo, &page)?;
+            commit(repo, &format!("Update {}", page.page_url), push)?;
+        }
+        PublishJob::Removed(url) => {
+            // the URL may have been another site's, while one of this site's pages is still at it.
+            if Page::read_one_by_url(url.clone(), db).optional()?.is_some() {
+                return Ok(());
+            }
+
+            let path = repo.join(file_for(&url));
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+
+            commit(repo, &format!("Remove {}", url), push)?;
+        }
+        PublishJob::Everything => {
+            export_all(repo, push, db)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Mirrors content changes to the repository as they come in, one commit per change. Only the default site is mirrored.
+pub fn run(pool: DbPool, repo: String, push: bool, jobs: Receiver<PublishJob>) {
+    let repo = PathBuf::from(repo);
+
+    if let Err(e) = init(&repo) {
+        tracing::warn!("Mirroring to git is turned off: {}", e);
+        return;
+    }
+
+    for job in jobs {
+        let res = match pool.get() {
+            Ok(conn) => site_service::scoped(Some(String::from(DEFAULT_SITE)), || handle(job, &repo, push, &conn)),
+            Err(e) => {
+                tracing::error!("git mirroring error: {:?}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = res {
+            tracing::error!("git mirroring error: {}", e);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::sync::Mutex;
+
+use actix_web::web;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Result, Schema};
+use chrono::NaiveDateTime;
+use diesel::OptionalExtension;
+
+use super::auth_service::Claims;
+use super::encryption_service::{reveal_fields, reveal_module, reveals_to};
+use super::pagination_service::{list, Listing, PageQuery};
+use super::sanitize_service::{on_render_fields, on_render_module};
+use super::visibility_service::{is_visible, list_viewable, require_published_module, strip_fields, viewable, Role};
+use crate::models::module_models::{CategoryDTO, FieldsDTO, Module};
+use crate::models::page_models::{is_live, Page, PageDTO};
+use crate::models::{with_connection, DbPool, Model};
+
+pub type RadicalSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Queries nested deeper than this, or asking for more fields than `MAX_COMPLEXITY`, are refused before they are run.
+const MAX_DEPTH: usize = 8;
+const MAX_COMPLEXITY: usize = 500;
+
+pub fn schema() -> RadicalSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+/// Who a query is for, which is put in the data of every request. Visitors only see published pages and the modules
+/// shown to everyone, like the delivery API, while users who are logged in see everything their role lets them, like
+/// with the management API.
+#[derive(Clone)]
+pub struct Viewer {
+    role: Role,
+    authorized: bool,
+    /// Whether encrypted content is decrypted, which `authorized` alone doesn't allow (see `encryption_service::reveals_to`).
+    reveals: bool,
+    /// Of the pages the user can see, or `None` for every one (see `visibility_service::viewable`).
+    visibilities: Option<Vec<String>>,
+}
+
+impl Viewer {
+    pub fn new(claim: &Option<Claims>) -> Self {
+        Self {
+            role: Role::of(claim),
+            authorized: claim.is_some(),
+            reveals: reveals_to(claim.as_ref()),
+            visibilities: viewable(claim),
+        }
+    }
+
+    fn sees_visibility(&self, visibility: &str) -> bool {
+        self.visibilities.as_ref().is_none_or(|v| v.iter().any(|v| v == visibility))
+    }
+
+    fn sees(&self, page: &PageDTO) -> bool {
+        (self.authorized || is_live(&page.status, page.publish_at)) && self.sees_visibility(&page.visibility)
+    }
+}
+
+fn parts<'a>(ctx: &'a Context<'_>) -> Result<(&'a Viewer, web::Data<DbPool>)> {
+    Ok((ctx.data::<Viewer>()?, ctx.data::<web::Data<DbPool>>()?.clone()))
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// Every page, or one page of them with `page` or `perPage`.
+    async fn pages(&self, ctx: &Context<'_>, page: Option<i64>, per_page: Option<i64>) -> Result<Vec<GqlPage>> {
+        let (viewer, pool) = parts(ctx)?;
+        let query = PageQuery { page, per_page };
+        let (authorized, visibilities) = (viewer.authorized, viewer.visibilities.clone());
+
+        let pages = with_connection(pool, move |db| match authorized {
+            true => list_viewable(&query, visibilities.as_deref(), db),
+            false => list(&query, db, Page::read_published, Page::read_published_paginated),
+        })
+        .await?;
+        let pages = match pages {
+            Listing::All(pages) => pages,
+            Listing::Page(page) => page.data,
+        };
+
+        Ok(pages.into_iter().map(GqlPage::new).collect())
+    }
+
+    async fn page(&self, ctx: &Context<'_>, id: String) -> Result<Option<GqlPage>> {
+        let (viewer, pool) = parts(ctx)?;
+        let page: Option<PageDTO> = with_connection(pool, move |db| Ok(Page::read_one(id, db).optional()?)).await?;
+
+        Ok(page.filter(|p| viewer.sees(p)).map(GqlPage::new))
+    }
+
+    /// The page at a URL, like `/blog/post`.
+    async fn page_by_url(&self, ctx: &Context<'_>, url: String) -> Result<Option<GqlPage>> {
+        let (viewer, pool) = parts(ctx)?;
+        let page = with_connection(pool, move |db| Ok(Page::read_one_by_url(url, db).optional()?)).await?;
+
+        Ok(page
+            .map(PageDTO::from)
+            .filter(|p| viewer.sees(p))
+            .map(GqlPage::new))
+    }
+
+    async fn module(&self, ctx: &Context<'_>, id: String) -> Result<Option<GqlModule>> {
+        let (viewer, pool) = parts(ctx)?;
+        let (role, authorized, reveals, viewer) = (viewer.role, viewer.authorized, viewer.reveals, viewer.clone());
+
+        let module = with_connection(pool, move |db| {
+            let module = match Module::read_one(id, db).optional()? {
+                Some(module) => module,
+                None => return Ok(None),
+            };
+            if !authorized && require_published_module(&module, db).is_err() {
+                return Ok(None);
+            }
+            if let Some(page_uuid) = &module.page_uuid {
+                if !viewer.sees_visibility(&Page::read_visibility(page_uuid, db)?) {
+                    return Ok(None);
+                }
+            }
+
+            match is_visible(&module, role) {
+                true => Ok(Some(on_render_module(reveal_module(module, reveals)?))),
+                false => Ok(None),
+            }
+        })
+        .await?;
+
+        Ok(module.map(GqlModule))
+    }
+}
+
+/// A page, whose modules are only read when a query asks for them.
+pub struct GqlPage {
+    page: PageDTO,
+    fields: Mutex<Option<FieldsDTO>>,
+}
+
+impl GqlPage {
+    fn new(page: PageDTO) -> Self {
+        Self {
+            page,
+            fields: Mutex::new(None),
+        }
+    }
+
+    /// The page's modules as the viewer can see them, read once however many of its fields ask for them.
+    async fn fields(&self, ctx: &Context<'_>) -> Result<FieldsDTO> {
+        if let Some(fields) = self.fields.lock().unwrap().clone() {
+            return Ok(fields);
+        }
+
+        let (viewer, pool) = parts(ctx)?;
+        let (role, reveals, id) = (viewer.role, viewer.reveals, self.page.uuid.clone());
+        let fields = with_connection(pool, move |db| {
+            let fields = Page::read_one_join_on(id, db)?.fields;
+
+            Ok(on_render_fields(reveal_fields(strip_fields(fields, role), reveals)?))
+        })
+        .await?;
+
+        *self.fields.lock().unwrap() = Some(fields.clone());
+        Ok(fields)
+    }
+}
+
+#[Object(name = "Page")]
+impl GqlPage {
+    async fn uuid(&self) -> &str {
+        &self.page.uuid
+    }
+
+    async fn page_name(&self) -> &str {
+        &self.page.page_name
+    }
+
+    async fn page_url(&self) -> &str {
+        &self.page.page_url
+    }
+
+    async fn page_title(&self) -> &str {
+        &self.page.page_title
+    }
+
+    async fn status(&self) -> &str {
+        &self.page.status
+    }
+
+    async fn time_created(&self) -> NaiveDateTime {
+        self.page.time_created
+    }
+
+    async fn publish_at(&self) -> Option<NaiveDateTime> {
+        self.page.publish_at
+    }
+
+    async fn parent_page(&self) -> Option<&str> {
+        self.page.parent_page.as_deref()
+    }
+
+    async fn meta_description(&self) -> Option<&str> {
+        self.page.meta_description.as_deref()
+    }
+
+    async fn meta_keywords(&self) -> Option<&str> {
+        self.page.meta_keywords.as_deref()
+    }
+
+    async fn og_image(&self) -> Option<&str> {
+        self.page.og_image.as_deref()
+    }
+
+    async fn noindex(&self) -> bool {
+        self.page.noindex
+    }
+
+    async fn visibility(&self) -> &str {
+        &self.page.visibility
+    }
+
+    async fn locale(&self) -> Option<&str> {
+        self.page.locale.as_deref()
+    }
+
+    async fn translation_group(&self) -> Option<&str> {
+        self.page.translation_group.as_deref()
+    }
+
+    /// The modules that aren't in a category, in order, or only the one with `title`.
+    async fn modules(&self, ctx: &Context<'_>, title: Option<String>) -> Result<Vec<GqlModule>> {
+        let modules = self.fields(ctx).await?.modules;
+
+        Ok(modules
+            .into_iter()
+            .filter(|m| title.as_ref().is_none_or(|t| &m.title == t))
+            .map(GqlModule)
+            .collect())
+    }
+
+    /// The page's categories, or only the one with `title`.
+    async fn categories(&self, ctx: &Context<'_>, title: Option<String>) -> Result<Vec<GqlCategory>> {
+        let categories = self.fields(ctx).await?.categories.unwrap_or_default();
+
+        Ok(categories
+            .into_iter()
+            .filter(|c| title.as_ref().is_none_or(|t| &c.title == t))
+            .map(GqlCategory)
+            .collect())
+    }
+}
+
+pub struct GqlCategory(CategoryDTO);
+
+#[Object(name = "Category")]
+impl GqlCategory {
+    async fn uuid(&self) -> &str {
+        &self.0.uuid
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn modules(&self) -> Vec<GqlModule> {
+        self.0.modules.iter().cloned().map(GqlModule).collect()
+    }
+}
+
+pub struct GqlModule(Module);
+
+#[Object(name = "Module")]
+impl GqlModule {
+    async fn uuid(&self) -> &str {
+        &self.0.uuid
+    }
+
+    async fn page_uuid(&self) -> Option<&str> {
+        self.0.page_uuid.as_deref()
+    }
+
+    async fn category_uuid(&self) -> Option<&str> {
+        self.0.category_uuid.as_deref()
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    /// Empty for encrypted modules, unless the viewer is logged in.
+    async fn content(&self) -> &str {
+        &self.0.content
+    }
+
+    async fn module_type(&self) -> &str {
+        &self.0.module_type
+    }
+
+    async fn order_index(&self) -> i32 {
+        self.0.order_index
+    }
+
+    async fn encrypted(&self) -> bool {
+        self.0.encrypted
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use diesel::OptionalExtension;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use utoipa::ToSchema;
+
+use super::errors_service::CustomHttpError;
+use super::validation_service::Validate;
+use crate::models::inbound_webhook_models::InboundWebhook;
+use crate::models::module_models::{Module, MutModule};
+use crate::models::page_models::{MutPage, Page};
+use crate::models::DbConnection;
+
+/// The header external systems send the signature in, as `sha256=<hex HMAC>` of the timestamp, a `.`, and the body.
+pub const SIGNATURE_HEADER: &str = "X-Radical-Signature";
+/// The header with when the delivery was signed, in seconds since the Unix epoch.
+pub const TIMESTAMP_HEADER: &str = "X-Radical-Timestamp";
+/// How many seconds a signature is good for, either way of the server's clock.
+const MAX_AGE: i64 = 300;
+
+/// Maps an incoming payload onto a page or module.
+/// `fields` maps each field to a JSON pointer into the payload, like `"page_title": "/product/name"`.
+/// `defaults` are used for fields that aren't mapped or are missing from the payload.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct Transform {
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+    #[serde(default)]
+    pub defaults: HashMap<String, String>,
+}
+
+/// Pages are matched on their URL and modules on their page and title, so sending the same payload twice updates
+/// rather than duplicates.
+fn required_fields(target: &str) -> Result<&'static [&'static str], CustomHttpError> {
+    match target {
+        "page" => Ok(&["page_url", "page_title", "page_name"]),
+        "module" => Ok(&["page_uuid", "title", "content"]),
+        _ => Err(CustomHttpError::BadRequest),
+    }
+}
+
+/// Makes sure a transform can always produce a complete page or module before it is saved.
+pub fn validate(target: &str, transform: &Transform) -> Result<(), CustomHttpError> {
+    let missing = required_fields(target)?
+        .iter()
+        .any(|field| !transform.fields.contains_key(*field) && !transform.defaults.contains_key(*field));
+
+    match missing {
+        true => Err(CustomHttpError::BadRequest),
+        false => Ok(()),
+    }
+}
+
+/// Signs `timestamp.body`, so that a delivery that was caught can't be sent again once `MAX_AGE` has passed.
+pub fn verify_signature(
+    secret: &str,
+    timestamp: Option<&str>,
+    body: &[u8],
+    signature: Option<&str>,
+    now: i64,
+) -> Result<(), CustomHttpError> {
+    let timestamp = timestamp.ok_or(CustomHttpError::Unauthorized)?;
+    let sent: i64 = timestamp.parse().map_err(|_| CustomHttpError::Unauthorized)?;
+    if (now - sent).abs() > MAX_AGE {
+        return Err(CustomHttpError::Unauthorized);
+    }
+    let signature = signature
+        .and_then(|s| s.strip_prefix("sha256="))
+        .and_then(decode_hex)
+        .ok_or(CustomHttpError::Unauthorized)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|_| CustomHttpError::Unknown)?;
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    mac.verify_slice(&signature).map_err(|_| CustomHttpError::Unauthorized)
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn resolve(transform: &Transform, payload: &Value, field: &str) -> Result<String, CustomHttpError> {
+    let mapped = transform
+        .fields
+        .get(field)
+        .and_then(|pointer| payload.pointer(pointer))
+        .and_then(|value| match value {
+            Value::Null => None,
+            Value::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
+        });
+
+    mapped
+        .or_else(|| transform.defaults.get(field).cloned())
+        .ok_or(CustomHttpError::BadRequest)
+}
+
+/// What a payload maps onto, before it is written like a page or module sent to the API would be.
+pub enum Mapped {
+    /// Along with the page at its URL, which is updated rather than created.
+    Page(MutPage, Option<String>),
+    /// Along with the module of its title on its page.
+    Module(MutModule, Option<String>),
+}
+
+/// Maps the payload onto whatever the webhook targets, checked like the body of a `POST` of it would be.
+pub fn map(webhook: &InboundWebhook, payload: &Value, db: &DbConnection) -> Result<Mapped, CustomHttpError> {
+    let transform: Transform = serde_json::from_str(&webhook.transform).map_err(|_| CustomHttpError::Unknown)?;
+
+    match webhook.target.as_str() {
+        "page" => {
+            let page = MutPage {
+                uuid: None,
+                page_url: resolve(&transform, payload, "page_url")?,
+                page_title: resolve(&transform, payload, "page_title")?,
+                page_name: resolve(&transform, payload, "page_name")?,
+                status: None,
+                parent_page: None,
+                meta_description: None,
+                meta_keywords: None,
+                og_image: None,
+                noindex: None,
+                locale: None,
+                translation_group: None,
+                visibility: None,
+            };
+            page.validate()?;
+            let existing = Page::read_one_by_url(page.page_url.clone(), db).optional()?;
+
+            Ok(Mapped::Page(page, existing.map(|p| p.uuid)))
+        }
+        "module" => {
+            let page_uuid: String = resolve(&transform, payload, "page_uuid")?;
+            let module = MutModule {
+                uuid: None,
+                page_uuid: Some(page_uuid.clone()),
+                title: resolve(&transform, payload, "title")?,
+                content: resolve(&transform, payload, "content")?,
+                category_uuid: resolve(&transform, payload, "category_uuid").ok(),
+                encrypted: false,
+                compressed: false,
+                visible_to: None,
+                order_index: None,
+                module_type: None,
+            };
+            module.validate()?;
+            let existing = Module::read_one_by_title(page_uuid, module.title.clone(), db).optional()?;
+
+            Ok(Mapped::Module(module, existing.map(|m| m.uuid)))
+        }
+        _ => Err(CustomHttpError::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "secret";
+    const NOW: i64 = 1_700_000_000;
+
+    fn sign(timestamp: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        let hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+
+        format!("sha256={}", hex)
+    }
+
+    #[test]
+    fn accepts_a_signed_delivery() {
+        let signature = sign("1700000000", b"{}");
+
+        let uppercase = format!("sha256={}", signature["sha256=".len()..].to_uppercase());
+
+        assert!(verify_signature(SECRET, Some("1700000000"), b"{}", Some(&signature), NOW).is_ok());
+        assert!(verify_signature(SECRET, Some("1700000000"), b"{}", Some(&uppercase), NOW).is_ok());
+    }
+
+    #[test]
+    fn refuses_a_changed_body_or_another_secret() {
+        let signature = sign("1700000000", b"{}");
+
+        assert!(verify_signature(SECRET, Some("1700000000"), b"{ }", Some(&signature), NOW).is_err());
+        assert!(verify_signature("other", Some("1700000000"), b"{}", Some(&signature), NOW).is_err());
+    }
+
+    #[test]
+    fn refuses_a_timestamp_that_wasnt_signed() {
+        let signature = sign("1700000000", b"{}");
+
+        assert!(verify_signature(SECRET, Some("1700000001"), b"{}", Some(&signature), NOW).is_err());
+    }
+
+    #[test]
+    fn refuses_deliveries_older_or_newer_than_max_age() {
+        for sent in [NOW - MAX_AGE - 1, NOW + MAX_AGE + 1] {
+            let timestamp = sent.to_string();
+            let signature = sign(&timestamp, b"{}");
+
+            assert!(verify_signature(SECRET, Some(&timestamp), b"{}", Some(&signature), NOW).is_err());
+        }
+
+        let timestamp = (NOW - MAX_AGE).to_string();
+        let signature = sign(&timestamp, b"{}");
+        assert!(verify_signature(SECRET, Some(&timestamp), b"{}", Some(&signature), NOW).is_ok());
+    }
+
+    #[test]
+    fn refuses_missing_or_malformed_headers() {
+        let signature = sign("1700000000", b"{}");
+
+        assert!(verify_signature(SECRET, None, b"{}", Some(&signature), NOW).is_err());
+        assert!(verify_signature(SECRET, Some("soon"), b"{}", Some(&signature), NOW).is_err());
+        assert!(verify_signature(SECRET, Some("1700000000"), b"{}", None, NOW).is_err());
+        assert!(verify_signature(SECRET, Some("1700000000"), b"{}", Some(&signature["sha256=".len()..]), NOW).is_err());
+        assert!(verify_signature(SECRET, Some("1700000000"), b"{}", Some("sha256=zz"), NOW).is_err());
+    }
+
+    #[test]
+    fn decodes_hex() {
+        assert_eq!(decode_hex("00ff7A"), Some(vec![0x00, 0xff, 0x7a]));
+        assert_eq!(decode_hex(""), Some(vec![]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("éa"), None);
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::job_models::{Job, MutJob, PENDING};
+use crate::models::DbPool;
+
+/// A job is given up on after this many attempts.
+const MAX_ATTEMPTS: i32 = 6;
+/// How long the first retry waits, which doubles with every one after it, so the last is about five minutes after the first.
+const FIRST_RETRY: Duration = Duration::from_secs(10);
+/// The longest a retry waits, however many attempts a job is given.
+const MAX_RETRY: Duration = Duration::from_secs(60 * 60);
+/// How often the table is checked for jobs that came due on their own, like retries and jobs sent by other servers.
+const POLL: Duration = Duration::from_secs(1);
+/// How many jobs are claimed at a time.
+const BATCH: i64 = 10;
+/// How long a job can run before it is taken to be lost, like with a server that crashed, and run again.
+const LEASE: Duration = Duration::from_secs(10 * 60);
+
+/// A job on its way to the table, to be run by the handler of its kind with `payload`.
+#[derive(Debug, Clone)]
+pub struct NewJob {
+    kind: &'static str,
+    payload: Value,
+    run_at: Option<NaiveDateTime>,
+    max_attempts: i32,
+}
+
+impl NewJob {
+    pub fn new(kind: &'static str, payload: impl Serialize) -> Self {
+        Self {
+            kind,
+            payload: serde_json::to_value(payload).unwrap_or_default(),
+            run_at: None,
+            max_attempts: MAX_ATTEMPTS,
+        }
+    }
+
+    /// Waits to be run until `at`, rather than straight away.
+    pub fn at(mut self, at: NaiveDateTime) -> Self {
+        self.run_at = Some(at);
+        self
+    }
+
+    fn into_row(self) -> MutJob {
+        MutJob {
+            uuid: Uuid::new_v4().to_string(),
+            kind: String::from(self.kind),
+            payload: self.payload.to_string(),
+            status: String::from(PENDING),
+            max_attempts: self.max_attempts,
+            run_at: self.run_at.unwrap_or_else(|| Utc::now().naive_utc()),
+        }
+    }
+}
+
+/// Where work that shouldn't hold up a request is sent, for `run` to keep in the `jobs` table and run until it succeeds.
+pub struct JobQueue {
+    sender: Mutex<Option<Sender<NewJob>>>,
+}
+
+impl JobQueue {
+    pub fn new() -> (Self, Receiver<NewJob>) {
+        let (sender, receiver) = channel();
+
+        (Self { sender: Mutex::new(Some(sender)) }, receiver)
+    }
+
+    pub fn send(&self, job: NewJob) {
+        // the worker only stops along with the server, so there is no one to report a failed send to.
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            sender.send(job).ok();
+        }
+    }
+
+    /// Stops taking jobs, so that `run` stores what it has and returns. Used when the server stops.
+    pub fn close(&self) {
+        self.sender.lock().unwrap().take();
+    }
+}
+
+type Handler = Box<dyn Fn(&Job) -> Result<(), String> + Send>;
+
+/// What runs each kind of job. A job that fails is retried until its `max_attempts`, so handlers should be safe to run
+/// again for the same job, and can tell the last attempt by `attempts` having reached it.
+#[derive(Default)]
+pub struct JobHandlers {
+    handlers: HashMap<&'static str, Handler>,
+}
+
+impl JobHandlers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on(mut self, kind: &'static str, handler: impl Fn(&Job) -> Result<(), String> + Send + 'static) -> Self {
+        self.handlers.insert(kind, Box::new(handler));
+        self
+    }
+
+    /// Runs the job with the handler of its kind, turning a panic into an error so it is retried like any other.
+    fn run(&self, job: &Job) -> Option<Result<(), String>> {
+        let handler = self.handlers.get(job.kind.as_str())?;
+
+        Some(catch_unwind(AssertUnwindSafe(|| handler(job))).unwrap_or_else(|_| Err(String::from("the handler panicked"))))
+    }
+}
+
+/// How long the retry after `attempts` waits.
+fn backoff(attempts: i32) -> Duration {
+    FIRST_RETRY.saturating_mul(2u32.saturating_pow(attempts.max(1) as u32 - 1)).min(MAX_RETRY)
+}
+
+fn store(pool: &DbPool, jobs: Vec<NewJob>) {
+    let db = match pool.get() {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!("Failed to store {} jobs: {:?}", jobs.len(), e);
+            return;
+        }
+    };
+
+    for job in jobs {
+        let kind = job.kind;
+        if let Err(e) = Job::create(&job.into_row(), &db) {
+            tracing::error!("Failed to store a {} job: {:?}", kind, e);
+        }
+    }
+}
+
+/// Claims the jobs that are due and runs them one after the other. Returns how many there were.
+fn run_due(pool: &DbPool, handlers: &JobHandlers) -> i64 {
+    let db = match pool.get() {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!("job error: {:?}", e);
+            return 0;
+        }
+    };
+    let lease = chrono::Duration::from_std(LEASE).unwrap_or_else(|_| chrono::Duration::minutes(10));
+    let due = match Job::claim_due(BATCH, lease, &db) {
+        Ok(due) => due,
+        Err(e) => {
+            tracing::error!("job error: {:?}", e);
+            return 0;
+        }
+    };
+
+    for job in &due {
+        let saved = match handlers.run(job) {
+            Some(Ok(())) => Job::complete(&job.uuid, &db),
+            Some(Err(e)) if job.attempts < job.max_attempts => {
+                tracing::warn!("Job {} ({}) failed, retrying: {}", job.uuid, job.kind, e);
+                let retry = chrono::Duration::from_std(backoff(job.attempts)).unwrap_or_else(|_| chrono::Duration::hours(1));
+                Job::retry(&job.uuid, &e, Utc::now().naive_utc() + retry, &db)
+            }
+            Some(Err(e)) => {
+                tracing::error!("Gave up on job {} ({}) after {} attempts: {}", job.uuid, job.kind, job.attempts, e);
+                Job::fail(&job.uuid, &e, &db)
+            }
+            None => {
+                tracing::error!("Job {} has no handler for {}.", job.uuid, job.kind);
+                Job::fail(&job.uuid, &format!("no handler for {}", job.kind), &db)
+            }
+        };
+
+        if let Err(e) = saved {
+            tracing::error!("Failed to save how job {} went: {:?}", job.uuid, e);
+        }
+    }
+
+    due.len() as i64
+}
+
+/// Stores jobs as they are sent, and runs the ones that are due with `handlers`, until the queue is closed. Jobs that are
+/// waiting when it is, including ones that are retried, stay in the table for the next time the server starts.
+pub fn run(pool: DbPool, jobs: Receiver<NewJob>, handlers: JobHandlers) {
+    let mut wait = POLL;
+
+    loop {
+        match jobs.recv_timeout(wait) {
+            Ok(job) => store(&pool, std::iter::once(job).chain(jobs.try_iter()).collect()),
+            Err(RecvTimeoutError::Timeout) => {}
+            // the channel only says it is closed once everything sent before has been taken.
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        // a full batch means there may be more due, which are run without waiting for the next poll.
+        wait = match run_due(&pool, &handlers) {
+            BATCH => Duration::ZERO,
+            _ => POLL,
+        };
+    }
+}
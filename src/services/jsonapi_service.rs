@@ -0,0 +1,275 @@
+use actix_web::dev::HttpResponseBuilder;
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use super::etag_service;
+use super::pagination_service::Listing;
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::{CategoryDTO, Module, ModuleCategory};
+use crate::models::page_models::{PageDTO, PageModuleDTO};
+
+pub const CONTENT_TYPE: &str = "application/vnd.api+json";
+const JSON: &str = "application/json";
+
+/// Whether JSON:API is the default for every response. Errors can only follow JSON:API when this is on,
+/// as they are built without access to the request.
+pub fn enabled() -> bool {
+    LocalConfig::loaded().is_some_and(|conf| conf.json_api)
+}
+
+/// Clients can ask for JSON:API on any content endpoint through the `Accept` header, even if it isn't the default.
+pub fn wants_json_api(req: &HttpRequest) -> bool {
+    enabled()
+        || req
+            .headers()
+            .get("Accept")
+            .and_then(|accept| accept.to_str().ok())
+            .map(|accept| accept.contains(CONTENT_TYPE))
+            .unwrap_or(false)
+}
+
+/// Maps a DTO onto a JSON:API resource object.
+/// Its serialized fields become the attributes, other than the id and whatever is sent as relationships instead.
+pub trait JsonApiResource: Serialize {
+    const TYPE: &'static str;
+
+    fn id(&self) -> String;
+
+    fn excluded_attributes() -> &'static [&'static str] {
+        &["uuid"]
+    }
+
+    fn relationships(&self) -> Option<Value> {
+        None
+    }
+
+    /// Full resource objects of everything in `relationships` that should be sent along in `included`.
+    fn included(&self) -> Vec<Value> {
+        Vec::new()
+    }
+
+    fn identifier(&self) -> Value {
+        json!({ "type": Self::TYPE, "id": self.id() })
+    }
+
+    fn to_resource(&self) -> Value {
+        let mut attributes = match serde_json::to_value(self) {
+            Ok(Value::Object(attributes)) => attributes,
+            _ => Map::new(),
+        };
+        for excluded in Self::excluded_attributes() {
+            attributes.remove(*excluded);
+        }
+
+        let mut resource = json!({
+            "type": Self::TYPE,
+            "id": self.id(),
+            "attributes": attributes,
+        });
+        if let Some(relationships) = self.relationships() {
+            resource["relationships"] = relationships;
+        }
+
+        resource
+    }
+}
+
+fn to_one(resource_type: &str, id: &str) -> Value {
+    json!({ "data": { "type": resource_type, "id": id } })
+}
+
+fn to_many<T: JsonApiResource>(resources: &[T]) -> Value {
+    json!({ "data": resources.iter().map(|r| r.identifier()).collect::<Vec<_>>() })
+}
+
+impl JsonApiResource for PageDTO {
+    const TYPE: &'static str = "pages";
+
+    fn id(&self) -> String {
+        self.uuid.clone()
+    }
+}
+
+impl JsonApiResource for Module {
+    const TYPE: &'static str = "modules";
+
+    fn id(&self) -> String {
+        self.uuid.clone()
+    }
+
+    fn excluded_attributes() -> &'static [&'static str] {
+        &["uuid", "page_uuid", "category_uuid"]
+    }
+
+    fn relationships(&self) -> Option<Value> {
+        // a global module isn't on any page, which is an empty to-one relationship.
+        let page = match &self.page_uuid {
+            Some(page_uuid) => to_one(PageDTO::TYPE, page_uuid),
+            None => json!({ "data": null }),
+        };
+        let mut relationships = json!({ "page": page });
+
+        if let Some(category_uuid) = &self.category_uuid {
+            relationships["category"] = to_one(ModuleCategory::TYPE, category_uuid);
+        }
+
+        Some(relationships)
+    }
+}
+
+impl JsonApiResource for ModuleCategory {
+    const TYPE: &'static str = "categories";
+
+    fn id(&self) -> String {
+        self.uuid.clone()
+    }
+
+    fn excluded_attributes() -> &'static [&'static str] {
+        &["uuid", "page_uuid"]
+    }
+
+    fn relationships(&self) -> Option<Value> {
+        Some(json!({ "page": to_one(PageDTO::TYPE, &self.page_uuid) }))
+    }
+}
+
+impl JsonApiResource for CategoryDTO {
+    const TYPE: &'static str = "categories";
+
+    fn id(&self) -> String {
+        self.uuid.clone()
+    }
+
+    fn excluded_attributes() -> &'static [&'static str] {
+        &["uuid", "modules"]
+    }
+
+    fn relationships(&self) -> Option<Value> {
+        Some(json!({ "modules": to_many(&self.modules) }))
+    }
+
+    fn included(&self) -> Vec<Value> {
+        self.modules.iter().map(|m| m.to_resource()).collect()
+    }
+}
+
+impl JsonApiResource for PageModuleDTO {
+    const TYPE: &'static str = "pages";
+
+    fn id(&self) -> String {
+        self.uuid.clone()
+    }
+
+    fn excluded_attributes() -> &'static [&'static str] {
+        &["uuid", "fields"]
+    }
+
+    fn relationships(&self) -> Option<Value> {
+        let categories = self.fields.categories.clone().unwrap_or_default();
+
+        Some(json!({
+            "modules": to_many(&self.fields.modules),
+            "categories": to_many(&categories),
+        }))
+    }
+
+    fn included(&self) -> Vec<Value> {
+        let mut included: Vec<Value> = self.fields.modules.iter().map(|m| m.to_resource()).collect();
+
+        for category in self.fields.categories.iter().flatten() {
+            included.push(category.to_resource());
+            included.extend(category.included());
+        }
+
+        included
+    }
+}
+
+/// Content is sent with an ETag of the body, so clients that already have it get a 304 instead.
+fn send<B: Serialize>(req: &HttpRequest, builder: HttpResponseBuilder, content_type: &str, body: &B) -> HttpResponse {
+    match serde_json::to_vec(body) {
+        Ok(body) => etag_service::send(req, builder, content_type, body),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+fn document(req: &HttpRequest, builder: HttpResponseBuilder, data: Value, included: Vec<Value>) -> HttpResponse {
+    let mut document = json!({ "data": data });
+    if !included.is_empty() {
+        document["included"] = Value::Array(included);
+    }
+
+    send(req, builder, CONTENT_TYPE, &document)
+}
+
+/// Sends a single resource, as a JSON:API document if the client wants one and as plain JSON otherwise.
+pub fn respond<T: JsonApiResource>(req: &HttpRequest, builder: HttpResponseBuilder, data: T) -> HttpResponse {
+    match wants_json_api(req) {
+        true => document(req, builder, data.to_resource(), data.included()),
+        false => send(req, builder, JSON, &data),
+    }
+}
+
+pub fn respond_many<T: JsonApiResource>(
+    req: &HttpRequest,
+    builder: HttpResponseBuilder,
+    data: Vec<T>,
+) -> HttpResponse {
+    match wants_json_api(req) {
+        true => document(
+            req,
+            builder,
+            Value::Array(data.iter().map(|d| d.to_resource()).collect()),
+            data.iter().flat_map(|d| d.included()).collect(),
+        ),
+        false => send(req, builder, JSON, &data),
+    }
+}
+
+/// Sends a list, or one page of it. JSON:API documents carry the pagination in their `meta`.
+pub fn respond_listing<T: JsonApiResource>(
+    req: &HttpRequest,
+    builder: HttpResponseBuilder,
+    listing: Listing<T>,
+) -> HttpResponse {
+    let page = match listing {
+        Listing::All(data) => return respond_many(req, builder, data),
+        Listing::Page(page) => page,
+    };
+
+    match wants_json_api(req) {
+        true => {
+            let mut document = json!({
+                "data": page.data.iter().map(|d| d.to_resource()).collect::<Vec<_>>(),
+                "meta": {
+                    "page": page.page,
+                    "per_page": page.per_page,
+                    "total": page.total,
+                    "total_pages": page.total_pages,
+                },
+            });
+            let included: Vec<Value> = page.data.iter().flat_map(|d| d.included()).collect();
+            if !included.is_empty() {
+                document["included"] = Value::Array(included);
+            }
+
+            send(req, builder, CONTENT_TYPE, &document)
+        }
+        false => send(req, builder, JSON, &page),
+    }
+}
+
+/// The JSON:API error object for an error response. The `id` is the ID of the request that failed, when it is known.
+pub fn error_document(status: u16, title: String, detail: String, id: Option<&str>) -> Value {
+    let mut error = json!({
+        "status": status.to_string(),
+        "title": title,
+        "detail": detail,
+    });
+    if let Some(id) = id {
+        error["id"] = json!(id);
+    }
+
+    json!({ "errors": [error] })
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeSet;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+use actix::{Actor, ActorContext, AsyncContext, Handler, Message, Recipient, StreamHandler};
+use actix_web::web::{self, Bytes};
+use actix_web_actors::ws;
+use chrono::NaiveDateTime;
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use futures::stream::{self, LocalBoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use super::site_service;
+use super::webhook_service::WebhookEvent;
+
+/// What a live socket is subscribed to, which is a kind of content like `page` or `module`, one item of it like
+/// `page:<uuid>`, or `*` for everything. A page's topic also covers its modules and categories.
+pub type Topic = String;
+
+/// How often event streams are sent a comment when nothing happens, so that proxies don't close them as idle.
+const HEARTBEAT: Duration = Duration::from_secs(15);
+/// What event streams start with, which tells clients to reconnect after 5 seconds if the stream is cut.
+const STREAM_START: &[u8] = b"retry: 5000\n\n";
+
+/// A change to content, the same as a webhook is sent, on its way to the sockets of the site it happened on.
+#[derive(Message, Clone)]
+#[rtype(result = "()")]
+pub struct LiveEvent(pub WebhookEvent);
+
+impl LiveEvent {
+    /// Whether a socket subscribed to `topic` wants the event.
+    fn matches(&self, topic: &str) -> bool {
+        let kind = self.0.event.split('.').next().unwrap_or_default();
+        let field = |name: &str| self.0.data.get(name).and_then(|value| value.as_str());
+
+        match topic.split_once(':') {
+            _ if topic == "*" => true,
+            None => topic == kind,
+            Some(("page", uuid)) if kind != "page" => field("page_uuid") == Some(uuid),
+            Some((topic_kind, uuid)) => topic_kind == kind && field("uuid") == Some(uuid),
+        }
+    }
+}
+
+/// Every connected live socket, along with the site it connected on.
+#[derive(Default)]
+pub struct LiveHub {
+    sockets: RwLock<Vec<(String, Recipient<LiveEvent>)>>,
+}
+
+impl LiveHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn join(&self, site: String, socket: Recipient<LiveEvent>) {
+        self.sockets.write().unwrap().push((site, socket));
+    }
+
+    fn leave(&self, socket: &Recipient<LiveEvent>) {
+        self.sockets.write().unwrap().retain(|(_, s)| s != socket);
+    }
+
+    /// Sends the event to every socket on the current site, which filter it by what they are subscribed to.
+    /// It is hooked to the webhook queue, so it runs in the request that made the change.
+    pub fn broadcast(&self, event: &WebhookEvent) {
+        let site = site_service::current_or_default();
+
+        for (_, socket) in self.sockets.read().unwrap().iter().filter(|(s, _)| *s == site) {
+            socket.do_send(LiveEvent(event.clone())).ok();
+        }
+    }
+}
+
+/// What clients send to change their subscriptions, like `{ "subscribe": ["page:<uuid>"] }`.
+#[derive(Deserialize)]
+struct Subscription {
+    #[serde(default)]
+    subscribe: Vec<Topic>,
+    #[serde(default)]
+    unsubscribe: Vec<Topic>,
+}
+
+/// What clients are sent back after they change their subscriptions, or when what they sent can't be read.
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Reply<'a> {
+    Subscribed(&'a BTreeSet<Topic>),
+    Error(&'a str),
+}
+
+/// The socket at `/ws`, which is sent every change to what it is subscribed to, as JSON shaped like a webhook's body.
+pub struct LiveSocket {
+    pub site: String,
+    pub hub: web::Data<LiveHub>,
+    pub topics: BTreeSet<Topic>,
+}
+
+impl LiveSocket {
+    fn reply(&self, reply: Reply, ctx: &mut ws::WebsocketContext<Self>) {
+        if let Ok(json) = serde_json::to_string(&reply) {
+            ctx.text(json);
+        }
+    }
+}
+
+impl Actor for LiveSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hub.join(self.site.clone(), ctx.address().recipient());
+        self.reply(Reply::Subscribed(&self.topics), ctx);
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        self.hub.leave(&ctx.address().recipient());
+    }
+}
+
+impl Handler<LiveEvent> for LiveSocket {
+    type Result = ();
+
+    fn handle(&mut self, event: LiveEvent, ctx: &mut Self::Context) {
+        if !self.topics.iter().any(|topic| event.matches(topic)) {
+            return;
+        }
+        if let Ok(json) = serde_json::to_string(&event.0) {
+            ctx.text(json);
+        }
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for LiveSocket {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Text(text)) => match serde_json::from_str::<Subscription>(&text) {
+                Ok(subscription) => {
+                    for topic in &subscription.unsubscribe {
+                        self.topics.remove(topic);
+                    }
+                    self.topics.extend(subscription.subscribe);
+                    self.reply(Reply::Subscribed(&self.topics), ctx);
+                }
+                Err(_) => self.reply(Reply::Error("expected `subscribe` or `unsubscribe` with a list of topics"), ctx),
+            },
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Err(_) => ctx.stop(),
+            _ => {}
+        }
+    }
+}
+
+/// A change as `GET /events` sends it, which is only what changed, for clients to read what they need of it themselves.
+#[derive(Serialize)]
+struct Activity<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    id: Option<&'a str>,
+    action: &'a str,
+    time: NaiveDateTime,
+}
+
+/// Every open `GET /events` stream, along with the site it was opened on.
+#[derive(Default)]
+pub struct EventStreams {
+    streams: Mutex<Vec<(String, UnboundedSender<Bytes>)>>,
+}
+
+impl EventStreams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a stream for the current site, which is sent every event on it until the client goes away.
+    pub fn open(&self) -> LocalBoxStream<'static, Result<Bytes, actix_web::Error>> {
+        let (sender, events) = unbounded();
+        self.streams.lock().unwrap().push((site_service::current_or_default(), sender));
+
+        let heartbeats = stream::unfold((), |_| async {
+            actix_web::rt::time::delay_for(HEARTBEAT).await;
+            Some((Bytes::from_static(b": keepalive\n\n"), ()))
+        });
+
+        let events = stream::iter(Some(Bytes::from_static(STREAM_START))).chain(events);
+        stream::select(events, heartbeats).map(Ok).boxed_local()
+    }
+
+    /// Sends the event to every stream on the current site, dropping the ones whose client has gone away.
+    /// It is hooked to the webhook queue, so it runs in the request that made the change.
+    pub fn broadcast(&self, event: &WebhookEvent) {
+        let (kind, action) = event.event.split_once('.').unwrap_or((event.event, ""));
+        let activity = Activity {
+            kind,
+            id: event.data.get("uuid").and_then(|uuid| uuid.as_str()),
+            action,
+            time: event.time,
+        };
+        let data = match serde_json::to_string(&activity) {
+            Ok(data) => Bytes::from(format!("event: {}\ndata: {}\n\n", event.event, data)),
+            Err(_) => return,
+        };
+
+        let site = site_service::current_or_default();
+        self.streams
+            .lock()
+            .unwrap()
+            .retain(|(s, stream)| *s != site || stream.unbounded_send(data.clone()).is_ok());
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::web;
+use handlebars::Handlebars;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::job_service::{JobQueue, NewJob};
+use crate::models::config_models::LocalConfig;
+use crate::models::job_models::Job;
+
+/// The kind of the jobs that send a mail.
+pub const JOB: &str = "mail.send";
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The bodies mails are sent with when the theme has no `templates/mail/<name>.hbs` of its own.
+const DEFAULTS: &[(&str, &str)] = &[
+    (
+        "form-submission",
+        "<p>{{form}} was submitted.</p>\n<ul>{{#each values}}<li><b>{{@key}}</b>: {{this}}</li>{{/each}}</ul>\n",
+    ),
+    (
+        "password-reset",
+        "<p>A new password was asked for {{username}}. {{#if url}}<a href=\"{{url}}\">Set a new one</a>{{else}}Set a new one with the token <code>{{token}}</code>{{/if}} within {{minutes}} minutes.</p>\n<p>If it wasn't you, the mail can be ignored, and the password stays the same.</p>\n",
+    ),
+    (
+        "webhook-failure",
+        "<p>Delivering <code>{{event}}</code> to {{url}} failed {{attempts}} times, and was given up on.</p>\n<p>{{error}}</p>\n",
+    ),
+];
+
+/// A mail on its way, with its body rendered from the template `mail/<template>` with `data` once it is sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mail {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub template: String,
+    pub data: Value,
+}
+
+/// Where mails are sent to be delivered as jobs by `handler`, so that a request never waits on the SMTP server, and a mail
+/// the server doesn't take is tried again. Without `APP_SMTP_HOST` mails are dropped.
+pub struct MailQueue {
+    jobs: Option<web::Data<JobQueue>>,
+    notify: Vec<String>,
+}
+
+impl MailQueue {
+    pub fn new(conf: &LocalConfig, jobs: web::Data<JobQueue>) -> Self {
+        let enabled = conf.smtp_host.as_deref().is_some_and(|h| !h.is_empty());
+        let notify = conf
+            .mail_notify
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|to| !to.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Self {
+            jobs: Some(jobs).filter(|_| enabled),
+            notify,
+        }
+    }
+
+    pub fn send(&self, mail: Mail) {
+        if mail.to.is_empty() {
+            return;
+        }
+        // nothing would send it when mail isn't set up, which is fine.
+        if let Some(jobs) = &self.jobs {
+            jobs.send(NewJob::new(JOB, mail));
+        }
+    }
+
+    /// Tells whoever is in `APP_MAIL_NOTIFY`, if anyone is.
+    pub fn notify(&self, subject: String, template: &'static str, data: Value) {
+        self.send(Mail {
+            to: self.notify.clone(),
+            subject,
+            template: String::from(template),
+            data,
+        });
+    }
+}
+
+/// The SMTP connection mails are sent through, and who they are from, if `APP_SMTP_HOST` is set.
+pub fn mailer(conf: &LocalConfig) -> Result<Option<(SmtpTransport, Mailbox)>, String> {
+    let host = match conf.smtp_host.as_deref().filter(|h| !h.is_empty()) {
+        Some(host) => host,
+        None => return Ok(None),
+    };
+    let from: Mailbox = conf.mail_from.as_deref().unwrap_or_default().parse().map_err(|e| format!("APP_MAIL_FROM: {}", e))?;
+
+    let mut builder = match conf.smtp_tls.as_str() {
+        "tls" => SmtpTransport::relay(host).map_err(|e| e.to_string())?,
+        "none" => SmtpTransport::builder_dangerous(host),
+        _ => SmtpTransport::starttls_relay(host).map_err(|e| e.to_string())?,
+    };
+    if let Some(port) = conf.smtp_port {
+        builder = builder.port(port);
+    }
+    if let (Some(username), Some(password)) = (&conf.smtp_username, &conf.smtp_password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    Ok(Some((builder.timeout(Some(TIMEOUT)).build(), from)))
+}
+
+/// The body of the mail, from the theme's template if it has one.
+fn render(mail: &Mail, hb: &Handlebars) -> Result<String, String> {
+    let name = format!("mail/{}", mail.template);
+    if hb.has_template(&name) {
+        return hb.render(&name, &mail.data).map_err(|e| e.to_string());
+    }
+
+    let template = DEFAULTS.iter().find(|(name, _)| *name == mail.template).map(|(_, t)| *t).unwrap_or_default();
+    hb.render_template(template, &mail.data).map_err(|e| e.to_string())
+}
+
+fn message(mail: &Mail, from: &Mailbox, body: String) -> Result<Message, String> {
+    let mut builder = Message::builder().from(from.clone()).subject(mail.subject.clone()).header(ContentType::TEXT_HTML);
+    for to in &mail.to {
+        builder = builder.to(to.parse().map_err(|e| format!("{}: {}", to, e))?);
+    }
+
+    builder.body(body).map_err(|e| e.to_string())
+}
+
+/// Runs the jobs of `JOB`, sending the mail each one holds.
+pub fn handler(
+    mailer: SmtpTransport,
+    from: Mailbox,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+) -> impl Fn(&Job) -> Result<(), String> + Send {
+    move |job| {
+        let mail: Mail = job.data()?;
+        let body = render(&mail, &hb.lock().unwrap())?;
+
+        mailer.send(&message(&mail, &from, body)?).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
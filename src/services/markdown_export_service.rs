@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+
+use super::errors_service::CustomHttpError;
+use crate::models::module_models::Module;
+use crate::models::page_models::{Page, PageDTO, PageModuleDTO};
+use crate::models::{DbConnection, Model};
+
+/// The static site generators content can be exported for.
+/// They mostly differ in where files go and what the front matter keys are called.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Hugo,
+    Jekyll,
+    Zola,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "hugo" => Some(Self::Hugo),
+            "jekyll" => Some(Self::Jekyll),
+            "zola" => Some(Self::Zola),
+            _ => None,
+        }
+    }
+
+    fn content_dir(&self) -> &'static str {
+        match self {
+            Self::Hugo | Self::Zola => "content",
+            Self::Jekyll => "",
+        }
+    }
+
+    fn assets_dir(&self) -> &'static str {
+        match self {
+            Self::Hugo | Self::Zola => "static/assets",
+            Self::Jekyll => "assets",
+        }
+    }
+
+    /// The file a page's URL gets written to, relative to the content directory.
+    fn file_for(&self, url: &str) -> PathBuf {
+        let path = url.trim_matches('/');
+
+        match (path.is_empty(), self) {
+            (true, Self::Jekyll) => PathBuf::from("index.md"),
+            (true, _) => PathBuf::from("_index.md"),
+            (false, _) => PathBuf::from(format!("{}.md", path)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ExportReport {
+    /// The files that were written, relative to the output directory.
+    pub written: Vec<PathBuf>,
+    pub assets_copied: usize,
+}
+
+/// Modules that the importer creates from front matter are turned back into front matter, rather than extra params.
+const CONTENT_MODULE: &str = "content";
+const DATE_MODULE: &str = "date";
+const TAGS_MODULE: &str = "tags";
+
+fn front_matter(format: ExportFormat, page: &PageModuleDTO, modules: &[Module]) -> (Value, String) {
+    let module = |title: &str| modules.iter().find(|m| m.title == title).map(|m| m.content.clone());
+
+    let body = module(CONTENT_MODULE).unwrap_or_default();
+    let date = module(DATE_MODULE).unwrap_or_else(|| page.time_created.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+    let tags: Vec<String> = module(TAGS_MODULE)
+        .map(|t| t.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+        .unwrap_or_default();
+
+    // everything else is kept as custom params, which every generator can reach from its templates.
+    let mut extra = Map::new();
+    for m in modules.iter().filter(|m| ![CONTENT_MODULE, DATE_MODULE, TAGS_MODULE].contains(&m.title.as_str())) {
+        extra.insert(m.title.clone(), Value::String(m.content.clone()));
+    }
+    for category in page.fields.categories.iter().flatten() {
+        let contents = category
+            .modules
+            .iter()
+            .filter(|m| !m.encrypted)
+            .map(|m| Value::String(m.content.clone()))
+            .collect();
+        extra.insert(category.title.clone(), Value::Array(contents));
+    }
+
+    let mut front_matter = json!({ "title": page.page_title, "date": date });
+    if let Some(description) = page.meta_description.as_ref().filter(|d| !d.is_empty()) {
+        front_matter["description"] = json!(description);
+    }
+    match format {
+        ExportFormat::Hugo => {
+            front_matter["url"] = json!(page.page_url);
+            front_matter["layout"] = json!(page.page_name);
+            front_matter["tags"] = json!(tags);
+            front_matter["params"] = Value::Object(extra);
+        }
+        ExportFormat::Jekyll => {
+            front_matter["permalink"] = json!(page.page_url);
+            front_matter["layout"] = json!(page.page_name);
+            front_matter["tags"] = json!(tags);
+            for (key, value) in extra {
+                front_matter[key] = value;
+            }
+        }
+        ExportFormat::Zola => {
+            front_matter["path"] = json!(page.page_url.trim_matches('/'));
+            front_matter["template"] = json!(format!("{}.html", page.page_name));
+            front_matter["taxonomies"] = json!({ "tags": tags });
+            front_matter["extra"] = Value::Object(extra);
+        }
+    }
+
+    (front_matter, body)
+}
+
+fn render(format: ExportFormat, front_matter: &Value, body: &str) -> Result<String, String> {
+    match format {
+        ExportFormat::Zola => {
+            let front_matter = toml::Value::try_from(front_matter)
+                .and_then(|v| toml::to_string(&v))
+                .map_err(|e| e.to_string())?;
+            Ok(format!("+++\n{}+++\n\n{}", front_matter, body))
+        }
+        _ => {
+            // serde_yaml starts documents with the opening `---` itself.
+            let front_matter = serde_yaml::to_string(front_matter).map_err(|e| e.to_string())?;
+            Ok(format!("{}---\n\n{}", front_matter, body))
+        }
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<usize> {
+    let mut copied = 0;
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let target = to.join(path.file_name().unwrap_or_default());
+
+        if path.is_dir() {
+            copied += copy_dir(&path, &target)?;
+        } else {
+            fs::copy(&path, &target)?;
+            copied += 1;
+        }
+    }
+
+    Ok(copied)
+}
+
+fn write(path: &Path, contents: String) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, contents)
+}
+
+/// Writes every page out as a source tree for the given static site generator, along with the theme's assets from `assets`.
+/// The page body comes from its `content` module and the other modules become params in the front matter,
+/// so a tree exported here can be imported back with `markdown_import_service`.
+/// Encrypted modules are left out entirely.
+pub fn export(format: ExportFormat, out: &Path, assets: &Path, db: &DbConnection) -> Result<ExportReport, CustomHttpError> {
+    let mut report = ExportReport::default();
+    let pages: Vec<PageDTO> = Page::read_all(db)?;
+
+    for page in pages {
+        let page = Page::read_one_join_on(page.uuid, db)?;
+        let modules: Vec<Module> = page.fields.modules.iter().filter(|m| !m.encrypted).cloned().collect();
+
+        let (front_matter, body) = front_matter(format, &page, &modules);
+        let contents = render(format, &front_matter, &body).map_err(|_| CustomHttpError::Unknown)?;
+
+        let file = Path::new(format.content_dir()).join(format.file_for(&page.page_url));
+        write(&out.join(&file), contents).map_err(|_| CustomHttpError::Unknown)?;
+        report.written.push(file);
+    }
+
+    if assets.is_dir() {
+        report.assets_copied = copy_dir(assets, &out.join(format.assets_dir()))
+            .map_err(|_| CustomHttpError::Unknown)?;
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,264 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use diesel::Connection;
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::errors_service::CustomHttpError;
+use super::plugin_service::Plugins;
+use crate::controllers::module_controllers::write_new_module;
+use crate::controllers::page_controllers::write_new_page;
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::MutModule;
+use crate::models::page_models::{MutPage, Page, PUBLISHED};
+use crate::models::DbConnection;
+
+/// The template pages are rendered with when the front matter has no `layout`.
+const DEFAULT_TEMPLATE: &str = "index";
+
+const TOML_DATETIME: &str = "$__toml_private_datetime";
+
+/// A Markdown file split into its front matter and body.
+/// Both YAML (`---`) and TOML (`+++`) front matter are understood, as used by Jekyll/Hugo and Hugo/Zola respectively.
+pub struct MarkdownDocument {
+    pub front_matter: Value,
+    pub body: String,
+}
+
+#[derive(Default)]
+pub struct ImportReport {
+    /// The URLs of the pages that were created.
+    pub imported: Vec<String>,
+    /// Files that were left alone, along with why.
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+pub fn parse(source: &str) -> Result<MarkdownDocument, String> {
+    let source = source.trim_start_matches('\u{feff}');
+
+    let delimiter = match source.lines().next().map(|l| l.trim_end()) {
+        Some("---") => "---",
+        Some("+++") => "+++",
+        _ => {
+            return Ok(MarkdownDocument {
+                front_matter: Value::Null,
+                body: source.to_string(),
+            })
+        }
+    };
+
+    // keeps the newline ending the opening delimiter, so that empty front matter is found by the same search.
+    let rest = &source[source.find('\n').unwrap_or(source.len())..];
+    let (raw, body) = match rest.find(&format!("\n{}", delimiter)) {
+        Some(end) => {
+            let after = &rest[end + 1 + delimiter.len()..];
+            (rest[..end].trim_start_matches('\n'), after.trim_start_matches(['\r', '\n']))
+        }
+        None => return Err(String::from("Front matter is never closed")),
+    };
+
+    let front_matter = match delimiter {
+        _ if raw.trim().is_empty() => Value::Null,
+        "---" => serde_yaml::from_str::<serde_yaml::Value>(raw)
+            .map_err(|e| e.to_string())
+            .and_then(|v| serde_json::to_value(v).map_err(|e| e.to_string()))?,
+        _ => toml::from_str::<toml::Value>(raw)
+            .map_err(|e| e.to_string())
+            .and_then(|v| serde_json::to_value(v).map_err(|e| e.to_string()))?,
+    };
+
+    Ok(MarkdownDocument {
+        front_matter,
+        body: body.to_string(),
+    })
+}
+
+/// Lowercases and replaces everything that isn't alphanumeric with dashes.
+pub fn slugify(s: &str) -> String {
+    s.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn front_matter_str(front_matter: &Value, key: &str) -> Option<String> {
+    match front_matter.get(key)? {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        // TOML dates are their own type, which serializes as an object holding the date as a string.
+        Value::Object(o) if o.contains_key(TOML_DATETIME) => o[TOML_DATETIME].as_str().map(String::from),
+        Value::Null | Value::String(_) => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Tags can either be a list or a single comma separated string. Hugo and Zola also nest them under `taxonomies`.
+fn tags(front_matter: &Value) -> Vec<String> {
+    let tags = front_matter
+        .get("tags")
+        .or_else(|| front_matter.get("taxonomies").and_then(|t| t.get("tags")));
+
+    match tags {
+        Some(Value::Array(tags)) => tags
+            .iter()
+            .filter_map(|t| t.as_str().map(String::from))
+            .collect(),
+        Some(Value::String(tags)) => tags
+            .split(',')
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Works out the URL the same way static site generators do.
+/// An explicit `url` or `permalink` wins, then the `slug` within the file's directory, then the file path itself.
+/// `index.md` and `_index.md` are the page for their directory.
+fn page_url(front_matter: &Value, relative_path: &Path) -> String {
+    if let Some(url) = front_matter_str(front_matter, "url").or_else(|| front_matter_str(front_matter, "permalink")) {
+        return format!("/{}", url.trim_matches('/'));
+    }
+
+    let mut segments: Vec<String> = relative_path
+        .parent()
+        .map(|p| p.iter().map(|s| slugify(&s.to_string_lossy())).collect())
+        .unwrap_or_default();
+
+    let stem = relative_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match front_matter_str(front_matter, "slug") {
+        Some(slug) => segments.push(slugify(&slug)),
+        None if stem == "index" || stem == "_index" => {}
+        None => segments.push(slugify(&stem)),
+    }
+
+    format!("/{}", segments.join("/"))
+}
+
+fn markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            markdown_files(&path, files)?;
+        } else if matches!(path.extension().and_then(|e| e.to_str()), Some("md") | Some("markdown")) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn import_file(
+    document: MarkdownDocument,
+    url: String,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(), CustomHttpError> {
+    let front_matter = &document.front_matter;
+    let page_uuid = Uuid::new_v4().to_string();
+
+    let title = front_matter_str(front_matter, "title").unwrap_or_else(|| url.clone());
+    let template = front_matter_str(front_matter, "layout")
+        .or_else(|| front_matter_str(front_matter, "template"))
+        .unwrap_or_else(|| String::from(DEFAULT_TEMPLATE));
+
+    let page = MutPage {
+        uuid: Some(page_uuid.clone()),
+        page_name: template,
+        page_url: url,
+        page_title: title,
+        status: Some(String::from(PUBLISHED)),
+        parent_page: None,
+        meta_description: front_matter_str(front_matter, "description"),
+        meta_keywords: None,
+        og_image: None,
+        noindex: None,
+        locale: None,
+        translation_group: None,
+        visibility: None,
+    };
+
+    let mut modules = vec![(String::from("content"), document.body)];
+    if let Some(date) = front_matter_str(front_matter, "date") {
+        modules.push((String::from("date"), date));
+    }
+    let tags = tags(front_matter);
+    if !tags.is_empty() {
+        modules.push((String::from("tags"), tags.join(",")));
+    }
+
+    db.transaction(|| {
+        write_new_page(page, conf, plugins, db)?;
+
+        for (title, content) in modules {
+            let module = MutModule {
+                uuid: None,
+                page_uuid: Some(page_uuid.clone()),
+                category_uuid: None,
+                title,
+                content,
+                encrypted: false,
+                compressed: false,
+                visible_to: None,
+                order_index: None,
+                module_type: None,
+            };
+            write_new_module(module, conf, plugins, db)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Imports every Markdown file under `dir` as a page, with its body in a `content` module
+/// and its `date` and `tags` front matter in modules of the same name.
+/// Drafts and files whose URL is already taken are skipped rather than overwritten. Pages are written as the API writes
+/// them, content hooks included.
+pub fn import_dir(
+    dir: &Path,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> std::io::Result<ImportReport> {
+    let mut files = Vec::new();
+    markdown_files(dir, &mut files)?;
+    files.sort();
+
+    let mut report = ImportReport::default();
+
+    for path in files {
+        let document = match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|s| parse(&s)) {
+            Ok(document) => document,
+            Err(e) => {
+                report.skipped.push((path, e));
+                continue;
+            }
+        };
+
+        if document.front_matter.get("draft").and_then(|d| d.as_bool()).unwrap_or(false) {
+            report.skipped.push((path, String::from("Draft")));
+            continue;
+        }
+
+        let url = page_url(&document.front_matter, path.strip_prefix(dir).unwrap_or(&path));
+
+        if Page::read_one_by_url(url.clone(), db).is_ok() {
+            report.skipped.push((path, format!("A page already exists at {}", url)));
+            continue;
+        }
+
+        match import_file(document, url.clone(), conf, plugins, db) {
+            Ok(_) => report.imported.push(url),
+            Err(e) => report.skipped.push((path, e.descriptor())),
+        }
+    }
+
+    Ok(report)
+}
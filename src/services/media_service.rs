@@ -0,0 +1,312 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use actix_multipart::{Field, Multipart};
+use actix_web::web;
+use diesel::result::Error::NotFound;
+use futures::StreamExt;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use utoipa::{IntoParams, ToSchema};
+
+use super::errors_service::CustomHttpError;
+use super::media_store_service::{staging_path, MediaStore};
+use super::validation_service::FieldError;
+use crate::models::config_models::LocalConfig;
+use crate::models::job_models::Job;
+use crate::models::media_models::Media;
+use crate::models::{DbPool, Model};
+
+/// The kind of the jobs that make the sizes of `APP_MEDIA_PRESETS` of an upload.
+pub const JOB: &str = "media.variants";
+/// Resized images are kept in the store under here, so they are only made once.
+const VARIANTS_PREFIX: &str = "variants";
+/// The largest width or height that can be asked for, so that one request can't take up all of the memory.
+const MAX_DIMENSION: u32 = 4000;
+/// The widths and heights made for clients that aren't logged in, who get the next one up from what they ask for, so
+/// that there are only so many sizes of an upload they can have made.
+const STEPS: [u32; 16] = [16, 32, 64, 128, 256, 320, 480, 640, 768, 1024, 1280, 1536, 1920, 2560, 3200, MAX_DIMENSION];
+
+/// What is known about an upload once all of it has been written.
+pub struct Upload {
+    pub filename: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub checksum: String,
+}
+
+/// How an image is made to fit the size asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Fit {
+    /// Scaled to fit inside the size, keeping its aspect ratio.
+    #[default]
+    Contain,
+    /// Scaled to fill the size, keeping its aspect ratio and cropping what is left over from the middle.
+    Cover,
+    /// Stretched to the size.
+    Fill,
+}
+
+impl Fit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Contain => "contain",
+            Self::Cover => "cover",
+            Self::Fill => "fill",
+        }
+    }
+}
+
+/// `?w=`, `?h=`, and `?fit=` on `GET /media/{id}`. With only one of the width and height, the other follows the aspect ratio.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq, IntoParams)]
+pub struct ResizeQuery {
+    pub w: Option<u32>,
+    pub h: Option<u32>,
+    #[serde(default)]
+    pub fit: Fit,
+}
+
+impl ResizeQuery {
+    /// Whether a size was asked for at all. The file is sent as it was uploaded otherwise.
+    pub fn is_requested(&self) -> bool {
+        self.w.is_some() || self.h.is_some()
+    }
+}
+
+/// The sizes of `APP_MEDIA_PRESETS`, like `400x0,1200x630-cover`, which are made of every image as it is uploaded so that
+/// the first request for one doesn't wait for it. A `0` leaves that side to follow the aspect ratio.
+pub fn presets(value: Option<&str>) -> Result<Vec<ResizeQuery>, String> {
+    value.into_iter().flat_map(|v| v.split(',')).map(str::trim).filter(|p| !p.is_empty()).map(preset).collect()
+}
+
+fn preset(value: &str) -> Result<ResizeQuery, String> {
+    let invalid = || format!("should be comma separated sizes like `400x0` or `1200x630-cover`, not `{}`", value);
+    let (size, fit) = value.split_once('-').unwrap_or((value, Fit::Contain.as_str()));
+    let fit = [Fit::Contain, Fit::Cover, Fit::Fill].iter().copied().find(|f| f.as_str() == fit).ok_or_else(invalid)?;
+    let (w, h) = size.split_once('x').ok_or_else(invalid)?;
+    let side = |side: &str| side.parse::<u32>().map(|n| Some(n).filter(|n| *n > 0)).map_err(|_| invalid());
+
+    let query = ResizeQuery { w: side(w)?, h: side(h)?, fit };
+    if !query.is_requested() || [query.w, query.h].iter().flatten().any(|n| *n > MAX_DIMENSION) {
+        return Err(invalid());
+    }
+
+    Ok(query)
+}
+
+/// Whether an upload gets a job to make the sizes of `APP_MEDIA_PRESETS`, which only images that can be resized do.
+pub fn has_presets(conf: &LocalConfig, media: &Media) -> bool {
+    resizable(&media.mime_type).is_some() && presets(conf.media_presets.as_deref()).is_ok_and(|p| !p.is_empty())
+}
+
+/// Runs the jobs of `JOB`, making every size of `APP_MEDIA_PRESETS` of the upload that hasn't been made yet.
+pub fn handler(pool: DbPool, conf: LocalConfig, store: web::Data<dyn MediaStore>) -> impl Fn(&Job) -> Result<(), String> + Send {
+    move |job| {
+        let uuid = job.data::<serde_json::Value>()?["media"].as_str().map(str::to_string).ok_or("invalid payload")?;
+        let media = match Media::read_one(uuid, &*pool.get().map_err(|e| e.to_string())?) {
+            Ok(media) => media,
+            // deleted before it came to this.
+            Err(NotFound) => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        };
+
+        for preset in presets(conf.media_presets.as_deref())? {
+            variant(&**store, &conf, &media, &preset).map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn variants_prefix(uuid: &str) -> String {
+    format!("{}/{}/", VARIANTS_PREFIX, uuid)
+}
+
+/// Removes an upload's file along with every size of it that was made. Files are stored by uuid rather than by name,
+/// so two uploads with the same name can't overwrite each other.
+pub fn remove(store: &dyn MediaStore, uuid: &str) -> Result<(), CustomHttpError> {
+    store.delete(uuid)?;
+    for key in store.list(&variants_prefix(uuid))? {
+        store.delete(&key)?;
+    }
+
+    Ok(())
+}
+
+fn invalid(field: &str, message: &str) -> CustomHttpError {
+    CustomHttpError::Invalid(vec![FieldError {
+        field: String::from(field),
+        message: String::from(message),
+    }])
+}
+
+/// The formats that can be resized, which are kept in the format they were uploaded in.
+fn resizable(mime_type: &str) -> Option<ImageFormat> {
+    ImageFormat::from_mime_type(mime_type)
+        .filter(|f| matches!(f, ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP))
+}
+
+fn check_dimension(field: &str, value: Option<u32>) -> Result<(), CustomHttpError> {
+    match value {
+        Some(value) if value == 0 || value > MAX_DIMENSION => {
+            Err(invalid(field, &format!("must be between 1 and {}", MAX_DIMENSION)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The size made for a client that isn't logged in. With `APP_MEDIA_PRESETS` set only those sizes can be asked for, and
+/// otherwise the width and height are rounded up to one of `STEPS`.
+pub fn for_visitor(conf: &LocalConfig, query: &ResizeQuery) -> Result<ResizeQuery, CustomHttpError> {
+    check_dimension("w", query.w)?;
+    check_dimension("h", query.h)?;
+
+    let presets = presets(conf.media_presets.as_deref()).unwrap_or_default();
+    if !presets.is_empty() {
+        return match presets.contains(query) {
+            true => Ok(query.clone()),
+            false => Err(invalid("w", "must be one of the sizes of APP_MEDIA_PRESETS")),
+        };
+    }
+
+    let step = |side: Option<u32>| side.map(|n| STEPS.iter().copied().find(|step| *step >= n).unwrap_or(MAX_DIMENSION));
+    Ok(ResizeQuery {
+        w: step(query.w),
+        h: step(query.h),
+        fit: query.fit,
+    })
+}
+
+/// The width and height to resize to, working out whichever is missing from the original's aspect ratio.
+fn target_size(image: &DynamicImage, query: &ResizeQuery) -> (u32, u32) {
+    let (width, height) = (image.width().max(1) as u64, image.height().max(1) as u64);
+    let scaled = |value: u32, from: u64, to: u64| ((value as u64 * to + from / 2) / from).clamp(1, MAX_DIMENSION as u64) as u32;
+
+    match (query.w, query.h) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, scaled(w, width, height)),
+        (None, Some(h)) => (scaled(h, height, width), h),
+        (None, None) => (width as u32, height as u32),
+    }
+}
+
+/// The upload resized as `query` asks, which is made the first time it is asked for and read from the store after that.
+/// Animated GIFs only keep their first frame.
+pub fn variant(store: &dyn MediaStore, conf: &LocalConfig, media: &Media, query: &ResizeQuery) -> Result<PathBuf, CustomHttpError> {
+    check_dimension("w", query.w)?;
+    check_dimension("h", query.h)?;
+    let format = resizable(&media.mime_type).ok_or_else(|| invalid("w", "can only be used with PNG, JPEG, GIF, and WebP images"))?;
+
+    let key = format!(
+        "{}{}x{}-{}",
+        variants_prefix(&media.uuid),
+        query.w.unwrap_or(0),
+        query.h.unwrap_or(0),
+        query.fit.as_str()
+    );
+    if let Some(path) = store.fetch(&key)? {
+        return Ok(path);
+    }
+
+    let original = store.fetch(&media.uuid)?.ok_or(CustomHttpError::NotFound)?;
+    let reader = ImageReader::open(&original).or(Err(CustomHttpError::NotFound))?;
+    let image = reader.with_guessed_format().ok().and_then(|r| r.decode().ok()).ok_or_else(|| {
+        tracing::warn!("Failed to read media {} as an image.", media.uuid);
+        invalid("w", "can only be used with files that are images")
+    })?;
+
+    let (w, h) = target_size(&image, query);
+    let resized = match query.fit {
+        Fit::Contain => image.resize(w, h, FilterType::Lanczos3),
+        Fit::Cover => image.resize_to_fill(w, h, FilterType::Lanczos3),
+        Fit::Fill => image.resize_exact(w, h, FilterType::Lanczos3),
+    };
+    // JPEG has no alpha channel to save.
+    let resized = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(resized.to_rgb8()),
+        _ => resized,
+    };
+
+    // written under another name first, so a request that comes in meanwhile never reads half of it.
+    let partial = staging_path(conf)?;
+    if let Err(e) = resized.save_with_format(&partial, format) {
+        tracing::error!("Failed to save a resized copy of media {}: {}", media.uuid, e);
+        fs::remove_file(&partial).ok();
+        return Err(CustomHttpError::Unknown);
+    }
+    let stored = store.put(&key, &partial, &media.mime_type);
+    if stored.is_err() {
+        fs::remove_file(&partial).ok();
+    }
+    stored?;
+
+    store.fetch(&key)?.ok_or(CustomHttpError::Unknown)
+}
+
+/// Only the name of the file is kept, without any of the path the client might have sent with it.
+fn clean_filename(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+
+    match name.is_empty() {
+        true => String::from("upload"),
+        false => name.chars().take(255).collect(),
+    }
+}
+
+/// Writes the field to `path` as it comes in, hashing it along the way, until it is over `limit` bytes.
+async fn write(field: &mut Field, path: &Path, limit: u64) -> Result<(i64, String), CustomHttpError> {
+    let mut file = fs::File::create(path).or(Err(CustomHttpError::Unknown))?;
+    let mut hasher = Sha256::new();
+    let mut size: u64 = 0;
+
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk.or(Err(CustomHttpError::BadRequest))?;
+
+        size += chunk.len() as u64;
+        if size > limit {
+            return Err(CustomHttpError::TooLarge { limit });
+        }
+
+        hasher.update(&chunk);
+        file.write_all(&chunk).or(Err(CustomHttpError::Unknown))?;
+    }
+
+    let checksum = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((size as i64, checksum))
+}
+
+/// Saves the first file in the form to `path`. Fields that aren't files are skipped,
+/// and nothing is left behind when the upload fails part of the way through.
+pub async fn receive(mut form: Multipart, path: &Path, limit: u64) -> Result<Upload, CustomHttpError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).or(Err(CustomHttpError::Unknown))?;
+    }
+
+    while let Some(field) = form.next().await {
+        let mut field = field.or(Err(CustomHttpError::BadRequest))?;
+        let filename = match field.content_disposition().and_then(|cd| cd.get_filename().map(clean_filename)) {
+            Some(filename) => filename,
+            None => continue,
+        };
+        let mime_type = field.content_type().to_string();
+
+        let written = write(&mut field, path, limit).await;
+        if written.is_err() {
+            fs::remove_file(path).ok();
+        }
+        let (size, checksum) = written?;
+
+        return Ok(Upload {
+            filename,
+            mime_type,
+            size,
+            checksum,
+        });
+    }
+
+    Err(CustomHttpError::BadRequest)
+}
@@ -0,0 +1,313 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use url::Url;
+use uuid::Uuid;
+
+use super::static_service::file_in;
+use crate::models::config_models::LocalConfig;
+
+/// Where uploads are kept on the local disk, unless `APP_MEDIA_DIR` says otherwise.
+const DEFAULT_MEDIA_DIR: &str = "./media";
+/// Files are written here, under the media directory, until they are finished and can be moved into the store.
+const STAGING_DIR: &str = "tmp";
+const DEFAULT_S3_REGION: &str = "us-east-1";
+/// Requests to S3 that take longer than this are counted as failed.
+const S3_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum MediaStoreError {
+    #[error("The media store is not configured correctly.")]
+    Config,
+    #[error("The media store could not be reached: {0}")]
+    Unavailable(String),
+}
+
+impl From<io::Error> for MediaStoreError {
+    fn from(e: io::Error) -> Self {
+        MediaStoreError::Unavailable(e.to_string())
+    }
+}
+
+/// The directory uploads are written to before they are stored, and where a store that isn't local keeps its copies.
+pub fn media_dir(conf: &LocalConfig) -> PathBuf {
+    PathBuf::from(conf.media_dir.as_deref().unwrap_or(DEFAULT_MEDIA_DIR))
+}
+
+/// A new file to write to under `dir`, which a store kept in `dir` can move in with `put` once it is finished.
+pub fn staging_in(dir: &Path) -> Result<PathBuf, MediaStoreError> {
+    let dir = dir.join(STAGING_DIR);
+    fs::create_dir_all(&dir)?;
+
+    Ok(dir.join(Uuid::new_v4().to_string()))
+}
+
+/// A new file to write to, which can be moved into the store with `put` once it is finished.
+pub fn staging_path(conf: &LocalConfig) -> Result<PathBuf, MediaStoreError> {
+    staging_in(&media_dir(conf))
+}
+
+/// Where uploads and their resized copies are kept, by keys like `<uuid>` or `variants/<uuid>/400x0-contain`.
+/// Files are always served from the local disk, so a store that is somewhere else keeps a copy of what it has read.
+pub trait MediaStore: Send + Sync {
+    /// Moves the finished file at `file`, which has to be under `APP_MEDIA_DIR`, into the store as `key`.
+    fn put(&self, key: &str, file: &Path, content_type: &str) -> Result<(), MediaStoreError>;
+    /// A file on the local disk with what is stored as `key`, or `None` when nothing is.
+    fn fetch(&self, key: &str) -> Result<Option<PathBuf>, MediaStoreError>;
+    /// Deleting a key that isn't stored does nothing.
+    fn delete(&self, key: &str) -> Result<(), MediaStoreError>;
+    /// The keys under `prefix`, which ends with a `/`.
+    fn list(&self, prefix: &str) -> Result<Vec<String>, MediaStoreError>;
+}
+
+fn remove(path: &Path) -> Result<(), MediaStoreError> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Keeps everything in `APP_MEDIA_DIR`, which works for a single server or servers that share the directory.
+pub struct LocalStore {
+    dir: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Keys are made by the server, but are still kept from reaching outside of the directory.
+    fn path(&self, key: &str) -> Result<PathBuf, MediaStoreError> {
+        file_in(&self.dir, key).ok_or(MediaStoreError::Config)
+    }
+}
+
+impl MediaStore for LocalStore {
+    fn put(&self, key: &str, file: &Path, _: &str) -> Result<(), MediaStoreError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Ok(fs::rename(file, path)?)
+    }
+
+    fn fetch(&self, key: &str) -> Result<Option<PathBuf>, MediaStoreError> {
+        let path = self.path(key)?;
+
+        Ok(Some(path).filter(|p| p.is_file()))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), MediaStoreError> {
+        let path = self.path(key)?;
+        remove(&path)?;
+
+        // the directory a resized image was in goes along with the last of them.
+        if key.contains('/') {
+            if let Some(parent) = path.parent() {
+                fs::remove_dir(parent).ok();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>, MediaStoreError> {
+        let entries = match fs::read_dir(self.path(prefix.trim_end_matches('/'))?) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(entries
+            .flatten()
+            .filter_map(|e| e.file_name().into_string().ok())
+            .map(|name| format!("{}{}", prefix, name))
+            .collect())
+    }
+}
+
+/// Keeps everything in an S3 bucket, or anything that speaks the S3 API like MinIO, so servers don't need a disk in common.
+/// What has been read is kept in `APP_MEDIA_DIR`, which can be emptied at any time.
+pub struct S3Store {
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    cache: LocalStore,
+    agent: ureq::Agent,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    // HMAC accepts keys of any length, so this can't fail.
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent encodes everything but the characters S3 leaves as they are, and `/` too unless it is a query value.
+fn encode(value: &str, keep_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b'/' if keep_slash => String::from("/"),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+impl S3Store {
+    /// The bucket of `APP_S3_BUCKET`, keeping what has been read from it in `cache`.
+    pub fn from_conf(conf: &LocalConfig, cache: PathBuf) -> Result<Self, MediaStoreError> {
+        let endpoint = conf.s3_endpoint.as_deref().ok_or(MediaStoreError::Config)?;
+        let required = |value: &Option<String>| value.clone().filter(|v| !v.is_empty()).ok_or(MediaStoreError::Config);
+
+        Ok(Self {
+            endpoint: Url::parse(endpoint).or(Err(MediaStoreError::Config))?,
+            bucket: required(&conf.s3_bucket)?,
+            region: conf.s3_region.clone().unwrap_or_else(|| String::from(DEFAULT_S3_REGION)),
+            access_key: required(&conf.s3_access_key)?,
+            secret_key: required(&conf.s3_secret_key)?,
+            cache: LocalStore::new(cache),
+            agent: ureq::AgentBuilder::new().timeout(S3_TIMEOUT).build(),
+        })
+    }
+
+    /// A request for `key` in the bucket, addressed by path so it works the same with S3 and MinIO,
+    /// and signed with AWS Signature Version 4. `query` has to be sorted by name.
+    fn request(&self, method: &str, key: &str, query: &[(&str, &str)], body: &[u8]) -> ureq::Request {
+        let mut path = format!("{}/{}", self.endpoint.path().trim_end_matches('/'), self.bucket);
+        if !key.is_empty() {
+            path = format!("{}/{}", path, key);
+        }
+        let path = encode(&path, true);
+        let query: Vec<String> = query.iter().map(|(k, v)| format!("{}={}", encode(k, false), encode(v, false))).collect();
+        let query = query.join("&");
+
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{}", self.endpoint.host_str().unwrap_or_default(), port),
+            None => self.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+        let now = Utc::now();
+        let (date, time) = (now.format("%Y%m%d").to_string(), now.format("%Y%m%dT%H%M%SZ").to_string());
+        let payload = hex(&Sha256::digest(body));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, query, host, payload, time, signed_headers, payload
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", time, scope, hex(&Sha256::digest(canonical.as_bytes())));
+
+        let key = hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date);
+        let key = hmac(&hmac(&hmac(&key, &self.region), "s3"), "aws4_request");
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key,
+            scope,
+            signed_headers,
+            hex(&hmac(&key, &to_sign))
+        );
+
+        let mut url = format!("{}://{}{}", self.endpoint.scheme(), host, path);
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query);
+        }
+
+        self.agent
+            .request(method, &url)
+            .set("x-amz-content-sha256", &payload)
+            .set("x-amz-date", &time)
+            .set("Authorization", &authorization)
+    }
+}
+
+fn unavailable(e: ureq::Error) -> MediaStoreError {
+    MediaStoreError::Unavailable(e.to_string())
+}
+
+impl MediaStore for S3Store {
+    fn put(&self, key: &str, file: &Path, content_type: &str) -> Result<(), MediaStoreError> {
+        let body = fs::read(file)?;
+        self.request("PUT", key, &[], &body)
+            .set("Content-Type", content_type)
+            .send_bytes(&body)
+            .map_err(unavailable)?;
+
+        // it was just written, so it is kept rather than read back the first time it is asked for.
+        self.cache.put(key, file, content_type)
+    }
+
+    fn fetch(&self, key: &str) -> Result<Option<PathBuf>, MediaStoreError> {
+        if let Some(path) = self.cache.fetch(key)? {
+            return Ok(Some(path));
+        }
+
+        let res = match self.request("GET", key, &[], &[]).call() {
+            Ok(res) => res,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(e) => return Err(unavailable(e)),
+        };
+
+        // read to another file first, so a request that comes in meanwhile never serves half of it.
+        let partial = staging_in(&self.cache.dir)?;
+        let copied = fs::File::create(&partial).and_then(|mut file| io::copy(&mut res.into_reader(), &mut file));
+        if let Err(e) = copied {
+            fs::remove_file(&partial).ok();
+            return Err(e.into());
+        }
+
+        self.cache.put(key, &partial, "")?;
+        self.cache.fetch(key)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), MediaStoreError> {
+        match self.request("DELETE", key, &[], &[]).call() {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => {}
+            Err(e) => return Err(unavailable(e)),
+        }
+
+        self.cache.delete(key)
+    }
+
+    /// Only the first 1000 keys are listed, which is far more than an upload has resized copies.
+    fn list(&self, prefix: &str) -> Result<Vec<String>, MediaStoreError> {
+        let body = self
+            .request("GET", "", &[("list-type", "2"), ("prefix", prefix)], &[])
+            .call()
+            .map_err(unavailable)?
+            .into_string()?;
+
+        // the keys are made by the server and never have anything in them that XML would escape.
+        Ok(body
+            .split("<Key>")
+            .skip(1)
+            .filter_map(|part| part.split("</Key>").next())
+            .map(String::from)
+            .collect())
+    }
+}
+
+/// The store chosen by `APP_MEDIA_STORE`, which is the local disk unless `s3` is asked for.
+pub fn store(conf: &LocalConfig) -> Result<Arc<dyn MediaStore>, MediaStoreError> {
+    match conf.media_store.as_deref() {
+        None | Some("local") => Ok(Arc::new(LocalStore::new(media_dir(conf)))),
+        Some("s3") => Ok(Arc::new(S3Store::from_conf(conf, media_dir(conf))?)),
+        Some(_) => Err(MediaStoreError::Config),
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::{HashMap, HashSet};
+
+use diesel::OptionalExtension;
+
+use super::errors_service::CustomHttpError;
+use super::validation_service::FieldError;
+use crate::models::menu_models::{Menu, MenuItem, MenuItemTree, MenuTree, NavItem, NewMenuItem};
+use crate::models::page_models::{is_live, Page, PageDTO, PUBLIC};
+use crate::models::{DbConnection, Model};
+
+fn invalid(field: &str, message: &str) -> CustomHttpError {
+    CustomHttpError::Invalid(vec![FieldError {
+        field: String::from(field),
+        message: String::from(message),
+    }])
+}
+
+/// Refuses links to pages that don't exist, and parents that are in another menu or nested under the item itself,
+/// as that would make a loop. `id` is the item being changed, if it already exists.
+pub fn check_item(menu_id: &str, id: Option<&str>, item: &NewMenuItem, db: &DbConnection) -> Result<(), CustomHttpError> {
+    if let Some(page_uuid) = &item.page_uuid {
+        let page: Option<PageDTO> = Page::read_one(page_uuid.clone(), db).optional()?;
+        if page.is_none() {
+            return Err(invalid("page_uuid", "has to be a page that isn't in the trash"));
+        }
+    }
+
+    let parent = match &item.parent_item {
+        Some(parent) => parent,
+        None => return Ok(()),
+    };
+    let parents: HashMap<String, Option<String>> = MenuItem::read_for_menu(menu_id.to_string(), db)?
+        .into_iter()
+        .map(|i| (i.uuid, i.parent_item))
+        .collect();
+    if !parents.contains_key(parent) {
+        return Err(invalid("parent_item", "has to be an item of the same menu"));
+    }
+
+    let mut seen = HashSet::new();
+    let mut current = Some(parent.clone());
+    while let Some(ancestor) = current {
+        if Some(ancestor.as_str()) == id {
+            return Err(invalid("parent_item", "can't be the item itself or an item nested under it"));
+        }
+        if !seen.insert(ancestor.clone()) {
+            break;
+        }
+
+        current = parents.get(&ancestor).cloned().flatten();
+    }
+
+    Ok(())
+}
+
+/// Builds the items under `parent` with `build`, which leaves out the ones it returns `None` for along with everything under them.
+/// `depth` stops at the number of items, which only a loop made outside of the API could get past.
+fn nest<T>(items: &[MenuItem], parent: Option<&str>, depth: usize, build: &dyn Fn(&MenuItem, Vec<T>) -> Option<T>) -> Vec<T> {
+    if depth > items.len() {
+        return Vec::new();
+    }
+
+    items
+        .iter()
+        .filter(|i| i.parent_item.as_deref() == parent)
+        .filter_map(|i| build(i, nest(items, Some(&i.uuid), depth + 1, build)))
+        .collect()
+}
+
+/// The menu with every one of its items, linking to a page or not.
+pub fn tree(menu: Menu, db: &DbConnection) -> Result<MenuTree, diesel::result::Error> {
+    let items = MenuItem::read_for_menu(menu.uuid.clone(), db)?;
+    let items = nest(&items, None, 0, &|item, children| {
+        Some(MenuItemTree {
+            item: item.clone(),
+            children,
+        })
+    });
+
+    Ok(MenuTree { menu, items })
+}
+
+/// Every menu by name, as the page at `current_url` shows it. Links to pages are made with the page's URL as it is now,
+/// and items for pages that visitors can't see, like drafts, are left out along with the items under them.
+pub fn navigation(current_url: &str, db: &DbConnection) -> Result<HashMap<String, Vec<NavItem>>, diesel::result::Error> {
+    let menus = Menu::read_all(db)?;
+    if menus.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let items = MenuItem::read_all(db)?;
+    let linked: Vec<String> = items.iter().filter_map(|i| i.page_uuid.clone()).collect();
+    let urls: HashMap<String, String> = Page::read_many(linked, db)?
+        .into_iter()
+        .filter(|p| is_live(&p.status, p.publish_at) && p.visibility == PUBLIC)
+        .map(|p| (p.uuid, p.page_url))
+        .collect();
+
+    let mut navigation = HashMap::new();
+    for menu in menus {
+        let in_menu: Vec<MenuItem> = items.iter().filter(|i| i.menu_uuid == menu.uuid).cloned().collect();
+        let nav = nest(&in_menu, None, 0, &|item, children| {
+            let url = match (&item.page_uuid, &item.url) {
+                (Some(page_uuid), _) => urls.get(page_uuid)?.clone(),
+                (None, url) => url.clone()?,
+            };
+
+            Some(NavItem {
+                title: item.title.clone(),
+                active: url == current_url,
+                url,
+                children,
+            })
+        });
+        navigation.insert(menu.name, nav);
+    }
+
+    Ok(navigation)
+}
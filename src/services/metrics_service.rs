@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::db_health_service;
+use crate::models::Databases;
+
+/// The upper bounds, in seconds, of the request duration histogram.
+const BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Requests that don't match a route are rendered as pages, or answered with a 404, and are counted together under this,
+/// so that every page's URL, and made up ones, don't each get their own route.
+pub const PAGE_ROUTE: &str = "pages";
+
+/// Hits and misses of one of the caches, kept in statics like the pool's checkouts, as the caches don't know about the registry.
+pub struct CacheCounter {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounter {
+    const fn new() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, hit: bool) {
+        match hit {
+            true => self.hits.fetch_add(1, Ordering::Relaxed),
+            false => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+}
+
+pub static PAGE_CACHE: CacheCounter = CacheCounter::new();
+pub static RESPONSE_CACHE: CacheCounter = CacheCounter::new();
+
+#[derive(Default)]
+struct RouteStats {
+    /// By status code.
+    requests: BTreeMap<u16, u64>,
+    /// How many requests took at most each of `BUCKETS`, not counting the ones in the buckets before.
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    seconds: f64,
+}
+
+/// Requests by route and method, which `RequestMetrics` records every request in, rendered at `/metrics`.
+/// Routes are their patterns, like `/api/v1/pages/{id}`, so every page is counted under the same route.
+#[derive(Default)]
+pub struct Metrics {
+    routes: Mutex<BTreeMap<(String, String), RouteStats>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, route: &str, method: &str, status: u16, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let mut routes = self.routes.lock().unwrap();
+        let stats = routes.entry((route.to_string(), method.to_string())).or_default();
+
+        *stats.requests.entry(status).or_default() += 1;
+        if let Some(bucket) = BUCKETS.iter().position(|le| seconds <= *le) {
+            stats.buckets[bucket] += 1;
+        }
+        stats.count += 1;
+        stats.seconds += seconds;
+    }
+
+    /// Everything in the Prometheus text format.
+    pub fn render(&self, databases: &Databases) -> String {
+        let mut out = String::new();
+
+        self.render_requests(&mut out);
+        render_pools(&mut out, databases);
+        render_caches(&mut out);
+
+        out
+    }
+
+    fn render_requests(&self, out: &mut String) {
+        let routes = self.routes.lock().unwrap();
+
+        header(out, "radical_http_requests_total", "counter", "Requests answered, by route, method, and status.");
+        for ((route, method), stats) in routes.iter() {
+            for (status, count) in &stats.requests {
+                let labels = format!("route=\"{}\",method=\"{}\",status=\"{}\"", escape(route), method, status);
+                writeln!(out, "radical_http_requests_total{{{}}} {}", labels, count).ok();
+            }
+        }
+
+        header(out, "radical_http_request_duration_seconds", "histogram", "How long requests took to answer, by route and method.");
+        for ((route, method), stats) in routes.iter() {
+            let labels = format!("route=\"{}\",method=\"{}\"", escape(route), method);
+            let mut cumulative = 0;
+            for (le, count) in BUCKETS.iter().zip(stats.buckets.iter()) {
+                cumulative += count;
+                writeln!(out, "radical_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}", labels, le, cumulative).ok();
+            }
+            writeln!(out, "radical_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, stats.count).ok();
+            writeln!(out, "radical_http_request_duration_seconds_sum{{{}}} {}", labels, stats.seconds).ok();
+            writeln!(out, "radical_http_request_duration_seconds_count{{{}}} {}", labels, stats.count).ok();
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).ok();
+    writeln!(out, "# TYPE {} {}", name, kind).ok();
+}
+
+/// Label values are quoted, so quotes and backslashes in them have to be escaped.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn render_pools(out: &mut String, databases: &Databases) {
+    header(out, "radical_db_pool_connections", "gauge", "Open connections of each database's pool, by whether they are in use.");
+    for (name, pool) in databases.all() {
+        let stats = db_health_service::pool_stats(pool);
+        writeln!(out, "radical_db_pool_connections{{database=\"{}\",state=\"idle\"}} {}", name, stats.idle).ok();
+        writeln!(out, "radical_db_pool_connections{{database=\"{}\",state=\"in_use\"}} {}", name, stats.in_use).ok();
+    }
+
+    header(out, "radical_db_pool_max_connections", "gauge", "How many connections each database's pool may open.");
+    for (name, pool) in databases.all() {
+        writeln!(out, "radical_db_pool_max_connections{{database=\"{}\"}} {}", name, pool.max_size()).ok();
+    }
+
+    // the counters are shared by every pool, like on `/api/v1/admin/system/db`.
+    let stats = db_health_service::pool_stats(databases.content());
+    header(out, "radical_db_pool_checkouts_total", "counter", "Connections checked out of the pools.");
+    writeln!(out, "radical_db_pool_checkouts_total {}", stats.checkouts).ok();
+    header(out, "radical_db_pool_checkout_failures_total", "counter", "Checkouts that timed out waiting for a connection.");
+    writeln!(out, "radical_db_pool_checkout_failures_total {}", stats.checkout_failures).ok();
+    header(out, "radical_db_pool_checkout_wait_seconds_max", "gauge", "The longest a checkout has waited for a connection.");
+    writeln!(out, "radical_db_pool_checkout_wait_seconds_max {}", stats.max_wait_ms / 1000.0).ok();
+}
+
+fn render_caches(out: &mut String) {
+    let caches = [("page", &PAGE_CACHE), ("response", &RESPONSE_CACHE)];
+
+    header(out, "radical_cache_hits_total", "counter", "Lookups that were answered from the cache, by cache.");
+    for (name, counter) in caches {
+        writeln!(out, "radical_cache_hits_total{{cache=\"{}\"}} {}", name, counter.hits.load(Ordering::Relaxed)).ok();
+    }
+
+    header(out, "radical_cache_misses_total", "counter", "Lookups that weren't in the cache, by cache.");
+    for (name, counter) in caches {
+        writeln!(out, "radical_cache_misses_total{{cache=\"{}\"}} {}", name, counter.misses.load(Ordering::Relaxed)).ok();
+    }
+}
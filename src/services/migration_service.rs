@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::Method;
+use diesel_migrations::{mark_migrations_in_directory, revert_latest_migration_in_directory, MigrationError, RunMigrationsError};
+use serde::Serialize;
+use utoipa::ToSchema;
+use super::api_version_service::api_path;
+use crate::models::DbConnection;
+
+/// Each database gets its own copy of every migration, under the same names.
+#[cfg(feature = "mysql")]
+const MIGRATIONS_DIRECTORY: &str = "migrations";
+#[cfg(feature = "postgres")]
+const MIGRATIONS_DIRECTORY: &str = "migrations_postgres";
+#[cfg(feature = "sqlite")]
+const MIGRATIONS_DIRECTORY: &str = "migrations_sqlite";
+
+// The migrations are also built into the binary, so that they can be run where there is no migrations directory.
+#[cfg(feature = "mysql")]
+embed_migrations!("migrations");
+#[cfg(feature = "postgres")]
+embed_migrations!("migrations_postgres");
+#[cfg(feature = "sqlite")]
+embed_migrations!("migrations_sqlite");
+
+/// What diesel writes before each migration it runs.
+const RUNNING: &str = "Running migration ";
+
+/// These stay open while the schema is behind, as they are how an admin gets it caught up. They are within the API's
+/// version, like `/user/login` for `/api/v1/user/login`.
+const ALWAYS_OPEN: &[&str] = &["/admin/system/migrations", "/user/login"];
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct MigrationStatus {
+    pub version: String,
+    /// The name of the migration's directory, like `2026-10-14-000001_create_signing_keys`.
+    pub name: String,
+    pub applied: bool,
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct MigrationReport {
+    pub pending: usize,
+    pub migrations: Vec<MigrationStatus>,
+}
+
+/// The migrations for the database this was built for, in the working directory or the closest one above it.
+fn migrations_directory() -> Result<PathBuf, MigrationError> {
+    let mut dir = std::env::current_dir()?;
+
+    loop {
+        let candidate = dir.join(MIGRATIONS_DIRECTORY);
+        if candidate.is_dir() {
+            return Ok(candidate);
+        }
+
+        if !dir.pop() {
+            return Err(MigrationError::MigrationDirectoryNotFound);
+        }
+    }
+}
+
+/// Every migration in the migrations directory, oldest first, and whether it has been applied.
+pub fn status(db: &DbConnection) -> Result<MigrationReport, RunMigrationsError> {
+    let dir = migrations_directory()?;
+
+    let mut migrations: Vec<MigrationStatus> = mark_migrations_in_directory(db, &dir)?
+        .into_iter()
+        .map(|(migration, applied)| MigrationStatus {
+            version: migration.version().to_string(),
+            name: migration
+                .file_path()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| migration.version().to_string()),
+            applied,
+        })
+        .collect();
+    migrations.sort_by(|a, b| a.version.cmp(&b.version));
+
+    Ok(MigrationReport {
+        pending: migrations.iter().filter(|m| !m.applied).count(),
+        migrations,
+    })
+}
+
+/// Runs every pending migration of the ones built into the binary, returning the ones that were run. Those are the
+/// migrations the binary was built for, so deploying it doesn't take the migrations directory or the diesel CLI.
+pub fn run(db: &DbConnection) -> Result<Vec<MigrationStatus>, RunMigrationsError> {
+    let mut output = Vec::new();
+    embedded_migrations::run_with_output(db, &mut output)?;
+
+    // the names are only known from the migrations directory, where there is one.
+    let names: Vec<MigrationStatus> = status(db).map(|report| report.migrations).unwrap_or_default();
+    let ran = String::from_utf8_lossy(&output)
+        .lines()
+        .filter_map(|line| line.strip_prefix(RUNNING))
+        .map(|version| MigrationStatus {
+            version: version.to_string(),
+            name: names
+                .iter()
+                .find(|m| m.version == version)
+                .map(|m| m.name.clone())
+                .unwrap_or_else(|| version.to_string()),
+            applied: true,
+        })
+        .collect();
+
+    Ok(ran)
+}
+
+/// Reverts the last migration that was run, returning its version.
+pub fn revert(db: &DbConnection) -> Result<String, RunMigrationsError> {
+    revert_latest_migration_in_directory(db, &migrations_directory()?)
+}
+
+/// Whether the database has caught up with the migrations this build expects.
+/// Shared with every worker so that writes are refused until the pending migrations have been run.
+#[derive(Default)]
+pub struct SchemaState {
+    pending: AtomicUsize,
+}
+
+impl SchemaState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks the database again. If the migrations can't be found, the schema is assumed to be up to date,
+    /// as there is nothing that could be run to catch it up.
+    pub fn refresh(&self, db: &DbConnection) -> Result<usize, RunMigrationsError> {
+        let pending = match status(db) {
+            Ok(report) => report.pending,
+            Err(RunMigrationsError::MigrationError(MigrationError::MigrationDirectoryNotFound)) => 0,
+            Err(e) => return Err(e),
+        };
+        self.pending.store(pending, Ordering::Relaxed);
+
+        Ok(pending)
+    }
+
+    pub fn is_behind(&self) -> bool {
+        self.pending.load(Ordering::Relaxed) > 0
+    }
+
+    /// Reads are still served while the schema is behind, as they are what keeps a site up.
+    pub fn refuses(&self, req: &ServiceRequest) -> bool {
+        let read = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+
+        let open = api_path(req.path()).is_some_and(|path| ALWAYS_OPEN.iter().any(|open| path.starts_with(open)));
+
+        self.is_behind() && !read && !open
+    }
+}
@@ -1,2 +1,78 @@
 pub mod errors_service;
-pub mod auth_service;
\ No newline at end of file
+pub mod feed_service;
+pub mod api_version_service;
+pub mod auth_service;
+pub mod backup_service;
+pub mod body_limit_service;
+pub mod bulk_service;
+pub mod cache_service;
+pub mod comment_service;
+pub mod compression_service;
+pub mod content_import_service;
+pub mod content_type_service;
+pub mod cors_service;
+pub mod cron_service;
+pub mod db_health_service;
+pub mod delivery_service;
+pub mod duplicate_service;
+pub mod encryption_service;
+pub mod etag_service;
+pub mod export_service;
+pub mod form_service;
+pub mod gc_service;
+pub mod git_service;
+pub mod graphql_service;
+pub mod inbound_webhook_service;
+pub mod job_service;
+pub mod live_service;
+pub mod mail_service;
+pub mod media_service;
+pub mod media_store_service;
+pub mod menu_service;
+pub mod jsonapi_service;
+pub mod markdown_export_service;
+pub mod markdown_import_service;
+pub mod metrics_service;
+pub mod migration_service;
+pub mod oidc_service;
+pub mod plugin_service;
+pub mod openapi_service;
+pub mod page_cache_service;
+pub mod pagination_service;
+pub mod password_reset_service;
+pub mod preview_service;
+pub mod privacy_service;
+pub mod publish_service;
+pub mod quota_service;
+pub mod rate_limit_service;
+pub mod redirect_service;
+pub mod render_service;
+pub mod response_cache_service;
+pub mod retention_service;
+pub mod revision_service;
+pub mod rollout_service;
+pub mod route_service;
+pub mod sanitize_service;
+pub mod schedule_service;
+pub mod search_service;
+pub mod session_service;
+pub mod settings_service;
+pub mod shutdown_service;
+pub mod site_service;
+pub mod sitemap_service;
+pub mod static_export_service;
+pub mod static_service;
+pub mod stats_service;
+pub mod theme_service;
+pub mod tls_service;
+pub mod trace_service;
+pub mod trash_service;
+pub mod translation_service;
+pub mod tree_service;
+pub mod two_factor_service;
+pub mod validation_service;
+pub mod version_service;
+pub mod visibility_service;
+pub mod warmup_service;
+pub mod webhook_service;
+pub mod wordpress_import_service;
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::auth_service::{encrypt_password, generate_secret};
+use super::cache_service::CacheBackend;
+use super::errors_service::CustomHttpError;
+use crate::models::config_models::LocalConfig;
+use crate::models::user_models::{MutUser, User};
+use crate::models::{DbConnection, Model};
+
+/// How long a login has to come back from the issuer before its state is forgotten.
+pub const STATE_TTL: Duration = Duration::from_secs(600);
+/// The cookie the state of a login is kept in by the browser that started it, until it comes back from the issuer.
+pub const STATE_COOKIE: &str = "oidc_state";
+
+#[derive(Error, Debug)]
+pub enum OidcError {
+    #[error("The OIDC issuer could not be reached: {0}")]
+    Request(String),
+    #[error("The OIDC issuer sent back something that couldn't be read.")]
+    InvalidResponse,
+    #[error("The ID token could not be verified.")]
+    InvalidToken,
+    #[error("The login has expired or was started somewhere else.")]
+    UnknownState,
+    #[error("There is no user for this identity.")]
+    NoUser,
+}
+
+impl From<OidcError> for CustomHttpError {
+    fn from(e: OidcError) -> Self {
+        match e {
+            OidcError::Request(_) | OidcError::InvalidResponse => Self::Unknown,
+            OidcError::InvalidToken | OidcError::UnknownState => Self::Unauthorized,
+            OidcError::NoUser => Self::Forbidden,
+        }
+    }
+}
+
+/// The issuer and client from `APP_OIDC_*`, which are there when OIDC logins are turned on.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// `/auth/oidc/callback` as the issuer reaches it, which has to be registered with the issuer as well.
+    pub redirect_url: String,
+}
+
+impl OidcConfig {
+    pub fn from_conf(conf: &LocalConfig) -> Option<Self> {
+        Some(Self {
+            issuer: conf.oidc_issuer.clone()?.trim_end_matches('/').to_string(),
+            client_id: conf.oidc_client_id.clone()?,
+            client_secret: conf.oidc_client_secret.clone()?,
+            redirect_url: conf.oidc_redirect_url.clone()?,
+        })
+    }
+}
+
+/// The parts of the issuer's `/.well-known/openid-configuration` that a login uses.
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+/// Only RSA keys are used, as those are what ID tokens are signed with unless a client asks for something else.
+#[derive(Debug, Deserialize)]
+struct Jwk {
+    kid: Option<String>,
+    kty: String,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+/// Who the issuer says logged in.
+#[derive(Debug, Deserialize)]
+pub struct IdClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+    pub nonce: Option<String>,
+}
+
+fn get<T: serde::de::DeserializeOwned>(url: &str) -> Result<T, OidcError> {
+    let body = ureq::get(url)
+        .call()
+        .map_err(|e| OidcError::Request(e.to_string()))?
+        .into_string()
+        .or(Err(OidcError::InvalidResponse))?;
+
+    serde_json::from_str(&body).or(Err(OidcError::InvalidResponse))
+}
+
+fn discover(config: &OidcConfig) -> Result<Discovery, OidcError> {
+    get(&format!("{}/.well-known/openid-configuration", config.issuer))
+}
+
+/// Remembers the login's state and nonce, and returns where to send the browser to log in at the issuer, along with the
+/// state, which the browser has to be given to send back (see `STATE_COOKIE`).
+pub fn start_login(config: &OidcConfig, cache: &dyn CacheBackend) -> Result<(String, String), OidcError> {
+    let discovery = discover(config)?;
+    let (state, nonce) = (generate_secret(), generate_secret());
+
+    cache
+        .set(&format!("oidc:{}", state), &nonce, Some(STATE_TTL))
+        .map_err(|e| OidcError::Request(e.to_string()))?;
+
+    let mut url = url::Url::parse(&discovery.authorization_endpoint).or(Err(OidcError::InvalidResponse))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &config.redirect_url)
+        .append_pair("scope", "openid email profile")
+        .append_pair("state", &state)
+        .append_pair("nonce", &nonce);
+
+    Ok((url.to_string(), state))
+}
+
+/// Trades the code the issuer sent the browser back with for an ID token, and checks that it is for this login.
+/// A state can only be used once, and only by the browser that started the login, which sends it back as `browser_state`.
+pub fn finish_login(
+    config: &OidcConfig,
+    cache: &dyn CacheBackend,
+    code: &str,
+    state: &str,
+    browser_state: Option<&str>,
+) -> Result<IdClaims, OidcError> {
+    // otherwise someone could have a browser finish a login they started, and be logged in as them.
+    if browser_state != Some(state) {
+        return Err(OidcError::UnknownState);
+    }
+    let key = format!("oidc:{}", state);
+    let nonce = cache.get(&key).ok().flatten().ok_or(OidcError::UnknownState)?;
+    cache.delete(&key).ok();
+
+    let discovery = discover(config)?;
+    let body = ureq::post(&discovery.token_endpoint)
+        .send_form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &config.redirect_url),
+            ("client_id", &config.client_id),
+            ("client_secret", &config.client_secret),
+        ])
+        .map_err(|e| OidcError::Request(e.to_string()))?
+        .into_string()
+        .or(Err(OidcError::InvalidResponse))?;
+    let token: TokenResponse = serde_json::from_str(&body).or(Err(OidcError::InvalidResponse))?;
+
+    let claims = verify(&token.id_token, &discovery, config)?;
+    match claims.nonce.as_deref() == Some(nonce.as_str()) {
+        true => Ok(claims),
+        false => Err(OidcError::InvalidToken),
+    }
+}
+
+/// Checks the ID token's signature against the issuer's keys, and that it was issued by the issuer for this client.
+fn verify(id_token: &str, discovery: &Discovery, config: &OidcConfig) -> Result<IdClaims, OidcError> {
+    let header = decode_header(id_token).or(Err(OidcError::InvalidToken))?;
+    if !matches!(header.alg, Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512) {
+        return Err(OidcError::InvalidToken);
+    }
+
+    let jwks: Jwks = get(&discovery.jwks_uri)?;
+    let key = jwks
+        .keys
+        .iter()
+        .filter(|k| k.kty == "RSA")
+        .find(|k| header.kid.is_none() || k.kid == header.kid)
+        .ok_or(OidcError::InvalidToken)?;
+    let (n, e) = match (&key.n, &key.e) {
+        (Some(n), Some(e)) => (n, e),
+        _ => return Err(OidcError::InvalidToken),
+    };
+
+    let mut validation = Validation::new(header.alg);
+    validation.iss = Some(discovery.issuer.clone());
+    validation.set_audience(&[&config.client_id]);
+
+    let token = decode::<IdClaims>(id_token, &DecodingKey::from_rsa_components(n, e), &validation)
+        .or(Err(OidcError::InvalidToken))?;
+
+    Ok(token.claims)
+}
+
+/// The local user for an identity at the issuer. Users are found by the identity they logged in with before,
+/// then by a verified email, and are otherwise only made when `APP_OIDC_CREATE_USERS` is on, as viewers.
+pub fn find_user(claims: &IdClaims, create: bool, db: &DbConnection) -> Result<User, CustomHttpError> {
+    if let Ok(user) = User::read_by_oidc_subject(claims.sub.clone(), db) {
+        return Ok(user);
+    }
+
+    let email = claims.email.clone().filter(|_| claims.email_verified);
+    if let Some(user) = email.clone().map(|email| User::read_by_email(email, db)).transpose()?.and_then(|u| u.into_iter().next()) {
+        User::set_oidc_subject(user.username.clone(), claims.sub.clone(), db)?;
+        return Ok(user);
+    }
+
+    if !create {
+        return Err(OidcError::NoUser.into());
+    }
+
+    let username = claims.preferred_username.clone().or_else(|| email.clone()).unwrap_or_else(|| claims.sub.clone());
+    User::create(
+        &MutUser {
+            uuid: Some(Uuid::new_v4().to_string()),
+            username: username.clone(),
+            // never told to anyone, so the user can only log in through the issuer.
+            password: Some(encrypt_password(&generate_secret())?),
+            token: None,
+            email,
+            role: None,
+        },
+        db,
+    )?;
+    User::set_oidc_subject(username.clone(), claims.sub.clone(), db)?;
+
+    Ok(User::read_one(username, db)?)
+}
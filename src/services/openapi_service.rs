@@ -0,0 +1,460 @@
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{Content, Ref, RefOr, ResponseBuilder};
+use utoipa::{Modify, OpenApi};
+
+use super::bulk_service::{BulkResult, ModuleOperation, PageOperation};
+use super::db_health_service::{DbHealth, DbProbe, PoolStats};
+use super::export_service::{ContentDump, ImportCounts, ImportReport};
+use super::gc_service::GcReport;
+use super::inbound_webhook_service::Transform;
+use super::migration_service::{MigrationReport, MigrationStatus};
+use super::privacy_service::{PersonalDataExport, UserExport};
+use super::quota_service::{QuotaUsage, Usage};
+use super::revision_service::{FieldChange, ModuleChange, RevisionDiff};
+use super::rollout_service::RolloutRequest;
+use super::search_service::{ReindexStatus, SearchResult};
+use super::session_service::SESSION_COOKIE;
+use super::stats_service::{ContentCounts, DashboardStats, PageStats, RecentEdit, StorageStats, SystemStats, TableStats};
+use super::trash_service::Trash;
+use crate::controllers::delivery_token_controllers::{CreatedDeliveryToken, NewDeliveryToken};
+use crate::controllers::gallery_controllers::NewGalleryItem;
+use crate::controllers::inbound_webhook_controllers::{CreatedInboundWebhook, NewInboundWebhook};
+use crate::controllers::preview_controllers::{NewPreview, Preview, PreviewLink};
+use crate::controllers::privacy_controllers::ErasureConfirmation;
+use crate::controllers::setting_controllers::SettingValue;
+use crate::controllers::theme_controllers::{ActiveTheme, ThemeList};
+use crate::controllers::webhook_controllers::{CreatedWebhook, NewWebhook};
+use crate::controllers::{
+    api_key_controllers, category_controllers, comment_controllers, content_type_controllers, delivery_controllers, delivery_token_controllers, export_controllers, form_controllers, gallery_controllers,
+    inbound_webhook_controllers, job_controllers, media_controllers, menu_controllers, module_controllers, oidc_controllers, page_controllers, preview_controllers, privacy_controllers, quota_controllers,
+    redirect_controllers, render_controllers, revision_controllers, rollout_controllers, search_controllers, session_controllers, setting_controllers, signing_key_controllers, site_controllers,
+    system_controllers, taxonomy_controllers, theme_controllers, trash_controllers, user_controllers, webhook_controllers,
+};
+use crate::models::api_key_models::ApiKey as Key;
+use crate::models::comment_models::{Comment, MutCommentStatus, NewComment, PublicComment};
+use crate::models::content_type_models::{ContentType, ContentTypeDTO, NewContentType};
+use crate::models::data_request_models::DataRequest;
+use crate::models::delivery_token_models::DeliveryToken;
+use crate::models::form_models::{Form, FormDTO, FormField, FormSubmission, FormSubmissionDTO, NewForm};
+use crate::models::gallery_item_models::{GalleryItem, MutGalleryItem};
+use crate::models::inbound_webhook_models::InboundWebhook;
+use crate::models::job_models::Job;
+use crate::models::media_models::Media;
+use crate::models::menu_models::{Menu, MenuItem, MenuItemTree, MenuTree, NewMenu, NewMenuItem};
+use crate::models::module_models::{
+    CategoryDTO, FieldsDTO, Module, ModuleCategory, ModuleType, MutCategory, MutModule, PatchModule,
+};
+use crate::models::page_models::{
+    MutPage, MutPageParent, MutPageStatus, NewPageWithModules, PageDTO, PageModuleDTO, PageTree, PatchPage,
+};
+use crate::models::page_revision_models::{PageRevision, PageRevisionDTO, PageSnapshot};
+use crate::models::page_rollout_models::{PageRollout, PageRolloutDTO};
+use crate::models::redirect_models::{NewRedirect, Redirect};
+use crate::models::setting_models::{Setting, SettingChange};
+use crate::models::signing_key_models::SigningKeyDTO;
+use crate::models::site_models::{NewSite, Site};
+use crate::models::taxonomy_models::{NewTerm, PageCategory, Tag};
+use crate::models::user_models::{
+    ForgotPassword, LoginBody, MutUser, MutUserRole, RecoveryCodes, ResetPassword, TwoFactorCode, TwoFactorSetup, UserRole,
+};
+use crate::models::webhook_models::Webhook;
+use crate::services::duplicate_service::{DuplicateWarning, ModuleWithWarnings, PageWithWarnings};
+use crate::services::errors_service::Problem;
+use crate::services::media_service::Fit;
+use crate::services::validation_service::FieldError;
+
+/// The spec served at `/openapi.json`, covering the management API, the delivery API, search, rendered fragments, OIDC logins, and the health and metrics endpoints.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Radical", description = "A headless CMS."),
+    paths(
+        page_controllers::create_page,
+        page_controllers::create_page_with_modules,
+        page_controllers::duplicate_page,
+        page_controllers::get_pages,
+        page_controllers::bulk_pages,
+        page_controllers::get_page,
+        page_controllers::get_page_join_modules,
+        page_controllers::update_page,
+        page_controllers::patch_page,
+        page_controllers::update_page_status,
+        page_controllers::update_page_parent,
+        page_controllers::reorder_page_modules,
+        page_controllers::get_page_tree,
+        page_controllers::get_page_translations,
+        preview_controllers::create_preview_link,
+        page_controllers::delete_page,
+        page_controllers::restore_page,
+        preview_controllers::create_preview,
+        preview_controllers::delete_preview,
+        revision_controllers::get_revisions,
+        revision_controllers::get_revision_diff,
+        revision_controllers::restore_revision,
+        rollout_controllers::get_rollout,
+        rollout_controllers::put_rollout,
+        rollout_controllers::promote_rollout,
+        rollout_controllers::rollback_rollout,
+        module_controllers::create_module,
+        module_controllers::get_modules,
+        module_controllers::get_global_modules,
+        module_controllers::bulk_modules,
+        module_controllers::get_module,
+        module_controllers::update_module,
+        module_controllers::patch_module,
+        module_controllers::delete_module,
+        module_controllers::restore_module,
+        module_controllers::get_module_category,
+        gallery_controllers::get_gallery,
+        gallery_controllers::add_gallery_item,
+        gallery_controllers::reorder_gallery,
+        gallery_controllers::remove_gallery_item,
+        category_controllers::create_category,
+        category_controllers::update_category,
+        category_controllers::get_category,
+        category_controllers::delete_category,
+        comment_controllers::create_comment,
+        comment_controllers::get_page_comments,
+        comment_controllers::get_comments,
+        comment_controllers::update_comment_status,
+        comment_controllers::delete_comment,
+        content_type_controllers::create_content_type,
+        content_type_controllers::get_content_types,
+        content_type_controllers::get_content_type,
+        content_type_controllers::update_content_type,
+        content_type_controllers::delete_content_type,
+        form_controllers::create_form,
+        form_controllers::get_forms,
+        form_controllers::get_form,
+        form_controllers::update_form,
+        form_controllers::delete_form,
+        form_controllers::submit_form,
+        form_controllers::get_submissions,
+        form_controllers::export_submissions,
+        form_controllers::delete_submission,
+        media_controllers::upload_media,
+        media_controllers::get_media_list,
+        media_controllers::get_media,
+        media_controllers::delete_media,
+        menu_controllers::create_menu,
+        menu_controllers::get_menus,
+        menu_controllers::get_menu,
+        menu_controllers::update_menu,
+        menu_controllers::delete_menu,
+        menu_controllers::add_menu_item,
+        menu_controllers::reorder_menu,
+        menu_controllers::update_menu_item,
+        menu_controllers::remove_menu_item,
+        redirect_controllers::create_redirect,
+        redirect_controllers::get_redirects,
+        redirect_controllers::get_redirect,
+        redirect_controllers::update_redirect,
+        redirect_controllers::delete_redirect,
+        site_controllers::create_site,
+        site_controllers::get_sites,
+        site_controllers::get_current_site,
+        site_controllers::get_site,
+        site_controllers::update_site,
+        site_controllers::delete_site,
+        taxonomy_controllers::create_tag,
+        taxonomy_controllers::get_tags,
+        taxonomy_controllers::get_tag,
+        taxonomy_controllers::update_tag,
+        taxonomy_controllers::delete_tag,
+        taxonomy_controllers::get_tag_pages,
+        taxonomy_controllers::get_page_tags,
+        taxonomy_controllers::set_page_tags,
+        taxonomy_controllers::create_page_category,
+        taxonomy_controllers::get_page_categories,
+        taxonomy_controllers::get_page_category,
+        taxonomy_controllers::update_page_category,
+        taxonomy_controllers::delete_page_category,
+        taxonomy_controllers::get_page_category_pages,
+        taxonomy_controllers::get_categories_of_page,
+        taxonomy_controllers::set_categories_of_page,
+        user_controllers::create_user,
+        user_controllers::check_login,
+        user_controllers::login,
+        user_controllers::logout,
+        user_controllers::update_user,
+        user_controllers::update_user_role,
+        user_controllers::get_user,
+        user_controllers::delete_user,
+        user_controllers::setup_two_factor,
+        user_controllers::enable_two_factor,
+        user_controllers::disable_two_factor,
+        user_controllers::replace_recovery_codes,
+        user_controllers::reset_two_factor,
+        user_controllers::forgot_password,
+        user_controllers::reset_password,
+        session_controllers::create_session,
+        session_controllers::get_session,
+        session_controllers::delete_session,
+        api_key_controllers::create_api_key,
+        api_key_controllers::get_api_keys,
+        api_key_controllers::delete_api_key,
+        signing_key_controllers::create_signing_key,
+        signing_key_controllers::get_signing_keys,
+        signing_key_controllers::retire_signing_key,
+        delivery_token_controllers::create_delivery_token,
+        delivery_token_controllers::get_delivery_tokens,
+        delivery_token_controllers::delete_delivery_token,
+        oidc_controllers::oidc_login,
+        oidc_controllers::oidc_callback,
+        setting_controllers::get_settings,
+        setting_controllers::get_setting,
+        setting_controllers::set_setting,
+        setting_controllers::delete_setting,
+        setting_controllers::get_setting_history,
+        setting_controllers::revert_setting_change,
+        theme_controllers::get_themes,
+        theme_controllers::set_theme,
+        webhook_controllers::create_webhook,
+        webhook_controllers::get_webhooks,
+        webhook_controllers::delete_webhook,
+        inbound_webhook_controllers::create_inbound_webhook,
+        inbound_webhook_controllers::get_inbound_webhooks,
+        inbound_webhook_controllers::delete_inbound_webhook,
+        inbound_webhook_controllers::receive_inbound_webhook,
+        privacy_controllers::export_personal_data,
+        privacy_controllers::erase_personal_data,
+        privacy_controllers::get_data_requests,
+        quota_controllers::get_usage,
+        trash_controllers::get_trash,
+        delivery_controllers::get_pages,
+        delivery_controllers::get_page,
+        delivery_controllers::get_page_by_url,
+        delivery_controllers::get_module,
+        delivery_controllers::get_category,
+        search_controllers::search,
+        render_controllers::get_module_fragment,
+        render_controllers::get_region_fragment,
+        export_controllers::export_content,
+        export_controllers::import_content,
+        job_controllers::get_jobs,
+        search_controllers::get_reindex_status,
+        search_controllers::reindex,
+        system_controllers::get_dashboard_stats,
+        system_controllers::get_db_health,
+        system_controllers::get_named_db_health,
+        system_controllers::clear_cache,
+        system_controllers::get_gc_report,
+        system_controllers::run_gc,
+        system_controllers::get_system_stats,
+        system_controllers::publish_everything,
+        system_controllers::export_static,
+        system_controllers::get_migrations,
+        system_controllers::run_migrations,
+        system_controllers::get_health,
+        system_controllers::get_readiness,
+        system_controllers::get_metrics,
+    ),
+    components(schemas(
+        MutPage,
+        PatchPage,
+        MutPageStatus,
+        MutPageParent,
+        PageDTO,
+        PageModuleDTO,
+        PageTree,
+        PageWithWarnings,
+        NewPageWithModules,
+        page_controllers::PageWithModules,
+        PreviewLink,
+        NewPreview,
+        Preview,
+        PageRevision,
+        PageSnapshot,
+        PageRevisionDTO,
+        RevisionDiff,
+        FieldChange,
+        ModuleChange,
+        PageRollout,
+        PageRolloutDTO,
+        RolloutRequest,
+        Trash,
+        Module,
+        ModuleType,
+        MutModule,
+        PatchModule,
+        ModuleWithWarnings,
+        ModuleCategory,
+        MutCategory,
+        CategoryDTO,
+        FieldsDTO,
+        Comment,
+        PublicComment,
+        NewComment,
+        MutCommentStatus,
+        ContentType,
+        ContentTypeDTO,
+        NewContentType,
+        Form,
+        FormDTO,
+        FormField,
+        NewForm,
+        FormSubmission,
+        FormSubmissionDTO,
+        GalleryItem,
+        MutGalleryItem,
+        NewGalleryItem,
+        Media,
+        Fit,
+        Menu,
+        NewMenu,
+        MenuItem,
+        NewMenuItem,
+        MenuItemTree,
+        MenuTree,
+        Redirect,
+        NewRedirect,
+        Site,
+        NewSite,
+        Tag,
+        PageCategory,
+        NewTerm,
+        MutUser,
+        MutUserRole,
+        LoginBody,
+        TwoFactorCode,
+        TwoFactorSetup,
+        RecoveryCodes,
+        ForgotPassword,
+        ResetPassword,
+        UserRole,
+        session_controllers::SessionInfo,
+        Key,
+        api_key_controllers::NewApiKey,
+        api_key_controllers::CreatedApiKey,
+        SigningKeyDTO,
+        DeliveryToken,
+        NewDeliveryToken,
+        CreatedDeliveryToken,
+        Setting,
+        SettingValue,
+        SettingChange,
+        ThemeList,
+        ActiveTheme,
+        Webhook,
+        NewWebhook,
+        CreatedWebhook,
+        InboundWebhook,
+        NewInboundWebhook,
+        CreatedInboundWebhook,
+        Transform,
+        PersonalDataExport,
+        UserExport,
+        ErasureConfirmation,
+        DataRequest,
+        Usage,
+        QuotaUsage,
+        SearchResult,
+        ReindexStatus,
+        DuplicateWarning,
+        PageOperation,
+        ModuleOperation,
+        BulkResult,
+        ContentDump,
+        ImportCounts,
+        ImportReport,
+        Job,
+        DashboardStats,
+        ContentCounts,
+        RecentEdit,
+        SystemStats,
+        TableStats,
+        PageStats,
+        StorageStats,
+        DbHealth,
+        PoolStats,
+        DbProbe,
+        GcReport,
+        MigrationReport,
+        MigrationStatus,
+        Problem,
+        FieldError,
+    )),
+    modifiers(&Auth, &Problems)
+)]
+pub struct ApiDoc;
+
+/// How requests log in. `token` is what `/api/v1/user/login` gives out, or `ApiKey <key>`, sent as the whole header.
+/// `metrics_token` is only for `/metrics`.
+struct Auth;
+
+impl Modify for Auth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "Authorization",
+                "The token from `/api/v1/user/login`, or `ApiKey <key>` for an API key.",
+            ))),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::with_description(
+                SESSION_COOKIE,
+                "The cookie from `/api/v1/session`. Writes also need its CSRF token in `X-CSRF-Token`.",
+            ))),
+        );
+        components.add_security_scheme(
+            "delivery_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("A delivery token, which can also be sent as `?access_token=`."))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "metrics_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("`APP_METRICS_TOKEN`, when it is set."))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Every error is a `Problem`, so rather than listing each one on every path they are all the default response.
+struct Problems;
+
+impl Modify for Problems {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let problem = ResponseBuilder::new()
+            .description("What went wrong")
+            .content(
+                super::errors_service::PROBLEM_CONTENT_TYPE,
+                Content::new(Ref::from_schema_name("Problem")),
+            )
+            .build();
+
+        for item in openapi.paths.paths.values_mut() {
+            for operation in item.operations.values_mut() {
+                operation.responses.responses.insert(String::from("default"), RefOr::T(problem.clone()));
+            }
+        }
+    }
+}
+
+/// Swagger UI for the spec, loaded from a CDN so that none of it has to be served from here.
+pub const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>Radical API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+        window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+    </script>
+</body>
+</html>
+"##;
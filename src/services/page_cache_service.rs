@@ -0,0 +1,111 @@
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web;
+
+use super::cache_service::CacheBackend;
+use super::metrics_service;
+use super::publish_service::PublishJob;
+use super::response_cache_service::ResponseCache;
+use super::route_service::is_pattern;
+use super::site_service;
+use crate::models::config_models::LocalConfig;
+use crate::models::menu_models::MenuItem;
+use crate::models::page_models::Page;
+use crate::models::site_models::Site;
+use crate::models::{DbPool, Model};
+
+const KEY_PREFIX: &str = "pages:";
+
+/// Rendered pages by site and URL, so that a popular page isn't rendered again for every visitor.
+/// It is turned off unless `APP_PAGE_CACHE_TTL` is set. Pages are dropped as soon as they or their modules change (see `run`),
+/// so the TTL only bounds how stale a page can get through changes made outside of the server.
+pub struct PageCache {
+    cache: Arc<dyn CacheBackend>,
+    ttl: Option<Duration>,
+}
+
+impl PageCache {
+    pub fn new(cache: Arc<dyn CacheBackend>, conf: &LocalConfig) -> Self {
+        Self {
+            cache,
+            ttl: conf.page_cache_ttl.map(Duration::from_secs),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.ttl.is_some()
+    }
+
+    fn key(site: &str, url: &str) -> String {
+        format!("{}{}:{}", KEY_PREFIX, site, url)
+    }
+
+    /// The page at `url` on the current site.
+    pub fn get(&self, url: &str) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let html = self.cache.get(&Self::key(&site_service::current_or_default(), url)).ok().flatten();
+        metrics_service::PAGE_CACHE.record(html.is_some());
+
+        html
+    }
+
+    pub fn set(&self, url: &str, html: &str) {
+        if self.is_enabled() {
+            self.cache.set(&Self::key(&site_service::current_or_default(), url), html, self.ttl).ok();
+        }
+    }
+
+    pub fn delete(&self, url: &str) {
+        self.delete_on(&site_service::current_or_default(), url);
+    }
+
+    /// Drops the page at `url` on another site than the current one, like the site of a page that changed.
+    pub fn delete_on(&self, site: &str, url: &str) {
+        self.cache.delete(&Self::key(site, url)).ok();
+    }
+
+    /// Drops every page, like after the templates change.
+    pub fn clear(&self) {
+        self.cache.clear(KEY_PREFIX).ok();
+    }
+}
+
+/// Drops pages from the cache as they change. A page that a menu links to is shown on every page with that menu,
+/// so all of them are dropped when it changes, as they are when there is no telling.
+/// Finding the pages takes the database, so this happens right after the change rather than in the request that made it.
+/// The cached responses are dropped again once it is done, as they may have been filled from the pages that were just dropped.
+pub fn run(pool: DbPool, pages: web::Data<PageCache>, responses: web::Data<ResponseCache>, jobs: Receiver<PublishJob>) {
+    for job in jobs {
+        let db = pool.get().ok();
+        match job {
+            PublishJob::Page(uuid) => {
+                let linked = db.as_ref().is_none_or(|db| MenuItem::links_to_page(&uuid, db).unwrap_or(true));
+                let page = db.as_ref().and_then(|db| Page::read_any(uuid, db).ok());
+                match page {
+                    Some(page) if !linked && !is_pattern(&page.page_url) => pages.delete_on(&page.site_id, &page.page_url),
+                    // without the URL there is no telling which page it was, and a pattern is cached under every URL it served.
+                    _ => pages.clear(),
+                }
+            }
+            PublishJob::Removed(url) => {
+                let linked = db.as_ref().is_none_or(|db| MenuItem::links_to_url(&url, db).unwrap_or(true));
+                // there is no telling which site the URL was on, so it is dropped on all of them.
+                let sites = db.as_ref().and_then(|db| Site::read_all(db).ok());
+                match sites {
+                    Some(sites) if !linked => sites.iter().for_each(|site| pages.delete_on(&site.uuid, &url)),
+                    _ => pages.clear(),
+                }
+            }
+            PublishJob::Everything => pages.clear(),
+        }
+
+        if pages.is_enabled() {
+            responses.clear();
+        }
+    }
+}
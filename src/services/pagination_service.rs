@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+use super::errors_service::CustomHttpError;
+use crate::models::DbConnection;
+
+pub const DEFAULT_PER_PAGE: i64 = 20;
+/// Asking for more than this gets this many, so that one request can't load a whole table.
+pub const MAX_PER_PAGE: i64 = 100;
+
+/// `?page=` and `?per_page=` on a list endpoint. Pages start at 1.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+pub struct PageQuery {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+impl PageQuery {
+    /// Whether the client asked for a page at all. Lists are sent whole otherwise, as they were before pagination.
+    pub fn is_requested(&self) -> bool {
+        self.page.is_some() || self.per_page.is_some()
+    }
+
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1)
+    }
+
+    pub fn per_page(&self) -> i64 {
+        self.per_page.unwrap_or(DEFAULT_PER_PAGE).min(MAX_PER_PAGE)
+    }
+
+    /// The offset and limit to read, or a 400 if either parameter is below 1.
+    pub fn window(&self) -> Result<(i64, i64), CustomHttpError> {
+        if self.page() < 1 || self.per_page() < 1 {
+            return Err(CustomHttpError::BadRequest);
+        }
+
+        Ok(((self.page() - 1) * self.per_page(), self.per_page()))
+    }
+}
+
+/// One page of a list, with what is needed to ask for the others.
+#[derive(Debug, Serialize, Clone)]
+pub struct Paginated<T> {
+    pub data: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    /// Every row there is, not only the ones on this page.
+    pub total: i64,
+    pub total_pages: i64,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(data: Vec<T>, total: i64, query: &PageQuery) -> Self {
+        let per_page = query.per_page();
+
+        Self {
+            data,
+            page: query.page(),
+            per_page,
+            total,
+            total_pages: (total + per_page - 1) / per_page,
+        }
+    }
+
+    pub fn try_map<U, E>(self, f: impl FnOnce(Vec<T>) -> Result<Vec<U>, E>) -> Result<Paginated<U>, E> {
+        Ok(Paginated {
+            data: f(self.data)?,
+            page: self.page,
+            per_page: self.per_page,
+            total: self.total,
+            total_pages: self.total_pages,
+        })
+    }
+}
+
+/// Either a whole list or one page of it, depending on whether the client asked for a page.
+/// A whole list is sent as a plain array and a page as a `Paginated` envelope.
+#[derive(Debug, Serialize, Clone)]
+#[serde(untagged)]
+pub enum Listing<T> {
+    All(Vec<T>),
+    Page(Paginated<T>),
+}
+
+impl<T> Listing<T> {
+    pub fn try_map<U, E>(self, f: impl FnOnce(Vec<T>) -> Result<Vec<U>, E>) -> Result<Listing<U>, E> {
+        match self {
+            Self::All(data) => Ok(Listing::All(f(data)?)),
+            Self::Page(page) => Ok(Listing::Page(page.try_map(f)?)),
+        }
+    }
+}
+
+type ReadPage<T> = fn(i64, i64, &DbConnection) -> Result<(Vec<T>, i64), diesel::result::Error>;
+
+/// Reads a whole list with `all`, or the page the client asked for with `page`, like `Model::read_all` and `Model::read_paginated`.
+pub fn list<T>(
+    query: &PageQuery,
+    db: &DbConnection,
+    all: fn(&DbConnection) -> Result<Vec<T>, diesel::result::Error>,
+    page: ReadPage<T>,
+) -> Result<Listing<T>, CustomHttpError> {
+    if !query.is_requested() {
+        return Ok(Listing::All(all(db)?));
+    }
+
+    let (offset, limit) = query.window()?;
+    let (data, total) = page(offset, limit, db)?;
+
+    Ok(Listing::Page(Paginated::new(data, total, query)))
+}
@@ -0,0 +1,91 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+
+use super::auth_service::{encrypt_password, generate_secret};
+use super::cache_service::CacheBackend;
+use super::delivery_service::hash_token;
+use super::errors_service::CustomHttpError;
+use super::mail_service::Mail;
+use super::rate_limit_service::check_hourly;
+use super::validation_service::FieldError;
+use crate::models::config_models::LocalConfig;
+use crate::models::password_reset_models::{MutPasswordReset, PasswordReset};
+use crate::models::user_models::User;
+use crate::models::DbConnection;
+
+/// How long the token in a reset mail works for.
+const TTL_MINUTES: i64 = 60;
+/// How many resets each IP can ask for an hour, as each one sends a mail.
+const RESETS_PER_HOUR: u64 = 5;
+
+fn error(field: &str, message: &str) -> CustomHttpError {
+    CustomHttpError::Invalid(vec![FieldError {
+        field: field.to_string(),
+        message: message.to_string(),
+    }])
+}
+
+/// Counts a reset asked for by `client`, like its IP, with a 429 once it is over `RESETS_PER_HOUR`.
+pub fn check_rate(client: &str, cache: &dyn CacheBackend) -> Result<(), CustomHttpError> {
+    check_hourly("password_resets", client, RESETS_PER_HOUR, cache)
+}
+
+/// Where the mail links to, `APP_PASSWORD_RESET_URL` with the token added, if it is set.
+fn reset_url(conf: &LocalConfig, token: &str) -> Option<String> {
+    let base = conf.password_reset_url.as_deref().filter(|url| !url.is_empty())?;
+    let separator = if base.contains('?') { '&' } else { '?' };
+
+    Some(format!("{}{}token={}", base, separator, token))
+}
+
+/// A new reset token for each user with the email, and the mails that send them. Nobody having the email isn't an
+/// error, so that the answer doesn't tell who has an account.
+pub fn request(email: &str, conf: &LocalConfig, db: &DbConnection) -> Result<Vec<Mail>, CustomHttpError> {
+    PasswordReset::delete_expired(db)?;
+
+    let mut mails = Vec::new();
+    for user in User::read_by_email(email.to_string(), db)? {
+        let token = generate_secret();
+        PasswordReset::create(
+            &MutPasswordReset {
+                uuid: Uuid::new_v4().to_string(),
+                user_uuid: user.uuid.clone(),
+                token_hash: hash_token(&token),
+                time_expires: (Utc::now() + Duration::minutes(TTL_MINUTES)).naive_utc(),
+            },
+            db,
+        )?;
+
+        mails.push(Mail {
+            to: vec![email.to_string()],
+            subject: String::from("Reset your password"),
+            template: String::from("password-reset"),
+            data: json!({
+                "username": user.username,
+                "url": reset_url(conf, &token),
+                "token": token,
+                "minutes": TTL_MINUTES,
+            }),
+        });
+    }
+
+    Ok(mails)
+}
+
+/// Sets the password of the user the token was mailed to, and uses up every token they were mailed.
+pub fn reset(token: &str, password: &str, db: &DbConnection) -> Result<(), CustomHttpError> {
+    if password.is_empty() {
+        return Err(error("password", "is required"));
+    }
+    let reset = match PasswordReset::read_valid(&hash_token(token), db) {
+        Err(diesel::result::Error::NotFound) => return Err(error("token", "is wrong or has expired")),
+        reset => reset?,
+    };
+    let user = User::read_by_uuid(reset.user_uuid.clone(), db)?;
+
+    User::set_password(user.username, encrypt_password(&password.to_string())?, db)?;
+    PasswordReset::delete_for_user(&reset.user_uuid, db)?;
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use actix_web::Scope;
+
+use super::errors_service::CustomHttpError;
+use super::webhook_service::WebhookEvent;
+use crate::models::module_models::MutModule;
+use crate::models::page_models::MutPage;
+
+/// A page or module that is about to be written, as `ContentHook::before_save` gets it.
+pub enum Content<'a> {
+    Page(&'a mut MutPage),
+    Module(&'a mut MutModule),
+}
+
+/// Runs as content is saved, for plugins like custom validation or analytics that shouldn't need the controllers patched.
+pub trait ContentHook: Send + Sync {
+    /// Runs before a page or module is created or updated, after it has been checked like any other but before it is
+    /// written. It can change what is written, or refuse the write with an error, like `CustomHttpError::Invalid`.
+    /// Writes through the API, the batch endpoints, imports, `radical git import`, and `radical seed` all go through it.
+    fn before_save(&self, _content: Content) -> Result<(), CustomHttpError> {
+        Ok(())
+    }
+
+    /// Runs after a change has been saved, with the same event webhooks are sent, like `page.updated`.
+    /// It runs in the request that made the change, so anything slow belongs on a thread of the plugin's own.
+    fn after_save(&self, _event: &WebhookEvent) {}
+}
+
+/// Adds endpoints to the API. Its scope is mounted under `/api/v1`, behind the same login as the rest of it,
+/// and is made again for every worker the server runs.
+pub trait RouteProvider: Send + Sync {
+    fn scope(&self) -> Scope;
+}
+
+/// The plugins the server runs with, which are installed before it starts and can't change after.
+#[derive(Default, Clone)]
+pub struct Plugins {
+    content_hooks: Vec<Arc<dyn ContentHook>>,
+    route_providers: Vec<Arc<dyn RouteProvider>>,
+}
+
+impl Plugins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Installs a content hook. Hooks run in the order they are installed, and the first to refuse a write stops it.
+    pub fn content_hook(&mut self, hook: impl ContentHook + 'static) {
+        self.content_hooks.push(Arc::new(hook));
+    }
+
+    pub fn route_provider(&mut self, provider: impl RouteProvider + 'static) {
+        self.route_providers.push(Arc::new(provider));
+    }
+
+    pub fn before_save_page(&self, page: &mut MutPage) -> Result<(), CustomHttpError> {
+        for hook in &self.content_hooks {
+            hook.before_save(Content::Page(page))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn before_save_module(&self, module: &mut MutModule) -> Result<(), CustomHttpError> {
+        for hook in &self.content_hooks {
+            hook.before_save(Content::Module(module))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn after_save(&self, event: &WebhookEvent) {
+        for hook in &self.content_hooks {
+            hook.after_save(event);
+        }
+    }
+
+    /// Mounts the scope of every route provider.
+    pub fn routes(&self, scope: Scope) -> Scope {
+        self.route_providers.iter().fold(scope, |scope, provider| scope.service(provider.scope()))
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
+use std::sync::{Mutex, RwLock};
+
+use actix::{Actor, ActorContext, AsyncContext, Handler, Message, Recipient, StreamHandler};
+use actix_web::{web, HttpRequest};
+use actix_web_actors::ws;
+use chrono::{Duration, NaiveDateTime, SubsecRound, Utc};
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+
+use super::auth_service;
+use super::errors_service::CustomHttpError;
+use super::publish_service::PublishJob;
+use super::render_service::{render_module, render_page, render_region};
+use super::site_service;
+use super::visibility_service::{strip_fields, Role};
+use crate::models::page_models::Page;
+use crate::models::{DbConnection, DbPool};
+
+/// Everything that makes up a page, rendered. Regions and modules are keyed by their title and uuid,
+/// which are what the fragment templates tag their HTML with.
+#[derive(Serialize, Message, Clone)]
+#[rtype(result = "()")]
+pub struct Fragments {
+    pub page: String,
+    pub regions: HashMap<String, String>,
+    pub modules: HashMap<String, String>,
+}
+
+pub fn render_fragments(page_uuid: String, db: &DbConnection, hb: &Handlebars) -> Result<Fragments, CustomHttpError> {
+    let mut page = Page::read_one_join_on(page_uuid.clone(), db)?;
+    // previews show the page the way visitors will see it.
+    page.fields = strip_fields(page.fields, Role::Public);
+
+    let mut regions = HashMap::new();
+    let mut modules = HashMap::new();
+
+    for module in &page.fields.modules {
+        modules.insert(module.uuid.clone(), render_module(module.uuid.clone(), db, hb)?);
+    }
+    for category in page.fields.categories.iter().flatten() {
+        regions.insert(
+            category.title.clone(),
+            render_region(page_uuid.clone(), category.title.clone(), db, hb)?,
+        );
+        for module in &category.modules {
+            modules.insert(module.uuid.clone(), render_module(module.uuid.clone(), db, hb)?);
+        }
+    }
+
+    Ok(Fragments {
+        page: render_page(page.page_url, db, hb)?,
+        regions,
+        modules,
+    })
+}
+
+/// The query parameter rendered pages take a preview link's token in.
+pub const PREVIEW_PARAM: &str = "preview";
+
+#[derive(Deserialize)]
+pub struct PreviewQuery {
+    pub preview: Option<String>,
+}
+
+/// The token of the preview link a request was made with, if any.
+pub fn link_token(req: &HttpRequest) -> Option<String> {
+    web::Query::<PreviewQuery>::from_query(req.query_string()).ok().and_then(|q| q.into_inner().preview)
+}
+
+/// What a preview link is signed with, so that whoever has it can see the page before it is published.
+#[derive(Serialize, Deserialize)]
+struct PreviewClaims {
+    exp: usize,
+    /// The uuid of the page, which is the only one the link shows.
+    preview: String,
+}
+
+/// Signs a link to the page that is good for `ttl` seconds, returning its token and when it expires.
+pub fn sign_link(page_uuid: String, ttl: u64, db: &DbConnection) -> Result<(String, NaiveDateTime), CustomHttpError> {
+    let expires = Utc::now().trunc_subsecs(0) + Duration::seconds(ttl as i64);
+    let claims = PreviewClaims {
+        exp: expires.timestamp() as usize,
+        preview: page_uuid,
+    };
+
+    Ok((auth_service::sign(&claims, db)?, expires.naive_utc()))
+}
+
+/// The uuid of the page a preview link shows, if its token is one and hasn't expired.
+pub fn verify_link(token: &str, db: &DbConnection) -> Option<String> {
+    auth_service::verify::<PreviewClaims>(token, db).ok().map(|c| c.preview)
+}
+
+/// A preview someone has opened, and the sockets currently watching it.
+struct PreviewSession {
+    page_uuid: String,
+    sockets: Vec<Recipient<Fragments>>,
+}
+
+/// Every open preview, by its token. Previews only live as long as the server does.
+#[derive(Default)]
+pub struct PreviewHub {
+    sessions: RwLock<HashMap<String, PreviewSession>>,
+}
+
+impl PreviewHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&self, token: String, page_uuid: String) {
+        self.sessions.write().unwrap().insert(
+            token,
+            PreviewSession {
+                page_uuid,
+                sockets: Vec::new(),
+            },
+        );
+    }
+
+    pub fn close(&self, token: &str) -> bool {
+        self.sessions.write().unwrap().remove(token).is_some()
+    }
+
+    pub fn page_for(&self, token: &str) -> Option<String> {
+        self.sessions.read().unwrap().get(token).map(|s| s.page_uuid.clone())
+    }
+
+    fn join(&self, token: &str, socket: Recipient<Fragments>) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(token) {
+            session.sockets.push(socket);
+        }
+    }
+
+    fn leave(&self, token: &str, socket: &Recipient<Fragments>) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(token) {
+            session.sockets.retain(|s| s != socket);
+        }
+    }
+
+    fn watching(&self, page_uuid: &str) -> bool {
+        self.sessions
+            .read()
+            .unwrap()
+            .values()
+            .any(|s| s.page_uuid == page_uuid && !s.sockets.is_empty())
+    }
+
+    fn broadcast(&self, page_uuid: &str, fragments: &Fragments) {
+        for session in self.sessions.read().unwrap().values().filter(|s| s.page_uuid == page_uuid) {
+            for socket in &session.sockets {
+                socket.do_send(fragments.clone()).ok();
+            }
+        }
+    }
+}
+
+/// The socket a preview pane listens on. It is sent the page as it is when it connects, and again on every save.
+pub struct PreviewSocket {
+    pub token: String,
+    pub hub: web::Data<PreviewHub>,
+    pub initial: Fragments,
+}
+
+impl Actor for PreviewSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hub.join(&self.token, ctx.address().recipient());
+        ctx.notify(self.initial.clone());
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        self.hub.leave(&self.token, &ctx.address().recipient());
+    }
+}
+
+impl Handler<Fragments> for PreviewSocket {
+    type Result = ();
+
+    fn handle(&mut self, fragments: Fragments, ctx: &mut Self::Context) {
+        if let Ok(json) = serde_json::to_string(&fragments) {
+            ctx.text(json);
+        }
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for PreviewSocket {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        // the preview only pushes, so all that is handled from the client is keeping the connection alive.
+        match msg {
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Err(_) => ctx.stop(),
+            _ => {}
+        }
+    }
+}
+
+/// Re-renders pages that are being previewed whenever they are saved, and pushes them to their sockets.
+pub fn run(
+    pool: DbPool,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+    hub: web::Data<PreviewHub>,
+    jobs: Receiver<PublishJob>,
+) {
+    for job in jobs {
+        let page_uuid = match job {
+            PublishJob::Page(uuid) if hub.watching(&uuid) => uuid,
+            _ => continue,
+        };
+
+        let fragments = match pool.get() {
+            Ok(conn) => {
+                // rendered as it is on its own site.
+                let site = Page::read_any(page_uuid.clone(), &conn).ok().map(|p| p.site_id);
+                site_service::scoped(site, || render_fragments(page_uuid.clone(), &conn, &hb.lock().unwrap()))
+            }
+            Err(e) => {
+                tracing::error!("preview error: {:?}", e);
+                continue;
+            }
+        };
+
+        match fragments {
+            Ok(fragments) => hub.broadcast(&page_uuid, &fragments),
+            Err(e) => tracing::error!("preview error: {}", e.descriptor()),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use diesel::Connection;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::auth_service::{encrypt_password, generate_secret};
+use super::delivery_service::hash_token;
+use super::errors_service::CustomHttpError;
+use crate::models::api_key_models::ApiKey;
+use crate::models::comment_models::Comment;
+use crate::models::data_request_models::{DataRequest, MutDataRequest};
+use crate::models::form_models::{FormSubmission, FormSubmissionDTO};
+use crate::models::password_reset_models::PasswordReset;
+use crate::models::recovery_code_models::RecoveryCode;
+use crate::models::user_models::User;
+use crate::models::DbConnection;
+
+/// The user with the password and token left out, as those are not personal data but would be a liability to hand out.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct UserExport {
+    pub uuid: String,
+    pub username: String,
+    pub email: Option<String>,
+}
+
+/// Everything stored that is tied to an email address.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct PersonalDataExport {
+    pub email: String,
+    pub users: Vec<UserExport>,
+    pub comments: Vec<Comment>,
+    /// Form submissions with the email as one of their values.
+    pub form_submissions: Vec<FormSubmissionDTO>,
+}
+
+impl PersonalDataExport {
+    pub fn record_count(&self) -> usize {
+        self.users.len() + self.comments.len() + self.form_submissions.len()
+    }
+}
+
+fn collect(email: &str, db: &DbConnection) -> Result<PersonalDataExport, diesel::result::Error> {
+    let users = User::read_by_email(email.to_string(), db)?
+        .into_iter()
+        .map(|u| UserExport {
+            uuid: u.uuid,
+            username: u.username,
+            email: u.email,
+        })
+        .collect();
+
+    Ok(PersonalDataExport {
+        email: email.to_string(),
+        users,
+        comments: Comment::read_by_email(email, db)?,
+        form_submissions: FormSubmission::read_by_email(email, db)?.into_iter().map(|s| s.into()).collect(),
+    })
+}
+
+fn record(
+    email: &str,
+    request_type: &str,
+    actor: String,
+    records_affected: usize,
+    db: &DbConnection,
+) -> Result<usize, diesel::result::Error> {
+    DataRequest::create(
+        &MutDataRequest {
+            uuid: Uuid::new_v4().to_string(),
+            email: hash_token(email),
+            request_type: request_type.to_string(),
+            actor,
+            records_affected: records_affected as i64,
+        },
+        db,
+    )
+}
+
+/// Exports all data tied to the email, recording that `actor` requested it.
+pub fn export(email: &str, actor: String, db: &DbConnection) -> Result<PersonalDataExport, CustomHttpError> {
+    let export = collect(email, db)?;
+
+    record(email, "export", actor, export.record_count(), db)?;
+
+    Ok(export)
+}
+
+/// Anonymizes all data tied to the email. Form submissions are deleted, as there is no telling which of their other
+/// values are personal too, and so are the API keys, recovery codes, and password resets of its users, which could
+/// otherwise still act as them.
+/// Before committing, the erasure is verified by checking that nothing can be exported for the email anymore.
+pub fn erase(email: &str, actor: String, db: &DbConnection) -> Result<usize, CustomHttpError> {
+    let unusable_password = encrypt_password(&generate_secret())?;
+
+    db.transaction::<_, CustomHttpError, _>(|| {
+        for user in User::read_by_email(email.to_string(), db)? {
+            ApiKey::delete_for_user(&user.uuid, db)?;
+            RecoveryCode::delete_for_user(&user.uuid, db)?;
+            PasswordReset::delete_for_user(&user.uuid, db)?;
+        }
+
+        let submissions = FormSubmission::read_by_email(email, db)?.into_iter().map(|s| s.uuid).collect();
+        let erased = User::anonymize_by_email(email.to_string(), unusable_password, db)?
+            + Comment::anonymize_by_email(email, db)?
+            + FormSubmission::delete_many(submissions, db)?;
+
+        if collect(email, db)?.record_count() != 0 {
+            return Err(CustomHttpError::Unknown);
+        }
+
+        DataRequest::replace_email(email, &hash_token(email), db)?;
+        record(email, "erasure", actor, erased, db)?;
+
+        Ok(erased)
+    })
+}
@@ -0,0 +1,427 @@
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use actix_web::web;
+use chrono::Utc;
+use handlebars::Handlebars;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use super::render_service::render_page;
+use super::route_service::is_pattern;
+use super::site_service::{self, DEFAULT_SITE};
+use super::theme_service::Themes;
+use crate::models::config_models::LocalConfig;
+use crate::models::page_models::{is_live, Page, PageDTO, PUBLIC};
+use crate::models::{DbConnection, DbPool, Model};
+
+#[derive(Error, Debug)]
+pub enum PublishError {
+    #[error("The publish target is not configured correctly.")]
+    Config,
+    #[error("The page could not be rendered.")]
+    Render,
+    #[error("The publish target rejected the request: {0}")]
+    Request(String),
+}
+
+/// Somewhere rendered pages can be pushed to.
+pub trait PublishTarget: Send {
+    fn put(&self, path: &str, body: &[u8], content_type: &str) -> Result<(), PublishError>;
+    fn delete(&self, path: &str) -> Result<(), PublishError>;
+}
+
+fn request_error(e: ureq::Error) -> PublishError {
+    PublishError::Request(e.to_string())
+}
+
+/// Any S3 compatible bucket, including GCS through its interoperability endpoint.
+/// `base_url` is the bucket's endpoint, like `https://bucket.s3.us-east-1.amazonaws.com`
+/// or `https://storage.googleapis.com/bucket`.
+pub struct S3Target {
+    base_url: url::Url,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3Target {
+    fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+        // HMAC accepts keys of any length, so this can't fail.
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Sends a request signed with AWS Signature Version 4.
+    fn send(&self, method: &str, path: &str, body: &[u8], content_type: Option<&str>) -> Result<(), PublishError> {
+        let url = self.base_url.join(path).map_err(|_| PublishError::Config)?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(PublishError::Config),
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = format!("{:x}", Sha256::digest(body));
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method,
+            url.path(),
+            host,
+            payload_hash,
+            amz_date,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+            amz_date,
+            scope,
+            Sha256::digest(canonical_request.as_bytes())
+        );
+
+        let signing_key = [date.as_str(), self.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", self.secret_key).into_bytes(), |key, part| Self::hmac(&key, part));
+        let signature: String = Self::hmac(&signing_key, &string_to_sign)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        let mut request = ureq::request(method, url.as_str())
+            .set("x-amz-content-sha256", &payload_hash)
+            .set("x-amz-date", &amz_date)
+            .set(
+                "Authorization",
+                &format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+                    self.access_key, scope, signature
+                ),
+            );
+        if let Some(content_type) = content_type {
+            request = request.set("Content-Type", content_type);
+        }
+
+        request.send_bytes(body).map_err(request_error)?;
+
+        Ok(())
+    }
+}
+
+impl PublishTarget for S3Target {
+    fn put(&self, path: &str, body: &[u8], content_type: &str) -> Result<(), PublishError> {
+        self.send("PUT", path, body, Some(content_type))
+    }
+
+    fn delete(&self, path: &str) -> Result<(), PublishError> {
+        self.send("DELETE", path, &[], None)
+    }
+}
+
+/// Any WebDAV server, authenticated with basic auth.
+pub struct WebDavTarget {
+    base_url: url::Url,
+    authorization: String,
+}
+
+impl WebDavTarget {
+    /// WebDAV servers only accept files into collections that already exist.
+    fn make_collections(&self, path: &str) -> Result<(), PublishError> {
+        let segments: Vec<&str> = path.split('/').collect();
+
+        for i in 1..segments.len() {
+            let collection = self
+                .base_url
+                .join(&format!("{}/", segments[..i].join("/")))
+                .map_err(|_| PublishError::Config)?;
+
+            match ureq::request("MKCOL", collection.as_str())
+                .set("Authorization", &self.authorization)
+                .call()
+            {
+                // 405 means the collection already exists.
+                Ok(_) | Err(ureq::Error::Status(405, _)) => {}
+                Err(e) => return Err(request_error(e)),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PublishTarget for WebDavTarget {
+    fn put(&self, path: &str, body: &[u8], content_type: &str) -> Result<(), PublishError> {
+        self.make_collections(path)?;
+
+        let url = self.base_url.join(path).map_err(|_| PublishError::Config)?;
+        ureq::put(url.as_str())
+            .set("Authorization", &self.authorization)
+            .set("Content-Type", content_type)
+            .send_bytes(body)
+            .map_err(request_error)?;
+
+        Ok(())
+    }
+
+    fn delete(&self, path: &str) -> Result<(), PublishError> {
+        let url = self.base_url.join(path).map_err(|_| PublishError::Config)?;
+
+        match ureq::delete(url.as_str())
+            .set("Authorization", &self.authorization)
+            .call()
+        {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+            Err(e) => Err(request_error(e)),
+        }
+    }
+}
+
+/// Builds the target from `APP_PUBLISH_TARGET` and friends. `None` means publishing is turned off.
+pub fn target(conf: &LocalConfig) -> Option<Result<Box<dyn PublishTarget>, PublishError>> {
+    let kind = conf.publish_target.as_deref()?;
+
+    let base_url = conf
+        .publish_url
+        .as_deref()
+        // join treats the last segment as a file unless the url ends with a slash.
+        .map(|u| format!("{}/", u.trim_end_matches('/')))
+        .and_then(|u| url::Url::parse(&u).ok());
+    let access_key = conf.publish_access_key.clone().unwrap_or_default();
+    let secret_key = conf.publish_secret_key.clone().unwrap_or_default();
+
+    let target: Result<Box<dyn PublishTarget>, PublishError> = match (kind, base_url) {
+        ("s3", Some(base_url)) => Ok(Box::new(S3Target {
+            base_url,
+            region: conf.publish_region.clone().unwrap_or_else(|| String::from("us-east-1")),
+            access_key,
+            secret_key,
+        })),
+        ("webdav", Some(base_url)) => Ok(Box::new(WebDavTarget {
+            base_url,
+            authorization: format!(
+                "Basic {}",
+                base64::encode(format!("{}:{}", access_key, secret_key))
+            ),
+        })),
+        _ => Err(PublishError::Config),
+    };
+
+    Some(target)
+}
+
+/// A change to published content, sent to everything that mirrors it.
+#[derive(Clone, Debug)]
+pub enum PublishJob {
+    /// Renders and uploads the page with this uuid.
+    Page(String),
+    /// Removes the object for a page that has been deleted, by its URL.
+    Removed(String),
+    /// Uploads every page and asset.
+    Everything,
+}
+
+impl PublishJob {
+    /// What is published after a module on `page_uuid` changes, which is every page for a global module, as they all show it.
+    pub fn for_module(page_uuid: Option<String>) -> Self {
+        page_uuid.map_or(Self::Everything, Self::Page)
+    }
+}
+
+/// What runs as soon as content changes, like dropping what is cached of it.
+type Hook = Box<dyn Fn(&PublishJob) + Send + Sync>;
+
+/// The queue publish jobs are sent through, which everything that changes content sends a job to once the change is saved.
+/// Every worker that subscribes gets its own copy of each job, so static publishing and mirroring to git don't hold each
+/// other up. Sending does nothing if nothing is subscribed, so callers don't have to check.
+#[derive(Default)]
+pub struct PublishQueue {
+    senders: Mutex<Vec<Sender<PublishJob>>>,
+    hooks: Vec<Hook>,
+}
+
+impl PublishQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<PublishJob> {
+        let (sender, receiver) = channel();
+        self.attach(sender);
+
+        receiver
+    }
+
+    /// Subscribes an existing channel, for workers that also take jobs from somewhere other than the queue.
+    pub fn attach(&mut self, sender: Sender<PublishJob>) {
+        self.senders.get_mut().unwrap().push(sender);
+    }
+
+    /// Runs `hook` on every job as it is sent, before the request that sent it responds, so that nothing cached outlives
+    /// the change. Hooks can't reach the database, so work like rendering belongs in a worker instead.
+    pub fn hook(&mut self, hook: impl Fn(&PublishJob) + Send + Sync + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    pub fn send(&self, job: PublishJob) {
+        for hook in &self.hooks {
+            hook(&job);
+        }
+        for sender in self.senders.lock().unwrap().iter() {
+            // workers only stop along with the server, so there is no one to report a failed send to.
+            sender.send(job.clone()).ok();
+        }
+    }
+
+    /// Stops sending jobs, so that the workers subscribed through `subscribe` return once they have done the jobs they
+    /// already have. Used when the server stops.
+    pub fn close(&self) {
+        self.senders.lock().unwrap().clear();
+    }
+}
+
+/// The object a page is stored as. Every page is a directory with an `index.html`,
+/// so that the URLs stay the same when served by a static host.
+pub fn object_path(url: &str) -> String {
+    match url.trim_matches('/') {
+        "" => String::from("index.html"),
+        path => format!("{}/index.html", path),
+    }
+}
+
+pub fn publish_page(
+    url: String,
+    target: &dyn PublishTarget,
+    db: &DbConnection,
+    hb: &Mutex<Handlebars<'static>>,
+) -> Result<(), PublishError> {
+    let html = render_page(url.clone(), db, &hb.lock().unwrap()).map_err(|_| PublishError::Render)?;
+
+    target.put(&object_path(&url), html.as_bytes(), "text/html; charset=utf-8")
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("css") => "text/css",
+        Some("js") => "application/javascript",
+        Some("html") => "text/html; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Publishes the files under `dir` by their path under `root`, which is where the theme's static files start.
+pub fn publish_assets(root: &Path, dir: &Path, target: &dyn PublishTarget) -> Result<usize, PublishError> {
+    let mut published = 0;
+    let entries = fs::read_dir(dir).map_err(|e| PublishError::Request(e.to_string()))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            published += publish_assets(root, &path, target)?;
+            continue;
+        }
+
+        let body = fs::read(&path).map_err(|e| PublishError::Request(e.to_string()))?;
+        // assets are served from /assets, the same as the server does.
+        let object = Path::new("assets").join(path.strip_prefix(root).unwrap_or(&path));
+
+        target.put(&object.to_string_lossy(), &body, content_type(&path))?;
+        published += 1;
+    }
+
+    Ok(published)
+}
+
+/// Publishes every published page other than patterns, which have no one file to be published as. Returns how many there were.
+pub fn publish_pages(target: &dyn PublishTarget, db: &DbConnection, hb: &Mutex<Handlebars<'static>>) -> Result<usize, PublishError> {
+    let pages: Vec<PageDTO> = Page::read_published(db).map_err(|_| PublishError::Render)?;
+    let pages: Vec<PageDTO> = pages.into_iter().filter(|p| !is_pattern(&p.page_url)).collect();
+    for page in &pages {
+        publish_page(page.page_url.clone(), target, db, hb)?;
+    }
+
+    Ok(pages.len())
+}
+
+fn handle(
+    job: PublishJob,
+    target: &dyn PublishTarget,
+    pool: &DbPool,
+    hb: &Mutex<Handlebars<'static>>,
+    themes: &Themes,
+) -> Result<(), PublishError> {
+    let db = pool.get().map_err(|_| PublishError::Render)?;
+
+    match job {
+        PublishJob::Page(uuid) => {
+            let page: PageDTO = match Page::read_one(uuid, &db) {
+                Ok(page) => page,
+                // like a page of another site.
+                Err(diesel::result::Error::NotFound) => return Ok(()),
+                Err(_) => return Err(PublishError::Render),
+            };
+
+            // a page going back to being a draft, or only being for some users, is taken down, and a pattern has no one
+            // file to be published as.
+            match is_live(&page.status, page.publish_at) && page.visibility == PUBLIC {
+                _ if is_pattern(&page.page_url) => Ok(()),
+                true => publish_page(page.page_url, target, &db, hb),
+                false => target.delete(&object_path(&page.page_url)),
+            }
+        }
+        // the URL may have been another site's, while one of this site's pages is still at it.
+        PublishJob::Removed(url) => match Page::read_one_by_url(url.clone(), &db) {
+            Ok(page) if is_live(&page.status, page.publish_at) && page.visibility == PUBLIC => Ok(()),
+            _ => target.delete(&object_path(&url)),
+        },
+        PublishJob::Everything => {
+            publish_pages(target, &db, hb)?;
+
+            let assets_dir = themes.assets_dir();
+            let assets = publish_assets(&assets_dir, &assets_dir, target)?;
+            tracing::info!("Published everything, including {} assets.", assets);
+
+            Ok(())
+        }
+    }
+}
+
+/// Works through publish jobs as they come in. Failed jobs are logged and dropped,
+/// as the next change to the page or a full republish will send it again. Only the default site is published.
+pub fn run(
+    pool: DbPool,
+    conf: LocalConfig,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+    themes: web::Data<Themes>,
+    jobs: Receiver<PublishJob>,
+) {
+    let target = match target(&conf) {
+        Some(Ok(target)) => target,
+        Some(Err(e)) => {
+            tracing::warn!("Publishing is turned off: {}", e);
+            return;
+        }
+        None => return,
+    };
+
+    for job in jobs {
+        let res = site_service::scoped(Some(String::from(DEFAULT_SITE)), || handle(job, target.as_ref(), &pool, &hb, &themes));
+        if let Err(e) = res {
+            tracing::error!("Failed to publish: {}", e);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::errors_service::CustomHttpError;
+use super::site_service;
+use crate::models::config_models::LocalConfig;
+use crate::models::media_models::Media;
+use crate::models::module_models::Module;
+use crate::models::page_models::Page;
+use crate::models::site_models::Site;
+use crate::models::{DbConnection, Model};
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct QuotaUsage {
+    pub used: i64,
+    /// `None` means there is no limit.
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct Usage {
+    pub pages: QuotaUsage,
+    pub modules: QuotaUsage,
+    /// The bytes all of the site's uploads take up.
+    pub media_bytes: QuotaUsage,
+}
+
+/// The quotas of the current site, which are the config's unless the site has its own.
+struct Limits {
+    pages: Option<i64>,
+    modules: Option<i64>,
+    media_bytes: Option<i64>,
+}
+
+fn limits(conf: &LocalConfig, db: &DbConnection) -> Result<Limits, CustomHttpError> {
+    let site = match site_service::current() {
+        Some(site) => Some(Site::read_one(site, db)?),
+        None => None,
+    };
+    let of = |quota: fn(&Site) -> Option<i64>, default: Option<i64>| site.as_ref().and_then(quota).or(default);
+
+    Ok(Limits {
+        pages: of(|s| s.max_pages, conf.max_pages),
+        modules: of(|s| s.max_modules, conf.max_modules),
+        media_bytes: of(|s| s.max_media_bytes, conf.max_media_bytes),
+    })
+}
+
+/// Errors if `adding` more of what `used` counts would go over `limit`.
+fn check(resource: &str, used: i64, adding: i64, limit: Option<i64>) -> Result<(), CustomHttpError> {
+    match limit {
+        Some(limit) if used + adding > limit => Err(CustomHttpError::QuotaExceeded {
+            resource: resource.to_string(),
+            limit,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Errors if creating another page would go over the quota.
+pub fn check_pages(conf: &LocalConfig, db: &DbConnection) -> Result<(), CustomHttpError> {
+    check("pages", Page::count(db)?, 1, limits(conf, db)?.pages)
+}
+
+/// Errors if creating another module would go over the quota.
+pub fn check_modules(conf: &LocalConfig, db: &DbConnection) -> Result<(), CustomHttpError> {
+    check("modules", Module::count(db)?, 1, limits(conf, db)?.modules)
+}
+
+/// Errors if an upload of `size` bytes would go over the quota.
+pub fn check_media(conf: &LocalConfig, size: i64, db: &DbConnection) -> Result<(), CustomHttpError> {
+    check("media bytes", Media::total_size(db)?, size, limits(conf, db)?.media_bytes)
+}
+
+pub fn usage(conf: &LocalConfig, db: &DbConnection) -> Result<Usage, CustomHttpError> {
+    let limits = limits(conf, db)?;
+
+    Ok(Usage {
+        pages: QuotaUsage {
+            used: Page::count(db)?,
+            limit: limits.pages,
+        },
+        modules: QuotaUsage {
+            used: Module::count(db)?,
+            limit: limits.modules,
+        },
+        media_bytes: QuotaUsage {
+            used: Media::total_size(db)?,
+            limit: limits.media_bytes,
+        },
+    })
+}
@@ -0,0 +1,297 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::ServiceRequest;
+use actix_web::http::{HeaderMap, HeaderName, HeaderValue, Method};
+use actix_web::{HttpMessage, HttpRequest};
+
+use super::cache_service::CacheBackend;
+use super::delivery_service::hash_token;
+use super::errors_service::CustomHttpError;
+use super::session_service::SESSION_COOKIE;
+use super::warmup_service::PROBES;
+use crate::models::config_models::LocalConfig;
+
+const HOUR: Duration = Duration::from_secs(3600);
+/// How long requests with a credential that was checked are counted against it, rather than against their IP.
+const VERIFIED_FOR: Duration = HOUR;
+
+/// Put on a request once the credential it was sent with has been checked, like by `Claims`, with the credential as it
+/// was sent.
+pub struct Verified(pub String);
+
+/// Tells the limiter that `credential` of the request was checked, so that the requests sent with it after this one are
+/// counted against it.
+pub fn verified(req: &HttpRequest, credential: String) {
+    req.extensions_mut().insert(Verified(credential));
+}
+
+/// The token, API key, session, or delivery token the request was sent with, whether it is valid or not.
+fn credential(req: &impl HttpMessage, query: &str) -> Option<String> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| req.cookie(SESSION_COOKIE).map(|c| c.value().to_string()))
+        .or_else(|| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "access_token")
+                .map(|(_, token)| token.to_string())
+        })
+}
+
+fn verified_key(credential: &str) -> String {
+    format!("ratelimit:verified:{}", hash_token(credential))
+}
+
+/// Where a client stands against the limit of the current interval.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until the interval ends and the count starts over.
+    pub reset: u64,
+    pub exceeded: bool,
+}
+
+impl RateLimit {
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert(HeaderName::from_static("x-ratelimit-limit"), HeaderValue::from(self.limit));
+        headers.insert(HeaderName::from_static("x-ratelimit-remaining"), HeaderValue::from(self.remaining));
+        headers.insert(HeaderName::from_static("x-ratelimit-reset"), HeaderValue::from(self.reset));
+        if self.exceeded {
+            headers.insert(HeaderName::from_static("retry-after"), HeaderValue::from(self.reset));
+        }
+    }
+}
+
+/// How many requests a client can make each interval. Reads are the public content routes, `GET`, `HEAD` and `OPTIONS`,
+/// and writes are everything else, so that a crawler reading the site can't use up what an editor needs to save.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub anonymous_reads: u64,
+    pub anonymous_writes: u64,
+    /// For clients that send a token, an API key, or a session that has been checked, which are counted on their own
+    /// rather than by IP.
+    pub key_reads: u64,
+    pub key_writes: u64,
+}
+
+impl Limits {
+    /// Each limit that isn't set falls back to `APP_MAX_REQ`.
+    pub fn from_conf(conf: &LocalConfig) -> Self {
+        let default = u64::from(conf.max_req);
+
+        Self {
+            anonymous_reads: conf.rate_limit_anonymous_reads.unwrap_or(default),
+            anonymous_writes: conf.rate_limit_anonymous_writes.unwrap_or(default),
+            key_reads: conf.rate_limit_key_reads.unwrap_or(default),
+            key_writes: conf.rate_limit_key_writes.unwrap_or(default),
+        }
+    }
+}
+
+/// Counts requests per client in fixed intervals. The counts are kept in the cache backend,
+/// so instances sharing a Redis also share their limits.
+pub struct RateLimiter {
+    cache: Arc<dyn CacheBackend>,
+    limits: Limits,
+    interval: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(cache: Arc<dyn CacheBackend>, limits: Limits, interval: Duration) -> Self {
+        Self {
+            cache,
+            limits,
+            interval,
+        }
+    }
+
+    /// Who the request is counted against, and whether that is a key rather than an IP.
+    /// Checking a credential takes the database, so it isn't done here. A credential is only counted on its own once a
+    /// request sent with it had it checked, and until then it is counted against the IP with the anonymous limits, so
+    /// that made up credentials can't be used to get around them.
+    fn client(&self, req: &ServiceRequest) -> Option<(String, bool)> {
+        let keyed = credential(req, req.query_string()).filter(|credential| matches!(self.cache.get(&verified_key(credential)), Ok(Some(_))));
+
+        match keyed {
+            Some(credential) => Some((format!("key:{}", hash_token(&credential)), true)),
+            None => req.peer_addr().map(|addr| (format!("ip:{}", addr.ip()), false)),
+        }
+    }
+
+    /// Remembers the credential of a request that had it checked, once it has been answered.
+    pub fn remember(&self, req: &HttpRequest) {
+        let verified = req.extensions().get::<Verified>().map(|verified| verified.0.clone());
+
+        if let (Some(credential), Some(verified)) = (credential(req, req.query_string()), verified) {
+            if credential == verified {
+                let _ = self.cache.set(&verified_key(&credential), "1", Some(VERIFIED_FOR));
+            }
+        }
+    }
+
+    /// Counts the request. Returns `None` when the cache can't be reached,
+    /// in which case requests are let through rather than every one of them refused.
+    pub fn check(&self, req: &ServiceRequest) -> Option<RateLimit> {
+        if PROBES.contains(&req.path()) {
+            return None;
+        }
+        // GraphQL queries are posted, but only ever read.
+        let read = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) || req.path() == "/graphql";
+        let (client, keyed) = self.client(req)?;
+        let max_requests = match (keyed, read) {
+            (false, true) => self.limits.anonymous_reads,
+            (false, false) => self.limits.anonymous_writes,
+            (true, true) => self.limits.key_reads,
+            (true, false) => self.limits.key_writes,
+        };
+
+        let interval = self.interval.as_secs().max(1);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let kind = if read { "read" } else { "write" };
+        let key = format!("ratelimit:{}:{}:{}", kind, client, now / interval);
+
+        let count = self.cache.increment(&key, self.interval).ok()?;
+
+        Some(RateLimit {
+            limit: max_requests,
+            remaining: max_requests.saturating_sub(count),
+            reset: interval - now % interval,
+            exceeded: count > max_requests,
+        })
+    }
+}
+
+/// Counts something `client`, like an IP, sent of `kind`, like a comment, with a 429 once it has sent more than `limit` this hour.
+/// Like the rate limits, the count is let through when the cache can't be reached.
+pub fn check_hourly(kind: &str, client: &str, limit: u64, cache: &dyn CacheBackend) -> Result<(), CustomHttpError> {
+    let hour = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs() / HOUR.as_secs()).unwrap_or_default();
+    let key = format!("{}:{}:{}", kind, hash_token(client), hour);
+
+    match cache.increment(&key, HOUR) {
+        Ok(count) if count > limit => Err(CustomHttpError::TooManyRequests { limit }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+    use crate::services::cache_service::MemoryCache;
+
+    const LIMITS: Limits = Limits {
+        anonymous_reads: 2,
+        anonymous_writes: 1,
+        key_reads: 4,
+        key_writes: 3,
+    };
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(Arc::new(MemoryCache::new()), LIMITS, HOUR)
+    }
+
+    fn request(method: Method, path: &str, ip: &str, token: Option<&str>) -> TestRequest {
+        let req = TestRequest::with_uri(path).method(method).peer_addr(format!("{}:1234", ip).parse().unwrap());
+
+        match token {
+            Some(token) => req.header("Authorization", token),
+            None => req,
+        }
+    }
+
+    fn check(limiter: &RateLimiter, method: Method, path: &str, ip: &str, token: Option<&str>) -> RateLimit {
+        limiter.check(&request(method, path, ip, token).to_srv_request()).unwrap()
+    }
+
+    /// Answers a request that had its token checked, as `Claims` does.
+    fn verify(limiter: &RateLimiter, token: &str, checked: &str) {
+        let req = request(Method::GET, "/", "10.0.0.1", Some(token)).to_http_request();
+        verified(&req, checked.to_string());
+        limiter.remember(&req);
+    }
+
+    #[test]
+    fn counts_reads_and_writes_apart() {
+        let limiter = limiter();
+
+        let first = check(&limiter, Method::GET, "/", "10.0.0.1", None);
+        assert_eq!((first.limit, first.remaining, first.exceeded), (2, 1, false));
+        assert!(!check(&limiter, Method::GET, "/", "10.0.0.1", None).exceeded);
+        assert!(check(&limiter, Method::GET, "/", "10.0.0.1", None).exceeded);
+
+        let write = check(&limiter, Method::POST, "/api/v1/pages", "10.0.0.1", None);
+        assert_eq!((write.limit, write.remaining, write.exceeded), (1, 0, false));
+        assert!(check(&limiter, Method::DELETE, "/api/v1/pages/x", "10.0.0.1", None).exceeded);
+    }
+
+    #[test]
+    fn counts_graphql_as_reads() {
+        let limiter = limiter();
+
+        assert_eq!(check(&limiter, Method::POST, "/graphql", "10.0.0.1", None).limit, LIMITS.anonymous_reads);
+    }
+
+    #[test]
+    fn counts_each_ip_on_its_own() {
+        let limiter = limiter();
+
+        check(&limiter, Method::POST, "/", "10.0.0.1", None);
+        assert!(!check(&limiter, Method::POST, "/", "10.0.0.2", None).exceeded);
+    }
+
+    #[test]
+    fn counts_unchecked_credentials_against_the_ip() {
+        let limiter = limiter();
+
+        check(&limiter, Method::POST, "/", "10.0.0.1", Some("made-up"));
+        let other = check(&limiter, Method::POST, "/", "10.0.0.1", Some("made-up-too"));
+        assert_eq!(other.limit, LIMITS.anonymous_writes);
+        assert!(other.exceeded);
+    }
+
+    #[test]
+    fn counts_checked_credentials_on_their_own() {
+        let limiter = limiter();
+        verify(&limiter, "token", "token");
+
+        let keyed = check(&limiter, Method::POST, "/", "10.0.0.1", Some("token"));
+        assert_eq!((keyed.limit, keyed.remaining), (LIMITS.key_writes, 2));
+        // the same key from another IP is the same client.
+        let elsewhere = check(&limiter, Method::POST, "/", "10.0.0.2", Some("token"));
+        assert_eq!(elsewhere.remaining, 1);
+        // and the IP's own count isn't touched.
+        assert_eq!(check(&limiter, Method::POST, "/", "10.0.0.1", None).remaining, 0);
+    }
+
+    #[test]
+    fn only_remembers_the_credential_that_was_checked() {
+        let limiter = limiter();
+        verify(&limiter, "token", "another");
+
+        assert_eq!(check(&limiter, Method::GET, "/", "10.0.0.1", Some("token")).limit, LIMITS.anonymous_reads);
+        assert_eq!(check(&limiter, Method::GET, "/", "10.0.0.1", Some("another")).limit, LIMITS.anonymous_reads);
+    }
+
+    #[test]
+    fn leaves_probes_alone() {
+        let limiter = limiter();
+
+        for probe in PROBES {
+            assert!(limiter.check(&request(Method::GET, probe, "10.0.0.1", None).to_srv_request()).is_none());
+        }
+    }
+
+    #[test]
+    fn limits_each_kind_an_hour() {
+        let cache = MemoryCache::new();
+
+        assert!(check_hourly("comment", "10.0.0.1", 1, &cache).is_ok());
+        assert!(check_hourly("comment", "10.0.0.1", 1, &cache).is_err());
+        assert!(check_hourly("comment", "10.0.0.2", 1, &cache).is_ok());
+        assert!(check_hourly("signup", "10.0.0.1", 1, &cache).is_ok());
+    }
+}
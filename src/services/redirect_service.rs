@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::cache_service::CacheBackend;
+use super::site_service;
+use crate::models::redirect_models::Redirect;
+use crate::models::{DbConnection, Model};
+
+const CACHE_KEY: &str = "redirects";
+/// The same as the routes (see `route_service`), as both are looked at for every path that isn't a page.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Where a redirect sends requests, and how.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Target {
+    pub location: String,
+    pub status_code: i32,
+}
+
+/// `/docs/` is the same page as `/docs`, so it is the same redirect as well.
+pub fn normalize(path: &str) -> String {
+    match path.trim_end_matches('/') {
+        "" => String::from("/"),
+        path => path.to_string(),
+    }
+}
+
+/// Every redirect of a site by its source, so that paths without a page are checked without a query of their own.
+/// Anything that creates, changes, or deletes a redirect must call `invalidate`.
+pub struct RedirectTable {
+    cache: Arc<dyn CacheBackend>,
+}
+
+impl RedirectTable {
+    pub fn new(cache: Arc<dyn CacheBackend>) -> Self {
+        Self { cache }
+    }
+
+    fn load(&self, db: &DbConnection) -> Result<HashMap<String, Target>, diesel::result::Error> {
+        let cached = self.cache.get(&site_service::cache_key(CACHE_KEY)).ok().flatten();
+        if let Some(redirects) = cached.and_then(|r| serde_json::from_str(&r).ok()) {
+            return Ok(redirects);
+        }
+
+        let redirects: HashMap<String, Target> = Redirect::read_all(db)?
+            .into_iter()
+            .map(|r| {
+                let target = Target {
+                    location: r.target,
+                    status_code: r.status_code,
+                };
+                (r.source, target)
+            })
+            .collect();
+
+        if let Ok(serialized) = serde_json::to_string(&redirects) {
+            self.cache.set(&site_service::cache_key(CACHE_KEY), &serialized, Some(CACHE_TTL)).ok();
+        }
+
+        Ok(redirects)
+    }
+
+    /// The redirect for `path`, with the query string carried over unless the target has one, or a fragment, of its own.
+    pub fn find(&self, path: &str, query: &str, db: &DbConnection) -> Result<Option<Target>, diesel::result::Error> {
+        let target = self.load(db)?.remove(&normalize(path));
+
+        Ok(target.map(|mut target| {
+            if !query.is_empty() && !target.location.contains(['?', '#']) {
+                target.location = format!("{}?{}", target.location, query);
+            }
+            target
+        }))
+    }
+
+    /// Drops the redirects of every site.
+    pub fn invalidate(&self) {
+        self.cache.clear(CACHE_KEY).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_trailing_slashes() {
+        assert_eq!(normalize("/docs/"), "/docs");
+        assert_eq!(normalize("/docs//"), "/docs");
+        assert_eq!(normalize("/docs"), "/docs");
+        assert_eq!(normalize("/docs/intro/"), "/docs/intro");
+    }
+
+    #[test]
+    fn keeps_the_root() {
+        assert_eq!(normalize("/"), "/");
+        assert_eq!(normalize("//"), "/");
+        assert_eq!(normalize(""), "/");
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use diesel::OptionalExtension;
+use handlebars::Handlebars;
+use serde::Serialize;
+
+use super::encryption_service::{reveal_fields, reveal_module, reveal_modules};
+use super::errors_service::CustomHttpError;
+use super::menu_service::navigation;
+use super::rollout_service;
+use super::sanitize_service::{on_render, on_render_fields, on_render_module};
+use super::route_service::Route;
+use super::translation_service::alternates;
+use super::visibility_service::{require_published, require_published_module, require_visible, strip_fields, strip_modules, Role};
+use crate::models::comment_models::{Comment, APPROVED};
+use crate::models::module_models::{FieldsDTO, Module};
+use crate::models::page_models::{is_live, Page, PageModuleDisplayDTO, PUBLIC};
+use crate::models::taxonomy_models::{PageCategory, Tag};
+use crate::models::{DbConnection, Model};
+
+/// The pages that visitors are shown instead of an error, when there is a published one at the URL.
+pub const NOT_FOUND_PAGE: &str = "/_404";
+pub const ERROR_PAGE: &str = "/_500";
+/// The templates of a theme that are rendered when there is no such page.
+const NOT_FOUND_TEMPLATE: &str = "404";
+const ERROR_TEMPLATE: &str = "500";
+
+/// The templates fragments are rendered with, unless there is a more specific one like `fragments/module-<title>`.
+const MODULE_FRAGMENT: &str = "fragments/module";
+const REGION_FRAGMENT: &str = "fragments/region";
+
+fn parse_page(page: (Page, FieldsDTO)) -> Result<PageModuleDisplayDTO, CustomHttpError> {
+    let origin_page = page.0;
+    // encrypted modules are never rendered onto public pages.
+    let fields = on_render_fields(reveal_fields(strip_fields(page.1, Role::Public), false)?);
+
+    // cast the origin page that is always standard into a new object that has the modules as a vec of children.
+    let mut res: PageModuleDisplayDTO = origin_page.into();
+
+    match fields.categories {
+        Some(modules) => {
+            for module in modules {
+                res.array_fields.insert(module.title, module.modules);
+            }
+        },
+        None => {}
+    };
+
+    for module in fields.modules {
+        if let Some(items) = fields.galleries.get(&module.uuid) {
+            res.galleries.insert(module.title.clone(), items.clone());
+        }
+        res.fields.insert(module.title.clone(), module);
+    }
+
+    Ok(res)
+}
+
+/// Renders the page at `url` with its template.
+/// Used both for serving pages and for publishing them as static files.
+pub fn render_page(url: String, db: &DbConnection, hb: &Handlebars) -> Result<String, CustomHttpError> {
+    render_page_with(Route::exact(url), None, db, hb)
+}
+
+/// The same as `render_page` for the page a request was routed to, with what its pattern matched as `params`,
+/// and the content of some modules swapped out, like for the canary of a rollout.
+pub fn render_page_with(
+    route: Route,
+    modules: Option<&HashMap<String, String>>,
+    db: &DbConnection,
+    hb: &Handlebars,
+) -> Result<String, CustomHttpError> {
+    let (page, fields) = Page::read_one_join_on_url(route.page_url, db).map_err(|_| CustomHttpError::NotFound)?;
+    let fields = match modules {
+        Some(modules) => rollout_service::apply(fields, modules),
+        None => fields,
+    };
+
+    let translations = alternates(&page, db)?;
+    let mut pagemodule = parse_page((page, fields))?;
+    pagemodule.translations = translations;
+    pagemodule.params = route.params;
+    pagemodule.menus = navigation(&pagemodule.page_url, db)?;
+    pagemodule.tags = Tag::read_for_page(&pagemodule.uuid, db)?;
+    pagemodule.categories = PageCategory::read_for_page(&pagemodule.uuid, db)?;
+    pagemodule.comments = Comment::read_filtered(Some(&pagemodule.uuid), Some(APPROVED), db)?
+        .into_iter()
+        .map(|comment| comment.into())
+        .collect();
+    pagemodule.globals = on_render(reveal_modules(strip_modules(Module::read_global(db)?, Role::Public), false)?)
+        .into_iter()
+        .map(|module| (module.title.clone(), module))
+        .collect();
+
+    // visitors only see a 500, so the reason is left for whoever runs the server.
+    if !hb.has_template(&pagemodule.page_name) {
+        tracing::warn!("No template named {} for {}.", pagemodule.page_name, pagemodule.page_url);
+        return Err(CustomHttpError::Unknown);
+    }
+
+    hb.render(&pagemodule.page_name, &pagemodule).map_err(|e| {
+        tracing::error!("Failed to render {}: {}", pagemodule.page_url, e);
+        CustomHttpError::Unknown
+    })
+}
+
+/// Renders what a visitor is shown for a path without a page, or for one that failed, if there is anything to show.
+/// That is the page at `NOT_FOUND_PAGE` or `ERROR_PAGE` if it is published, and otherwise the theme's `404` or `500` template.
+pub fn render_error_page(not_found: bool, db: &DbConnection, hb: &Handlebars) -> Result<Option<String>, CustomHttpError> {
+    let (url, template) = match not_found {
+        true => (NOT_FOUND_PAGE, NOT_FOUND_TEMPLATE),
+        false => (ERROR_PAGE, ERROR_TEMPLATE),
+    };
+
+    let page = Page::read_one_by_url(url.to_string(), db).optional()?;
+    if page.is_some_and(|page| is_live(&page.status, page.publish_at) && page.visibility == PUBLIC) {
+        return render_page(url.to_string(), db, hb).map(Some);
+    }
+
+    match hb.has_template(template) {
+        true => hb.render(template, &()).map(Some).map_err(|_| CustomHttpError::Unknown),
+        false => Ok(None),
+    }
+}
+
+/// Prefers the template made for this specific module or region.
+fn fragment_template(hb: &Handlebars, default: &str, name: &str) -> String {
+    let specific = format!("{}-{}", default, name);
+
+    match hb.has_template(&specific) {
+        true => specific,
+        false => default.to_string(),
+    }
+}
+
+/// Renders a single module as an HTML fragment.
+pub fn render_module(id: String, db: &DbConnection, hb: &Handlebars) -> Result<String, CustomHttpError> {
+    let module = reveal_module(require_visible(Module::read_one(id, db)?, Role::Public)?, false)?;
+    require_published_module(&module, db)?;
+    let module = on_render_module(module);
+
+    hb.render(&fragment_template(hb, MODULE_FRAGMENT, &module.title), &module)
+        .map_err(|_| CustomHttpError::Unknown)
+}
+
+#[derive(Serialize)]
+struct Region<'a> {
+    page_uuid: &'a str,
+    title: &'a str,
+    modules: &'a [Module],
+}
+
+/// Renders one of a page's categories, which is how templates lay out repeated content, as an HTML fragment.
+pub fn render_region(
+    page_id: String,
+    name: String,
+    db: &DbConnection,
+    hb: &Handlebars,
+) -> Result<String, CustomHttpError> {
+    require_published(&page_id, db)?;
+    let page = Page::read_one_join_on(page_id, db)?;
+    let fields = on_render_fields(reveal_fields(strip_fields(page.fields, Role::Public), false)?);
+
+    let category = fields
+        .categories
+        .iter()
+        .flatten()
+        .find(|c| c.title == name)
+        .ok_or(CustomHttpError::NotFound)?;
+
+    let region = Region {
+        page_uuid: &page.uuid,
+        title: &category.title,
+        modules: &category.modules,
+    };
+
+    hb.render(&fragment_template(hb, REGION_FRAGMENT, &name), &region)
+        .map_err(|_| CustomHttpError::Unknown)
+}
@@ -0,0 +1,159 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::dev::{Body, ResponseBody, ServiceRequest};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{HttpMessage, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use super::api_version_service::api_path;
+use super::cache_service::CacheBackend;
+use super::delivery_service::hash_token;
+use super::etag_service;
+use super::metrics_service;
+use super::session_service::SESSION_COOKIE;
+use crate::models::config_models::LocalConfig;
+
+const KEY_PREFIX: &str = "responses:";
+/// Larger responses, like big listings, are sent without being kept.
+const MAX_BODY: usize = 1024 * 1024;
+/// Health checks have to reach the server, the system endpoints report on it as it is, and streams never end.
+const UNCACHED: &[&str] = &["/healthz", "/readyz", "/metrics", "/ws", "/events"];
+
+/// Sent on responses that were answered from the cache.
+const HIT_HEADER: &str = "x-cache";
+
+/// A response as it was sent, to be sent again as it is.
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// Base64, as the cache only holds text.
+    body: String,
+}
+
+/// Whole responses by request, for rendered pages and the API alike, so that public reads are answered without the
+/// database (see `ResponseCaching`). It is kept in the cache backend, so instances sharing a Redis share it too, and is
+/// turned off unless `APP_RESPONSE_CACHE_TTL` is set. It is emptied by every write the server makes, so the TTL only bounds
+/// how stale a response can get through changes made outside of the server, like by the CLI.
+pub struct ResponseCache {
+    cache: Arc<dyn CacheBackend>,
+    ttl: Option<Duration>,
+}
+
+impl ResponseCache {
+    pub fn new(cache: Arc<dyn CacheBackend>, conf: &LocalConfig) -> Self {
+        Self {
+            cache,
+            ttl: conf.response_cache_ttl.map(Duration::from_secs),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.ttl.is_some()
+    }
+
+    /// What the response to `req` is cached under, or `None` if it can't be.
+    /// Requests with a session aren't cached, while tokens are part of the key so each one only gets its own responses.
+    pub fn key(&self, req: &ServiceRequest) -> Option<String> {
+        let system = api_path(req.path()).is_some_and(|p| p.starts_with("/admin"));
+        if !self.is_enabled() || req.method() != Method::GET || system || UNCACHED.iter().any(|p| req.path().starts_with(p)) {
+            return None;
+        }
+        if req.cookie(SESSION_COOKIE).is_some() {
+            return None;
+        }
+
+        let header = |name| req.headers().get(name).and_then(|h| h.to_str().ok()).unwrap_or_default();
+        let credential = match header(header::AUTHORIZATION) {
+            "" => String::new(),
+            token => hash_token(token),
+        };
+
+        let request = format!(
+            "{} {}?{} accept={} lang={} auth={}",
+            req.connection_info().host().to_ascii_lowercase(),
+            req.path(),
+            req.query_string(),
+            header(header::ACCEPT),
+            header(header::ACCEPT_LANGUAGE),
+            credential,
+        );
+
+        // hashed, so that long URLs make keys no longer than short ones.
+        Some(format!("{}{}", KEY_PREFIX, hash_token(&request)))
+    }
+
+    /// The response kept for `req`, or a 304 when the client already has it (see `etag_service`).
+    /// Anything that can't be read back, like when the backend can't be reached, is a miss.
+    pub fn get(&self, key: &str, req: &ServiceRequest) -> Option<HttpResponse> {
+        let res = self.lookup(key, req);
+        metrics_service::RESPONSE_CACHE.record(res.is_some());
+
+        res
+    }
+
+    fn lookup(&self, key: &str, req: &ServiceRequest) -> Option<HttpResponse> {
+        let cached = self.cache.get(key).ok().flatten()?;
+        let cached: CachedResponse = serde_json::from_str(&cached).ok()?;
+        let body = base64::decode(&cached.body).ok()?;
+
+        let mut res = HttpResponse::build(StatusCode::from_u16(cached.status).ok()?);
+        for (name, value) in &cached.headers {
+            res.header(name.as_str(), value.as_str());
+        }
+        res.header(HIT_HEADER, "HIT");
+
+        let etag = cached.headers.iter().find(|(name, _)| name == header::ETAG.as_str()).map(|(_, etag)| etag.as_str());
+        if etag.is_some_and(|etag| etag_service::is_fresh(req.headers(), etag)) {
+            return Some(res.status(StatusCode::NOT_MODIFIED).finish());
+        }
+
+        Some(res.body(body))
+    }
+
+    /// Keeps the response if it is the same for everyone who sends the same request: a success with a plain body,
+    /// without cookies, and that shared caches may keep too.
+    pub fn set(&self, key: &str, res: &HttpResponse) {
+        let ttl = match self.ttl {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        if !res.status().is_success() || res.headers().contains_key(header::SET_COOKIE) {
+            return;
+        }
+        let private = res
+            .headers()
+            .get(header::CACHE_CONTROL)
+            .and_then(|h| h.to_str().ok())
+            .is_some_and(|h| h.contains("private") || h.contains("no-store"));
+        let body = match res.body() {
+            ResponseBody::Body(Body::Bytes(body)) if !private && body.len() <= MAX_BODY => base64::encode(body),
+            _ => return,
+        };
+        // headers that aren't text can't be kept, so neither can their response.
+        let headers: Option<Vec<(String, String)>> = res
+            .headers()
+            .iter()
+            .map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let cached = match headers {
+            Some(headers) => CachedResponse {
+                status: res.status().as_u16(),
+                headers,
+                body,
+            },
+            None => return,
+        };
+
+        if let Ok(serialized) = serde_json::to_string(&cached) {
+            self.cache.set(key, &serialized, Some(ttl)).ok();
+        }
+    }
+
+    pub fn clear(&self) {
+        if self.is_enabled() {
+            self.cache.clear(KEY_PREFIX).ok();
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+
+use super::site_service;
+use crate::models::config_models::LocalConfig;
+use crate::models::page_revision_models::PageRevision;
+use crate::models::setting_models::SettingChange;
+use crate::models::{DbConnection, DbPool};
+
+/// How many revisions of any one item are kept around.
+/// A revision is kept if it is one of the newest `keep_last`, or if it is younger than `keep_days`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    pub keep_last: Option<i64>,
+    pub keep_days: Option<i64>,
+    /// Page revisions taken while the page was published are kept regardless, so that what was live can always be restored.
+    pub keep_published: bool,
+}
+
+impl From<&LocalConfig> for RetentionPolicy {
+    fn from(conf: &LocalConfig) -> Self {
+        Self {
+            keep_last: conf.revision_keep_last,
+            keep_days: conf.revision_keep_days,
+            keep_published: conf.revision_keep_published,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    fn is_unbounded(&self) -> bool {
+        self.keep_last.is_none() && self.keep_days.is_none()
+    }
+
+    /// Takes the `(id, time_created)` of every revision of a single item, newest first,
+    /// and returns the ids of the ones that fall outside of the policy.
+    pub fn prunable(&self, revisions: Vec<(String, NaiveDateTime)>, now: NaiveDateTime) -> Vec<String> {
+        if self.is_unbounded() {
+            return Vec::new();
+        }
+
+        revisions
+            .into_iter()
+            .enumerate()
+            .filter(|(index, (_, time_created))| {
+                let within_count = matches!(self.keep_last, Some(n) if (*index as i64) < n);
+                let within_age =
+                    matches!(self.keep_days, Some(days) if now - *time_created < chrono::Duration::days(days));
+
+                !within_count && !within_age
+            })
+            .map(|(_, (id, _))| id)
+            .collect()
+    }
+}
+
+fn prune_setting_changes(
+    policy: &RetentionPolicy,
+    now: NaiveDateTime,
+    db: &DbConnection,
+) -> Result<usize, diesel::result::Error> {
+    let mut pruned = 0;
+
+    for (site, key) in SettingChange::read_keys(db)? {
+        let revisions = site_service::scoped(Some(site), || SettingChange::read_for_key(key, db))?
+            .into_iter()
+            .map(|c| (c.uuid, c.time_created))
+            .collect();
+
+        let prunable = policy.prunable(revisions, now);
+        if !prunable.is_empty() {
+            pruned += SettingChange::delete_many(prunable, db)?;
+        }
+    }
+
+    Ok(pruned)
+}
+
+fn prune_page_revisions(
+    policy: &RetentionPolicy,
+    now: NaiveDateTime,
+    db: &DbConnection,
+) -> Result<usize, diesel::result::Error> {
+    let mut pruned = 0;
+
+    for page_id in PageRevision::read_page_ids(db)? {
+        let revisions = PageRevision::read_for_page(page_id, db)?;
+        let exempt: Vec<String> = match policy.keep_published {
+            true => revisions
+                .iter()
+                .filter(|r| r.snapshot().is_ok_and(|s| s.published))
+                .map(|r| r.uuid.clone())
+                .collect(),
+            false => Vec::new(),
+        };
+
+        let prunable: Vec<String> = policy
+            .prunable(revisions.into_iter().map(|r| (r.uuid, r.time_created)).collect(), now)
+            .into_iter()
+            .filter(|id| !exempt.contains(id))
+            .collect();
+        if !prunable.is_empty() {
+            pruned += PageRevision::delete_many(prunable, db)?;
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// Deletes every revision that falls outside of the policy, returning how many were deleted.
+pub fn prune(policy: &RetentionPolicy, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+    if policy.is_unbounded() {
+        return Ok(0);
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+
+    Ok(prune_setting_changes(policy, now, db)? + prune_page_revisions(policy, now, db)?)
+}
+
+/// The scheduled pruning job. Runs forever, so it should be given its own thread.
+pub fn run(pool: DbPool, conf: LocalConfig) {
+    let policy = RetentionPolicy::from(&conf);
+
+    if policy.is_unbounded() {
+        return;
+    }
+
+    loop {
+        match pool.get() {
+            Ok(conn) => match prune(&policy, &conn) {
+                Ok(0) => {}
+                Ok(pruned) => tracing::info!("Pruned {} revisions.", pruned),
+                Err(e) => tracing::error!("pruning error: {:?}", e),
+            },
+            Err(e) => tracing::error!("pruning error: {:?}", e),
+        }
+
+        std::thread::sleep(Duration::from_secs(conf.revision_prune_interval));
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use similar::TextDiff;
+use utoipa::ToSchema;
+
+use super::errors_service::CustomHttpError;
+use super::tree_service::{self, Moved};
+use crate::models::module_models::{Module, ModuleCategory, MutModule};
+use crate::models::page_models::{MutPage, Page, PageDTO};
+use crate::models::page_revision_models::{PageRevision, PageSnapshot};
+use crate::models::{DbConnection, Model};
+
+/// The page's URL before and after a restore, which are different when the revision moved it back.
+pub struct Restored {
+    pub old_url: String,
+    pub page_url: String,
+    /// The pages under it, which move along with it.
+    pub moved: Vec<Moved>,
+}
+
+/// Stands in for a revision in a diff to compare with the page as it is now.
+pub const CURRENT: &str = "current";
+/// Lines of content kept around each change in the text diff of a module.
+const CONTEXT_LINES: usize = 3;
+
+/// One field that is different between two revisions.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub from: Value,
+    pub to: Value,
+}
+
+/// A module that was added, removed, or changed between two revisions.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct ModuleChange {
+    pub uuid: String,
+    pub title: String,
+    /// `added`, `removed`, or `changed`.
+    pub change: &'static str,
+    /// What is different apart from the content.
+    pub fields: Vec<FieldChange>,
+    /// A unified diff of the content, by line, if it is different.
+    pub content: Option<String>,
+}
+
+/// What changed from one revision of a page to another.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct RevisionDiff {
+    pub from: String,
+    pub to: String,
+    pub fields: Vec<FieldChange>,
+    pub modules: Vec<ModuleChange>,
+}
+
+fn changes(pairs: Vec<(&'static str, Value, Value)>) -> Vec<FieldChange> {
+    pairs
+        .into_iter()
+        .filter(|(_, from, to)| from != to)
+        .map(|(field, from, to)| FieldChange { field, from, to })
+        .collect()
+}
+
+fn text_diff(from: &str, to: &str) -> Option<String> {
+    match from == to {
+        true => None,
+        false => Some(TextDiff::from_lines(from, to).unified_diff().context_radius(CONTEXT_LINES).to_string()),
+    }
+}
+
+fn module_changes(from: Option<&Module>, to: Option<&Module>) -> Option<ModuleChange> {
+    let field = |name, value: fn(&Module) -> Value| (name, from.map_or(Value::Null, value), to.map_or(Value::Null, value));
+    let fields = changes(vec![
+        field("title", |m| json!(m.title)),
+        field("module_type", |m| json!(m.module_type)),
+        field("category_uuid", |m| json!(m.category_uuid)),
+        field("order_index", |m| json!(m.order_index)),
+        field("encrypted", |m| json!(m.encrypted)),
+        field("visible_to", |m| json!(m.visible_to)),
+    ]);
+    let content = text_diff(from.map_or("", |m| m.content.as_str()), to.map_or("", |m| m.content.as_str()));
+
+    let module = to.or(from)?;
+    let change = match (from, to) {
+        (None, _) => "added",
+        (_, None) => "removed",
+        _ if fields.is_empty() && content.is_none() => return None,
+        _ => "changed",
+    };
+
+    Some(ModuleChange {
+        uuid: module.uuid.clone(),
+        title: module.title.clone(),
+        change,
+        fields,
+        content,
+    })
+}
+
+/// The diff from the snapshot `from` to `to`, of the page's fields and of each of its modules, which are told apart by uuid.
+/// Modules are in the order they have in `to`, followed by the ones that were removed.
+pub fn diff(from: (String, &PageSnapshot), to: (String, &PageSnapshot)) -> RevisionDiff {
+    let ((from_id, a), (to_id, b)) = (from, to);
+    let fields = changes(vec![
+        ("page_name", json!(a.page_name), json!(b.page_name)),
+        ("page_url", json!(a.page_url), json!(b.page_url)),
+        ("page_title", json!(a.page_title), json!(b.page_title)),
+        ("meta_description", json!(a.meta_description), json!(b.meta_description)),
+        ("meta_keywords", json!(a.meta_keywords), json!(b.meta_keywords)),
+        ("og_image", json!(a.og_image), json!(b.og_image)),
+        ("noindex", json!(a.noindex), json!(b.noindex)),
+    ]);
+
+    fn find<'a>(modules: &'a [Module], uuid: &str) -> Option<&'a Module> {
+        modules.iter().find(|m| m.uuid == uuid)
+    }
+    let mut modules: Vec<ModuleChange> =
+        b.modules.iter().filter_map(|m| module_changes(find(&a.modules, &m.uuid), Some(m))).collect();
+    modules.extend(
+        a.modules
+            .iter()
+            .filter(|m| find(&b.modules, &m.uuid).is_none())
+            .filter_map(|m| module_changes(Some(m), None)),
+    );
+
+    RevisionDiff {
+        from: from_id,
+        to: to_id,
+        fields,
+        modules,
+    }
+}
+
+/// The snapshot of one side of a diff, a revision of the page or `CURRENT`.
+pub fn snapshot(page_id: &str, revision_id: String, db: &DbConnection) -> Result<PageSnapshot, CustomHttpError> {
+    if revision_id == CURRENT {
+        return PageSnapshot::read(page_id, db)?.ok_or(CustomHttpError::NotFound);
+    }
+
+    let revision = PageRevision::read_one(revision_id, db)?;
+    if revision.page_uuid != page_id {
+        return Err(CustomHttpError::NotFound);
+    }
+
+    Ok(revision.snapshot()?)
+}
+
+fn to_mut(module: Module, category_uuid: Option<String>) -> MutModule {
+    MutModule {
+        uuid: Some(module.uuid),
+        title: module.title,
+        page_uuid: module.page_uuid,
+        category_uuid,
+        content: module.content,
+        encrypted: module.encrypted,
+        compressed: false,
+        visible_to: module.visible_to,
+        order_index: Some(module.order_index),
+        module_type: Some(module.module_type),
+    }
+}
+
+/// Puts a page and its modules back the way they were in a revision. Modules added since are removed and
+/// ones removed since are added back, outside of their category if it is gone too. Gallery items aren't part of a revision.
+/// The page as it was before the restore is recorded as a new revision, so a restore can be undone as well.
+pub fn restore(page_id: String, revision_id: String, db: &DbConnection) -> Result<Restored, CustomHttpError> {
+    let revision = PageRevision::read_one(revision_id, db)?;
+    if revision.page_uuid != page_id {
+        return Err(CustomHttpError::NotFound);
+    }
+    let snapshot = revision.snapshot()?;
+
+    let res = db.transaction(|| {
+        let old_page: PageDTO = Page::read_one(page_id.clone(), db)?;
+        PageRevision::record(&page_id, db)?;
+
+        Page::overwrite(
+            page_id.clone(),
+            &MutPage {
+                uuid: Some(page_id.clone()),
+                page_name: snapshot.page_name.clone(),
+                page_url: snapshot.page_url.clone(),
+                page_title: snapshot.page_title.clone(),
+                status: None,
+                parent_page: None,
+                meta_description: Some(snapshot.meta_description.clone().unwrap_or_default()),
+                meta_keywords: Some(snapshot.meta_keywords.clone().unwrap_or_default()),
+                og_image: Some(snapshot.og_image.clone().unwrap_or_default()),
+                noindex: Some(snapshot.noindex),
+                // which translation a page is isn't part of its revisions.
+                locale: None,
+                translation_group: None,
+                visibility: None,
+            },
+            db,
+        )?;
+
+        let kept: HashSet<&String> = snapshot.modules.iter().map(|m| &m.uuid).collect();
+        // Modules in the trash since are taken back out, rather than added again under the uuid they still have.
+        Module::restore(kept.iter().map(|id| id.to_string()).collect(), db)?;
+        let current = Module::read_for_pages(vec![page_id.clone()], db)?;
+        let existing: HashSet<String> = current.iter().map(|m| m.uuid.clone()).collect();
+
+        for module in current.into_iter().filter(|m| !kept.contains(&m.uuid)) {
+            Module::delete(module.uuid, db)?;
+        }
+
+        for module in snapshot.modules.clone() {
+            if existing.contains(&module.uuid) {
+                let category_uuid = module.category_uuid.clone();
+                Module::overwrite(module.uuid.clone(), &to_mut(module, category_uuid), db)?;
+                continue;
+            }
+
+            let category_uuid = match module.category_uuid.clone() {
+                Some(id) => ModuleCategory::read_one(id.clone(), db).optional()?.map(|_| id),
+                None => None,
+            };
+            Module::create(&to_mut(module, category_uuid), db)?;
+        }
+
+        let moved = tree_service::move_descendants(page_id.clone(), &old_page.page_url, &snapshot.page_url, db)?;
+
+        Ok::<_, diesel::result::Error>(Restored {
+            old_url: old_page.page_url,
+            page_url: snapshot.page_url.clone(),
+            moved,
+        })
+    })?;
+
+    Ok(res)
+}
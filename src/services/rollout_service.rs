@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use actix_web::{HttpMessage, HttpRequest};
+use diesel::prelude::*;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::content_type_service;
+use super::errors_service::CustomHttpError;
+use crate::models::module_models::{FieldsDTO, Module, MutModule};
+use crate::models::page_revision_models::PageRevision;
+use crate::models::page_rollout_models::PageRollout;
+use crate::models::{DbConnection, Model};
+
+/// Set to `canary` or `control` to see that version no matter the percentage, like for a preview cohort.
+pub const COHORT_HEADER: &str = "X-Radical-Cohort";
+pub const COHORT_COOKIE: &str = "radical_cohort";
+/// Keeps a visitor on the same version between requests.
+pub const BUCKET_COOKIE: &str = "radical_bucket";
+/// Tells the client which version it was sent.
+pub const VARIANT_HEADER: &str = "X-Radical-Variant";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    Control,
+    Canary,
+}
+
+impl Variant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Control => "control",
+            Self::Canary => "canary",
+        }
+    }
+
+    fn parse(cohort: &str) -> Option<Self> {
+        match cohort.trim().to_lowercase().as_str() {
+            "control" => Some(Self::Control),
+            "canary" => Some(Self::Canary),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct RolloutRequest {
+    /// How much of the traffic sees the canary, from 0 to 100.
+    pub percentage: i32,
+    /// Module uuids to the content they have in the canary.
+    pub modules: HashMap<String, String>,
+}
+
+/// Picks the version a visitor sees. A cohort header or cookie decides it outright,
+/// otherwise the visitor's bucket from 0 to 99 does, and a new bucket is returned when the visitor doesn't have one yet.
+pub fn choose(req: &HttpRequest, percentage: i32) -> (Variant, Option<u32>) {
+    let cohort = req
+        .headers()
+        .get(COHORT_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(String::from)
+        .or_else(|| req.cookie(COHORT_COOKIE).map(|c| c.value().to_string()))
+        .and_then(|c| Variant::parse(&c));
+
+    if let Some(variant) = cohort {
+        return (variant, None);
+    }
+
+    let existing = req
+        .cookie(BUCKET_COOKIE)
+        .and_then(|c| c.value().parse::<u32>().ok())
+        .filter(|bucket| *bucket < 100);
+    let bucket = existing.unwrap_or_else(|| OsRng.next_u32() % 100);
+
+    let variant = match (bucket as i32) < percentage {
+        true => Variant::Canary,
+        false => Variant::Control,
+    };
+
+    (variant, if existing.is_none() { Some(bucket) } else { None })
+}
+
+/// Swaps in the canary content of every module that has one.
+pub fn apply(mut fields: FieldsDTO, modules: &HashMap<String, String>) -> FieldsDTO {
+    let swap = |module: &mut Module| {
+        if let Some(content) = modules.get(&module.uuid) {
+            module.content = content.clone();
+        }
+    };
+
+    fields.modules.iter_mut().for_each(swap);
+    if let Some(categories) = fields.categories.as_mut() {
+        categories.iter_mut().flat_map(|c| c.modules.iter_mut()).for_each(swap);
+    }
+
+    fields
+}
+
+/// Checks that the percentage is in range and that every module is a plain module of the page, with content that suits its type.
+/// Encrypted modules are never shown on public pages, so there is nothing to roll out for them.
+pub fn validate(rollout: &RolloutRequest, page_uuid: &str, db: &DbConnection) -> Result<(), CustomHttpError> {
+    if !(0..=100).contains(&rollout.percentage) || rollout.modules.is_empty() {
+        return Err(CustomHttpError::BadRequest);
+    }
+
+    let modules: HashMap<String, Module> = Module::read_for_pages(vec![page_uuid.to_string()], db)?
+        .into_iter()
+        .map(|m| (m.uuid.clone(), m))
+        .collect();
+
+    for (id, content) in &rollout.modules {
+        match modules.get(id) {
+            Some(module) if !module.encrypted => content_type_service::check_content(&module.module_type, content, db)?,
+            _ => return Err(CustomHttpError::BadRequest),
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes the canary content the content of its modules for everyone, and ends the rollout.
+/// The page is recorded as one revision beforehand, rather than one for every module.
+pub fn promote(rollout: &PageRollout, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+    db.transaction(|| {
+        let mut updated = 0;
+        PageRevision::record(&rollout.page_uuid, db)?;
+
+        for (id, content) in rollout.modules()? {
+            // modules deleted since the rollout started have nothing to promote to.
+            let module = match Module::read_one(id.clone(), db).optional()? {
+                Some(module) => module,
+                None => continue,
+            };
+
+            updated += Module::overwrite(
+                id,
+                &MutModule {
+                    uuid: Some(module.uuid),
+                    title: module.title,
+                    page_uuid: module.page_uuid,
+                    category_uuid: module.category_uuid,
+                    content,
+                    encrypted: false,
+                    compressed: false,
+                    visible_to: module.visible_to,
+                    order_index: None,
+                    module_type: None,
+                },
+                db,
+            )?;
+        }
+
+        PageRollout::delete(rollout.uuid.clone(), db)?;
+
+        Ok(updated)
+    })
+}
@@ -0,0 +1,189 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::cache_service::CacheBackend;
+use super::site_service;
+use crate::models::page_models::Page;
+use crate::models::DbConnection;
+
+const CACHE_KEY: &str = "routes";
+/// Pages can also be created by other processes, like the import subcommands, which can't invalidate a memory cache.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// What a wildcard segment that isn't named, like the one of `/docs/*`, is passed to templates as.
+const WILDCARD: &str = "path";
+
+/// The page a request is for, and what its URL's pattern matched, like `slug` for `/blog/:slug`.
+#[derive(Debug, Clone, Default)]
+pub struct Route {
+    pub page_url: String,
+    pub params: HashMap<String, String>,
+}
+
+impl Route {
+    /// The page at exactly `page_url`, with nothing matched.
+    pub fn exact(page_url: String) -> Self {
+        Self {
+            page_url,
+            params: HashMap::new(),
+        }
+    }
+}
+
+/// Whether a `page_url` is a pattern, with `:name` segments or a `*` at the end, rather than one URL.
+pub fn is_pattern(url: &str) -> bool {
+    url.split('/').any(|s| s.starts_with(':') || s.starts_with('*'))
+}
+
+/// The parameters `path` matches `pattern` with, if it does. `:name` matches one segment, and `*` or `*name`, which can only
+/// be last, matches one or more.
+fn matches(pattern: &str, path: &str) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+    let mut parts = path.split('/').filter(|p| !p.is_empty());
+
+    for segment in pattern.split('/').filter(|s| !s.is_empty()) {
+        if let Some(name) = segment.strip_prefix('*') {
+            let rest: Vec<&str> = parts.collect();
+            if rest.is_empty() {
+                return None;
+            }
+            params.insert(if name.is_empty() { WILDCARD } else { name }.to_string(), rest.join("/"));
+            return Some(params);
+        }
+
+        let part = parts.next()?;
+        match segment.strip_prefix(':') {
+            Some(name) => {
+                params.insert(name.to_string(), part.to_string());
+            }
+            None if segment != part => return None,
+            None => {}
+        }
+    }
+
+    match parts.next() {
+        Some(_) => None,
+        None => Some(params),
+    }
+}
+
+/// Orders patterns from the most specific, comparing segment by segment with fixed ones first, then `:name`, then `*`.
+fn specificity(pattern: &str) -> Vec<u8> {
+    pattern
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.chars().next() {
+            Some(':') => 1,
+            Some('*') => 2,
+            _ => 0,
+        })
+        .collect()
+}
+
+/// Every page URL of each site, so that requests for URLs that aren't pages, like the ones crawlers guess at,
+/// are answered without loading a page and its modules.
+/// Anything that creates a page, deletes one, or changes its URL must call `invalidate`.
+pub struct RouteTable {
+    cache: Arc<dyn CacheBackend>,
+}
+
+impl RouteTable {
+    pub fn new(cache: Arc<dyn CacheBackend>) -> Self {
+        Self { cache }
+    }
+
+    fn load(&self, db: &DbConnection) -> Result<HashSet<String>, diesel::result::Error> {
+        let cached = self.cache.get(&site_service::cache_key(CACHE_KEY)).ok().flatten();
+        if let Some(routes) = cached.and_then(|r| serde_json::from_str(&r).ok()) {
+            return Ok(routes);
+        }
+
+        let routes: HashSet<String> = Page::read_urls(db)?.into_iter().collect();
+
+        if let Ok(serialized) = serde_json::to_string(&routes) {
+            self.cache.set(&site_service::cache_key(CACHE_KEY), &serialized, Some(CACHE_TTL)).ok();
+        }
+
+        Ok(routes)
+    }
+
+    /// The page a request for `path` is served by. A page at exactly the path comes first, then the most specific pattern
+    /// that matches it, so `/blog/archive` is served by its own page rather than by `/blog/:slug`.
+    pub fn resolve(&self, path: &str, db: &DbConnection) -> Result<Option<Route>, diesel::result::Error> {
+        let routes = self.load(db)?;
+        if routes.contains(path) {
+            return Ok(Some(Route::exact(path.to_string())));
+        }
+
+        let mut patterns: Vec<&String> = routes.iter().filter(|url| is_pattern(url)).collect();
+        patterns.sort_by(|a, b| specificity(a).cmp(&specificity(b)).then_with(|| a.cmp(b)));
+
+        Ok(patterns.into_iter().find_map(|pattern| {
+            matches(pattern, path).map(|params| Route {
+                page_url: pattern.clone(),
+                params,
+            })
+        }))
+    }
+
+    pub fn contains(&self, url: &str, db: &DbConnection) -> Result<bool, diesel::result::Error> {
+        Ok(self.resolve(url, db)?.is_some())
+    }
+
+    /// How many routes there are, loading them into the cache if they aren't already.
+    pub fn count(&self, db: &DbConnection) -> Result<usize, diesel::result::Error> {
+        Ok(self.load(db)?.len())
+    }
+
+    pub fn invalidate(&self) {
+        self.cache.clear(CACHE_KEY).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn matches_fixed_segments_only_exactly() {
+        assert_eq!(matches("/blog/latest", "/blog/latest"), Some(params(&[])));
+        assert_eq!(matches("/blog/latest/", "blog/latest"), Some(params(&[])));
+        assert_eq!(matches("/blog/latest", "/blog/oldest"), None);
+        assert_eq!(matches("/blog", "/blog/latest"), None);
+        assert_eq!(matches("/blog/latest", "/blog"), None);
+    }
+
+    #[test]
+    fn matches_one_segment_by_name() {
+        assert_eq!(matches("/blog/:slug", "/blog/hello"), Some(params(&[("slug", "hello")])));
+        assert_eq!(
+            matches("/:year/:slug", "/2024/hello"),
+            Some(params(&[("year", "2024"), ("slug", "hello")]))
+        );
+        assert_eq!(matches("/blog/:slug", "/blog"), None);
+        assert_eq!(matches("/blog/:slug", "/blog/hello/comments"), None);
+    }
+
+    #[test]
+    fn matches_the_rest_with_a_wildcard() {
+        assert_eq!(matches("/docs/*", "/docs/a/b"), Some(params(&[(WILDCARD, "a/b")])));
+        assert_eq!(matches("/docs/*rest", "/docs/a"), Some(params(&[("rest", "a")])));
+        assert_eq!(matches("/docs/*", "/docs"), None);
+        assert_eq!(matches("/docs/*", "/docs/"), None);
+    }
+
+    #[test]
+    fn orders_fixed_segments_before_names_before_wildcards() {
+        let mut patterns = vec!["/blog/*", "/:section/latest", "/blog/:slug", "/blog/latest"];
+        patterns.sort_by(|a, b| specificity(a).cmp(&specificity(b)).then_with(|| a.cmp(b)));
+
+        assert_eq!(patterns, ["/blog/latest", "/blog/:slug", "/blog/*", "/:section/latest"]);
+        assert_eq!(specificity("/a/:b/*c"), [0, 1, 2]);
+        assert!(specificity("/").is_empty());
+    }
+}
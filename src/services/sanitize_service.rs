@@ -0,0 +1,322 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use ammonia::Builder;
+use serde_json::Value;
+
+use super::auth_service::Claims;
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::{FieldsDTO, Module, ModuleType};
+
+/// The sanitizer of the config that was loaded last. Modules are saved and rendered in places the config isn't passed to,
+/// like the models, so it is kept here rather than handed down.
+static SANITIZER: RwLock<Option<Arc<Sanitizer>>> = RwLock::new(None);
+
+/// When module content is sanitized, from `APP_SANITIZE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum When {
+    Save,
+    Render,
+    Both,
+    Off,
+}
+
+impl When {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "save" => Some(Self::Save),
+            "render" => Some(Self::Render),
+            "both" => Some(Self::Both),
+            "off" => Some(Self::Off),
+            _ => None,
+        }
+    }
+
+    fn on_save(self) -> bool {
+        matches!(self, Self::Save | Self::Both)
+    }
+
+    fn on_render(self) -> bool {
+        matches!(self, Self::Render | Self::Both)
+    }
+}
+
+/// The tags one module type's content can keep, and the attributes each of them can keep.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Allowlist {
+    tags: HashSet<String>,
+    /// The attributes every tag can keep, written as `*[class]`.
+    generic: HashSet<String>,
+    attributes: HashMap<String, HashSet<String>>,
+}
+
+impl Allowlist {
+    fn builder(&self) -> Builder<'_> {
+        let mut builder = Builder::default();
+        builder
+            .tags(self.tags.iter().map(String::as_str).collect())
+            // ammonia refuses to clean with a tag that is both kept and dropped along with its content, like `script`.
+            .rm_clean_content_tags(&self.tags)
+            .generic_attributes(self.generic.iter().map(String::as_str).collect())
+            .tag_attributes(
+                self.attributes
+                    .iter()
+                    .map(|(tag, attributes)| (tag.as_str(), attributes.iter().map(String::as_str).collect()))
+                    .collect(),
+            );
+
+        // links are given a `rel` of their own, unless the allowlist keeps the one they have.
+        let keeps_rel = |attributes: Option<&HashSet<String>>| attributes.is_some_and(|a| a.contains("rel"));
+        if keeps_rel(Some(&self.generic)) || keeps_rel(self.attributes.get("a")) {
+            builder.link_rel(None);
+        }
+
+        builder
+    }
+}
+
+/// The allowlists of `APP_SANITIZE_TYPES`, like `rich_text=p br strong em a[href|title],product=b i`, by module type. Every
+/// entry lists the tags its type keeps, separated by spaces, with the attributes a tag keeps in brackets after it, and
+/// attributes every tag keeps as `*[class]`. `text=` keeps no tags at all.
+pub fn allowlists(value: Option<&str>) -> Result<HashMap<String, Allowlist>, String> {
+    value.into_iter().flat_map(|v| v.split(',')).map(str::trim).filter(|e| !e.is_empty()).map(allowlist).collect()
+}
+
+fn allowlist(entry: &str) -> Result<(String, Allowlist), String> {
+    let invalid = || format!("should be comma separated allowlists like `rich_text=p strong a[href|title]`, not `{}`", entry);
+    let is_name = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'));
+
+    let (module_type, tags) = entry.split_once('=').ok_or_else(invalid)?;
+    let module_type = module_type.trim();
+    if !is_name(module_type) {
+        return Err(invalid());
+    }
+
+    let mut allowlist = Allowlist::default();
+    for tag in tags.split_whitespace() {
+        let (tag, attributes) = match tag.split_once('[') {
+            Some((tag, attributes)) => (tag, attributes.strip_suffix(']').ok_or_else(invalid)?.split('|').collect()),
+            None => (tag, Vec::new()),
+        };
+        if !(is_name(tag) || tag == "*" && !attributes.is_empty()) || !attributes.iter().all(|a| is_name(a)) {
+            return Err(invalid());
+        }
+
+        let attributes = attributes.into_iter().map(str::to_ascii_lowercase);
+        match tag {
+            "*" => allowlist.generic.extend(attributes),
+            tag => {
+                let tag = tag.to_ascii_lowercase();
+                allowlist.tags.insert(tag.clone());
+                allowlist.attributes.entry(tag).or_default().extend(attributes);
+            }
+        }
+    }
+
+    Ok((String::from(module_type), allowlist))
+}
+
+/// What sanitizes module content, by module type.
+#[derive(Debug, Clone)]
+pub struct Sanitizer {
+    when: When,
+    /// `None` for the types that keep what ammonia keeps by default, which rich text does unless it has an allowlist.
+    types: HashMap<String, Option<Allowlist>>,
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Self {
+            when: When::Both,
+            types: std::iter::once((String::from(ModuleType::RichText.as_str()), None)).collect(),
+        }
+    }
+}
+
+impl Sanitizer {
+    /// Invalid values are refused when the config is loaded, so they are only left out here.
+    pub fn new(conf: &LocalConfig) -> Self {
+        let mut sanitizer = Self {
+            when: When::parse(&conf.sanitize).unwrap_or(When::Both),
+            ..Self::default()
+        };
+        for (module_type, allowlist) in allowlists(conf.sanitize_types.as_deref()).unwrap_or_default() {
+            sanitizer.types.insert(module_type, Some(allowlist));
+        }
+
+        sanitizer
+    }
+
+    /// The content sanitized for its type. Text and rich text are sanitized as HTML, and the content of every other type as
+    /// JSON whose strings are HTML. Content of types without an allowlist, or that isn't JSON when it should be, is left
+    /// as it is.
+    pub fn clean(&self, module_type: &str, content: &str) -> String {
+        let builder = match self.types.get(module_type) {
+            Some(Some(allowlist)) => allowlist.builder(),
+            Some(None) => Builder::default(),
+            None => return String::from(content),
+        };
+
+        if matches!(ModuleType::parse(module_type), Some(ModuleType::Text) | Some(ModuleType::RichText)) {
+            return builder.clean(content).to_string();
+        }
+        let mut value = match serde_json::from_str::<Value>(content) {
+            Ok(value) => value,
+            Err(_) => return String::from(content),
+        };
+        // written again only when something was taken out, so JSON formatted by hand stays as it was.
+        match clean_strings(&mut value, &builder) {
+            true => value.to_string(),
+            false => String::from(content),
+        }
+    }
+}
+
+/// Sanitizes every string in `value`, and says whether any of them changed.
+fn clean_strings(value: &mut Value, builder: &Builder) -> bool {
+    match value {
+        Value::String(s) => {
+            let clean = builder.clean(s).to_string();
+            let changed = clean != *s;
+            *s = clean;
+            changed
+        }
+        Value::Array(items) => items.iter_mut().fold(false, |changed, item| clean_strings(item, builder) | changed),
+        Value::Object(object) => object.values_mut().fold(false, |changed, item| clean_strings(item, builder) | changed),
+        _ => false,
+    }
+}
+
+/// Sanitizes with `conf` from now on. Done whenever the config is loaded.
+pub fn configure(conf: &LocalConfig) {
+    *SANITIZER.write().unwrap() = Some(Arc::new(Sanitizer::new(conf)));
+}
+
+/// The sanitizer of the config, or the default one when no config was loaded.
+fn current() -> Arc<Sanitizer> {
+    SANITIZER.read().unwrap().clone().unwrap_or_default()
+}
+
+/// The content as it is saved, sanitized unless `APP_SANITIZE` leaves it to rendering.
+pub fn on_save(module_type: &str, content: &str) -> String {
+    let sanitizer = current();
+
+    match sanitizer.when.on_save() {
+        true => sanitizer.clean(module_type, content),
+        false => String::from(content),
+    }
+}
+
+fn clean_modules(sanitizer: &Sanitizer, modules: Vec<Module>) -> Vec<Module> {
+    modules
+        .into_iter()
+        .map(|mut module| {
+            // encrypted content is never rendered, and can't be read until it is revealed.
+            if !module.encrypted {
+                module.content = sanitizer.clean(&module.module_type, &module.content);
+            }
+            module
+        })
+        .collect()
+}
+
+/// The modules as they are rendered, or read through the delivery API, GraphQL, gRPC, or the module API without logging
+/// in, sanitized unless `APP_SANITIZE` leaves it to saving.
+pub fn on_render(modules: Vec<Module>) -> Vec<Module> {
+    let sanitizer = current();
+
+    match sanitizer.when.on_render() {
+        true => clean_modules(&sanitizer, modules),
+        false => modules,
+    }
+}
+
+pub fn on_render_module(module: Module) -> Module {
+    on_render(vec![module]).remove(0)
+}
+
+/// `on_render` for clients of the module API that aren't logged in. Users get the modules as they were saved, so that
+/// what they change is what was saved.
+pub fn on_read(claim: &Option<Claims>, modules: Vec<Module>) -> Vec<Module> {
+    match claim {
+        Some(_) => modules,
+        None => on_render(modules),
+    }
+}
+
+pub fn on_read_module(claim: &Option<Claims>, module: Module) -> Module {
+    on_read(claim, vec![module]).remove(0)
+}
+
+/// `on_render` for the modules of a page, along with the ones in its categories.
+pub fn on_render_fields(mut fields: FieldsDTO) -> FieldsDTO {
+    fields.modules = on_render(fields.modules);
+    if let Some(categories) = fields.categories.as_mut() {
+        for category in categories.iter_mut() {
+            category.modules = on_render(std::mem::take(&mut category.modules));
+        }
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn reads_tags_and_their_attributes() {
+        let (module_type, allowlist) = allowlist("rich_text=p BR a[href|Title] *[class]").unwrap();
+
+        assert_eq!(module_type, "rich_text");
+        assert_eq!(allowlist.tags, set(&["p", "br", "a"]));
+        assert_eq!(allowlist.generic, set(&["class"]));
+        assert_eq!(allowlist.attributes["a"], set(&["href", "title"]));
+        assert!(allowlist.attributes["p"].is_empty());
+    }
+
+    #[test]
+    fn keeps_no_tags_when_none_are_listed() {
+        assert_eq!(allowlist("text=").unwrap(), (String::from("text"), Allowlist::default()));
+    }
+
+    #[test]
+    fn refuses_invalid_entries() {
+        for entry in ["rich_text", "=p", "rich text=p", "rich_text=a[href", "rich_text=a[]", "rich_text=*", "rich_text=a[on<x>]", "rich_text=<p>"] {
+            assert!(allowlist(entry).is_err(), "{} should be refused", entry);
+        }
+    }
+
+    #[test]
+    fn reads_every_allowlist_of_a_value() {
+        let read = allowlists(Some(" rich_text=p strong, product=b i ,")).unwrap();
+
+        assert_eq!(read.len(), 2);
+        assert_eq!(read["rich_text"].tags, set(&["p", "strong"]));
+        assert_eq!(read["product"].tags, set(&["b", "i"]));
+        assert!(allowlists(None).unwrap().is_empty());
+        assert!(allowlists(Some("rich_text=p,oops")).is_err());
+    }
+
+    #[test]
+    fn cleans_content_with_its_types_allowlist() {
+        let mut sanitizer = Sanitizer::default();
+        sanitizer.types.insert(String::from("rich_text"), Some(allowlist("rich_text=p a[href]").unwrap().1));
+        sanitizer.types.insert(String::from("product"), Some(allowlist("product=b").unwrap().1));
+
+        assert_eq!(
+            sanitizer.clean("rich_text", r#"<p class="x"><a href="/a" title="t">a</a><script>x</script></p>"#),
+            r#"<p><a href="/a" rel="noopener noreferrer">a</a></p>"#
+        );
+        assert_eq!(
+            sanitizer.clean("product", r#"{"name":"<b>x</b><i>y</i>","price":1}"#),
+            r#"{"name":"<b>x</b>y","price":1}"#
+        );
+        assert_eq!(sanitizer.clean("product", r#"{ "name": "<b>x</b>" }"#), r#"{ "name": "<b>x</b>" }"#);
+        assert_eq!(sanitizer.clean("unlisted", "<script>x</script>"), "<script>x</script>");
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use actix_web::web;
+
+use super::publish_service::{PublishJob, PublishQueue};
+use super::route_service::RouteTable;
+use super::shutdown_service::Shutdown;
+use crate::models::config_models::LocalConfig;
+use crate::models::job_models::Job;
+use crate::models::page_models::Page;
+use crate::models::DbPool;
+
+/// The kind of the jobs queued for when a scheduled draft is due, so that it is published on time rather than on the
+/// next round of `run`.
+pub const JOB: &str = "page.publish";
+
+/// Publishes every draft whose `publish_at` has passed. Public lookups already treat such drafts as published, so this is
+/// what catches everything else up, like the route table, the search index, and static publishing.
+fn publish_due(pool: &DbPool, publish: &PublishQueue, routes: &RouteTable) -> Result<(), String> {
+    let db = pool.get().map_err(|e| e.to_string())?;
+    let due = Page::publish_due(&db).map_err(|e| e.to_string())?;
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    tracing::info!("Published {} scheduled pages.", due.len());
+    routes.invalidate();
+    for uuid in due {
+        publish.send(PublishJob::Page(uuid));
+    }
+
+    Ok(())
+}
+
+/// Runs the jobs of `JOB`. Every draft that is due is published, not only the job's, which does no harm as publishing
+/// one that already is does nothing.
+pub fn handler(
+    pool: DbPool,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+) -> impl Fn(&Job) -> Result<(), String> + Send {
+    move |_| publish_due(&pool, &publish, &routes)
+}
+
+/// Publishes drafts as their `publish_at` passes, every `APP_SCHEDULE_INTERVAL`. Runs until the shutdown begins, so it
+/// should be given its own thread. Drafts scheduled through the API are also published by a job at their time, so this
+/// catches the ones that weren't, like imported ones.
+pub fn run(
+    pool: DbPool,
+    conf: LocalConfig,
+    publish: web::Data<PublishQueue>,
+    routes: web::Data<RouteTable>,
+    shutdown: web::Data<Shutdown>,
+) {
+    loop {
+        if let Err(e) = publish_due(&pool, &publish, &routes) {
+            tracing::error!("scheduling error: {}", e);
+        }
+
+        if !shutdown.sleep(Duration::from_secs(conf.schedule_interval)) {
+            return;
+        }
+    }
+}
@@ -0,0 +1,586 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use actix_web::error::BlockingError;
+use actix_web::web;
+use chrono::{NaiveDateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use super::errors_service::CustomHttpError;
+use super::pagination_service::{PageQuery, Paginated};
+use super::publish_service::PublishJob;
+use super::site_service;
+use super::visibility_service::{is_visible, Role};
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::Module;
+use crate::models::page_models::{is_live, Page, PUBLIC};
+use crate::models::search_document_models::{MutSearchDocument, SearchDocument};
+use crate::models::{DbConnection, DbPool, DbPooledConnection};
+
+/// How many pages are read and indexed at once during a rebuild.
+const BATCH_SIZE: i64 = 100;
+/// How many characters of content a search result shows around its first match.
+const SNIPPET_LENGTH: usize = 160;
+/// The Meilisearch index pages go in unless `APP_MEILISEARCH_INDEX` says otherwise.
+const DEFAULT_MEILISEARCH_INDEX: &str = "pages";
+/// Requests to Meilisearch that take longer than this are counted as failed.
+const MEILISEARCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum SearchError {
+    #[error("The search index could not be reached: {0}")]
+    Unavailable(String),
+    #[error("The content could not be read: {0}")]
+    Database(#[from] diesel::result::Error),
+    #[error("The search backend is not configured correctly.")]
+    Config,
+}
+
+/// The index failing is never the client's fault, so why it failed is printed rather than sent.
+impl From<SearchError> for CustomHttpError {
+    fn from(e: SearchError) -> Self {
+        tracing::error!("Search error: {}", e);
+        CustomHttpError::Unknown
+    }
+}
+
+/// A page's document, along with the site it is searched on.
+#[derive(Debug, Clone)]
+pub struct IndexedPage {
+    pub site_id: String,
+    pub document: MutSearchDocument,
+}
+
+/// Somewhere page documents can be indexed and searched, like the internal FULLTEXT index or Meilisearch.
+/// It is shared by the search worker, which keeps it up to date, and the server, which searches it.
+pub trait SearchIndex: Send + Sync {
+    /// Gets the index ready to be written to and searched, which is done once, before anything else.
+    fn prepare(&self) -> Result<(), SearchError> {
+        Ok(())
+    }
+    /// How many documents there are, which is 0 when search was just turned on, or the index was lost.
+    fn count(&self) -> Result<i64, SearchError>;
+    /// Creates or replaces the documents of these pages.
+    fn put(&self, pages: &[IndexedPage]) -> Result<(), SearchError>;
+    /// Removes the document of a page that has been deleted, by its URL.
+    fn remove(&self, page_url: &str) -> Result<(), SearchError>;
+    /// Removes every document indexed before `time`, which after a rebuild is everything it didn't come across.
+    fn remove_stale(&self, time: NaiveDateTime) -> Result<(), SearchError>;
+    /// The documents of the current site's pages that match `q`, most relevant first, along with how many match in all.
+    fn search(&self, q: &str, offset: i64, limit: i64) -> Result<(Vec<SearchDocument>, i64), SearchError>;
+}
+
+/// The `search_documents` table, which has a FULLTEXT index over the title, name, and content of every page.
+pub struct FulltextIndex {
+    pool: DbPool,
+}
+
+impl FulltextIndex {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    fn connection(&self) -> Result<DbPooledConnection, SearchError> {
+        self.pool.get().map_err(|e| SearchError::Unavailable(e.to_string()))
+    }
+}
+
+impl SearchIndex for FulltextIndex {
+    fn count(&self) -> Result<i64, SearchError> {
+        Ok(SearchDocument::count(&*self.connection()?)?)
+    }
+
+    fn put(&self, pages: &[IndexedPage]) -> Result<(), SearchError> {
+        if pages.is_empty() {
+            return Ok(());
+        }
+
+        // the table finds the site of a document through its page.
+        let documents: Vec<MutSearchDocument> = pages.iter().map(|page| page.document.clone()).collect();
+        SearchDocument::upsert_many(&documents, &*self.connection()?)?;
+
+        Ok(())
+    }
+
+    fn remove(&self, page_url: &str) -> Result<(), SearchError> {
+        SearchDocument::delete_by_url(page_url, &*self.connection()?)?;
+
+        Ok(())
+    }
+
+    fn remove_stale(&self, time: NaiveDateTime) -> Result<(), SearchError> {
+        SearchDocument::delete_indexed_before(time, &*self.connection()?)?;
+
+        Ok(())
+    }
+
+    fn search(&self, q: &str, offset: i64, limit: i64) -> Result<(Vec<SearchDocument>, i64), SearchError> {
+        Ok(SearchDocument::search(q, offset, limit, &*self.connection()?)?)
+    }
+}
+
+/// A document as it is kept in Meilisearch, which also knows its page's site so that it can filter on it.
+#[derive(Debug, Serialize, Deserialize)]
+struct MeilisearchDocument {
+    page_uuid: String,
+    page_url: String,
+    page_name: String,
+    page_title: String,
+    content: String,
+    site_id: String,
+    /// A Unix timestamp, as Meilisearch only compares numbers.
+    time_indexed: i64,
+}
+
+#[derive(Deserialize)]
+struct MeilisearchHits {
+    hits: Vec<MeilisearchDocument>,
+    #[serde(rename = "estimatedTotalHits")]
+    estimated_total_hits: i64,
+}
+
+#[derive(Deserialize)]
+struct MeilisearchStats {
+    #[serde(rename = "numberOfDocuments")]
+    number_of_documents: i64,
+}
+
+/// Quotes a value for a Meilisearch filter.
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Meilisearch, which ranks by typos, proximity, and which field matched, so searches find pages the FULLTEXT index misses.
+/// Writes are queued by Meilisearch and done in order, so a page can take a moment to be found after it changes.
+pub struct MeilisearchIndex {
+    url: String,
+    index: String,
+    key: Option<String>,
+    agent: ureq::Agent,
+}
+
+fn unavailable(e: impl ToString) -> SearchError {
+    SearchError::Unavailable(e.to_string())
+}
+
+impl MeilisearchIndex {
+    pub fn from_conf(conf: &LocalConfig) -> Result<Self, SearchError> {
+        let url = conf.meilisearch_url.as_deref().filter(|url| !url.is_empty()).ok_or(SearchError::Config)?;
+
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            index: conf.meilisearch_index.clone().unwrap_or_else(|| String::from(DEFAULT_MEILISEARCH_INDEX)),
+            key: conf.meilisearch_key.clone().filter(|key| !key.is_empty()),
+            agent: ureq::AgentBuilder::new().timeout(MEILISEARCH_TIMEOUT).build(),
+        })
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self
+            .agent
+            .request(method, &format!("{}/indexes/{}{}", self.url, self.index, path))
+            .set("Content-Type", "application/json");
+
+        match &self.key {
+            Some(key) => request.set("Authorization", &format!("Bearer {}", key)),
+            None => request,
+        }
+    }
+
+    fn send(&self, method: &str, path: &str, body: serde_json::Value) -> Result<String, SearchError> {
+        self.request(method, path)
+            .send_string(&body.to_string())
+            .map_err(unavailable)?
+            .into_string()
+            .map_err(unavailable)
+    }
+}
+
+impl SearchIndex for MeilisearchIndex {
+    /// Creates the index if it doesn't exist yet, and makes the fields filtered on filterable.
+    fn prepare(&self) -> Result<(), SearchError> {
+        let settings = json!({
+            "searchableAttributes": ["page_title", "page_name", "content"],
+            "filterableAttributes": ["site_id", "page_url", "time_indexed"],
+        });
+        self.send("PATCH", "/settings", settings)?;
+
+        Ok(())
+    }
+
+    fn count(&self) -> Result<i64, SearchError> {
+        let stats = match self.request("GET", "/stats").call() {
+            Ok(res) => res.into_string().map_err(unavailable)?,
+            // an index that hasn't been made yet has nothing in it.
+            Err(ureq::Error::Status(404, _)) => return Ok(0),
+            Err(e) => return Err(unavailable(e)),
+        };
+        let stats: MeilisearchStats = serde_json::from_str(&stats).map_err(unavailable)?;
+
+        Ok(stats.number_of_documents)
+    }
+
+    fn put(&self, pages: &[IndexedPage]) -> Result<(), SearchError> {
+        if pages.is_empty() {
+            return Ok(());
+        }
+
+        let documents: Vec<MeilisearchDocument> = pages
+            .iter()
+            .map(|page| MeilisearchDocument {
+                page_uuid: page.document.page_uuid.clone(),
+                page_url: page.document.page_url.clone(),
+                page_name: page.document.page_name.clone(),
+                page_title: page.document.page_title.clone(),
+                content: page.document.content.clone(),
+                site_id: page.site_id.clone(),
+                time_indexed: page.document.time_indexed.timestamp(),
+            })
+            .collect();
+        self.send("POST", "/documents?primaryKey=page_uuid", json!(documents))?;
+
+        Ok(())
+    }
+
+    fn remove(&self, page_url: &str) -> Result<(), SearchError> {
+        self.send("POST", "/documents/delete", json!({ "filter": format!("page_url = {}", quoted(page_url)) }))?;
+
+        Ok(())
+    }
+
+    fn remove_stale(&self, time: NaiveDateTime) -> Result<(), SearchError> {
+        let filter = format!("time_indexed < {}", time.timestamp());
+        self.send("POST", "/documents/delete", json!({ "filter": filter }))?;
+
+        Ok(())
+    }
+
+    fn search(&self, q: &str, offset: i64, limit: i64) -> Result<(Vec<SearchDocument>, i64), SearchError> {
+        let query = json!({
+            "q": q,
+            "offset": offset,
+            "limit": limit,
+            "filter": format!("site_id = {}", quoted(&site_service::current_or_default())),
+        });
+        let hits: MeilisearchHits = serde_json::from_str(&self.send("POST", "/search", query)?).map_err(unavailable)?;
+
+        let documents = hits
+            .hits
+            .into_iter()
+            .map(|hit| SearchDocument {
+                page_uuid: hit.page_uuid,
+                page_url: hit.page_url,
+                page_name: hit.page_name,
+                page_title: hit.page_title,
+                content: hit.content,
+                time_indexed: NaiveDateTime::from_timestamp_opt(hit.time_indexed, 0).unwrap_or_else(|| Utc::now().naive_utc()),
+            })
+            .collect();
+
+        Ok((documents, hits.estimated_total_hits))
+    }
+}
+
+/// The index chosen by `APP_SEARCH_BACKEND`, which is the internal one unless `meilisearch` is asked for.
+pub fn index(conf: &LocalConfig, pool: DbPool) -> Result<Arc<dyn SearchIndex>, SearchError> {
+    match conf.search_backend.as_deref() {
+        None | Some("fulltext") => Ok(Arc::new(FulltextIndex::new(pool))),
+        Some("meilisearch") => Ok(Arc::new(MeilisearchIndex::from_conf(conf)?)),
+        Some(_) => Err(SearchError::Config),
+    }
+}
+
+/// The progress of the last full rebuild of the index.
+#[derive(Debug, Serialize, Clone, Default, ToSchema)]
+pub struct ReindexStatus {
+    pub running: bool,
+    pub total: i64,
+    pub indexed: i64,
+    pub batch_size: i64,
+    pub time_started: Option<NaiveDateTime>,
+    pub time_finished: Option<NaiveDateTime>,
+    pub error: Option<String>,
+}
+
+/// Shared between the search worker and the server, so that a rebuild can be started and followed from the API.
+pub struct SearchIndexer {
+    status: RwLock<ReindexStatus>,
+    jobs: Mutex<Sender<PublishJob>>,
+}
+
+impl SearchIndexer {
+    /// Returns the end of the channel the worker takes its jobs from.
+    /// The same channel should be attached to the `PublishQueue` so content changes are indexed as they happen.
+    pub fn new() -> (Self, Receiver<PublishJob>) {
+        let (sender, receiver) = channel();
+
+        let indexer = Self {
+            status: RwLock::new(ReindexStatus::default()),
+            jobs: Mutex::new(sender),
+        };
+
+        (indexer, receiver)
+    }
+
+    pub fn sender(&self) -> Sender<PublishJob> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    pub fn status(&self) -> ReindexStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Queues a full rebuild. Returns false without queueing anything if one is already running.
+    pub fn rebuild(&self) -> bool {
+        let mut status = self.status.write().unwrap();
+
+        if status.running {
+            return false;
+        }
+
+        *status = ReindexStatus {
+            running: true,
+            batch_size: BATCH_SIZE,
+            ..ReindexStatus::default()
+        };
+        self.jobs.lock().unwrap().send(PublishJob::Everything).is_ok()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ReindexStatus)) {
+        f(&mut self.status.write().unwrap());
+    }
+}
+
+/// A page that matched a search.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct SearchResult {
+    pub page_uuid: String,
+    pub page_url: String,
+    pub page_name: String,
+    pub page_title: String,
+    /// The content around the first match, HTML escaped, with every search term in it wrapped in `<mark>`.
+    pub snippet: String,
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Cuts `SNIPPET_LENGTH` characters out of `content`, starting a little before the first term it contains,
+/// or from the start if the page only matched on its title or name.
+pub fn snippet(content: &str, q: &str) -> String {
+    let text: Vec<char> = content.split_whitespace().collect::<Vec<_>>().join(" ").chars().collect();
+    let folded: Vec<char> = text.iter().map(|c| fold(*c)).collect();
+    let terms: Vec<Vec<char>> = q.split_whitespace().map(|t| t.chars().map(fold).collect()).collect();
+
+    // the length of the longest term at `i`, if there is one.
+    let term_at = |i: usize| {
+        terms
+            .iter()
+            .filter(|t| folded[i..].starts_with(t))
+            .map(|t| t.len())
+            .max()
+    };
+
+    let first = (0..text.len()).find(|i| term_at(*i).is_some()).unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_LENGTH / 4);
+    let end = (start + SNIPPET_LENGTH).min(text.len());
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+
+    let mut i = start;
+    while i < end {
+        match term_at(i) {
+            Some(len) => {
+                let matched: String = text[i..i + len].iter().collect();
+                snippet.push_str(&format!("<mark>{}</mark>", handlebars::html_escape(&matched)));
+                i += len;
+            }
+            None => {
+                snippet.push_str(&handlebars::html_escape(&text[i].to_string()));
+                i += 1;
+            }
+        }
+    }
+
+    if end < text.len() {
+        snippet.push('…');
+    }
+
+    snippet
+}
+
+/// Searches the index for the request's site, on the blocking thread pool as the index may be a database or a server away.
+/// Only what the public can see is ever indexed, so the results are the same for everyone.
+pub async fn search(
+    q: String,
+    query: PageQuery,
+    index: web::Data<dyn SearchIndex>,
+) -> Result<Paginated<SearchResult>, CustomHttpError> {
+    if q.trim().is_empty() {
+        return Err(CustomHttpError::BadRequest);
+    }
+
+    let (offset, limit) = query.window()?;
+    let site = site_service::current();
+    let terms = q.trim().to_string();
+    let (documents, total) = web::block(move || site_service::scoped(site, || index.search(&terms, offset, limit)))
+        .await
+        .map_err(|e| match e {
+            BlockingError::Error(e) => CustomHttpError::from(e),
+            BlockingError::Canceled => CustomHttpError::Unknown,
+        })?;
+
+    let results = documents
+        .into_iter()
+        .map(|d| SearchResult {
+            snippet: snippet(&d.content, &q),
+            page_uuid: d.page_uuid,
+            page_url: d.page_url,
+            page_name: d.page_name,
+            page_title: d.page_title,
+        })
+        .collect();
+
+    Ok(Paginated::new(results, total, &query))
+}
+
+/// The module content of a page as one document. Encrypted modules and ones the public can't see
+/// are left out so that nothing secret ends up in the index.
+pub fn document(page: Page, modules: &[Module], time_indexed: NaiveDateTime) -> MutSearchDocument {
+    let content = modules
+        .iter()
+        .filter(|m| m.page_uuid.as_ref() == Some(&page.uuid) && !m.encrypted && is_visible(m, Role::Public))
+        .map(|m| m.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    MutSearchDocument {
+        page_uuid: page.uuid,
+        page_url: page.page_url,
+        page_name: page.page_name,
+        page_title: page.page_title,
+        content,
+        time_indexed,
+    }
+}
+
+fn index_pages(
+    pages: Vec<Page>,
+    time_indexed: NaiveDateTime,
+    index: &dyn SearchIndex,
+    db: &DbConnection,
+) -> Result<usize, SearchError> {
+    // drafts and pages only some users can see aren't public, so they are taken out of the index until they are.
+    let (pages, drafts): (Vec<Page>, Vec<Page>) =
+        pages.into_iter().partition(|p| is_live(&p.status, p.publish_at) && p.visibility == PUBLIC);
+    for draft in drafts {
+        index.remove(&draft.page_url)?;
+    }
+
+    let modules = Module::read_for_pages(pages.iter().map(|p| p.uuid.clone()).collect(), db)?;
+    let indexed: Vec<IndexedPage> = pages
+        .into_iter()
+        .map(|page| IndexedPage {
+            site_id: page.site_id.clone(),
+            document: document(page, &modules, time_indexed),
+        })
+        .collect();
+
+    index.put(&indexed)?;
+
+    Ok(indexed.len())
+}
+
+/// Timestamps are stored to the second, so this is truncated to make `remove_stale` keep everything indexed along with it.
+fn now() -> NaiveDateTime {
+    let now = Utc::now().naive_utc();
+
+    now.with_nanosecond(0).unwrap_or(now)
+}
+
+/// Indexes every page `BATCH_SIZE` at a time, then removes the documents of pages that no longer exist.
+/// The old documents stay searchable until they are replaced, so search keeps working during a rebuild.
+fn rebuild(indexer: &SearchIndexer, index: &dyn SearchIndex, db: &DbConnection) -> Result<(), SearchError> {
+    let time_started = now();
+    let total = Page::count(db)?;
+    indexer.update(|s| {
+        s.running = true;
+        s.total = total;
+        s.batch_size = BATCH_SIZE;
+        s.time_started = Some(time_started);
+    });
+
+    let mut offset = 0;
+    loop {
+        let pages = Page::read_batch(offset, BATCH_SIZE, db)?;
+        if pages.is_empty() {
+            break;
+        }
+
+        // drafts are read but not indexed, so the offset moves on by what was read.
+        let read = pages.len() as i64;
+        index_pages(pages, time_started, index, db)?;
+        offset += read;
+        indexer.update(|s| s.indexed = offset);
+    }
+
+    index.remove_stale(time_started)?;
+
+    Ok(())
+}
+
+fn handle(job: PublishJob, indexer: &SearchIndexer, index: &dyn SearchIndex, pool: &DbPool) -> Result<(), SearchError> {
+    let db = pool.get().map_err(|e| SearchError::Unavailable(e.to_string()))?;
+
+    match job {
+        PublishJob::Page(uuid) => index_pages(Page::read_many(vec![uuid], &db)?, now(), index, &db).map(|_| ()),
+        PublishJob::Removed(url) => index.remove(&url),
+        PublishJob::Everything => {
+            let res = rebuild(indexer, index, &db);
+            let error = res.as_ref().err().map(|e| e.to_string());
+
+            indexer.update(|s| {
+                s.running = false;
+                s.time_finished = Some(now());
+                s.error = error;
+            });
+
+            res
+        }
+    }
+}
+
+/// Keeps the index up to date with content as it changes, and runs full rebuilds when asked to.
+/// Failed jobs are logged and dropped, as the next change to the page or a rebuild will index it again.
+pub fn run(
+    pool: DbPool,
+    indexer: web::Data<SearchIndexer>,
+    index: Arc<dyn SearchIndex>,
+    jobs: Receiver<PublishJob>,
+) {
+    if let Err(e) = index.prepare() {
+        tracing::error!("Failed to prepare the search index: {}", e);
+    }
+
+    // an empty index with content to put in it means search was just turned on, or the index was lost.
+    let needs_rebuild = pool.get().ok().map(|db| {
+        matches!((index.count(), Page::count(&db)), (Ok(0), Ok(pages)) if pages > 0)
+    });
+    if needs_rebuild == Some(true) {
+        indexer.rebuild();
+    }
+
+    for job in jobs {
+        if let Err(e) = handle(job, &indexer, index.as_ref(), &pool) {
+            tracing::error!("Failed to index: {}", e);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_session::{CookieSession, Session};
+use actix_web::cookie::SameSite;
+use actix_web::http::HeaderValue;
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+
+use super::auth_service::generate_secret;
+use super::cache_service::{CacheBackend, CacheError, RedisCache};
+use super::delivery_service::hash_token;
+use super::errors_service::CustomHttpError;
+use crate::models::config_models::LocalConfig;
+
+/// How long a session lasts without logging in again, the same as the token it holds.
+pub const SESSION_TTL: Duration = Duration::from_secs(60 * 60 * 24 * 10);
+
+/// Writes made with a session have to send its CSRF token in this header, which a page on another site can't read.
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+pub const SESSION_COOKIE: &str = "radical_session";
+
+/// What a session keeps: the token the user logged in with, and the CSRF token its writes have to send.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionData {
+    pub token: String,
+    pub csrf_token: String,
+}
+
+impl SessionData {
+    pub fn new(token: String) -> Self {
+        Self {
+            token,
+            csrf_token: generate_secret(),
+        }
+    }
+
+    /// The token as it would be sent in the `Authorization` header, so it goes through `authenticate` the same way.
+    pub fn auth_header(&self) -> Result<HeaderValue, CustomHttpError> {
+        HeaderValue::from_str(&self.token).or(Err(CustomHttpError::Unauthorized))
+    }
+}
+
+/// Where a session's data is kept. The session cookie is private either way, so it can't be read or changed by the browser.
+pub trait SessionStore: Send + Sync {
+    fn load(&self, session: &Session) -> Result<Option<SessionData>, CustomHttpError>;
+    fn save(&self, session: &Session, data: &SessionData) -> Result<(), CustomHttpError>;
+    fn remove(&self, session: &Session) -> Result<(), CustomHttpError>;
+}
+
+/// Keeps everything in the encrypted cookie, so nothing is stored on the server.
+pub struct CookieStore;
+
+impl SessionStore for CookieStore {
+    fn load(&self, session: &Session) -> Result<Option<SessionData>, CustomHttpError> {
+        session.get::<SessionData>("data").or(Err(CustomHttpError::Unauthorized))
+    }
+
+    fn save(&self, session: &Session, data: &SessionData) -> Result<(), CustomHttpError> {
+        session.set("data", data).or(Err(CustomHttpError::Unknown))
+    }
+
+    fn remove(&self, session: &Session) -> Result<(), CustomHttpError> {
+        session.purge();
+
+        Ok(())
+    }
+}
+
+/// Keeps only an id in the cookie and the rest in Redis, so a session can be ended on the server.
+pub struct RedisStore {
+    cache: RedisCache,
+}
+
+impl RedisStore {
+    fn key(id: &str) -> String {
+        format!("session:{}", id)
+    }
+}
+
+impl SessionStore for RedisStore {
+    fn load(&self, session: &Session) -> Result<Option<SessionData>, CustomHttpError> {
+        let id = match session.get::<String>("id").or(Err(CustomHttpError::Unauthorized))? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        match self.cache.get(&Self::key(&id)).or(Err(CustomHttpError::Unknown))? {
+            Some(data) => Ok(serde_json::from_str(&data).ok()),
+            None => Ok(None),
+        }
+    }
+
+    fn save(&self, session: &Session, data: &SessionData) -> Result<(), CustomHttpError> {
+        let id = generate_secret();
+        let data = serde_json::to_string(data).or(Err(CustomHttpError::Unknown))?;
+
+        self.cache.set(&Self::key(&id), &data, Some(SESSION_TTL)).or(Err(CustomHttpError::Unknown))?;
+        session.set("id", id).or(Err(CustomHttpError::Unknown))
+    }
+
+    fn remove(&self, session: &Session) -> Result<(), CustomHttpError> {
+        if let Ok(Some(id)) = session.get::<String>("id") {
+            self.cache.delete(&Self::key(&id)).or(Err(CustomHttpError::Unknown))?;
+        }
+        session.purge();
+
+        Ok(())
+    }
+}
+
+/// The session's data when it is logged in. Without a store there are no sessions.
+pub fn logged_in(session: &Session, store: Option<&web::Data<dyn SessionStore>>) -> Option<SessionData> {
+    store?.load(session).ok().flatten()
+}
+
+/// The store chosen by `APP_SESSION_STORE`, which is the cookie unless `redis` is asked for.
+pub fn store(conf: &LocalConfig) -> Result<Arc<dyn SessionStore>, CacheError> {
+    match conf.session_store.as_deref() {
+        None | Some("cookie") => Ok(Arc::new(CookieStore)),
+        Some("redis") => {
+            let url = conf.redis_url.as_deref().ok_or(CacheError::Config)?;
+            Ok(Arc::new(RedisStore {
+                cache: RedisCache::new(url)?,
+            }))
+        }
+        Some(_) => Err(CacheError::Config),
+    }
+}
+
+/// The session cookie, encrypted with a key derived from `APP_JWT_KEY`. It is only sent to the management API at `path`,
+/// or to the whole site with `APP_PAGE_SESSIONS`, never read by scripts, and not sent along with requests from other sites.
+pub fn middleware(conf: &LocalConfig, path: &str) -> CookieSession {
+    let path = match conf.page_sessions {
+        true => "/",
+        false => path,
+    };
+
+    CookieSession::private(hash_token(&conf.jwt_key).as_bytes())
+        .name(SESSION_COOKIE)
+        .path(path)
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .max_age(SESSION_TTL.as_secs() as i64)
+        .lazy(true)
+}
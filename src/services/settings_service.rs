@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+
+use super::cache_service::CacheBackend;
+use super::site_service;
+use crate::models::setting_models::Setting;
+use crate::models::{DbConnection, Model};
+
+const CACHE_KEY: &str = "settings";
+/// Settings are also dropped from the cache after a while,
+/// so that an invalidation that couldn't reach a shared cache doesn't leave them stale for good.
+const CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Read-through cache in front of the settings table, kept for each site.
+/// The whole table is loaded on the first read after an invalidation, since settings are small and read on most requests.
+/// Anything that writes to the settings table must call `invalidate`.
+/// When the cache backend can't be reached, settings are read straight from the database.
+pub struct SettingsCache {
+    cache: Arc<dyn CacheBackend>,
+}
+
+impl SettingsCache {
+    pub fn new(cache: Arc<dyn CacheBackend>) -> Self {
+        Self { cache }
+    }
+
+    fn load(&self, db: &DbConnection) -> Result<HashMap<String, String>, diesel::result::Error> {
+        let cached = self.cache.get(&site_service::cache_key(CACHE_KEY)).ok().flatten();
+        if let Some(settings) = cached.and_then(|s| serde_json::from_str(&s).ok()) {
+            return Ok(settings);
+        }
+
+        let settings: HashMap<String, String> = Setting::read_all(db)?
+            .into_iter()
+            .map(|s| (s.setting_key, s.setting_value))
+            .collect();
+
+        if let Ok(serialized) = serde_json::to_string(&settings) {
+            self.cache.set(&site_service::cache_key(CACHE_KEY), &serialized, Some(CACHE_TTL)).ok();
+        }
+
+        Ok(settings)
+    }
+
+    pub fn get(&self, key: &str, db: &DbConnection) -> Result<Option<String>, diesel::result::Error> {
+        Ok(self.load(db)?.remove(key))
+    }
+
+    pub fn all(&self, db: &DbConnection) -> Result<Vec<Setting>, diesel::result::Error> {
+        Ok(self
+            .load(db)?
+            .into_iter()
+            .map(|(setting_key, setting_value)| Setting {
+                setting_key,
+                setting_value,
+            })
+            .collect())
+    }
+
+    /// Drops the settings of every site.
+    pub fn invalidate(&self) {
+        self.cache.clear(CACHE_KEY).ok();
+    }
+}
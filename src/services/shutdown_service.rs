@@ -0,0 +1,65 @@
+use std::sync::{Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Lets background workers that run on an interval know that the server is stopping, so they finish what they are doing
+/// and return instead of starting again.
+#[derive(Default)]
+pub struct Shutdown {
+    stopping: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn begin(&self) {
+        *self.stopping.lock().unwrap() = true;
+        self.wake.notify_all();
+    }
+
+    /// Waits for `duration`, or until the shutdown begins. Returns whether the worker should keep going.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let stopping = self.stopping.lock().unwrap();
+        let (stopping, _) = self.wake.wait_timeout_while(stopping, duration, |stopping| !*stopping).unwrap();
+
+        !*stopping
+    }
+}
+
+/// Waits for SIGTERM or SIGINT, which both stop the server the same graceful way.
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use actix_web::rt::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                futures::future::select(Box::pin(term.recv()), Box::pin(actix_web::rt::signal::ctrl_c())).await;
+            }
+            Err(_) => {
+                actix_web::rt::signal::ctrl_c().await.ok();
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    actix_web::rt::signal::ctrl_c().await.ok();
+}
+
+/// Waits for the worker `name` to return until `deadline`, after which it is left to be stopped along with the process.
+pub fn join(name: &str, worker: JoinHandle<()>, deadline: Instant) {
+    while !worker.is_finished() {
+        if Instant::now() >= deadline {
+            tracing::warn!("Stopped before the {} worker finished.", name);
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    if worker.join().is_err() {
+        tracing::error!("The {} worker panicked while stopping.", name);
+    }
+}
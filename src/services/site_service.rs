@@ -0,0 +1,131 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::cache_service::CacheBackend;
+use crate::models::site_models::Site;
+use crate::models::DbConnection;
+
+/// The site everything there was before there were sites belongs to, which answers every host no other site has.
+pub const DEFAULT_SITE: &str = "default";
+
+const CACHE_KEY: &str = "sites";
+/// Sites can also be changed by other processes, which can't invalidate a memory cache.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+tokio::task_local! {
+    /// The site of the request being handled, set by the `Sites` middleware around everything the request does.
+    static REQUEST_SITE: String;
+}
+
+thread_local! {
+    /// The same on threads the request's task hands work off to, like the blocking threads of `with_connection`.
+    static THREAD_SITE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The site that queries for pages, modules, and media are scoped to. It is `None` for work that isn't done for a request,
+/// like the background workers and the CLI, which see every site.
+pub fn current() -> Option<String> {
+    THREAD_SITE
+        .with(|site| site.borrow().clone())
+        .or_else(|| REQUEST_SITE.try_with(|site| site.clone()).ok())
+}
+
+/// The site new pages and media are created in.
+pub fn current_or_default() -> String {
+    current().unwrap_or_else(|| String::from(DEFAULT_SITE))
+}
+
+/// Runs `f` scoped to `site` on this thread, putting back whatever it was scoped to afterwards, even if `f` panics.
+pub fn scoped<T>(site: Option<String>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<String>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            THREAD_SITE.with(|site| *site.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(THREAD_SITE.with(|current| current.replace(site)));
+
+    f()
+}
+
+/// Runs a request's future scoped to `site`.
+pub async fn serve<F: Future>(site: String, f: F) -> F::Output {
+    REQUEST_SITE.scope(site, f).await
+}
+
+/// `key` for the current site, so that what is cached of one site, like its route table, is kept apart from the others.
+/// Clearing `key` as a prefix drops it for every site.
+pub fn cache_key(key: &str) -> String {
+    match current() {
+        Some(site) => format!("{}:{}", key, site),
+        None => key.to_string(),
+    }
+}
+
+/// The host of a `Host` header, like `example.com` of `Example.com:8080`, which is what sites are looked up by.
+pub fn hostname(host: &str) -> String {
+    let host = host.trim();
+    let host = match host.strip_prefix('[') {
+        // an IPv6 address, whose colons aren't a port.
+        Some(v6) => v6.split(']').next().unwrap_or(v6),
+        None => host.split(':').next().unwrap_or(host),
+    };
+
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// The sites by hostname, so that requests are matched to their site without going to the database.
+/// Anything that creates a site, deletes one, or changes its hostname must call `invalidate`.
+pub struct SiteTable {
+    cache: Arc<dyn CacheBackend>,
+}
+
+impl SiteTable {
+    pub fn new(cache: Arc<dyn CacheBackend>) -> Self {
+        Self { cache }
+    }
+
+    fn cached(&self) -> Option<HashMap<String, String>> {
+        let cached = self.cache.get(CACHE_KEY).ok().flatten()?;
+
+        serde_json::from_str(&cached).ok()
+    }
+
+    fn load(&self, db: &DbConnection) -> Result<HashMap<String, String>, diesel::result::Error> {
+        if let Some(sites) = self.cached() {
+            return Ok(sites);
+        }
+
+        let sites: HashMap<String, String> = Site::read_hostnames(db)?.into_iter().collect();
+
+        if let Ok(serialized) = serde_json::to_string(&sites) {
+            self.cache.set(CACHE_KEY, &serialized, Some(CACHE_TTL)).ok();
+        }
+
+        Ok(sites)
+    }
+
+    /// The site for `host` if the sites are cached, so that most requests are resolved without a connection.
+    pub fn resolve_cached(&self, host: &str) -> Option<String> {
+        let sites = self.cached()?;
+
+        Some(sites.get(&hostname(host)).cloned().unwrap_or_else(|| String::from(DEFAULT_SITE)))
+    }
+
+    /// The site a request to `host` is for, which is the default site when no site has it.
+    pub fn resolve(&self, host: &str, db: &DbConnection) -> Result<String, diesel::result::Error> {
+        let sites = self.load(db)?;
+
+        Ok(sites.get(&hostname(host)).cloned().unwrap_or_else(|| String::from(DEFAULT_SITE)))
+    }
+
+    pub fn invalidate(&self) {
+        self.cache.delete(CACHE_KEY).ok();
+    }
+}
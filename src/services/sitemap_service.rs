@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::HttpRequest;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::cache_service::CacheBackend;
+use super::errors_service::CustomHttpError;
+use super::route_service::is_pattern;
+use super::site_service::{self, DEFAULT_SITE};
+use crate::models::config_models::LocalConfig;
+use crate::models::page_models::Page;
+use crate::models::DbConnection;
+
+const CACHE_KEY: &str = "sitemap";
+/// Pages can also be published by other processes, like the import subcommands, which can't invalidate a memory cache.
+const CACHE_TTL: Duration = Duration::from_secs(600);
+/// The most URLs one sitemap can list.
+const MAX_URLS: usize = 50_000;
+
+/// A published page as a sitemap lists it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    modified: NaiveDateTime,
+}
+
+/// The published pages of each site, kept without the site's URL so that however the site is reached, they are only read once.
+/// It is dropped as soon as a page changes (see `run`).
+pub struct Sitemap {
+    cache: Arc<dyn CacheBackend>,
+}
+
+impl Sitemap {
+    pub fn new(cache: Arc<dyn CacheBackend>) -> Self {
+        Self { cache }
+    }
+
+    fn load(&self, db: &DbConnection) -> Result<Vec<Entry>, diesel::result::Error> {
+        let cached = self.cache.get(&site_service::cache_key(CACHE_KEY)).ok().flatten();
+        if let Some(entries) = cached.and_then(|e| serde_json::from_str(&e).ok()) {
+            return Ok(entries);
+        }
+
+        let mut entries: Vec<Entry> = Page::read_published(db)?
+            .into_iter()
+            .filter(|p| !p.noindex && !is_pattern(&p.page_url))
+            .map(|p| Entry {
+                url: p.page_url,
+                modified: p.time_created,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+        entries.dedup_by(|a, b| a.url == b.url);
+        entries.truncate(MAX_URLS);
+
+        if let Ok(serialized) = serde_json::to_string(&entries) {
+            self.cache.set(&site_service::cache_key(CACHE_KEY), &serialized, Some(CACHE_TTL)).ok();
+        }
+
+        Ok(entries)
+    }
+
+    /// `sitemap.xml` for the site at `base`, like `https://example.com`, listing every published page by URL
+    /// other than the ones marked `noindex`.
+    pub fn render(&self, base: &Url, db: &DbConnection) -> Result<String, diesel::result::Error> {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push_str("\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+
+        // a URL that can't be joined onto the base couldn't be visited either.
+        for entry in self.load(db)? {
+            if let Ok(loc) = base.join(&entry.url) {
+                xml.push_str(&format!(
+                    "  <url><loc>{}</loc><lastmod>{}</lastmod></url>\n",
+                    escape(loc.as_str()),
+                    entry.modified.format("%Y-%m-%dT%H:%M:%S+00:00")
+                ));
+            }
+        }
+
+        xml.push_str("</urlset>\n");
+        Ok(xml)
+    }
+
+    pub fn invalidate(&self) {
+        self.cache.clear(CACHE_KEY).ok();
+    }
+}
+
+/// `APP_SITE_URL`, or the scheme and host the request was made to when it isn't set.
+/// `APP_SITE_URL` is the default site's, so the other sites always go by the request.
+pub fn site_url(req: &HttpRequest, conf: &LocalConfig) -> Result<Url, CustomHttpError> {
+    let base = match &conf.site_url {
+        Some(url) if site_service::current_or_default() == DEFAULT_SITE => url.clone(),
+        _ => {
+            let info = req.connection_info();
+            format!("{}://{}", info.scheme(), info.host())
+        }
+    };
+
+    Url::parse(&base).map_err(|e| {
+        tracing::warn!("{} can't be used as the site's URL: {}", base, e);
+        CustomHttpError::Unknown
+    })
+}
+
+/// Escapes text for XML, in elements and attributes alike.
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,245 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use actix_web::web;
+use handlebars::Handlebars;
+use serde::Serialize;
+use url::Url;
+
+use super::api_version_service;
+use super::cache_service::MemoryCache;
+use super::feed_service::{self, Feed};
+use super::media_store_service::MediaStore;
+use super::publish_service::{publish_assets, publish_pages, PublishError, PublishTarget};
+use super::render_service::render_error_page;
+use super::site_service::{self, DEFAULT_SITE};
+use super::sitemap_service::Sitemap;
+use super::static_service::DEFAULT_STATIC_DIR;
+use super::theme_service::Themes;
+use crate::models::config_models::LocalConfig;
+use crate::models::job_models::Job;
+use crate::models::media_models::Media;
+use crate::models::{DbConnection, DbPool, Model};
+use crate::routers::v1_routers::V1;
+use crate::routers::ApiVersion;
+
+/// The kind of the jobs that export the site to `APP_STATIC_EXPORT_DIR`.
+pub const JOB: &str = "site.export";
+/// Where the site is exported to when `APP_STATIC_EXPORT_DIR` isn't set.
+pub const DEFAULT_DIR: &str = "./dist";
+/// Left in every export, so that exporting again only ever replaces a directory that was exported before.
+const MARKER: &str = ".radical-export";
+
+/// What an export wrote.
+#[derive(Debug, Default, Serialize)]
+pub struct StaticExport {
+    pub pages: usize,
+    pub assets: usize,
+    pub files: usize,
+    pub media: usize,
+    /// Whether `sitemap.xml` and `feed.xml` were written, which they are only with `APP_SITE_URL` to link to.
+    pub sitemap: bool,
+}
+
+fn write_error(e: std::io::Error) -> PublishError {
+    PublishError::Request(e.to_string())
+}
+
+/// A directory on disk, laid out the way a static host serves it.
+struct DirTarget {
+    root: PathBuf,
+}
+
+impl PublishTarget for DirTarget {
+    fn put(&self, path: &str, body: &[u8], _: &str) -> Result<(), PublishError> {
+        let path = self.root.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+
+        fs::write(path, body).map_err(write_error)
+    }
+
+    fn delete(&self, path: &str) -> Result<(), PublishError> {
+        match fs::remove_file(self.root.join(path)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(write_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Copies the files under `dir` to `prefix` in the target, by their path under `root`.
+fn copy_files(root: &Path, dir: &Path, prefix: &str, target: &DirTarget) -> Result<usize, PublishError> {
+    let mut copied = 0;
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // a site doesn't need to have any files of its own.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(write_error(e)),
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            copied += copy_files(root, &path, prefix, target)?;
+            continue;
+        }
+
+        let object = Path::new(prefix).join(path.strip_prefix(root).unwrap_or(&path));
+        let destination = target.root.join(object);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        fs::copy(&path, destination).map_err(write_error)?;
+        copied += 1;
+    }
+
+    Ok(copied)
+}
+
+/// Copies every upload to where the server serves it, like `api/v1/media/<uuid>`, so links to them keep working.
+fn copy_media(store: &dyn MediaStore, db: &DbConnection, target: &DirTarget) -> Result<usize, PublishError> {
+    let media = Media::read_all(db).map_err(|_| PublishError::Render)?;
+    let dir = target.root.join(api_version_service::mount(V1::NAME).trim_start_matches('/')).join("media");
+    fs::create_dir_all(&dir).map_err(write_error)?;
+
+    let mut copied = 0;
+    for media in media {
+        match store.fetch(&media.uuid).map_err(|e| PublishError::Request(e.to_string()))? {
+            Some(path) => {
+                fs::copy(path, dir.join(&media.uuid)).map_err(write_error)?;
+                copied += 1;
+            }
+            None => tracing::warn!("Media {} has no file to export.", media.uuid),
+        }
+    }
+
+    Ok(copied)
+}
+
+/// Writes `sitemap.xml`, and `feed.xml` with `APP_FEED_PREFIX`, linking to `base`.
+fn write_feeds(base: &Url, conf: &LocalConfig, db: &DbConnection, target: &DirTarget) -> Result<(), PublishError> {
+    // read fresh, rather than from a cache the server may not have dropped yet.
+    let cache = Arc::new(MemoryCache::new());
+
+    let sitemap = Sitemap::new(cache.clone()).render(base, db).map_err(|_| PublishError::Render)?;
+    target.put("sitemap.xml", sitemap.as_bytes(), "application/xml")?;
+    if let Some(prefix) = feed_service::prefix(conf) {
+        let feed = Feed::new(cache).render(conf, &prefix, base, db).map_err(|_| PublishError::Render)?;
+        target.put("feed.xml", feed.as_bytes(), "application/atom+xml")?;
+    }
+
+    Ok(())
+}
+
+fn write_site(
+    target: &DirTarget,
+    conf: &LocalConfig,
+    db: &DbConnection,
+    hb: &Mutex<Handlebars<'static>>,
+    themes: &Themes,
+    store: &dyn MediaStore,
+) -> Result<StaticExport, PublishError> {
+    let mut export = StaticExport {
+        pages: publish_pages(target, db, hb)?,
+        ..Default::default()
+    };
+
+    // static hosts show `404.html` for paths without a file.
+    if let Some(html) = render_error_page(true, db, &hb.lock().unwrap()).map_err(|_| PublishError::Render)? {
+        target.put("404.html", html.as_bytes(), "text/html; charset=utf-8")?;
+    }
+
+    let assets_dir = themes.assets_dir();
+    if assets_dir.is_dir() {
+        export.assets = publish_assets(&assets_dir, &assets_dir, target)?;
+    }
+    let static_dir = Path::new(conf.static_dir.as_deref().unwrap_or(DEFAULT_STATIC_DIR));
+    export.files = copy_files(static_dir, static_dir, "static", target)?;
+    export.media = copy_media(store, db, target)?;
+
+    if let Some(base) = conf.site_url.as_deref().and_then(|url| Url::parse(url).ok()) {
+        write_feeds(&base, conf, db, target)?;
+        export.sitemap = true;
+    }
+
+    Ok(export)
+}
+
+/// Whether `dir` is missing, empty, or an earlier export.
+fn replaceable(dir: &Path) -> bool {
+    match fs::read_dir(dir) {
+        Ok(mut entries) => entries.next().is_none() || dir.join(MARKER).is_file(),
+        Err(_) => !dir.exists(),
+    }
+}
+
+/// Renders every published page of the default site, along with its 404 page, the theme's assets, the files of
+/// `APP_STATIC_DIR`, and the uploads, into `out`, so that it can be served by any static host or CDN. The export is
+/// written next to `out` first and swapped in once it is done, so a host serving `out` never sees half of one.
+/// `out` is only replaced if it is empty or was exported before.
+pub fn export(
+    out: &Path,
+    conf: &LocalConfig,
+    db: &DbConnection,
+    hb: &Mutex<Handlebars<'static>>,
+    themes: &Themes,
+    store: &dyn MediaStore,
+) -> Result<StaticExport, PublishError> {
+    // without a trailing slash, so the partial export goes next to it rather than in it.
+    let out: PathBuf = out.components().collect();
+    let partial = PathBuf::from(format!("{}.partial", out.display()));
+    for dir in [&out, &partial] {
+        if !replaceable(dir) {
+            return Err(PublishError::Request(format!("{} isn't empty and wasn't exported to before", dir.display())));
+        }
+    }
+
+    if partial.exists() {
+        fs::remove_dir_all(&partial).map_err(write_error)?;
+    }
+    let target = DirTarget { root: partial.clone() };
+    target.put(MARKER, b"", "text/plain")?;
+
+    let written = site_service::scoped(Some(String::from(DEFAULT_SITE)), || write_site(&target, conf, db, hb, themes, store));
+    let export = match written {
+        Ok(export) => export,
+        Err(e) => {
+            fs::remove_dir_all(&partial).ok();
+            return Err(e);
+        }
+    };
+
+    if out.exists() {
+        fs::remove_dir_all(&out).map_err(write_error)?;
+    }
+    fs::rename(&partial, &out).map_err(write_error)?;
+
+    Ok(export)
+}
+
+/// Runs the jobs of `JOB`, exporting the site to `APP_STATIC_EXPORT_DIR`.
+pub fn handler(
+    pool: DbPool,
+    conf: LocalConfig,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+    themes: web::Data<Themes>,
+    store: web::Data<dyn MediaStore>,
+) -> impl Fn(&Job) -> Result<(), String> + Send {
+    move |_| {
+        let out = conf.static_export_dir.as_deref().unwrap_or(DEFAULT_DIR);
+        let db = pool.get().map_err(|e| e.to_string())?;
+        let export = export(Path::new(out), &conf, &db, &hb, &themes, &**store).map_err(|e| e.to_string())?;
+
+        tracing::info!(
+            "Exported {} pages, {} assets, {} files, and {} uploads to {}.",
+            export.pages,
+            export.assets,
+            export.files,
+            export.media,
+            out
+        );
+        Ok(())
+    }
+}
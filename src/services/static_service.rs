@@ -0,0 +1,61 @@
+use std::path::{Component, Path, PathBuf};
+
+use actix_files::NamedFile;
+use actix_web::http::{header, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse};
+use mime::Mime;
+
+use super::errors_service::CustomHttpError;
+
+/// Where `/static` is served from, unless `APP_STATIC_DIR` says otherwise.
+pub const DEFAULT_STATIC_DIR: &str = "./static";
+
+/// A file under `root`, as long as the path stays inside of it.
+pub fn file_in(root: &Path, path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+
+    match path.components().all(|c| matches!(c, Component::Normal(_))) {
+        true => Some(root.join(path)),
+        false => None,
+    }
+}
+
+/// Assets can be kept by browsers and proxies for `max_age` seconds, and are checked with their `ETag` after that.
+pub fn cache_control(max_age: u64) -> String {
+    match max_age {
+        0 => String::from("no-cache"),
+        max_age => format!("public, max-age={}", max_age),
+    }
+}
+
+/// Sends a file with its content type, `ETag`, and `Last-Modified`, answering conditional and range requests.
+pub fn serve(req: &HttpRequest, file: Option<PathBuf>, max_age: u64) -> Result<HttpResponse, CustomHttpError> {
+    serve_as(req, file, None, &cache_control(max_age))
+}
+
+/// The same as `serve`, with the content type given rather than guessed from the file's extension.
+/// Everything is shown inline, so that scripts and fonts opened on their own aren't downloaded instead.
+pub fn serve_as(
+    req: &HttpRequest,
+    file: Option<PathBuf>,
+    content_type: Option<Mime>,
+    cache: &str,
+) -> Result<HttpResponse, CustomHttpError> {
+    let file = file.filter(|f| f.is_file()).ok_or(CustomHttpError::NotFound)?;
+    let mut named = NamedFile::open(file).or(Err(CustomHttpError::NotFound))?;
+    if let Some(content_type) = content_type {
+        named = named.set_content_type(content_type);
+    }
+
+    let mut res = named
+        .disable_content_disposition()
+        .prefer_utf8(true)
+        .into_response(req)
+        .or(Err(CustomHttpError::Unknown))?;
+
+    if let Ok(value) = HeaderValue::from_str(cache) {
+        res.headers_mut().insert(header::CACHE_CONTROL, value);
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,237 @@
+use chrono::NaiveDateTime;
+use diesel::sql_types::{BigInt, Nullable, Timestamp, Varchar};
+use diesel::RunQueryDsl;
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::models::job_models::Job;
+use crate::models::page_models::DRAFT;
+use crate::models::DbConnection;
+use crate::services::{site_service, webhook_service};
+
+/// How many of the largest pages are reported.
+const LARGEST_PAGES_LIMIT: i64 = 10;
+/// How many of the latest edits the dashboard shows.
+const RECENT_EDITS_LIMIT: i64 = 10;
+
+/// How the site is sent with the dashboard's queries, which every count is scoped to unless it is `NULL`.
+#[cfg(feature = "postgres")]
+const SITE_PARAM: &str = "$1";
+#[cfg(not(feature = "postgres"))]
+const SITE_PARAM: &str = "?";
+
+/// What integers are cast to, as the databases don't share a name for a 64 bit integer in `CAST`.
+#[cfg(feature = "mysql")]
+const INTEGER: &str = "SIGNED";
+#[cfg(feature = "postgres")]
+const INTEGER: &str = "BIGINT";
+#[cfg(feature = "sqlite")]
+const INTEGER: &str = "INTEGER";
+
+#[cfg(feature = "mysql")]
+const TABLES_QUERY: &str = "SELECT TABLE_NAME AS name, \
+        CAST(COALESCE(DATA_LENGTH, 0) AS SIGNED) AS data_bytes, \
+        CAST(COALESCE(INDEX_LENGTH, 0) AS SIGNED) AS index_bytes, \
+        CAST(0 AS SIGNED) AS rows \
+    FROM information_schema.TABLES \
+    WHERE TABLE_SCHEMA = DATABASE() AND TABLE_TYPE = 'BASE TABLE' \
+    ORDER BY TABLE_NAME";
+#[cfg(feature = "postgres")]
+const TABLES_QUERY: &str = "SELECT c.relname AS name, \
+        CAST(pg_table_size(c.oid) AS BIGINT) AS data_bytes, \
+        CAST(pg_indexes_size(c.oid) AS BIGINT) AS index_bytes, \
+        CAST(0 AS BIGINT) AS rows \
+    FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+    WHERE n.nspname = current_schema() AND c.relkind = 'r' \
+    ORDER BY c.relname";
+/// SQLite doesn't keep sizes per table without the `dbstat` extension, so they are reported as 0.
+#[cfg(feature = "sqlite")]
+const TABLES_QUERY: &str = "SELECT name, \
+        CAST(0 AS INTEGER) AS data_bytes, \
+        CAST(0 AS INTEGER) AS index_bytes, \
+        CAST(0 AS INTEGER) AS rows \
+    FROM sqlite_master \
+    WHERE type = 'table' AND name NOT LIKE 'sqlite_%' \
+    ORDER BY name";
+
+/// Quotes a table name the way the database expects.
+fn quote(table: &str) -> String {
+    match cfg!(feature = "mysql") {
+        true => format!("`{}`", table),
+        false => format!("\"{}\"", table),
+    }
+}
+
+#[derive(Debug, QueryableByName, Serialize, Clone, ToSchema)]
+pub struct TableStats {
+    #[sql_type = "Varchar"]
+    pub name: String,
+    #[sql_type = "BigInt"]
+    pub data_bytes: i64,
+    #[sql_type = "BigInt"]
+    pub index_bytes: i64,
+    /// Filled in with an exact count afterwards, as the `TABLE_ROWS` estimate can be way off for InnoDB.
+    #[sql_type = "BigInt"]
+    pub rows: i64,
+}
+
+#[derive(Debug, QueryableByName, Serialize, Clone)]
+struct RowCount {
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
+#[derive(Debug, QueryableByName, Serialize, Clone, ToSchema)]
+pub struct PageStats {
+    #[sql_type = "Varchar"]
+    pub uuid: String,
+    #[sql_type = "Varchar"]
+    pub page_name: String,
+    #[sql_type = "Varchar"]
+    pub page_url: String,
+    #[sql_type = "BigInt"]
+    pub module_count: i64,
+    /// Total length of the stored content of all of the page's modules.
+    #[sql_type = "BigInt"]
+    pub content_bytes: i64,
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct StorageStats {
+    pub data_bytes: i64,
+    pub index_bytes: i64,
+    pub total_bytes: i64,
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct SystemStats {
+    pub tables: Vec<TableStats>,
+    pub largest_pages_by_module_count: Vec<PageStats>,
+    pub largest_pages_by_size: Vec<PageStats>,
+    pub storage: StorageStats,
+}
+
+fn table_stats(db: &DbConnection) -> Result<Vec<TableStats>, diesel::result::Error> {
+    let mut tables = diesel::sql_query(TABLES_QUERY).load::<TableStats>(db)?;
+
+    // table names come straight from the database's catalog, so they are safe to put in the query.
+    for table in tables.iter_mut() {
+        table.rows = diesel::sql_query(format!("SELECT COUNT(*) AS count FROM {}", quote(&table.name)))
+            .get_result::<RowCount>(db)?
+            .count;
+    }
+
+    Ok(tables)
+}
+
+fn largest_pages(order_by: &str, db: &DbConnection) -> Result<Vec<PageStats>, diesel::result::Error> {
+    diesel::sql_query(format!(
+        "SELECT p.uuid, p.page_name, p.page_url, \
+            COUNT(m.uuid) AS module_count, \
+            CAST(COALESCE(SUM(LENGTH(m.content)), 0) AS {}) AS content_bytes \
+        FROM pages p LEFT JOIN modules m ON m.page_uuid = p.uuid \
+        GROUP BY p.uuid, p.page_name, p.page_url \
+        ORDER BY {} DESC \
+        LIMIT {}",
+        INTEGER, order_by, LARGEST_PAGES_LIMIT
+    ))
+    .load::<PageStats>(db)
+}
+
+#[derive(Debug, QueryableByName, Serialize, Clone, ToSchema)]
+pub struct ContentCounts {
+    #[sql_type = "BigInt"]
+    pub pages: i64,
+    #[sql_type = "BigInt"]
+    pub drafts: i64,
+    /// Drafts that are set to go live at their `publish_at`.
+    #[sql_type = "BigInt"]
+    pub scheduled: i64,
+    #[sql_type = "BigInt"]
+    pub modules: i64,
+    #[sql_type = "BigInt"]
+    pub media: i64,
+}
+
+/// A change to a page, from the revision it left behind.
+#[derive(Debug, QueryableByName, Serialize, Clone, ToSchema)]
+pub struct RecentEdit {
+    #[sql_type = "Varchar"]
+    pub page_uuid: String,
+    #[sql_type = "Varchar"]
+    pub page_name: String,
+    #[sql_type = "Varchar"]
+    pub page_url: String,
+    #[sql_type = "Timestamp"]
+    pub time: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct DashboardStats {
+    #[serde(flatten)]
+    pub counts: ContentCounts,
+    pub recent_edits: Vec<RecentEdit>,
+    /// Of every site, as webhooks are shared by all of them.
+    pub pending_webhook_deliveries: i64,
+}
+
+/// The numbers an admin panel shows on its dashboard, for the current site, without it reading every page to count them.
+/// Pages and modules in the trash aren't counted.
+pub fn dashboard(db: &DbConnection) -> Result<DashboardStats, diesel::result::Error> {
+    let site = site_service::current();
+    let on_site = |table: &str| format!("(s.site IS NULL OR {}.site_id = s.site)", table);
+
+    let counts = diesel::sql_query(format!(
+        "SELECT \
+            (SELECT COUNT(*) FROM pages p WHERE p.deleted_at IS NULL AND {pages}) AS pages, \
+            (SELECT COUNT(*) FROM pages p WHERE p.deleted_at IS NULL AND p.status = '{draft}' AND {pages}) AS drafts, \
+            (SELECT COUNT(*) FROM pages p WHERE p.deleted_at IS NULL AND p.status = '{draft}' AND p.publish_at IS NOT NULL AND {pages}) AS scheduled, \
+            (SELECT COUNT(*) FROM modules m WHERE m.deleted_at IS NULL AND {modules}) AS modules, \
+            (SELECT COUNT(*) FROM media f WHERE {media}) AS media \
+        FROM (SELECT {param} AS site) s",
+        pages = on_site("p"),
+        modules = on_site("m"),
+        media = on_site("f"),
+        draft = DRAFT,
+        param = SITE_PARAM,
+    ))
+    .bind::<Nullable<Varchar>, _>(site.clone())
+    .get_result::<ContentCounts>(db)?;
+
+    let recent_edits = diesel::sql_query(format!(
+        "SELECT p.uuid AS page_uuid, p.page_name, p.page_url, r.time_created AS time \
+        FROM page_revisions r JOIN pages p ON p.uuid = r.page_uuid, (SELECT {} AS site) s \
+        WHERE p.deleted_at IS NULL AND {} \
+        ORDER BY r.time_created DESC \
+        LIMIT {}",
+        SITE_PARAM,
+        on_site("p"),
+        RECENT_EDITS_LIMIT
+    ))
+    .bind::<Nullable<Varchar>, _>(site)
+    .load::<RecentEdit>(db)?;
+    let pending_webhook_deliveries = Job::count_waiting(webhook_service::JOB, db)?;
+
+    Ok(DashboardStats {
+        counts,
+        recent_edits,
+        pending_webhook_deliveries,
+    })
+}
+
+pub fn stats(db: &DbConnection) -> Result<SystemStats, diesel::result::Error> {
+    let tables = table_stats(db)?;
+
+    let data_bytes = tables.iter().map(|t| t.data_bytes).sum();
+    let index_bytes = tables.iter().map(|t| t.index_bytes).sum();
+
+    Ok(SystemStats {
+        largest_pages_by_module_count: largest_pages("module_count", db)?,
+        largest_pages_by_size: largest_pages("content_bytes", db)?,
+        storage: StorageStats {
+            data_bytes,
+            index_bytes,
+            total_bytes: data_bytes + index_bytes,
+        },
+        tables,
+    })
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use diesel::OptionalExtension;
+use handlebars::Handlebars;
+
+use super::errors_service::CustomHttpError;
+use super::static_service::file_in;
+use super::site_service::{self, DEFAULT_SITE};
+use super::validation_service::FieldError;
+use crate::models::config_models::LocalConfig;
+use crate::models::setting_models::Setting;
+use crate::models::{DbConnection, Model};
+
+/// Every theme is a directory under here, with its templates in `templates` and the files served from `/assets` in `static`.
+pub const THEMES_DIR: &str = "./themes";
+/// What is used with no theme set, which is how a site is laid out without themes.
+pub const DEFAULT_TEMPLATES: &str = "./templates";
+const DEFAULT_ASSETS: &str = "./templates/assets";
+/// The setting that picks the theme, which wins over `APP_THEME`.
+pub const THEME_SETTING: &str = "theme";
+
+/// Theme names are directory names, so anything that could reach outside of `THEMES_DIR` is refused.
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn templates_dir(theme: Option<&str>) -> PathBuf {
+    match theme {
+        Some(name) => Path::new(THEMES_DIR).join(name).join("templates"),
+        None => PathBuf::from(DEFAULT_TEMPLATES),
+    }
+}
+
+pub fn assets_dir(theme: Option<&str>) -> PathBuf {
+    match theme {
+        Some(name) => Path::new(THEMES_DIR).join(name).join("static"),
+        None => PathBuf::from(DEFAULT_ASSETS),
+    }
+}
+
+pub fn exists(name: &str) -> bool {
+    is_name(name) && templates_dir(Some(name)).is_dir()
+}
+
+/// The themes on disk, by name.
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(THEMES_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter(|name| exists(name))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    names.sort();
+    names
+}
+
+/// The theme the setting or `APP_THEME` picks. One that isn't on disk is skipped, so a missing theme can't keep the site from starting.
+pub fn configured(conf: &LocalConfig, db: &DbConnection) -> Option<String> {
+    let setting = site_service::scoped(Some(String::from(DEFAULT_SITE)), || Setting::read_one(THEME_SETTING.to_string(), db))
+        .optional()
+        .ok()
+        .flatten()
+        .map(|s| s.setting_value);
+    let name = setting.or_else(|| conf.theme.clone())?;
+
+    match exists(&name) {
+        true => Some(name),
+        false => {
+            tracing::warn!("There is no theme named {} in {}, so {} is used.", name, THEMES_DIR, DEFAULT_TEMPLATES);
+            None
+        }
+    }
+}
+
+/// The active theme, which is shared between the workers and the threads that render or publish, so a switch reaches all of them.
+pub struct Themes {
+    active: RwLock<Option<String>>,
+}
+
+impl Themes {
+    pub fn new(active: Option<String>) -> Self {
+        Self {
+            active: RwLock::new(active),
+        }
+    }
+
+    pub fn active(&self) -> Option<String> {
+        self.active.read().unwrap().clone()
+    }
+
+    pub fn assets_dir(&self) -> PathBuf {
+        assets_dir(self.active().as_deref())
+    }
+
+    /// A file under the active theme's `static`, as long as the path stays inside of it.
+    pub fn asset(&self, path: &str) -> Option<PathBuf> {
+        file_in(&self.assets_dir(), path)
+    }
+
+    /// Reads the active theme's templates again, keeping the old ones if the new ones don't compile.
+    pub fn reload(&self, hb: &Mutex<Handlebars<'_>>) -> Result<(), CustomHttpError> {
+        load(self.active().as_deref(), hb)
+    }
+
+    /// Makes `theme` the active theme, or the default with `None`. Nothing changes unless all of its templates compile.
+    pub fn switch(&self, theme: Option<String>, hb: &Mutex<Handlebars<'_>>) -> Result<(), CustomHttpError> {
+        // held until the templates are in, so renders don't see one theme's templates with another's assets.
+        let mut active = self.active.write().unwrap();
+        load(theme.as_deref(), hb)?;
+        *active = theme;
+
+        Ok(())
+    }
+}
+
+/// Checks that a theme exists and that its templates compile, without using it.
+pub fn check(theme: Option<&str>) -> Result<(), CustomHttpError> {
+    if let Some(name) = theme {
+        if !exists(name) {
+            return Err(CustomHttpError::NotFound);
+        }
+    }
+
+    compile(theme)
+}
+
+/// Compiles the templates on their own, so that a broken one is found before any are replaced.
+fn compile(theme: Option<&str>) -> Result<(), CustomHttpError> {
+    Handlebars::new().register_templates_directory(".hbs", templates_dir(theme)).map_err(|e| {
+        CustomHttpError::Invalid(vec![FieldError {
+            field: String::from("name"),
+            message: format!("has templates that don't compile: {}", e),
+        }])
+    })
+}
+
+/// Swaps the templates for the theme's, keeping the helpers that are registered.
+fn load(theme: Option<&str>, hb: &Mutex<Handlebars<'_>>) -> Result<(), CustomHttpError> {
+    compile(theme)?;
+
+    let mut hb = hb.lock().unwrap();
+    hb.clear_templates();
+    // the same templates compiled a moment ago, so this only fails if they changed since.
+    hb.register_templates_directory(".hbs", templates_dir(theme)).map_err(|_| CustomHttpError::Unknown)
+}
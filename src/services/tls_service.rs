@@ -0,0 +1,51 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+
+use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use rustls::{NoClientAuth, PrivateKey, ServerConfig};
+
+use crate::models::config_models::LocalConfig;
+
+fn invalid(path: &str, what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{} has no {}", path, what))
+}
+
+fn open(path: &str) -> io::Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+fn read_key(path: &str) -> io::Result<PrivateKey> {
+    let mut keys = pkcs8_private_keys(&mut open(path)?).map_err(|_| invalid(path, "readable private key"))?;
+    if keys.is_empty() {
+        // not PKCS#8, so it may be an older `BEGIN RSA PRIVATE KEY`.
+        keys = rsa_private_keys(&mut open(path)?).map_err(|_| invalid(path, "readable private key"))?;
+    }
+
+    keys.into_iter().next().ok_or_else(|| invalid(path, "private key"))
+}
+
+/// The TLS the server is bound with when `APP_TLS_CERT` and `APP_TLS_KEY` are both set, or `None` to serve plain HTTP.
+/// The certificate file is PEM, with the certificate first and then the chain, as issuers like Let's Encrypt hand them out.
+pub fn server_config(conf: &LocalConfig) -> io::Result<Option<ServerConfig>> {
+    let (cert_path, key_path) = match (&conf.tls_cert, &conf.tls_key) {
+        (Some(cert), Some(key)) => (cert, key),
+        _ => return Ok(None),
+    };
+
+    let chain = certs(&mut open(cert_path)?).map_err(|_| invalid(cert_path, "readable certificate"))?;
+    if chain.is_empty() {
+        return Err(invalid(cert_path, "certificate"));
+    }
+    let key = read_key(key_path)?;
+
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(chain, key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", key_path, e)))?;
+    // so browsers that can speak HTTP/2 do.
+    config.set_protocols(&[b"h2".to_vec(), b"http/1.1".to_vec()]);
+
+    Ok(Some(config))
+}
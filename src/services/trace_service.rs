@@ -0,0 +1,29 @@
+use tracing_subscriber::EnvFilter;
+
+use crate::models::config_models::LocalConfig;
+
+/// Sends what the server logs to stdout, one line per event with the spans it happened in, or as JSON with `APP_LOG_FORMAT=json`
+/// for log collectors. What actix-web and diesel log through `log` is sent along with it.
+pub fn init(conf: &LocalConfig) {
+    // `check` has already made sure the filter is valid.
+    let subscriber = tracing_subscriber::fmt().with_env_filter(EnvFilter::new(&conf.log_level));
+
+    let res = match conf.log_format.as_str() {
+        "json" => subscriber.json().with_current_span(true).with_span_list(false).try_init(),
+        _ => subscriber.try_init(),
+    };
+    if let Err(e) = res {
+        eprintln!("Logging is turned off: {}", e);
+    }
+}
+
+/// For the subcommands, which may run before there is a config, so that what the services they share with the server log
+/// is seen on the console too.
+pub fn init_console() {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new("info"))
+        .without_time()
+        .with_target(false)
+        .try_init()
+        .ok();
+}
@@ -0,0 +1,213 @@
+use std::cmp::Ordering;
+
+use diesel::OptionalExtension;
+
+use super::errors_service::CustomHttpError;
+use super::publish_service::{PublishJob, PublishQueue};
+use super::validation_service::FieldError;
+use crate::models::page_models::{is_live, Page, Translation, PUBLIC};
+use crate::models::DbConnection;
+
+/// What the translation without a locale is called in `hreflang`.
+const DEFAULT_HREFLANG: &str = "x-default";
+
+/// Empty strings are how the API clears a page's locale or translation group.
+fn set(value: &Option<String>) -> Option<&str> {
+    value.as_deref().filter(|v| !v.is_empty())
+}
+
+/// A language tag, like `fr`, `pt-BR`, or `zh-Hant`.
+pub fn is_locale(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let language = subtags.next().unwrap_or_default();
+
+    (2..=8).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|s| (1..=8).contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// The languages of an `Accept-Language` header, most wanted first. `*` and languages with `q=0` are left out.
+pub fn accepted(header: &str) -> Vec<String> {
+    let mut languages: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let tag = params.next()?.trim();
+            let q = params
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .map_or(1.0, |q| q.trim().parse().unwrap_or(0.0));
+
+            (is_locale(tag) && q > 0.0).then(|| (tag.to_string(), q))
+        })
+        .collect();
+    // the sort is stable, so languages of the same weight stay in the order they were sent in.
+    languages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+    languages.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// `pt` of `pt-BR`.
+fn language(tag: &str) -> &str {
+    tag.split('-').next().unwrap_or(tag)
+}
+
+/// The URL of the one of `available`, by locale, that a visitor asking for `accepted` wants most.
+/// Each language matches its own locale first, then any of the same language, like `pt-BR` for `pt` and the other way round.
+fn pick<'a>(accepted: &[String], available: &'a [(String, String)]) -> Option<&'a str> {
+    accepted.iter().find_map(|wanted| {
+        let exact = available.iter().find(|(locale, _)| locale.eq_ignore_ascii_case(wanted));
+        let close = || available.iter().find(|(locale, _)| language(locale).eq_ignore_ascii_case(language(wanted)));
+
+        exact.or_else(close).map(|(_, url)| url.as_str())
+    })
+}
+
+/// The other pages in the group that visitors can see.
+fn live_translations(group: &str, page_id: &str, db: &DbConnection) -> Result<Vec<Page>, diesel::result::Error> {
+    Ok(Page::read_translations(group, db)?
+        .into_iter()
+        .filter(|p| p.uuid != page_id && is_live(&p.status, p.publish_at) && p.visibility == PUBLIC)
+        .collect())
+}
+
+/// How the page at a URL answers a visitor, given the languages they accept.
+pub enum Negotiation {
+    /// The page has no translations to pick from.
+    Fixed,
+    /// The page is the one the visitor wants, though it could be another one for someone else.
+    Varies,
+    /// The visitor wants the translation at this URL instead.
+    Redirect(String),
+}
+
+/// Picks the translation of the page at `url` the visitor wants most, against `APP_DEFAULT_LOCALE` for the page itself.
+/// Only pages without a locale are picked for. Someone at a page with one, like `/fr/about`, asked for it in that language.
+pub fn negotiate(url: &str, accepted: &[String], default_locale: &str, db: &DbConnection) -> Result<Negotiation, diesel::result::Error> {
+    let page = match Page::read_one_by_url(url.to_string(), db).optional()? {
+        Some(page) => page,
+        None => return Ok(Negotiation::Fixed),
+    };
+    let group = match (set(&page.locale), set(&page.translation_group)) {
+        (None, Some(group)) => group,
+        _ => return Ok(Negotiation::Fixed),
+    };
+
+    let mut available = vec![(default_locale.to_string(), page.page_url.clone())];
+    available.extend(
+        live_translations(group, &page.uuid, db)?
+            .into_iter()
+            .filter_map(|p| Some((set(&p.locale)?.to_string(), p.page_url))),
+    );
+    if available.len() == 1 {
+        return Ok(Negotiation::Fixed);
+    }
+
+    Ok(match pick(accepted, &available) {
+        Some(target) if target != page.page_url => Negotiation::Redirect(target.to_string()),
+        _ => Negotiation::Varies,
+    })
+}
+
+/// The translation a path like `/fr/about`, which no page has, is for: the translation in `fr` of the page at `/about`,
+/// or in another `fr`, like `fr-CA`, when it has none in `fr` itself.
+/// `/en/about` is `/about` itself when that page has no locale and `APP_DEFAULT_LOCALE` is `en`.
+pub fn by_prefix(path: &str, default_locale: &str, db: &DbConnection) -> Result<Option<String>, diesel::result::Error> {
+    let (prefix, rest) = match path.trim_start_matches('/').split_once('/') {
+        Some((prefix, rest)) => (prefix, format!("/{}", rest)),
+        None => (path.trim_start_matches('/'), String::from("/")),
+    };
+    if !is_locale(prefix) {
+        return Ok(None);
+    }
+
+    let page = match Page::read_one_by_url(rest, db).optional()? {
+        Some(page) if is_live(&page.status, page.publish_at) => page,
+        _ => return Ok(None),
+    };
+    if set(&page.locale).is_none() && prefix.eq_ignore_ascii_case(default_locale) {
+        return Ok(Some(page.page_url));
+    }
+
+    let group = match set(&page.translation_group) {
+        Some(group) => group,
+        None => return Ok(None),
+    };
+
+    let available: Vec<(String, String)> = live_translations(group, &page.uuid, db)?
+        .into_iter()
+        .filter_map(|p| Some((set(&p.locale)?.to_string(), p.page_url)))
+        .collect();
+
+    Ok(pick(&[prefix.to_string()], &available).map(String::from))
+}
+
+/// Every published page of the page's translation group, the page included, for templates to link to.
+/// Empty when it has no translations.
+pub fn alternates(page: &Page, db: &DbConnection) -> Result<Vec<Translation>, diesel::result::Error> {
+    let group = match set(&page.translation_group) {
+        Some(group) => group,
+        None => return Ok(Vec::new()),
+    };
+    let others = live_translations(group, &page.uuid, db)?;
+    if others.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(std::iter::once(page)
+        .chain(others.iter())
+        .map(|p| Translation {
+            locale: set(&p.locale).unwrap_or(DEFAULT_HREFLANG).to_string(),
+            page_url: p.page_url.clone(),
+        })
+        .collect())
+}
+
+/// Refuses a second page in the same locale, or a second page without one, in a translation group.
+/// `page_id` is the page being changed, if it already exists.
+pub fn check(page_id: Option<&str>, locale: &Option<String>, group: &Option<String>, db: &DbConnection) -> Result<(), CustomHttpError> {
+    let group = match set(group) {
+        Some(group) => group,
+        None => return Ok(()),
+    };
+    let locale = set(locale);
+
+    let taken = Page::read_translations(group, db)?
+        .into_iter()
+        .filter(|p| Some(p.uuid.as_str()) != page_id)
+        .any(|p| match (set(&p.locale), locale) {
+            (Some(other), Some(locale)) => other.eq_ignore_ascii_case(locale),
+            (other, locale) => other.is_none() && locale.is_none(),
+        });
+    if !taken {
+        return Ok(());
+    }
+
+    Err(CustomHttpError::Invalid(vec![FieldError {
+        field: String::from("locale"),
+        message: match locale {
+            Some(locale) => format!("the translation group already has a page in {}", locale),
+            None => String::from("the translation group already has a page without a locale"),
+        },
+    }]))
+}
+
+/// The uuids of the other pages in any of `groups`, whose translations change along with the page's.
+pub fn translation_ids(page_id: &str, groups: &[&Option<String>], db: &DbConnection) -> Result<Vec<String>, diesel::result::Error> {
+    let mut ids = Vec::new();
+    for group in groups.iter().filter_map(|g| set(g)) {
+        for page in Page::read_translations(group, db)? {
+            if page.uuid != page_id && !ids.contains(&page.uuid) {
+                ids.push(page.uuid);
+            }
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Publishes the page's translations again, as they link to it.
+pub fn publish_translations(ids: Vec<String>, publish: &PublishQueue) {
+    for id in ids {
+        publish.send(PublishJob::Page(id));
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::auth_service::Claims;
+use super::encryption_service::{reveal_modules, reveals_to};
+use super::errors_service::CustomHttpError;
+use crate::models::module_models::Module;
+use crate::models::page_models::{Page, PageDTO};
+use crate::models::DbConnection;
+
+/// `?permanent=true` on a delete skips the trash, and what is deleted can't be restored.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+pub struct DeleteQuery {
+    #[serde(default)]
+    pub permanent: bool,
+}
+
+/// Everything in the trash. Modules that went in with their page aren't listed on their own.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct Trash {
+    pub pages: Vec<PageDTO>,
+    pub modules: Vec<Module>,
+}
+
+/// Encrypted modules are decrypted for admins (see `encryption_service::reveals_to`).
+pub fn read(claim: &Claims, db: &DbConnection) -> Result<Trash, CustomHttpError> {
+    Ok(Trash {
+        pages: Page::read_trashed(db)?,
+        modules: reveal_modules(Module::read_trashed(db)?, reveals_to(Some(claim)))?,
+    })
+}
@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+
+use diesel::OptionalExtension;
+
+use super::errors_service::CustomHttpError;
+use super::markdown_import_service::slugify;
+use super::publish_service::{PublishJob, PublishQueue};
+use crate::models::page_models::{Page, PageDTO};
+use crate::models::{DbConnection, Model};
+
+/// A page whose URL changed because a page above it moved.
+pub struct Moved {
+    pub uuid: String,
+    pub old_url: String,
+}
+
+/// The URL a page ends up at under `parent`. A `page_url` without a leading `/` is relative to the parent's,
+/// so `install` under `/docs/getting-started` is `/docs/getting-started/install`, and a full one has to be under the parent's.
+pub fn resolve_url(page_url: &str, parent: Option<&str>, db: &DbConnection) -> Result<String, CustomHttpError> {
+    let parent = match parent {
+        Some(parent) => parent,
+        None => return Ok(page_url.to_string()),
+    };
+
+    let parent: PageDTO = Page::read_one(parent.to_string(), db).optional()?.ok_or(CustomHttpError::BadRequest)?;
+    let base = parent.page_url.trim_end_matches('/');
+
+    if page_url.starts_with('/') {
+        return match page_url.strip_prefix(base).is_some_and(|rest| rest.len() > 1 && rest.starts_with('/')) {
+            true => Ok(page_url.to_string()),
+            false => Err(CustomHttpError::BadRequest),
+        };
+    }
+
+    match page_url.trim_matches('/') {
+        "" => Err(CustomHttpError::BadRequest),
+        segment => Ok(format!("{}/{}", base, segment)),
+    }
+}
+
+/// The `page_url` a new page that was sent without one gets from its title, which is relative to its parent if it has one.
+pub fn url_from_title(title: &str, parent: Option<&str>) -> String {
+    let slug = match slugify(title) {
+        slug if slug.is_empty() => String::from("page"),
+        slug => slug,
+    };
+
+    match parent {
+        Some(_) => slug,
+        None => format!("/{}", slug),
+    }
+}
+
+/// The first of `url`, `url-2`, `url-3`, and so on that no page is at.
+pub fn free_url(url: &str, db: &DbConnection) -> Result<String, CustomHttpError> {
+    let mut candidate = url.to_string();
+    let mut n = 1;
+    while Page::url_taken(&candidate, None, db)? {
+        n += 1;
+        candidate = format!("{}-{}", url, n);
+    }
+
+    Ok(candidate)
+}
+
+/// Refuses a URL that a page other than `page` is already at with a 409, as only one of them could be served there.
+pub fn check_url_free(url: &str, page: Option<&str>, db: &DbConnection) -> Result<(), CustomHttpError> {
+    match Page::url_taken(url, page, db)? {
+        true => Err(CustomHttpError::Conflict),
+        false => Ok(()),
+    }
+}
+
+/// Refuses to nest a page under itself or under a page nested under it, as that would make a loop.
+pub fn check_parent(id: &str, parent: &str, db: &DbConnection) -> Result<(), CustomHttpError> {
+    let mut seen = HashSet::new();
+    let mut current = Some(parent.to_string());
+
+    while let Some(ancestor) = current {
+        if ancestor == id {
+            return Err(CustomHttpError::BadRequest);
+        }
+        if !seen.insert(ancestor.clone()) {
+            break;
+        }
+
+        current = Page::read_any(ancestor, db).optional()?.and_then(|p| p.parent_page);
+    }
+
+    Ok(())
+}
+
+/// The URL of a page after moving it under `parent`, which keeps the last part of its own URL.
+pub fn rebase_url(page_url: &str, parent: Option<&str>, db: &DbConnection) -> Result<String, CustomHttpError> {
+    if parent.is_none() {
+        return Ok(page_url.to_string());
+    }
+
+    resolve_url(page_url.rsplit('/').next().unwrap_or_default(), parent, db)
+}
+
+/// Gives every page under `id` the page's new URL in place of its old one, after it went from `old_url` to `new_url`.
+pub fn move_descendants(
+    id: String,
+    old_url: &str,
+    new_url: &str,
+    db: &DbConnection,
+) -> Result<Vec<Moved>, diesel::result::Error> {
+    if old_url == new_url {
+        return Ok(Vec::new());
+    }
+
+    let (old_base, new_base) = (old_url.trim_end_matches('/'), new_url.trim_end_matches('/'));
+    let tree = Page::read_tree(id, db)?;
+
+    let mut moved = Vec::new();
+    for page in tree.descendants() {
+        let rest = match page.page_url.strip_prefix(old_base) {
+            Some(rest) if rest.starts_with('/') => rest,
+            _ => continue,
+        };
+
+        Page::set_url(page.uuid.clone(), &format!("{}{}", new_base, rest), db)?;
+        moved.push(Moved {
+            uuid: page.uuid.clone(),
+            old_url: page.page_url.clone(),
+        });
+    }
+
+    Ok(moved)
+}
+
+/// Takes down each moved page's old object and publishes it at its new URL.
+pub fn publish_moved(moved: Vec<Moved>, publish: &PublishQueue) {
+    for page in moved {
+        publish.send(PublishJob::Removed(page.old_url));
+        publish.send(PublishJob::Page(page.uuid));
+    }
+}
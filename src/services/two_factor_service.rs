@@ -0,0 +1,212 @@
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sha1::Sha1;
+use uuid::Uuid;
+
+use super::delivery_service::hash_token;
+use super::errors_service::CustomHttpError;
+use crate::models::recovery_code_models::{MutRecoveryCode, RecoveryCode};
+use crate::models::user_models::User;
+use crate::models::DbConnection;
+
+// Codes are the TOTP of RFC 6238 with the defaults authenticator apps assume: HMAC-SHA1, 6 digits, and 30 second steps.
+
+/// What authenticator apps show the account under.
+const ISSUER: &str = "Radical";
+const STEP: i64 = 30;
+/// How many steps a code can be off by either way, for clocks that are a little off.
+const SKEW: i64 = 1;
+/// How many recovery codes a user is given.
+const RECOVERY_CODES: usize = 10;
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+
+    encoded
+}
+
+/// Spaces, padding, and lowercase letters are allowed, as apps show secrets in groups.
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+
+    for c in encoded.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32.iter().position(|b| *b as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+/// A new secret for an authenticator app, as base32.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    OsRng.fill_bytes(&mut bytes);
+
+    base32_encode(&bytes)
+}
+
+/// The URI authenticator apps take the secret from, usually as a QR code.
+pub fn otpauth_uri(username: &str, secret: &str) -> String {
+    let label: String = url::form_urlencoded::byte_serialize(format!("{}:{}", ISSUER, username).as_bytes()).collect();
+
+    format!("otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits=6&period={}", label, secret, ISSUER, STEP)
+}
+
+fn code_at(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).unwrap();
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[19] & 0xf) as usize;
+    let value = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]) & 0x7fff_ffff;
+
+    value % 1_000_000
+}
+
+/// The time step `code` was made for, if it is one of the secret's codes from around now,
+/// and from after `last_step` so that a code that was used already isn't taken again.
+pub fn check_code(secret: &str, code: &str, last_step: Option<i64>) -> Option<i64> {
+    let secret = base32_decode(secret)?;
+    let code = code.trim();
+    if code.len() != 6 {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+    let now = chrono::Utc::now().timestamp() / STEP;
+
+    (now - SKEW..=now + SKEW)
+        .filter(|step| last_step.is_none_or(|last| *step > last))
+        .find(|step| code_at(&secret, *step) == code)
+}
+
+/// Recovery codes are hashed without `-`s or case, so that they can be typed however they were written down.
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+
+    hash_token(&normalized.to_ascii_lowercase())
+}
+
+/// Gives the user new recovery codes in place of the ones they had, and returns them. Only their hashes are kept.
+pub fn replace_recovery_codes(user: &User, db: &DbConnection) -> Result<Vec<String>, CustomHttpError> {
+    let codes: Vec<String> = (0..RECOVERY_CODES)
+        .map(|_| {
+            let mut bytes = [0u8; 10];
+            OsRng.fill_bytes(&mut bytes);
+            let code = base32_encode(&bytes).to_ascii_lowercase();
+
+            format!("{}-{}-{}-{}", &code[..4], &code[4..8], &code[8..12], &code[12..])
+        })
+        .collect();
+
+    let rows: Vec<MutRecoveryCode> = codes
+        .iter()
+        .map(|code| MutRecoveryCode {
+            uuid: Uuid::new_v4().to_string(),
+            user_uuid: user.uuid.clone(),
+            code_hash: hash_recovery_code(code),
+        })
+        .collect();
+    RecoveryCode::replace_for_user(&user.uuid, &rows, db)?;
+
+    Ok(codes)
+}
+
+/// Whether `code` is one from the user's authenticator app or one of their recovery codes, which is used up if it is.
+pub fn verify(user: &User, code: &str, db: &DbConnection) -> Result<bool, CustomHttpError> {
+    let secret = match &user.totp_secret {
+        Some(secret) => secret,
+        None => return Ok(false),
+    };
+
+    if let Some(step) = check_code(secret, code, user.totp_last_step) {
+        User::set_totp_last_step(user.username.clone(), step, db)?;
+        return Ok(true);
+    }
+
+    Ok(RecoveryCode::consume(&user.uuid, &hash_recovery_code(code), db)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The secret of RFC 6238's SHA-1 test vectors, `12345678901234567890`, as base32.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn code_now(secret: &str, offset: i64) -> (String, i64) {
+        let step = chrono::Utc::now().timestamp() / STEP + offset;
+
+        (format!("{:06}", code_at(&base32_decode(secret).unwrap(), step)), step)
+    }
+
+    #[test]
+    fn makes_the_codes_of_rfc_6238() {
+        let secret = base32_decode(RFC_SECRET).unwrap();
+
+        assert_eq!(secret, b"12345678901234567890");
+        assert_eq!(code_at(&secret, 59 / STEP), 287_082);
+        assert_eq!(code_at(&secret, 1_111_111_109 / STEP), 81_804);
+        assert_eq!(code_at(&secret, 2_000_000_000 / STEP), 279_037);
+    }
+
+    #[test]
+    fn decodes_secrets_as_apps_show_them() {
+        let grouped = "gezd gnbv gy3t qojq gezd gnbv gy3t qojq";
+
+        assert_eq!(base32_decode(grouped), base32_decode(RFC_SECRET));
+        assert_eq!(base32_decode("GEZDGNBV===="), base32_decode("GEZDGNBV"));
+        assert_eq!(base32_decode("GEZ1"), None);
+        assert_eq!(base32_decode(&base32_encode(b"radical")), Some(b"radical".to_vec()));
+    }
+
+    #[test]
+    fn takes_a_code_from_around_now() {
+        for offset in -SKEW..=SKEW {
+            let (code, step) = code_now(RFC_SECRET, offset);
+
+            assert_eq!(check_code(RFC_SECRET, &code, None), Some(step));
+            assert_eq!(check_code(RFC_SECRET, &format!(" {} ", code), None), Some(step));
+        }
+    }
+
+    #[test]
+    fn refuses_a_code_that_was_used_already() {
+        let (code, step) = code_now(RFC_SECRET, 0);
+
+        assert_eq!(check_code(RFC_SECRET, &code, Some(step)), None);
+        assert_eq!(check_code(RFC_SECRET, &code, Some(step - 1)), Some(step));
+    }
+
+    #[test]
+    fn refuses_codes_that_arent_the_secrets() {
+        let (code, _) = code_now(RFC_SECRET, SKEW + 2);
+        let (other, _) = code_now("JBSWY3DPEHPK3PXP", 0);
+
+        assert_eq!(check_code(RFC_SECRET, &code, None), None);
+        assert_eq!(check_code(RFC_SECRET, &other, None), None);
+        assert_eq!(check_code(RFC_SECRET, "12345", None), None);
+        assert_eq!(check_code(RFC_SECRET, "1234567", None), None);
+        assert_eq!(check_code(RFC_SECRET, "abcdef", None), None);
+        assert_eq!(check_code("not base32!", "123456", None), None);
+    }
+}
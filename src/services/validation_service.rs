@@ -0,0 +1,372 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::content_type_service::schema_errors;
+use super::errors_service::CustomHttpError;
+use super::translation_service::is_locale;
+use super::visibility_service::Visibility;
+use crate::models::comment_models::{is_comment_status, MutCommentStatus, NewComment};
+use crate::models::content_type_models::NewContentType;
+use crate::models::form_models::{NewForm, FIELD_TYPES};
+use crate::models::menu_models::{NewMenu, NewMenuItem};
+use crate::models::module_models::{ModuleType, MutCategory, MutModule};
+use crate::models::page_models::{is_status, MutPage};
+use crate::models::redirect_models::{NewRedirect, STATUS_CODES};
+use crate::models::site_models::NewSite;
+use crate::models::taxonomy_models::NewTerm;
+
+/// The most a `varchar(255)` column holds.
+const MAX_LENGTH: usize = 255;
+/// The most a column for a URL holds, like `og_image`, which is longer so that URLs with a query string fit.
+const MAX_URL_LENGTH: usize = 1024;
+/// The most the `locale` column holds, which the longest language tags in use fit in.
+const MAX_LOCALE_LENGTH: usize = 35;
+/// The longest comment a visitor can leave.
+const MAX_COMMENT_LENGTH: usize = 5000;
+
+/// What is wrong with one field of a request body.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Checks a request body before it reaches a `Model`, finding every problem with it rather than only the first.
+pub trait Validate {
+    fn errors(&self) -> Vec<FieldError>;
+
+    /// A 422 listing every field that is wrong, if any are.
+    fn validate(&self) -> Result<(), CustomHttpError> {
+        let errors = self.errors();
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(CustomHttpError::Invalid(errors)),
+        }
+    }
+}
+
+/// Collects the problems of a body as it is checked.
+#[derive(Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn add(&mut self, field: &str, message: &str) {
+        self.0.push(FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    /// No more than `max` characters, when it is set at all.
+    fn at_most(&mut self, field: &str, value: Option<&str>, max: usize) {
+        if value.is_some_and(|v| v.chars().count() > max) {
+            self.add(field, &format!("can't be longer than {} characters", max));
+        }
+    }
+
+    fn required(&mut self, field: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.add(field, "can't be empty");
+        } else if value.chars().count() > MAX_LENGTH {
+            self.add(field, "can't be longer than 255 characters");
+        }
+    }
+}
+
+/// A name like `product` or `blog_post`, which is what modules of a content type have as their `module_type`.
+fn is_type_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+/// A path like `/blog/post`, or `post` under a parent page. Query strings, fragments, and `.` or `..` segments
+/// can't be part of a page's URL, as requests for it would never match it.
+/// The segments of a pattern, like `/blog/:slug` or `/docs/*`, are named with letters, digits, and `_`.
+fn is_url_pattern(url: &str) -> bool {
+    let is_name = |name: &str| name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let segments: Vec<&str> = url.split('/').filter(|s| !s.is_empty()).collect();
+
+    segments.iter().enumerate().all(|(i, segment)| match segment.strip_prefix(':') {
+        Some(name) => !name.is_empty() && is_name(name),
+        None => match segment.strip_prefix('*') {
+            Some(name) => i == segments.len() - 1 && is_name(name),
+            None => true,
+        },
+    })
+}
+
+fn is_url_path(url: &str) -> bool {
+    let allowed = |c: char| !c.is_whitespace() && !c.is_control() && !"?#\\<>\"{}|^`".contains(c);
+    let segments: Vec<&str> = url.trim_start_matches('/').trim_end_matches('/').split('/').collect();
+
+    url.chars().all(allowed)
+        && !url.contains("//")
+        && (url.trim_matches('/').is_empty() || segments.iter().all(|s| !s.is_empty() && *s != "." && *s != ".."))
+}
+
+/// A path on the site or an http(s) URL, which can be sent as a `Location` header or put in an `href` as it is.
+/// Headers can only hold ASCII, so anything else has to be percent encoded.
+fn is_link(target: &str) -> bool {
+    ["/", "http://", "https://"].iter().any(|p| target.starts_with(p)) && target.chars().all(|c| c.is_ascii_graphic())
+}
+
+impl Validate for MutPage {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("page_name", &self.page_name);
+        errors.required("page_title", &self.page_title);
+        // an empty one is made from the title.
+        if !self.page_url.is_empty() && !is_url_path(&self.page_url) {
+            errors.add("page_url", "has to be a path, like /blog/post, without a query string or fragment");
+        } else if !is_url_pattern(&self.page_url) {
+            errors.add("page_url", "can only have :name segments, like /blog/:slug, and a * or *name at the end");
+        }
+        if !self.status.as_deref().is_none_or(is_status) {
+            errors.add("status", "has to be draft or published");
+        }
+        if self.visibility.as_deref().is_some_and(|v| Visibility::parse(v).is_none()) {
+            errors.add("visibility", "has to be public, authenticated, or role:viewer, role:editor, or role:admin");
+        }
+        if self.parent_page.as_deref().is_some_and(|p| p.trim().is_empty()) {
+            errors.add("parent_page", "can't be empty");
+        }
+        errors.at_most("meta_keywords", self.meta_keywords.as_deref(), MAX_LENGTH);
+        errors.at_most("og_image", self.og_image.as_deref(), MAX_URL_LENGTH);
+        // empty clears it, and anything else has to be something a link preview can load.
+        let image = self.og_image.as_deref().unwrap_or_default();
+        if !image.is_empty() && !["/", "http://", "https://"].iter().any(|p| image.starts_with(p)) {
+            errors.add("og_image", "has to be a path, like /api/v1/media/<uuid>, or an http(s) URL");
+        }
+        let locale = self.locale.as_deref().unwrap_or_default();
+        if !locale.is_empty() && !is_locale(locale) {
+            errors.add("locale", "has to be a language tag, like fr or pt-BR");
+        }
+        errors.at_most("locale", Some(locale), MAX_LOCALE_LENGTH);
+        if self.translation_group.as_deref().is_some_and(|g| !g.is_empty() && g.trim().is_empty()) {
+            errors.add("translation_group", "can't be only whitespace");
+        }
+        errors.at_most("translation_group", self.translation_group.as_deref(), MAX_LENGTH);
+
+        errors.0
+    }
+}
+
+impl Validate for MutModule {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("title", &self.title);
+        // a module without a page is global, and categories are always of a page.
+        match &self.page_uuid {
+            Some(page_uuid) => errors.required("page_uuid", page_uuid),
+            None if self.category_uuid.is_some() => errors.add("category_uuid", "can't be set on a global module"),
+            None => {}
+        }
+        // whether a content type of the name exists is checked against its schema, along with the content.
+        if self.module_type.as_deref().is_some_and(|t| ModuleType::parse(t).is_none() && !is_type_name(t)) {
+            errors.add("module_type", "has to be text, rich_text, image, boolean, json, or the name of a content type");
+        }
+
+        errors.0
+    }
+}
+
+impl Validate for MutCategory {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("title", &self.title);
+        errors.required("page_uuid", &self.page_uuid);
+
+        errors.0
+    }
+}
+
+impl Validate for NewTerm {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("name", &self.name);
+        // slugs go into URLs as they are, like `/api/v1/tags/<slug>/pages`.
+        let slug = self.slug();
+        let allowed = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-';
+        if slug.is_empty() && self.slug.is_none() {
+            errors.add("slug", "can't be made from the name, so it has to be set");
+        } else if slug.is_empty() || !slug.chars().all(allowed) {
+            errors.add("slug", "can only have lowercase letters, digits, and -");
+        }
+        errors.at_most("slug", Some(&slug), MAX_LENGTH);
+
+        errors.0
+    }
+}
+
+impl Validate for NewRedirect {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("source", &self.source);
+        if !self.source.starts_with('/') || !is_url_path(&self.source) {
+            errors.add("source", "has to be a path, like /blog/old-post, without a query string or fragment");
+        }
+        let target = self.target.as_str();
+        if !is_link(target) {
+            errors.add("target", "has to be a path, like /blog/new-post, or an http(s) URL, with anything other than ASCII percent encoded");
+        }
+        errors.at_most("target", Some(target), MAX_URL_LENGTH);
+        if target.trim_end_matches('/') == self.source.trim_end_matches('/') {
+            errors.add("target", "can't be the same as the source");
+        }
+        if self.status_code.is_some_and(|c| !STATUS_CODES.contains(&c)) {
+            errors.add("status_code", "has to be 301 or 302");
+        }
+
+        errors.0
+    }
+}
+
+impl Validate for NewSite {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("name", &self.name);
+        errors.at_most("name", Some(&self.name), MAX_LENGTH);
+        // requests are matched to the site by their `Host`, which never has anything else in it.
+        if let Some(hostname) = &self.hostname {
+            let label = |l: &str| !l.is_empty() && !l.starts_with('-') && !l.ends_with('-') && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !hostname.split('.').all(label) {
+                errors.add("hostname", "has to be a hostname, like blog.example.com, without a scheme or port");
+            }
+            errors.at_most("hostname", Some(hostname), MAX_LENGTH);
+        }
+        for (field, limit) in [("max_pages", self.max_pages), ("max_modules", self.max_modules), ("max_media_bytes", self.max_media_bytes)] {
+            if limit.is_some_and(|limit| limit < 0) {
+                errors.add(field, "can't be negative");
+            }
+        }
+
+        errors.0
+    }
+}
+
+impl Validate for NewComment {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("author_name", &self.author_name);
+        if self.body.trim().is_empty() {
+            errors.add("body", "can't be empty");
+        }
+        errors.at_most("body", Some(&self.body), MAX_COMMENT_LENGTH);
+        let email = self.author_email.as_deref().unwrap_or_default();
+        if !email.is_empty() && !email.contains('@') {
+            errors.add("author_email", "has to be an email address");
+        }
+        errors.at_most("author_email", Some(email), MAX_LENGTH);
+
+        errors.0
+    }
+}
+
+impl Validate for NewForm {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("name", &self.name);
+        // forms are sent to by name, like `/api/v1/forms/contact/submit`.
+        if !self.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            errors.add("name", "can only have letters, digits, _, and -");
+        }
+        if self.fields.is_empty() {
+            errors.add("fields", "has to have at least one field");
+        }
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let at = |name: &str| format!("fields.{}.{}", i, name);
+
+            errors.required(&at("name"), &field.name);
+            // names starting with `_` are left out of submissions, like the honeypot.
+            if field.name.starts_with('_') {
+                errors.add(&at("name"), "can't start with _");
+            }
+            if self.fields[..i].iter().any(|f| f.name == field.name) {
+                errors.add(&at("name"), "is the name of another field");
+            }
+            errors.at_most(&at("label"), field.label.as_deref(), MAX_LENGTH);
+            if !FIELD_TYPES.contains(&field.field_type.as_str()) {
+                errors.add(&at("type"), &format!("has to be one of {}", FIELD_TYPES.join(", ")));
+            }
+            match field.field_type == "select" {
+                true if field.options.is_empty() => errors.add(&at("options"), "has to have at least one option"),
+                false if !field.options.is_empty() => errors.add(&at("options"), "is only for select fields"),
+                _ => {}
+            }
+            if field.max_length == Some(0) {
+                errors.add(&at("max_length"), "has to be at least 1");
+            }
+        }
+
+        errors.0
+    }
+}
+
+impl Validate for MutCommentStatus {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        if !is_comment_status(&self.status) {
+            errors.add("status", "has to be pending, approved, or spam");
+        }
+
+        errors.0
+    }
+}
+
+impl Validate for NewContentType {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("name", &self.name);
+        if !self.name.is_empty() && !is_type_name(&self.name) {
+            errors.add("name", "can only have lowercase letters, digits, _, and -");
+        } else if ModuleType::parse(&self.name).is_some() {
+            errors.add("name", "can't be the name of a built in type");
+        }
+        errors.0.extend(schema_errors("schema", &self.schema));
+
+        errors.0
+    }
+}
+
+impl Validate for NewMenu {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("name", &self.name);
+        // templates look menus up by name, like `menus.main`.
+        if !self.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            errors.add("name", "can only have letters, digits, _, and -");
+        }
+
+        errors.0
+    }
+}
+
+impl Validate for NewMenuItem {
+    fn errors(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        errors.required("title", &self.title);
+        match (&self.page_uuid, &self.url) {
+            (Some(_), Some(_)) | (None, None) => errors.add("url", "has to be set, or page_uuid, but not both"),
+            (None, Some(url)) if !is_link(url) => {
+                errors.add("url", "has to be a path, like /contact, or an http(s) URL, with anything other than ASCII percent encoded")
+            }
+            _ => {}
+        }
+        errors.at_most("url", self.url.as_deref(), MAX_URL_LENGTH);
+
+        errors.0
+    }
+}
@@ -0,0 +1,52 @@
+use actix_web::http::header;
+use actix_web::HttpRequest;
+use serde::Deserialize;
+
+use super::errors_service::CustomHttpError;
+
+/// The body of an update of a page or module, along with the `version` of it the update was made from.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Versioned<T> {
+    #[serde(flatten)]
+    pub body: T,
+    #[serde(default)]
+    pub version: Option<i32>,
+}
+
+/// Which version of a page or module an update was made from, so that it isn't saved over a change someone else saved
+/// since. Two editors would otherwise silently undo each other's changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precondition {
+    /// `If-Match: *`, for clients that mean to overwrite whatever is there.
+    Any,
+    Version(i32),
+}
+
+impl Precondition {
+    /// From the request's `If-Match`, like `"3"`, or else the `version` of its body. An update with neither is a 428.
+    pub fn of(req: &HttpRequest, version: Option<i32>) -> Result<Self, CustomHttpError> {
+        let if_match = match req.headers().get(header::IF_MATCH) {
+            Some(if_match) => if_match.to_str().map_err(|_| CustomHttpError::BadRequest)?.trim(),
+            None => return version.map(Self::Version).ok_or(CustomHttpError::PreconditionRequired),
+        };
+
+        if if_match == "*" {
+            return Ok(Self::Any);
+        }
+
+        if_match
+            .trim_start_matches("W/")
+            .trim_matches('"')
+            .parse()
+            .map(Self::Version)
+            .map_err(|_| CustomHttpError::BadRequest)
+    }
+
+    /// Refuses the update with a 409 when the row isn't at the version anymore.
+    pub fn check(&self, current: i32) -> Result<(), CustomHttpError> {
+        match self {
+            Self::Version(version) if *version != current => Err(CustomHttpError::Stale),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+
+use super::auth_service::Claims;
+use super::errors_service::CustomHttpError;
+use super::pagination_service::{list, Listing, PageQuery, Paginated};
+use crate::models::module_models::{FieldsDTO, Module};
+use crate::models::page_models::{Page, PageDTO, PUBLIC};
+use crate::models::user_models::UserRole;
+use crate::models::{DbConnection, Model};
+
+const AUTHENTICATED: &str = "authenticated";
+const ROLE_PREFIX: &str = "role:";
+
+/// Who a response is for. Everyone who is signed in sees every module, whatever their user's role.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    Public,
+    Admin,
+}
+
+impl Role {
+    pub fn of(claim: &Option<Claims>) -> Self {
+        match claim {
+            Some(_) => Self::Admin,
+            None => Self::Public,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+/// Who can see a page, from its `visibility`. Pages that aren't public are only served to users who log in, whether as
+/// rendered pages or through the management API, and are left out of everything the public is given, like the delivery
+/// API, the sitemap, search, and static targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visibility {
+    Public,
+    /// Every user who is logged in, whatever their role.
+    Authenticated,
+    /// Users with at least the role, like `role:editor`.
+    Role(UserRole),
+}
+
+impl Visibility {
+    pub fn parse(visibility: &str) -> Option<Self> {
+        match visibility {
+            PUBLIC => Some(Self::Public),
+            AUTHENTICATED => Some(Self::Authenticated),
+            _ => visibility.strip_prefix(ROLE_PREFIX).and_then(UserRole::parse).map(Self::Role),
+        }
+    }
+
+    pub fn as_string(&self) -> String {
+        match self {
+            Self::Public => String::from(PUBLIC),
+            Self::Authenticated => String::from(AUTHENTICATED),
+            Self::Role(role) => format!("{}{}", ROLE_PREFIX, role.as_str()),
+        }
+    }
+
+    pub fn allows(&self, claim: &Option<Claims>) -> bool {
+        match (self, claim) {
+            (Self::Public, _) => true,
+            (_, None) => false,
+            (Self::Authenticated, Some(_)) => true,
+            (Self::Role(role), Some(claim)) => claim.role >= *role,
+        }
+    }
+}
+
+/// A visibility that isn't understood, like one written to the database by hand, only lets admins see the page.
+pub fn can_view(visibility: &str, claim: &Option<Claims>) -> bool {
+    Visibility::parse(visibility).unwrap_or(Visibility::Role(UserRole::Admin)).allows(claim)
+}
+
+/// Visitors who aren't logged in are told to with a 401, and users whose role is too low get a 403.
+pub fn require_viewable(visibility: &str, claim: &Option<Claims>) -> Result<(), CustomHttpError> {
+    match (can_view(visibility, claim), claim) {
+        (true, _) => Ok(()),
+        (false, None) => Err(CustomHttpError::Unauthorized),
+        (false, Some(_)) => Err(CustomHttpError::Forbidden),
+    }
+}
+
+/// The visibilities of the pages `claim` can see, or `None` when it can see every page, like an admin can.
+pub fn viewable(claim: &Option<Claims>) -> Option<Vec<String>> {
+    let role = match claim {
+        Some(claim) if claim.role == UserRole::Admin => return None,
+        Some(claim) => claim.role,
+        None => return Some(vec![String::from(PUBLIC)]),
+    };
+
+    let roles = [UserRole::Viewer, UserRole::Editor, UserRole::Admin];
+    let allowed = roles.iter().filter(|r| **r <= role).map(|r| Visibility::Role(*r));
+    let visibilities = [Visibility::Public, Visibility::Authenticated].iter().copied().chain(allowed);
+
+    Some(visibilities.map(|v| v.as_string()).collect())
+}
+
+/// The pages with one of `visibilities` (see `viewable`), whole or one page of them like `list`.
+pub fn list_viewable(query: &PageQuery, visibilities: Option<&[String]>, db: &DbConnection) -> Result<Listing<PageDTO>, CustomHttpError> {
+    let visibilities = match visibilities {
+        Some(visibilities) => visibilities,
+        None => return list(query, db, Page::read_all, Page::read_paginated),
+    };
+    if !query.is_requested() {
+        return Ok(Listing::All(Page::read_visible(visibilities, db)?));
+    }
+
+    let (offset, limit) = query.window()?;
+    let (data, total) = Page::read_visible_paginated(visibilities, offset, limit, db)?;
+
+    Ok(Listing::Page(Paginated::new(data, total, query)))
+}
+
+/// Modules on pages without one of `visibilities` are left out, like the pages are. Global modules are on every page,
+/// so they stay.
+pub fn strip_hidden_pages(modules: Vec<Module>, visibilities: Option<&[String]>, db: &DbConnection) -> Result<Vec<Module>, CustomHttpError> {
+    let hidden: HashSet<String> = match visibilities {
+        Some(visibilities) => Page::read_hidden(visibilities, db)?.into_iter().collect(),
+        None => return Ok(modules),
+    };
+
+    Ok(modules.into_iter().filter(|m| m.page_uuid.as_ref().is_none_or(|p| !hidden.contains(p))).collect())
+}
+
+/// `require_viewable` for the page a module is on.
+pub fn require_viewable_module(module: &Module, claim: &Option<Claims>, db: &DbConnection) -> Result<(), CustomHttpError> {
+    match &module.page_uuid {
+        Some(page_uuid) => require_viewable(&Page::read_visibility(page_uuid, db)?, claim),
+        None => Ok(()),
+    }
+}
+
+/// Admins can see every module, as they are the ones managing them.
+pub fn is_visible(module: &Module, role: Role) -> bool {
+    let roles = match module.visible_to.as_deref().map(str::trim) {
+        None | Some("") => return true,
+        Some(roles) => roles,
+    };
+
+    role == Role::Admin || roles.split(',').any(|r| r.trim() == role.as_str())
+}
+
+/// Hidden modules are reported as missing, the same as ones that don't exist.
+pub fn require_visible(module: Module, role: Role) -> Result<Module, CustomHttpError> {
+    match is_visible(&module, role) {
+        true => Ok(module),
+        false => Err(CustomHttpError::NotFound),
+    }
+}
+
+/// Drafts are reported as missing to the public, along with everything on them.
+pub fn require_published(page_uuid: &str, db: &DbConnection) -> Result<(), CustomHttpError> {
+    match Page::is_published(page_uuid, db)? {
+        true => Ok(()),
+        false => Err(CustomHttpError::NotFound),
+    }
+}
+
+/// Modules on a draft are reported as missing to the public, like the draft is. Global modules are shown on every
+/// published page, so they always are.
+pub fn require_published_module(module: &Module, db: &DbConnection) -> Result<(), CustomHttpError> {
+    match &module.page_uuid {
+        Some(page_uuid) => require_published(page_uuid, db),
+        None => Ok(()),
+    }
+}
+
+pub fn strip_modules(modules: Vec<Module>, role: Role) -> Vec<Module> {
+    modules.into_iter().filter(|m| is_visible(m, role)).collect()
+}
+
+/// Removes the modules `role` can't see from a page's fields, along with their galleries.
+pub fn strip_fields(mut fields: FieldsDTO, role: Role) -> FieldsDTO {
+    fields.modules = strip_modules(fields.modules, role);
+
+    if let Some(categories) = fields.categories.as_mut() {
+        for category in categories.iter_mut() {
+            category.modules = strip_modules(std::mem::take(&mut category.modules), role);
+        }
+    }
+
+    let visible: HashSet<&String> = fields
+        .modules
+        .iter()
+        .chain(fields.categories.iter().flatten().flat_map(|c| c.modules.iter()))
+        .map(|m| &m.uuid)
+        .collect();
+    let hidden: Vec<String> = fields.galleries.keys().filter(|id| !visible.contains(id)).cloned().collect();
+    for id in hidden {
+        fields.galleries.remove(&id);
+    }
+
+    fields
+}
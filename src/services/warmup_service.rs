@@ -0,0 +1,133 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use actix_web::web;
+use handlebars::Handlebars;
+use serde::Serialize;
+
+use super::errors_service::CustomHttpError;
+use super::page_cache_service::PageCache;
+use super::render_service::render_page_with;
+use super::route_service::RouteTable;
+use super::settings_service::SettingsCache;
+use super::site_service::{self, DEFAULT_SITE};
+use crate::models::config_models::LocalConfig;
+use crate::models::page_models::{Page, PUBLIC};
+use crate::models::{DbConnection, DbPool};
+
+/// The paths probes and scrapers are sent to, which are answered no matter the site, and never limited.
+pub const PROBES: &[&str] = &["/healthz", "/readyz", "/metrics"];
+
+/// Whether the server has finished warming up, and hasn't started stopping, so it should be sent traffic. Reported at `/readyz`.
+#[derive(Default)]
+pub struct Readiness {
+    ready: AtomicBool,
+    stopping: AtomicBool,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed) && !self.stopping.load(Ordering::Relaxed)
+    }
+
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::Relaxed);
+    }
+
+    pub fn mark_stopping(&self) {
+        self.stopping.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct WarmupReport {
+    pub routes: usize,
+    pub settings: usize,
+    pub pages: usize,
+    pub time_ms: u128,
+}
+
+/// The URLs from `APP_WARMUP_URLS`, or just the home page.
+fn hot_urls(conf: &LocalConfig) -> Vec<String> {
+    conf.warmup_urls
+        .as_deref()
+        .unwrap_or("/")
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect()
+}
+
+/// Loads the route table and settings into their caches, and renders the hot pages into the page cache.
+/// Hot URLs that aren't public pages are skipped.
+pub fn warm(
+    conf: &LocalConfig,
+    routes: &RouteTable,
+    settings: &SettingsCache,
+    pages: &PageCache,
+    hb: &Mutex<Handlebars<'static>>,
+    db: &DbConnection,
+) -> Result<WarmupReport, CustomHttpError> {
+    let started = Instant::now();
+    let mut report = WarmupReport {
+        routes: routes.count(db)?,
+        settings: settings.all(db)?.len(),
+        ..WarmupReport::default()
+    };
+
+    if pages.is_enabled() {
+        for url in hot_urls(conf) {
+            let route = match routes.resolve(&url, db)? {
+                Some(route) => route,
+                None => continue,
+            };
+            // only public pages are cached, as the cache is shared by everyone.
+            if Page::read_one_by_url(route.page_url.clone(), db)?.visibility != PUBLIC {
+                continue;
+            }
+
+            let html = render_page_with(route, None, db, &hb.lock().unwrap())?;
+            pages.set(&url, &html);
+            report.pages += 1;
+        }
+    }
+
+    report.time_ms = started.elapsed().as_millis();
+
+    Ok(report)
+}
+
+/// Warms up if `APP_WARMUP` is on, then marks the server as ready.
+/// A failed warmup still ends in ready, as the caches fill up on their own anyway, just more slowly.
+pub fn run(
+    pool: DbPool,
+    conf: LocalConfig,
+    routes: web::Data<RouteTable>,
+    settings: web::Data<SettingsCache>,
+    pages: web::Data<PageCache>,
+    hb: web::Data<Mutex<Handlebars<'static>>>,
+    readiness: web::Data<Readiness>,
+) {
+    if conf.warmup {
+        // the hot URLs are the default site's.
+        let res = pool
+            .get()
+            .map_err(|_| CustomHttpError::Unknown)
+            .and_then(|db| site_service::scoped(Some(String::from(DEFAULT_SITE)), || warm(&conf, &routes, &settings, &pages, &hb, &db)));
+
+        match res {
+            Ok(report) => tracing::info!(
+                "Warmed up {} routes, {} settings, and {} pages in {}ms.",
+                report.routes, report.settings, report.pages, report.time_ms
+            ),
+            Err(e) => tracing::warn!("Warmup failed: {}", e),
+        }
+    }
+
+    readiness.mark_ready();
+}
@@ -0,0 +1,196 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::web;
+use chrono::Utc;
+use diesel::result::Error::NotFound;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use super::inbound_webhook_service::SIGNATURE_HEADER;
+use super::job_service::{JobQueue, NewJob};
+use super::mail_service::MailQueue;
+use crate::models::job_models::Job;
+use crate::models::webhook_models::Webhook;
+use crate::models::{DbPool, Model};
+
+pub const EVENT_HEADER: &str = "X-Radical-Event";
+/// The same for every attempt at a delivery, so receivers can tell a retry from a new event.
+pub const DELIVERY_HEADER: &str = "X-Radical-Delivery";
+
+/// The kind of the jobs that deliver an event to a webhook.
+pub const JOB: &str = "webhook.deliver";
+/// Receivers that take longer than this are counted as failed.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something that happened to content, like `page.created`, along with what it happened to.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    pub event: &'static str,
+    pub time: chrono::NaiveDateTime,
+    pub data: serde_json::Value,
+}
+
+impl WebhookEvent {
+    pub fn new(event: &'static str, data: impl Serialize) -> Self {
+        Self {
+            event,
+            time: Utc::now().naive_utc(),
+            data: serde_json::to_value(data).unwrap_or_default(),
+        }
+    }
+
+    /// For deletes, where all that is left of the item is its uuid.
+    pub fn deleted(event: &'static str, uuid: &str) -> Self {
+        Self::new(event, serde_json::json!({ "uuid": uuid }))
+    }
+}
+
+/// What sees every event as it is sent, like the live sockets.
+type Hook = Box<dyn Fn(&WebhookEvent) + Send + Sync>;
+
+/// Where the controllers send events after a change has been saved, for `run` to turn into deliveries.
+pub struct WebhookQueue {
+    sender: Mutex<Option<Sender<WebhookEvent>>>,
+    hooks: Vec<Hook>,
+}
+
+impl WebhookQueue {
+    pub fn new() -> (Self, Receiver<WebhookEvent>) {
+        let (sender, receiver) = channel();
+
+        (
+            Self {
+                sender: Mutex::new(Some(sender)),
+                hooks: Vec::new(),
+            },
+            receiver,
+        )
+    }
+
+    /// Runs `hook` on every event as it is sent, in the request that made the change, whether or not a webhook wants it.
+    pub fn hook(&mut self, hook: impl Fn(&WebhookEvent) + Send + Sync + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    pub fn send(&self, event: WebhookEvent) {
+        for hook in &self.hooks {
+            hook(&event);
+        }
+        // the worker only stops along with the server, so there is no one to report a failed send to.
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            sender.send(event).ok();
+        }
+    }
+
+    /// Stops taking events, so that `run` queues the deliveries of what it has and returns. Used when the server stops.
+    pub fn close(&self) {
+        self.sender.lock().unwrap().take();
+    }
+}
+
+/// `sha256=<hex HMAC of the body>`, which is what inbound webhooks expect too.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    // HMAC accepts keys of any length, so this can't fail.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body);
+
+    let signature: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", signature)
+}
+
+/// One event on its way to one webhook, which is the payload of its job. The webhook is read again for every attempt,
+/// so one that is changed gets its retries at its new URL and secret, and one that is deleted gets none.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Delivery {
+    id: String,
+    webhook: String,
+    event: String,
+    body: String,
+}
+
+impl Delivery {
+    fn send(&self, webhook: &Webhook, agent: &ureq::Agent) -> Result<(), String> {
+        agent
+            .post(&webhook.url)
+            .set("Content-Type", "application/json")
+            .set(EVENT_HEADER, &self.event)
+            .set(DELIVERY_HEADER, &self.id)
+            .set(SIGNATURE_HEADER, &sign(&webhook.secret, self.body.as_bytes()))
+            .send_string(&self.body)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// A delivery for every webhook that subscribes to the event, read when it happens so new webhooks are told straight away.
+fn deliveries(event: &WebhookEvent, pool: &DbPool) -> Vec<Delivery> {
+    let webhooks = match pool.get().map(|db| Webhook::read_all(&db)) {
+        Ok(Ok(webhooks)) => webhooks,
+        _ => {
+            tracing::error!("Failed to read the webhooks for {}.", event.event);
+            return Vec::new();
+        }
+    };
+    let body = serde_json::to_string(event).unwrap_or_default();
+
+    webhooks
+        .into_iter()
+        .filter(|w| w.wants(event.event))
+        .map(|w| Delivery {
+            id: Uuid::new_v4().to_string(),
+            webhook: w.uuid,
+            event: String::from(event.event),
+            body: body.clone(),
+        })
+        .collect()
+}
+
+/// Queues a job for every delivery of the events as they come in, until the queue is closed.
+pub fn run(pool: DbPool, events: Receiver<WebhookEvent>, jobs: web::Data<JobQueue>) {
+    for event in events {
+        for delivery in deliveries(&event, &pool) {
+            jobs.send(NewJob::new(JOB, delivery));
+        }
+    }
+}
+
+/// Runs the jobs of `JOB`. Receivers that fail or don't answer with a 2xx are tried again with the job's backoff, and
+/// `APP_MAIL_NOTIFY` is told about every delivery that is given up on.
+pub fn handler(pool: DbPool, mail: Arc<MailQueue>) -> impl Fn(&Job) -> Result<(), String> + Send {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+
+    move |job| {
+        let delivery: Delivery = job.data()?;
+        let db = pool.get().map_err(|e| e.to_string())?;
+        let webhook = match Webhook::read_one(delivery.webhook.clone(), &db) {
+            Ok(webhook) => webhook,
+            Err(NotFound) => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        };
+        // not held on to while waiting for the receiver.
+        drop(db);
+
+        let sent = delivery.send(&webhook, &agent);
+        if let Err(e) = &sent {
+            if job.attempts >= job.max_attempts {
+                mail.notify(
+                    format!("A webhook to {} is failing", webhook.url),
+                    "webhook-failure",
+                    serde_json::json!({
+                        "delivery": delivery.id,
+                        "url": webhook.url,
+                        "event": delivery.event,
+                        "attempts": job.attempts,
+                        "error": e,
+                    }),
+                );
+            }
+        }
+
+        sent
+    }
+}
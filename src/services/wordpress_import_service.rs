@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use diesel::Connection;
+use roxmltree::{Document, Node};
+use uuid::Uuid;
+
+use super::errors_service::CustomHttpError;
+use super::markdown_import_service::slugify;
+use super::plugin_service::Plugins;
+use crate::controllers::module_controllers::write_new_module;
+use crate::controllers::page_controllers::write_new_page;
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::MutModule;
+use crate::models::page_models::{MutPage, Page, DRAFT, PUBLISHED};
+use crate::models::DbConnection;
+
+/// The template imported pages are rendered with, as WordPress themes don't map onto Radical's.
+const DEFAULT_TEMPLATE: &str = "index";
+/// The versions of the export format only differ in the end of their namespace, like `http://wordpress.org/export/1.2/`.
+const WP_NAMESPACE: &str = "http://wordpress.org/export/";
+const CONTENT_NAMESPACE: &str = "http://purl.org/rss/1.0/modules/content/";
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// A post or page from a WordPress export, known as an `item` in the WXR file.
+#[derive(Debug, Clone)]
+pub struct WordPressItem {
+    pub id: String,
+    pub title: String,
+    /// Empty for drafts that were never published.
+    pub slug: String,
+    /// The permalink, or `?p=<id>` for drafts.
+    pub link: String,
+    /// `post`, `page`, `attachment`, `nav_menu_item`, and so on.
+    pub post_type: String,
+    /// `publish`, `future`, `draft`, `pending`, `private`, `trash`, and so on.
+    pub status: String,
+    /// When it was published, or is going to be, in UTC.
+    pub date: Option<NaiveDateTime>,
+    pub parent: Option<String>,
+    pub content: String,
+    pub excerpt: String,
+    pub tags: Vec<String>,
+    pub categories: Vec<String>,
+}
+
+/// A WordPress export split into its items.
+pub struct WordPressExport {
+    /// The site's address, which permalinks start with.
+    pub site_url: String,
+    pub items: Vec<WordPressItem>,
+}
+
+#[derive(Default)]
+pub struct ImportReport {
+    /// The URLs of the pages that were created.
+    pub imported: Vec<String>,
+    /// Items that were left alone by title, along with why.
+    pub skipped: Vec<(String, String)>,
+}
+
+fn child<'a>(node: Node<'a, 'a>, namespace: &str, name: &str) -> Option<Node<'a, 'a>> {
+    node.children().find(|c| {
+        c.tag_name().name() == name && c.tag_name().namespace().unwrap_or_default().starts_with(namespace)
+    })
+}
+
+fn child_text(node: Node, namespace: &str, name: &str) -> String {
+    child(node, namespace, name).and_then(|c| c.text()).unwrap_or_default().trim().to_string()
+}
+
+/// WordPress dates are `2021-03-04 05:06:07`, and all zeros for drafts that were never given one.
+fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(date, DATE_FORMAT).ok()
+}
+
+fn terms(item: Node, domain: &str) -> Vec<String> {
+    item.children()
+        .filter(|c| c.tag_name().name() == "category" && c.attribute("domain") == Some(domain))
+        .filter_map(|c| c.text().map(|t| t.trim().to_string()))
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+pub fn parse(xml: &str) -> Result<WordPressExport, String> {
+    let document = Document::parse(xml).map_err(|e| e.to_string())?;
+    let channel = document
+        .root_element()
+        .children()
+        .find(|c| c.tag_name().name() == "channel")
+        .ok_or_else(|| String::from("Not a WordPress export, as it has no <channel>"))?;
+
+    let items = channel
+        .children()
+        .filter(|c| c.tag_name().name() == "item")
+        .map(|item| {
+            let date = parse_date(&child_text(item, WP_NAMESPACE, "post_date_gmt"))
+                .or_else(|| parse_date(&child_text(item, WP_NAMESPACE, "post_date")));
+
+            WordPressItem {
+                id: child_text(item, WP_NAMESPACE, "post_id"),
+                title: child_text(item, "", "title"),
+                slug: child_text(item, WP_NAMESPACE, "post_name"),
+                link: child_text(item, "", "link"),
+                post_type: child_text(item, WP_NAMESPACE, "post_type"),
+                status: child_text(item, WP_NAMESPACE, "status"),
+                date,
+                parent: Some(child_text(item, WP_NAMESPACE, "post_parent")).filter(|p| !p.is_empty() && p != "0"),
+                content: child_text(item, CONTENT_NAMESPACE, "encoded"),
+                excerpt: child(item, WP_NAMESPACE, "encoded")
+                    .filter(|c| c.tag_name().namespace().unwrap_or_default().ends_with("/excerpt/"))
+                    .and_then(|c| c.text())
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                tags: terms(item, "post_tag"),
+                categories: terms(item, "category"),
+            }
+        })
+        .collect();
+
+    Ok(WordPressExport {
+        site_url: child_text(channel, "", "link"),
+        items,
+    })
+}
+
+/// The permalink's path under the site, which keeps URLs the same after moving. Drafts have no permalink, only `?p=<id>`.
+fn permalink_path(link: &str, site_url: &str) -> Option<String> {
+    let link = url::Url::parse(link).ok()?;
+    if link.query().is_some() {
+        return None;
+    }
+
+    let base = url::Url::parse(site_url).map(|u| u.path().trim_end_matches('/').to_string()).unwrap_or_default();
+    let path = link.path().strip_prefix(&base).unwrap_or(link.path()).trim_matches('/');
+
+    Some(format!("/{}", path))
+}
+
+fn slug(item: &WordPressItem) -> String {
+    match slugify(if item.slug.is_empty() { &item.title } else { &item.slug }) {
+        slug if slug.is_empty() => format!("{}-{}", item.post_type, item.id),
+        slug => slug,
+    }
+}
+
+/// Where the item was, or for drafts where WordPress would put it by default: posts at their slug,
+/// and pages under the slugs of their parents.
+fn page_url(item: &WordPressItem, site_url: &str, by_id: &HashMap<&str, &WordPressItem>) -> String {
+    if let Some(path) = permalink_path(&item.link, site_url) {
+        return path;
+    }
+
+    let mut segments = vec![slug(item)];
+    let mut parent = item.parent.as_deref().and_then(|p| by_id.get(p));
+    // an export that loops back on itself would never end otherwise.
+    while let Some(p) = parent.filter(|_| segments.len() < 32) {
+        segments.insert(0, slug(p));
+        parent = p.parent.as_deref().and_then(|id| by_id.get(id));
+    }
+
+    format!("/{}", segments.join("/"))
+}
+
+/// Drops the `<!-- wp:paragraph -->` comments the block editor wraps blocks in. What is left is HTML, with paragraphs
+/// apart from the classic editor split by blank lines, which reads as Markdown as it is.
+fn clean_content(content: &str) -> String {
+    let mut cleaned = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find("<!-- ") {
+        let is_block = rest[start + 5..].starts_with("wp:") || rest[start + 5..].starts_with("/wp:");
+        let end = rest[start..].find("-->").map(|end| start + end + 3);
+        match end {
+            Some(end) if is_block => {
+                cleaned.push_str(&rest[..start]);
+                rest = &rest[end..];
+            }
+            _ => {
+                cleaned.push_str(&rest[..start + 5]);
+                rest = &rest[start + 5..];
+            }
+        }
+    }
+    cleaned.push_str(rest);
+
+    let mut collapsed = String::with_capacity(cleaned.len());
+    for line in cleaned.trim().lines().map(str::trim_end) {
+        if !(line.is_empty() && collapsed.ends_with("\n\n")) {
+            collapsed.push_str(line);
+            collapsed.push('\n');
+        }
+    }
+
+    collapsed
+}
+
+/// The page's status, and when a scheduled post goes live, or why the item isn't imported.
+fn status(item: &WordPressItem) -> Result<(&'static str, Option<NaiveDateTime>), String> {
+    match item.status.as_str() {
+        "publish" => Ok((PUBLISHED, None)),
+        "future" => Ok((DRAFT, item.date)),
+        // private posts are hidden from visitors, which a draft is too.
+        "draft" | "pending" | "private" => Ok((DRAFT, None)),
+        other => Err(format!("Has the status {}", other)),
+    }
+}
+
+fn import_item(
+    item: &WordPressItem,
+    url: String,
+    conf: &LocalConfig,
+    plugins: &Plugins,
+    db: &DbConnection,
+) -> Result<(), CustomHttpError> {
+    let page_uuid = Uuid::new_v4().to_string();
+    let (page_status, publish_at) = status(item).map_err(|_| CustomHttpError::BadRequest)?;
+
+    let page = MutPage {
+        uuid: Some(page_uuid.clone()),
+        page_name: String::from(DEFAULT_TEMPLATE),
+        page_title: if item.title.is_empty() { url.clone() } else { item.title.clone() },
+        page_url: url,
+        status: Some(String::from(page_status)),
+        parent_page: None,
+        meta_description: Some(item.excerpt.clone()).filter(|e| !e.is_empty()),
+        meta_keywords: None,
+        og_image: None,
+        noindex: None,
+        locale: None,
+        translation_group: None,
+        visibility: None,
+    };
+
+    let mut modules = vec![(String::from("content"), clean_content(&item.content))];
+    if let Some(date) = item.date {
+        modules.push((String::from("date"), date.format("%Y-%m-%dT%H:%M:%S").to_string()));
+    }
+    if !item.tags.is_empty() {
+        modules.push((String::from("tags"), item.tags.join(",")));
+    }
+    if !item.categories.is_empty() {
+        modules.push((String::from("categories"), item.categories.join(",")));
+    }
+
+    db.transaction(|| {
+        write_new_page(page, conf, plugins, db)?;
+        if let Some(date) = item.date {
+            Page::set_time_created(page_uuid.clone(), date, db)?;
+        }
+        if publish_at.is_some() {
+            Page::set_status(page_uuid.clone(), page_status, publish_at, db)?;
+        }
+
+        for (title, content) in modules {
+            let module = MutModule {
+                uuid: None,
+                page_uuid: Some(page_uuid.clone()),
+                category_uuid: None,
+                title,
+                content,
+                encrypted: false,
+                compressed: false,
+                visible_to: None,
+                order_index: None,
+                module_type: None,
+            };
+            write_new_module(module, conf, plugins, db)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Imports the posts and pages of a WordPress export as pages at their permalinks, dated to when they were published,
+/// with their content in a `content` module and their date, tags, and categories in modules of the same name.
+/// Scheduled posts become drafts that go live when they would have. Attachments, menus, trashed items, and items whose
+/// URL is already taken are skipped rather than overwritten. Pages are written as the API writes them, content hooks
+/// included.
+pub fn import(export: &WordPressExport, conf: &LocalConfig, plugins: &Plugins, db: &DbConnection) -> ImportReport {
+    let by_id: HashMap<&str, &WordPressItem> = export.items.iter().map(|item| (item.id.as_str(), item)).collect();
+    let mut report = ImportReport::default();
+
+    for item in &export.items {
+        let name = if item.title.is_empty() { format!("{} {}", item.post_type, item.id) } else { item.title.clone() };
+
+        if item.post_type != "post" && item.post_type != "page" {
+            report.skipped.push((name, format!("Is of the type {}, not a post or page", item.post_type)));
+            continue;
+        }
+        if let Err(reason) = status(item) {
+            report.skipped.push((name, reason));
+            continue;
+        }
+
+        let url = page_url(item, &export.site_url, &by_id);
+        if Page::read_one_by_url(url.clone(), db).is_ok() {
+            report.skipped.push((name, format!("A page already exists at {}", url)));
+            continue;
+        }
+
+        match import_item(item, url.clone(), conf, plugins, db) {
+            Ok(_) => report.imported.push(url),
+            Err(e) => report.skipped.push((name, e.descriptor())),
+        }
+    }
+
+    report
+}
@@ -0,0 +1,401 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test, web};
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool};
+use diesel::Connection;
+use handlebars::Handlebars;
+use uuid::Uuid;
+
+use crate::app::AppState;
+use crate::helpers;
+use crate::models::config_models::LocalConfig;
+use crate::models::module_models::MutModule;
+use crate::models::page_models::MutPage;
+use crate::models::user_models::{MutUser, User};
+use crate::models::{self, Databases, DbPooledConnection, Model};
+use crate::services::auth_service::{encrypt, encrypt_password, Claims};
+use crate::services::feed_service::Feed;
+use crate::services::job_service::JobQueue;
+use crate::services::live_service::{EventStreams, LiveHub};
+use crate::services::mail_service::MailQueue;
+use crate::services::metrics_service::Metrics;
+use crate::services::migration_service::{self, SchemaState};
+use crate::services::page_cache_service::PageCache;
+use crate::services::preview_service::PreviewHub;
+use crate::services::publish_service::PublishQueue;
+use crate::services::rate_limit_service::{Limits, RateLimiter};
+use crate::services::redirect_service::RedirectTable;
+use crate::services::response_cache_service::ResponseCache;
+use crate::services::route_service::RouteTable;
+use crate::services::search_service::{self, SearchIndexer};
+use crate::services::settings_service::SettingsCache;
+use crate::services::site_service::SiteTable;
+use crate::services::sitemap_service::Sitemap;
+use crate::services::theme_service::{self, Themes};
+use crate::services::warmup_service::Readiness;
+use crate::services::webhook_service::WebhookQueue;
+use crate::services::{cache_service, graphql_service, media_store_service, session_service};
+use crate::Plugins;
+
+pub use crate::models::module_models::Module;
+pub use crate::models::page_models::{PageDTO, DRAFT, PUBLISHED};
+pub use crate::models::user_models::UserRole;
+pub use crate::models::{DbConnection, DbPool};
+
+/// What `APP_JWT_KEY` is for tests that don't set it.
+const TEST_JWT_KEY: &str = "radical-test-key";
+/// The password of every user `TestDb::user` makes, for tests that log in.
+pub const TEST_PASSWORD: &str = "password";
+/// How long a test waits for the one connection of its database before it fails, rather than hanging.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tests that share a database would otherwise run its pending migrations at the same time.
+static MIGRATING: Mutex<()> = Mutex::new(());
+
+/// The config tests run with, which is read like the server's, with what every test needs filled in. `APP_JWT_KEY` is
+/// set for the process if it isn't, as the config can't be loaded without it, rate limits are lifted,
+/// and SQLite builds keep their database in memory unless `APP_SQLITE_PATH` is set.
+pub fn test_config() -> LocalConfig {
+    if std::env::var("APP_JWT_KEY").map_or(true, |key| key.is_empty()) {
+        std::env::set_var("APP_JWT_KEY", TEST_JWT_KEY);
+    }
+    let mut conf = LocalConfig::load().unwrap_or_else(|e| panic!("Invalid config for tests: {}", e));
+
+    #[cfg(feature = "sqlite")]
+    if conf.sqlite_path.is_none() {
+        conf.sqlite_path = Some(String::from(":memory:"));
+    }
+    conf.rate_limit_anonymous_reads = Some(u64::MAX);
+    conf.rate_limit_anonymous_writes = Some(u64::MAX);
+    conf.rate_limit_key_reads = Some(u64::MAX);
+    conf.rate_limit_key_writes = Some(u64::MAX);
+
+    conf
+}
+
+/// Catches the database up and opens a transaction on the connection that is never committed, so whatever a test
+/// writes is gone along with its `TestDb`.
+#[derive(Debug)]
+struct TestTransaction;
+
+impl CustomizeConnection<DbConnection, diesel::r2d2::Error> for TestTransaction {
+    fn on_acquire(&self, connection: &mut DbConnection) -> Result<(), diesel::r2d2::Error> {
+        #[cfg(feature = "sqlite")]
+        diesel::connection::SimpleConnection::batch_execute(connection, models::SQLITE_PRAGMAS)
+            .map_err(diesel::r2d2::Error::QueryError)?;
+
+        let migrating = MIGRATING.lock().unwrap_or_else(|e| e.into_inner());
+        migration_service::run(connection).map_err(|e| {
+            diesel::r2d2::Error::QueryError(diesel::result::Error::QueryBuilderError(e.to_string().into()))
+        })?;
+        drop(migrating);
+
+        connection.begin_test_transaction().map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// A database of a test's own. It has a single connection, which stays in a transaction that is rolled back once
+/// the `TestDb` is dropped, so tests can run side by side against the same database without seeing each other's
+/// changes. Connections taken from it have to be given back before the app is called, as it has no other.
+pub struct TestDb {
+    pool: DbPool,
+    conf: LocalConfig,
+}
+
+impl TestDb {
+    /// A database configured by `test_config`.
+    pub fn new() -> Self {
+        Self::with_config(test_config())
+    }
+
+    pub fn with_config(conf: LocalConfig) -> Self {
+        let manager = ConnectionManager::<DbConnection>::new(models::format_connection_string(conf.clone()));
+        let pool = Pool::builder()
+            .max_size(1)
+            .connection_timeout(CONNECTION_TIMEOUT)
+            // the connection is the database, so it is kept for as long as the test runs.
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connection_customizer(Box::new(TestTransaction))
+            .build(manager)
+            .unwrap_or_else(|e| panic!("Could not connect to the test database: {}", e));
+
+        Self { pool, conf }
+    }
+
+    pub fn pool(&self) -> &DbPool {
+        &self.pool
+    }
+
+    pub fn conf(&self) -> &LocalConfig {
+        &self.conf
+    }
+
+    /// The database's connection, for setting up a test or checking what it did.
+    pub fn conn(&self) -> DbPooledConnection {
+        self.pool.get().unwrap_or_else(|e| panic!("The test database's connection is still taken: {}", e))
+    }
+
+    /// Makes a user with the role and `TEST_PASSWORD`, or gives an existing one the role, and logs them in, returning the
+    /// token to send as the `Authorization` header.
+    pub fn user(&self, username: &str, role: UserRole) -> String {
+        let db = self.conn();
+        let user = MutUser {
+            uuid: None,
+            username: username.to_string(),
+            password: Some(encrypt_password(&TEST_PASSWORD.to_string()).unwrap()),
+            token: None,
+            email: None,
+            role: Some(String::from(role.as_str())),
+        };
+        match User::read_one(username.to_string(), &db) {
+            Ok(_) => User::update(username.to_string(), &user, &db),
+            Err(_) => User::create(&MutUser { uuid: Some(Uuid::new_v4().to_string()), ..user.clone() }, &db),
+        }
+        .unwrap();
+
+        let claims = Claims {
+            exp: (chrono::Utc::now() + chrono::Duration::days(1)).timestamp() as usize,
+            sub: username.to_string(),
+            role,
+        };
+        let token = encrypt(claims, &db).unwrap();
+        User::update_with_token(&MutUser { token: Some(token.clone()), password: None, role: None, ..user }, &db).unwrap();
+
+        token
+    }
+}
+
+impl Default for TestDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A page to make for a test, a draft at `/<name>` titled like its name unless it is told otherwise.
+pub struct PageFixture {
+    page: MutPage,
+}
+
+impl PageFixture {
+    pub fn new(name: &str) -> Self {
+        Self {
+            page: MutPage {
+                uuid: None,
+                page_name: name.to_string(),
+                page_url: format!("/{}", name),
+                page_title: name.to_string(),
+                status: Some(String::from(DRAFT)),
+                parent_page: None,
+                meta_description: None,
+                meta_keywords: None,
+                og_image: None,
+                noindex: None,
+                locale: None,
+                translation_group: None,
+                visibility: None,
+            },
+        }
+    }
+
+    pub fn url(mut self, url: &str) -> Self {
+        self.page.page_url = url.to_string();
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.page.page_title = title.to_string();
+        self
+    }
+
+    pub fn published(mut self) -> Self {
+        self.page.status = Some(String::from(PUBLISHED));
+        self
+    }
+
+    /// Nests the page under another, which makes a `url` without a leading `/` relative to the parent's.
+    pub fn parent(mut self, parent: &PageDTO) -> Self {
+        self.page.parent_page = Some(parent.uuid.clone());
+        self
+    }
+
+    pub fn locale(mut self, locale: &str) -> Self {
+        self.page.locale = Some(locale.to_string());
+        self
+    }
+
+    /// Who can see the page, like `authenticated` or `role:editor`.
+    pub fn visibility(mut self, visibility: &str) -> Self {
+        self.page.visibility = Some(visibility.to_string());
+        self
+    }
+
+    pub fn create(self, db: &TestDb) -> PageDTO {
+        let uuid = Uuid::new_v4().to_string();
+        let conn = db.conn();
+        crate::models::page_models::Page::create(&MutPage { uuid: Some(uuid.clone()), ..self.page }, &conn).unwrap();
+
+        crate::models::page_models::Page::read_one(uuid, &conn).unwrap()
+    }
+}
+
+/// A module to make for a test, a text module with no content unless it is told otherwise.
+pub struct ModuleFixture {
+    module: MutModule,
+}
+
+impl ModuleFixture {
+    /// A module of `page`, after the ones it already has.
+    pub fn new(page: &PageDTO, title: &str) -> Self {
+        Self {
+            module: MutModule {
+                page_uuid: Some(page.uuid.clone()),
+                ..Self::global(title).module
+            },
+        }
+    }
+
+    /// A global module, which every page shows.
+    pub fn global(title: &str) -> Self {
+        Self {
+            module: MutModule {
+                uuid: None,
+                title: title.to_string(),
+                page_uuid: None,
+                category_uuid: None,
+                content: String::new(),
+                encrypted: false,
+                compressed: false,
+                visible_to: None,
+                order_index: None,
+                module_type: None,
+            },
+        }
+    }
+
+    pub fn content(mut self, content: &str) -> Self {
+        self.module.content = content.to_string();
+        self
+    }
+
+    /// One of the `ModuleType`s, or the name of a content type.
+    pub fn module_type(mut self, module_type: &str) -> Self {
+        self.module.module_type = Some(module_type.to_string());
+        self
+    }
+
+    pub fn order_index(mut self, order_index: i32) -> Self {
+        self.module.order_index = Some(order_index);
+        self
+    }
+
+    /// Only shown to the roles, like `editor,admin`.
+    pub fn visible_to(mut self, roles: &str) -> Self {
+        self.module.visible_to = Some(roles.to_string());
+        self
+    }
+
+    pub fn create(self, db: &TestDb) -> Module {
+        let uuid = Uuid::new_v4().to_string();
+        let conn = db.conn();
+        Module::create(&MutModule { uuid: Some(uuid.clone()), ..self.module }, &conn).unwrap();
+
+        Module::read_one(uuid, &conn).unwrap()
+    }
+}
+
+/// What the server's handlers are given, made the way `run` makes it but without any of the background workers, so
+/// nothing is published, indexed, delivered, or mailed. Caches are only used if the config turns them on.
+fn state(db: &TestDb, plugins: Plugins) -> AppState {
+    let (pool, conf) = (db.pool.clone(), db.conf.clone());
+
+    let handlebars = web::Data::new(Mutex::new(Handlebars::new()));
+    let themes = web::Data::new(Themes::new(theme_service::configured(&conf, &db.conn())));
+    themes.reload(&handlebars).unwrap_or_else(|e| panic!("Invalid templates: {:?}", e));
+    helpers::default::register_helpers(handlebars.clone());
+
+    let cache = cache_service::backend(&conf).unwrap_or_else(|e| panic!("Invalid APP_CACHE_BACKEND: {}", e));
+
+    let sitemap = web::Data::new(Sitemap::new(cache.clone()));
+    let feed = web::Data::new(Feed::new(cache.clone()));
+    let mut publish_queue = PublishQueue::new();
+    let (sitemap_ref, feed_ref) = (sitemap.clone(), feed.clone());
+    publish_queue.hook(move |_| {
+        sitemap_ref.invalidate();
+        feed_ref.invalidate();
+    });
+
+    let (mut webhook_queue, _) = WebhookQueue::new();
+    let live_hub = web::Data::new(LiveHub::new());
+    let event_streams = web::Data::new(EventStreams::new());
+    let (live_hub_ref, event_streams_ref, after_save) = (live_hub.clone(), event_streams.clone(), plugins.clone());
+    webhook_queue.hook(move |event| {
+        live_hub_ref.broadcast(event);
+        event_streams_ref.broadcast(event);
+        after_save.after_save(event);
+    });
+
+    let (search_indexer, _) = SearchIndexer::new();
+    let search_index =
+        search_service::index(&conf, pool.clone()).unwrap_or_else(|e| panic!("Invalid APP_SEARCH_BACKEND: {}", e));
+    let session_store = session_service::store(&conf).unwrap_or_else(|e| panic!("Invalid APP_SESSION_STORE: {}", e));
+    let media_store = media_store_service::store(&conf).unwrap_or_else(|e| panic!("Invalid APP_MEDIA_STORE: {}", e));
+    // jobs are dropped as they are sent, so none of them are stored to be run.
+    let job_queue = web::Data::new(JobQueue::new().0);
+    let mail_queue = MailQueue::new(&conf, job_queue.clone());
+    let databases = Databases::new(pool.clone(), &conf).unwrap_or_else(|e| panic!("Invalid APP_DATABASES: {}", e));
+
+    AppState {
+        databases: web::Data::new(databases),
+        handlebars,
+        themes,
+        cache: web::Data::from(cache.clone()),
+        settings_cache: web::Data::new(SettingsCache::new(cache.clone())),
+        route_table: web::Data::new(RouteTable::new(cache.clone())),
+        redirect_table: web::Data::new(RedirectTable::new(cache.clone())),
+        site_table: web::Data::new(SiteTable::new(cache.clone())),
+        page_cache: web::Data::new(PageCache::new(cache.clone(), &conf)),
+        response_cache: web::Data::new(ResponseCache::new(cache.clone(), &conf)),
+        sitemap,
+        feed,
+        readiness: web::Data::new(Readiness::new()),
+        metrics: web::Data::new(Metrics::new()),
+        rate_limiter: web::Data::new(RateLimiter::new(cache, Limits::from_conf(&conf), Duration::from_secs(60))),
+        session_store: web::Data::from(session_store),
+        media_store: web::Data::from(media_store),
+        search_index: web::Data::from(search_index),
+        search_indexer: web::Data::new(search_indexer),
+        publish_queue: web::Data::new(publish_queue),
+        job_queue,
+        webhook_queue: web::Data::new(webhook_queue),
+        mail_queue: web::Data::new(mail_queue),
+        preview_hub: web::Data::new(PreviewHub::new()),
+        live_hub,
+        event_streams,
+        plugins: web::Data::new(plugins),
+        schema_state: web::Data::new(SchemaState::new()),
+        graphql_schema: web::Data::new(graphql_service::schema()),
+        pool,
+        conf,
+    }
+}
+
+/// The app against `db`, with every route and middleware the server has, to call with `actix_web::test::call_service`.
+pub async fn init_app(
+    db: &TestDb,
+) -> impl Service<Request = actix_http::Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error> {
+    init_app_with(db, Plugins::new()).await
+}
+
+/// `init_app` with plugins installed.
+pub async fn init_app_with(
+    db: &TestDb,
+    plugins: Plugins,
+) -> impl Service<Request = actix_http::Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error> {
+    let state = state(db, plugins);
+
+    test::init_service(app!(&state)).await
+}
@@ -2,28 +2,36 @@ extern crate notify;
 
 use actix_web::web::Data;
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
 use std::sync::mpsc::channel;
 use std::sync::Mutex;
 use std::time::Duration;
 
-/// Watches the templates directory and refreshes the templates in memory on update.
-pub fn watch(hb: Data<Mutex<handlebars::Handlebars<'_>>>) -> notify::Result<()> {
+use crate::services::page_cache_service::PageCache;
+use crate::services::theme_service::{Themes, DEFAULT_TEMPLATES, THEMES_DIR};
+
+/// Watches the templates directory and the themes, and refreshes the active theme's templates in memory on update.
+/// Cached pages were rendered with the old templates, so they are dropped too.
+pub fn watch(hb: Data<Mutex<handlebars::Handlebars<'_>>>, pages: Data<PageCache>, themes: Data<Themes>) -> notify::Result<()> {
     let (tx, rx) = channel();
 
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(2))?;
 
-    watcher.watch("./templates", RecursiveMode::Recursive)?;
+    watcher.watch(DEFAULT_TEMPLATES, RecursiveMode::Recursive)?;
+    if Path::new(THEMES_DIR).is_dir() {
+        watcher.watch(THEMES_DIR, RecursiveMode::Recursive)?;
+    }
 
     loop {
         match rx.recv() {
             Ok(_) => {
-                hb.lock().unwrap().clear_templates();
-                hb.lock()
-                    .unwrap()
-                    .register_templates_directory(".hbs", "./templates")
-                    .unwrap();
+                // a template that is still being edited doesn't compile, and the last ones that did are kept until it does.
+                if let Err(e) = themes.reload(&hb) {
+                    tracing::error!("watch error: {:?}", e);
+                }
+                pages.clear();
             }
-            Err(e) => println!("watch error: {:?}", e),
+            Err(e) => tracing::error!("watch error: {:?}", e),
         }
     }
 }
@@ -0,0 +1,83 @@
+#![cfg(feature = "test-utils")]
+
+mod common;
+
+use actix_web::test::{self, TestRequest};
+use radical::test_utils::{init_app, test_config, PageFixture, TestDb, UserRole};
+use uuid::Uuid;
+
+use common::run;
+
+/// A database that outlives the `TestDb`s opened on it, which SQLite's in-memory ones don't, so that what one test
+/// leaves behind would be seen by the next.
+fn db_at(path: &str) -> TestDb {
+    #[allow(unused_mut)]
+    let mut conf = test_config();
+    #[cfg(feature = "sqlite")]
+    {
+        conf.sqlite_path = Some(path.to_string());
+    }
+    #[cfg(not(feature = "sqlite"))]
+    let _ = path;
+
+    TestDb::with_config(conf)
+}
+
+async fn page_urls(db: &TestDb) -> Vec<String> {
+    let mut app = init_app(db).await;
+    let body = test::read_response(&mut app, TestRequest::get().uri("/api/v1/pages").to_request()).await;
+    let pages: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+
+    pages.iter().filter_map(|page| page["page_url"].as_str().map(String::from)).collect()
+}
+
+#[test]
+fn writes_are_rolled_back_between_tests() {
+    let path = std::env::temp_dir().join(format!("radical-test-{}.sqlite", Uuid::new_v4()));
+    let path = path.to_string_lossy().to_string();
+
+    let written = path.clone();
+    run(async move {
+        let db = db_at(&written);
+        let token = db.user("editor", UserRole::Editor);
+        PageFixture::new("fixture").published().create(&db);
+        let mut app = init_app(&db).await;
+
+        let create = TestRequest::post()
+            .uri("/api/v1/pages")
+            .header("Authorization", token)
+            .set_json(&serde_json::json!({ "page_name": "index", "page_url": "/posted", "page_title": "Posted" }))
+            .to_request();
+        assert!(test::call_service(&mut app, create).await.status().is_success());
+        drop(app);
+
+        let urls = page_urls(&db).await;
+        assert!(urls.contains(&String::from("/fixture")) && urls.contains(&String::from("/posted")));
+    });
+
+    let read = path.clone();
+    run(async move {
+        let db = db_at(&read);
+        let urls = page_urls(&db).await;
+        assert!(!urls.contains(&String::from("/fixture")) && !urls.contains(&String::from("/posted")), "{:?}", urls);
+
+        // the user made for the last test is gone as well, so it can be made again.
+        db.user("editor", UserRole::Editor);
+    });
+
+    for suffix in ["", "-wal", "-shm"] {
+        std::fs::remove_file(format!("{}{}", path, suffix)).ok();
+    }
+}
+
+#[test]
+fn test_databases_are_their_own() {
+    run(async {
+        let first = TestDb::new();
+        let second = TestDb::new();
+        PageFixture::new("first").create(&first);
+
+        assert!(page_urls(&first).await.contains(&String::from("/first")));
+        assert!(!page_urls(&second).await.contains(&String::from("/first")));
+    });
+}
@@ -0,0 +1,8 @@
+use std::future::Future;
+
+use actix_web::rt::System;
+
+/// actix-web 3 runs on a system of its own, which the runtime of `#[actix_rt::test]` isn't.
+pub fn run(test: impl Future<Output = ()> + 'static) {
+    System::new("test").block_on(test);
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "test-utils")]
+
+#[macro_use]
+extern crate diesel;
+
+mod common;
+
+use actix_web::test::{self, TestRequest};
+use diesel::sql_types::{Bool, Text};
+use diesel::RunQueryDsl;
+use radical::test_utils::{init_app, PageFixture, TestDb, UserRole};
+use serde_json::{json, Value};
+
+use common::run;
+
+const SECRET: &str = "the launch code is 0000";
+
+/// A database whose config has an encryption key, which every test of this file sets the same.
+fn encrypting_db() -> TestDb {
+    std::env::set_var("APP_ENCRYPTION_KEY", "radical-test-encryption-key");
+
+    TestDb::new()
+}
+
+/// A module as it is stored.
+#[derive(QueryableByName)]
+struct Stored {
+    #[sql_type = "Text"]
+    content: String,
+    #[sql_type = "Bool"]
+    encrypted: bool,
+}
+
+fn body_of(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).to_string()
+}
+
+#[test]
+fn editors_are_never_sent_encrypted_content() {
+    run(async {
+        let db = encrypting_db();
+        let admin = db.user("admin", UserRole::Admin);
+        let editor = db.user("editor", UserRole::Editor);
+        let page = PageFixture::new("vault").create(&db);
+        let mut app = init_app(&db).await;
+
+        let send = |req: TestRequest, token: &str| req.header("Authorization", token.to_string()).to_request();
+
+        let create = TestRequest::post().uri("/api/v1/modules").set_json(&json!({
+            "title": "secret",
+            "page_uuid": page.uuid,
+            "content": SECRET,
+            "encrypted": true,
+        }));
+        let created: Value = serde_json::from_slice(&test::read_response(&mut app, send(create, &admin)).await).unwrap();
+        let uuid = created["uuid"].as_str().unwrap().to_string();
+        assert_eq!(created["content"], SECRET);
+
+        // an editor makes one too, and isn't sent back what they sent.
+        let create = TestRequest::post().uri("/api/v1/modules").set_json(&json!({
+            "title": "another",
+            "page_uuid": page.uuid,
+            "content": SECRET,
+            "encrypted": true,
+        }));
+        let body = body_of(&test::read_response(&mut app, send(create, &editor)).await);
+        assert!(body.contains("\"uuid\"") && !body.contains(SECRET), "{}", body);
+
+        let patch = TestRequest::patch().uri(&format!("/api/v1/modules/{}", uuid)).header("If-Match", "*");
+        let body = body_of(&test::read_response(&mut app, send(patch.set_json(&json!({ "title": "renamed" })), &editor)).await);
+        assert!(body.contains("renamed") && !body.contains(SECRET), "{}", body);
+
+        let trash = TestRequest::delete().uri(&format!("/api/v1/modules/{}", uuid));
+        assert!(test::call_service(&mut app, send(trash, &editor)).await.status().is_success());
+        let restore = TestRequest::post().uri(&format!("/api/v1/modules/{}/restore", uuid));
+        let body = body_of(&test::read_response(&mut app, send(restore, &editor)).await);
+        assert!(body.contains(&uuid) && !body.contains(SECRET), "{}", body);
+
+        let modules = TestRequest::get().uri(&format!("/api/v1/pages/{}/modules", page.uuid));
+        let read: Value = serde_json::from_slice(&test::read_response(&mut app, send(modules, &admin)).await).unwrap();
+        let order: Vec<Value> = read["fields"]["modules"].as_array().unwrap().iter().rev().map(|m| m["uuid"].clone()).collect();
+        let reorder = TestRequest::put().uri(&format!("/api/v1/pages/{}/modules/reorder", page.uuid)).set_json(&order);
+        let body = body_of(&test::read_response(&mut app, send(reorder, &editor)).await);
+        assert!(body.contains(&uuid) && !body.contains(SECRET), "{}", body);
+
+        let duplicate = TestRequest::post().uri(&format!("/api/v1/pages/{}/duplicate", page.uuid));
+        let body = body_of(&test::read_response(&mut app, send(duplicate, &editor)).await);
+        assert!(body.contains("\"modules\"") && !body.contains(SECRET), "{}", body);
+
+        // content that wasn't sent with the patch was kept, encrypted, and an admin still reads it.
+        let read = TestRequest::get().uri(&format!("/api/v1/modules/{}", uuid));
+        let read: Value = serde_json::from_slice(&test::read_response(&mut app, send(read, &admin)).await).unwrap();
+        assert_eq!((read["title"].as_str(), read["content"].as_str()), (Some("renamed"), Some(SECRET)));
+    });
+}
+
+#[test]
+fn encrypted_content_is_stored_encrypted() {
+    run(async {
+        let db = encrypting_db();
+        let admin = db.user("admin", UserRole::Admin);
+        let page = PageFixture::new("vault").create(&db);
+        let mut app = init_app(&db).await;
+
+        let create = TestRequest::post()
+            .uri("/api/v1/modules")
+            .header("Authorization", admin)
+            .set_json(&json!({ "title": "secret", "page_uuid": page.uuid, "content": SECRET, "encrypted": true }))
+            .to_request();
+        let created: Value = serde_json::from_slice(&test::read_response(&mut app, create).await).unwrap();
+        drop(app);
+
+        // the stored module only has the ciphertext, and visitors aren't sent even that.
+        let uuid = created["uuid"].as_str().unwrap().to_string();
+        let query = format!("SELECT content, encrypted FROM modules WHERE uuid = '{}'", uuid);
+        let stored: Stored = diesel::sql_query(query).get_result(&*db.conn()).unwrap();
+        assert!(stored.encrypted && !stored.content.is_empty() && !stored.content.contains(SECRET));
+
+        let mut app = init_app(&db).await;
+        let read = TestRequest::get().uri(&format!("/api/v1/modules/{}", uuid)).to_request();
+        let body = body_of(&test::read_response(&mut app, read).await);
+        assert!(!body.contains(SECRET) && !body.contains(&stored.content), "{}", body);
+    });
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "test-utils")]
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use radical::test_utils::{init_app, ModuleFixture, PageFixture, TestDb, UserRole};
+use serde_json::{json, Value};
+
+use common::run;
+
+const MEDIA_URL: &str = "/media/floor-plan.png";
+
+#[test]
+fn galleries_are_only_listed_to_who_can_see_their_module() {
+    run(async {
+        let db = TestDb::new();
+        let viewer = db.user("viewer", UserRole::Viewer);
+        let editor = db.user("editor", UserRole::Editor);
+        let page = PageFixture::new("intranet").published().visibility("role:editor").create(&db);
+        let module = ModuleFixture::new(&page, "plans").create(&db);
+        let hidden = ModuleFixture::new(&PageFixture::new("about").published().create(&db), "notes").visible_to("admin").create(&db);
+        let mut app = init_app(&db).await;
+
+        for uuid in [&module.uuid, &hidden.uuid] {
+            let add = TestRequest::post()
+                .uri(&format!("/api/v1/modules/{}/gallery", uuid))
+                .header("Authorization", editor.clone())
+                .set_json(&json!({ "media_url": MEDIA_URL, "caption": "Second floor" }))
+                .to_request();
+            assert!(test::call_service(&mut app, add).await.status().is_success());
+        }
+
+        let gallery = |uuid: &str| TestRequest::get().uri(&format!("/api/v1/modules/{}/gallery", uuid));
+
+        let items: Vec<Value> = serde_json::from_slice(
+            &test::read_response(&mut app, gallery(&module.uuid).header("Authorization", editor.clone()).to_request()).await,
+        )
+        .unwrap();
+        assert_eq!(items.iter().map(|item| item["media_url"].as_str()).collect::<Vec<_>>(), vec![Some(MEDIA_URL)]);
+
+        // the page's visibility keeps out visitors and roles below the one it asks for.
+        let res = test::call_service(&mut app, gallery(&module.uuid).to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = test::call_service(&mut app, gallery(&module.uuid).header("Authorization", viewer).to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // and a module hidden from visitors hides its gallery too, on a page anyone can see.
+        let res = test::call_service(&mut app, gallery(&hidden.uuid).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let body = test::read_response(&mut app, gallery(&hidden.uuid).header("Authorization", editor).to_request()).await;
+        assert!(String::from_utf8_lossy(&body).contains(MEDIA_URL));
+    });
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "test-utils")]
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use radical::test_utils::{init_app, TestDb, UserRole};
+
+use common::run;
+
+#[test]
+fn live_events_are_only_streamed_to_editors() {
+    run(async {
+        let db = TestDb::new();
+        let viewer = db.user("viewer", UserRole::Viewer);
+        let editor = db.user("editor", UserRole::Editor);
+        let mut app = init_app(&db).await;
+
+        let events = |token: Option<&str>| match token {
+            Some(token) => TestRequest::get().uri("/events").header("Authorization", token).to_request(),
+            None => TestRequest::get().uri("/events").to_request(),
+        };
+
+        assert_eq!(test::call_service(&mut app, events(None)).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(test::call_service(&mut app, events(Some(&viewer))).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(test::call_service(&mut app, events(Some(&editor))).await.status(), StatusCode::OK);
+    });
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "test-utils")]
+
+mod common;
+
+use actix_web::test::{self, TestRequest};
+use radical::test_utils::{init_app, TestDb};
+use serde_json::Value;
+
+use common::run;
+
+/// Every schema the spec points at, wherever it is.
+fn refs(value: &Value, found: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(to)) = map.get("$ref") {
+                found.push(to.clone());
+            }
+            map.values().for_each(|v| refs(v, found));
+        }
+        Value::Array(values) => values.iter().for_each(|v| refs(v, found)),
+        _ => {}
+    }
+}
+
+#[test]
+fn the_spec_covers_the_admin_endpoints_and_every_schema_it_uses() {
+    run(async {
+        let db = TestDb::new();
+        let mut app = init_app(&db).await;
+
+        let spec: Value = test::read_body_json(test::call_service(&mut app, TestRequest::get().uri("/openapi.json").to_request()).await).await;
+
+        let paths = spec["paths"].as_object().unwrap();
+        for (path, method) in [
+            ("/api/v1/settings/{id}", "put"),
+            ("/api/v1/admin/system/migrations", "post"),
+            ("/api/v1/keys", "post"),
+            ("/api/v1/privacy/{email}/erase", "post"),
+            ("/api/v1/pages/{id}/revisions/{from}/diff/{to}", "get"),
+            ("/api/v1/pages/{id}/rollout", "put"),
+            ("/search", "get"),
+            ("/api/v1/modules/{id}/gallery", "get"),
+            ("/api/v1/webhooks", "post"),
+            ("/api/v1/themes/active", "put"),
+            ("/api/v1/inbound_webhooks/{id}/receive", "post"),
+            ("/api/v1/delivery_tokens", "post"),
+            ("/api/v1/usage", "get"),
+            ("/render/modules/{id}", "get"),
+            ("/api/v1/trash", "get"),
+            ("/auth/oidc/callback", "get"),
+            ("/api/v1/preview", "post"),
+            ("/api/v1/user/{id}", "delete"),
+        ] {
+            assert!(paths.get(path).and_then(|p| p.get(method)).is_some(), "{} {} isn't in the spec", method, path);
+        }
+
+        let schemas = spec["components"]["schemas"].as_object().unwrap();
+        let mut found = Vec::new();
+        refs(&spec, &mut found);
+        let mut missing: Vec<String> = found
+            .iter()
+            .map(|to| to.trim_start_matches("#/components/schemas/").to_string())
+            .filter(|name| !schemas.contains_key(name))
+            .collect();
+        missing.dedup();
+        assert!(missing.is_empty(), "{:?} aren't in the spec's schemas", missing);
+    });
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "test-utils")]
+
+#[macro_use]
+extern crate diesel;
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use diesel::sql_types::Text;
+use diesel::RunQueryDsl;
+use radical::test_utils::{init_app, TestDb, UserRole, TEST_PASSWORD};
+use serde_json::{json, Value};
+
+use common::run;
+
+const EMAIL: &str = "alice@example.com";
+
+/// A user as it is stored.
+#[derive(QueryableByName)]
+struct Stored {
+    #[sql_type = "Text"]
+    username: String,
+}
+
+#[test]
+fn erased_users_can_not_be_acted_as() {
+    run(async {
+        let db = TestDb::new();
+        let dpo = db.user("dpo", UserRole::Admin);
+        let alice = db.user("alice", UserRole::Admin);
+        diesel::sql_query(format!("UPDATE users SET email = '{}', oidc_subject = 'alice-sub' WHERE username = 'alice'", EMAIL))
+            .execute(&*db.conn())
+            .unwrap();
+        let mut app = init_app(&db).await;
+
+        let create = TestRequest::post()
+            .uri("/api/v1/api_keys")
+            .header("Authorization", alice)
+            .set_json(&json!({ "name": "deploys", "scopes": ["admin"] }))
+            .to_request();
+        let created: Value = serde_json::from_slice(&test::read_response(&mut app, create).await).unwrap();
+        let key = format!("ApiKey {}", created["key"].as_str().unwrap());
+        let keys = || TestRequest::get().uri("/api/v1/api_keys").header("Authorization", key.clone()).to_request();
+        assert!(test::call_service(&mut app, keys()).await.status().is_success());
+
+        let erase = TestRequest::post()
+            .uri(&format!("/api/v1/privacy/{}/erase", EMAIL))
+            .header("Authorization", dpo.clone())
+            .set_json(&json!({ "confirm_email": EMAIL }))
+            .to_request();
+        assert!(test::call_service(&mut app, erase).await.status().is_success());
+
+        assert_eq!(test::call_service(&mut app, keys()).await.status(), StatusCode::UNAUTHORIZED);
+
+        drop(app);
+        let erased: Vec<Stored> = diesel::sql_query("SELECT username FROM users WHERE username LIKE 'deleted-%'")
+            .load(&*db.conn())
+            .unwrap();
+        assert_eq!(erased.len(), 1);
+        let subjects: Vec<Stored> = diesel::sql_query("SELECT username FROM users WHERE oidc_subject IS NOT NULL")
+            .load(&*db.conn())
+            .unwrap();
+        assert!(subjects.is_empty(), "the issuer could still log them in");
+
+        // logging in as what is left of them fails, rather than panicking on the password that was taken off.
+        let mut app = init_app(&db).await;
+        for password in ["", TEST_PASSWORD] {
+            let login = TestRequest::post()
+                .uri("/api/v1/user/login")
+                .set_json(&json!({ "username": erased[0].username, "password": password }))
+                .to_request();
+            assert_eq!(test::call_service(&mut app, login).await.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        let requests = TestRequest::get().uri("/api/v1/privacy/requests").header("Authorization", dpo).to_request();
+        let body = String::from_utf8_lossy(&test::read_response(&mut app, requests).await).to_string();
+        assert!(body.contains("erasure") && !body.contains(EMAIL), "{}", body);
+    });
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "test-utils")]
+
+#[macro_use]
+extern crate diesel;
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use diesel::sql_types::Text;
+use diesel::RunQueryDsl;
+use radical::test_utils::{init_app, TestDb, UserRole, TEST_PASSWORD};
+use serde_json::json;
+
+use common::run;
+
+/// A user as it is stored.
+#[derive(QueryableByName)]
+struct Stored {
+    #[sql_type = "Text"]
+    uuid: String,
+    #[sql_type = "Text"]
+    role: String,
+}
+
+fn stored(username: &str, db: &TestDb) -> Stored {
+    let query = format!("SELECT uuid, role FROM users WHERE username = '{}'", username);
+
+    diesel::sql_query(query).get_result(&*db.conn()).unwrap()
+}
+
+#[test]
+fn writes_need_the_role_that_allows_them() {
+    run(async {
+        let db = TestDb::new();
+        let viewer = db.user("viewer", UserRole::Viewer);
+        let editor = db.user("editor", UserRole::Editor);
+        let admin = db.user("admin", UserRole::Admin);
+        let mut app = init_app(&db).await;
+
+        let page = || {
+            TestRequest::post()
+                .uri("/api/v1/pages")
+                .set_json(&json!({ "page_name": "index", "page_url": "/index", "page_title": "Index" }))
+        };
+        let key = || TestRequest::post().uri("/api/v1/api_keys").set_json(&json!({ "name": "deploys", "scopes": ["read"] }));
+
+        let status = |req: TestRequest, token: &str| req.header("Authorization", token.to_string()).to_request();
+        assert_eq!(test::call_service(&mut app, status(page(), &viewer)).await.status(), StatusCode::FORBIDDEN);
+        assert!(test::call_service(&mut app, status(page(), &editor)).await.status().is_success());
+        assert_eq!(test::call_service(&mut app, status(key(), &editor)).await.status(), StatusCode::FORBIDDEN);
+        assert!(test::call_service(&mut app, status(key(), &admin)).await.status().is_success());
+
+        // reads are still open to viewers.
+        let read = TestRequest::get().uri("/api/v1/pages").header("Authorization", viewer).to_request();
+        assert!(test::call_service(&mut app, read).await.status().is_success());
+    });
+}
+
+#[test]
+fn users_can_not_raise_their_own_role_or_take_another_uuid() {
+    run(async {
+        let db = TestDb::new();
+        let viewer = db.user("viewer", UserRole::Viewer);
+        db.user("admin", UserRole::Admin);
+        let (before, admin) = (stored("viewer", &db), stored("admin", &db));
+        let mut app = init_app(&db).await;
+
+        let update = TestRequest::put()
+            .uri("/api/v1/user/viewer")
+            .header("Authorization", viewer)
+            .set_json(&json!({ "username": "viewer", "password": TEST_PASSWORD, "role": "admin", "uuid": admin.uuid }))
+            .to_request();
+        assert!(test::call_service(&mut app, update).await.status().is_success());
+        drop(app);
+
+        let after = stored("viewer", &db);
+        assert_eq!((after.uuid, after.role), (before.uuid, String::from("viewer")));
+        assert_eq!(stored("admin", &db).role, "admin");
+    });
+}
+
+#[test]
+fn admins_delete_users_but_not_themselves() {
+    run(async {
+        let db = TestDb::new();
+        let admin = db.user("admin", UserRole::Admin);
+        db.user("editor", UserRole::Editor);
+        let mut app = init_app(&db).await;
+
+        let delete = |username: &str| {
+            TestRequest::delete().uri(&format!("/api/v1/user/{}", username)).header("Authorization", admin.clone()).to_request()
+        };
+        let deleted: usize = test::read_body_json(test::call_service(&mut app, delete("editor")).await).await;
+        assert_eq!(deleted, 1);
+        assert_eq!(test::call_service(&mut app, delete("admin")).await.status(), StatusCode::BAD_REQUEST);
+        drop(app);
+
+        assert_eq!(stored("admin", &db).role, "admin");
+        let left: Vec<Stored> = diesel::sql_query("SELECT uuid, role FROM users WHERE username = 'editor'").load(&*db.conn()).unwrap();
+        assert!(left.is_empty(), "the editor is still there");
+    });
+}
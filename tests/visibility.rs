@@ -0,0 +1,35 @@
+#![cfg(feature = "test-utils")]
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use radical::test_utils::{init_app, ModuleFixture, PageFixture, TestDb, UserRole};
+
+use common::run;
+
+#[test]
+fn hidden_modules_are_only_served_to_users() {
+    run(async {
+        let db = TestDb::new();
+        let token = db.user("editor", UserRole::Editor);
+        let page = PageFixture::new("about").published().create(&db);
+        let shown = ModuleFixture::new(&page, "body").content("hi").create(&db);
+        let hidden = ModuleFixture::new(&page, "notes").content("internal").visible_to("admin").create(&db);
+        let mut app = init_app(&db).await;
+
+        let read = |uuid: &str| TestRequest::get().uri(&format!("/api/v1/modules/{}", uuid));
+
+        let res = test::call_service(&mut app, read(&shown.uuid).to_request()).await;
+        assert!(res.status().is_success());
+        let res = test::call_service(&mut app, read(&hidden.uuid).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let res = test::call_service(&mut app, read(&hidden.uuid).header("Authorization", token).to_request()).await;
+        assert!(res.status().is_success());
+
+        let page_modules = TestRequest::get().uri(&format!("/api/v1/pages/{}/modules", page.uuid));
+        let body = test::read_response(&mut app, page_modules.to_request()).await;
+        let visitor: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(visitor["fields"]["modules"].as_array().map(Vec::len), Some(1));
+    });
+}